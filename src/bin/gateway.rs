@@ -2,21 +2,101 @@ use bytes::Bytes;
 use http_body_util::{BodyExt, Full};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
-use hyper::{body::Incoming, Method, Request, Response, StatusCode};
+use hyper::{
+    body::Body,
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, CONNECTION},
+    http::request::Parts,
+    Method, Request, Response, StatusCode,
+};
 use hyper_util::rt::TokioIo;
+use jpc_rust::crypto::request_signing::{
+    RequestSigner, SIGNATURE_HEADER, SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+};
+use jpc_rust::gateway::balancing::{BalancingConfig, UpstreamPool};
+use jpc_rust::gateway::capture::{CapturedExchange, TrafficCapture};
+use jpc_rust::gateway::deadline::{DeadlinePolicy, GatewayDeadlineExceeded, RequestDeadline};
+use jpc_rust::gateway::debug_dump::DebugEndpoints;
+use jpc_rust::gateway::draining::{self, DrainConfig, GatewayDrain};
+use jpc_rust::gateway::form_routes::FormRoutes;
+use jpc_rust::gateway::header_forwarding::HeaderForwarding;
+use jpc_rust::gateway::health_events::{HealthEvent, HealthEventBus};
+use jpc_rust::gateway::hedging::{HedgePolicy, HedgingConfig};
+use jpc_rust::gateway::id_normalization::{IdNormalizationPlan, IdNormalizer};
+use jpc_rust::gateway::idempotency::IdempotencyRegistry;
+use jpc_rust::gateway::listeners::{self, ListenerConfig, ListenerRole};
+use jpc_rust::gateway::log_sampling;
+use jpc_rust::gateway::org_keys::OrgKeys;
+use jpc_rust::gateway::overload::{OverloadConfig, OverloadController};
+use jpc_rust::gateway::priority_lanes::PriorityLanes;
+use jpc_rust::gateway::probe_coalescing::{ProbeCoalescer, ProbeResult, ProbeSource};
+use jpc_rust::gateway::rate_limit_overrides::RateLimitOverrides;
+use jpc_rust::gateway::redaction::{RedactionPlan, RedactionPolicy};
+use jpc_rust::gateway::request_filter::RequestFilter;
+use jpc_rust::gateway::response_cache::{CacheConfig, CacheKey, CacheLookup, ResponseCache};
+use jpc_rust::gateway::route_debug::{
+    method_routes, method_rule, AdminTokens, RouteAttempt, RouteDecision, ROUTE_DEBUG_HEADER,
+};
+use jpc_rust::gateway::routing::Upstream;
+use jpc_rust::gateway::routing_rules::{RouteOutcome, RoutingRules};
+use jpc_rust::gateway::sanitizer::RequestSanitizer;
+use jpc_rust::gateway::schema_validation::MethodSchemaRegistry;
+use jpc_rust::gateway::self_test::SelfTestReport;
+use jpc_rust::gateway::shared_health::{
+    SharedHealthConfig, SharedHealthStore, SharedServiceHealth,
+};
+use jpc_rust::gateway::slo::SloTracker;
+use jpc_rust::gateway::snapshot::{fetch_catalog_snapshot, SnapshotCaller, SnapshotSource};
+use jpc_rust::gateway::status_policy::{StatusPolicy, UpstreamOutcome};
+use jpc_rust::gateway::streaming::{MeteredBody, StreamEnd, StreamingRoutes};
+use jpc_rust::gateway::tcp_listener::{response_frame, serve_frames, TcpListenerConfig};
+use jpc_rust::gateway::upstream::{UpstreamConnection, UpstreamCredentials};
+use jpc_rust::gateway::upstream_metrics::UpstreamFailure;
+use jpc_rust::middleware::api_version::requested_version;
+use jpc_rust::middleware::authorization::CLIENT_AUTHORIZATION_HEADER;
+use jpc_rust::middleware::client_ip::FORWARDED_FOR_HEADER;
+use jpc_rust::middleware::deadline::DEADLINE_HEADER;
+use jpc_rust::middleware::load_shedding::{busy_error, SERVICE_BUSY_HEADER};
+use jpc_rust::middleware::notifications::is_notification_body;
+use jpc_rust::middleware::org_context::ORG_HEADER;
+use jpc_rust::middleware::request_id::{RequestId, REQUEST_ID_HEADER};
+use jpc_rust::middleware::server_timing::SERVER_TIMING_HEADER;
+use jpc_rust::middleware::timestamp_format::requested_format;
+use jpc_rust::services::startup::{
+    check_config_requested, exit_code_for, print_config_report, BindFailed,
+};
+use jpc_rust::telemetry::latency_histogram::LatencyHistogram;
+use jpc_rust::telemetry::log_policy::{
+    init_tracing, log_policy, sample_success, with_sampling, TRACE_DEBUG_HEADER,
+    TRACE_SAMPLED_HEADER,
+};
 use std::collections::HashMap;
 use std::convert::Infallible;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::net::TcpListener;
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::{sleep, timeout};
-use tracing::{error, info, warn};
-use uuid::Uuid;
+use tracing::{debug, error, info, warn};
 
 type BoxBody = http_body_util::combinators::BoxBody<Bytes, hyper::Error>;
 
+/// Consecutive failures (health checks or 5xx responses) that open the circuit
+const CIRCUIT_BREAKER_THRESHOLD: u32 = 3;
+/// Longest a single proxied attempt may wait for response headers; retries
+/// continue while the request's deadline allows
+const UPSTREAM_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time between upstream health probes
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// How long one health probe may take
+const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Where the services listen
+const UPSTREAM_HOST: &str = "127.0.0.1";
+/// Shared health state older than this is ignored; its leader stopped reporting
+const SHARED_HEALTH_MAX_AGE: Duration = Duration::from_secs(90);
+
 // Metrics structure
 #[derive(Debug, Default)]
 struct GatewayMetrics {
@@ -24,8 +104,17 @@ struct GatewayMetrics {
     successful_requests: AtomicU64,
     failed_requests: AtomicU64,
     service_errors: AtomicU64,
-    average_response_time_ms: AtomicU64,
+    response_times: LatencyHistogram,
     active_connections: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_stale_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    streams_completed: AtomicU64,
+    streams_interrupted: AtomicU64,
+    streams_failed: AtomicU64,
+    streamed_bytes: AtomicU64,
+    /// Requests no routing rule, method or path matched
+    unmatched_routes: AtomicU64,
 }
 
 impl GatewayMetrics {
@@ -45,16 +134,8 @@ impl GatewayMetrics {
         self.service_errors.fetch_add(1, Ordering::Relaxed);
     }
 
-    fn update_response_time(&self, duration_ms: u64) {
-        // Simple moving average (in production, use proper metrics library)
-        let current = self.average_response_time_ms.load(Ordering::Relaxed);
-        let new_avg = if current == 0 {
-            duration_ms
-        } else {
-            (current + duration_ms) / 2
-        };
-        self.average_response_time_ms
-            .store(new_avg, Ordering::Relaxed);
+    fn record_response_time(&self, duration: Duration) {
+        self.response_times.record(duration);
     }
 
     fn increment_active_connections(&self) {
@@ -65,7 +146,40 @@ impl GatewayMetrics {
         self.active_connections.fetch_sub(1, Ordering::Relaxed);
     }
 
-    fn get_stats(&self) -> String {
+    fn increment_cache_hits(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn increment_cache_stale_hits(&self) {
+        self.cache_stale_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn increment_cache_misses(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn increment_unmatched_routes(&self) {
+        self.unmatched_routes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_stream_end(&self, end: &StreamEnd, bytes: u64) {
+        let counter = match end {
+            StreamEnd::Completed => &self.streams_completed,
+            StreamEnd::ClientGone => &self.streams_interrupted,
+            StreamEnd::UpstreamError(_) => &self.streams_failed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.streamed_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn get_stats(
+        &self,
+        overload: &serde_json::Value,
+        lanes: &serde_json::Value,
+        hedging: &serde_json::Value,
+        upstreams: &serde_json::Value,
+        slo: &serde_json::Value,
+    ) -> String {
         let total = self.total_requests.load(Ordering::Relaxed);
         let successful = self.successful_requests.load(Ordering::Relaxed);
         let success_rate = if total > 0 {
@@ -73,6 +187,7 @@ impl GatewayMetrics {
         } else {
             0.0
         };
+        let latency = self.response_times.snapshot();
 
         format!(
             r#"{{
@@ -80,16 +195,56 @@ impl GatewayMetrics {
                 "successful_requests": {},
                 "failed_requests": {},
                 "service_errors": {},
-                "average_response_time_ms": {},
+                "average_response_time_ms": {:.3},
+                "response_time_ms": {{
+                    "count": {},
+                    "p50": {:.3},
+                    "p95": {:.3},
+                    "p99": {:.3},
+                    "max": {:.3}
+                }},
                 "active_connections": {},
+                "cache_hits": {},
+                "cache_stale_hits": {},
+                "cache_misses": {},
+                "unmatched_routes": {},
+                "streams": {{
+                    "completed": {},
+                    "interrupted": {},
+                    "failed": {},
+                    "bytes": {}
+                }},
+                "overload": {},
+                "lanes": {},
+                "hedging": {},
+                "upstreams": {},
+                "slo": {},
                 "success_rate": {:.2}
             }}"#,
             total,
             successful,
             self.failed_requests.load(Ordering::Relaxed),
             self.service_errors.load(Ordering::Relaxed),
-            self.average_response_time_ms.load(Ordering::Relaxed),
+            latency.mean_ms,
+            latency.count,
+            latency.p50_ms,
+            latency.p95_ms,
+            latency.p99_ms,
+            latency.max_ms,
             self.active_connections.load(Ordering::Relaxed),
+            self.cache_hits.load(Ordering::Relaxed),
+            self.cache_stale_hits.load(Ordering::Relaxed),
+            self.cache_misses.load(Ordering::Relaxed),
+            self.unmatched_routes.load(Ordering::Relaxed),
+            self.streams_completed.load(Ordering::Relaxed),
+            self.streams_interrupted.load(Ordering::Relaxed),
+            self.streams_failed.load(Ordering::Relaxed),
+            self.streamed_bytes.load(Ordering::Relaxed),
+            overload,
+            lanes,
+            hedging,
+            upstreams,
+            slo,
             success_rate
         )
     }
@@ -110,21 +265,23 @@ impl RateLimiter {
         }
     }
 
-    async fn is_allowed(&self, client_ip: &str) -> bool {
+    /// Counts a request against `bucket`, a client IP or an override
+    /// subject, allowing `limit` per minute
+    async fn is_allowed(&self, bucket: &str, limit: u64) -> bool {
         let mut requests = self.requests.lock().await;
         let now = Instant::now();
 
         // Clean up old entries (older than 1 minute)
         requests.retain(|_, (_, timestamp)| now.duration_since(*timestamp).as_secs() < 60);
 
-        match requests.get_mut(client_ip) {
+        match requests.get_mut(bucket) {
             Some((count, timestamp)) => {
                 if now.duration_since(*timestamp).as_secs() >= 60 {
                     // Reset counter after 1 minute
                     *count = 1;
                     *timestamp = now;
                     true
-                } else if *count < self.max_requests_per_minute {
+                } else if *count < limit {
                     *count += 1;
                     true
                 } else {
@@ -132,11 +289,34 @@ impl RateLimiter {
                 }
             }
             None => {
-                requests.insert(client_ip.to_string(), (1, now));
+                requests.insert(bucket.to_string(), (1, now));
                 true
             }
         }
     }
+
+    /// Live buckets, busiest first, for debug dumps
+    async fn snapshot(&self) -> serde_json::Value {
+        let requests = self.requests.lock().await;
+        let mut buckets: Vec<_> = requests
+            .iter()
+            .filter(|(_, (_, timestamp))| timestamp.elapsed().as_secs() < 60)
+            .map(|(bucket, (count, timestamp))| (bucket.clone(), *count, timestamp.elapsed()))
+            .collect();
+        buckets.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        serde_json::json!({
+            "max_requests_per_minute": self.max_requests_per_minute,
+            "buckets": buckets
+                .into_iter()
+                .map(|(bucket, count, age)| serde_json::json!({
+                    "bucket": bucket,
+                    "requests": count,
+                    "resets_in_secs": 60u64.saturating_sub(age.as_secs()),
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
 }
 
 // Service instance for load balancing (prepared for future use)
@@ -219,10 +399,84 @@ struct HealthChecker {
     product_service: Arc<RwLock<ServiceHealth>>,
     metrics: Arc<GatewayMetrics>,
     rate_limiter: Arc<RateLimiter>,
+    rate_limit_overrides: Arc<RateLimitOverrides>,
+    schema_registry: Arc<MethodSchemaRegistry>,
+    response_cache: Arc<ResponseCache>,
+    user_upstream: Arc<UpstreamPool>,
+    product_upstream: Arc<UpstreamPool>,
+    /// How requests are spread over a service's instances
+    balancing: Arc<BalancingConfig>,
+    overload: Arc<OverloadController>,
+    /// Separate concurrency budgets for high and low priority traffic
+    lanes: Option<Arc<PriorityLanes>>,
+    /// Second attempts for slow idempotent requests
+    hedging: Option<Arc<HedgePolicy>>,
+    status_policy: Arc<StatusPolicy>,
+    redaction: Arc<RedactionPolicy>,
+    id_normalizer: Arc<IdNormalizer>,
+    health_events: Arc<HealthEventBus>,
+    idempotency: Arc<IdempotencyRegistry>,
+    deadlines: Arc<DeadlinePolicy>,
+    admin_tokens: Arc<AdminTokens>,
+    /// Bearer token -> organization forwarded in `X-Org-Id`
+    org_keys: Arc<OrgKeys>,
+    /// Routes whose responses are piped through instead of buffered
+    streaming_routes: Arc<StreamingRoutes>,
+    /// Paths whose query strings and form posts become JSON-RPC calls
+    form_routes: Arc<FormRoutes>,
+    sanitizer: Arc<RequestSanitizer>,
+    /// Client headers stripped on arrival and allowed upstream
+    header_forwarding: Arc<HeaderForwarding>,
+    /// Allow and deny rules for client addresses, user agents and headers
+    request_filter: Arc<RequestFilter>,
+    /// Request/response pairs recorded for replay while an admin asks
+    capture: Arc<TrafficCapture>,
+    /// Internal state dumps, switched on and off at runtime
+    debug: Arc<DebugEndpoints>,
+    /// Upstream health probes shared by the health checks and `/health`
+    probes: Arc<ProbeCoalescer>,
+    routing_rules: Arc<RoutingRules>,
+    slo: Arc<SloTracker>,
+    shared_health: Option<Arc<SharedHealthStore>>,
+    /// Whether this replica runs the health probes; always true without
+    /// shared health state
+    is_probe_leader: AtomicBool,
+    /// Connection draining when the gateway shuts down
+    drain: Arc<GatewayDrain>,
 }
 
 impl HealthChecker {
-    fn new() -> Self {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        schema_registry: MethodSchemaRegistry,
+        cache_config: CacheConfig,
+        user_upstream: UpstreamPool,
+        product_upstream: UpstreamPool,
+        balancing: BalancingConfig,
+        overload_config: OverloadConfig,
+        lanes: Option<PriorityLanes>,
+        status_policy: StatusPolicy,
+        redaction: RedactionPolicy,
+        id_normalizer: IdNormalizer,
+        idempotency: IdempotencyRegistry,
+        deadlines: DeadlinePolicy,
+        admin_tokens: AdminTokens,
+        sanitizer: RequestSanitizer,
+        routing_rules: RoutingRules,
+        slo: SloTracker,
+        shared_health: Option<SharedHealthStore>,
+        rate_limit_overrides: RateLimitOverrides,
+        hedging: Option<HedgingConfig>,
+        org_keys: OrgKeys,
+        streaming_routes: StreamingRoutes,
+        form_routes: FormRoutes,
+        request_filter: RequestFilter,
+        capture: TrafficCapture,
+        debug: DebugEndpoints,
+        probes: ProbeCoalescer,
+        header_forwarding: HeaderForwarding,
+        drain_config: DrainConfig,
+    ) -> Self {
         Self {
             user_service: Arc::new(RwLock::new(ServiceHealth::default())),
             product_service: Arc::new(RwLock::new(ServiceHealth::default())),
@@ -233,53 +487,189 @@ impl HealthChecker {
                     .parse()
                     .unwrap_or(1000),
             )), // Configurable rate limit per minute per IP
+            rate_limit_overrides: Arc::new(rate_limit_overrides),
+            schema_registry: Arc::new(schema_registry),
+            response_cache: Arc::new(ResponseCache::new(cache_config)),
+            user_upstream: Arc::new(user_upstream),
+            product_upstream: Arc::new(product_upstream),
+            balancing: Arc::new(balancing),
+            overload: Arc::new(OverloadController::new(overload_config)),
+            lanes: lanes.map(Arc::new),
+            hedging: hedging.map(|config| Arc::new(HedgePolicy::new(config))),
+            status_policy: Arc::new(status_policy),
+            redaction: Arc::new(redaction),
+            id_normalizer: Arc::new(id_normalizer),
+            health_events: Arc::new(HealthEventBus::new()),
+            idempotency: Arc::new(idempotency),
+            deadlines: Arc::new(deadlines),
+            admin_tokens: Arc::new(admin_tokens),
+            org_keys: Arc::new(org_keys),
+            streaming_routes: Arc::new(streaming_routes),
+            form_routes: Arc::new(form_routes),
+            sanitizer: Arc::new(sanitizer),
+            header_forwarding: Arc::new(header_forwarding),
+            request_filter: Arc::new(request_filter),
+            capture: Arc::new(capture),
+            debug: Arc::new(debug),
+            probes: Arc::new(probes),
+            routing_rules: Arc::new(routing_rules),
+            slo: Arc::new(slo),
+            // With shared state, the first lease attempt decides who probes
+            is_probe_leader: AtomicBool::new(shared_health.is_none()),
+            shared_health: shared_health.map(Arc::new),
+            drain: Arc::new(GatewayDrain::new(drain_config)),
         }
     }
 
-    async fn start_health_checks(&self) {
-        let user_health = Arc::clone(&self.user_service);
-        let product_health = Arc::clone(&self.product_service);
-
+    async fn start_health_checks(self: &Arc<Self>) {
         // Spawn health check tasks
-        tokio::spawn(async move {
-            loop {
-                Self::check_service_health(&user_health, 8080, "User Service").await;
-                sleep(Duration::from_secs(30)).await;
+        for service in [TargetService::UserService, TargetService::ProductService] {
+            let checker = Arc::clone(self);
+            tokio::spawn(async move {
+                loop {
+                    // With shared health state only the lease holder probes
+                    if checker.is_probe_leader.load(Ordering::Relaxed) {
+                        let (result, _) = checker.probe(&service).await;
+                        Self::check_service_health(
+                            checker.health(&service),
+                            result.healthy,
+                            service.name(),
+                            &checker.health_events,
+                        )
+                        .await;
+                        checker
+                            .publish_shared_health(&service, "health_check")
+                            .await;
+                    }
+                    sleep(HEALTH_CHECK_INTERVAL).await;
+                }
+            });
+        }
+
+        if let Some(store) = &self.shared_health {
+            tokio::spawn(Arc::clone(self).follow_shared_health(Arc::clone(store)));
+        }
+    }
+
+    /// Competes for the probe lease and, while another replica holds it,
+    /// mirrors the health it reports. Probes locally whenever the shared
+    /// state is unreachable.
+    async fn follow_shared_health(self: Arc<Self>, store: Arc<SharedHealthStore>) {
+        let mut backend_down = false;
+        loop {
+            let leader = match store.try_acquire_leadership().await {
+                Ok(leader) => {
+                    if backend_down {
+                        info!("🤝 Shared health state reachable again");
+                        backend_down = false;
+                    }
+                    leader
+                }
+                Err(err) => {
+                    if !backend_down {
+                        warn!(
+                            "⚠️ Shared health state unavailable, probing locally: {}",
+                            err
+                        );
+                        backend_down = true;
+                    }
+                    true
+                }
+            };
+
+            let was_leader = self.is_probe_leader.swap(leader, Ordering::Relaxed);
+            if leader && !was_leader && !backend_down {
+                info!("👑 This gateway now holds the probe lease and runs health checks");
+            } else if !leader && was_leader {
+                info!("👥 Another gateway holds the probe lease; following its health reports");
             }
-        });
 
-        tokio::spawn(async move {
-            loop {
-                Self::check_service_health(&product_health, 8081, "Product Service").await;
-                sleep(Duration::from_secs(30)).await;
+            if !leader {
+                self.apply_shared_health(&store).await;
             }
-        });
+            sleep(store.config().sync_interval).await;
+        }
     }
 
-    async fn check_service_health(
-        health: &Arc<RwLock<ServiceHealth>>,
-        port: u16,
-        service_name: &str,
-    ) {
-        let client =
-            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-                .build_http();
-
-        let health_check_req = Request::builder()
-            .method("POST")
-            .uri(format!("http://127.0.0.1:{}", port))
-            .header("Content-Type", "application/json")
-            .body(Full::new(Bytes::from(
-                r#"{"jsonrpc":"2.0","method":"health","id":0}"#,
-            )))
-            .unwrap();
+    async fn apply_shared_health(&self, store: &SharedHealthStore) {
+        let states = match store.fetch().await {
+            Ok(states) => states,
+            Err(err) => {
+                warn!("⚠️ Failed to read shared health state: {}", err);
+                return;
+            }
+        };
 
-        let is_healthy =
-            match timeout(Duration::from_secs(5), client.request(health_check_req)).await {
-                Ok(Ok(response)) => response.status().is_success(),
-                _ => false,
+        for state in states {
+            let Some(service) = TargetService::from_key(&state.service) else {
+                continue;
             };
+            if state.age() > SHARED_HEALTH_MAX_AGE {
+                continue;
+            }
+
+            let mut health_guard = self.health(&service).write().await;
+            let was_healthy = health_guard.is_healthy;
+            health_guard.is_healthy = state.is_healthy;
+            health_guard.consecutive_failures = state.consecutive_failures;
+            health_guard.last_check = Instant::now();
+
+            if state.is_healthy != was_healthy {
+                info!(
+                    "🔁 {} is {} according to gateway '{}' ({})",
+                    service.name(),
+                    if state.is_healthy { "up" } else { "down" },
+                    state.reported_by,
+                    state.reason
+                );
+                self.health_events.publish(HealthEvent::new(
+                    service.name(),
+                    state.is_healthy,
+                    state.consecutive_failures,
+                    Some("shared_state"),
+                ));
+            }
+        }
+    }
+
+    /// Reports this replica's view of `service` to the other replicas
+    async fn publish_shared_health(&self, service: &TargetService, reason: &str) {
+        let Some(store) = &self.shared_health else {
+            return;
+        };
+        let state = {
+            let health = self.health(service).read().await;
+            SharedServiceHealth::new(
+                service.key(),
+                health.is_healthy,
+                health.consecutive_failures,
+                reason,
+                &store.config().instance_id,
+            )
+        };
+        if let Err(err) = store.publish(state).await {
+            warn!(
+                "⚠️ Failed to publish {} health to shared state: {}",
+                service.name(),
+                err
+            );
+        }
+    }
+
+    /// Probes `service`, or shares a probe another local caller just ran
+    async fn probe(&self, service: &TargetService) -> (ProbeResult, ProbeSource) {
+        let pool = self.pool(service);
+        self.probes
+            .probe(service.key(), || pool.probe_health(HEALTH_PROBE_TIMEOUT))
+            .await
+    }
 
+    async fn check_service_health(
+        health: &Arc<RwLock<ServiceHealth>>,
+        is_healthy: bool,
+        service_name: &'static str,
+        events: &HealthEventBus,
+    ) {
         let mut health_guard = health.write().await;
         let was_healthy = health_guard.is_healthy;
 
@@ -298,29 +688,444 @@ impl HealthChecker {
                 );
             }
             // Mark as unhealthy after 3 consecutive failures
-            if health_guard.consecutive_failures >= 3 {
+            if health_guard.consecutive_failures >= CIRCUIT_BREAKER_THRESHOLD {
                 health_guard.is_healthy = false;
             }
         }
 
         health_guard.last_check = Instant::now();
+
+        if health_guard.is_healthy != was_healthy {
+            events.publish(HealthEvent::new(
+                service_name,
+                health_guard.is_healthy,
+                health_guard.consecutive_failures,
+                Some("health_check"),
+            ));
+        }
     }
 
-    async fn is_service_healthy(&self, service: &TargetService) -> bool {
-        let health = match service {
+    fn health(&self, service: &TargetService) -> &Arc<RwLock<ServiceHealth>> {
+        match service {
             TargetService::UserService => &self.user_service,
             TargetService::ProductService => &self.product_service,
+        }
+    }
+
+    async fn is_service_healthy(&self, service: &TargetService) -> bool {
+        self.health(service).read().await.is_healthy
+    }
+
+    /// Feeds proxied response statuses into the circuit breaker: 5xx
+    /// responses count like failed health checks, anything else resets the
+    /// failure streak. Only health checks close the circuit again.
+    async fn record_upstream_outcome(&self, service: &TargetService, outcome: UpstreamOutcome) {
+        let mut health_guard = self.health(service).write().await;
+        if !outcome.is_failure() {
+            health_guard.consecutive_failures = 0;
+            return;
+        }
+
+        health_guard.consecutive_failures += 1;
+        if health_guard.is_healthy && health_guard.consecutive_failures >= CIRCUIT_BREAKER_THRESHOLD
+        {
+            health_guard.is_healthy = false;
+            warn!(
+                "❌ {} marked down after {} consecutive 5xx responses",
+                service.name(),
+                health_guard.consecutive_failures
+            );
+            self.health_events.publish(HealthEvent::new(
+                service.name(),
+                false,
+                health_guard.consecutive_failures,
+                Some("upstream_5xx"),
+            ));
+            drop(health_guard);
+            self.publish_shared_health(service, "upstream_5xx").await;
+        }
+    }
+
+    /// Probe results and circuit state of every service for `/health`,
+    /// probing both upstreams at once
+    async fn health_report(&self) -> (bool, serde_json::Value) {
+        let services = [TargetService::UserService, TargetService::ProductService];
+        let (user, product) = tokio::join!(self.probe(&services[0]), self.probe(&services[1]));
+
+        let mut healthy = true;
+        let mut report = serde_json::Map::new();
+        for (service, (result, source)) in services.iter().zip([user, product]) {
+            let circuit_open = !self.is_service_healthy(service).await;
+            healthy &= result.healthy && !circuit_open;
+            report.insert(
+                service.key().to_string(),
+                serde_json::json!({
+                    "healthy": result.healthy,
+                    "error": result.error,
+                    "latency_ms": result.latency_ms,
+                    "age_ms": result.probed_at.elapsed().as_millis() as u64,
+                    "source": source,
+                    "circuit": if circuit_open { "open" } else { "closed" },
+                }),
+            );
+        }
+        // Load balancers stop sending traffic to a draining gateway
+        let draining = self.drain.is_draining();
+        let status = match (draining, healthy) {
+            (true, _) => "draining",
+            (false, true) => "healthy",
+            (false, false) => "degraded",
         };
+        (
+            healthy && !draining,
+            serde_json::json!({
+                "status": status,
+                "services": report,
+                "fresh_for_ms": self.probes.fresh_for().as_millis() as u64,
+            }),
+        )
+    }
+
+    /// Current state of every service, sent first on `/health/stream`
+    async fn health_snapshot(&self) -> Vec<HealthEvent> {
+        let mut snapshot = Vec::new();
+        for service in [TargetService::UserService, TargetService::ProductService] {
+            let health = self.health(&service).read().await;
+            snapshot.push(HealthEvent::new(
+                service.name(),
+                health.is_healthy,
+                health.consecutive_failures,
+                None,
+            ));
+        }
+        snapshot
+    }
+
+    /// Outbound call metrics per upstream, for `/metrics`
+    fn upstream_stats(&self) -> serde_json::Value {
+        serde_json::json!({
+            "user_service": self.user_upstream.primary().metrics.snapshot(),
+            "product_service": self.product_upstream.primary().metrics.snapshot(),
+        })
+    }
+
+    /// The active routing table, for `/routes`
+    async fn routes_table(&self) -> serde_json::Value {
+        let mut services = Vec::new();
+        for service in [TargetService::UserService, TargetService::ProductService] {
+            services.push(serde_json::json!({
+                "service": service.key(),
+                "name": service.name(),
+                "instance": self.upstream(&service).base_url(),
+                "instances": self.pool(&service).base_urls(),
+                "healthy": self.is_service_healthy(&service).await,
+            }));
+        }
+        serde_json::json!({
+            "services": services,
+            "rules": self.routing_rules.describe_configured(),
+            "methods": method_routes(),
+            "paths": self.routing_rules.describe_fallback(),
+            "forms": self.form_routes.describe(),
+            "default": self.routing_rules.unmatched().name(),
+            "headers": self.header_forwarding.describe(),
+            "balancing": self.balancing.describe(),
+        })
+    }
+
+    fn pool(&self, service: &TargetService) -> &UpstreamPool {
+        match service {
+            TargetService::UserService => &self.user_upstream,
+            TargetService::ProductService => &self.product_upstream,
+        }
+    }
+
+    /// The service's primary instance, which stands for all of them in
+    /// metrics and debug output
+    fn upstream(&self, service: &TargetService) -> &UpstreamConnection {
+        self.pool(service).primary()
+    }
+
+    /// Rate limiter buckets, circuit breakers, a cache summary and
+    /// in-flight requests, answered by `dump_state` on `/admin/debug`
+    async fn debug_state(&self) -> serde_json::Value {
+        let mut circuit_breakers = Vec::new();
+        for service in [TargetService::UserService, TargetService::ProductService] {
+            let health = self.health(&service).read().await.clone();
+            circuit_breakers.push(serde_json::json!({
+                "service": service.key(),
+                "state": if health.is_healthy { "closed" } else { "open" },
+                "consecutive_failures": health.consecutive_failures,
+                "threshold": CIRCUIT_BREAKER_THRESHOLD,
+                "last_check_secs_ago": health.last_check.elapsed().as_secs(),
+                "busy_remaining_ms": self
+                    .upstream(&service)
+                    .busy_remaining()
+                    .map(|remaining| remaining.as_millis() as u64),
+            }));
+        }
+
+        let mut rate_limiter = self.rate_limiter.snapshot().await;
+        rate_limiter["overrides"] = serde_json::json!(self.rate_limit_overrides.list());
+        serde_json::json!({
+            "rate_limiter": rate_limiter,
+            "circuit_breakers": circuit_breakers,
+            "cache": self.response_cache.summary().await,
+            "in_flight": {
+                "active_connections": self.metrics.active_connections.load(Ordering::Relaxed),
+                "overload": self.overload.stats(),
+                "lanes": self.lanes.as_ref().map(|lanes| lanes.stats()),
+            },
+            "is_probe_leader": self.is_probe_leader.load(Ordering::Relaxed),
+            "probes": self.probes.stats(),
+            "generated_at": chrono::Utc::now(),
+        })
+    }
+}
+
+/// Answers one frame from the raw TCP listener by running it through the
+/// same path as an HTTP `POST /`
+async fn handle_frame(frame: Bytes, client_addr: SocketAddr) -> Bytes {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/")
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Full::new(frame.clone()))
+        .unwrap();
+    let Ok(response) = handle_sampled_request(request, client_addr, ListenerRole::Public).await;
+    let (parts, body) = response.into_parts();
+    let body = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(err) => Bytes::from(format!("Proxy error: {}", err)),
+    };
+    response_frame(&frame, parts.status, body)
+}
+
+/// Gateway endpoints for operators, behind `GATEWAY_ADMIN_TOKENS`. All
+/// but `/routes` answer JSON-RPC calls posted to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AdminEndpoint {
+    /// Where requests go
+    Routes,
+    /// Runtime rate limit overrides
+    RateLimits,
+    /// Traffic capture for replay
+    Capture,
+    /// Internal state dumps for incidents
+    Debug,
+    /// Runtime allow and deny rules
+    FilterRules,
+    /// Runtime log sampling
+    LogSampling,
+    /// Taking upstream instances out of rotation
+    Drain,
+}
+
+/// The path each admin endpoint is served on
+const ADMIN_ROUTES: &[(&str, AdminEndpoint)] = &[
+    ("/routes", AdminEndpoint::Routes),
+    ("/admin/rate-limits", AdminEndpoint::RateLimits),
+    ("/admin/capture", AdminEndpoint::Capture),
+    ("/admin/debug", AdminEndpoint::Debug),
+    ("/admin/filter-rules", AdminEndpoint::FilterRules),
+    ("/admin/log-sampling", AdminEndpoint::LogSampling),
+    ("/admin/drain", AdminEndpoint::Drain),
+];
+
+impl AdminEndpoint {
+    fn for_path(path: &str) -> Option<Self> {
+        ADMIN_ROUTES
+            .iter()
+            .find(|(route, _)| *route == path)
+            .map(|(_, endpoint)| *endpoint)
+    }
+
+    /// What failed calls are logged as
+    fn name(&self) -> &'static str {
+        match self {
+            AdminEndpoint::Routes => "Routes",
+            AdminEndpoint::RateLimits => "Rate limit override",
+            AdminEndpoint::Capture => "Capture",
+            AdminEndpoint::Debug => "Debug",
+            AdminEndpoint::FilterRules => "Filter rule",
+            AdminEndpoint::LogSampling => "Log sampling",
+            AdminEndpoint::Drain => "Drain",
+        }
+    }
+
+    /// Answers one call with its JSON-RPC response, or `/routes` with the
+    /// routing table
+    async fn call(&self, health_checker: &HealthChecker, body: &[u8]) -> serde_json::Value {
+        match self {
+            AdminEndpoint::Routes => health_checker.routes_table().await,
+            AdminEndpoint::RateLimits => {
+                health_checker.rate_limit_overrides.handle_admin_call(body)
+            }
+            AdminEndpoint::Capture => health_checker.capture.handle_admin_call(body),
+            AdminEndpoint::Debug => {
+                health_checker
+                    .debug
+                    .handle_admin_call(body, health_checker.debug_state())
+                    .await
+            }
+            AdminEndpoint::FilterRules => health_checker.request_filter.handle_admin_call(body),
+            AdminEndpoint::LogSampling => log_sampling::handle_admin_call(body),
+            AdminEndpoint::Drain => {
+                let pools = [
+                    (
+                        TargetService::UserService.key(),
+                        health_checker.user_upstream.as_ref(),
+                    ),
+                    (
+                        TargetService::ProductService.key(),
+                        health_checker.product_upstream.as_ref(),
+                    ),
+                ];
+                draining::handle_admin_call(body, &pools)
+            }
+        }
+    }
+
+    /// Records a successful call in the audit log; plain reads are left out
+    fn audit(&self, request_id: RequestId, result: &serde_json::Value) {
+        match self {
+            AdminEndpoint::Routes => {}
+            AdminEndpoint::RateLimits => {
+                if result.get("subject").is_some() {
+                    info!(target: "audit", "🎚️ [{}] Rate limit override changed: {}", request_id, result);
+                }
+            }
+            AdminEndpoint::Capture => {
+                info!(target: "audit", "🎥 [{}] Capture: {}", request_id, result)
+            }
+            AdminEndpoint::Debug => match result.get("enabled") {
+                Some(enabled) => {
+                    info!(target: "audit", "🐞 [{}] Debug endpoints enabled: {}", request_id, enabled)
+                }
+                None => info!(target: "audit", "🐞 [{}] Internal state dumped", request_id),
+            },
+            AdminEndpoint::FilterRules => {
+                if result.get("rules").is_none() {
+                    info!(target: "audit", "⛔ [{}] Filter rules changed: {}", request_id, result);
+                }
+            }
+            AdminEndpoint::LogSampling => {
+                info!(target: "audit", "🎚️ [{}] Log sampling: {}", request_id, result)
+            }
+            AdminEndpoint::Drain => {
+                info!(target: "audit", "🚰 [{}] Upstream instances: {}", request_id, result)
+            }
+        }
+    }
+}
 
-        health.read().await.is_healthy
+/// Checks the admin token and answers a call to an admin endpoint
+async fn admin_json_rpc<B>(
+    req: Request<B>,
+    endpoint: AdminEndpoint,
+    request_id: RequestId,
+) -> Response<BoxBody>
+where
+    B: Body<Data = Bytes>,
+    B::Error: std::fmt::Display,
+{
+    let health_checker = HEALTH_CHECKER.get().unwrap();
+    health_checker.metrics.decrement_active_connections();
+    if !health_checker.admin_tokens.is_admin(req.headers()) {
+        warn!(
+            "🚫 [{}] {} without an admin token",
+            request_id,
+            req.uri().path()
+        );
+        health_checker.metrics.increment_failed_requests();
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .header("Access-Control-Allow-Origin", "*")
+            .header("X-Request-ID", request_id)
+            .body(full_body("Admin token required"))
+            .unwrap();
     }
+
+    let response = if endpoint == AdminEndpoint::Routes {
+        health_checker.metrics.increment_successful_requests();
+        endpoint.call(health_checker, &[]).await
+    } else {
+        let body_bytes = match req.into_body().collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(err) => {
+                warn!("⚠️ [{}] Failed to read request body: {}", request_id, err);
+                health_checker.metrics.increment_failed_requests();
+                return Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .header("Access-Control-Allow-Origin", "*")
+                    .header("X-Request-ID", request_id)
+                    .body(full_body("Failed to read request body"))
+                    .unwrap();
+            }
+        };
+        let response = endpoint.call(health_checker, &body_bytes).await;
+        match (response.get("error"), &response["result"]) {
+            (Some(error), _) => {
+                warn!(
+                    "🚫 [{}] {} call failed: {}",
+                    request_id,
+                    endpoint.name(),
+                    error
+                );
+                health_checker.metrics.increment_failed_requests();
+            }
+            (None, result) => {
+                endpoint.audit(request_id, result);
+                health_checker.metrics.increment_successful_requests();
+            }
+        }
+        response
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("X-Request-ID", request_id)
+        .body(full_body(response.to_string()))
+        .unwrap()
+}
+
+/// Decides once whether this request's success logs are kept, at the
+/// gateway and in the services, and handles it under that decision. Admins
+/// force sampling with the debug header.
+async fn handle_sampled_request<B>(
+    req: Request<B>,
+    client_addr: SocketAddr,
+    listener: ListenerRole,
+) -> Result<Response<BoxBody>, Infallible>
+where
+    B: Body<Data = Bytes>,
+    B::Error: std::fmt::Display,
+{
+    let health_checker = HEALTH_CHECKER.get().unwrap();
+    let forced = req.headers().contains_key(TRACE_DEBUG_HEADER)
+        && health_checker.admin_tokens.is_admin(req.headers());
+    let sampled = log_policy().decide(forced);
+    let mut response = with_sampling(sampled, handle_request(req, client_addr, listener)).await?;
+    // Clients move to another gateway after this response
+    health_checker.drain.mark_response(&mut response);
+    Ok(response)
 }
 
-async fn handle_request(req: Request<Incoming>) -> Result<Response<BoxBody>, Infallible> {
+async fn handle_request<B>(
+    mut req: Request<B>,
+    client_addr: SocketAddr,
+    listener: ListenerRole,
+) -> Result<Response<BoxBody>, Infallible>
+where
+    B: Body<Data = Bytes>,
+    B::Error: std::fmt::Display,
+{
     let start_time = Instant::now();
-    let request_id = Uuid::new_v4().to_string();
+    let request_id = RequestId::generate();
 
-    info!(
+    debug!(
         "🔄 [{}] Handling request: {} {}",
         request_id,
         req.method(),
@@ -333,6 +1138,38 @@ async fn handle_request(req: Request<Incoming>) -> Result<Response<BoxBody>, Inf
     health_checker.metrics.increment_total_requests();
     health_checker.metrics.increment_active_connections();
 
+    // Normalize the path before anything matches on it, and refuse requests
+    // that could be read differently here and upstream
+    match health_checker.sanitizer.check(req.uri(), req.headers()) {
+        Ok(Some(normalized)) => {
+            debug!(
+                "🧽 [{}] Normalized {} to {}",
+                request_id,
+                req.uri().path(),
+                normalized.path()
+            );
+            *req.uri_mut() = normalized;
+        }
+        Ok(None) => {}
+        Err(rejection) => {
+            warn!(
+                "🚫 [{}] Rejected {} {}: {}",
+                request_id,
+                req.method(),
+                req.uri().path(),
+                rejection
+            );
+            health_checker.metrics.increment_failed_requests();
+            health_checker.metrics.decrement_active_connections();
+            return Ok(Response::builder()
+                .status(rejection.status())
+                .header("Access-Control-Allow-Origin", "*")
+                .header("X-Request-ID", request_id)
+                .body(full_body(format!("Request rejected: {}", rejection)))
+                .unwrap());
+        }
+    }
+
     // Handle CORS preflight
     if req.method() == Method::OPTIONS {
         health_checker.metrics.decrement_active_connections();
@@ -346,9 +1183,67 @@ async fn handle_request(req: Request<Incoming>) -> Result<Response<BoxBody>, Inf
             .unwrap());
     }
 
+    // Admin endpoints and API traffic can be kept on separate listeners
+    if !listener.serves(req.uri().path()) {
+        debug!(
+            "🚪 [{}] {} is not served on the {} listener",
+            request_id,
+            req.uri().path(),
+            listener.name()
+        );
+        health_checker.metrics.increment_failed_requests();
+        health_checker.metrics.decrement_active_connections();
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header("Access-Control-Allow-Origin", "*")
+            .header("X-Request-ID", request_id)
+            .body(full_body("Not found"))
+            .unwrap());
+    }
+
+    // Abuse mitigation rules, before anything is routed. Admin endpoints
+    // are exempt so a bad rule cannot lock admins out.
+    if !listeners::is_admin_path(req.uri().path()) {
+        if let Some(rule) = health_checker
+            .request_filter
+            .check(client_addr.ip(), req.headers())
+        {
+            warn!(
+                "⛔ [{}] Denied {} {} from {} by filter rule {}",
+                request_id,
+                req.method(),
+                req.uri().path(),
+                client_addr.ip(),
+                rule
+            );
+            health_checker.metrics.increment_failed_requests();
+            health_checker.metrics.decrement_active_connections();
+            return Ok(Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .header("Access-Control-Allow-Origin", "*")
+                .header("X-Request-ID", request_id)
+                .body(full_body("Request denied"))
+                .unwrap());
+        }
+    }
+
     // Handle metrics endpoint
     if req.uri().path() == "/metrics" {
-        let metrics_json = health_checker.metrics.get_stats();
+        let lanes = health_checker
+            .lanes
+            .as_ref()
+            .map_or(serde_json::Value::Null, |lanes| lanes.stats());
+        let hedging = health_checker
+            .hedging
+            .as_ref()
+            .map_or(serde_json::Value::Null, |hedging| hedging.stats());
+        let metrics_json = health_checker.metrics.get_stats(
+            &health_checker.overload.stats(),
+            &lanes,
+            &hedging,
+            &health_checker.upstream_stats(),
+            &health_checker.slo.stats(),
+        );
         health_checker.metrics.decrement_active_connections();
         return Ok(Response::builder()
             .status(StatusCode::OK)
@@ -359,10 +1254,17 @@ async fn handle_request(req: Request<Incoming>) -> Result<Response<BoxBody>, Inf
             .unwrap());
     }
 
-    // Rate limiting (simplified - get client IP from headers in production)
-    let client_ip = "127.0.0.1"; // In production, extract from X-Forwarded-For or similar
-    if !health_checker.rate_limiter.is_allowed(client_ip).await {
-        warn!("🚫 [{}] Rate limit exceeded for {}", request_id, client_ip);
+    // Rate limiting per client IP, or per API key or tenant with an override
+    let client_ip = client_addr.ip().to_string();
+    let (bucket, limit) = match health_checker.rate_limit_overrides.limit_for(req.headers()) {
+        Some((subject, limit)) => (subject.bucket(), limit),
+        None => (
+            client_ip.clone(),
+            health_checker.rate_limiter.max_requests_per_minute,
+        ),
+    };
+    if listener.is_rate_limited() && !health_checker.rate_limiter.is_allowed(&bucket, limit).await {
+        warn!("🚫 [{}] Rate limit exceeded for {}", request_id, bucket);
         health_checker.metrics.increment_failed_requests();
         health_checker.metrics.decrement_active_connections();
         return Ok(Response::builder()
@@ -373,109 +1275,1126 @@ async fn handle_request(req: Request<Incoming>) -> Result<Response<BoxBody>, Inf
             .unwrap());
     }
 
-    // Route requests based on path
-    let path = req.uri().path();
-    let target_service = determine_target_service(path);
+    // Operator endpoints behind the admin tokens
+    if let Some(endpoint) = AdminEndpoint::for_path(req.uri().path()) {
+        return Ok(admin_json_rpc(req, endpoint, request_id).await);
+    }
 
-    // Check service health before proxying
-    if !health_checker.is_service_healthy(&target_service).await {
-        warn!(
-            "🔴 [{}] Service {} unavailable",
+    // Aggregated upstream health; concurrent callers share one probe per
+    // upstream and reuse its result while it is fresh
+    if req.uri().path() == "/health" {
+        let (healthy, report) = health_checker.health_report().await;
+        health_checker.metrics.increment_successful_requests();
+        health_checker.metrics.decrement_active_connections();
+        return Ok(Response::builder()
+            .status(if healthy {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            })
+            .header("Content-Type", "application/json")
+            .header("Cache-Control", "no-cache")
+            .header("Access-Control-Allow-Origin", "*")
+            .header("X-Request-ID", request_id)
+            .body(full_body(report.to_string()))
+            .unwrap());
+    }
+
+    // Push health transitions to dashboards as Server-Sent Events
+    if req.uri().path() == "/health/stream" {
+        let snapshot = health_checker.health_snapshot().await;
+        let stream = health_checker.health_events.stream(snapshot);
+        debug!(
+            "📡 [{}] Health stream opened ({} subscribers)",
             request_id,
-            target_service.name()
+            health_checker.health_events.subscribers()
         );
-        health_checker.metrics.increment_service_errors();
-        health_checker.metrics.increment_failed_requests();
+        health_checker.metrics.increment_successful_requests();
         health_checker.metrics.decrement_active_connections();
         return Ok(Response::builder()
-            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .status(StatusCode::OK)
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
             .header("Access-Control-Allow-Origin", "*")
             .header("X-Request-ID", request_id)
-            .body(full_body("Service unavailable"))
+            .body(stream.map_err(|never| match never {}).boxed())
             .unwrap());
     }
 
-    match proxy_request_with_retry(req, target_service, &request_id).await {
-        Ok(response) => {
-            let duration = start_time.elapsed().as_millis() as u64;
-            health_checker.metrics.update_response_time(duration);
-            health_checker.metrics.increment_successful_requests();
-            health_checker.metrics.decrement_active_connections();
-
-            info!("✅ [{}] Request completed in {}ms", request_id, duration);
-
-            // Add request ID to response
-            let (mut parts, body) = response.into_parts();
-            parts
-                .headers
-                .insert("X-Request-ID", request_id.parse().unwrap());
-            Ok(Response::from_parts(parts, body))
-        }
-        Err(err) => {
-            let duration = start_time.elapsed().as_millis() as u64;
-            health_checker.metrics.update_response_time(duration);
+    // Shed low-priority routes while latency is over the threshold. The guard
+    // keeps this request in the concurrency gauges until it completes.
+    let _in_flight = match health_checker
+        .overload
+        .admit(client_addr.ip(), req.uri().path())
+    {
+        Ok(guard) => guard,
+        Err(retry_after) => {
+            debug!(
+                "🛑 [{}] Shed {} under overload",
+                request_id,
+                req.uri().path()
+            );
             health_checker.metrics.increment_failed_requests();
             health_checker.metrics.decrement_active_connections();
-
-            error!(
-                "❌ [{}] Proxy error after {}ms: {}",
-                request_id, duration, err
-            );
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
+            return Ok(Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header("Retry-After", retry_after.as_secs().max(1))
                 .header("Access-Control-Allow-Origin", "*")
                 .header("X-Request-ID", request_id)
-                .body(full_body(format!("Proxy error: {}", err)))
-                .unwrap())
+                .body(full_body("Service overloaded, please retry later"))
+                .unwrap());
         }
-    }
-}
-
-async fn proxy_request_with_retry(
-    req: Request<Incoming>,
-    target_service: TargetService,
-    request_id: &str,
-) -> Result<Response<BoxBody>, Box<dyn std::error::Error + Send + Sync>> {
-    const MAX_RETRIES: u32 = 3;
-    const RETRY_DELAY_MS: u64 = 100;
-
-    // Extract request parts before consuming the body
-    let method = req.method().clone();
-    let uri = req.uri().clone();
-    let headers = req.headers().clone();
-
-    // Get the body once and clone it for retries
-    let body_bytes = req.collect().await?.to_bytes();
-
-    for attempt in 1..=MAX_RETRIES {
-        // Build a new request for each attempt
-        let mut upstream_req = Request::builder().method(&method);
-
-        // Build the upstream request URL using the target service port
-        let upstream_url = format!(
-            "http://127.0.0.1:{}{}",
-            target_service.port(),
-            uri.path_and_query().map(|x| x.as_str()).unwrap_or("/")
-        );
-
-        upstream_req = upstream_req.uri(&upstream_url);
+    };
 
-        // Copy headers (except host)
-        for (name, value) in &headers {
-            if name != "host" {
-                upstream_req = upstream_req.header(name, value);
+    // Keep batch traffic from crowding out interactive calls. The permit
+    // holds this request's slot in its lane until it completes.
+    let _lane_permit = match &health_checker.lanes {
+        Some(lanes) => {
+            let lane = lanes.classify(req.headers());
+            match lanes.acquire(lane).await {
+                Ok(permit) => Some(permit),
+                Err(rejection) => {
+                    debug!(
+                        "🛣️ [{}] Rejected from {} lane: {}",
+                        request_id,
+                        lane.name(),
+                        rejection
+                    );
+                    health_checker.metrics.increment_failed_requests();
+                    health_checker.metrics.decrement_active_connections();
+                    return Ok(Response::builder()
+                        .status(StatusCode::SERVICE_UNAVAILABLE)
+                        .header("Retry-After", 1)
+                        .header("Access-Control-Allow-Origin", "*")
+                        .header("X-Request-ID", request_id)
+                        .body(full_body(format!(
+                            "The {} priority lane is saturated, please retry later",
+                            lane.name()
+                        )))
+                        .unwrap());
+                }
             }
         }
+        None => None,
+    };
 
-        let upstream_req = upstream_req.body(Full::new(body_bytes.clone()))?;
+    // Dashboard bootstrap: users and products in one call
+    if req.uri().path() == "/catalog/snapshot" {
+        // Internal-only headers never come from clients; the organization
+        // comes from the caller's token
+        let mut headers = req.headers().clone();
+        health_checker
+            .header_forwarding
+            .strip_internal(&mut headers);
+        headers.remove(ORG_HEADER);
+        let caller = SnapshotCaller {
+            headers: &headers,
+            forwards_authorization: health_checker
+                .header_forwarding
+                .forwards(req.uri().path(), &AUTHORIZATION),
+            org: health_checker.org_keys.org_for(&headers),
+            redaction: &health_checker.redaction,
+        };
+        let snapshot = fetch_catalog_snapshot(
+            &caller,
+            SnapshotSource {
+                section: "users",
+                service_name: TargetService::UserService.name(),
+                method: "list_users",
+                upstream: health_checker.user_upstream.pick(None, 1),
+                healthy: health_checker
+                    .is_service_healthy(&TargetService::UserService)
+                    .await,
+            },
+            SnapshotSource {
+                section: "products",
+                service_name: TargetService::ProductService.name(),
+                method: "list_products",
+                upstream: health_checker.product_upstream.pick(None, 1),
+                healthy: health_checker
+                    .is_service_healthy(&TargetService::ProductService)
+                    .await,
+            },
+        )
+        .await;
+
+        health_checker
+            .metrics
+            .record_response_time(start_time.elapsed());
+        if snapshot["partial"] == serde_json::Value::Bool(true) {
+            warn!(
+                "🧩 [{}] Catalog snapshot is partial: {}",
+                request_id, snapshot["warnings"]
+            );
+            health_checker.metrics.increment_service_errors();
+        }
+        health_checker.metrics.increment_successful_requests();
+        health_checker.metrics.decrement_active_connections();
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .header("Access-Control-Allow-Origin", "*")
+            .header("X-Request-ID", request_id)
+            .body(full_body(snapshot.to_string()))
+            .unwrap());
+    }
 
-        let client =
-            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-                .build_http();
+    // The budget starts now and covers every attempt, retries included
+    let deadline = health_checker
+        .deadlines
+        .deadline_for(req.uri().path(), req.headers());
+    let route_debug = health_checker.admin_tokens.wants_route_debug(req.headers());
+
+    let (mut parts, body) = req.into_parts();
+
+    // Internal-only headers never come from clients
+    let stripped = health_checker
+        .header_forwarding
+        .strip_internal(&mut parts.headers);
+    if stripped > 0 {
+        debug!(
+            "🧹 [{}] Stripped {} internal-only headers",
+            request_id, stripped
+        );
+    }
 
-        match timeout(Duration::from_secs(10), client.request(upstream_req)).await {
-            Ok(Ok(upstream_resp)) => {
+    // Upstreams see the address this connection came from, never one the
+    // client supplied
+    if let Ok(value) = client_ip.parse() {
+        parts.headers.insert(FORWARDED_FOR_HEADER, value);
+    }
+    // Likewise the organization comes from the caller's token, never from
+    // the client
+    parts.headers.remove(ORG_HEADER);
+    let caller_org = health_checker
+        .org_keys
+        .org_for(&parts.headers)
+        .map(str::to_string);
+    if let Some(value) = caller_org.as_deref().and_then(|org| org.parse().ok()) {
+        parts.headers.insert(ORG_HEADER, value);
+    }
+    // Services log under the gateway's id, never one the client chose
+    parts.headers.insert(REQUEST_ID_HEADER, request_id.into());
+    // The services keep this request's success logs exactly when the
+    // gateway does, whatever the client sent
+    let sampled = if sample_success() { "1" } else { "0" };
+    parts
+        .headers
+        .insert(TRACE_SAMPLED_HEADER, HeaderValue::from_static(sampled));
+
+    let mut body_bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(err) => {
+            warn!("⚠️ [{}] Failed to read request body: {}", request_id, err);
+            health_checker.metrics.increment_failed_requests();
+            health_checker.metrics.decrement_active_connections();
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header("Access-Control-Allow-Origin", "*")
+                .header("X-Request-ID", request_id)
+                .body(full_body("Failed to read request body"))
+                .unwrap());
+        }
+    };
+
+    // Query strings and form posts on form routes become a JSON-RPC call
+    // to the root path, routed and proxied like any other
+    if let Some(route) = health_checker
+        .form_routes
+        .route_for(parts.uri.path())
+        .filter(|route| route.converts(&parts.method, &parts.headers))
+    {
+        let form = (parts.method == Method::POST).then_some(body_bytes.as_ref());
+        match route.to_call(parts.uri.query(), form, request_id.to_string()) {
+            Ok(call) => {
+                debug!(
+                    "📝 [{}] {} {} converted to {}",
+                    request_id,
+                    parts.method,
+                    parts.uri.path(),
+                    route.method()
+                );
+                body_bytes = call;
+                parts.method = Method::POST;
+                parts.uri = hyper::Uri::from_static("/");
+                parts.headers.remove(hyper::header::CONTENT_LENGTH);
+                parts.headers.insert(
+                    hyper::header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                );
+            }
+            Err(err) => {
+                warn!(
+                    "📝 [{}] Rejected {} {}: {}",
+                    request_id,
+                    parts.method,
+                    parts.uri.path(),
+                    err
+                );
+                health_checker.metrics.increment_failed_requests();
+                health_checker.metrics.decrement_active_connections();
+                return Ok(Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .header("Content-Type", "application/json")
+                    .header("Access-Control-Allow-Origin", "*")
+                    .header("X-Request-ID", request_id)
+                    .body(full_body(err.to_response().to_string()))
+                    .unwrap());
+            }
+        }
+    }
+
+    // Configured rules first, then the JSON-RPC method map, then the path
+    let (target_service, matched_rule, rewritten_path) = match health_checker
+        .routing_rules
+        .evaluate(parts.uri.path(), &parts.headers, &body_bytes)
+    {
+        RouteOutcome::Route {
+            upstream,
+            rule,
+            rewritten_path,
+        } => (TargetService::from(upstream), rule, rewritten_path),
+        RouteOutcome::Fallback {
+            upstream,
+            rewritten_path,
+        } => {
+            health_checker.metrics.increment_unmatched_routes();
+            debug!(
+                "🧭 [{}] No route for {}, falling back to {}",
+                request_id,
+                parts.uri.path(),
+                TargetService::from(upstream).name()
+            );
+            (
+                TargetService::from(upstream),
+                Some("default".to_string()),
+                rewritten_path,
+            )
+        }
+        RouteOutcome::Unmatched { status, body } => {
+            health_checker.metrics.increment_unmatched_routes();
+            warn!("🧭 [{}] No route for {}", request_id, parts.uri.path());
+            health_checker.metrics.increment_failed_requests();
+            health_checker.metrics.decrement_active_connections();
+            return Ok(Response::builder()
+                .status(status)
+                .header("Content-Type", "application/json")
+                .header("Access-Control-Allow-Origin", "*")
+                .header("X-Request-ID", request_id)
+                .body(full_body(body.to_string()))
+                .unwrap());
+        }
+        RouteOutcome::Reject {
+            status,
+            message,
+            rule,
+        } => {
+            warn!(
+                "🚫 [{}] {} rejected by {}",
+                request_id,
+                parts.uri.path(),
+                rule
+            );
+            health_checker.metrics.increment_failed_requests();
+            health_checker.metrics.decrement_active_connections();
+            return Ok(Response::builder()
+                .status(status)
+                .header("Access-Control-Allow-Origin", "*")
+                .header("X-Request-ID", request_id)
+                .body(full_body(message))
+                .unwrap());
+        }
+    };
+    if let Some(path) = rewritten_path {
+        match rewrite_path(&parts.uri, &path) {
+            Some(uri) => parts.uri = uri,
+            None => warn!("⚠️ [{}] Invalid rewritten path {}", request_id, path),
+        }
+    }
+    // Only described for admins who asked for X-Route-Debug
+    let route_rule = route_debug.then(|| match matched_rule {
+        Some(rule) => rule,
+        None => method_rule(&body_bytes),
+    });
+
+    // Check service health before proxying
+    if !health_checker.is_service_healthy(&target_service).await {
+        warn!(
+            "🔴 [{}] Service {} unavailable",
+            request_id,
+            target_service.name()
+        );
+        health_checker.metrics.increment_service_errors();
+        health_checker.metrics.increment_failed_requests();
+        health_checker.metrics.decrement_active_connections();
+        return Ok(Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .header("Access-Control-Allow-Origin", "*")
+            .header("X-Request-ID", request_id)
+            .body(full_body("Service unavailable"))
+            .unwrap());
+    }
+
+    // Notifications (no id) expect no response body, not even for errors
+    let notification = is_notification_body(&body_bytes);
+    // Streamed bodies are never held whole, so they skip the cache,
+    // redaction and id normalization
+    let streaming = health_checker
+        .streaming_routes
+        .is_streaming(parts.uri.path());
+
+    // Reject params that don't match the method's schema without a round trip
+    if let Some(error_response) = health_checker
+        .schema_registry
+        .validate_request_body(&body_bytes)
+    {
+        warn!(
+            "🧾 [{}] Request params failed schema validation",
+            request_id
+        );
+        health_checker.metrics.increment_failed_requests();
+        health_checker.metrics.decrement_active_connections();
+        if notification {
+            return Ok(Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .header("Access-Control-Allow-Origin", "*")
+                .header("X-Request-ID", request_id)
+                .body(empty_body())
+                .unwrap());
+        }
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .header("Access-Control-Allow-Origin", "*")
+            .header("X-Request-ID", request_id)
+            .body(full_body(error_response.to_string()))
+            .unwrap());
+    }
+
+    // Redaction depends on the caller, so it runs after the (shared) cache
+    let redaction = if notification || streaming {
+        None
+    } else {
+        health_checker.redaction.plan(&parts.headers, &body_bytes)
+    };
+    let id_normalization = if notification || streaming {
+        None
+    } else {
+        health_checker.id_normalizer.plan(&body_bytes)
+    };
+
+    let cache_key = if notification || streaming {
+        None
+    } else {
+        let api_version = requested_version(&parts.headers, parts.uri.query());
+        let timestamp_format = requested_format(&parts.headers, parts.uri.query());
+        let authorization = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        health_checker.response_cache.key_for(
+            target_service.name(),
+            api_version.as_deref(),
+            timestamp_format.as_deref(),
+            caller_org.as_deref(),
+            authorization,
+            &body_bytes,
+        )
+    };
+
+    if let Some(key) = &cache_key {
+        match health_checker.response_cache.lookup(key).await {
+            CacheLookup::Fresh(cached) => {
+                health_checker.metrics.increment_cache_hits();
+                health_checker.metrics.increment_successful_requests();
+                health_checker.metrics.decrement_active_connections();
+                debug!("📦 [{}] Served from cache", request_id);
+                return Ok(with_route_debug(
+                    cached_response(
+                        cached,
+                        redaction.as_ref(),
+                        id_normalization.as_ref(),
+                        &request_id,
+                        "HIT",
+                    ),
+                    route_rule.as_deref(),
+                    &target_service,
+                    Some("HIT"),
+                    Vec::new(),
+                    start_time,
+                ));
+            }
+            CacheLookup::Stale(cached) => {
+                health_checker.metrics.increment_cache_stale_hits();
+                health_checker.metrics.increment_successful_requests();
+                health_checker.metrics.decrement_active_connections();
+                if health_checker.response_cache.begin_refresh(key).await {
+                    spawn_cache_refresh(
+                        key.clone(),
+                        &parts,
+                        body_bytes.clone(),
+                        target_service.clone(),
+                        &request_id,
+                    );
+                }
+                debug!("📦 [{}] Served stale from cache", request_id);
+                return Ok(with_route_debug(
+                    cached_response(
+                        cached,
+                        redaction.as_ref(),
+                        id_normalization.as_ref(),
+                        &request_id,
+                        "STALE",
+                    ),
+                    route_rule.as_deref(),
+                    &target_service,
+                    Some("STALE"),
+                    Vec::new(),
+                    start_time,
+                ));
+            }
+            CacheLookup::Miss => health_checker.metrics.increment_cache_misses(),
+        }
+    }
+
+    // Recorded for replay while an admin captures this route
+    let capture = (!streaming && health_checker.capture.wants(parts.uri.path()))
+        .then(|| CapturedExchange::for_request(&request_id, &parts, &body_bytes));
+
+    let mut attempts = Vec::new();
+    match proxy_request_with_retry(
+        parts,
+        body_bytes.clone(),
+        target_service.clone(),
+        &request_id,
+        deadline,
+        streaming,
+        &mut attempts,
+    )
+    .await
+    {
+        Ok(response) => {
+            let status = response.status();
+            let outcome = UpstreamOutcome::classify(status);
+            // A service shedding load is saturated, not broken: it must not
+            // open the circuit, and clients get its busy error as is
+            let busy = response.headers().contains_key(SERVICE_BUSY_HEADER);
+            if !busy {
+                health_checker
+                    .record_upstream_outcome(&target_service, outcome)
+                    .await;
+            }
+
+            let response = match &cache_key {
+                Some(key) if status.is_success() => {
+                    store_in_cache(&health_checker.response_cache, key, response).await
+                }
+                _ => response,
+            };
+
+            // Optionally replace upstream error bodies with the gateway's envelope
+            let rewrite = if notification || busy {
+                None
+            } else {
+                health_checker.status_policy.rewrite(
+                    status,
+                    target_service.name(),
+                    &body_bytes,
+                    &request_id,
+                )
+            };
+            let response = match rewrite {
+                Some(envelope) => Response::builder()
+                    .status(status)
+                    .header("Content-Type", "application/json")
+                    .header("Access-Control-Allow-Origin", "*")
+                    .body(full_body(envelope.to_string()))
+                    .unwrap(),
+                None => response,
+            };
+            let response = match &redaction {
+                Some(plan) if status.is_success() => {
+                    redact_response(plan, response, &request_id).await
+                }
+                _ => response,
+            };
+            let response = match &id_normalization {
+                Some(plan) if status.is_success() => {
+                    normalize_response_ids(plan, response, &request_id).await
+                }
+                _ => response,
+            };
+            let response = match capture {
+                Some(exchange) => {
+                    capture_response(&health_checker.capture, exchange, response, start_time).await
+                }
+                None => response,
+            };
+
+            // Latency counts until the headers arrive, since streams are
+            // open-ended; the request itself is counted when its body ends
+            let stream = streaming && !busy && !outcome.is_failure();
+            let elapsed = start_time.elapsed();
+            let duration = elapsed.as_millis() as u64;
+            health_checker.metrics.record_response_time(elapsed);
+            if !stream {
+                health_checker.metrics.decrement_active_connections();
+            }
+            health_checker.slo.record(
+                target_service.key(),
+                !busy && !outcome.is_failure(),
+                elapsed,
+            );
+
+            if stream {
+                debug!(
+                    "📺 [{}] Streaming response from {} after {}ms",
+                    request_id,
+                    target_service.name(),
+                    duration
+                );
+            } else if busy {
+                health_checker.metrics.increment_failed_requests();
+                debug!(
+                    "⏸️ [{}] {} is busy, asked client to retry",
+                    request_id,
+                    target_service.name()
+                );
+            } else if outcome.is_failure() {
+                health_checker.metrics.increment_service_errors();
+                health_checker.metrics.increment_failed_requests();
+                warn!(
+                    "⚠️ [{}] {} responded {} after {}ms",
+                    request_id,
+                    target_service.name(),
+                    status,
+                    duration
+                );
+            } else {
+                health_checker.metrics.increment_successful_requests();
+                if sample_success() {
+                    info!("✅ [{}] Request completed in {}ms", request_id, duration);
+                }
+            }
+
+            // Add request ID to response
+            let (mut parts, body) = response.into_parts();
+            parts.headers.insert("X-Request-ID", request_id.into());
+            // Extend the service's timing breakdown with the gateway's share
+            if let Some(timing) = parts
+                .headers
+                .get(SERVER_TIMING_HEADER)
+                .and_then(|value| value.to_str().ok())
+            {
+                let timing = format!(
+                    "{}, gateway;dur={:.1}",
+                    timing,
+                    elapsed.as_secs_f64() * 1000.0
+                );
+                if let Ok(value) = timing.parse() {
+                    parts.headers.insert(SERVER_TIMING_HEADER, value);
+                }
+            }
+            let body = if stream {
+                // Keep holding the overload and lane slots while streaming
+                let guards = (_in_flight, _lane_permit);
+                let target_service = target_service.clone();
+                // The stream ends outside this request's sampling scope
+                let sampled = sample_success();
+                MeteredBody::new(body, move |end, bytes| {
+                    drop(guards);
+                    finish_stream(
+                        &target_service,
+                        &request_id,
+                        end,
+                        bytes,
+                        sampled,
+                        start_time,
+                    );
+                })
+                .boxed()
+            } else {
+                body
+            };
+            Ok(with_route_debug(
+                Response::from_parts(parts, body),
+                route_rule.as_deref(),
+                &target_service,
+                None,
+                attempts,
+                start_time,
+            ))
+        }
+        Err(err) => {
+            let elapsed = start_time.elapsed();
+            let duration = elapsed.as_millis() as u64;
+            health_checker.metrics.record_response_time(elapsed);
+            health_checker.metrics.increment_failed_requests();
+            health_checker.metrics.decrement_active_connections();
+            health_checker
+                .slo
+                .record(target_service.key(), false, elapsed);
+
+            // Tell clients their budget ran out, not that the proxy broke
+            if let Some(exceeded) = err.downcast_ref::<GatewayDeadlineExceeded>() {
+                warn!("⏰ [{}] {} after {}ms", request_id, exceeded, duration);
+                let envelope = exceeded.envelope(&body_bytes, &request_id);
+                let response = Response::builder()
+                    .status(StatusCode::GATEWAY_TIMEOUT)
+                    .header("Content-Type", "application/json")
+                    .header("Access-Control-Allow-Origin", "*")
+                    .header("X-Request-ID", request_id)
+                    .body(full_body(envelope.to_string()))
+                    .unwrap();
+                return Ok(with_route_debug(
+                    response,
+                    route_rule.as_deref(),
+                    &target_service,
+                    None,
+                    attempts,
+                    start_time,
+                ));
+            }
+
+            error!(
+                "❌ [{}] Proxy error after {}ms: {}",
+                request_id, duration, err
+            );
+            let response = Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .header("Access-Control-Allow-Origin", "*")
+                .header("X-Request-ID", request_id)
+                .body(full_body(format!("Proxy error: {}", err)))
+                .unwrap();
+            Ok(with_route_debug(
+                response,
+                route_rule.as_deref(),
+                &target_service,
+                None,
+                attempts,
+                start_time,
+            ))
+        }
+    }
+}
+
+/// Counts a streamed request once its body has ended
+fn finish_stream(
+    target_service: &TargetService,
+    request_id: &RequestId,
+    end: StreamEnd,
+    bytes: u64,
+    sampled: bool,
+    start_time: Instant,
+) {
+    let health_checker = HEALTH_CHECKER.get().unwrap();
+    let duration = start_time.elapsed().as_millis();
+    health_checker.metrics.record_stream_end(&end, bytes);
+    health_checker.metrics.decrement_active_connections();
+    match end {
+        StreamEnd::UpstreamError(err) => {
+            health_checker
+                .upstream(target_service)
+                .metrics
+                .record_failure(UpstreamFailure::BodyRead);
+            health_checker.metrics.increment_service_errors();
+            health_checker.metrics.increment_failed_requests();
+            warn!(
+                "⚠️ [{}] Stream from {} broke after {}ms and {} bytes: {}",
+                request_id,
+                target_service.name(),
+                duration,
+                bytes,
+                err
+            );
+        }
+        StreamEnd::Completed | StreamEnd::ClientGone => {
+            health_checker.metrics.increment_successful_requests();
+            if sampled {
                 info!(
+                    "✅ [{}] Stream {} after {}ms and {} bytes",
+                    request_id,
+                    if end == StreamEnd::Completed {
+                        "completed"
+                    } else {
+                        "closed by the client"
+                    },
+                    duration,
+                    bytes
+                );
+            }
+        }
+    }
+}
+
+/// Adds the routing decision to the response when an admin asked for it
+/// (`rule` is only set then)
+fn with_route_debug(
+    mut response: Response<BoxBody>,
+    rule: Option<&str>,
+    target_service: &TargetService,
+    cache: Option<&'static str>,
+    attempts: Vec<RouteAttempt>,
+    start_time: Instant,
+) -> Response<BoxBody> {
+    let Some(rule) = rule else {
+        return response;
+    };
+    let instance = attempts
+        .iter()
+        .rev()
+        .find_map(|attempt| attempt.instance.clone())
+        .unwrap_or_else(|| {
+            HEALTH_CHECKER
+                .get()
+                .unwrap()
+                .upstream(target_service)
+                .base_url()
+        });
+    let decision = RouteDecision {
+        rule: rule.to_string(),
+        service: target_service.key().to_string(),
+        instance,
+        cache,
+        attempts,
+        total_ms: start_time.elapsed().as_millis() as u64,
+    };
+    if let Some(value) = decision.header_value() {
+        response.headers_mut().insert(ROUTE_DEBUG_HEADER, value);
+    }
+    response
+}
+
+fn cached_response(
+    mut body: serde_json::Value,
+    redaction: Option<&RedactionPlan>,
+    id_normalization: Option<&IdNormalizationPlan>,
+    request_id: &RequestId,
+    cache_status: &str,
+) -> Response<BoxBody> {
+    if let Some(plan) = redaction {
+        plan.apply(&mut body);
+    }
+    if let Some(plan) = id_normalization {
+        plan.apply(&mut body);
+    }
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("X-Request-ID", *request_id)
+        .header("X-Cache", cache_status)
+        .body(full_body(body.to_string()))
+        .unwrap()
+}
+
+/// Buffers the upstream response and strips the fields the caller may not see
+async fn redact_response(
+    plan: &RedactionPlan,
+    response: Response<BoxBody>,
+    request_id: &RequestId,
+) -> Response<BoxBody> {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(err) => return Response::from_parts(parts, full_body(format!("Proxy error: {}", err))),
+    };
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, full_body(bytes));
+    };
+
+    let redacted = plan.apply(&mut value);
+    if redacted == 0 {
+        return Response::from_parts(parts, full_body(bytes));
+    }
+    debug!("🙈 [{}] Redacted {} response fields", request_id, redacted);
+    parts.headers.remove(hyper::header::CONTENT_LENGTH);
+    Response::from_parts(parts, full_body(value.to_string()))
+}
+
+/// Buffers the upstream response and rewrites record ids into plain strings
+async fn normalize_response_ids(
+    plan: &IdNormalizationPlan,
+    response: Response<BoxBody>,
+    request_id: &RequestId,
+) -> Response<BoxBody> {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(err) => return Response::from_parts(parts, full_body(format!("Proxy error: {}", err))),
+    };
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, full_body(bytes));
+    };
+
+    let normalized = plan.apply(&mut value);
+    if normalized == 0 {
+        return Response::from_parts(parts, full_body(bytes));
+    }
+    debug!("🪪 [{}] Normalized {} record ids", request_id, normalized);
+    parts.headers.remove(hyper::header::CONTENT_LENGTH);
+    Response::from_parts(parts, full_body(value.to_string()))
+}
+
+/// Buffers the upstream response, caches it and hands back an equivalent one
+async fn store_in_cache(
+    cache: &ResponseCache,
+    key: &CacheKey,
+    response: Response<BoxBody>,
+) -> Response<BoxBody> {
+    let (parts, body) = response.into_parts();
+    match body.collect().await {
+        Ok(collected) => {
+            let bytes = collected.to_bytes();
+            cache.store(key, &bytes).await;
+            Response::from_parts(parts, full_body(bytes))
+        }
+        Err(err) => Response::from_parts(parts, full_body(format!("Proxy error: {}", err))),
+    }
+}
+
+/// Buffers the response as the client gets it and appends the exchange to
+/// the capture file
+async fn capture_response(
+    capture: &TrafficCapture,
+    mut exchange: CapturedExchange,
+    response: Response<BoxBody>,
+    start_time: Instant,
+) -> Response<BoxBody> {
+    let (parts, body) = response.into_parts();
+    let bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(err) => return Response::from_parts(parts, full_body(format!("Proxy error: {}", err))),
+    };
+    exchange.status = parts.status.as_u16();
+    exchange.response_body = String::from_utf8_lossy(&bytes).into_owned();
+    exchange.duration_ms = start_time.elapsed().as_millis() as u64;
+    if let Err(err) = capture.record(&exchange) {
+        warn!(
+            "⚠️ [{}] Failed to capture request: {}",
+            exchange.request_id, err
+        );
+    }
+    Response::from_parts(parts, full_body(bytes))
+}
+
+/// Re-issues a request in the background to replace a stale cache entry
+fn spawn_cache_refresh(
+    key: CacheKey,
+    parts: &Parts,
+    body_bytes: Bytes,
+    target_service: TargetService,
+    request_id: &RequestId,
+) {
+    let (mut refresh_parts, _) = Request::builder()
+        .method(parts.method.clone())
+        .uri(parts.uri.clone())
+        .body(())
+        .unwrap()
+        .into_parts();
+    refresh_parts.headers = parts.headers.clone();
+    let refresh_id = RequestId::generate();
+    debug!(
+        "♻️ [{}] Refreshing stale cache entry as {}",
+        request_id, refresh_id
+    );
+
+    tokio::spawn(async move {
+        let health_checker = HEALTH_CHECKER.get().unwrap();
+        // Nobody waits on a refresh, so it gets the route's full budget
+        let deadline = health_checker
+            .deadlines
+            .deadline_for(refresh_parts.uri.path(), &HeaderMap::new());
+        match proxy_request_with_retry(
+            refresh_parts,
+            body_bytes,
+            target_service,
+            &refresh_id,
+            deadline,
+            false,
+            &mut Vec::new(),
+        )
+        .await
+        {
+            Ok(response) if response.status().is_success() => {
+                store_in_cache(&health_checker.response_cache, &key, response).await;
+            }
+            Ok(response) => {
+                warn!(
+                    "⚠️ [{}] Cache refresh got status {}",
+                    refresh_id,
+                    response.status()
+                );
+            }
+            Err(err) => {
+                warn!("⚠️ [{}] Cache refresh failed: {}", refresh_id, err);
+            }
+        }
+        health_checker.response_cache.end_refresh(&key).await;
+    });
+}
+
+async fn proxy_request_with_retry(
+    parts: Parts,
+    body_bytes: Bytes,
+    target_service: TargetService,
+    request_id: &RequestId,
+    deadline: RequestDeadline,
+    streaming: bool,
+    attempts: &mut Vec<RouteAttempt>,
+) -> Result<Response<BoxBody>, Box<dyn std::error::Error + Send + Sync>> {
+    const MAX_RETRIES: u32 = 3;
+    const RETRY_DELAY_MS: u64 = 100;
+
+    let method = parts.method;
+    let uri = parts.uri;
+    let headers = parts.headers;
+
+    let health_checker = HEALTH_CHECKER.get().unwrap();
+    let pool = health_checker.pool(&target_service);
+    let balance_key = health_checker
+        .balancing
+        .request_key(&headers, uri.path(), &body_bytes);
+
+    // A timed-out create may still have succeeded upstream, so only requests
+    // that never left the gateway are retried unless every call is idempotent
+    let retry_safe = health_checker
+        .idempotency
+        .is_retry_safe(&method, &body_bytes);
+
+    for attempt in 1..=MAX_RETRIES {
+        let remaining = deadline.remaining();
+        if remaining.is_zero() {
+            return Err(deadline.exceeded(target_service.name()).into());
+        }
+        let order = pool.pick_order(balance_key.as_deref(), attempt);
+        let upstream = &pool.instances()[order[0]];
+        let instance = (pool.instances().len() > 1).then(|| upstream.base_url());
+
+        // Every instance asked for a break; don't add to their load
+        if let Some(wait) = upstream.busy_remaining() {
+            debug!(
+                "⏸️ [{}] {} is shedding load, backing off for {}ms",
+                request_id,
+                target_service.name(),
+                wait.as_millis()
+            );
+            let mut route_attempt = RouteAttempt::failed(attempt, "backing off", Duration::ZERO);
+            route_attempt.instance = instance;
+            attempts.push(route_attempt);
+            return Ok(busy_response(wait));
+        }
+
+        let upstream_req = build_upstream_request(
+            &method,
+            &uri,
+            &headers,
+            &health_checker.header_forwarding,
+            upstream,
+            remaining,
+            &body_bytes,
+        )?;
+
+        // A slow first try at an idempotent request may get a second one,
+        // sent to the next instance that can take it; a single instance is
+        // never hedged, as the copy would only add to its load
+        let hedge_upstream = order
+            .get(1)
+            .map(|index| &pool.instances()[*index])
+            .filter(|next| {
+                !next.is_down() && !next.is_draining() && next.busy_remaining().is_none()
+            });
+        let hedge = match (&health_checker.hedging, hedge_upstream) {
+            (Some(policy), Some(next)) if attempt == 1 && retry_safe => policy
+                .delay_for(&upstream.metrics.ttfb_snapshot())
+                .map(|delay| (Arc::clone(policy), delay, next)),
+            _ => None,
+        };
+        let hedge = match hedge {
+            Some((policy, delay, next)) => Some(Hedge {
+                policy,
+                delay,
+                upstream: next,
+                request: build_upstream_request(
+                    &method,
+                    &uri,
+                    &headers,
+                    &health_checker.header_forwarding,
+                    next,
+                    remaining,
+                    &body_bytes,
+                )?,
+            }),
+            None => None,
+        };
+
+        let sent_at = Instant::now();
+        let result = timeout(
+            remaining.min(UPSTREAM_ATTEMPT_TIMEOUT),
+            send_hedged(upstream, upstream_req, hedge, request_id),
+        )
+        .await;
+        let (result, hedged, sent_at) = match result {
+            Ok((result, hedged_at)) => (
+                Ok(result),
+                hedged_at.is_some(),
+                hedged_at.unwrap_or(sent_at),
+            ),
+            Err(elapsed) => (Err(elapsed), false, sent_at),
+        };
+        match &result {
+            Ok(Ok(upstream_resp)) => upstream
+                .metrics
+                .record_response(upstream_resp.status(), sent_at.elapsed()),
+            Ok(Err(err)) if err.is_connect() => {
+                upstream.metrics.record_failure(UpstreamFailure::Connect)
+            }
+            Ok(Err(_)) => upstream.metrics.record_failure(UpstreamFailure::Transport),
+            Err(_) => upstream.metrics.record_failure(UpstreamFailure::Timeout),
+        }
+        let mut route_attempt = match &result {
+            Ok(Ok(upstream_resp)) => {
+                RouteAttempt::responded(attempt, upstream_resp.status().as_u16(), sent_at.elapsed())
+            }
+            Ok(Err(err)) => RouteAttempt::failed(attempt, err.to_string(), sent_at.elapsed()),
+            Err(_) => RouteAttempt::failed(attempt, "timed out", sent_at.elapsed()),
+        };
+        route_attempt.hedged = hedged;
+        // The instance whose response is used
+        let answered_by = match hedge_upstream {
+            Some(next) if hedged => {
+                route_attempt.instance = Some(next.base_url());
+                next
+            }
+            _ => {
+                route_attempt.instance = instance;
+                upstream
+            }
+        };
+        attempts.push(route_attempt);
+        // Retrying a busy service would only add to its load
+        let busy = matches!(&result, Ok(Ok(upstream_resp)) if answered_by.note_busy(upstream_resp));
+
+        match result {
+            Ok(Ok(upstream_resp))
+                if attempt < MAX_RETRIES
+                    && retry_safe
+                    && !busy
+                    && health_checker
+                        .status_policy
+                        .should_retry(upstream_resp.status()) =>
+            {
+                warn!(
+                    "🔁 [{}] {} responded {} on attempt {}/{}",
+                    request_id,
+                    target_service.name(),
+                    upstream_resp.status(),
+                    attempt,
+                    MAX_RETRIES
+                );
+            }
+            Ok(Ok(upstream_resp)) => {
+                debug!(
                     "✅ [{}] Request to {} succeeded on attempt {}",
                     request_id,
                     target_service.name(),
@@ -491,8 +2410,19 @@ async fn proxy_request_with_retry(
                 }
                 resp_builder = resp_builder.header("Access-Control-Allow-Origin", "*");
 
+                // Streamed routes hand the body over as it arrives
+                if streaming {
+                    return Ok(resp_builder.body(upstream_resp.into_body().boxed())?);
+                }
+
                 // Get response body
-                let response_body_bytes = upstream_resp.collect().await?.to_bytes();
+                let response_body_bytes = match upstream_resp.collect().await {
+                    Ok(collected) => collected.to_bytes(),
+                    Err(err) => {
+                        upstream.metrics.record_failure(UpstreamFailure::BodyRead);
+                        return Err(err.into());
+                    }
+                };
 
                 return Ok(resp_builder.body(full_body(response_body_bytes))?);
             }
@@ -505,6 +2435,14 @@ async fn proxy_request_with_retry(
                     MAX_RETRIES,
                     err
                 );
+                if !retry_safe && !err.is_connect() {
+                    warn!(
+                        "🛑 [{}] Not retrying a non-idempotent request that may have reached {}",
+                        request_id,
+                        target_service.name()
+                    );
+                    return Err(err.into());
+                }
             }
             Err(_) => {
                 warn!(
@@ -514,12 +2452,24 @@ async fn proxy_request_with_retry(
                     attempt,
                     MAX_RETRIES
                 );
+                if deadline.is_expired() {
+                    return Err(deadline.exceeded(target_service.name()).into());
+                }
+                if !retry_safe {
+                    warn!(
+                        "🛑 [{}] Not retrying a non-idempotent request that may have reached {}",
+                        request_id,
+                        target_service.name()
+                    );
+                    return Err(format!("Request to {} timed out", target_service.name()).into());
+                }
             }
         }
 
         // Wait before retrying (except on last attempt)
         if attempt < MAX_RETRIES {
-            sleep(Duration::from_millis(RETRY_DELAY_MS * attempt as u64)).await;
+            sleep(Duration::from_millis(RETRY_DELAY_MS * attempt as u64).min(deadline.remaining()))
+                .await;
         }
     }
 
@@ -531,6 +2481,137 @@ async fn proxy_request_with_retry(
     .into())
 }
 
+/// One upstream request for `proxy_request_with_retry`, with the headers
+/// `forwarding` allows for the path minus any previous deadline or
+/// signature, authorized for the client by
+/// [`UpstreamConnection::authorize_client`]
+fn build_upstream_request(
+    method: &Method,
+    uri: &hyper::Uri,
+    headers: &HeaderMap,
+    forwarding: &HeaderForwarding,
+    upstream: &UpstreamConnection,
+    remaining: Duration,
+    body_bytes: &Bytes,
+) -> Result<Request<Full<Bytes>>, hyper::http::Error> {
+    let path_and_query = uri.path_and_query().map_or("/", |p| p.as_str());
+    let mut upstream_req =
+        Request::builder()
+            .method(method)
+            .uri(format!("{}{}", upstream.base_url(), path_and_query));
+    for (name, value) in headers {
+        if !forwarding.forwards(uri.path(), name)
+            || name == DEADLINE_HEADER
+            || name == CLIENT_AUTHORIZATION_HEADER
+            || name == SIGNATURE_TIMESTAMP_HEADER
+            || name == SIGNATURE_NONCE_HEADER
+            || name == SIGNATURE_HEADER
+            || name == AUTHORIZATION
+        {
+            continue;
+        }
+        upstream_req = upstream_req.header(name, value);
+    }
+    let client_authorization = headers
+        .get(AUTHORIZATION)
+        .filter(|_| forwarding.forwards(uri.path(), &AUTHORIZATION));
+    upstream_req = upstream.authorize_client(upstream_req, client_authorization);
+    upstream_req = upstream_req.header(DEADLINE_HEADER, remaining.as_millis().to_string());
+    // Keep no pooled connection to an instance being taken out
+    if upstream.is_draining() {
+        upstream_req = upstream_req.header(CONNECTION, "close");
+    }
+    upstream_req = upstream.sign(upstream_req, method.as_str(), path_and_query, body_bytes);
+    upstream_req.body(Full::new(body_bytes.clone()))
+}
+
+/// A hedged copy of a request, for another instance of the service
+struct Hedge<'a> {
+    policy: Arc<HedgePolicy>,
+    /// How long the first attempt gets before the copy is sent
+    delay: Duration,
+    upstream: &'a UpstreamConnection,
+    request: Request<Full<Bytes>>,
+}
+
+/// Sends `request` and, when `hedge` is given and no response headers have
+/// arrived after its delay, the hedged copy to its instance too (budget
+/// permitting). The first response wins and the other request is dropped,
+/// which cancels it. Also returns when the hedge was sent, if its answer is
+/// the one used.
+async fn send_hedged(
+    upstream: &UpstreamConnection,
+    request: Request<Full<Bytes>>,
+    hedge: Option<Hedge<'_>>,
+    request_id: &RequestId,
+) -> (
+    Result<Response<hyper::body::Incoming>, hyper_util::client::legacy::Error>,
+    Option<Instant>,
+) {
+    let primary = upstream.client.request(request);
+    let Some(Hedge {
+        policy,
+        delay,
+        upstream: hedge_upstream,
+        request: hedge_req,
+    }) = hedge
+    else {
+        return (primary.await, None);
+    };
+    tokio::pin!(primary);
+    tokio::select! {
+        result = &mut primary => return (result, None),
+        _ = sleep(delay) => {}
+    }
+    if !policy.try_hedge() {
+        return (primary.await, None);
+    }
+
+    debug!(
+        "🪞 [{}] No response after {}ms, hedging to {}",
+        request_id,
+        delay.as_millis(),
+        hedge_upstream.base_url()
+    );
+    let hedged_at = Instant::now();
+    let hedged = hedge_upstream.client.request(hedge_req);
+    tokio::pin!(hedged);
+    // A failed attempt doesn't win; the other may still get through
+    tokio::select! {
+        result = &mut primary => match result {
+            Ok(response) => (Ok(response), None),
+            Err(_) => {
+                let result = hedged.await;
+                if result.is_ok() {
+                    policy.record_hedge_win();
+                }
+                (result, Some(hedged_at))
+            }
+        },
+        result = &mut hedged => match result {
+            Ok(response) => {
+                policy.record_hedge_win();
+                (Ok(response), Some(hedged_at))
+            }
+            Err(_) => (primary.await, None),
+        },
+    }
+}
+
+/// `503` telling the client the service is shedding load, in the same shape
+/// the service itself sends
+fn busy_response(retry_after: Duration) -> Response<BoxBody> {
+    let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header("Content-Type", "application/json")
+        .header("Retry-After", retry_after_secs)
+        .header(SERVICE_BUSY_HEADER, "1")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(full_body(busy_error(retry_after).to_string()))
+        .unwrap()
+}
+
 fn empty_body() -> BoxBody {
     Full::new(Bytes::new())
         .map_err(|never| match never {})
@@ -563,90 +2644,745 @@ impl TargetService {
             TargetService::ProductService => "Product Service",
         }
     }
+
+    /// Identifier in shared health state
+    fn key(&self) -> &'static str {
+        match self {
+            TargetService::UserService => "user_service",
+            TargetService::ProductService => "product_service",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "user_service" => Some(TargetService::UserService),
+            "product_service" => Some(TargetService::ProductService),
+            _ => None,
+        }
+    }
 }
 
-fn determine_target_service(path: &str) -> TargetService {
-    if path.starts_with("/api/users") || path.contains("user") {
-        TargetService::UserService
-    } else if path.starts_with("/api/products") || path.contains("product") {
-        TargetService::ProductService
-    } else {
-        // Default to user service for backward compatibility
-        TargetService::UserService
+impl From<Upstream> for TargetService {
+    fn from(upstream: Upstream) -> Self {
+        match upstream {
+            Upstream::User => TargetService::UserService,
+            Upstream::Product => TargetService::ProductService,
+        }
     }
 }
 
+/// `uri` with its path replaced, keeping the query
+fn rewrite_path(uri: &hyper::Uri, path: &str) -> Option<hyper::Uri> {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    hyper::Uri::from_parts(parts).ok()
+}
+
 // Global health checker instance
 static HEALTH_CHECKER: tokio::sync::OnceCell<Arc<HealthChecker>> =
     tokio::sync::OnceCell::const_new();
 
+/// Loads every setting the gateway reads at startup, as `--check-config`
+fn check_config() -> SelfTestReport {
+    load_config().0
+}
+
+/// The configuration checks, with the upstream connections that loaded so
+/// the self-test can probe them
+fn load_config() -> (SelfTestReport, Vec<(TargetService, UpstreamPool)>) {
+    let mut report = SelfTestReport::default();
+    report.check(
+        "config.schemas",
+        MethodSchemaRegistry::from_env(),
+        |registry| format!("{} methods", registry.len()),
+    );
+    report.check("config.overload", OverloadConfig::from_env(), |_| {
+        "loaded".to_string()
+    });
+    report.check(
+        "config.priority_lanes",
+        PriorityLanes::from_env(),
+        |lanes| {
+            lanes
+                .as_ref()
+                .map_or("disabled".to_string(), PriorityLanes::describe)
+        },
+    );
+    report.check("config.hedging", HedgingConfig::from_env(), |config| {
+        config.as_ref().map_or("disabled".to_string(), |config| {
+            format!("up to {}% of idempotent requests", config.max_percent)
+        })
+    });
+    report.check("config.org_keys", OrgKeys::from_env(), |keys| {
+        format!("{} tokens mapped to organizations", keys.len())
+    });
+    report.check(
+        "config.streaming_routes",
+        StreamingRoutes::from_env(),
+        |routes| {
+            if routes.is_empty() {
+                "none".to_string()
+            } else {
+                routes.describe()
+            }
+        },
+    );
+    report.check("config.form_routes", FormRoutes::from_env(), |routes| {
+        format!("{} routes", routes.len())
+    });
+    report.check("config.status_policy", StatusPolicy::from_env(), |policy| {
+        format!("{} retried statuses", policy.retry_statuses.len())
+    });
+    report.check("config.redaction", RedactionPolicy::from_env(), |policy| {
+        format!("{} rules", policy.rule_count())
+    });
+    report.check("config.deadlines", DeadlinePolicy::from_env(), |_| {
+        "loaded".to_string()
+    });
+    report.check(
+        "config.sanitizer",
+        RequestSanitizer::from_env(),
+        |sanitizer| format!("{} blocked paths", sanitizer.blocked_count()),
+    );
+    report.check(
+        "config.header_forwarding",
+        HeaderForwarding::from_env(),
+        |forwarding| {
+            format!(
+                "{} forwarded headers, {} route overrides",
+                forwarding.forwarded_count(),
+                forwarding.route_count()
+            )
+        },
+    );
+    report.check(
+        "config.balancing",
+        BalancingConfig::from_env(),
+        BalancingConfig::describe,
+    );
+    report.check(
+        "config.request_filter",
+        RequestFilter::from_env(),
+        |filter| format!("{} rules", filter.rule_count()),
+    );
+    report.check("config.routing_rules", RoutingRules::from_env(), |rules| {
+        format!(
+            "{} rules, unmatched routes: {}",
+            rules.configured_count(),
+            rules.unmatched().name()
+        )
+    });
+    report.check(
+        "config.rate_limit_overrides",
+        RateLimitOverrides::from_env(),
+        |overrides| format!("{} overrides", overrides.list().len()),
+    );
+    report.check("config.listeners", ListenerConfig::from_env(), |configs| {
+        listeners::describe(configs)
+    });
+    report.check(
+        "config.tcp_listener",
+        TcpListenerConfig::from_env(),
+        |config| {
+            config.as_ref().map_or("disabled".to_string(), |config| {
+                format!("tcp://{}", config.addr)
+            })
+        },
+    );
+    report.check("config.draining", DrainConfig::from_env(), |config| {
+        format!(
+            "{}s delay, {}s timeout",
+            config.delay.as_secs(),
+            config.timeout.as_secs()
+        )
+    });
+    report.check("config.slo", SloTracker::from_env(), |slo| {
+        if slo.is_enabled() {
+            slo.describe()
+        } else {
+            "disabled".to_string()
+        }
+    });
+    report.check(
+        "config.shared_health",
+        SharedHealthConfig::from_env(),
+        |config| {
+            config.as_ref().map_or("disabled".to_string(), |config| {
+                format!("instance {}", config.instance_id)
+            })
+        },
+    );
+    let signer = report
+        .check(
+            "config.request_signing",
+            RequestSigner::from_env(),
+            |signer| {
+                if signer.is_some() {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+                .to_string()
+            },
+        )
+        .flatten()
+        .map(Arc::new);
+
+    let mut upstreams = Vec::new();
+    for service in [TargetService::UserService, TargetService::ProductService] {
+        let key = service.key();
+        let Some(credentials) = report.check(
+            format!("{}.credentials", key),
+            UpstreamCredentials::from_env(&key.to_ascii_uppercase()),
+            |credentials| {
+                if credentials.auth.is_some() {
+                    "authenticated"
+                } else {
+                    "anonymous"
+                }
+                .to_string()
+            },
+        ) else {
+            continue;
+        };
+        let tls = credentials.tls.clone();
+        let Some(upstream) = report.check(
+            format!("{}.tls", key),
+            UpstreamConnection::new(UPSTREAM_HOST, service.port(), credentials),
+            |_| match &tls {
+                Some(identity) => format!("client certificate {} loaded", identity.cert_path),
+                None => "not configured".to_string(),
+            },
+        ) else {
+            continue;
+        };
+        let Some(pool) = report.check(
+            format!("{}.instances", key),
+            UpstreamPool::from_env(
+                upstream.with_signer(signer.clone()),
+                &key.to_ascii_uppercase(),
+            ),
+            UpstreamPool::describe,
+        ) else {
+            continue;
+        };
+        upstreams.push((service, pool));
+    }
+    (report, upstreams)
+}
+
+/// Loads every setting the gateway reads at startup, then resolves and
+/// probes each upstream once, as `--self-test` for deployment pipelines
+async fn self_test() -> SelfTestReport {
+    let (mut report, upstreams) = load_config();
+    for (service, pool) in upstreams {
+        let key = service.key();
+        for (index, upstream) in pool.instances().iter().enumerate() {
+            // The first instance keeps the plain names
+            let name = match index {
+                0 => key.to_string(),
+                _ => format!("{}.{}", key, index),
+            };
+            let resolved = tokio::net::lookup_host((upstream.host.as_str(), upstream.port))
+                .await
+                .map(|addrs| addrs.map(|addr| addr.to_string()).collect::<Vec<_>>());
+            report.check(format!("{}.resolve", name), resolved, |addrs| {
+                addrs.join(", ")
+            });
+
+            let started = Instant::now();
+            report.check(
+                format!("{}.health", name),
+                upstream.probe_health(HEALTH_PROBE_TIMEOUT).await,
+                |_| {
+                    format!(
+                        "healthy at {} in {}ms",
+                        upstream.base_url(),
+                        started.elapsed().as_millis()
+                    )
+                },
+            );
+        }
+    }
+    report
+}
+
+/// Serves HTTP on one listener until accepting fails; each listener's
+/// requests run through the middleware for its role. Connections close
+/// once the gateway drains them, after the request they are serving.
+async fn accept_connections(
+    listener: TcpListener,
+    role: ListenerRole,
+    drain: Arc<GatewayDrain>,
+) -> std::io::Result<()> {
+    loop {
+        let (stream, client_addr) = listener.accept().await?;
+        let io = TokioIo::new(stream);
+        let open = drain.track_connection();
+        let mut closing = drain.closing();
+
+        tokio::task::spawn(async move {
+            let connection = http1::Builder::new().serve_connection(
+                io,
+                service_fn(move |req| handle_sampled_request(req, client_addr, role)),
+            );
+            tokio::pin!(connection);
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                _ = async { closing.wait_for(|closing| *closing).await.is_ok() } => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(err) = result {
+                error!("Error serving connection: {:?}", err);
+            }
+            drop(open);
+        });
+    }
+}
+
+/// Resolves on ctrl+c, or SIGTERM where there are signals
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.expect("Failed to listen for ctrl+c"),
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to listen for ctrl+c");
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn main() -> ExitCode {
+    // The report is the only output, so this runs before tracing starts
+    if std::env::args().skip(1).any(|arg| arg == "--self-test") {
+        let report = self_test().await;
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(err) => eprintln!("Failed to print the self-test report: {}", err),
+        }
+        return if report.ok {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+    if check_config_requested() {
+        return print_config_report(&check_config());
+    }
+    exit_code_for("Gateway", run().await, check_config)
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
+    init_tracing();
 
     info!("Starting Gateway...");
 
-    let addr = "127.0.0.1:8082";
-    let listener = TcpListener::bind(addr).await?;
+    let listener_configs = ListenerConfig::from_env()?;
+    let mut http_listeners = Vec::new();
+    for config in &listener_configs {
+        let listener = TcpListener::bind(config.addr)
+            .await
+            .map_err(|source| BindFailed {
+                addr: config.addr.to_string(),
+                source,
+            })?;
+        http_listeners.push((listener, config.role));
+    }
+
+    let schema_registry = MethodSchemaRegistry::from_env()?;
+    let cache_config = CacheConfig::from_env();
+    let overload_config = OverloadConfig::from_env()?;
+    let lanes = PriorityLanes::from_env()?;
+    let status_policy = StatusPolicy::from_env()?;
+    let redaction = RedactionPolicy::from_env()?;
+    let id_normalizer = IdNormalizer::from_env();
+    let idempotency = IdempotencyRegistry::from_env();
+    let deadlines = DeadlinePolicy::from_env()?;
+    let admin_tokens = AdminTokens::from_env();
+    let sanitizer = RequestSanitizer::from_env()?;
+    let routing_rules = RoutingRules::from_env()?;
+    let slo = SloTracker::from_env()?;
+    let rate_limit_overrides = RateLimitOverrides::from_env()?;
+    let hedging = HedgingConfig::from_env()?;
+    let org_keys = OrgKeys::from_env()?;
+    let streaming_routes = StreamingRoutes::from_env()?;
+    let form_routes = FormRoutes::from_env()?;
+    let request_filter = RequestFilter::from_env()?;
+    let capture = TrafficCapture::from_env();
+    let debug = DebugEndpoints::from_env();
+    let probes = ProbeCoalescer::from_env();
+    let header_forwarding = HeaderForwarding::from_env()?;
+    let drain_config = DrainConfig::from_env()?;
+    // Replicas that cannot reach the shared state run standalone
+    let tcp_listener = TcpListenerConfig::from_env()?;
+    let shared_health = match SharedHealthConfig::from_env()? {
+        Some(config) => match SharedHealthStore::connect(config).await {
+            Ok(store) => Some(store),
+            Err(err) => {
+                warn!(
+                    "⚠️ Shared health state unavailable, running standalone: {}",
+                    err
+                );
+                None
+            }
+        },
+        None => None,
+    };
+    let request_signer = RequestSigner::from_env()?.map(Arc::new);
+    let user_upstream = UpstreamConnection::new(
+        UPSTREAM_HOST,
+        TargetService::UserService.port(),
+        UpstreamCredentials::from_env("USER_SERVICE")?,
+    )?
+    .with_signer(request_signer.clone());
+    let user_upstream = UpstreamPool::from_env(user_upstream, "USER_SERVICE")?;
+    let product_upstream = UpstreamConnection::new(
+        UPSTREAM_HOST,
+        TargetService::ProductService.port(),
+        UpstreamCredentials::from_env("PRODUCT_SERVICE")?,
+    )?
+    .with_signer(request_signer);
+    let product_upstream = UpstreamPool::from_env(product_upstream, "PRODUCT_SERVICE")?;
+    let balancing = BalancingConfig::from_env()?;
 
     // Initialize health checker
-    let health_checker = Arc::new(HealthChecker::new());
+    let health_checker = Arc::new(HealthChecker::new(
+        schema_registry,
+        cache_config,
+        user_upstream,
+        product_upstream,
+        balancing,
+        overload_config,
+        lanes,
+        status_policy,
+        redaction,
+        id_normalizer,
+        idempotency,
+        deadlines,
+        admin_tokens,
+        sanitizer,
+        routing_rules,
+        slo,
+        shared_health,
+        rate_limit_overrides,
+        hedging,
+        org_keys,
+        streaming_routes,
+        form_routes,
+        request_filter,
+        capture,
+        debug,
+        probes,
+        header_forwarding,
+        drain_config,
+    ));
     HEALTH_CHECKER.set(Arc::clone(&health_checker)).unwrap();
 
     // Start health checks
     health_checker.start_health_checks().await;
+    health_checker.slo.spawn_alerts();
 
-    info!("🌐 Gateway started on http://{}", addr);
+    info!(
+        "🌐 Gateway started on {}",
+        listeners::describe(&listener_configs)
+    );
     info!("Production Features Enabled:");
     info!("  📊 Metrics endpoint: /metrics");
+    info!("  🗂️ Catalog snapshot endpoint: /catalog/snapshot");
+    info!(
+        "  🩺 Aggregated health: /health (probes shared for {}ms)",
+        health_checker.probes.fresh_for().as_millis()
+    );
+    info!("  📡 Health event stream: /health/stream");
+    if !health_checker.admin_tokens.is_empty() {
+        info!(
+            "  🧭 Routing table at /routes and X-Route-Debug for {} admin tokens",
+            health_checker.admin_tokens.len()
+        );
+    }
+    if !health_checker.admin_tokens.is_empty() {
+        let overrides = &health_checker.rate_limit_overrides;
+        info!(
+            "  🎚️ Rate limit overrides at /admin/rate-limits ({} active, {})",
+            overrides.list().len(),
+            if overrides.is_persistent() {
+                "saved to GATEWAY_RATE_LIMIT_OVERRIDES"
+            } else {
+                "kept in memory"
+            }
+        );
+    }
     info!("  🔍 Request tracing with X-Request-ID");
+    if health_checker.capture.is_available() && !health_checker.admin_tokens.is_empty() {
+        info!("  🎥 Traffic capture for replay at /admin/capture, written to GATEWAY_CAPTURE_FILE");
+    }
+    if !health_checker.admin_tokens.is_empty() {
+        info!(
+            "  🐞 Internal state dumps at /admin/debug ({})",
+            if health_checker.debug.is_enabled() {
+                "enabled"
+            } else {
+                "disabled until enable_debug_endpoints"
+            }
+        );
+    }
+    let request_filter = &health_checker.request_filter;
+    if request_filter.rule_count() > 0 || !health_checker.admin_tokens.is_empty() {
+        info!(
+            "  ⛔ {} filter rules, managed at /admin/filter-rules ({})",
+            request_filter.rule_count(),
+            if request_filter.is_persistent() {
+                "saved to GATEWAY_FILTER_RULES"
+            } else {
+                "kept in memory"
+            }
+        );
+    }
+    info!(
+        "  🎲 Logging {}% of successful requests (/admin/log-sampling, X-Trace-Debug for admins)",
+        log_policy().sampling_status().success_percent
+    );
+    let drain_config = health_checker.drain.config();
+    info!(
+        "  🚰 Connection draining on shutdown: {}s delay, {}s to finish{}",
+        drain_config.delay.as_secs(),
+        drain_config.timeout.as_secs(),
+        if health_checker.admin_tokens.is_empty() {
+            ""
+        } else {
+            "; upstream instances drained at /admin/drain"
+        }
+    );
+    let sanitizer = &health_checker.sanitizer;
+    match sanitizer.max_header_bytes() {
+        Some(limit) => info!(
+            "  🧽 Paths normalized before routing; headers up to {} bytes, {} blocked path patterns",
+            limit,
+            sanitizer.blocked_count()
+        ),
+        None => info!(
+            "  🧽 Paths normalized before routing; {} blocked path patterns",
+            sanitizer.blocked_count()
+        ),
+    }
+    if health_checker.slo.is_enabled() {
+        info!(
+            "  🎯 SLOs: {} (error budgets under slo in /metrics)",
+            health_checker.slo.describe()
+        );
+    }
+    if health_checker.routing_rules.configured_count() > 0 {
+        info!(
+            "  🧭 {} routing rules from GATEWAY_ROUTING_RULES",
+            health_checker.routing_rules.configured_count()
+        );
+    }
+    if !health_checker.form_routes.is_empty() {
+        info!(
+            "  📝 {} form routes from GATEWAY_FORM_ROUTES",
+            health_checker.form_routes.len()
+        );
+    }
     info!("  🚦 Rate limiting: 1000 requests/minute per IP");
     info!("  🔄 Circuit breaker with 3-failure threshold");
     info!("  ⚡ Retry logic: 3 attempts with exponential backoff");
+    info!(
+        "  🔂 Retries after timeouts or retryable statuses limited to {} idempotent methods",
+        health_checker.idempotency.len()
+    );
+    if let Some(hedging) = &health_checker.hedging {
+        let config = hedging.config();
+        info!(
+            "  🪞 Hedging idempotent requests slower than the upstream p95 (at least {}ms), up to {}% extra",
+            config.min_delay.as_millis(),
+            config.max_percent
+        );
+    }
+    info!(
+        "  ⏳ Request deadlines: {}ms by default, {} route overrides, propagated in {}",
+        health_checker.deadlines.default_timeout.as_millis(),
+        health_checker.deadlines.route_timeouts.len(),
+        DEADLINE_HEADER
+    );
+    if !health_checker.streaming_routes.is_empty() {
+        info!(
+            "  📺 Streaming pass-through for {}",
+            health_checker.streaming_routes.describe()
+        );
+    }
+    if !health_checker.org_keys.is_empty() {
+        info!(
+            "  🏢 Organization context forwarded for {} tokens",
+            health_checker.org_keys.len()
+        );
+    }
+    if let Some(lanes) = &health_checker.lanes {
+        info!(
+            "  🛣️ Priority lanes: {} ({} keyed tokens)",
+            lanes.describe(),
+            lanes.key_count()
+        );
+    }
     info!("  🌐 CORS support for web clients");
+    if health_checker.user_upstream.primary().signer.is_some() {
+        info!("  🔏 Upstream requests signed with GATEWAY_SIGNING_SECRET");
+    }
+    let status_policy = &health_checker.status_policy;
+    if !status_policy.retry_statuses.is_empty() || status_policy.rewrite_errors {
+        info!(
+            "  🩺 Upstream status policy: retry {:?}, rewrite errors: {}",
+            status_policy.retry_statuses, status_policy.rewrite_errors
+        );
+    }
+    info!(
+        "  🧾 Params schema validation: {} methods",
+        health_checker.schema_registry.len()
+    );
+    if health_checker.redaction.is_enabled() {
+        info!(
+            "  🙈 Response redaction: {} rules",
+            health_checker.redaction.rule_count()
+        );
+    }
+    if health_checker.id_normalizer.is_enabled() {
+        info!(
+            "  🪪 Record ids normalized for: {}",
+            health_checker.id_normalizer.describe()
+        );
+    }
+    if health_checker.response_cache.is_enabled() {
+        let cache_config = health_checker.response_cache.config();
+        info!(
+            "  📦 Response cache: {} methods, ttl {}s, serve stale up to {}s",
+            cache_config.methods.len(),
+            cache_config.ttl.as_secs(),
+            cache_config.max_stale.as_secs()
+        );
+    }
     info!("Routing configuration:");
-    info!("  - User Service: http://127.0.0.1:8080 (paths: /api/users, *user*)");
-    info!("  - Product Service: http://127.0.0.1:8081 (paths: /api/products, *product*)");
+    info!(
+        "  - User Service: {} (paths: /api/users, *user*)",
+        health_checker.user_upstream.describe()
+    );
+    info!(
+        "  - Product Service: {} (paths: /api/products, *product*)",
+        health_checker.product_upstream.describe()
+    );
     info!("  - Default: User Service (for backward compatibility)");
+    info!("  - Balancing: {}", health_checker.balancing.describe());
     info!("🔍 Health checks enabled - services monitored every 30 seconds");
+    if let Some(store) = &health_checker.shared_health {
+        let config = store.config();
+        info!(
+            "🤝 Shared health state: gateway '{}', probe lease {}s, sync every {}s",
+            config.instance_id,
+            config.lease.as_secs(),
+            config.sync_interval.as_secs()
+        );
+    }
 
-    // Set up graceful shutdown handling
-    let shutdown_signal = async {
-        tokio::signal::ctrl_c()
+    // Framed JSON-RPC for internal clients, next to the HTTP listener
+    if let Some(config) = tcp_listener {
+        let framed = TcpListener::bind(config.addr)
             .await
-            .expect("Failed to listen for ctrl+c");
-        info!("Received shutdown signal, gracefully shutting down gateway...");
-    };
+            .map_err(|source| BindFailed {
+                addr: config.addr.to_string(),
+                source,
+            })?;
+        info!(
+            "🔌 Framed JSON-RPC on tcp://{} (frames up to {} bytes)",
+            config.addr, config.max_frame_bytes
+        );
+        tokio::spawn(async move {
+            if let Err(err) = serve_frames(framed, config.max_frame_bytes, handle_frame).await {
+                error!("Framed listener stopped: {}", err);
+            }
+        });
+    }
 
-    // Run the server with graceful shutdown
+    let drain = Arc::clone(&health_checker.drain);
+    let mut serving = Box::pin(futures::future::try_join_all(
+        http_listeners
+            .into_iter()
+            .map(|(listener, role)| accept_connections(listener, role, Arc::clone(&drain))),
+    ));
+
+    // Run the server until a shutdown signal, then drain it
     tokio::select! {
-        _ = shutdown_signal => {
-            info!("Gateway shutdown signal received");
-        }
-        _ = async {
-            loop {
-                let (stream, _) = listener.accept().await?;
-                let io = TokioIo::new(stream);
-
-                tokio::task::spawn(async move {
-                    if let Err(err) = http1::Builder::new()
-                        .serve_connection(io, service_fn(handle_request))
-                        .await
-                    {
-                        error!("Error serving connection: {:?}", err);
-                    }
-                });
+        _ = shutdown_signal() => {
+            info!("Received shutdown signal, gracefully shutting down gateway...");
+        }
+        result = &mut serving => {
+            if let Err(err) = result {
+                error!("Gateway listener failed: {}", err);
             }
-            #[allow(unreachable_code)]
-            Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
-        } => {
             error!("Gateway server unexpectedly stopped");
         }
     }
 
+    // Fail /health and ask clients to reconnect elsewhere, while still
+    // accepting connections for the drain delay
+    drain.begin();
+    let drain_config = drain.config();
+    if !drain_config.delay.is_zero() {
+        info!(
+            "🚰 Draining: /health reports draining for {}s before the listeners close",
+            drain_config.delay.as_secs()
+        );
+        tokio::select! {
+            _ = sleep(drain_config.delay) => {}
+            _ = &mut serving => {}
+        }
+    }
+    drop(serving);
+
+    drain.close_connections();
+    if drain.open_connections() > 0 {
+        info!(
+            "🚰 Waiting up to {}s for {} open connections to finish",
+            drain_config.timeout.as_secs(),
+            drain.open_connections()
+        );
+    }
+    if !drain.wait_idle().await {
+        warn!(
+            "⚠️ Closing {} connections still open after {}s",
+            drain.open_connections(),
+            drain_config.timeout.as_secs()
+        );
+    }
+
     info!("Gateway shut down gracefully");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_endpoints_are_only_served_on_admin_listeners() {
+        for (path, endpoint) in ADMIN_ROUTES {
+            assert!(
+                listeners::is_admin_path(path),
+                "{path} is not an admin path"
+            );
+            assert!(ListenerRole::Admin.serves(path), "{path}");
+            assert!(!ListenerRole::Public.serves(path), "{path}");
+            assert_eq!(AdminEndpoint::for_path(path), Some(*endpoint));
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::{bail, Context};
+use hyper::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use jpc_rust::{
+    config::database::DatabaseConfig,
+    crypto::pii::PiiCipher,
+    models::{
+        product_model::{ExportProductsRequest, ExportProductsResponse, Product},
+        user_model::{ExportUsersRequest, ExportUsersResponse, User},
+    },
+    telemetry::log_policy::init_tracing,
+};
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::{HttpClient, HttpClientBuilder},
+    rpc_params,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+use surrealdb::{
+    engine::remote::ws::{Client, Ws},
+    opt::auth::Root,
+    Surreal,
+};
+use tracing::info;
+
+const PAGE_SIZE: usize = 500;
+
+struct MigrationConfig {
+    user_service_url: String,
+    product_service_url: String,
+    bearer_token: Option<String>,
+    target_address: String,
+    target_username: String,
+    target_password: String,
+}
+
+impl MigrationConfig {
+    fn from_env() -> Self {
+        let var =
+            |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+
+        Self {
+            user_service_url: var("MIGRATE_USER_SERVICE_URL", "http://127.0.0.1:8080"),
+            product_service_url: var("MIGRATE_PRODUCT_SERVICE_URL", "http://127.0.0.1:8081"),
+            bearer_token: std::env::var("MIGRATE_BEARER_TOKEN").ok(),
+            target_address: var("MIGRATE_TARGET_ADDRESS", "127.0.0.1:8000"),
+            target_username: var("MIGRATE_TARGET_USERNAME", "root"),
+            target_password: var("MIGRATE_TARGET_PASSWORD", "root"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CountResult {
+    total: usize,
+}
+
+async fn export_users(client: &HttpClient) -> anyhow::Result<Vec<User>> {
+    let mut users = Vec::new();
+
+    loop {
+        let request = ExportUsersRequest {
+            offset: users.len(),
+            limit: PAGE_SIZE,
+        };
+        let page: ExportUsersResponse = client
+            .request("export_users", rpc_params![request])
+            .await
+            .context("export_users failed")?;
+
+        let fetched = page.users.len();
+        users.extend(page.users);
+
+        if users.len() >= page.total || fetched == 0 {
+            if users.len() != page.total {
+                bail!(
+                    "User count changed during export: service reports {}, exported {}",
+                    page.total,
+                    users.len()
+                );
+            }
+            return Ok(users);
+        }
+    }
+}
+
+async fn export_products(client: &HttpClient) -> anyhow::Result<Vec<Product>> {
+    let mut products = Vec::new();
+
+    loop {
+        let request = ExportProductsRequest {
+            offset: products.len(),
+            limit: PAGE_SIZE,
+        };
+        let page: ExportProductsResponse = client
+            .request("export_products", rpc_params![request])
+            .await
+            .context("export_products failed")?;
+
+        let fetched = page.products.len();
+        products.extend(page.products);
+
+        if products.len() >= page.total || fetched == 0 {
+            if products.len() != page.total {
+                bail!(
+                    "Product count changed during export: service reports {}, exported {}",
+                    page.total,
+                    products.len()
+                );
+            }
+            return Ok(products);
+        }
+    }
+}
+
+/// Fails if any value appears twice, since the target enforces uniqueness
+fn ensure_unique<'a>(
+    values: impl Iterator<Item = &'a str>,
+    table: &str,
+    field: &str,
+) -> anyhow::Result<()> {
+    let mut seen = HashSet::new();
+    for value in values {
+        if !seen.insert(value) {
+            bail!("Duplicate {}.{} in source data: {}", table, field, value);
+        }
+    }
+    Ok(())
+}
+
+async fn count(db: &Surreal<Client>, table: &str) -> anyhow::Result<usize> {
+    let count: Option<CountResult> = db
+        .query("SELECT count() AS total FROM type::table($table) GROUP ALL")
+        .bind(("table", table))
+        .await?
+        .take(0)?;
+
+    Ok(count.map(|c| c.total).unwrap_or(0))
+}
+
+async fn prepare_table(
+    db: &Surreal<Client>,
+    table: &str,
+    unique_field: &str,
+) -> anyhow::Result<()> {
+    let existing = count(db, table).await?;
+    if existing > 0 {
+        bail!(
+            "Target table '{}' already contains {} records; refusing to merge",
+            table,
+            existing
+        );
+    }
+
+    db.query(format!(
+        "DEFINE INDEX {table}_{unique_field} ON TABLE {table} COLUMNS {unique_field} UNIQUE"
+    ))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+async fn verify_count(db: &Surreal<Client>, table: &str, expected: usize) -> anyhow::Result<()> {
+    let actual = count(db, table).await?;
+    if actual != expected {
+        bail!(
+            "Verification failed for '{}': expected {} records, found {}",
+            table,
+            expected,
+            actual
+        );
+    }
+    info!("✅ Verified {} records in '{}'", actual, table);
+    Ok(())
+}
+
+async fn import_users(
+    db: &Surreal<Client>,
+    target: &DatabaseConfig,
+    users: &[User],
+    cipher: Option<&PiiCipher>,
+) -> anyhow::Result<()> {
+    db.use_ns(&target.namespace)
+        .use_db(&target.database)
+        .await?;
+    // Encrypted emails are unique per write, so uniqueness moves to the blind index
+    let unique_field = if cipher.is_some() {
+        "email_hash"
+    } else {
+        "email"
+    };
+    prepare_table(db, "user", unique_field).await?;
+
+    for user in users {
+        let content = match cipher {
+            Some(cipher) => cipher.seal_user(user.for_creation())?,
+            None => user.for_creation(),
+        };
+
+        // Keep the original record ids so existing references stay valid
+        let created: Option<User> = db
+            .create(("user", user.id.id.to_raw()))
+            .content(content)
+            .await
+            .with_context(|| format!("Failed to import user {}", user.id))?;
+        if created.is_none() {
+            bail!("Failed to import user {}", user.id);
+        }
+    }
+
+    verify_count(db, "user", users.len()).await
+}
+
+async fn import_products(
+    db: &Surreal<Client>,
+    target: &DatabaseConfig,
+    products: &[Product],
+) -> anyhow::Result<()> {
+    db.use_ns(&target.namespace)
+        .use_db(&target.database)
+        .await?;
+    prepare_table(db, "product", "name").await?;
+
+    for product in products {
+        let created: Option<Product> = db
+            .create(("product", product.id.id.to_raw()))
+            .content(product.for_creation())
+            .await
+            .with_context(|| format!("Failed to import product {}", product.id))?;
+        if created.is_none() {
+            bail!("Failed to import product {}", product.id);
+        }
+    }
+
+    verify_count(db, "product", products.len()).await
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Initialize tracing
+    init_tracing();
+
+    let config = MigrationConfig::from_env();
+    // Import into the same namespaces and databases the services are configured with
+    let user_db = DatabaseConfig::user()?;
+    let product_db = DatabaseConfig::product()?;
+    let cipher = PiiCipher::from_env()?;
+
+    info!("Starting in-memory to persistent migration...");
+    info!("  Source users: {}", config.user_service_url);
+    info!("  Source products: {}", config.product_service_url);
+    info!("  Target SurrealDB: {}", config.target_address);
+    match &cipher {
+        Some(cipher) => info!("  PII encryption key: {}", cipher.active_key_id()),
+        None => info!("  PII encryption: disabled"),
+    }
+
+    let mut headers = HeaderMap::new();
+    if let Some(token) = &config.bearer_token {
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token))
+                .context("MIGRATE_BEARER_TOKEN is not a valid header value")?,
+        );
+    }
+    let user_client = HttpClientBuilder::default()
+        .set_headers(headers.clone())
+        .build(&config.user_service_url)?;
+    let product_client = HttpClientBuilder::default()
+        .set_headers(headers)
+        .build(&config.product_service_url)?;
+
+    let users = export_users(&user_client).await?;
+    info!("📤 Exported {} users", users.len());
+    ensure_unique(users.iter().map(|u| u.email.as_str()), "user", "email")?;
+
+    let products = export_products(&product_client).await?;
+    info!("📤 Exported {} products", products.len());
+    ensure_unique(products.iter().map(|p| p.name.as_str()), "product", "name")?;
+
+    let db = Surreal::new::<Ws>(config.target_address.as_str())
+        .await
+        .context("Failed to connect to target SurrealDB")?;
+    db.signin(Root {
+        username: &config.target_username,
+        password: &config.target_password,
+    })
+    .await
+    .context("Failed to sign in to target SurrealDB")?;
+
+    import_users(&db, &user_db, &users, cipher.as_ref()).await?;
+    import_products(&db, &product_db, &products).await?;
+
+    info!(
+        "🎉 Migration complete: {} users, {} products",
+        users.len(),
+        products.len()
+    );
+    Ok(())
+}
@@ -1,52 +1,375 @@
 use jpc_rust::{
+    config::database::DatabaseConfig,
+    crypto::{page_cursor::CursorSigner, request_signing::RequestSigner},
     errors::product_error::ProductServiceError,
-    models::product_model::{
-        CreateProductRequest, CreateProductResponse, GetProductRequest, GetProductsByCategoryRequest,
-        ListProductsResponse, Product, UpdateProductStockRequest,
+    gateway::self_test::SelfTestReport,
+    models::{
+        admin_model::{
+            DatabaseIsolationReport, LogSamplingStatus, ReadOnlyStatus, RetentionReport,
+            SetLogSamplingRequest, SetReadOnlyRequest, StorageReport,
+        },
+        change_model::SubscribeChangesRequest,
+        feature_flag_model::{
+            EvaluateFeatureFlagRequest, FeatureFlag, FlagEvaluation, ListFeatureFlagsResponse,
+            SetFeatureFlagRequest,
+        },
+        coupon_model::{
+            CouponCheckout, CreateCouponRequest, CreateCouponResponse, RedeemCouponRequest,
+            RedeemCouponResponse, ValidateCouponResponse,
+        },
+        attribute_model::{
+            AttributeDefinition, DefineAttributeRequest, ListAttributesResponse,
+            RemoveAttributeRequest, SearchProductsByAttributesRequest,
+            SearchProductsByAttributesResponse, SetAttributeRequest,
+        },
+        event_model::LogEventRequest,
+        job_model::{Job, JobIdRequest, StartJobRequest, SubscribeJobEventsRequest},
+        inventory_model::{
+            CreateLocationRequest, CreateLocationResponse, ForecastStockRequest,
+            ListLocationsResponse, ProductDetails, ReconcileStockRequest, StockForecast,
+            StockReconciliationReport, TransferStockRequest, TransferStockResponse,
+            UpdateStockBulkRequest, UpdateStockBulkResponse,
+        },
+        product_model::{
+            CategoryRollupsResponse, CreateProductRequest, CreateProductResponse, ExportProductsRequest,
+            ExportProductsResponse, FindSimilarProductsRequest, FindSimilarProductsResponse, GenerateFeedRequest, GetPriceHistoryRequest, GetProductRequest,
+            GetProductsByCategoryRequest, ImportProductsCsvRequest, ImportProductsCsvResponse,
+            ListProductsRequest, ListProductsResponse, PriceHistoryResponse, Product, ProductFeed,
+            ProductStats,
+            SchedulePriceChangeRequest, SchedulePriceChangeResponse, SetProductSkuRequest, SetProductVisibilityRequest, SetTranslationRequest,
+            UpdateProductStockRequest,
+        },
+        recommendation_model::{
+            CountOrdersByUserRequest, GetRecommendedProductsRequest, ListOrdersByUserRequest, ListOrdersResponse,
+            RecommendedProductsResponse, RecordOrderRequest, RecordOrderResponse, RecordedOrder, ShipOrderRequest, UserOrderCount,
+        },
+        return_model::{CreateReturnRequest, ProductReturn, ReturnIdRequest},
+    },
+    middleware::{
+        api_version::{ApiVersion, ApiVersionHeaderLayer, ApiVersionLayer},
+        authorization::{AuthorizationLayer, AuthorizationPolicy, BearerToken, BearerTokenLayer, MethodPolicy, PolicyError, ADMIN_ROLE, SERVICE_ROLE},
+        deadline::{DeadlineHeaderLayer, DeadlineLayer},
+        load_shedding::LoadSheddingLayer,
+        notifications::NotificationLayer,
+        org_context::{CallerOrg, OrgContextLayer},
+        panic_recovery::{install_panic_hook, PanicRecoveryLayer, PanicStats, PanicStatsSnapshot},
+        payload_limits::{PayloadLimitHeaderLayer, PayloadLimitLayer, PayloadLimits, PAYLOAD_TOO_LARGE_CODE},
+        request_id::{RequestIdHeaderLayer, RequestIdLayer},
+        request_signing::RequestSignatureLayer,
+        server_timing::ServerTimingLayer,
+        timestamp_format::{TimestampFormat, TimestampFormatHeaderLayer, TimestampFormatLayer},
+        trace_sampling::{TraceSamplingHeaderLayer, TraceSamplingLayer},
+    },
+    repositories::connection::DATABASE_UNAVAILABLE_CODE,
+    services::{
+        change_feed::forward_changes,
+        client_events::log_client_event,
+        database_isolation::{check_configured, PRODUCT_SERVICE},
+        method_namespaces::{
+            register_method_list, register_namespaced_methods, COMMON_METHODS, PRODUCT_METHODS,
+        },
+        jobs::{forward_job_events, spawn_job_workers, JobWorkerConfig},
+        price_scheduler::{scheduler_interval_from_env, spawn_price_scheduler},
+        product_feed::{
+            feed_interval_from_env, feed_output_dir_from_env, spawn_feed_scheduler,
+            ProductFeedConfig,
+        },
+        product_service::{ProductService, BULK_ARRAY_LIMITS},
+        read_only::ReadOnlyMode,
+        recommendations::{spawn_recommendation_builder, CoOccurrenceConfig},
+        retention::{spawn_retention_job, RetentionPolicy, PRODUCT_RETENTION_RULES},
+        storage_caps::{spawn_storage_monitor, StorageMonitor},
+        stock_reconciliation::{reconcile_interval_from_env, spawn_stock_reconciler},
+        startup::{
+            check_config_requested, exit_code_for, init_with_backoff, print_config_report, BindFailed, StartupMode,
+            SERVICE_STARTING_CODE,
+        },
+    },
+    telemetry::{
+        log_policy::{init_tracing, log_policy, sample_success},
+        query_metrics::{QueryStats, QueryStatsSnapshot},
     },
-    services::product_service::ProductService,
 };
 use jsonrpsee::{
-    core::{async_trait, RpcResult},
+    core::{async_trait, RpcResult, SubscriptionResult},
     proc_macros::rpc,
-    server::ServerBuilder,
+    server::{BatchRequestConfig, RpcServiceBuilder, ServerBuilder},
     types::{ErrorCode, ErrorObject},
+    Extensions, PendingSubscriptionSink,
 };
+use std::process::ExitCode;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{error, info, Level};
-use tracing_subscriber;
+use tokio::sync::{RwLock, RwLockReadGuard};
+use tracing::{debug, error, info, warn};
 
 #[rpc(server)]
 pub trait ProductRpc {
     #[method(name = "create_product")]
     async fn create_product(&self, request: CreateProductRequest) -> RpcResult<CreateProductResponse>;
 
-    #[method(name = "get_product")]
-    async fn get_product(&self, request: GetProductRequest) -> RpcResult<Product>;
+    #[method(name = "get_product", with_extensions)]
+    async fn get_product(&self, request: GetProductRequest) -> RpcResult<ProductDetails>;
+
+    #[method(name = "find_similar_products", with_extensions)]
+    async fn find_similar_products(&self, request: FindSimilarProductsRequest) -> RpcResult<FindSimilarProductsResponse>;
+
+    /// Restricts a product to the given organizations; an empty list makes it public
+    #[method(name = "set_product_visibility")]
+    async fn set_product_visibility(&self, request: SetProductVisibilityRequest) -> RpcResult<Product>;
+
+    #[method(name = "set_product_sku")]
+    async fn set_product_sku(&self, request: SetProductSkuRequest) -> RpcResult<Product>;
+
+    #[method(name = "set_translation")]
+    async fn set_translation(&self, request: SetTranslationRequest) -> RpcResult<Product>;
+
+    #[method(name = "define_attribute")]
+    async fn define_attribute(&self, request: DefineAttributeRequest) -> RpcResult<AttributeDefinition>;
 
-    #[method(name = "list_products")]
-    async fn list_products(&self) -> RpcResult<ListProductsResponse>;
+    #[method(name = "list_attributes")]
+    async fn list_attributes(&self) -> RpcResult<ListAttributesResponse>;
 
-    #[method(name = "get_products_by_category")]
+    #[method(name = "set_attribute")]
+    async fn set_attribute(&self, request: SetAttributeRequest) -> RpcResult<Product>;
+
+    #[method(name = "remove_attribute")]
+    async fn remove_attribute(&self, request: RemoveAttributeRequest) -> RpcResult<Product>;
+
+    #[method(name = "search_products_by_attributes", with_extensions)]
+    async fn search_products_by_attributes(&self, request: SearchProductsByAttributesRequest) -> RpcResult<SearchProductsByAttributesResponse>;
+
+    #[method(name = "list_products", with_extensions)]
+    async fn list_products(&self, request: Option<ListProductsRequest>) -> RpcResult<ListProductsResponse>;
+
+    #[method(name = "get_product_stats")]
+    async fn get_product_stats(&self) -> RpcResult<ProductStats>;
+
+    #[method(name = "get_category_rollups")]
+    async fn get_category_rollups(&self) -> RpcResult<CategoryRollupsResponse>;
+
+    #[method(name = "get_products_by_category", with_extensions)]
     async fn get_products_by_category(&self, request: GetProductsByCategoryRequest) -> RpcResult<ListProductsResponse>;
 
     #[method(name = "update_product_stock")]
     async fn update_product_stock(&self, request: UpdateProductStockRequest) -> RpcResult<Product>;
 
+    /// Sets many products' stock at once with a result per item, for
+    /// warehouse syncs
+    #[method(name = "update_stock_bulk")]
+    async fn update_stock_bulk(&self, request: UpdateStockBulkRequest) -> RpcResult<UpdateStockBulkResponse>;
+
+    #[method(name = "transfer_stock")]
+    async fn transfer_stock(&self, request: TransferStockRequest) -> RpcResult<TransferStockResponse>;
+
+    #[method(name = "create_location")]
+    async fn create_location(&self, request: CreateLocationRequest) -> RpcResult<CreateLocationResponse>;
+
+    #[method(name = "list_locations")]
+    async fn list_locations(&self) -> RpcResult<ListLocationsResponse>;
+
+    #[method(name = "reconcile_stock")]
+    async fn reconcile_stock(&self, request: ReconcileStockRequest) -> RpcResult<StockReconciliationReport>;
+
+    /// Average daily consumption and predicted stock-out from the stock
+    /// movement ledger, for replenishment planning
+    #[method(name = "forecast_stock")]
+    async fn forecast_stock(&self, request: ForecastStockRequest) -> RpcResult<StockForecast>;
+
+    #[method(name = "export_products")]
+    async fn export_products(&self, request: ExportProductsRequest) -> RpcResult<ExportProductsResponse>;
+
+    #[method(name = "import_products_csv")]
+    async fn import_products_csv(&self, request: ImportProductsCsvRequest) -> RpcResult<ImportProductsCsvResponse>;
+
+    /// Queues a CSV import, full export or reindex and returns the job
+    /// right away; a background worker runs it
+    #[method(name = "start_job")]
+    async fn start_job(&self, request: StartJobRequest) -> RpcResult<Job>;
+
+    #[method(name = "get_job_status")]
+    async fn get_job_status(&self, request: JobIdRequest) -> RpcResult<Job>;
+
+    #[method(name = "schedule_price_change")]
+    async fn schedule_price_change(&self, request: SchedulePriceChangeRequest) -> RpcResult<SchedulePriceChangeResponse>;
+
+    #[method(name = "get_price_history")]
+    async fn get_price_history(&self, request: GetPriceHistoryRequest) -> RpcResult<PriceHistoryResponse>;
+
+    #[method(name = "generate_feed")]
+    async fn generate_feed(&self, request: GenerateFeedRequest) -> RpcResult<ProductFeed>;
+
+    #[method(name = "create_coupon")]
+    async fn create_coupon(&self, request: CreateCouponRequest) -> RpcResult<CreateCouponResponse>;
+
+    #[method(name = "validate_coupon")]
+    async fn validate_coupon(&self, request: CouponCheckout) -> RpcResult<ValidateCouponResponse>;
+
+    #[method(name = "redeem_coupon")]
+    async fn redeem_coupon(&self, request: RedeemCouponRequest) -> RpcResult<RedeemCouponResponse>;
+
+    /// Records the products of a completed order for recommendations
+    #[method(name = "record_order")]
+    async fn record_order(&self, request: RecordOrderRequest) -> RpcResult<RecordOrderResponse>;
+
+    /// A user's orders for account pages; callers may only list their own
+    /// unless they are admins
+    #[method(name = "list_orders_by_user", with_extensions)]
+    async fn list_orders_by_user(&self, request: ListOrdersByUserRequest) -> RpcResult<ListOrdersResponse>;
+
+    /// Ships a recorded order to a validated address
+    #[method(name = "ship_order")]
+    async fn ship_order(&self, request: ShipOrderRequest) -> RpcResult<RecordedOrder>;
+
+    /// How many orders a user has, for the user service's delete guard
+    #[method(name = "count_orders_by_user")]
+    async fn count_orders_by_user(&self, request: CountOrdersByUserRequest) -> RpcResult<UserOrderCount>;
+
+    /// Products frequently bought together with the given one
+    #[method(name = "get_recommended_products", with_extensions)]
+    async fn get_recommended_products(&self, request: GetRecommendedProductsRequest) -> RpcResult<RecommendedProductsResponse>;
+
+    /// Opens a return against a recorded order
+    #[method(name = "create_return")]
+    async fn create_return(&self, request: CreateReturnRequest) -> RpcResult<ProductReturn>;
+
+    #[method(name = "approve_return")]
+    async fn approve_return(&self, request: ReturnIdRequest) -> RpcResult<ProductReturn>;
+
+    /// Restocks the returned items and records the refund on the order
+    #[method(name = "complete_return")]
+    async fn complete_return(&self, request: ReturnIdRequest) -> RpcResult<ProductReturn>;
+
+    #[method(name = "get_return")]
+    async fn get_return(&self, request: ReturnIdRequest) -> RpcResult<ProductReturn>;
+
+    /// Fire-and-forget: usually sent as a notification, without an id
+    #[method(name = "log_event")]
+    async fn log_event(&self, request: LogEventRequest) -> RpcResult<()>;
+
+    /// Pushes a `change` notification for every record created, updated or
+    /// deleted in the service's tables. Needs `LIVE_QUERIES=true`.
+    #[subscription(name = "subscribe_changes" => "change", unsubscribe = "unsubscribe_changes", item = ChangeEvent)]
+    async fn subscribe_changes(&self, request: SubscribeChangesRequest) -> SubscriptionResult;
+
+    /// Pushes a `job_event` notification whenever a job, or the given one,
+    /// is queued, starts, progresses or finishes
+    #[subscription(name = "subscribe_job_events" => "job_event", unsubscribe = "unsubscribe_job_events", item = JobEvent)]
+    async fn subscribe_job_events(&self, request: SubscribeJobEventsRequest) -> SubscriptionResult;
+
+    #[method(name = "set_feature_flag")]
+    async fn set_feature_flag(&self, request: SetFeatureFlagRequest) -> RpcResult<FeatureFlag>;
+
+    #[method(name = "list_feature_flags")]
+    async fn list_feature_flags(&self) -> RpcResult<ListFeatureFlagsResponse>;
+
+    #[method(name = "evaluate_feature_flag")]
+    async fn evaluate_feature_flag(&self, request: EvaluateFeatureFlagRequest) -> RpcResult<FlagEvaluation>;
+
+    #[method(name = "set_read_only")]
+    async fn set_read_only(&self, request: SetReadOnlyRequest) -> RpcResult<ReadOnlyStatus>;
+
+    /// Changes the percentage of successful requests logged; failures are
+    /// always logged
+    #[method(name = "set_log_sampling")]
+    async fn set_log_sampling(
+        &self,
+        request: SetLogSamplingRequest,
+    ) -> RpcResult<LogSamplingStatus>;
+
+    #[method(name = "query_stats")]
+    async fn query_stats(&self) -> RpcResult<QueryStatsSnapshot>;
+
+    #[method(name = "panic_stats")]
+    async fn panic_stats(&self) -> RpcResult<PanicStatsSnapshot>;
+
+    #[method(name = "retention_stats")]
+    async fn retention_stats(&self) -> RpcResult<RetentionReport>;
+
+    /// Record counts and approximate size per table against the storage caps
+    #[method(name = "storage_stats")]
+    async fn storage_stats(&self) -> RpcResult<StorageReport>;
+
+    /// The namespace, database and tables actually in use, checked against
+    /// the config
+    #[method(name = "database_isolation")]
+    async fn database_isolation(&self) -> RpcResult<DatabaseIsolationReport>;
+
     #[method(name = "health")]
     async fn health(&self) -> RpcResult<String>;
 }
 
 pub struct ProductRpcImpl {
-    service: Arc<RwLock<ProductService>>,
+    /// `None` until the repository has been initialized
+    service: Arc<RwLock<Option<ProductService>>>,
+    read_only: Arc<ReadOnlyMode>,
+    feed_config: Arc<ProductFeedConfig>,
+    cursors: Arc<CursorSigner>,
+    retention: Arc<RetentionPolicy>,
+    storage: Arc<StorageMonitor>,
+    authorization: Arc<AuthorizationPolicy>,
 }
 
 impl ProductRpcImpl {
-    pub async fn new() -> Result<Self, ProductServiceError> {
-        let service = ProductService::new().await?;
+    pub async fn new(db_config: &DatabaseConfig, feed_config: Arc<ProductFeedConfig>, cursors: Arc<CursorSigner>, retention: Arc<RetentionPolicy>, storage: Arc<StorageMonitor>, authorization: Arc<AuthorizationPolicy>) -> Result<Self, ProductServiceError> {
+        let read_only = Arc::new(ReadOnlyMode::from_env());
+        let service = ProductService::new(Arc::clone(&read_only), Arc::clone(&storage), Arc::clone(&feed_config), Arc::clone(&cursors), db_config).await?;
         Ok(Self {
-            service: Arc::new(RwLock::new(service)),
+            service: Arc::new(RwLock::new(Some(service))),
+            read_only,
+            feed_config,
+            cursors,
+            retention,
+            storage,
+            authorization,
+        })
+    }
+
+    /// Creates the RPC handler without a service; calls fail with
+    /// "Service is starting" until `initialize_in_background` completes.
+    pub fn starting(feed_config: Arc<ProductFeedConfig>, cursors: Arc<CursorSigner>, retention: Arc<RetentionPolicy>, storage: Arc<StorageMonitor>, authorization: Arc<AuthorizationPolicy>) -> Self {
+        Self {
+            service: Arc::new(RwLock::new(None)),
+            read_only: Arc::new(ReadOnlyMode::from_env()),
+            feed_config,
+            cursors,
+            retention,
+            storage,
+            authorization,
+        }
+    }
+
+    pub fn initialize_in_background(&self, db_config: DatabaseConfig) {
+        let slot = Arc::clone(&self.service);
+        let read_only = Arc::clone(&self.read_only);
+        let storage = Arc::clone(&self.storage);
+        let feed_config = Arc::clone(&self.feed_config);
+        let cursors = Arc::clone(&self.cursors);
+        tokio::spawn(async move {
+            let service = init_with_backoff("ProductService", || {
+                ProductService::new(Arc::clone(&read_only), Arc::clone(&storage), Arc::clone(&feed_config), Arc::clone(&cursors), &db_config)
+            })
+            .await;
+            match service.database_isolation().await {
+                Ok(report) if !report.isolated => {
+                    for problem in &report.problems {
+                        warn!("⚠️ Database isolation: {}", problem);
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => warn!("⚠️ Failed to check database isolation: {}", err),
+            }
+            *slot.write().await = Some(service);
+            info!("🟢 Product Service is ready");
+        });
+    }
+
+    async fn ready_service(&self) -> RpcResult<RwLockReadGuard<'_, ProductService>> {
+        RwLockReadGuard::try_map(self.service.read().await, Option::as_ref).map_err(|_| {
+            ErrorObject::owned(
+                SERVICE_STARTING_CODE,
+                "Service is starting",
+                Some("starting"),
+            )
         })
     }
 }
@@ -54,12 +377,14 @@ impl ProductRpcImpl {
 #[async_trait]
 impl ProductRpcServer for ProductRpcImpl {
     async fn create_product(&self, request: CreateProductRequest) -> RpcResult<CreateProductResponse> {
-        info!("Creating product: {:?}", request);
+        debug!("Creating product: {:?}", request);
 
-        let service = self.service.read().await;
+        let service = self.ready_service().await?;
         match service.create_product(request).await {
             Ok(response) => {
-                info!("Product created successfully: {}", response.id);
+                if !response.dry_run && sample_success() {
+                    info!("Product created successfully: {}", response.id);
+                }
                 Ok(response)
             }
             Err(err) => {
@@ -67,19 +392,45 @@ impl ProductRpcServer for ProductRpcImpl {
                 Err(ErrorObject::owned(
                     ErrorCode::InternalError.code(),
                     "Failed to create product",
-                    Some(err.to_string()),
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn find_similar_products(&self, ext: &Extensions, request: FindSimilarProductsRequest) -> RpcResult<FindSimilarProductsResponse> {
+        debug!("Finding products similar to: {:?}", request);
+
+        let org = ext.get::<CallerOrg>().map(|CallerOrg(org)| org.as_str());
+        let service = self.ready_service().await?;
+        match service.find_similar_products(request, org).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Similar products found: {}", response.products.len());
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to find similar products: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to find similar products",
+                    Some(err.error_data()),
                 ))
             }
         }
     }
 
-    async fn get_product(&self, request: GetProductRequest) -> RpcResult<Product> {
-        info!("Getting product: {:?}", request);
+    async fn get_product(&self, ext: &Extensions, request: GetProductRequest) -> RpcResult<ProductDetails> {
+        debug!("Getting product: {:?}", request);
 
-        let service = self.service.read().await;
-        match service.get_product(request).await {
+        let org = ext.get::<CallerOrg>().map(|CallerOrg(org)| org.as_str());
+        let service = self.ready_service().await?;
+        match service.get_product(request, org).await {
             Ok(product) => {
-                info!("Product retrieved successfully: {}", product.id);
+                if sample_success() {
+                    info!("Product retrieved successfully: {}", product.product.id);
+                }
                 Ok(product)
             }
             Err(err) => {
@@ -87,39 +438,274 @@ impl ProductRpcServer for ProductRpcImpl {
                 Err(ErrorObject::owned(
                     ErrorCode::InternalError.code(),
                     "Failed to get product",
-                    Some(err.to_string()),
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn set_product_visibility(&self, request: SetProductVisibilityRequest) -> RpcResult<Product> {
+        debug!("Setting product visibility: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.set_product_visibility(request).await {
+            Ok(product) => {
+                if sample_success() {
+                    info!("Product visibility set: {} ({} organizations)", product.id, product.visible_to.len());
+                }
+                Ok(product)
+            }
+            Err(err) => {
+                error!("Failed to set product visibility: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to set product visibility",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn set_product_sku(&self, request: SetProductSkuRequest) -> RpcResult<Product> {
+        debug!("Setting product SKU: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.set_product_sku(request).await {
+            Ok(product) => {
+                if sample_success() {
+                    info!("Product SKU set: {} ({})", product.id, product.sku.as_deref().unwrap_or("none"));
+                }
+                Ok(product)
+            }
+            Err(err) => {
+                error!("Failed to set product SKU: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to set product SKU",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn set_translation(&self, request: SetTranslationRequest) -> RpcResult<Product> {
+        debug!("Setting product translation: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.set_translation(request).await {
+            Ok(product) => {
+                if sample_success() {
+                    info!("Product translation set: {}", product.id);
+                }
+                Ok(product)
+            }
+            Err(err) => {
+                error!("Failed to set product translation: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to set product translation",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn define_attribute(&self, request: DefineAttributeRequest) -> RpcResult<AttributeDefinition> {
+        debug!("Defining attribute: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.define_attribute(request).await {
+            Ok(definition) => {
+                if sample_success() {
+                    info!("Attribute defined: {}", definition.key);
+                }
+                Ok(definition)
+            }
+            Err(err) => {
+                error!("Failed to define attribute: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to define attribute",
+                    Some(err.error_data()),
                 ))
             }
         }
     }
 
-    async fn list_products(&self) -> RpcResult<ListProductsResponse> {
-        info!("Listing products");
+    async fn list_attributes(&self) -> RpcResult<ListAttributesResponse> {
+        debug!("Listing attributes");
 
-        let service = self.service.read().await;
-        match service.list_products().await {
+        let service = self.ready_service().await?;
+        match service.list_attributes().await {
             Ok(response) => {
-                info!("Products listed successfully: {} products", response.total);
+                if sample_success() {
+                    info!("Attributes listed: {}", response.attributes.len());
+                }
                 Ok(response)
             }
             Err(err) => {
-                error!("Failed to list products: {}", err);
+                error!("Failed to list attributes: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to list attributes",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn set_attribute(&self, request: SetAttributeRequest) -> RpcResult<Product> {
+        debug!("Setting product attribute: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.set_attribute(request).await {
+            Ok(product) => {
+                if sample_success() {
+                    info!("Product attribute set: {} ({} attributes)", product.id, product.attributes.len());
+                }
+                Ok(product)
+            }
+            Err(err) => {
+                error!("Failed to set product attribute: {}", err);
+                let code = match err {
+                    ProductServiceError::PayloadTooLarge { .. } => PAYLOAD_TOO_LARGE_CODE,
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to set product attribute",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn remove_attribute(&self, request: RemoveAttributeRequest) -> RpcResult<Product> {
+        debug!("Removing product attribute: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.remove_attribute(request).await {
+            Ok(product) => {
+                if sample_success() {
+                    info!("Product attribute removed: {} ({} attributes)", product.id, product.attributes.len());
+                }
+                Ok(product)
+            }
+            Err(err) => {
+                error!("Failed to remove product attribute: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to remove product attribute",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn search_products_by_attributes(&self, ext: &Extensions, request: SearchProductsByAttributesRequest) -> RpcResult<SearchProductsByAttributesResponse> {
+        debug!("Searching products by attributes: {:?}", request);
+
+        let org = ext.get::<CallerOrg>().map(|CallerOrg(org)| org.as_str());
+        let service = self.ready_service().await?;
+        match service.search_products_by_attributes(request, org).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Products found by attributes: {}", response.total);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to search products by attributes: {}", err);
                 Err(ErrorObject::owned(
                     ErrorCode::InternalError.code(),
+                    "Failed to search products by attributes",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn list_products(&self, ext: &Extensions, request: Option<ListProductsRequest>) -> RpcResult<ListProductsResponse> {
+        debug!("Listing products: {:?}", request);
+
+        let org = ext.get::<CallerOrg>().map(|CallerOrg(org)| org.as_str());
+        let service = self.ready_service().await?;
+        match service.list_products(request.unwrap_or_default(), org).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Products listed successfully: {} products", response.total);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to list products: {}", err);
+                let code = match err {
+                    ProductServiceError::InvalidCursor(_) | ProductServiceError::Validation { .. } => ErrorCode::InvalidParams.code(),
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
                     "Failed to list products",
-                    Some(err.to_string()),
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn get_product_stats(&self) -> RpcResult<ProductStats> {
+        debug!("Getting product stats");
+
+        let service = self.ready_service().await?;
+        match service.get_product_stats().await {
+            Ok(stats) => {
+                if sample_success() {
+                    info!("Product stats computed: {} products in {} categories", stats.total_products, stats.categories.len());
+                }
+                Ok(stats)
+            }
+            Err(err) => {
+                error!("Failed to get product stats: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to get product stats",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn get_category_rollups(&self) -> RpcResult<CategoryRollupsResponse> {
+        debug!("Getting category rollups");
+
+        let service = self.ready_service().await?;
+        match service.get_category_rollups().await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Category rollups retrieved: {} categories", response.categories.len());
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to get category rollups: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to get category rollups",
+                    Some(err.error_data()),
                 ))
             }
         }
     }
 
-    async fn get_products_by_category(&self, request: GetProductsByCategoryRequest) -> RpcResult<ListProductsResponse> {
-        info!("Getting products by category: {:?}", request);
+    async fn get_products_by_category(&self, ext: &Extensions, request: GetProductsByCategoryRequest) -> RpcResult<ListProductsResponse> {
+        debug!("Getting products by category: {:?}", request);
 
-        let service = self.service.read().await;
-        match service.get_products_by_category(request).await {
+        let org = ext.get::<CallerOrg>().map(|CallerOrg(org)| org.as_str());
+        let service = self.ready_service().await?;
+        match service.get_products_by_category(request, org).await {
             Ok(response) => {
-                info!("Products by category retrieved successfully: {} products", response.total);
+                if sample_success() {
+                    info!("Products by category retrieved successfully: {} products", response.total);
+                }
                 Ok(response)
             }
             Err(err) => {
@@ -127,19 +713,21 @@ impl ProductRpcServer for ProductRpcImpl {
                 Err(ErrorObject::owned(
                     ErrorCode::InternalError.code(),
                     "Failed to get products by category",
-                    Some(err.to_string()),
+                    Some(err.error_data()),
                 ))
             }
         }
     }
 
     async fn update_product_stock(&self, request: UpdateProductStockRequest) -> RpcResult<Product> {
-        info!("Updating product stock: {:?}", request);
+        debug!("Updating product stock: {:?}", request);
 
-        let service = self.service.read().await;
+        let service = self.ready_service().await?;
         match service.update_product_stock(request).await {
             Ok(product) => {
-                info!("Product stock updated successfully: {}", product.id);
+                if sample_success() {
+                    info!("Product stock updated successfully: {}", product.id);
+                }
                 Ok(product)
             }
             Err(err) => {
@@ -147,55 +735,1169 @@ impl ProductRpcServer for ProductRpcImpl {
                 Err(ErrorObject::owned(
                     ErrorCode::InternalError.code(),
                     "Failed to update product stock",
-                    Some(err.to_string()),
+                    Some(err.error_data()),
                 ))
             }
         }
     }
 
-    async fn health(&self) -> RpcResult<String> {
-        Ok("Product Service is healthy!".to_string())
+    async fn update_stock_bulk(&self, request: UpdateStockBulkRequest) -> RpcResult<UpdateStockBulkResponse> {
+        debug!("Updating stock in bulk ({} items, atomic: {})", request.items.len(), request.atomic);
+
+        let service = self.ready_service().await?;
+        match service.update_stock_bulk(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Stock updated in bulk: {} of {} items applied", response.applied, response.total);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to update stock in bulk: {}", err);
+                let code = match err {
+                    ProductServiceError::PayloadTooLarge { .. } => PAYLOAD_TOO_LARGE_CODE,
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to update stock in bulk",
+                    Some(err.error_data()),
+                ))
+            }
+        }
     }
-}
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+    async fn transfer_stock(&self, request: TransferStockRequest) -> RpcResult<TransferStockResponse> {
+        debug!("Transferring stock: {:?}", request);
 
-    info!("Starting Product Service...");
+        let service = self.ready_service().await?;
+        match service.transfer_stock(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Stock transferred for product {}: {} now has {}, {} has {}",
+                        response.product_id,
+                        response.from.location,
+                        response.from.quantity,
+                        response.to.location,
+                        response.to.quantity
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to transfer stock: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to transfer stock",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
 
-    // Create the RPC service
-    let product_rpc = ProductRpcImpl::new().await?;
+    async fn create_location(&self, request: CreateLocationRequest) -> RpcResult<CreateLocationResponse> {
+        debug!("Creating location: {:?}", request);
 
-    // Build the server on a different port than user service
-    let server = ServerBuilder::default().build("127.0.0.1:8081").await?;
+        let service = self.ready_service().await?;
+        match service.create_location(request).await {
+            Ok(response) => {
+                info!("Location created successfully: {}", response.id);
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to create location: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to create location",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
 
-    // Register the methods
-    let handle = server.start(product_rpc.into_rpc());
+    async fn list_locations(&self) -> RpcResult<ListLocationsResponse> {
+        debug!("Listing locations");
 
-    info!("🚀 Product Service started on http://127.0.0.1:8081");
-    info!("Available methods:");
-    info!("  - create_product(name: String, description: String, price: f64, category: String, stock_quantity: i32)");
-    info!("  - get_product(id: String)");
-    info!("  - list_products()");
-    info!("  - get_products_by_category(category: String)");
-    info!("  - update_product_stock(id: String, quantity: i32)");
-    info!("  - health()");
+        let service = self.ready_service().await?;
+        match service.list_locations().await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Locations listed successfully: {} locations", response.total);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to list locations: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to list locations",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
 
-    // Set up graceful shutdown handling
-    let handle_clone = handle.clone();
-    tokio::spawn(async move {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("Failed to listen for ctrl+c");
-        info!("Received shutdown signal, gracefully shutting down...");
-        handle_clone.stop().unwrap();
-    });
+    async fn reconcile_stock(&self, request: ReconcileStockRequest) -> RpcResult<StockReconciliationReport> {
+        debug!("Reconciling stock: {:?}", request);
 
-    // Wait for the server to finish
-    handle.stopped().await;
-    info!("Product Service shut down gracefully");
+        let service = self.ready_service().await?;
+        match service.reconcile_stock(request).await {
+            Ok(report) => {
+                if sample_success() {
+                    info!("Stock reconciled: {} discrepancies", report.discrepancies.len());
+                }
+                Ok(report)
+            }
+            Err(err) => {
+                error!("Failed to reconcile stock: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to reconcile stock",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn forecast_stock(&self, request: ForecastStockRequest) -> RpcResult<StockForecast> {
+        debug!("Forecasting stock: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.forecast_stock(request).await {
+            Ok(forecast) => {
+                if sample_success() {
+                    info!(
+                        "Stock forecast for {}: {:.2} units/day, stock-out {:?}",
+                        forecast.product_id, forecast.average_daily_consumption, forecast.predicted_stock_out
+                    );
+                }
+                Ok(forecast)
+            }
+            Err(err) => {
+                error!("Failed to forecast stock: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to forecast stock",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn export_products(&self, request: ExportProductsRequest) -> RpcResult<ExportProductsResponse> {
+        debug!("Exporting products: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.export_products(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Products exported successfully: {} of {}", response.products.len(), response.total);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to export products: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to export products",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn import_products_csv(&self, request: ImportProductsCsvRequest) -> RpcResult<ImportProductsCsvResponse> {
+        debug!("Importing products from CSV ({} bytes)", request.csv.len());
+
+        let service = self.ready_service().await?;
+        match service.import_products_csv(request).await {
+            Ok(response) => {
+                info!("Products imported: {} succeeded, {} failed", response.imported, response.failed);
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to import products: {}", err);
+                let code = match err {
+                    ProductServiceError::PayloadTooLarge { .. } => PAYLOAD_TOO_LARGE_CODE,
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to import products",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn start_job(&self, request: StartJobRequest) -> RpcResult<Job> {
+        debug!("Starting {} job", request.kind);
+
+        let service = self.ready_service().await?;
+        match service.start_job(request).await {
+            Ok(job) => {
+                info!("Job queued: {} {}", job.kind, job.id);
+                Ok(job)
+            }
+            Err(err) => {
+                error!("Failed to start job: {}", err);
+                let code = match err {
+                    ProductServiceError::PayloadTooLarge { .. } => PAYLOAD_TOO_LARGE_CODE,
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to start job",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn get_job_status(&self, request: JobIdRequest) -> RpcResult<Job> {
+        debug!("Getting job status: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.get_job_status(request).await {
+            Ok(job) => {
+                if sample_success() {
+                    info!("Job {} is {}", job.id, job.status);
+                }
+                Ok(job)
+            }
+            Err(err) => {
+                error!("Failed to get job status: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to get job status",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn schedule_price_change(&self, request: SchedulePriceChangeRequest) -> RpcResult<SchedulePriceChangeResponse> {
+        info!("Scheduling price change: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.schedule_price_change(request).await {
+            Ok(response) => {
+                info!("Price change scheduled: {}", response.id);
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to schedule price change: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to schedule price change",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn get_price_history(&self, request: GetPriceHistoryRequest) -> RpcResult<PriceHistoryResponse> {
+        debug!("Getting price history: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.get_price_history(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Price history retrieved for {}: {} changes, {} pending",
+                        response.product_id,
+                        response.changes.len(),
+                        response.pending.len()
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to get price history: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to get price history",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn generate_feed(&self, request: GenerateFeedRequest) -> RpcResult<ProductFeed> {
+        debug!("Generating product feed: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.generate_feed(request).await {
+            Ok(feed) => {
+                if sample_success() {
+                    info!("Product feed generated: {:?} with {} products", feed.format, feed.product_count);
+                }
+                Ok(feed)
+            }
+            Err(err) => {
+                error!("Failed to generate product feed: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to generate product feed",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn create_coupon(&self, request: CreateCouponRequest) -> RpcResult<CreateCouponResponse> {
+        debug!("Creating coupon: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.create_coupon(request).await {
+            Ok(response) => {
+                info!("Coupon created successfully: {}", response.code);
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to create coupon: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to create coupon",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn validate_coupon(&self, request: CouponCheckout) -> RpcResult<ValidateCouponResponse> {
+        debug!("Validating coupon: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.validate_coupon(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Coupon {} validated: valid = {}", response.code, response.valid);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to validate coupon: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to validate coupon",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn redeem_coupon(&self, request: RedeemCouponRequest) -> RpcResult<RedeemCouponResponse> {
+        debug!("Redeeming coupon: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.redeem_coupon(request).await {
+            Ok(response) => Ok(response),
+            Err(err) => {
+                error!("Failed to redeem coupon: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to redeem coupon",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn record_order(&self, request: RecordOrderRequest) -> RpcResult<RecordOrderResponse> {
+        debug!("Recording order: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.record_order(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Order recorded with {} products", response.product_count);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to record order: {}", err);
+                let code = match err {
+                    ProductServiceError::PayloadTooLarge { .. } => PAYLOAD_TOO_LARGE_CODE,
+                    ProductServiceError::Validation { .. } | ProductServiceError::OrderAlreadyRecorded { .. } => ErrorCode::InvalidParams.code(),
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to record order",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn ship_order(&self, request: ShipOrderRequest) -> RpcResult<RecordedOrder> {
+        debug!("Shipping order: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.ship_order(request).await {
+            Ok(order) => {
+                if sample_success() {
+                    info!("Order shipped: {}", order.id);
+                }
+                Ok(order)
+            }
+            Err(err) => {
+                error!("Failed to ship order: {}", err);
+                let code = match err {
+                    ProductServiceError::Validation { .. } | ProductServiceError::OrderNotFound { .. } | ProductServiceError::InvalidOrderTransition { .. } => ErrorCode::InvalidParams.code(),
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to ship order",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn list_orders_by_user(&self, ext: &Extensions, request: ListOrdersByUserRequest) -> RpcResult<ListOrdersResponse> {
+        debug!("Listing orders by user: {:?}", request);
+
+        let token = ext.get::<BearerToken>().map(|t| t.0.as_str());
+        if let Err(denial) = self.authorization.authorize_owner(token, request.user_id.trim()) {
+            warn!("🔒 Denied list_orders_by_user for {}: {:?}", request.user_id, denial);
+            return Err(denial.into_error_object("list_orders_by_user"));
+        }
+
+        let service = self.ready_service().await?;
+        match service.list_orders_by_user(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Orders listed successfully: {} orders", response.total);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to list orders: {}", err);
+                let code = match err {
+                    ProductServiceError::InvalidCursor(_) | ProductServiceError::Validation { .. } => ErrorCode::InvalidParams.code(),
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to list orders",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn count_orders_by_user(&self, request: CountOrdersByUserRequest) -> RpcResult<UserOrderCount> {
+        debug!("Counting orders by user: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.count_orders_by_user(request).await {
+            Ok(count) => {
+                if sample_success() {
+                    info!("Orders counted for user {}: {}", count.user_id, count.count);
+                }
+                Ok(count)
+            }
+            Err(err) => {
+                error!("Failed to count orders: {}", err);
+                let code = match err {
+                    ProductServiceError::Validation { .. } => ErrorCode::InvalidParams.code(),
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to count orders",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+    async fn get_recommended_products(&self, ext: &Extensions, request: GetRecommendedProductsRequest) -> RpcResult<RecommendedProductsResponse> {
+        debug!("Getting recommended products: {:?}", request);
+
+        let org = ext.get::<CallerOrg>().map(|CallerOrg(org)| org.as_str());
+        let service = self.ready_service().await?;
+        match service.get_recommended_products(request, org).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Recommended products found: {}", response.products.len());
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to get recommended products: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to get recommended products",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn create_return(&self, request: CreateReturnRequest) -> RpcResult<ProductReturn> {
+        debug!("Creating return: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.create_return(request).await {
+            Ok(product_return) => {
+                if sample_success() {
+                    info!("Return created with id: {}", product_return.id);
+                }
+                Ok(product_return)
+            }
+            Err(err) => {
+                error!("Failed to create return: {}", err);
+                let code = match err {
+                    ProductServiceError::PayloadTooLarge { .. } => PAYLOAD_TOO_LARGE_CODE,
+                    _ => ErrorCode::InternalError.code(),
+                };
+                Err(ErrorObject::owned(
+                    code,
+                    "Failed to create return",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn approve_return(&self, request: ReturnIdRequest) -> RpcResult<ProductReturn> {
+        debug!("Approving return: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.approve_return(request).await {
+            Ok(product_return) => {
+                if sample_success() {
+                    info!("Return approved: {}", product_return.id);
+                }
+                Ok(product_return)
+            }
+            Err(err) => {
+                error!("Failed to approve return: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to approve return",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn complete_return(&self, request: ReturnIdRequest) -> RpcResult<ProductReturn> {
+        debug!("Completing return: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.complete_return(request).await {
+            Ok(product_return) => {
+                if sample_success() {
+                    info!("Return completed: {}", product_return.id);
+                }
+                Ok(product_return)
+            }
+            Err(err) => {
+                error!("Failed to complete return: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to complete return",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn get_return(&self, request: ReturnIdRequest) -> RpcResult<ProductReturn> {
+        debug!("Getting return: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.get_return(request).await {
+            Ok(product_return) => {
+                if sample_success() {
+                    info!("Return found: {}", product_return.id);
+                }
+                Ok(product_return)
+            }
+            Err(err) => {
+                error!("Failed to get return: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to get return",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn log_event(&self, request: LogEventRequest) -> RpcResult<()> {
+        log_client_event("Product Service", &request);
+        Ok(())
+    }
+
+    async fn subscribe_changes(
+        &self,
+        pending: PendingSubscriptionSink,
+        request: SubscribeChangesRequest,
+    ) -> SubscriptionResult {
+        debug!("Subscribing to changes: {:?}", request);
+
+        let feed = match self.ready_service().await {
+            Ok(service) => service.change_feed().cloned(),
+            Err(err) => {
+                pending.reject(err).await;
+                return Ok(());
+            }
+        };
+        let Some(feed) = feed else {
+            pending
+                .reject(ErrorObject::owned(
+                    ErrorCode::InvalidRequest.code(),
+                    "Change subscriptions are disabled",
+                    Some("set LIVE_QUERIES=true"),
+                ))
+                .await;
+            return Ok(());
+        };
+        let tables = match feed.resolve_tables(&request.tables) {
+            Ok(tables) => tables,
+            Err(table) => {
+                pending
+                    .reject(ErrorObject::owned(
+                        ErrorCode::InvalidParams.code(),
+                        "Unknown table",
+                        Some(table),
+                    ))
+                    .await;
+                return Ok(());
+            }
+        };
+
+        let receiver = feed.subscribe();
+        let sink = pending.accept().await?;
+        tokio::spawn(forward_changes(receiver, tables, sink));
+        Ok(())
+    }
+
+    async fn subscribe_job_events(
+        &self,
+        pending: PendingSubscriptionSink,
+        request: SubscribeJobEventsRequest,
+    ) -> SubscriptionResult {
+        debug!("Subscribing to job events: {:?}", request);
+
+        let service = match self.ready_service().await {
+            Ok(service) => service,
+            Err(err) => {
+                pending.reject(err).await;
+                return Ok(());
+            }
+        };
+        let receiver = service.job_board().subscribe();
+        if let Some(job_id) = &request.job_id {
+            if let Err(err) = service.get_job_status(JobIdRequest { job_id: job_id.clone() }).await {
+                pending
+                    .reject(ErrorObject::owned(
+                        ErrorCode::InvalidParams.code(),
+                        "Unknown job",
+                        Some(err.error_data()),
+                    ))
+                    .await;
+                return Ok(());
+            }
+        }
+        drop(service);
+
+        let sink = pending.accept().await?;
+        tokio::spawn(forward_job_events(receiver, request.job_id, sink));
+        Ok(())
+    }
+
+    async fn set_feature_flag(&self, request: SetFeatureFlagRequest) -> RpcResult<FeatureFlag> {
+        debug!("Setting feature flag: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.feature_flags().set(request).await {
+            Ok(flag) => Ok(flag),
+            Err(err) => {
+                error!("Failed to set feature flag: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to set feature flag",
+                    Some(err.to_string()),
+                ))
+            }
+        }
+    }
+
+    async fn list_feature_flags(&self) -> RpcResult<ListFeatureFlagsResponse> {
+        debug!("Listing feature flags");
+
+        let service = self.ready_service().await?;
+        match service.feature_flags().list().await {
+            Ok(flags) => Ok(ListFeatureFlagsResponse {
+                total: flags.len(),
+                flags,
+            }),
+            Err(err) => {
+                error!("Failed to list feature flags: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to list feature flags",
+                    Some(err.to_string()),
+                ))
+            }
+        }
+    }
+
+    async fn evaluate_feature_flag(&self, request: EvaluateFeatureFlagRequest) -> RpcResult<FlagEvaluation> {
+        debug!("Evaluating feature flag: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.feature_flags().evaluate(&request.key, &request.context).await {
+            Ok(evaluation) => Ok(evaluation),
+            Err(err) => {
+                error!("Failed to evaluate feature flag: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to evaluate feature flag",
+                    Some(err.to_string()),
+                ))
+            }
+        }
+    }
+
+    async fn set_read_only(&self, request: SetReadOnlyRequest) -> RpcResult<ReadOnlyStatus> {
+        self.read_only.set(request.enabled);
+        warn!(
+            "Product Service read-only mode {}",
+            if request.enabled { "enabled" } else { "disabled" }
+        );
+        Ok(ReadOnlyStatus {
+            read_only: self.read_only.is_enabled(),
+        })
+    }
+
+    async fn set_log_sampling(
+        &self,
+        request: SetLogSamplingRequest,
+    ) -> RpcResult<LogSamplingStatus> {
+        let policy = log_policy();
+        if let Err(err) = policy.set_success_percent(request.success_percent) {
+            return Err(ErrorObject::owned(
+                ErrorCode::InvalidParams.code(),
+                "Invalid log sampling",
+                Some(err.to_string()),
+            ));
+        }
+        warn!(
+            "Product Service now logs {}% of successful requests",
+            request.success_percent
+        );
+        Ok(policy.sampling_status())
+    }
+
+    async fn query_stats(&self) -> RpcResult<QueryStatsSnapshot> {
+        Ok(QueryStats::global().snapshot())
+    }
+
+    async fn panic_stats(&self) -> RpcResult<PanicStatsSnapshot> {
+        Ok(PanicStats::global().snapshot())
+    }
+
+    async fn retention_stats(&self) -> RpcResult<RetentionReport> {
+        Ok(self.retention.report())
+    }
+
+    async fn storage_stats(&self) -> RpcResult<StorageReport> {
+        Ok(self.storage.report())
+    }
+
+    async fn database_isolation(&self) -> RpcResult<DatabaseIsolationReport> {
+        debug!("Checking database isolation");
+        let service = self.ready_service().await?;
+        match service.database_isolation().await {
+            Ok(report) => {
+                if !report.isolated {
+                    warn!("Product Service database is not isolated: {}", report.problems.join("; "));
+                }
+                Ok(report)
+            }
+            Err(err) => {
+                error!("Failed to check database isolation: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to check database isolation",
+                    Some(err.to_string()),
+                ))
+            }
+        }
+    }
+
+    async fn health(&self) -> RpcResult<String> {
+        // Reports "starting" as an error until the repository is ready, and
+        // the database status while its connection is down
+        let service = self.ready_service().await?;
+        let database = service.database_health();
+        if !database.connected {
+            return Err(ErrorObject::owned(
+                DATABASE_UNAVAILABLE_CODE,
+                "Database unavailable",
+                Some(database),
+            ));
+        }
+        Ok("Product Service is healthy!".to_string())
+    }
+}
+
+/// Methods kept to admins unless `AUTH_POLICY_FILE` lists them itself
+const ADMIN_METHODS: &[&str] = &["set_read_only", "set_feature_flag", "approve_return", "complete_return"];
+
+/// Methods only other services call: orders name the user they belong to,
+/// warehouses ship them, and the user service counts them before deleting
+/// a user
+const SERVICE_METHODS: &[&str] = &["record_order", "ship_order", "count_orders_by_user"];
+
+/// The policy from `AUTH_POLICY_FILE`, with the service's defaults
+fn authorization_policy() -> Result<AuthorizationPolicy, PolicyError> {
+    Ok(with_default_policies(AuthorizationPolicy::from_env()?))
+}
+
+/// Keeps the [`ADMIN_METHODS`] to admins, and the [`SERVICE_METHODS`] to
+/// the services calling them
+fn with_default_policies(policy: AuthorizationPolicy) -> AuthorizationPolicy {
+    let services = MethodPolicy { roles: vec![SERVICE_ROLE.to_string(), ADMIN_ROLE.to_string()], scopes: Vec::new() };
+    SERVICE_METHODS.iter().fold(policy.with_admin_defaults(ADMIN_METHODS), |policy, method| policy.with_default(method, services.clone()))
+}
+
+/// Loads every setting the service reads at startup, without opening the
+/// database or the port
+fn check_config() -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+    report.check("config.database", DatabaseConfig::product(), |config| config.endpoint.clone());
+    report.check(
+        "config.database_isolation",
+        DatabaseConfig::product().map(|config| check_configured(PRODUCT_SERVICE, &config)).unwrap_or(Ok(())),
+        |_| "not shared with another service".to_string(),
+    );
+    report.check("config.feeds", ProductFeedConfig::from_env(), |_| "loaded".to_string());
+    report.check("config.pagination_cursors", CursorSigner::from_env(), |_| "loaded".to_string());
+    report.check("config.retention", RetentionPolicy::from_env(PRODUCT_RETENTION_RULES), |policy| policy.describe());
+    report.check("config.storage_caps", StorageMonitor::from_env(), |storage| storage.describe());
+    report.check("config.authorization", authorization_policy(), |policy| {
+        format!("{} protected methods", policy.methods.len())
+    });
+    report.check("config.api_version", ApiVersion::default_from_env(), |version| format!("v{}", version.number()));
+    report.check("config.payload_limits", PayloadLimits::from_env().map(|limits| limits.with_method_array_lens(BULK_ARRAY_LIMITS)), PayloadLimits::describe);
+    report.check("config.timestamp_format", TimestampFormat::default_from_env(), |format| format.name().to_string());
+    report.check("config.request_signing", RequestSigner::from_env(), |signer| {
+        if signer.is_some() { "enabled" } else { "disabled" }.to_string()
+    });
+    report
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // The report is the only output, so this runs before tracing starts
+    if check_config_requested() {
+        return print_config_report(&check_config());
+    }
+    exit_code_for("Product Service", run().await, check_config)
+}
+
+async fn run() -> anyhow::Result<()> {
+    // Initialize tracing
+    init_tracing();
+    install_panic_hook();
+
+    info!("Starting Product Service...");
+
+    // Load the database settings up front so misconfiguration fails fast,
+    // even in lazy startup mode
+    let db_config = DatabaseConfig::product()?;
+
+    // Field mapping for the marketing feeds
+    let feed_config = Arc::new(ProductFeedConfig::from_env()?);
+
+    // Load the per-method authorization policy
+    let policy = Arc::new(authorization_policy()?);
+    if policy.is_enforcing() {
+        info!(
+            "🔒 Authorization policy loaded: {} protected methods",
+            policy.methods.len()
+        );
+    }
+
+    // Key signing list_products and list_orders_by_user cursors
+    let cursors = Arc::new(CursorSigner::from_env()?);
+
+    // Which expired records the cleanup job purges
+    let retention = Arc::new(RetentionPolicy::from_env(PRODUCT_RETENTION_RULES)?);
+    // Soft and hard caps on the database size
+    let storage = Arc::new(StorageMonitor::from_env()?);
+
+    // Create the RPC service, initializing the repository now or in the background
+    let product_rpc = match StartupMode::from_env() {
+        StartupMode::Eager => ProductRpcImpl::new(&db_config, Arc::clone(&feed_config), Arc::clone(&cursors), Arc::clone(&retention), Arc::clone(&storage), Arc::clone(&policy)).await?,
+        StartupMode::Lazy => {
+            let product_rpc = ProductRpcImpl::starting(Arc::clone(&feed_config), Arc::clone(&cursors), Arc::clone(&retention), Arc::clone(&storage), Arc::clone(&policy));
+            product_rpc.initialize_in_background(db_config);
+            product_rpc
+        }
+    };
+
+    // Allow operators to flip read-only mode without an RPC call
+    #[cfg(unix)]
+    jpc_rust::services::read_only::toggle_on_sigusr1(
+        Arc::clone(&product_rpc.read_only),
+        "Product Service",
+    )?;
+
+    // Apply scheduled price changes as they come due
+    if let Some(interval) = scheduler_interval_from_env() {
+        spawn_price_scheduler(Arc::clone(&product_rpc.service), interval);
+        info!("💲 Price scheduler running every {}s", interval.as_secs());
+    }
+
+    // Purge expired records in the background
+    if let Some(interval) = retention.interval() {
+        spawn_retention_job(Arc::clone(&product_rpc.service), Arc::clone(&retention), interval);
+        info!("🧹 Purging expired records every {}s: {}", interval.as_secs(), retention.describe());
+    }
+
+    // Measure storage against its caps in the background
+    if let Some(interval) = storage.interval() {
+        spawn_storage_monitor(Arc::clone(&product_rpc.service), Arc::clone(&storage), interval);
+        info!("💾 Measuring storage every {}s against caps: {}", interval.as_secs(), storage.describe());
+    }
+
+    // Catch stock pipeline bugs before customers do
+    if let Some(interval) = reconcile_interval_from_env() {
+        spawn_stock_reconciler(Arc::clone(&product_rpc.service), interval);
+        info!("🧮 Stock reconciled every {}s", interval.as_secs());
+    }
+
+    // Rebuild the frequently-bought-together table from the order history
+    let co_occurrence = CoOccurrenceConfig::from_env();
+    if let Some(interval) = co_occurrence.interval {
+        spawn_recommendation_builder(Arc::clone(&product_rpc.service), interval);
+        info!("🛒 Recommendations rebuilt every {}s from pairs bought together in at least {} orders", interval.as_secs(), co_occurrence.min_orders);
+    }
+
+    // Keep the marketing feeds fresh
+    if let Some(interval) = feed_interval_from_env() {
+        spawn_feed_scheduler(Arc::clone(&product_rpc.service), interval, feed_output_dir_from_env());
+        info!("📰 Product feeds regenerated every {}s", interval.as_secs());
+    }
+
+    // Run queued imports, exports and reindexes in the background
+    let job_workers = JobWorkerConfig::from_env();
+    spawn_job_workers(Arc::clone(&product_rpc.service), job_workers);
+    if job_workers.workers > 0 {
+        info!("⚙️ {} job workers polling every {}s", job_workers.workers, job_workers.poll_interval.as_secs());
+    }
+
+    // Response shape for clients that don't send Accept-Version
+    let api_version = ApiVersion::default_from_env()?;
+    info!(
+        "🏷️ Serving API version {} by default (latest {})",
+        api_version.number(),
+        ApiVersion::LATEST.number()
+    );
+
+    // Timestamp format for clients that don't send Timestamp-Format
+    let timestamp_format = TimestampFormat::default_from_env()?;
+    if timestamp_format != TimestampFormat::Rfc3339 {
+        info!(
+            "🕰️ Serving timestamps as {} by default",
+            timestamp_format.name()
+        );
+    }
+
+    // Only accept traffic signed by the gateway, when a secret is shared
+    let request_signer = RequestSigner::from_env()?.map(Arc::new);
+    if let Some(signer) = &request_signer {
+        info!(
+            "🔏 Requiring gateway signatures (max age {}s)",
+            signer.max_age_secs()
+        );
+    }
+
+    // Per-request db/app time for clients, when enabled
+    let server_timing = ServerTimingLayer::from_env();
+    if server_timing.is_enabled() {
+        info!("⏱️ Server-Timing headers enabled");
+    }
+
+    // Refuse oversized bodies, batches and bulk params up front
+    let payload_limits = PayloadLimits::from_env()?.with_method_array_lens(BULK_ARRAY_LIMITS);
+    info!("📦 Payload limits: {}", payload_limits.describe());
+
+    // Refuse work past capacity instead of queueing it, when configured
+    let load_shedding = LoadSheddingLayer::from_env();
+    if let Some((max_in_flight, latency_threshold)) = load_shedding.limits() {
+        match latency_threshold {
+            Some(threshold) => info!(
+                "🛑 Shedding load above {} requests in flight, halved while latency exceeds {}ms",
+                max_in_flight,
+                threshold.as_millis()
+            ),
+            None => info!("🛑 Shedding load above {} requests in flight", max_in_flight),
+        }
+    }
+
+    // Build the server on a different port than user service
+    let server = ServerBuilder::default()
+        .max_request_body_size(payload_limits.max_request_bytes)
+        .set_batch_request_config(BatchRequestConfig::Limit(payload_limits.max_batch_len))
+        .set_http_middleware(
+            tower::ServiceBuilder::new()
+                .layer(server_timing)
+                .layer(load_shedding)
+                .layer(PayloadLimitHeaderLayer::new(payload_limits))
+                .layer(RequestSignatureLayer::new(request_signer))
+                .layer(OrgContextLayer)
+                .layer(BearerTokenLayer)
+                .layer(DeadlineHeaderLayer)
+                .layer(ApiVersionHeaderLayer::new(api_version))
+                .layer(TimestampFormatHeaderLayer::new(timestamp_format))
+                .layer(TraceSamplingHeaderLayer)
+                .layer(RequestIdHeaderLayer)
+                .layer(NotificationLayer),
+        )
+        .set_rpc_middleware(
+            RpcServiceBuilder::new()
+                .layer(RequestIdLayer)
+                .layer(PanicRecoveryLayer)
+                .layer(PayloadLimitLayer::new(payload_limits))
+                .layer(DeadlineLayer)
+                .layer(AuthorizationLayer::new(policy))
+                .layer(ApiVersionLayer)
+                .layer(TimestampFormatLayer)
+                .layer(TraceSamplingLayer),
+        )
+        .build("127.0.0.1:8081")
+        .await
+        .map_err(|source| BindFailed { addr: "127.0.0.1:8081".to_string(), source })?;
+
+    // Register the methods under their flat and namespaced names
+    let mut module = product_rpc.into_rpc();
+    register_namespaced_methods(&mut module, PRODUCT_METHODS)?;
+    register_method_list(&mut module)?;
+    let handle = server.start(module);
+
+    info!("🚀 Product Service started on http://127.0.0.1:8081");
+    info!("Available methods:");
+    info!("  - create_product(name: String, description: String, price: f64, category: String, sku?: String, stock_quantity: number, unit?: piece|kg|liter, max_similarity?: f64, visible_to?: [String], dry_run?: bool)");
+    info!("  - get_product(id: String, locale?: String)");
+    info!("  - find_similar_products(name: String, threshold?: f64, limit?: usize)");
+    info!("  - set_product_visibility(product_id: String, visible_to: [String])");
+    info!("  - set_product_sku(product_id: String, sku: Option<String>)");
+    info!("  - set_translation(product_id: String, locale: String, name?: String, description?: String)");
+    info!("  - define_attribute(key: String, value_type: text|number|boolean, indexed?: bool, description?: String)");
+    info!("  - list_attributes()");
+    info!("  - set_attribute(product_id: String, key: String, value: String|number|bool)");
+    info!("  - remove_attribute(product_id: String, key: String)");
+    info!("  - search_products_by_attributes(attributes: object, limit?: usize)");
+    info!("  - list_products(sort_by?: String, sort_dir?: asc|desc, limit?: usize, cursor?: String)");
+    info!("  - get_products_by_category(category: String)");
+    info!("  - get_product_stats()");
+    info!("  - get_category_rollups()");
+    info!("  - update_product_stock(id: String, quantity: number, location?: String)");
+    info!("  - update_stock_bulk(items: [BulkStockItem], atomic?: bool)");
+    info!("  - transfer_stock(product_id: String, from: String, to: String, quantity: number)");
+    info!("  - create_location(code: String, name: String)");
+    info!("  - list_locations()");
+    info!("  - reconcile_stock(refresh?: bool)");
+    info!("  - forecast_stock(product_id: String, horizon_days: u32)");
+    info!("  - export_products(offset: usize, limit: usize)");
+    info!("  - import_products_csv(csv: String, batch_size?: usize)");
+    info!("  - start_job(kind: import_products_csv|export_products|reindex, params?: Object)");
+    info!("  - get_job_status(job_id: String)");
+    info!("  - schedule_price_change(product_id: String, new_price: f64, effective_at: DateTime)");
+    info!("  - get_price_history(product_id: String, limit?: usize)");
+    info!("  - generate_feed(format?: xml|csv, refresh?: bool)");
+    info!("  - create_coupon(code: String, discount_type: percent|fixed, value: f64, constraints?, expires_at?, max_redemptions?)");
+    info!("  - validate_coupon(code: String, order_total: f64, items?: [CheckoutItem])");
+    info!("  - redeem_coupon(code: String, order_total: f64, items?: [CheckoutItem], order_id?: String)");
+    info!("  - record_order(product_ids: [String], lines?: [OrderLine], order_id?: String, user_id?: String)");
+    info!("  - ship_order(order_id: String, shipping_address: Address)");
+    info!("  - list_orders_by_user(user_id: String, status?: completed|shipped|partially_returned|returned, from?: DateTime, to?: DateTime, limit?: usize, cursor?: String)");
+    info!("  - get_recommended_products(product_id: String, limit?: usize)");
+    info!("  - create_return(order_id: String, items: [ReturnItemRequest], reason: String)");
+    info!("  - approve_return(return_id: String)");
+    info!("  - complete_return(return_id: String)");
+    info!("  - get_return(return_id: String)");
+    info!("  - log_event(event: String, level?: String, fields?: Object) (notification)");
+    info!("  - subscribe_changes(tables?: [String]) (subscription, LIVE_QUERIES=true)");
+    info!("  - subscribe_job_events(job_id?: String) (subscription)");
+    info!("  - set_feature_flag(key: String, enabled: bool, rollout_percent?: u8, tenants?: [String], users?: [String])");
+    info!("  - list_feature_flags()");
+    info!("  - evaluate_feature_flag(key: String, context?: FlagContext)");
+    info!("  - set_read_only(enabled: bool)");
+    info!("  - set_log_sampling(success_percent: u8)");
+    info!("  - query_stats()");
+    info!("  - panic_stats()");
+    info!("  - retention_stats()");
+    info!("  - storage_stats()");
+    info!("  - database_isolation()");
+    info!("  - health()");
+    info!("  - rpc.methods()");
+    info!(
+        "Namespaced aliases (e.g. product.stock.update): {}",
+        PRODUCT_METHODS.len() + COMMON_METHODS.len()
+    );
+
+    // Set up graceful shutdown handling
+    let handle_clone = handle.clone();
+    tokio::spawn(async move {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for ctrl+c");
+        info!("Received shutdown signal, gracefully shutting down...");
+        handle_clone.stop().unwrap();
+    });
+
+    // Wait for the server to finish
+    handle.stopped().await;
+    info!("Product Service shut down gracefully");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jpc_rust::middleware::authorization::Denial;
+
+    #[test]
+    fn admin_methods_are_closed_without_a_policy_file() {
+        let policy = with_default_policies(AuthorizationPolicy::default());
+        for method in ["set_read_only", "set_feature_flag", "admin.flags.set", "approve_return", "product.returns.complete"] {
+            assert_eq!(policy.authorize(method, None), Err(Denial::Unauthenticated), "{}", method);
+        }
+        assert_eq!(policy.authorize("get_product", None), Ok(()));
+    }
+
+    #[test]
+    fn order_reports_and_counts_are_for_services_only() {
+        let policy: AuthorizationPolicy = serde_json::from_value(serde_json::json!({
+            "principals": {
+                "checkout-token": { "name": "checkout", "roles": ["service"] },
+                "alice-token": { "name": "alice", "user_id": "alice" }
+            }
+        }))
+        .unwrap();
+        let policy = with_default_policies(policy);
+        let cases = [
+            (None, Err(Denial::Unauthenticated)),
+            (Some("alice-token"), Err(Denial::MissingRole { principal: "alice".to_string() })),
+            (Some("checkout-token"), Ok(())),
+        ];
+        for (token, expected) in cases {
+            for method in ["product.orders.record", "product.orders.ship", "count_orders_by_user"] {
+                assert_eq!(policy.authorize(method, token), expected, "{} {:?}", method, token);
+            }
+        }
+    }
+}
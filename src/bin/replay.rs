@@ -0,0 +1,143 @@
+use anyhow::{bail, Context};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::header::AUTHORIZATION;
+use hyper::{Method, Request};
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use jpc_rust::{gateway::capture::CapturedExchange, telemetry::log_policy::init_tracing};
+use serde_json::Value;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+struct ReplayConfig {
+    capture_file: String,
+    target_url: String,
+    bearer_token: Option<String>,
+    ignore_fields: HashSet<String>,
+}
+
+impl ReplayConfig {
+    fn from_env() -> anyhow::Result<Self> {
+        let Some(capture_file) = std::env::args().nth(1) else {
+            bail!("Usage: replay <capture-file>");
+        };
+
+        Ok(Self {
+            capture_file,
+            target_url: std::env::var("REPLAY_TARGET_URL")
+                .unwrap_or_else(|_| "http://127.0.0.1:8082".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            bearer_token: std::env::var("REPLAY_BEARER_TOKEN").ok(),
+            ignore_fields: std::env::var("REPLAY_IGNORE_FIELDS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|field| !field.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+}
+
+/// Drops ignored keys at any depth, so timestamps and generated ids don't
+/// count as differences
+fn strip_fields(value: &mut Value, ignore: &HashSet<String>) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| !ignore.contains(key));
+            map.values_mut()
+                .for_each(|value| strip_fields(value, ignore));
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|value| strip_fields(value, ignore)),
+        _ => {}
+    }
+}
+
+/// Compares JSON bodies without the ignored fields, and other bodies as
+/// text
+fn same_body(captured: &str, replayed: &str, ignore: &HashSet<String>) -> bool {
+    match (
+        serde_json::from_str::<Value>(captured),
+        serde_json::from_str::<Value>(replayed),
+    ) {
+        (Ok(mut captured), Ok(mut replayed)) => {
+            strip_fields(&mut captured, ignore);
+            strip_fields(&mut replayed, ignore);
+            captured == replayed
+        }
+        _ => captured == replayed,
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    init_tracing();
+
+    let config = ReplayConfig::from_env()?;
+    let contents = std::fs::read_to_string(&config.capture_file)
+        .with_context(|| format!("Failed to read {}", config.capture_file))?;
+    let client = Client::builder(TokioExecutor::new()).build_http::<Full<Bytes>>();
+
+    info!("Replaying captured traffic...");
+    info!("  Capture file: {}", config.capture_file);
+    info!("  Target: {}", config.target_url);
+
+    let mut replayed = 0usize;
+    let mut mismatches = 0usize;
+    for (line_number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let exchange: CapturedExchange = serde_json::from_str(line)
+            .with_context(|| format!("Invalid capture on line {}", line_number + 1))?;
+
+        let mut request = Request::builder()
+            .method(Method::from_bytes(exchange.method.as_bytes())?)
+            .uri(format!("{}{}", config.target_url, exchange.path));
+        for (name, value) in &exchange.headers {
+            request = request.header(name, value);
+        }
+        if let Some(token) = &config.bearer_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = request.body(Full::new(Bytes::from(exchange.request_body.clone())))?;
+
+        let response = client
+            .request(request)
+            .await
+            .with_context(|| format!("Failed to replay {}", exchange.request_id))?;
+        let status = response.status().as_u16();
+        let body = response.into_body().collect().await?.to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        replayed += 1;
+
+        if status != exchange.status {
+            mismatches += 1;
+            warn!(
+                "❌ [{}] {} {}: status {} was {}",
+                exchange.request_id, exchange.method, exchange.path, status, exchange.status
+            );
+        } else if !same_body(&exchange.response_body, &body, &config.ignore_fields) {
+            mismatches += 1;
+            warn!(
+                "❌ [{}] {} {}: response differs\n  captured: {}\n  replayed: {}",
+                exchange.request_id, exchange.method, exchange.path, exchange.response_body, body
+            );
+        }
+    }
+
+    info!(
+        "📼 Replayed {} requests, {} matched, {} differed",
+        replayed,
+        replayed - mismatches,
+        mismatches
+    );
+    if mismatches > 0 {
+        bail!("{} replayed requests differed from the capture", mismatches);
+    }
+    Ok(())
+}
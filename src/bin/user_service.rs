@@ -1,78 +1,416 @@
 use jpc_rust::{
+    config::database::DatabaseConfig,
+    crypto::{pii::PiiCipher, request_signing::RequestSigner},
     errors::user_error::UserServiceError,
-    models::user_model::{
-        CreateUserRequest, CreateUserResponse, GetUserRequest, ListUsersResponse, User,
+    gateway::self_test::SelfTestReport,
+    middleware::{
+        api_version::{ApiVersion, ApiVersionHeaderLayer, ApiVersionLayer},
+        authorization::{
+            AuthorizationLayer, AuthorizationPolicy, BearerToken, BearerTokenLayer, PolicyError,
+        },
+        avatar_uploads::AvatarUploadLayer,
+        client_ip::{ClientIp, ClientIpLayer},
+        deadline::{DeadlineHeaderLayer, DeadlineLayer},
+        load_shedding::LoadSheddingLayer,
+        notifications::NotificationLayer,
+        panic_recovery::{install_panic_hook, PanicRecoveryLayer, PanicStats, PanicStatsSnapshot},
+        payload_limits::{PayloadLimitHeaderLayer, PayloadLimitLayer, PayloadLimits},
+        request_id::{RequestIdHeaderLayer, RequestIdLayer},
+        request_signing::RequestSignatureLayer,
+        server_timing::ServerTimingLayer,
+        timestamp_format::{TimestampFormat, TimestampFormatHeaderLayer, TimestampFormatLayer},
+        trace_sampling::{TraceSamplingHeaderLayer, TraceSamplingLayer},
+    },
+    models::{
+        address_model::{ValidateAddressRequest, ValidateAddressResponse},
+        admin_model::{
+            DatabaseIsolationReport, LogSamplingStatus, ReadOnlyStatus, RetentionReport,
+            SetLogSamplingRequest, SetReadOnlyRequest, StorageReport,
+        },
+        change_model::SubscribeChangesRequest,
+        event_model::LogEventRequest,
+        feature_flag_model::{
+            EvaluateFeatureFlagRequest, FeatureFlag, FlagEvaluation, ListFeatureFlagsResponse,
+            SetFeatureFlagRequest,
+        },
+        fraud_model::{ListFraudHitsRequest, ListFraudHitsResponse},
+        organization_model::{
+            AddMemberRequest, CreateOrgRequest, CreateOrgResponse, ListOrgMembersRequest,
+            ListOrgMembersResponse, MembershipResponse, RemoveMemberRequest,
+        },
+        user_model::{
+            AvatarUploadResponse, ConfirmAvatarRequest, CreateUserRequest, CreateUserResponse,
+            DeleteUserRequest, DeleteUserResponse, ExportUsersRequest, ExportUsersResponse,
+            GetNameHistoryRequest, GetUserHistoryRequest, GetUserRequest, GetUserStatsRequest,
+            ListInactiveUsersRequest, ListInactiveUsersResponse, ListUsersResponse,
+            LoginAttemptResponse, NameHistoryResponse, RecordActivityRequest,
+            RecordLoginAttemptRequest, RequestAvatarUploadRequest, RotateEncryptionKeysRequest,
+            RotateEncryptionKeysResponse, UnlockUserRequest, UpdateUserNameRequest,
+            UpdateUserRequest, User, UserHistoryResponse, UserStats,
+        },
+    },
+    repositories::connection::DATABASE_UNAVAILABLE_CODE,
+    services::{
+        address_validation::validate_address,
+        avatar_storage::{AvatarBackend, AvatarStorage},
+        change_feed::forward_changes,
+        client_events::log_client_event,
+        database_isolation::{check_configured, USER_SERVICE},
+        delete_guards::DeleteGuards,
+        login_throttle::LoginPolicy,
+        method_namespaces::{
+            register_method_list, register_namespaced_methods, COMMON_METHODS, USER_METHODS,
+        },
+        read_only::ReadOnlyMode,
+        retention::{spawn_retention_job, RetentionPolicy, USER_RETENTION_RULES},
+        signup_rules::{SignupRules, SIGNUP_RATE_LIMITED_CODE, SIGNUP_REJECTED_CODE},
+        startup::{
+            check_config_requested, exit_code_for, init_with_backoff, print_config_report,
+            BindFailed, StartupMode, SERVICE_STARTING_CODE,
+        },
+        storage_caps::{spawn_storage_monitor, StorageMonitor},
+        user_activity::{activity_flush_interval_from_env, spawn_activity_flusher},
+        user_service::{UserService, USER_DEPENDENTS},
+    },
+    telemetry::{
+        log_policy::{init_tracing, log_policy, sample_success},
+        query_metrics::{QueryStats, QueryStatsSnapshot},
     },
-    services::user_service::UserService,
 };
 use jsonrpsee::{
-    core::{async_trait, RpcResult},
+    core::{async_trait, RpcResult, SubscriptionResult},
     proc_macros::rpc,
-    server::ServerBuilder,
+    server::{BatchRequestConfig, RpcServiceBuilder, ServerBuilder},
     types::{ErrorCode, ErrorObject},
+    Extensions, PendingSubscriptionSink,
 };
+use std::process::ExitCode;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{error, info, Level};
-use tracing_subscriber;
+use tokio::sync::{RwLock, RwLockReadGuard};
+use tracing::{debug, error, info, warn};
 
 #[rpc(server)]
 pub trait UserRpc {
-    #[method(name = "create_user")]
+    /// Uses the caller's address forwarded by the gateway for velocity checks
+    #[method(name = "create_user", with_extensions)]
     async fn create_user(&self, request: CreateUserRequest) -> RpcResult<CreateUserResponse>;
 
     #[method(name = "get_user")]
     async fn get_user(&self, request: GetUserRequest) -> RpcResult<User>;
 
+    #[method(name = "update_user")]
+    async fn update_user(&self, request: UpdateUserRequest) -> RpcResult<User>;
+
+    /// Called by users renaming themselves, or by admins; keeps the old name
+    #[method(name = "update_user_name", with_extensions)]
+    async fn update_user_name(&self, request: UpdateUserNameRequest) -> RpcResult<User>;
+
+    #[method(name = "delete_user")]
+    async fn delete_user(&self, request: DeleteUserRequest) -> RpcResult<DeleteUserResponse>;
+
+    #[method(name = "request_avatar_upload")]
+    async fn request_avatar_upload(
+        &self,
+        request: RequestAvatarUploadRequest,
+    ) -> RpcResult<AvatarUploadResponse>;
+
+    #[method(name = "confirm_avatar")]
+    async fn confirm_avatar(&self, request: ConfirmAvatarRequest) -> RpcResult<User>;
+
+    #[method(name = "get_user_history")]
+    async fn get_user_history(
+        &self,
+        request: GetUserHistoryRequest,
+    ) -> RpcResult<UserHistoryResponse>;
+
+    /// Admins only unless `AUTH_POLICY_FILE` says otherwise
+    #[method(name = "get_name_history")]
+    async fn get_name_history(
+        &self,
+        request: GetNameHistoryRequest,
+    ) -> RpcResult<NameHistoryResponse>;
+
     #[method(name = "list_users")]
     async fn list_users(&self) -> RpcResult<ListUsersResponse>;
 
+    #[method(name = "export_users")]
+    async fn export_users(&self, request: ExportUsersRequest) -> RpcResult<ExportUsersResponse>;
+
+    #[method(name = "get_user_stats")]
+    async fn get_user_stats(&self, request: GetUserStatsRequest) -> RpcResult<UserStats>;
+
+    #[method(name = "list_inactive_users")]
+    async fn list_inactive_users(
+        &self,
+        request: ListInactiveUsersRequest,
+    ) -> RpcResult<ListInactiveUsersResponse>;
+
+    /// Fire-and-forget: sent on a signed-in user's activity, usually as a
+    /// notification
+    #[method(name = "record_activity")]
+    async fn record_activity(&self, request: RecordActivityRequest) -> RpcResult<()>;
+
+    /// Reported by the auth frontend after it checks a password; the
+    /// sign-in must be refused when the response says it isn't allowed
+    #[method(name = "record_login_attempt")]
+    async fn record_login_attempt(
+        &self,
+        request: RecordLoginAttemptRequest,
+    ) -> RpcResult<LoginAttemptResponse>;
+
+    #[method(name = "unlock_user")]
+    async fn unlock_user(&self, request: UnlockUserRequest) -> RpcResult<User>;
+
+    #[method(name = "rotate_encryption_keys")]
+    async fn rotate_encryption_keys(
+        &self,
+        request: RotateEncryptionKeysRequest,
+    ) -> RpcResult<RotateEncryptionKeysResponse>;
+
+    #[method(name = "list_fraud_hits")]
+    async fn list_fraud_hits(
+        &self,
+        request: ListFraudHitsRequest,
+    ) -> RpcResult<ListFraudHitsResponse>;
+
+    #[method(name = "create_org")]
+    async fn create_org(&self, request: CreateOrgRequest) -> RpcResult<CreateOrgResponse>;
+
+    /// Also changes the role of an existing member
+    #[method(name = "add_member")]
+    async fn add_member(&self, request: AddMemberRequest) -> RpcResult<MembershipResponse>;
+
+    #[method(name = "remove_member")]
+    async fn remove_member(&self, request: RemoveMemberRequest) -> RpcResult<MembershipResponse>;
+
+    #[method(name = "list_org_members")]
+    async fn list_org_members(
+        &self,
+        request: ListOrgMembersRequest,
+    ) -> RpcResult<ListOrgMembersResponse>;
+
+    #[method(name = "validate_address")]
+    async fn validate_address(
+        &self,
+        request: ValidateAddressRequest,
+    ) -> RpcResult<ValidateAddressResponse>;
+
+    /// Fire-and-forget: usually sent as a notification, without an id
+    #[method(name = "log_event")]
+    async fn log_event(&self, request: LogEventRequest) -> RpcResult<()>;
+
+    /// Pushes a `change` notification for every record created, updated or
+    /// deleted in the service's tables. Needs `LIVE_QUERIES=true`.
+    #[subscription(name = "subscribe_changes" => "change", unsubscribe = "unsubscribe_changes", item = ChangeEvent)]
+    async fn subscribe_changes(&self, request: SubscribeChangesRequest) -> SubscriptionResult;
+
+    #[method(name = "set_feature_flag")]
+    async fn set_feature_flag(&self, request: SetFeatureFlagRequest) -> RpcResult<FeatureFlag>;
+
+    #[method(name = "list_feature_flags")]
+    async fn list_feature_flags(&self) -> RpcResult<ListFeatureFlagsResponse>;
+
+    #[method(name = "evaluate_feature_flag")]
+    async fn evaluate_feature_flag(
+        &self,
+        request: EvaluateFeatureFlagRequest,
+    ) -> RpcResult<FlagEvaluation>;
+
+    #[method(name = "set_read_only")]
+    async fn set_read_only(&self, request: SetReadOnlyRequest) -> RpcResult<ReadOnlyStatus>;
+
+    /// Changes the percentage of successful requests logged; failures are
+    /// always logged
+    #[method(name = "set_log_sampling")]
+    async fn set_log_sampling(
+        &self,
+        request: SetLogSamplingRequest,
+    ) -> RpcResult<LogSamplingStatus>;
+
+    #[method(name = "query_stats")]
+    async fn query_stats(&self) -> RpcResult<QueryStatsSnapshot>;
+
+    #[method(name = "panic_stats")]
+    async fn panic_stats(&self) -> RpcResult<PanicStatsSnapshot>;
+
+    #[method(name = "retention_stats")]
+    async fn retention_stats(&self) -> RpcResult<RetentionReport>;
+
+    /// Record counts and approximate size per table against the storage caps
+    #[method(name = "storage_stats")]
+    async fn storage_stats(&self) -> RpcResult<StorageReport>;
+
+    /// The namespace, database and tables actually in use, checked against
+    /// the config
+    #[method(name = "database_isolation")]
+    async fn database_isolation(&self) -> RpcResult<DatabaseIsolationReport>;
+
     #[method(name = "health")]
     async fn health(&self) -> RpcResult<String>;
 }
 
 pub struct UserRpcImpl {
-    service: Arc<RwLock<UserService>>,
+    /// `None` until the repository has been initialized
+    service: Arc<RwLock<Option<UserService>>>,
+    read_only: Arc<ReadOnlyMode>,
+    signup_rules: Arc<SignupRules>,
+    login_policy: Arc<LoginPolicy>,
+    delete_guards: Arc<DeleteGuards>,
+    avatars: Arc<dyn AvatarStorage>,
+    retention: Arc<RetentionPolicy>,
+    storage: Arc<StorageMonitor>,
+    authorization: Arc<AuthorizationPolicy>,
 }
 
 impl UserRpcImpl {
-    pub async fn new() -> Result<Self, UserServiceError> {
-        let service = UserService::new().await?;
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        db_config: &DatabaseConfig,
+        signup_rules: Arc<SignupRules>,
+        login_policy: Arc<LoginPolicy>,
+        delete_guards: Arc<DeleteGuards>,
+        avatars: Arc<dyn AvatarStorage>,
+        retention: Arc<RetentionPolicy>,
+        storage: Arc<StorageMonitor>,
+        authorization: Arc<AuthorizationPolicy>,
+    ) -> Result<Self, UserServiceError> {
+        let read_only = Arc::new(ReadOnlyMode::from_env());
+        let service = UserService::new(
+            Arc::clone(&read_only),
+            Arc::clone(&storage),
+            Arc::clone(&signup_rules),
+            Arc::clone(&login_policy),
+            Arc::clone(&delete_guards),
+            Arc::clone(&avatars),
+            db_config,
+        )
+        .await?;
         Ok(Self {
-            service: Arc::new(RwLock::new(service)),
+            service: Arc::new(RwLock::new(Some(service))),
+            read_only,
+            signup_rules,
+            login_policy,
+            delete_guards,
+            avatars,
+            retention,
+            storage,
+            authorization,
+        })
+    }
+
+    /// Creates the RPC handler without a service; calls fail with
+    /// "Service is starting" until `initialize_in_background` completes.
+    pub fn starting(
+        signup_rules: Arc<SignupRules>,
+        login_policy: Arc<LoginPolicy>,
+        delete_guards: Arc<DeleteGuards>,
+        avatars: Arc<dyn AvatarStorage>,
+        retention: Arc<RetentionPolicy>,
+        storage: Arc<StorageMonitor>,
+        authorization: Arc<AuthorizationPolicy>,
+    ) -> Self {
+        Self {
+            service: Arc::new(RwLock::new(None)),
+            read_only: Arc::new(ReadOnlyMode::from_env()),
+            signup_rules,
+            login_policy,
+            delete_guards,
+            avatars,
+            retention,
+            storage,
+            authorization,
+        }
+    }
+
+    pub fn initialize_in_background(&self, db_config: DatabaseConfig) {
+        let slot = Arc::clone(&self.service);
+        let read_only = Arc::clone(&self.read_only);
+        let storage = Arc::clone(&self.storage);
+        let signup_rules = Arc::clone(&self.signup_rules);
+        let login_policy = Arc::clone(&self.login_policy);
+        let delete_guards = Arc::clone(&self.delete_guards);
+        let avatars = Arc::clone(&self.avatars);
+        tokio::spawn(async move {
+            let service = init_with_backoff("UserService", || {
+                UserService::new(
+                    Arc::clone(&read_only),
+                    Arc::clone(&storage),
+                    Arc::clone(&signup_rules),
+                    Arc::clone(&login_policy),
+                    Arc::clone(&delete_guards),
+                    Arc::clone(&avatars),
+                    &db_config,
+                )
+            })
+            .await;
+            match service.database_isolation().await {
+                Ok(report) if !report.isolated => {
+                    for problem in &report.problems {
+                        warn!("⚠️ Database isolation: {}", problem);
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => warn!("⚠️ Failed to check database isolation: {}", err),
+            }
+            *slot.write().await = Some(service);
+            info!("🟢 User Service is ready");
+        });
+    }
+
+    async fn ready_service(&self) -> RpcResult<RwLockReadGuard<'_, UserService>> {
+        RwLockReadGuard::try_map(self.service.read().await, Option::as_ref).map_err(|_| {
+            ErrorObject::owned(
+                SERVICE_STARTING_CODE,
+                "Service is starting",
+                Some("starting"),
+            )
         })
     }
 }
 
 #[async_trait]
 impl UserRpcServer for UserRpcImpl {
-    async fn create_user(&self, request: CreateUserRequest) -> RpcResult<CreateUserResponse> {
-        info!("Creating user: {:?}", request);
+    async fn create_user(
+        &self,
+        ext: &Extensions,
+        request: CreateUserRequest,
+    ) -> RpcResult<CreateUserResponse> {
+        debug!("Creating user: {:?}", request);
 
-        let service = self.service.read().await;
-        match service.create_user(request).await {
+        let client_ip = ext.get::<ClientIp>().map(|ClientIp(ip)| *ip);
+        let service = self.ready_service().await?;
+        match service.create_user(request, client_ip).await {
             Ok(response) => {
-                info!("User created successfully: {}", response.id);
+                if !response.dry_run && sample_success() {
+                    info!("User created successfully: {}", response.id);
+                }
                 Ok(response)
             }
             Err(err) => {
                 error!("Failed to create user: {}", err);
+                let code = match err {
+                    UserServiceError::SignupRateLimited { .. } => SIGNUP_RATE_LIMITED_CODE,
+                    UserServiceError::SignupRejected { .. } => SIGNUP_REJECTED_CODE,
+                    _ => ErrorCode::InternalError.code(),
+                };
                 Err(ErrorObject::owned(
-                    ErrorCode::InternalError.code(),
+                    code,
                     "Failed to create user",
-                    Some(err.to_string()),
+                    Some(err.error_data()),
                 ))
             }
         }
     }
 
     async fn get_user(&self, request: GetUserRequest) -> RpcResult<User> {
-        info!("Getting user: {:?}", request);
+        debug!("Getting user: {:?}", request);
 
-        let service = self.service.read().await;
+        let service = self.ready_service().await?;
         match service.get_user(request).await {
             Ok(user) => {
-                info!("User retrieved successfully: {}", user.id);
+                if sample_success() {
+                    info!("User retrieved successfully: {}", user.id);
+                }
                 Ok(user)
             }
             Err(err) => {
@@ -80,19 +418,205 @@ impl UserRpcServer for UserRpcImpl {
                 Err(ErrorObject::owned(
                     ErrorCode::InternalError.code(),
                     "Failed to get user",
-                    Some(err.to_string()),
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn update_user(&self, request: UpdateUserRequest) -> RpcResult<User> {
+        debug!("Updating user: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.update_user(request).await {
+            Ok(user) => {
+                if sample_success() {
+                    info!("User updated successfully: {}", user.id);
+                }
+                Ok(user)
+            }
+            Err(err) => {
+                error!("Failed to update user: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to update user",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn update_user_name(
+        &self,
+        ext: &Extensions,
+        request: UpdateUserNameRequest,
+    ) -> RpcResult<User> {
+        debug!("Updating user name: {:?}", request);
+
+        let token = ext.get::<BearerToken>().map(|t| t.0.as_str());
+        if let Err(denial) = self.authorization.authorize_owner(token, &request.id) {
+            warn!(
+                "🔒 Denied update_user_name for {}: {:?}",
+                request.id, denial
+            );
+            return Err(denial.into_error_object("update_user_name"));
+        }
+
+        let service = self.ready_service().await?;
+        match service.update_user_name(request).await {
+            Ok(user) => {
+                if sample_success() {
+                    info!("User name updated successfully: {}", user.id);
+                }
+                Ok(user)
+            }
+            Err(err) => {
+                error!("Failed to update user name: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to update user name",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn delete_user(&self, request: DeleteUserRequest) -> RpcResult<DeleteUserResponse> {
+        debug!("Deleting user: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.delete_user(request).await {
+            Ok(response) => {
+                if !response.dry_run && sample_success() {
+                    info!("User deleted successfully: {}", response.id);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to delete user: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to delete user",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn request_avatar_upload(
+        &self,
+        request: RequestAvatarUploadRequest,
+    ) -> RpcResult<AvatarUploadResponse> {
+        debug!("Requesting avatar upload: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.request_avatar_upload(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Avatar upload URL issued: {}", response.key);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to request avatar upload: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to request avatar upload",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn confirm_avatar(&self, request: ConfirmAvatarRequest) -> RpcResult<User> {
+        debug!("Confirming avatar: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.confirm_avatar(request).await {
+            Ok(user) => {
+                if sample_success() {
+                    info!("Avatar confirmed successfully: {}", user.id);
+                }
+                Ok(user)
+            }
+            Err(err) => {
+                error!("Failed to confirm avatar: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to confirm avatar",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn get_user_history(
+        &self,
+        request: GetUserHistoryRequest,
+    ) -> RpcResult<UserHistoryResponse> {
+        debug!("Getting user history: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.get_user_history(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "User history retrieved successfully: {} ({} events)",
+                        response.user_id,
+                        response.events.len()
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to get user history: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to get user history",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn get_name_history(
+        &self,
+        request: GetNameHistoryRequest,
+    ) -> RpcResult<NameHistoryResponse> {
+        debug!("Getting name history: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.get_name_history(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Name history retrieved successfully: {} ({} previous names)",
+                        response.user_id,
+                        response.history.len()
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to get name history: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to get name history",
+                    Some(err.error_data()),
                 ))
             }
         }
     }
 
     async fn list_users(&self) -> RpcResult<ListUsersResponse> {
-        info!("Listing users");
+        debug!("Listing users");
 
-        let service = self.service.read().await;
+        let service = self.ready_service().await?;
         match service.list_users().await {
             Ok(response) => {
-                info!("Users listed successfully: {} users", response.total);
+                if sample_success() {
+                    info!("Users listed successfully: {} users", response.total);
+                }
                 Ok(response)
             }
             Err(err) => {
@@ -100,6 +624,500 @@ impl UserRpcServer for UserRpcImpl {
                 Err(ErrorObject::owned(
                     ErrorCode::InternalError.code(),
                     "Failed to list users",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn export_users(&self, request: ExportUsersRequest) -> RpcResult<ExportUsersResponse> {
+        debug!("Exporting users: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.export_users(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Users exported successfully: {} of {}",
+                        response.users.len(),
+                        response.total
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to export users: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to export users",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn list_inactive_users(
+        &self,
+        request: ListInactiveUsersRequest,
+    ) -> RpcResult<ListInactiveUsersResponse> {
+        debug!("Listing inactive users: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.list_inactive_users(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Inactive users listed: {} of {}",
+                        response.users.len(),
+                        response.total
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to list inactive users: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to list inactive users",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn record_activity(&self, request: RecordActivityRequest) -> RpcResult<()> {
+        let service = self.ready_service().await?;
+        service.record_activity(request).map_err(|err| {
+            ErrorObject::owned(
+                ErrorCode::InvalidParams.code(),
+                "Failed to record activity",
+                Some(err.error_data()),
+            )
+        })
+    }
+
+    async fn record_login_attempt(
+        &self,
+        request: RecordLoginAttemptRequest,
+    ) -> RpcResult<LoginAttemptResponse> {
+        debug!("Recording login attempt: success={}", request.success);
+
+        let service = self.ready_service().await?;
+        match service.record_login_attempt(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Login attempt recorded: allowed={}, failed_attempts={}",
+                        response.allowed, response.failed_attempts
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to record login attempt: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to record login attempt",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn unlock_user(&self, request: UnlockUserRequest) -> RpcResult<User> {
+        debug!("Unlocking user: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.unlock_user(request).await {
+            Ok(user) => {
+                if sample_success() {
+                    info!("User unlocked successfully: {}", user.id);
+                }
+                Ok(user)
+            }
+            Err(err) => {
+                error!("Failed to unlock user: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to unlock user",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn rotate_encryption_keys(
+        &self,
+        request: RotateEncryptionKeysRequest,
+    ) -> RpcResult<RotateEncryptionKeysResponse> {
+        info!("Rotating PII encryption keys: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.rotate_encryption_keys(request).await {
+            Ok(response) => {
+                info!(
+                    "Key rotation complete: {} users re-encrypted under '{}'",
+                    response.reencrypted, response.active_key_id
+                );
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to rotate encryption keys: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to rotate encryption keys",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn get_user_stats(&self, request: GetUserStatsRequest) -> RpcResult<UserStats> {
+        debug!("Computing user stats: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.get_user_stats(request).await {
+            Ok(stats) => {
+                if sample_success() {
+                    info!(
+                        "User stats computed: {} users, {} verified",
+                        stats.total_users, stats.verified_users
+                    );
+                }
+                Ok(stats)
+            }
+            Err(err) => {
+                error!("Failed to compute user stats: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to compute user stats",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn list_fraud_hits(
+        &self,
+        request: ListFraudHitsRequest,
+    ) -> RpcResult<ListFraudHitsResponse> {
+        debug!("Listing fraud hits: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.list_fraud_hits(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Fraud hits retrieved: {} of {}",
+                        response.hits.len(),
+                        response.total
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to list fraud hits: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to list fraud hits",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn create_org(&self, request: CreateOrgRequest) -> RpcResult<CreateOrgResponse> {
+        debug!("Creating organization: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.create_org(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!("Organization created successfully: {}", response.id);
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to create organization: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to create organization",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn add_member(&self, request: AddMemberRequest) -> RpcResult<MembershipResponse> {
+        debug!("Adding organization member: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.add_member(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Organization {} member {}: {}",
+                        response.org_id, response.user_id, response.message
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to add organization member: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to add member",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn remove_member(&self, request: RemoveMemberRequest) -> RpcResult<MembershipResponse> {
+        debug!("Removing organization member: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.remove_member(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Member {} removed from organization {}",
+                        response.user_id, response.org_id
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to remove organization member: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to remove member",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn list_org_members(
+        &self,
+        request: ListOrgMembersRequest,
+    ) -> RpcResult<ListOrgMembersResponse> {
+        debug!("Listing organization members: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.list_org_members(request).await {
+            Ok(response) => {
+                if sample_success() {
+                    info!(
+                        "Organization {} has {} members",
+                        response.organization.id, response.total
+                    );
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                error!("Failed to list organization members: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to list organization members",
+                    Some(err.error_data()),
+                ))
+            }
+        }
+    }
+
+    async fn validate_address(
+        &self,
+        request: ValidateAddressRequest,
+    ) -> RpcResult<ValidateAddressResponse> {
+        debug!("Validating address: {:?}", request);
+
+        // Pure validation, so this works before the repository is ready
+        let response = validate_address(&request.address);
+        if !response.valid {
+            debug!("Address rejected: {:?}", response.errors);
+        }
+        Ok(response)
+    }
+
+    async fn log_event(&self, request: LogEventRequest) -> RpcResult<()> {
+        log_client_event("User Service", &request);
+        Ok(())
+    }
+
+    async fn subscribe_changes(
+        &self,
+        pending: PendingSubscriptionSink,
+        request: SubscribeChangesRequest,
+    ) -> SubscriptionResult {
+        debug!("Subscribing to changes: {:?}", request);
+
+        let feed = match self.ready_service().await {
+            Ok(service) => service.change_feed().cloned(),
+            Err(err) => {
+                pending.reject(err).await;
+                return Ok(());
+            }
+        };
+        let Some(feed) = feed else {
+            pending
+                .reject(ErrorObject::owned(
+                    ErrorCode::InvalidRequest.code(),
+                    "Change subscriptions are disabled",
+                    Some("set LIVE_QUERIES=true"),
+                ))
+                .await;
+            return Ok(());
+        };
+        let tables = match feed.resolve_tables(&request.tables) {
+            Ok(tables) => tables,
+            Err(table) => {
+                pending
+                    .reject(ErrorObject::owned(
+                        ErrorCode::InvalidParams.code(),
+                        "Unknown table",
+                        Some(table),
+                    ))
+                    .await;
+                return Ok(());
+            }
+        };
+
+        let receiver = feed.subscribe();
+        let sink = pending.accept().await?;
+        tokio::spawn(forward_changes(receiver, tables, sink));
+        Ok(())
+    }
+
+    async fn set_feature_flag(&self, request: SetFeatureFlagRequest) -> RpcResult<FeatureFlag> {
+        debug!("Setting feature flag: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service.feature_flags().set(request).await {
+            Ok(flag) => Ok(flag),
+            Err(err) => {
+                error!("Failed to set feature flag: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to set feature flag",
+                    Some(err.to_string()),
+                ))
+            }
+        }
+    }
+
+    async fn list_feature_flags(&self) -> RpcResult<ListFeatureFlagsResponse> {
+        debug!("Listing feature flags");
+
+        let service = self.ready_service().await?;
+        match service.feature_flags().list().await {
+            Ok(flags) => Ok(ListFeatureFlagsResponse {
+                total: flags.len(),
+                flags,
+            }),
+            Err(err) => {
+                error!("Failed to list feature flags: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to list feature flags",
+                    Some(err.to_string()),
+                ))
+            }
+        }
+    }
+
+    async fn evaluate_feature_flag(
+        &self,
+        request: EvaluateFeatureFlagRequest,
+    ) -> RpcResult<FlagEvaluation> {
+        debug!("Evaluating feature flag: {:?}", request);
+
+        let service = self.ready_service().await?;
+        match service
+            .feature_flags()
+            .evaluate(&request.key, &request.context)
+            .await
+        {
+            Ok(evaluation) => Ok(evaluation),
+            Err(err) => {
+                error!("Failed to evaluate feature flag: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to evaluate feature flag",
+                    Some(err.to_string()),
+                ))
+            }
+        }
+    }
+
+    async fn set_read_only(&self, request: SetReadOnlyRequest) -> RpcResult<ReadOnlyStatus> {
+        self.read_only.set(request.enabled);
+        warn!(
+            "User Service read-only mode {}",
+            if request.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+        Ok(ReadOnlyStatus {
+            read_only: self.read_only.is_enabled(),
+        })
+    }
+
+    async fn set_log_sampling(
+        &self,
+        request: SetLogSamplingRequest,
+    ) -> RpcResult<LogSamplingStatus> {
+        let policy = log_policy();
+        if let Err(err) = policy.set_success_percent(request.success_percent) {
+            return Err(ErrorObject::owned(
+                ErrorCode::InvalidParams.code(),
+                "Invalid log sampling",
+                Some(err.to_string()),
+            ));
+        }
+        warn!(
+            "User Service now logs {}% of successful requests",
+            request.success_percent
+        );
+        Ok(policy.sampling_status())
+    }
+
+    async fn query_stats(&self) -> RpcResult<QueryStatsSnapshot> {
+        Ok(QueryStats::global().snapshot())
+    }
+
+    async fn panic_stats(&self) -> RpcResult<PanicStatsSnapshot> {
+        Ok(PanicStats::global().snapshot())
+    }
+
+    async fn retention_stats(&self) -> RpcResult<RetentionReport> {
+        Ok(self.retention.report())
+    }
+
+    async fn storage_stats(&self) -> RpcResult<StorageReport> {
+        Ok(self.storage.report())
+    }
+
+    async fn database_isolation(&self) -> RpcResult<DatabaseIsolationReport> {
+        debug!("Checking database isolation");
+        let service = self.ready_service().await?;
+        match service.database_isolation().await {
+            Ok(report) => {
+                if !report.isolated {
+                    warn!(
+                        "User Service database is not isolated: {}",
+                        report.problems.join("; ")
+                    );
+                }
+                Ok(report)
+            }
+            Err(err) => {
+                error!("Failed to check database isolation: {}", err);
+                Err(ErrorObject::owned(
+                    ErrorCode::InternalError.code(),
+                    "Failed to check database isolation",
                     Some(err.to_string()),
                 ))
             }
@@ -107,32 +1125,384 @@ impl UserRpcServer for UserRpcImpl {
     }
 
     async fn health(&self) -> RpcResult<String> {
+        // Reports "starting" as an error until the repository is ready, and
+        // the database status while its connection is down
+        let service = self.ready_service().await?;
+        let database = service.database_health();
+        if !database.connected {
+            return Err(ErrorObject::owned(
+                DATABASE_UNAVAILABLE_CODE,
+                "Database unavailable",
+                Some(database),
+            ));
+        }
         Ok("User Service is healthy!".to_string())
     }
 }
 
+/// Methods kept to admins unless `AUTH_POLICY_FILE` lists them itself
+const ADMIN_METHODS: &[&str] = &[
+    "get_name_history",
+    "set_read_only",
+    "unlock_user",
+    "rotate_encryption_keys",
+    "set_feature_flag",
+];
+
+/// The policy from `AUTH_POLICY_FILE`, with the [`ADMIN_METHODS`] defaults
+fn authorization_policy() -> Result<AuthorizationPolicy, PolicyError> {
+    Ok(AuthorizationPolicy::from_env()?.with_admin_defaults(ADMIN_METHODS))
+}
+
+/// Loads every setting the service reads at startup, without opening the
+/// database or the port
+fn check_config() -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+    report.check("config.database", DatabaseConfig::user(), |config| {
+        config.endpoint.clone()
+    });
+    report.check(
+        "config.database_isolation",
+        DatabaseConfig::user()
+            .map(|config| check_configured(USER_SERVICE, &config))
+            .unwrap_or(Ok(())),
+        |_| "not shared with another service".to_string(),
+    );
+    report.check("config.pii_encryption", PiiCipher::from_env(), |cipher| {
+        cipher.as_ref().map_or("disabled".to_string(), |cipher| {
+            format!("key {}", cipher.active_key_id())
+        })
+    });
+    report.check("config.avatars", AvatarBackend::from_env(), |backend| {
+        backend.storage().backend().to_string()
+    });
+    report.check("config.signup_rules", SignupRules::from_env(), |rules| {
+        format!("{} blocked domains", rules.blocked_domains.len())
+    });
+    report.check("config.login_policy", LoginPolicy::from_env(), |policy| {
+        format!("lock after {} failures", policy.max_failures)
+    });
+    report.check(
+        "config.delete_policies",
+        DeleteGuards::from_env("USER_DELETE_POLICIES", USER_DEPENDENTS),
+        |guards| guards.describe(),
+    );
+    report.check(
+        "config.retention",
+        RetentionPolicy::from_env(USER_RETENTION_RULES),
+        |policy| policy.describe(),
+    );
+    report.check(
+        "config.storage_caps",
+        StorageMonitor::from_env(),
+        |storage| storage.describe(),
+    );
+    report.check("config.authorization", authorization_policy(), |policy| {
+        format!("{} protected methods", policy.methods.len())
+    });
+    report.check(
+        "config.api_version",
+        ApiVersion::default_from_env(),
+        |version| format!("v{}", version.number()),
+    );
+    report.check(
+        "config.payload_limits",
+        PayloadLimits::from_env(),
+        PayloadLimits::describe,
+    );
+    report.check(
+        "config.timestamp_format",
+        TimestampFormat::default_from_env(),
+        |format| format.name().to_string(),
+    );
+    report.check(
+        "config.request_signing",
+        RequestSigner::from_env(),
+        |signer| {
+            if signer.is_some() {
+                "enabled"
+            } else {
+                "disabled"
+            }
+            .to_string()
+        },
+    );
+    report
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
+    // The report is the only output, so this runs before tracing starts
+    if check_config_requested() {
+        return print_config_report(&check_config());
+    }
+    exit_code_for("User Service", run().await, check_config)
+}
+
+async fn run() -> anyhow::Result<()> {
     // Initialize tracing
-    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+    init_tracing();
+    install_panic_hook();
 
     info!("Starting User Service...");
 
-    // Create the RPC service
-    let user_rpc = UserRpcImpl::new().await?;
+    // Load the database settings up front so misconfiguration fails fast,
+    // even in lazy startup mode
+    let db_config = DatabaseConfig::user()?;
+
+    // Avatar images go straight to this store through presigned URLs
+    let avatar_backend = AvatarBackend::from_env()?;
+    info!("🖼️ Avatar storage: {}", avatar_backend.storage().backend());
+
+    // Anti-fraud rules for create_user
+    let signup_rules = Arc::new(SignupRules::from_env()?);
+    info!(
+        "🛡️ Signup rules: {} blocked domains, per-domain limit {:?}/h, per-IP limit {:?}/h",
+        signup_rules.blocked_domains.len(),
+        signup_rules.max_per_domain,
+        signup_rules.max_per_ip
+    );
+
+    // When repeated failed sign-ins lock an account
+    let login_policy = Arc::new(LoginPolicy::from_env()?);
+    info!(
+        "🔒 Accounts lock after {} failed sign-ins, for {}s doubling up to {}s",
+        login_policy.max_failures,
+        login_policy.base_lockout.as_secs(),
+        login_policy.max_lockout.as_secs()
+    );
+
+    // What deleting a user does to the records that refer to them
+    let delete_guards = Arc::new(DeleteGuards::from_env(
+        "USER_DELETE_POLICIES",
+        USER_DEPENDENTS,
+    )?);
+    info!("🧷 Delete policies: {}", delete_guards.describe());
+
+    // Which expired records the cleanup job purges
+    let retention = Arc::new(RetentionPolicy::from_env(USER_RETENTION_RULES)?);
+    // Soft and hard caps on the database size
+    let storage = Arc::new(StorageMonitor::from_env()?);
+
+    // Load the per-method authorization policy
+    let policy = Arc::new(authorization_policy()?);
+    if policy.is_enforcing() {
+        info!(
+            "🔒 Authorization policy loaded: {} protected methods",
+            policy.methods.len()
+        );
+    }
+
+    // Create the RPC service, initializing the repository now or in the background
+    let user_rpc = match StartupMode::from_env() {
+        StartupMode::Eager => {
+            UserRpcImpl::new(
+                &db_config,
+                Arc::clone(&signup_rules),
+                Arc::clone(&login_policy),
+                Arc::clone(&delete_guards),
+                avatar_backend.storage(),
+                Arc::clone(&retention),
+                Arc::clone(&storage),
+                Arc::clone(&policy),
+            )
+            .await?
+        }
+        StartupMode::Lazy => {
+            let user_rpc = UserRpcImpl::starting(
+                Arc::clone(&signup_rules),
+                Arc::clone(&login_policy),
+                Arc::clone(&delete_guards),
+                avatar_backend.storage(),
+                Arc::clone(&retention),
+                Arc::clone(&storage),
+                Arc::clone(&policy),
+            );
+            user_rpc.initialize_in_background(db_config);
+            user_rpc
+        }
+    };
+
+    // Purge expired records in the background
+    if let Some(interval) = retention.interval() {
+        info!(
+            "🧹 Purging expired records every {}s: {}",
+            interval.as_secs(),
+            retention.describe()
+        );
+        spawn_retention_job(
+            Arc::clone(&user_rpc.service),
+            Arc::clone(&retention),
+            interval,
+        );
+    }
+
+    // Measure storage against its caps in the background
+    if let Some(interval) = storage.interval() {
+        info!(
+            "💾 Measuring storage every {}s against caps: {}",
+            interval.as_secs(),
+            storage.describe()
+        );
+        spawn_storage_monitor(
+            Arc::clone(&user_rpc.service),
+            Arc::clone(&storage),
+            interval,
+        );
+    }
+
+    // Write batched user activity
+    if let Some(interval) = activity_flush_interval_from_env() {
+        info!("👣 User activity written every {}s", interval.as_secs());
+        spawn_activity_flusher(Arc::clone(&user_rpc.service), interval);
+    }
+
+    // Allow operators to flip read-only mode without an RPC call
+    #[cfg(unix)]
+    jpc_rust::services::read_only::toggle_on_sigusr1(
+        Arc::clone(&user_rpc.read_only),
+        "User Service",
+    )?;
+
+    // Response shape for clients that don't send Accept-Version
+    let api_version = ApiVersion::default_from_env()?;
+    info!(
+        "🏷️ Serving API version {} by default (latest {})",
+        api_version.number(),
+        ApiVersion::LATEST.number()
+    );
+
+    // Timestamp format for clients that don't send Timestamp-Format
+    let timestamp_format = TimestampFormat::default_from_env()?;
+    if timestamp_format != TimestampFormat::Rfc3339 {
+        info!(
+            "🕰️ Serving timestamps as {} by default",
+            timestamp_format.name()
+        );
+    }
+
+    // Only accept traffic signed by the gateway, when a secret is shared
+    let request_signer = RequestSigner::from_env()?.map(Arc::new);
+    if let Some(signer) = &request_signer {
+        info!(
+            "🔏 Requiring gateway signatures (max age {}s)",
+            signer.max_age_secs()
+        );
+    }
+
+    // Per-request db/app time for clients, when enabled
+    let server_timing = ServerTimingLayer::from_env();
+    if server_timing.is_enabled() {
+        info!("⏱️ Server-Timing headers enabled");
+    }
+
+    // Refuse oversized bodies, batches and bulk params up front
+    let payload_limits = PayloadLimits::from_env()?;
+    info!("📦 Payload limits: {}", payload_limits.describe());
+
+    // Refuse work past capacity instead of queueing it, when configured
+    let load_shedding = LoadSheddingLayer::from_env();
+    if let Some((max_in_flight, latency_threshold)) = load_shedding.limits() {
+        match latency_threshold {
+            Some(threshold) => info!(
+                "🛑 Shedding load above {} requests in flight, halved while latency exceeds {}ms",
+                max_in_flight,
+                threshold.as_millis()
+            ),
+            None => info!(
+                "🛑 Shedding load above {} requests in flight",
+                max_in_flight
+            ),
+        }
+    }
 
     // Build the server
-    let server = ServerBuilder::default().build("127.0.0.1:8080").await?;
+    let server = ServerBuilder::default()
+        .max_request_body_size(payload_limits.max_request_bytes)
+        .set_batch_request_config(BatchRequestConfig::Limit(payload_limits.max_batch_len))
+        .set_http_middleware(
+            tower::ServiceBuilder::new()
+                .layer(AvatarUploadLayer::new(avatar_backend.local()))
+                .layer(server_timing)
+                .layer(load_shedding)
+                .layer(PayloadLimitHeaderLayer::new(payload_limits))
+                .layer(RequestSignatureLayer::new(request_signer))
+                .layer(ClientIpLayer)
+                .layer(BearerTokenLayer)
+                .layer(DeadlineHeaderLayer)
+                .layer(ApiVersionHeaderLayer::new(api_version))
+                .layer(TimestampFormatHeaderLayer::new(timestamp_format))
+                .layer(TraceSamplingHeaderLayer)
+                .layer(RequestIdHeaderLayer)
+                .layer(NotificationLayer),
+        )
+        .set_rpc_middleware(
+            RpcServiceBuilder::new()
+                .layer(RequestIdLayer)
+                .layer(PanicRecoveryLayer)
+                .layer(PayloadLimitLayer::new(payload_limits))
+                .layer(DeadlineLayer)
+                .layer(AuthorizationLayer::new(policy))
+                .layer(ApiVersionLayer)
+                .layer(TimestampFormatLayer)
+                .layer(TraceSamplingLayer),
+        )
+        .build("127.0.0.1:8080")
+        .await
+        .map_err(|source| BindFailed {
+            addr: "127.0.0.1:8080".to_string(),
+            source,
+        })?;
 
-    // Register the methods
-    let handle = server.start(user_rpc.into_rpc());
+    // Register the methods under their flat and namespaced names
+    let mut module = user_rpc.into_rpc();
+    register_namespaced_methods(&mut module, USER_METHODS)?;
+    register_method_list(&mut module)?;
+    let handle = server.start(module);
 
     info!("🚀 User Service started on http://127.0.0.1:8080");
     info!("Available methods:");
-    info!("  - create_user(name: String, email: String)");
+    info!("  - create_user(name: String, email: String, phone?: String, dry_run?: bool)");
     info!("  - get_user(id: String)");
+    info!("  - update_user(id: String, name?: String, email?: String, phone?: String, email_verified?: bool, dry_run?: bool)");
+    info!("  - update_user_name(id: String, name: String)");
+    info!("  - delete_user(id: String, dry_run?: bool)");
+    info!("  - request_avatar_upload(user_id: String, content_type: String)");
+    info!("  - confirm_avatar(user_id: String, key: String)");
+    info!("  - get_user_history(user_id: String, as_of?: DateTime)");
+    info!("  - get_name_history(user_id: String) (admins)");
     info!("  - list_users()");
+    info!("  - export_users(offset: usize, limit: usize)");
+    info!("  - get_user_stats(days?: u32)");
+    info!("  - list_inactive_users(days: u32, limit?: usize)");
+    info!("  - record_activity(user_id: String, login?: bool) (notification)");
+    info!("  - record_login_attempt(email: String, success: bool)");
+    info!("  - unlock_user(id: String)");
+    info!("  - rotate_encryption_keys(batch_size: usize)");
+    info!("  - list_fraud_hits(limit?: usize)");
+    info!("  - create_org(name: String, owner_id: String)");
+    info!("  - add_member(org_id: String, user_id: String, role?: owner|admin|member)");
+    info!("  - remove_member(org_id: String, user_id: String)");
+    info!("  - list_org_members(org_id: String)");
+    info!("  - validate_address(address: Address)");
+    info!("  - log_event(event: String, level?: String, fields?: Object) (notification)");
+    info!("  - subscribe_changes(tables?: [String]) (subscription, LIVE_QUERIES=true)");
+    info!("  - set_feature_flag(key: String, enabled: bool, rollout_percent?: u8, tenants?: [String], users?: [String])");
+    info!("  - list_feature_flags()");
+    info!("  - evaluate_feature_flag(key: String, context?: FlagContext)");
+    info!("  - set_read_only(enabled: bool)");
+    info!("  - set_log_sampling(success_percent: u8)");
+    info!("  - query_stats()");
+    info!("  - panic_stats()");
+    info!("  - retention_stats()");
+    info!("  - storage_stats()");
+    info!("  - database_isolation()");
     info!("  - health()");
+    info!("  - rpc.methods()");
+    info!(
+        "Namespaced aliases (e.g. user.create): {}",
+        USER_METHODS.len() + COMMON_METHODS.len()
+    );
 
     // Set up graceful shutdown handling
     let handle_clone = handle.clone();
@@ -150,3 +1520,30 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jpc_rust::middleware::authorization::Denial;
+
+    #[test]
+    fn admin_methods_are_closed_without_a_policy_file() {
+        let policy = AuthorizationPolicy::default().with_admin_defaults(ADMIN_METHODS);
+        for method in [
+            "set_read_only",
+            "admin.read_only.set",
+            "get_name_history",
+            "unlock_user",
+            "user.encryption.rotate_keys",
+            "admin.flags.set",
+        ] {
+            assert_eq!(
+                policy.authorize(method, None),
+                Err(Denial::Unauthenticated),
+                "{}",
+                method
+            );
+        }
+        assert_eq!(policy.authorize("get_user", None), Ok(()));
+    }
+}
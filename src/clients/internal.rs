@@ -0,0 +1,176 @@
+use crate::errors::error_detail::ErrorDetail;
+use crate::middleware::load_shedding::SERVICE_BUSY_CODE;
+use crate::models::admin_model::DependentRecords;
+use crate::models::quantity_model::Quantity;
+use crate::repositories::connection::DATABASE_UNAVAILABLE_CODE;
+use crate::services::startup::SERVICE_STARTING_CODE;
+use hyper::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::core::ClientError;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::rpc_params;
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a call to another service failed. Errors the service sent with a
+/// detail come back as their own variants, so a caller can tell a missing
+/// user from a stock shortage without reading the message.
+#[derive(Error, Debug)]
+pub enum InternalClientError {
+    #[error("User not found with id: {id}")]
+    UserNotFound { id: String },
+
+    #[error("Product not found with id: {id}")]
+    ProductNotFound { id: String },
+
+    #[error("Insufficient stock for product {id}. Available: {available}, Requested: {requested}")]
+    InsufficientStock {
+        id: String,
+        available: Quantity,
+        requested: Quantity,
+    },
+
+    #[error("Too similar to existing product '{similar_to}' ({id}, similarity {similarity})")]
+    SimilarProductExists {
+        id: String,
+        similar_to: String,
+        similarity: f64,
+    },
+
+    #[error("{id} is still referenced by other records")]
+    DeleteBlocked {
+        id: String,
+        dependents: Vec<DependentRecords>,
+    },
+
+    /// The service is starting, lost its database or is shedding load
+    #[error("Service unavailable: {message}")]
+    Unavailable { code: i32, message: String },
+
+    #[error("{message} (code {code})")]
+    Rpc {
+        code: i32,
+        message: String,
+        data: Option<String>,
+    },
+
+    #[error("Invalid bearer token for {url}")]
+    InvalidToken { url: String },
+
+    #[error("Transport error: {0}")]
+    Transport(ClientError),
+}
+
+impl InternalClientError {
+    /// Whether the same call may succeed if sent again later
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            InternalClientError::Unavailable { .. }
+                | InternalClientError::Transport(ClientError::Transport(_))
+                | InternalClientError::Transport(ClientError::RequestTimeout)
+        )
+    }
+}
+
+impl From<ErrorDetail> for InternalClientError {
+    fn from(detail: ErrorDetail) -> Self {
+        match detail {
+            ErrorDetail::UserNotFound { id } => InternalClientError::UserNotFound { id },
+            ErrorDetail::ProductNotFound { id } => InternalClientError::ProductNotFound { id },
+            ErrorDetail::InsufficientStock {
+                id,
+                available,
+                requested,
+            } => InternalClientError::InsufficientStock {
+                id,
+                available,
+                requested,
+            },
+            ErrorDetail::SimilarProductExists {
+                id,
+                similar_to,
+                similarity,
+            } => InternalClientError::SimilarProductExists {
+                id,
+                similar_to,
+                similarity,
+            },
+            ErrorDetail::DeleteBlocked { id, dependents } => {
+                InternalClientError::DeleteBlocked { id, dependents }
+            }
+        }
+    }
+}
+
+impl From<ClientError> for InternalClientError {
+    fn from(err: ClientError) -> Self {
+        let ClientError::Call(error) = err else {
+            return InternalClientError::Transport(err);
+        };
+        if let Some(detail) = error.data().and_then(ErrorDetail::from_data) {
+            return detail.into();
+        }
+        match error.code() {
+            code @ (SERVICE_STARTING_CODE | DATABASE_UNAVAILABLE_CODE | SERVICE_BUSY_CODE) => {
+                InternalClientError::Unavailable {
+                    code,
+                    message: error.message().to_string(),
+                }
+            }
+            code => InternalClientError::Rpc {
+                code,
+                message: error.message().to_string(),
+                data: error.data().map(|data| data.get().to_string()),
+            },
+        }
+    }
+}
+
+/// A JSON-RPC connection to one of the services, presenting
+/// `bearer_token` when set
+#[derive(Debug, Clone)]
+pub struct InternalClient {
+    client: HttpClient,
+}
+
+impl InternalClient {
+    pub fn new(url: &str, bearer_token: Option<&str>) -> Result<Self, InternalClientError> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = bearer_token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|_| {
+                InternalClientError::InvalidToken {
+                    url: url.to_string(),
+                }
+            })?;
+            headers.insert(AUTHORIZATION, value);
+        }
+        let client = HttpClientBuilder::default()
+            .set_headers(headers)
+            .request_timeout(REQUEST_TIMEOUT)
+            .build(url)
+            .map_err(InternalClientError::Transport)?;
+        Ok(Self { client })
+    }
+
+    /// Reads `{PREFIX}_URL` (falling back to `default_url`) and
+    /// `{PREFIX}_BEARER_TOKEN`
+    pub fn from_env(prefix: &str, default_url: &str) -> Result<Self, InternalClientError> {
+        let url =
+            std::env::var(format!("{}_URL", prefix)).unwrap_or_else(|_| default_url.to_string());
+        let token = std::env::var(format!("{}_BEARER_TOKEN", prefix)).ok();
+        Self::new(&url, token.as_deref())
+    }
+
+    /// Calls `method` with `request` as its single parameter
+    pub async fn call<R, T>(&self, method: &str, request: R) -> Result<T, InternalClientError>
+    where
+        R: Serialize + Send,
+        T: DeserializeOwned,
+    {
+        Ok(self.client.request(method, rpc_params![request]).await?)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod internal;
+pub mod product_client;
+pub mod user_client;
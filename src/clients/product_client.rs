@@ -0,0 +1,60 @@
+use crate::clients::internal::{InternalClient, InternalClientError};
+use crate::models::inventory_model::{TransferStockRequest, TransferStockResponse};
+use crate::models::product_model::{GetProductRequest, Product, UpdateProductStockRequest};
+use crate::models::recommendation_model::{CountOrdersByUserRequest, UserOrderCount};
+
+/// Typed calls to the product service for other services
+#[derive(Debug, Clone)]
+pub struct ProductClient {
+    inner: InternalClient,
+}
+
+impl ProductClient {
+    pub fn new(url: &str, bearer_token: Option<&str>) -> Result<Self, InternalClientError> {
+        Ok(Self {
+            inner: InternalClient::new(url, bearer_token)?,
+        })
+    }
+
+    /// Reads `PRODUCT_SERVICE_URL` (default `http://127.0.0.1:8081`) and
+    /// `PRODUCT_SERVICE_BEARER_TOKEN`
+    pub fn from_env() -> Result<Self, InternalClientError> {
+        Ok(Self {
+            inner: InternalClient::from_env("PRODUCT_SERVICE", "http://127.0.0.1:8081")?,
+        })
+    }
+
+    /// Fails with [`InternalClientError::ProductNotFound`] for unknown ids
+    pub async fn get_product(&self, id: &str) -> Result<Product, InternalClientError> {
+        let request = GetProductRequest {
+            id: id.to_string(),
+            locale: None,
+        };
+        self.inner.call("get_product", request).await
+    }
+
+    pub async fn update_product_stock(
+        &self,
+        request: UpdateProductStockRequest,
+    ) -> Result<Product, InternalClientError> {
+        self.inner.call("update_product_stock", request).await
+    }
+
+    /// Fails with [`InternalClientError::InsufficientStock`] when the source
+    /// location holds less than `quantity`
+    pub async fn transfer_stock(
+        &self,
+        request: TransferStockRequest,
+    ) -> Result<TransferStockResponse, InternalClientError> {
+        self.inner.call("transfer_stock", request).await
+    }
+
+    /// Orders recorded for `user_id`, whatever their status
+    pub async fn count_orders_by_user(&self, user_id: &str) -> Result<u64, InternalClientError> {
+        let request = CountOrdersByUserRequest {
+            user_id: user_id.to_string(),
+        };
+        let count: UserOrderCount = self.inner.call("count_orders_by_user", request).await?;
+        Ok(count.count)
+    }
+}
@@ -0,0 +1,31 @@
+use crate::clients::internal::{InternalClient, InternalClientError};
+use crate::models::user_model::{GetUserRequest, User};
+
+/// Typed calls to the user service for other services
+#[derive(Debug, Clone)]
+pub struct UserClient {
+    inner: InternalClient,
+}
+
+impl UserClient {
+    pub fn new(url: &str, bearer_token: Option<&str>) -> Result<Self, InternalClientError> {
+        Ok(Self {
+            inner: InternalClient::new(url, bearer_token)?,
+        })
+    }
+
+    /// Reads `USER_SERVICE_URL` (default `http://127.0.0.1:8080`) and
+    /// `USER_SERVICE_BEARER_TOKEN`
+    pub fn from_env() -> Result<Self, InternalClientError> {
+        Ok(Self {
+            inner: InternalClient::from_env("USER_SERVICE", "http://127.0.0.1:8080")?,
+        })
+    }
+
+    /// Fails with [`InternalClientError::UserNotFound`] for unknown ids
+    pub async fn get_user(&self, id: &str) -> Result<User, InternalClientError> {
+        self.inner
+            .call("get_user", GetUserRequest { id: id.to_string() })
+            .await
+    }
+}
@@ -0,0 +1,151 @@
+use serde::Deserialize;
+use surrealdb::engine::any::{self, Any};
+use surrealdb::opt::auth::Root;
+use surrealdb::Surreal;
+use thiserror::Error;
+
+/// Connections a service keeps to a remote database when `pool_size` is
+/// not set
+const DEFAULT_POOL_SIZE: usize = 4;
+const MAX_POOL_SIZE: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to load configuration: {0}")]
+    Load(#[from] ::config::ConfigError),
+
+    #[error("Invalid [{section}] configuration: {message}")]
+    Invalid { section: String, message: String },
+}
+
+/// SurrealDB connection settings for one service
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatabaseConfig {
+    /// `mem://` for an in-process database, or `ws://` / `wss://` for a server
+    pub endpoint: String,
+    pub namespace: String,
+    pub database: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Connections kept to a `ws://` / `wss://` server, 4 by default.
+    /// `mem://` databases always use one.
+    #[serde(default)]
+    pub pool_size: Option<usize>,
+}
+
+impl DatabaseConfig {
+    /// Settings for the user service (`[user_db]`)
+    pub fn user() -> Result<Self, ConfigError> {
+        Self::load("user_db", "user_service", "users")
+    }
+
+    /// Settings for the product service (`[product_db]`)
+    pub fn product() -> Result<Self, ConfigError> {
+        Self::load("product_db", "product_service", "products")
+    }
+
+    /// Settings for the gateways' shared health state (`[gateway_health_db]`)
+    pub fn gateway_health() -> Result<Self, ConfigError> {
+        Self::load("gateway_health_db", "gateway", "health")
+    }
+
+    /// Loads and validates `[section]` from, in increasing precedence: the
+    /// built-in defaults, `config/default.toml`, `config/<APP_ENV>.toml`
+    /// (`APP_ENV` defaults to `development`) and `APP_<SECTION>__<KEY>`
+    /// environment variables, e.g. `APP_USER_DB__ENDPOINT`.
+    fn load(section: &str, namespace: &str, database: &str) -> Result<Self, ConfigError> {
+        let environment = std::env::var("APP_ENV").unwrap_or_else(|_| "development".to_string());
+
+        let settings = ::config::Config::builder()
+            .set_default(format!("{}.endpoint", section), "mem://")?
+            .set_default(format!("{}.namespace", section), namespace)?
+            .set_default(format!("{}.database", section), database)?
+            .add_source(::config::File::with_name("config/default").required(false))
+            .add_source(
+                ::config::File::with_name(&format!("config/{}", environment)).required(false),
+            )
+            .add_source(::config::Environment::with_prefix("APP").separator("__"))
+            .build()?;
+
+        let config: Self = settings.get(section)?;
+        config.validate(section)?;
+        Ok(config)
+    }
+
+    fn validate(&self, section: &str) -> Result<(), ConfigError> {
+        let invalid = |message: String| ConfigError::Invalid {
+            section: section.to_string(),
+            message,
+        };
+
+        let remote = self.is_remote();
+        if !remote && self.endpoint != "mem://" {
+            return Err(invalid(format!(
+                "endpoint '{}' must be mem://, ws://host:port or wss://host:port",
+                self.endpoint
+            )));
+        }
+
+        for (key, value) in [("namespace", &self.namespace), ("database", &self.database)] {
+            if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(invalid(format!(
+                    "{} '{}' must be non-empty and contain only letters, digits and '_'",
+                    key, value
+                )));
+            }
+        }
+
+        match self.pool_size {
+            Some(size) if size == 0 || size > MAX_POOL_SIZE => {
+                return Err(invalid(format!(
+                    "pool_size {} must be between 1 and {}",
+                    size, MAX_POOL_SIZE
+                )));
+            }
+            Some(size) if size > 1 && !remote => {
+                return Err(invalid(
+                    "pool_size above 1 is only used with ws:// or wss:// endpoints".to_string(),
+                ));
+            }
+            _ => {}
+        }
+
+        match (&self.username, &self.password) {
+            (Some(_), None) | (None, Some(_)) => Err(invalid(
+                "username and password must be set together".to_string(),
+            )),
+            (Some(_), Some(_)) if !remote => Err(invalid(
+                "credentials are only used with ws:// or wss:// endpoints".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// True for `ws://` / `wss://` endpoints, i.e. a separate database server
+    pub fn is_remote(&self) -> bool {
+        self.endpoint.starts_with("ws://") || self.endpoint.starts_with("wss://")
+    }
+
+    /// Connections to open: `pool_size` for a server, one for `mem://`,
+    /// where every connection would be a separate database
+    pub fn pool_size(&self) -> usize {
+        if self.is_remote() {
+            self.pool_size.unwrap_or(DEFAULT_POOL_SIZE)
+        } else {
+            1
+        }
+    }
+
+    /// Connects, signs in when credentials are configured and selects the
+    /// namespace and database
+    pub async fn connect(&self) -> Result<Surreal<Any>, surrealdb::Error> {
+        let db = any::connect(self.endpoint.as_str()).await?;
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            db.signin(Root { username, password }).await?;
+        }
+        db.use_ns(&self.namespace).use_db(&self.database).await?;
+        Ok(db)
+    }
+}
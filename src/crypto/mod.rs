@@ -0,0 +1,3 @@
+pub mod pii;
+pub mod request_signing;
+pub mod page_cursor;
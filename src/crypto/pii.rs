@@ -0,0 +1,455 @@
+use crate::models::user_model::{User, UserChanges, UserForCreation};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+/// Prefix of every encrypted value: `enc:v1:<key id>:<base64(nonce || ciphertext)>`
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const KEY_LEN: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum CryptoError {
+    #[error("Failed to read key file {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid key file {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid key '{id}': {reason}")]
+    InvalidKey { id: String, reason: String },
+
+    #[error("Active key '{id}' is not in the keyring")]
+    MissingActiveKey { id: String },
+
+    #[error("No key '{id}' available to decrypt {field}")]
+    UnknownKey { id: String, field: String },
+
+    #[error("Malformed encrypted value in {field}")]
+    Malformed { field: String },
+
+    #[error("Failed to encrypt {field}")]
+    Encrypt { field: String },
+
+    #[error("Failed to decrypt {field}")]
+    Decrypt { field: String },
+}
+
+/// On-disk keyring. Keys and the index key are base64-encoded 32-byte values.
+#[derive(Deserialize)]
+struct KeyringFile {
+    active_key_id: String,
+    keys: HashMap<String, String>,
+    index_key: String,
+}
+
+/// AES-256-GCM encryption for PII fields with a keyring, so values written
+/// under retired keys stay readable until they are rotated.
+///
+/// Because ciphertexts are randomized, exact-match lookups go through a
+/// keyed blind index (HMAC-SHA256 of the normalized value) instead.
+pub struct PiiCipher {
+    active_key_id: String,
+    keys: HashMap<String, LessSafeKey>,
+    index_key: hmac::Key,
+    rng: SystemRandom,
+}
+
+impl std::fmt::Debug for PiiCipher {
+    // Never print key material
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut key_ids: Vec<_> = self.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("PiiCipher")
+            .field("active_key_id", &self.active_key_id)
+            .field("key_ids", &key_ids)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PiiCipher {
+    pub fn new(
+        active_key_id: &str,
+        keys: &HashMap<String, String>,
+        index_key: &str,
+    ) -> Result<Self, CryptoError> {
+        let keys = keys
+            .iter()
+            .map(|(id, encoded)| {
+                if id.is_empty() || id.contains(':') {
+                    return Err(CryptoError::InvalidKey {
+                        id: id.clone(),
+                        reason: "key ids must be non-empty and must not contain ':'".to_string(),
+                    });
+                }
+                let bytes = decode_key(id, encoded)?;
+                let key =
+                    UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| CryptoError::InvalidKey {
+                        id: id.clone(),
+                        reason: "rejected by AES-256-GCM".to_string(),
+                    })?;
+                Ok((id.clone(), LessSafeKey::new(key)))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        if !keys.contains_key(active_key_id) {
+            return Err(CryptoError::MissingActiveKey {
+                id: active_key_id.to_string(),
+            });
+        }
+
+        let index_key = decode_key("index", index_key)?;
+        Ok(Self {
+            active_key_id: active_key_id.to_string(),
+            keys,
+            index_key: hmac::Key::new(hmac::HMAC_SHA256, &index_key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// Loads the keyring from the JSON file named by `PII_KEYS_FILE`, or from
+    /// `PII_ENCRYPTION_KEYS` (`id:base64key,...`), `PII_ACTIVE_KEY_ID` and
+    /// `PII_INDEX_KEY`. Returns `None` when neither is configured, in which
+    /// case PII is stored in plaintext.
+    pub fn from_env() -> Result<Option<Self>, CryptoError> {
+        if let Ok(path) = std::env::var("PII_KEYS_FILE") {
+            return Self::from_file(path).map(Some);
+        }
+
+        let Ok(encoded_keys) = std::env::var("PII_ENCRYPTION_KEYS") else {
+            return Ok(None);
+        };
+
+        let mut keys = HashMap::new();
+        for entry in encoded_keys
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| CryptoError::InvalidKey {
+                    id: entry.to_string(),
+                    reason: "expected id:base64key".to_string(),
+                })?;
+            keys.insert(id.to_string(), key.to_string());
+        }
+
+        let active_key_id =
+            std::env::var("PII_ACTIVE_KEY_ID").map_err(|_| CryptoError::MissingActiveKey {
+                id: "<PII_ACTIVE_KEY_ID unset>".to_string(),
+            })?;
+        let index_key = std::env::var("PII_INDEX_KEY").map_err(|_| CryptoError::InvalidKey {
+            id: "index".to_string(),
+            reason: "PII_INDEX_KEY is not set".to_string(),
+        })?;
+
+        Self::new(&active_key_id, &keys, &index_key).map(Some)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, CryptoError> {
+        let path_str = path.as_ref().display().to_string();
+        let contents = std::fs::read_to_string(&path).map_err(|source| CryptoError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+        let file: KeyringFile =
+            serde_json::from_str(&contents).map_err(|source| CryptoError::Parse {
+                path: path_str,
+                source,
+            })?;
+
+        Self::new(&file.active_key_id, &file.keys, &file.index_key)
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    /// Prefix shared by every value encrypted under the active key
+    pub fn active_prefix(&self) -> String {
+        format!("{}{}:", ENCRYPTED_PREFIX, self.active_key_id)
+    }
+
+    /// Whether `stored` is already encrypted under the active key
+    pub fn is_current(&self, stored: &str) -> bool {
+        stored.starts_with(&self.active_prefix())
+    }
+
+    /// Encrypts `plaintext` under the active key. `field` is bound as
+    /// associated data, so a ciphertext cannot be moved to another field.
+    pub fn encrypt(&self, field: &str, plaintext: &str) -> Result<String, CryptoError> {
+        let error = || CryptoError::Encrypt {
+            field: field.to_string(),
+        };
+        let key = &self.keys[&self.active_key_id];
+
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce).map_err(|_| error())?;
+
+        let mut in_out = plaintext.as_bytes().to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(field.as_bytes()),
+            &mut in_out,
+        )
+        .map_err(|_| error())?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&in_out);
+        Ok(format!(
+            "{}{}",
+            self.active_prefix(),
+            STANDARD.encode(payload)
+        ))
+    }
+
+    /// Decrypts a stored value. Values without the encryption prefix were
+    /// written before encryption was enabled and are returned unchanged.
+    pub fn decrypt(&self, field: &str, stored: &str) -> Result<String, CryptoError> {
+        let Some(rest) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let malformed = || CryptoError::Malformed {
+            field: field.to_string(),
+        };
+
+        let (key_id, encoded) = rest.split_once(':').ok_or_else(malformed)?;
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| CryptoError::UnknownKey {
+                id: key_id.to_string(),
+                field: field.to_string(),
+            })?;
+
+        let mut payload = STANDARD.decode(encoded).map_err(|_| malformed())?;
+        if payload.len() < NONCE_LEN {
+            return Err(malformed());
+        }
+        let mut ciphertext = payload.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&payload).map_err(|_| malformed())?;
+
+        let plaintext = key
+            .open_in_place(nonce, Aad::from(field.as_bytes()), &mut ciphertext)
+            .map_err(|_| CryptoError::Decrypt {
+                field: field.to_string(),
+            })?;
+        String::from_utf8(plaintext.to_vec()).map_err(|_| malformed())
+    }
+
+    /// Deterministic lookup token for a value; emails compare case-insensitively
+    pub fn blind_index(&self, value: &str) -> String {
+        let normalized = value.trim().to_lowercase();
+        URL_SAFE_NO_PAD.encode(hmac::sign(&self.index_key, normalized.as_bytes()))
+    }
+
+    /// Encrypts the PII of a user about to be written and fills in its
+    /// email blind index
+    pub fn seal_user(&self, user: UserForCreation) -> Result<UserForCreation, CryptoError> {
+        Ok(UserForCreation {
+            email_hash: Some(self.blind_index(&user.email)),
+            email: self.encrypt("email", &user.email)?,
+            phone: user
+                .phone
+                .as_deref()
+                .map(|phone| self.encrypt("phone", phone))
+                .transpose()?,
+            ..user
+        })
+    }
+
+    /// Decrypts the PII of a user read from the database
+    pub fn open_user(&self, user: User) -> Result<User, CryptoError> {
+        Ok(User {
+            email: self.decrypt("email", &user.email)?,
+            phone: user
+                .phone
+                .as_deref()
+                .map(|phone| self.decrypt("phone", phone))
+                .transpose()?,
+            ..user
+        })
+    }
+
+    /// Encrypts the PII in a user event, like [`PiiCipher::seal_user`]. An
+    /// empty phone marks a removal and is kept as is.
+    pub fn seal_changes(&self, changes: UserChanges) -> Result<UserChanges, CryptoError> {
+        Ok(UserChanges {
+            email_hash: changes
+                .email
+                .as_deref()
+                .map(|email| self.blind_index(email)),
+            email: changes
+                .email
+                .as_deref()
+                .map(|email| self.encrypt("email", email))
+                .transpose()?,
+            phone: changes
+                .phone
+                .as_deref()
+                .map(|phone| match phone {
+                    "" => Ok(String::new()),
+                    phone => self.encrypt("phone", phone),
+                })
+                .transpose()?,
+            ..changes
+        })
+    }
+
+    /// Decrypts the PII in a user event read from the database
+    pub fn open_changes(&self, changes: UserChanges) -> Result<UserChanges, CryptoError> {
+        Ok(UserChanges {
+            email: changes
+                .email
+                .as_deref()
+                .map(|email| self.decrypt("email", email))
+                .transpose()?,
+            phone: changes
+                .phone
+                .as_deref()
+                .map(|phone| self.decrypt("phone", phone))
+                .transpose()?,
+            ..changes
+        })
+    }
+}
+
+fn decode_key(id: &str, encoded: &str) -> Result<Vec<u8>, CryptoError> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| CryptoError::InvalidKey {
+            id: id.to_string(),
+            reason: e.to_string(),
+        })?;
+    if bytes.len() != KEY_LEN {
+        return Err(CryptoError::InvalidKey {
+            id: id.to_string(),
+            reason: format!("expected {} bytes, got {}", KEY_LEN, bytes.len()),
+        });
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> String {
+        STANDARD.encode([byte; KEY_LEN])
+    }
+
+    fn cipher(active_key_id: &str, key_ids: &[(&str, u8)]) -> PiiCipher {
+        let keys = key_ids
+            .iter()
+            .map(|(id, byte)| (id.to_string(), key(*byte)))
+            .collect();
+        PiiCipher::new(active_key_id, &keys, &key(0)).unwrap()
+    }
+
+    #[test]
+    fn encrypted_values_round_trip() {
+        let cipher = cipher("k1", &[("k1", 1)]);
+        let cases = ["alice@example.com", "+1 555 0100", "", "ünïcødé"];
+        for plaintext in cases {
+            let stored = cipher.encrypt("email", plaintext).unwrap();
+            assert!(cipher.is_current(&stored), "{}", plaintext);
+            assert_ne!(stored, plaintext);
+            assert_eq!(cipher.decrypt("email", &stored).unwrap(), plaintext);
+        }
+        assert_ne!(
+            cipher.encrypt("email", "alice@example.com").unwrap(),
+            cipher.encrypt("email", "alice@example.com").unwrap()
+        );
+        assert_eq!(cipher.decrypt("email", "legacy").unwrap(), "legacy");
+    }
+
+    #[test]
+    fn rotated_keys_still_decrypt_old_values() {
+        let old = cipher("k1", &[("k1", 1)]);
+        let rotated = cipher("k2", &[("k1", 1), ("k2", 2)]);
+        let retired = cipher("k2", &[("k2", 2)]);
+
+        let stored = old.encrypt("email", "alice@example.com").unwrap();
+        assert!(!rotated.is_current(&stored));
+        assert_eq!(
+            rotated.decrypt("email", &stored).unwrap(),
+            "alice@example.com"
+        );
+        assert!(matches!(
+            retired.decrypt("email", &stored),
+            Err(CryptoError::UnknownKey { id, .. }) if id == "k1"
+        ));
+
+        let rewritten = rotated.encrypt("email", "alice@example.com").unwrap();
+        assert!(rewritten.starts_with("enc:v1:k2:"));
+        assert!(rotated.is_current(&rewritten));
+        assert_eq!(
+            old.blind_index("Alice@Example.com "),
+            rotated.blind_index("alice@example.com")
+        );
+    }
+
+    #[test]
+    fn altered_values_do_not_decrypt() {
+        let cipher = cipher("k1", &[("k1", 1)]);
+        let stored = cipher.encrypt("email", "alice@example.com").unwrap();
+        let (prefix, encoded) = stored.rsplit_once(':').unwrap();
+        let mut payload = STANDARD.decode(encoded).unwrap();
+        *payload.last_mut().unwrap() ^= 1;
+        let flipped = format!("{}:{}", prefix, STANDARD.encode(payload));
+
+        let cases = [
+            ("email", flipped.as_str(), "decrypt"),
+            ("phone", stored.as_str(), "decrypt"),
+            ("email", "enc:v1:k1", "malformed"),
+            ("email", "enc:v1:k1:not base64", "malformed"),
+            ("email", "enc:v1:k1:AAAA", "malformed"),
+        ];
+        for (field, stored, expected) in cases {
+            let error = cipher.decrypt(field, stored).unwrap_err();
+            let kind = match error {
+                CryptoError::Decrypt { .. } => "decrypt",
+                CryptoError::Malformed { .. } => "malformed",
+                _ => "other",
+            };
+            assert_eq!(kind, expected, "{} {}", field, stored);
+        }
+    }
+
+    #[test]
+    fn invalid_keyrings_are_refused() {
+        let keys = |entries: &[(&str, &str)]| -> HashMap<String, String> {
+            entries
+                .iter()
+                .map(|(id, key)| (id.to_string(), key.to_string()))
+                .collect()
+        };
+        let good = key(1);
+        let cases = [
+            ("k2", keys(&[("k1", &good)]), good.clone()),
+            ("k1", keys(&[("k1", "c2hvcnQ=")]), good.clone()),
+            ("a:b", keys(&[("a:b", &good)]), good.clone()),
+            ("k1", keys(&[("k1", &good)]), "c2hvcnQ=".to_string()),
+        ];
+        for (active, keys, index_key) in cases {
+            assert!(
+                PiiCipher::new(active, &keys, &index_key).is_err(),
+                "{} {:?}",
+                active,
+                keys
+            );
+        }
+    }
+}
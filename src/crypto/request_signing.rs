@@ -0,0 +1,405 @@
+use crate::middleware::authorization::CLIENT_AUTHORIZATION_HEADER;
+use crate::middleware::client_ip::FORWARDED_FOR_HEADER;
+use crate::middleware::deadline::DEADLINE_HEADER;
+use crate::middleware::org_context::ORG_HEADER;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use hyper::header::HeaderMap;
+use ring::hmac;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Mutex, PoisonError};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Unix time (seconds) the gateway signed the request at
+pub const SIGNATURE_TIMESTAMP_HEADER: &str = "x-gateway-timestamp";
+/// Random value making every signed request unique
+pub const SIGNATURE_NONCE_HEADER: &str = "x-gateway-nonce";
+/// Base64 HMAC-SHA256 over the timestamp, nonce, method, path, the
+/// [`SIGNED_HEADERS`] and the body
+pub const SIGNATURE_HEADER: &str = "x-gateway-signature";
+
+/// Headers the services make decisions on, covered by the signature in this
+/// order so none can be added, changed or dropped on the way: who is
+/// calling and for which organization, the client address rate limits and
+/// audit entries use, and how long the caller will wait
+pub const SIGNED_HEADERS: &[&str] = &[
+    "authorization",
+    CLIENT_AUTHORIZATION_HEADER,
+    ORG_HEADER,
+    FORWARDED_FOR_HEADER,
+    DEADLINE_HEADER,
+];
+
+const MIN_SECRET_LEN: usize = 32;
+const DEFAULT_MAX_AGE_SECS: i64 = 300;
+/// Span of signature timestamps whose nonces expire together
+const NONCE_BUCKET_SECS: i64 = 10;
+
+#[derive(Error, Debug)]
+pub enum RequestSigningError {
+    #[error("GATEWAY_SIGNING_SECRET must be at least {MIN_SECRET_LEN} bytes")]
+    WeakSecret,
+
+    #[error("Invalid GATEWAY_SIGNING_MAX_AGE_SECS '{0}', expected a positive number")]
+    InvalidMaxAge(String),
+}
+
+/// Why a service refused a request as not coming from the gateway
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureRejection {
+    #[error("missing gateway signature")]
+    Missing,
+
+    #[error("signature timestamp outside the allowed window")]
+    Stale,
+
+    #[error("invalid gateway signature")]
+    Invalid,
+
+    #[error("request already seen")]
+    Replayed,
+}
+
+/// Values of the three signature headers for one request
+#[derive(Debug, Clone)]
+pub struct SignedHeaders {
+    pub timestamp: String,
+    pub nonce: String,
+    pub signature: String,
+}
+
+/// HMAC request signing between the gateway and the services.
+///
+/// The gateway signs every request it sends upstream with a secret shared
+/// with the services. A service accepts a request only with a valid
+/// signature whose timestamp is within the allowed age, and remembers the
+/// nonces it has seen for that long so a captured request cannot be
+/// replayed.
+pub struct RequestSigner {
+    key: hmac::Key,
+    max_age_secs: i64,
+    seen_nonces: Mutex<SeenNonces>,
+}
+
+impl std::fmt::Debug for RequestSigner {
+    // Never print key material
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestSigner")
+            .field("max_age_secs", &self.max_age_secs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RequestSigner {
+    pub fn new(secret: &[u8], max_age_secs: i64) -> Result<Self, RequestSigningError> {
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(RequestSigningError::WeakSecret);
+        }
+        Ok(Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+            max_age_secs,
+            seen_nonces: Mutex::new(SeenNonces::default()),
+        })
+    }
+
+    /// Reads `GATEWAY_SIGNING_SECRET` and `GATEWAY_SIGNING_MAX_AGE_SECS`
+    /// (default 300). `None` when no secret is set: requests are neither
+    /// signed nor checked.
+    pub fn from_env() -> Result<Option<Self>, RequestSigningError> {
+        let Ok(secret) = std::env::var("GATEWAY_SIGNING_SECRET") else {
+            return Ok(None);
+        };
+        let max_age_secs = match std::env::var("GATEWAY_SIGNING_MAX_AGE_SECS") {
+            Ok(value) => value
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .ok_or(RequestSigningError::InvalidMaxAge(value))?,
+            Err(_) => DEFAULT_MAX_AGE_SECS,
+        };
+        Self::new(secret.as_bytes(), max_age_secs).map(Some)
+    }
+
+    pub fn max_age_secs(&self) -> i64 {
+        self.max_age_secs
+    }
+
+    /// Signs a request about to be sent with `headers`, which must already
+    /// hold every [`SIGNED_HEADERS`] entry it will carry; each call gets a
+    /// fresh timestamp and nonce, so retries are signed anew
+    pub fn sign(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> SignedHeaders {
+        let timestamp = Utc::now().timestamp().to_string();
+        let nonce = Uuid::new_v4().simple().to_string();
+        let message = signed_message(&timestamp, &nonce, method, path_and_query, headers, body);
+        let signature = STANDARD.encode(hmac::sign(&self.key, &message).as_ref());
+        SignedHeaders {
+            timestamp,
+            nonce,
+            signature,
+        }
+    }
+
+    /// Checks the signature headers of a received request
+    pub fn verify(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(), SignatureRejection> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(SignatureRejection::Missing)
+        };
+        let timestamp = header(SIGNATURE_TIMESTAMP_HEADER)?;
+        let nonce = header(SIGNATURE_NONCE_HEADER)?;
+        let signature = header(SIGNATURE_HEADER)?;
+
+        let signed_at: i64 = timestamp.parse().map_err(|_| SignatureRejection::Invalid)?;
+        let now = Utc::now().timestamp();
+        if (now - signed_at).abs() > self.max_age_secs {
+            return Err(SignatureRejection::Stale);
+        }
+
+        let tag = STANDARD
+            .decode(signature)
+            .map_err(|_| SignatureRejection::Invalid)?;
+        let message = signed_message(timestamp, nonce, method, path_and_query, headers, body);
+        hmac::verify(&self.key, &message, &tag).map_err(|_| SignatureRejection::Invalid)?;
+
+        // Only valid signatures reach the nonce cache, so it cannot be
+        // flooded by unsigned traffic
+        let fresh = self
+            .seen_nonces
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(nonce, signed_at, now, self.max_age_secs);
+        if !fresh {
+            return Err(SignatureRejection::Replayed);
+        }
+        Ok(())
+    }
+}
+
+/// Nonces of requests still inside the window, grouped by the bucket of
+/// [`NONCE_BUCKET_SECS`] their timestamp falls in. A replay carries the
+/// signed timestamp, so only its own bucket is looked at, and expired
+/// nonces are dropped a bucket at a time instead of checked one by one on
+/// every request.
+#[derive(Debug, Default)]
+struct SeenNonces {
+    buckets: BTreeMap<i64, HashSet<String>>,
+}
+
+impl SeenNonces {
+    /// Records `nonce`, signed at `signed_at`; false when it was already
+    /// seen. Buckets whose newest timestamp is older than `max_age_secs`
+    /// at `now` are dropped first.
+    fn insert(&mut self, nonce: &str, signed_at: i64, now: i64, max_age_secs: i64) -> bool {
+        while let Some(oldest) = self.buckets.first_entry() {
+            let newest_timestamp = (oldest.key() + 1) * NONCE_BUCKET_SECS - 1;
+            if now - newest_timestamp <= max_age_secs {
+                break;
+            }
+            oldest.remove();
+        }
+        self.buckets
+            .entry(signed_at.div_euclid(NONCE_BUCKET_SECS))
+            .or_default()
+            .insert(nonce.to_string())
+    }
+}
+
+/// The signed bytes. Each of the [`SIGNED_HEADERS`] is a `name:value` line,
+/// repeated values joined with `,`, or a bare `name` line when absent, so a
+/// missing header never matches an empty one.
+fn signed_message(
+    timestamp: &str,
+    nonce: &str,
+    method: &str,
+    path_and_query: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Vec<u8> {
+    let mut message = format!("{timestamp}\n{nonce}\n{method}\n{path_and_query}\n").into_bytes();
+    for name in SIGNED_HEADERS {
+        message.extend_from_slice(name.as_bytes());
+        let values: Vec<&[u8]> = headers
+            .get_all(*name)
+            .iter()
+            .map(|value| value.as_bytes())
+            .collect();
+        if !values.is_empty() {
+            message.push(b':');
+            message.extend_from_slice(&values.join(&b","[..]));
+        }
+        message.push(b'\n');
+    }
+    message.extend_from_slice(body);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn signed(signer: &RequestSigner, body: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer service"));
+        let signed = signer.sign("POST", "/", &headers, body);
+        for (name, value) in [
+            (SIGNATURE_TIMESTAMP_HEADER, signed.timestamp),
+            (SIGNATURE_NONCE_HEADER, signed.nonce),
+            (SIGNATURE_HEADER, signed.signature),
+        ] {
+            headers.insert(name, HeaderValue::from_str(&value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn signed_requests_verify_once() {
+        let signer = RequestSigner::new(SECRET, 300).unwrap();
+        let headers = signed(&signer, b"{}");
+        assert_eq!(signer.verify("POST", "/", &headers, b"{}"), Ok(()));
+        assert_eq!(
+            signer.verify("POST", "/", &headers, b"{}"),
+            Err(SignatureRejection::Replayed)
+        );
+    }
+
+    /// Changes one part of a signed request: its headers, body or path
+    type Tamper = fn(&mut HeaderMap, &mut Vec<u8>, &mut &'static str);
+
+    #[test]
+    fn altered_requests_are_rejected() {
+        let signer = RequestSigner::new(SECRET, 300).unwrap();
+        let cases: [(&str, Tamper, SignatureRejection); 10] = [
+            (
+                "body",
+                |_, body, _| body.push(b' '),
+                SignatureRejection::Invalid,
+            ),
+            (
+                "path",
+                |_, _, path| *path = "/admin",
+                SignatureRejection::Invalid,
+            ),
+            (
+                "signed header",
+                |headers, _, _| {
+                    headers.insert("authorization", HeaderValue::from_static("Bearer admin"));
+                },
+                SignatureRejection::Invalid,
+            ),
+            (
+                "added header",
+                |headers, _, _| {
+                    headers.insert(ORG_HEADER, HeaderValue::from_static("other-org"));
+                },
+                SignatureRejection::Invalid,
+            ),
+            (
+                "forwarded for",
+                |headers, _, _| {
+                    headers.insert(FORWARDED_FOR_HEADER, HeaderValue::from_static("10.0.0.1"));
+                },
+                SignatureRejection::Invalid,
+            ),
+            (
+                "deadline",
+                |headers, _, _| {
+                    headers.insert(DEADLINE_HEADER, HeaderValue::from_static("600000"));
+                },
+                SignatureRejection::Invalid,
+            ),
+            (
+                "dropped header",
+                |headers, _, _| {
+                    headers.remove("authorization");
+                },
+                SignatureRejection::Invalid,
+            ),
+            (
+                "garbled signature",
+                |headers, _, _| {
+                    headers.insert(SIGNATURE_HEADER, HeaderValue::from_static("not base64!"));
+                },
+                SignatureRejection::Invalid,
+            ),
+            (
+                "missing signature",
+                |headers, _, _| {
+                    headers.remove(SIGNATURE_HEADER);
+                },
+                SignatureRejection::Missing,
+            ),
+            (
+                "stale timestamp",
+                |headers, _, _| {
+                    let stale = (Utc::now().timestamp() - 301).to_string();
+                    headers.insert(
+                        SIGNATURE_TIMESTAMP_HEADER,
+                        HeaderValue::from_str(&stale).unwrap(),
+                    );
+                },
+                SignatureRejection::Stale,
+            ),
+        ];
+        for (name, tamper, expected) in cases {
+            let mut headers = signed(&signer, b"{}");
+            let mut body = b"{}".to_vec();
+            let mut path = "/";
+            tamper(&mut headers, &mut body, &mut path);
+            assert_eq!(
+                signer.verify("POST", path, &headers, &body),
+                Err(expected),
+                "{}",
+                name
+            );
+        }
+
+        let other = RequestSigner::new(&[b'x'; 32], 300).unwrap();
+        assert_eq!(
+            signer.verify("POST", "/", &signed(&other, b"{}"), b"{}"),
+            Err(SignatureRejection::Invalid)
+        );
+    }
+
+    #[test]
+    fn nonces_expire_a_bucket_at_a_time() {
+        let mut seen = SeenNonces::default();
+        assert!(seen.insert("a", 1_000, 1_000, 300));
+        assert!(seen.insert("b", 1_005, 1_005, 300));
+        assert!(!seen.insert("a", 1_000, 1_300, 300));
+        assert_eq!(seen.buckets.len(), 1);
+
+        // Both nonces were signed in the bucket ending at 1009, which
+        // leaves the window at 1310
+        assert!(seen.insert("c", 1_300, 1_310, 300));
+        assert_eq!(seen.buckets.len(), 1);
+        assert!(seen.insert("a", 1_000, 1_310, 300));
+    }
+
+    #[test]
+    fn short_secrets_are_refused() {
+        assert!(matches!(
+            RequestSigner::new(&SECRET[..31], 300),
+            Err(RequestSigningError::WeakSecret)
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::models::quantity_model::Quantity;
+use crate::models::admin_model::DependentRecords;
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value};
+
+/// The errors callers are expected to branch on, sent as the error's data
+/// next to the message. Other errors carry the message alone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ErrorDetail {
+    UserNotFound {
+        id: String,
+    },
+    ProductNotFound {
+        id: String,
+    },
+    InsufficientStock {
+        id: String,
+        available: Quantity,
+        requested: Quantity,
+    },
+    SimilarProductExists {
+        id: String,
+        similar_to: String,
+        similarity: f64,
+    },
+    /// Records refer to the record and their policy is to block deletes
+    DeleteBlocked {
+        id: String,
+        dependents: Vec<DependentRecords>,
+    },
+}
+
+impl ErrorDetail {
+    /// `{"kind": ..., <fields>, "message": ...}`
+    pub fn to_data(&self, message: String) -> Value {
+        let mut data = serde_json::to_value(self).unwrap_or(Value::Null);
+        match &mut data {
+            Value::Object(fields) => {
+                fields.insert("message".to_string(), Value::String(message));
+                data
+            }
+            _ => Value::String(message),
+        }
+    }
+
+    /// Reads the detail back from error data; `None` for a plain message
+    pub fn from_data(data: &RawValue) -> Option<Self> {
+        serde_json::from_str(data.get()).ok()
+    }
+}
@@ -0,0 +1,26 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FeatureFlagError {
+    #[error("Database error: {0}")]
+    Database(#[from] surrealdb::Error),
+
+    #[error("{0}")]
+    Connection(#[from] crate::repositories::connection::ConnectionError),
+
+    #[error("Feature flag not found: {key}")]
+    NotFound { key: String },
+
+    #[error("Validation error: {message}")]
+    Validation { message: String },
+}
+
+impl From<FeatureFlagError> for jsonrpsee::types::ErrorCode {
+    fn from(err: FeatureFlagError) -> Self {
+        match err {
+            FeatureFlagError::NotFound { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            FeatureFlagError::Validation { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            _ => jsonrpsee::types::ErrorCode::InternalError,
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod user_error;
 pub mod product_error;
+pub mod feature_flag_error;
+pub mod error_detail;
@@ -1,9 +1,14 @@
+use crate::errors::error_detail::ErrorDetail;
+use serde_json::Value;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ProductServiceError {
     #[error("Database error: {0}")]
-    Database(#[from] surrealdb::Error),
+    Database(#[from] Box<surrealdb::Error>),
+    
+    #[error("{0}")]
+    Connection(#[from] crate::repositories::connection::ConnectionError),
     
     #[error("Product not found with id: {id}")]
     ProductNotFound { id: String },
@@ -14,24 +19,131 @@ pub enum ProductServiceError {
     #[error("Product already exists with name: {name}")]
     ProductAlreadyExists { name: String },
     
+    #[error("Product already exists with SKU: {sku}")]
+    SkuAlreadyExists { sku: String },
+    
     #[error("Insufficient stock for product {id}. Available: {available}, Requested: {requested}")]
-    InsufficientStock { id: String, available: i32, requested: i32 },
+    InsufficientStock { id: String, available: crate::models::quantity_model::Quantity, requested: crate::models::quantity_model::Quantity },
+    
+    #[error("Product '{name}' is too similar to existing product '{similar_to}' ({id}, similarity {similarity})")]
+    SimilarProductExists { name: String, similar_to: String, id: String, similarity: f64 },
+    
+    #[error("Location not found: {code}")]
+    LocationNotFound { code: String },
+    
+    #[error("Location already exists with code: {code}")]
+    LocationAlreadyExists { code: String },
+    
+    #[error("Coupon not found: {code}")]
+    CouponNotFound { code: String },
+    
+    #[error("Coupon already exists with code: {code}")]
+    CouponAlreadyExists { code: String },
+    
+    #[error("Coupon {code} cannot be applied: {reason}")]
+    CouponRejected { code: String, reason: String },
+    
+    #[error("Order not found: {id}")]
+    OrderNotFound { id: String },
+    
+    #[error("Order already recorded: {id}")]
+    OrderAlreadyRecorded { id: String },
+    
+    #[error("Order {id} is {status} and cannot become {to}")]
+    InvalidOrderTransition { id: String, status: crate::models::recommendation_model::OrderStatus, to: crate::models::recommendation_model::OrderStatus },
+    
+    #[error("Product {id} has {orders} orders waiting to ship or shipped and cannot be restricted")]
+    ProductHasOpenOrders { id: String, orders: usize },
+    
+    #[error("Return not found with id: {id}")]
+    ReturnNotFound { id: String },
+    
+    #[error("Return {id} is {status} and cannot become {to}")]
+    InvalidReturnTransition { id: String, status: crate::models::return_model::ReturnStatus, to: crate::models::return_model::ReturnStatus },
+    
+    #[error("Attribute not defined: {key}")]
+    AttributeNotDefined { key: String },
+    
+    #[error("Attribute already defined: {key}")]
+    AttributeAlreadyDefined { key: String },
+    
+    #[error("Job not found with id: {id}")]
+    JobNotFound { id: String },
     
     #[error("Validation error: {message}")]
     Validation { message: String },
     
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(#[from] crate::crypto::page_cursor::CursorRejection),
+    
+    #[error("Payload too large: {what} has {len} items, at most {max} are accepted")]
+    PayloadTooLarge { what: String, len: usize, max: usize },
+    
+    #[error("Service is in read-only mode")]
+    ServiceReadOnly,
+    
+    #[error(transparent)]
+    StorageFull(#[from] crate::services::storage_caps::StorageFull),
+    
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 }
 
+/// Boxed, as the database error would otherwise make every `Result`
+/// carrying this error large
+impl From<surrealdb::Error> for ProductServiceError {
+    fn from(err: surrealdb::Error) -> Self {
+        ProductServiceError::Database(Box::new(err))
+    }
+}
+
+impl ProductServiceError {
+    /// Data for the RPC error: the message, with the detail for errors
+    /// callers branch on
+    pub fn error_data(&self) -> Value {
+        match self {
+            ProductServiceError::ProductNotFound { id } => {
+                ErrorDetail::ProductNotFound { id: id.clone() }.to_data(self.to_string())
+            }
+            ProductServiceError::InsufficientStock { id, available, requested } => {
+                ErrorDetail::InsufficientStock { id: id.clone(), available: *available, requested: *requested }
+                    .to_data(self.to_string())
+            }
+            ProductServiceError::SimilarProductExists { similar_to, id, similarity, .. } => {
+                ErrorDetail::SimilarProductExists { id: id.clone(), similar_to: similar_to.clone(), similarity: *similarity }
+                    .to_data(self.to_string())
+            }
+            _ => Value::String(self.to_string()),
+        }
+    }
+}
+
 impl From<ProductServiceError> for jsonrpsee::types::ErrorCode {
     fn from(err: ProductServiceError) -> Self {
         match err {
             ProductServiceError::ProductNotFound { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
             ProductServiceError::InvalidPrice { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
             ProductServiceError::ProductAlreadyExists { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::SkuAlreadyExists { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
             ProductServiceError::InsufficientStock { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::SimilarProductExists { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::LocationNotFound { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::LocationAlreadyExists { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::CouponNotFound { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::CouponAlreadyExists { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::CouponRejected { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::OrderNotFound { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::OrderAlreadyRecorded { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::InvalidOrderTransition { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::ProductHasOpenOrders { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::ReturnNotFound { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::InvalidReturnTransition { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::AttributeNotDefined { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::AttributeAlreadyDefined { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::JobNotFound { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
             ProductServiceError::Validation { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::PayloadTooLarge { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            ProductServiceError::InvalidCursor(_) => jsonrpsee::types::ErrorCode::InvalidParams,
             _ => jsonrpsee::types::ErrorCode::InternalError,
         }
     }
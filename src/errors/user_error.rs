@@ -1,35 +1,130 @@
+use crate::errors::error_detail::ErrorDetail;
+use crate::models::admin_model::DependentRecords;
+use crate::services::signup_rules::{SIGNUP_RATE_LIMITED_CODE, SIGNUP_REJECTED_CODE};
+use serde_json::Value;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum UserServiceError {
     #[error("Database error: {0}")]
-    Database(#[from] surrealdb::Error),
+    Database(#[from] Box<surrealdb::Error>),
+
+    #[error("{0}")]
+    Connection(#[from] crate::repositories::connection::ConnectionError),
 
     #[error("User not found with id: {id}")]
     UserNotFound { id: String },
 
+    #[error("Organization not found with id: {id}")]
+    OrganizationNotFound { id: String },
+
+    #[error("User {user_id} is not a member of organization {org_id}")]
+    MembershipNotFound { org_id: String, user_id: String },
+
     #[error("Invalid email format: {email}")]
     InvalidEmail { email: String },
 
     #[error("User already exists with email: {email}")]
     UserAlreadyExists { email: String },
 
+    #[error("User {id} was modified concurrently (version {version} already exists); retry")]
+    VersionConflict { id: String, version: u64 },
+
     #[error("Validation error: {message}")]
     Validation { message: String },
 
+    #[error("User {id} is still referenced by {}", describe_dependents(.dependents))]
+    DeleteBlocked {
+        id: String,
+        dependents: Vec<DependentRecords>,
+    },
+
+    #[error("Cannot check the {dependent} of user {id}: {source}")]
+    DependentCheck {
+        id: String,
+        dependent: &'static str,
+        source: crate::clients::internal::InternalClientError,
+    },
+
+    #[error("Too many signups ({rule}); retry in {retry_after_secs}s")]
+    SignupRateLimited {
+        rule: &'static str,
+        retry_after_secs: u64,
+    },
+
+    #[error("Signup rejected ({rule}): {reason}")]
+    SignupRejected { rule: &'static str, reason: String },
+
+    #[error("Service is in read-only mode")]
+    ServiceReadOnly,
+
+    #[error(transparent)]
+    StorageFull(#[from] crate::services::storage_caps::StorageFull),
+
+    #[error("Encryption error: {0}")]
+    Encryption(#[from] crate::crypto::pii::CryptoError),
+
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 }
 
+/// Boxed, as the database error would otherwise make every `Result`
+/// carrying this error large
+impl From<surrealdb::Error> for UserServiceError {
+    fn from(err: surrealdb::Error) -> Self {
+        UserServiceError::Database(Box::new(err))
+    }
+}
+
+impl UserServiceError {
+    /// Data for the RPC error: the message, with the detail for errors
+    /// callers branch on
+    pub fn error_data(&self) -> Value {
+        match self {
+            UserServiceError::UserNotFound { id } => {
+                ErrorDetail::UserNotFound { id: id.clone() }.to_data(self.to_string())
+            }
+            UserServiceError::DeleteBlocked { id, dependents } => ErrorDetail::DeleteBlocked {
+                id: id.clone(),
+                dependents: dependents.clone(),
+            }
+            .to_data(self.to_string()),
+            _ => Value::String(self.to_string()),
+        }
+    }
+}
+
+/// `2 org_memberships`, for error messages
+fn describe_dependents(dependents: &[DependentRecords]) -> String {
+    dependents
+        .iter()
+        .map(|records| format!("{} {}", records.count, records.dependent))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl From<UserServiceError> for jsonrpsee::types::ErrorCode {
     fn from(err: UserServiceError) -> Self {
         match err {
             UserServiceError::UserNotFound { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            UserServiceError::OrganizationNotFound { .. } => {
+                jsonrpsee::types::ErrorCode::InvalidParams
+            }
+            UserServiceError::MembershipNotFound { .. } => {
+                jsonrpsee::types::ErrorCode::InvalidParams
+            }
             UserServiceError::InvalidEmail { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
             UserServiceError::UserAlreadyExists { .. } => {
                 jsonrpsee::types::ErrorCode::InvalidParams
             }
             UserServiceError::Validation { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            UserServiceError::DeleteBlocked { .. } => jsonrpsee::types::ErrorCode::InvalidParams,
+            UserServiceError::SignupRateLimited { .. } => {
+                jsonrpsee::types::ErrorCode::ServerError(SIGNUP_RATE_LIMITED_CODE)
+            }
+            UserServiceError::SignupRejected { .. } => {
+                jsonrpsee::types::ErrorCode::ServerError(SIGNUP_REJECTED_CODE)
+            }
             _ => jsonrpsee::types::ErrorCode::InternalError,
         }
     }
@@ -0,0 +1,459 @@
+use crate::gateway::upstream::{ProbeError, UpstreamConnection};
+use futures::future::join_all;
+use hyper::header::{HeaderMap, AUTHORIZATION};
+use ring::digest::{digest, SHA256};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Points each instance gets on the hash ring. More points spread keys
+/// more evenly, and move fewer of them when an instance joins or leaves.
+const VIRTUAL_NODES: usize = 100;
+
+#[derive(Error, Debug)]
+pub enum BalancingError {
+    #[error("Invalid GATEWAY_BALANCE '{0}', expected round_robin or consistent_hash")]
+    InvalidStrategy(String),
+
+    #[error("Invalid GATEWAY_HASH_KEY entry '{0}', expected user, api_key or path")]
+    InvalidHashKey(String),
+
+    #[error("Invalid {var} entry '{entry}', expected host:port")]
+    InvalidInstance { var: String, entry: String },
+}
+
+/// How requests are spread over a service's instances
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceStrategy {
+    /// Each request goes to the next instance
+    RoundRobin,
+    /// Requests with the same key go to the same instance, so its local
+    /// caches stay warm for the entities that key stands for
+    ConsistentHash,
+}
+
+/// What a request is hashed by under [`BalanceStrategy::ConsistentHash`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashKey {
+    /// The `user_id` param, or `id`, of a single JSON-RPC call
+    User,
+    /// The `Authorization: Bearer` token
+    ApiKey,
+    /// The request path
+    Path,
+}
+
+impl HashKey {
+    fn name(&self) -> &'static str {
+        match self {
+            HashKey::User => "user",
+            HashKey::ApiKey => "api_key",
+            HashKey::Path => "path",
+        }
+    }
+
+    /// The request's value for this key, if it has one
+    fn value(&self, headers: &HeaderMap, path: &str, body: &[u8]) -> Option<String> {
+        match self {
+            HashKey::User => user_param(body).map(|id| format!("user:{}", id)),
+            HashKey::ApiKey => headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(|token| format!("api_key:{}", token.trim())),
+            HashKey::Path => Some(format!("path:{}", path)),
+        }
+    }
+}
+
+/// `user_id`, else `id`, from the params of a single call, given by name
+/// or as the request object the services take as their only param
+fn user_param(body: &[u8]) -> Option<String> {
+    let call: Value = serde_json::from_slice(body).ok()?;
+    let params = match call.get("params")? {
+        Value::Array(params) => params.first()?,
+        params => params,
+    };
+    ["user_id", "id"]
+        .iter()
+        .find_map(|name| params.get(name).and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+#[derive(Debug, Clone)]
+pub struct BalancingConfig {
+    pub strategy: BalanceStrategy,
+    /// Tried in order; the first one a request has is its key
+    pub hash_keys: Vec<HashKey>,
+}
+
+impl Default for BalancingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: BalanceStrategy::RoundRobin,
+            hash_keys: vec![HashKey::User, HashKey::ApiKey, HashKey::Path],
+        }
+    }
+}
+
+impl BalancingConfig {
+    /// Reads `GATEWAY_BALANCE` (`round_robin`, the default, or
+    /// `consistent_hash`) and `GATEWAY_HASH_KEY`, the keys to hash by in
+    /// order of preference (default `user,api_key,path`)
+    pub fn from_env() -> Result<Self, BalancingError> {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var("GATEWAY_BALANCE") {
+            config.strategy = match value.trim() {
+                "round_robin" | "" => BalanceStrategy::RoundRobin,
+                "consistent_hash" => BalanceStrategy::ConsistentHash,
+                _ => return Err(BalancingError::InvalidStrategy(value)),
+            };
+        }
+        if let Ok(value) = std::env::var("GATEWAY_HASH_KEY") {
+            let keys = value
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| match key {
+                    "user" => Ok(HashKey::User),
+                    "api_key" => Ok(HashKey::ApiKey),
+                    "path" => Ok(HashKey::Path),
+                    _ => Err(BalancingError::InvalidHashKey(key.to_string())),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if !keys.is_empty() {
+                config.hash_keys = keys;
+            }
+        }
+        Ok(config)
+    }
+
+    /// What the request is balanced by: `None` under round-robin, or when
+    /// it has none of the hash keys
+    pub fn request_key(&self, headers: &HeaderMap, path: &str, body: &[u8]) -> Option<String> {
+        match self.strategy {
+            BalanceStrategy::RoundRobin => None,
+            BalanceStrategy::ConsistentHash => self
+                .hash_keys
+                .iter()
+                .find_map(|key| key.value(headers, path, body)),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.strategy {
+            BalanceStrategy::RoundRobin => "round robin".to_string(),
+            BalanceStrategy::ConsistentHash => format!(
+                "consistent hash on {}",
+                self.hash_keys
+                    .iter()
+                    .map(HashKey::name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// The instances of one upstream service and the hash ring over them
+#[derive(Debug)]
+pub struct UpstreamPool {
+    instances: Vec<UpstreamConnection>,
+    /// (point, instance index), sorted by point
+    ring: Vec<(u64, usize)>,
+    next: AtomicUsize,
+}
+
+impl UpstreamPool {
+    /// The instances listed in `{PREFIX}_INSTANCES` as comma-separated
+    /// `host:port`, each reached like `upstream`; just `upstream` when
+    /// unset
+    pub fn from_env(upstream: UpstreamConnection, prefix: &str) -> Result<Self, BalancingError> {
+        let var = format!("{}_INSTANCES", prefix);
+        let Ok(list) = std::env::var(&var) else {
+            return Ok(Self::new(vec![upstream]));
+        };
+
+        let mut instances = Vec::new();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let invalid = || BalancingError::InvalidInstance {
+                var: var.clone(),
+                entry: entry.to_string(),
+            };
+            let (host, port) = entry.rsplit_once(':').ok_or_else(invalid)?;
+            let port = port.parse().map_err(|_| invalid())?;
+            if host.is_empty() {
+                return Err(invalid());
+            }
+            instances.push(upstream.instance(host, port));
+        }
+        if instances.is_empty() {
+            instances.push(upstream);
+        }
+        Ok(Self::new(instances))
+    }
+
+    /// Panics without instances
+    pub fn new(instances: Vec<UpstreamConnection>) -> Self {
+        assert!(!instances.is_empty(), "an upstream needs an instance");
+        let mut ring: Vec<(u64, usize)> = instances
+            .iter()
+            .enumerate()
+            .flat_map(|(index, instance)| {
+                let base_url = instance.base_url();
+                (0..VIRTUAL_NODES)
+                    .map(move |node| (ring_point(&format!("{}#{}", base_url, node)), index))
+            })
+            .collect();
+        ring.sort_unstable();
+        Self {
+            instances,
+            ring,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The first instance listed. Instances share one set of metrics, so
+    /// it also stands for the service in metrics and debug output.
+    pub fn primary(&self) -> &UpstreamConnection {
+        &self.instances[0]
+    }
+
+    pub fn instances(&self) -> &[UpstreamConnection] {
+        &self.instances
+    }
+
+    pub fn base_urls(&self) -> Vec<String> {
+        self.instances
+            .iter()
+            .map(UpstreamConnection::base_url)
+            .collect()
+    }
+
+    /// The instance for one attempt at a request. Without a key, the next
+    /// instance in turn. With one, the instance owning the key on the
+    /// ring, and on later attempts the instances following it, so retries
+    /// go elsewhere while the key's other requests stay put. Instances that
+    /// failed their last health probe are skipped in that order while
+    /// another one is up, as are those draining or asking the gateway to
+    /// back off while another one can take the request.
+    pub fn pick(&self, key: Option<&str>, attempt: u32) -> &UpstreamConnection {
+        &self.instances[self.pick_order(key, attempt)[0]]
+    }
+
+    /// Every instance once, in the order [`UpstreamPool::pick`] prefers
+    /// them for one attempt at a request
+    pub fn pick_order(&self, key: Option<&str>, attempt: u32) -> Vec<usize> {
+        if self.instances.len() == 1 {
+            return vec![0];
+        }
+
+        let mut order = match key {
+            Some(key) => {
+                let mut order = self.ring_order(key);
+                let shift = (attempt.saturating_sub(1) as usize) % order.len();
+                order.rotate_left(shift);
+                order
+            }
+            None => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..self.instances.len())
+                    .map(|offset| (start + offset) % self.instances.len())
+                    .collect()
+            }
+        };
+        // Stable, so each group keeps the ring or round-robin order
+        order.sort_by_key(|index| {
+            let instance = &self.instances[*index];
+            let unavailable = instance.is_draining() || instance.busy_remaining().is_some();
+            (instance.is_down(), unavailable)
+        });
+        order
+    }
+
+    /// Every instance once, in ring order from the point `key` hashes to
+    fn ring_order(&self, key: &str) -> Vec<usize> {
+        let point = ring_point(key);
+        let start = self.ring.partition_point(|(p, _)| *p < point);
+        let mut order = Vec::with_capacity(self.instances.len());
+        for (_, index) in self.ring[start..].iter().chain(&self.ring[..start]) {
+            if !order.contains(index) {
+                order.push(*index);
+                if order.len() == self.instances.len() {
+                    break;
+                }
+            }
+        }
+        order
+    }
+
+    /// Probes every instance at once and records which are down; the
+    /// service is up while any of them is
+    pub async fn probe_health(&self, limit: Duration) -> Result<(), ProbeError> {
+        let results = join_all(
+            self.instances
+                .iter()
+                .map(|instance| instance.probe_health(limit)),
+        )
+        .await;
+
+        let mut last_error = None;
+        for (instance, result) in self.instances.iter().zip(results) {
+            instance.set_down(result.is_err());
+            if let Err(err) = result {
+                last_error = Some(err);
+            }
+        }
+        match last_error {
+            Some(err) if self.instances.iter().all(UpstreamConnection::is_down) => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// The instance whose base URL, or `host:port`, is `address`
+    pub fn find(&self, address: &str) -> Option<&UpstreamConnection> {
+        self.instances.iter().find(|instance| {
+            instance.base_url() == address
+                || format!("{}:{}", instance.host, instance.port) == address
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        match self.instances.len() {
+            1 => self.primary().base_url(),
+            _ => self.base_urls().join(", "),
+        }
+    }
+}
+
+/// Position on the ring. A digest rather than `std`'s hasher, so every
+/// gateway replica puts a key on the same instance.
+fn ring_point(text: &str) -> u64 {
+    let hash = digest(&SHA256, text.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.as_ref()[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::upstream::UpstreamCredentials;
+
+    fn pool(ports: &[u16]) -> UpstreamPool {
+        let upstream =
+            UpstreamConnection::new("10.0.0.1", ports[0], UpstreamCredentials::default()).unwrap();
+        UpstreamPool::new(
+            ports
+                .iter()
+                .map(|port| upstream.instance("10.0.0.1", *port))
+                .collect(),
+        )
+    }
+
+    fn owner(pool: &UpstreamPool, key: &str) -> u16 {
+        pool.pick(Some(key), 1).port
+    }
+
+    fn keys() -> Vec<String> {
+        (0..1000).map(|n| format!("user:{}", n)).collect()
+    }
+
+    #[test]
+    fn keys_stay_on_their_instance() {
+        let cases = [
+            ("same list", vec![8081, 8082, 8083]),
+            ("reordered list", vec![8083, 8081, 8082]),
+        ];
+        let reference = pool(&[8081, 8082, 8083]);
+        for (name, ports) in cases {
+            let other = pool(&ports);
+            for key in keys() {
+                assert_eq!(
+                    owner(&other, &key),
+                    owner(&reference, &key),
+                    "{}: {}",
+                    name,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn membership_changes_only_move_the_changed_instances_keys() {
+        let before = pool(&[8081, 8082, 8083]);
+        let cases = [
+            ("instance added", vec![8081, 8082, 8083, 8084], None),
+            ("instance removed", vec![8081, 8083], Some(8082)),
+        ];
+        for (name, ports, removed) in cases {
+            let after = pool(&ports);
+            let mut moved = 0;
+            for key in keys() {
+                let (old, new) = (owner(&before, &key), owner(&after, &key));
+                if old != new {
+                    moved += 1;
+                    // Keys only leave the removed instance, or move to the added one
+                    assert!(
+                        Some(old) == removed || !before.instances().iter().any(|i| i.port == new),
+                        "{}: {} moved from {} to {}",
+                        name,
+                        key,
+                        old,
+                        new
+                    );
+                }
+            }
+            assert!(
+                moved > 0 && moved < 600,
+                "{}: {} of 1000 keys moved",
+                name,
+                moved
+            );
+        }
+    }
+
+    #[test]
+    fn retries_and_down_instances_follow_the_ring() {
+        let pool = pool(&[8081, 8082, 8083]);
+        let key = "user:42";
+        let order = pool.pick_order(Some(key), 1);
+        assert_eq!(order.len(), 3);
+        for attempt in 1..=3u32 {
+            let expected = order[(attempt as usize - 1) % 3];
+            assert_eq!(
+                pool.pick(Some(key), attempt).port,
+                pool.instances()[expected].port
+            );
+        }
+
+        // The key's owner is down: its requests go to the next instance
+        pool.instances()[order[0]].set_down(true);
+        assert_eq!(
+            pool.pick(Some(key), 1).port,
+            pool.instances()[order[1]].port
+        );
+        // Down instances are only used when every instance is down
+        pool.instances()[order[1]].set_down(true);
+        pool.instances()[order[2]].set_down(true);
+        assert_eq!(
+            pool.pick(Some(key), 1).port,
+            pool.instances()[order[0]].port
+        );
+    }
+
+    #[test]
+    fn round_robin_skips_down_instances() {
+        let pool = pool(&[8081, 8082, 8083]);
+        pool.instances()[1].set_down(true);
+        let picked: Vec<u16> = (0..6).map(|_| pool.pick(None, 1).port).collect();
+        assert!(!picked.contains(&8082), "{:?}", picked);
+        assert!(
+            picked.contains(&8081) && picked.contains(&8083),
+            "{:?}",
+            picked
+        );
+    }
+}
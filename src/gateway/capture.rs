@@ -0,0 +1,291 @@
+use crate::crypto::request_signing::{
+    SIGNATURE_HEADER, SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+};
+use crate::gateway::route_debug::ROUTE_DEBUG_HEADER;
+use crate::middleware::client_ip::FORWARDED_FOR_HEADER;
+use crate::middleware::deadline::DEADLINE_HEADER;
+use crate::middleware::org_context::ORG_HEADER;
+use crate::middleware::request_id::{RequestId, REQUEST_ID_HEADER};
+use crate::telemetry::log_policy::{TRACE_DEBUG_HEADER, TRACE_SAMPLED_HEADER};
+use chrono::{DateTime, Utc};
+use hyper::http::request::Parts;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+const INVALID_PARAMS_CODE: i32 = -32602;
+const INTERNAL_ERROR_CODE: i32 = -32603;
+const METHOD_NOT_FOUND_CODE: i32 = -32601;
+
+const DEFAULT_MAX_REQUESTS: u64 = 1000;
+
+/// Headers never written to a capture: credentials, signatures, values the
+/// gateway sets itself and framing that replay recomputes
+const UNCAPTURED_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    SIGNATURE_HEADER,
+    SIGNATURE_TIMESTAMP_HEADER,
+    SIGNATURE_NONCE_HEADER,
+    FORWARDED_FOR_HEADER,
+    ORG_HEADER,
+    REQUEST_ID_HEADER,
+    DEADLINE_HEADER,
+    TRACE_SAMPLED_HEADER,
+    TRACE_DEBUG_HEADER,
+    ROUTE_DEBUG_HEADER,
+    "host",
+    "content-length",
+    "connection",
+    "transfer-encoding",
+];
+
+/// One request and the response the client got, a line in the capture file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedExchange {
+    pub captured_at: DateTime<Utc>,
+    pub request_id: String,
+    pub method: String,
+    /// Path and query as routed
+    pub path: String,
+    pub headers: BTreeMap<String, String>,
+    pub request_body: String,
+    pub status: u16,
+    pub response_body: String,
+    pub duration_ms: u64,
+}
+
+impl CapturedExchange {
+    /// The request half; the response is filled in once it arrives. Only
+    /// headers worth replaying are kept, without credentials or anything
+    /// the gateway adds.
+    pub fn for_request(request_id: &RequestId, parts: &Parts, body: &[u8]) -> Self {
+        let headers = parts
+            .headers
+            .iter()
+            .filter(|(name, _)| !UNCAPTURED_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        Self {
+            captured_at: Utc::now(),
+            request_id: request_id.to_string(),
+            method: parts.method.to_string(),
+            path: parts
+                .uri
+                .path_and_query()
+                .map_or_else(|| parts.uri.path().to_string(), ToString::to_string),
+            headers,
+            request_body: String::from_utf8_lossy(body).into_owned(),
+            status: 0,
+            response_body: String::new(),
+            duration_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureStatus {
+    pub active: bool,
+    /// Path prefix being captured
+    pub route: Option<String>,
+    pub sample_percent: u8,
+    pub captured: u64,
+    pub max_requests: u64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub file: Option<String>,
+}
+
+#[derive(Debug)]
+struct CaptureSession {
+    route: String,
+    sample_percent: u8,
+    max_requests: u64,
+    started_at: DateTime<Utc>,
+    file: File,
+}
+
+#[derive(Debug, Deserialize)]
+struct StartCaptureParams {
+    route: String,
+    sample_percent: Option<u8>,
+    max_requests: Option<u64>,
+}
+
+/// Records full request/response pairs for one route into a JSON Lines
+/// file, for replaying against another environment with the `replay`
+/// binary.
+///
+/// Nothing is captured until an admin starts a session on
+/// `/admin/capture`; a session captures `sample_percent` of the requests
+/// under its route until `max_requests` are written or it is stopped.
+/// Responses are recorded as the client got them, after redaction.
+/// Streamed responses are not captured.
+#[derive(Debug, Default)]
+pub struct TrafficCapture {
+    path: Option<PathBuf>,
+    session: Mutex<Option<CaptureSession>>,
+    seen: AtomicU64,
+    captured: AtomicU64,
+}
+
+impl TrafficCapture {
+    /// Reads `GATEWAY_CAPTURE_FILE`, where sessions append their captures;
+    /// unset disables capturing
+    pub fn from_env() -> Self {
+        Self {
+            path: std::env::var("GATEWAY_CAPTURE_FILE")
+                .ok()
+                .map(PathBuf::from),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Whether to capture this request: under the session's route and
+    /// picked by its sample, spread evenly
+    pub fn wants(&self, path: &str) -> bool {
+        let session = self.session.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(session) = session.as_ref() else {
+            return false;
+        };
+        if !path.starts_with(session.route.as_str()) {
+            return false;
+        }
+        let percent = u64::from(session.sample_percent);
+        let n = self.seen.fetch_add(1, Ordering::Relaxed);
+        (n + 1) * percent / 100 > n * percent / 100
+    }
+
+    /// Appends an exchange to the file and ends the session once it has
+    /// `max_requests`. Fails only when the file cannot be written.
+    pub fn record(&self, exchange: &CapturedExchange) -> std::io::Result<()> {
+        let mut session = self.session.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(active) = session.as_mut() else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(exchange).map_err(std::io::Error::other)?;
+        line.push('\n');
+        active.file.write_all(line.as_bytes())?;
+
+        let captured = self.captured.fetch_add(1, Ordering::Relaxed) + 1;
+        if captured >= active.max_requests {
+            *session = None;
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> CaptureStatus {
+        let session = self.session.lock().unwrap_or_else(PoisonError::into_inner);
+        CaptureStatus {
+            active: session.is_some(),
+            route: session.as_ref().map(|session| session.route.clone()),
+            sample_percent: session.as_ref().map_or(0, |session| session.sample_percent),
+            captured: self.captured.load(Ordering::Relaxed),
+            max_requests: session.as_ref().map_or(0, |session| session.max_requests),
+            started_at: session.as_ref().map(|session| session.started_at),
+            file: self.path.as_ref().map(|path| path.display().to_string()),
+        }
+    }
+
+    /// Answers one JSON-RPC call to the admin endpoint:
+    ///
+    /// - `start_capture` `{route, sample_percent?, max_requests?}`
+    /// - `stop_capture`
+    /// - `capture_status`
+    pub fn handle_admin_call(&self, body: &[u8]) -> Value {
+        let request: Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(err) => return rpc_error(Value::Null, -32700, format!("Parse error: {}", err)),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+
+        let result = match request.get("method").and_then(Value::as_str) {
+            Some("start_capture") => self.admin_start(params),
+            Some("stop_capture") => {
+                *self.session.lock().unwrap_or_else(PoisonError::into_inner) = None;
+                Ok(json!(self.status()))
+            }
+            Some("capture_status") => Ok(json!(self.status())),
+            Some(method) => Err((
+                METHOD_NOT_FOUND_CODE,
+                format!("Method not found: {}", method),
+            )),
+            None => Err((INVALID_PARAMS_CODE, "Missing method".to_string())),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => rpc_error(id, code, message),
+        }
+    }
+
+    fn admin_start(&self, params: Value) -> Result<Value, (i32, String)> {
+        let params: StartCaptureParams =
+            serde_json::from_value(params).map_err(|err| (INVALID_PARAMS_CODE, err.to_string()))?;
+        let Some(path) = &self.path else {
+            return Err((
+                INVALID_PARAMS_CODE,
+                "Capturing is disabled, set GATEWAY_CAPTURE_FILE".to_string(),
+            ));
+        };
+        if !params.route.starts_with('/') {
+            return Err((
+                INVALID_PARAMS_CODE,
+                "route must be a path starting with /".to_string(),
+            ));
+        }
+        let sample_percent = params.sample_percent.unwrap_or(100);
+        if sample_percent == 0 || sample_percent > 100 {
+            return Err((
+                INVALID_PARAMS_CODE,
+                "sample_percent must be between 1 and 100".to_string(),
+            ));
+        }
+        let max_requests = params.max_requests.unwrap_or(DEFAULT_MAX_REQUESTS);
+        if max_requests == 0 {
+            return Err((
+                INVALID_PARAMS_CODE,
+                "max_requests must be positive".to_string(),
+            ));
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| {
+                (
+                    INTERNAL_ERROR_CODE,
+                    format!("Failed to open {}: {}", path.display(), err),
+                )
+            })?;
+        // A new session replaces any running one
+        *self.session.lock().unwrap_or_else(PoisonError::into_inner) = Some(CaptureSession {
+            route: params.route,
+            sample_percent,
+            max_requests,
+            started_at: Utc::now(),
+            file,
+        });
+        self.seen.store(0, Ordering::Relaxed);
+        self.captured.store(0, Ordering::Relaxed);
+        Ok(json!(self.status()))
+    }
+}
+
+fn rpc_error(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
@@ -0,0 +1,158 @@
+use crate::middleware::deadline::DEADLINE_EXCEEDED_CODE;
+use crate::middleware::request_id::RequestId;
+use hyper::header::HeaderMap;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Request header in which clients may announce their own timeout, in
+/// milliseconds
+pub const CLIENT_TIMEOUT_HEADER: &str = "x-request-timeout-ms";
+
+#[derive(Error, Debug)]
+pub enum DeadlineConfigError {
+    #[error("Invalid route timeout '{0}', expected path=milliseconds")]
+    InvalidRoute(String),
+
+    #[error("Invalid timeout '{0}', expected a positive number of milliseconds")]
+    InvalidTimeout(String),
+}
+
+/// Returned by the proxy when a request's budget ran out before the
+/// upstream answered
+#[derive(Error, Debug, Clone)]
+#[error("Deadline of {budget_ms}ms exceeded waiting for {service}")]
+pub struct GatewayDeadlineExceeded {
+    pub service: &'static str,
+    pub budget_ms: u64,
+}
+
+impl GatewayDeadlineExceeded {
+    /// JSON-RPC error body sent to the client with `504 Gateway Timeout`.
+    /// Batches get a null id.
+    pub fn envelope(&self, request_body: &[u8], request_id: &RequestId) -> Value {
+        let id = serde_json::from_slice::<Value>(request_body)
+            .ok()
+            .and_then(|request| request.get("id").cloned())
+            .unwrap_or(Value::Null);
+
+        json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "code": DEADLINE_EXCEEDED_CODE,
+                "message": "Deadline exceeded",
+                "data": {
+                    "service": self.service,
+                    "budget_ms": self.budget_ms,
+                    "request_id": request_id,
+                },
+            },
+            "id": id,
+        })
+    }
+}
+
+/// How long the gateway and the services may spend on a request.
+///
+/// The budget is the route's timeout (or the default), shortened to the
+/// client's own timeout when it sends [`CLIENT_TIMEOUT_HEADER`]; clients
+/// can never extend it. Upstreams receive what is left of it in
+/// `X-Request-Deadline-Ms` on every attempt, retries included.
+#[derive(Debug, Clone)]
+pub struct DeadlinePolicy {
+    pub default_timeout: Duration,
+    /// Path prefix -> timeout; the longest matching prefix wins
+    pub route_timeouts: Vec<(String, Duration)>,
+}
+
+impl DeadlinePolicy {
+    /// Reads `GATEWAY_REQUEST_TIMEOUT_MS` (default 30000) and
+    /// `GATEWAY_ROUTE_TIMEOUTS` (`/path=milliseconds,...`).
+    pub fn from_env() -> Result<Self, DeadlineConfigError> {
+        let default_timeout = match std::env::var("GATEWAY_REQUEST_TIMEOUT_MS") {
+            Ok(value) => parse_timeout(&value)?,
+            Err(_) => Duration::from_secs(30),
+        };
+
+        let mut route_timeouts = std::env::var("GATEWAY_ROUTE_TIMEOUTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (path, timeout) = entry
+                    .split_once('=')
+                    .ok_or_else(|| DeadlineConfigError::InvalidRoute(entry.to_string()))?;
+                Ok((path.trim().to_string(), parse_timeout(timeout)?))
+            })
+            .collect::<Result<Vec<_>, DeadlineConfigError>>()?;
+        route_timeouts.sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+
+        Ok(Self {
+            default_timeout,
+            route_timeouts,
+        })
+    }
+
+    pub fn timeout_for(&self, path: &str) -> Duration {
+        self.route_timeouts
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, timeout)| *timeout)
+            .unwrap_or(self.default_timeout)
+    }
+
+    /// The budget for a request to `path`, starting now
+    pub fn deadline_for(&self, path: &str, headers: &HeaderMap) -> RequestDeadline {
+        let route_timeout = self.timeout_for(path);
+        let client_timeout = headers
+            .get(CLIENT_TIMEOUT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_millis);
+        let budget = client_timeout.map_or(route_timeout, |client| client.min(route_timeout));
+
+        RequestDeadline {
+            expires_at: Instant::now() + budget,
+            budget,
+        }
+    }
+}
+
+fn parse_timeout(value: &str) -> Result<Duration, DeadlineConfigError> {
+    value
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|millis| *millis > 0)
+        .map(Duration::from_millis)
+        .ok_or_else(|| DeadlineConfigError::InvalidTimeout(value.to_string()))
+}
+
+/// A proxied request's deadline
+#[derive(Debug, Clone, Copy)]
+pub struct RequestDeadline {
+    expires_at: Instant,
+    budget: Duration,
+}
+
+impl RequestDeadline {
+    pub fn remaining(&self) -> Duration {
+        self.expires_at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn exceeded(&self, service: &'static str) -> GatewayDeadlineExceeded {
+        GatewayDeadlineExceeded {
+            service,
+            budget_ms: self.budget.as_millis() as u64,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::middleware::authorization::FORBIDDEN_CODE;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const INVALID_PARAMS_CODE: i32 = -32602;
+const METHOD_NOT_FOUND_CODE: i32 = -32601;
+
+/// Dumps of the gateway's internal state for diagnosing incidents: rate
+/// limiter buckets, circuit breakers, a cache summary and in-flight
+/// requests.
+///
+/// Dumps hold client addresses, so `dump_state` answers only while debug
+/// endpoints are enabled, either from `GATEWAY_DEBUG_ENDPOINTS` or at
+/// runtime through `/admin/debug`.
+#[derive(Debug, Default)]
+pub struct DebugEndpoints {
+    enabled: AtomicBool,
+}
+
+impl DebugEndpoints {
+    /// Reads `GATEWAY_DEBUG_ENDPOINTS` (`true` or `1` enables them on
+    /// startup)
+    pub fn from_env() -> Self {
+        let enabled = matches!(
+            std::env::var("GATEWAY_DEBUG_ENDPOINTS").as_deref(),
+            Ok("true") | Ok("1")
+        );
+        Self {
+            enabled: AtomicBool::new(enabled),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Answers a JSON-RPC call to `/admin/debug`: `enable_debug_endpoints`,
+    /// `disable_debug_endpoints`, `debug_endpoints_status` or `dump_state`.
+    /// `dump` builds the state and is only awaited for an allowed
+    /// `dump_state`.
+    pub async fn handle_admin_call(&self, body: &[u8], dump: impl Future<Output = Value>) -> Value {
+        let request: Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(err) => return rpc_error(Value::Null, -32700, format!("Parse error: {}", err)),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+
+        let result = match request.get("method").and_then(Value::as_str) {
+            Some("enable_debug_endpoints") => {
+                self.enabled.store(true, Ordering::Relaxed);
+                Ok(self.status())
+            }
+            Some("disable_debug_endpoints") => {
+                self.enabled.store(false, Ordering::Relaxed);
+                Ok(self.status())
+            }
+            Some("debug_endpoints_status") => Ok(self.status()),
+            Some("dump_state") if self.is_enabled() => Ok(dump.await),
+            Some("dump_state") => Err((
+                FORBIDDEN_CODE,
+                "Debug endpoints are disabled, call enable_debug_endpoints first".to_string(),
+            )),
+            Some(method) => Err((
+                METHOD_NOT_FOUND_CODE,
+                format!("Method not found: {}", method),
+            )),
+            None => Err((INVALID_PARAMS_CODE, "Missing method".to_string())),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => rpc_error(id, code, message),
+        }
+    }
+
+    fn status(&self) -> Value {
+        json!({ "enabled": self.is_enabled() })
+    }
+}
+
+fn rpc_error(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
@@ -0,0 +1,248 @@
+use crate::gateway::balancing::UpstreamPool;
+use hyper::header::{HeaderValue, CONNECTION};
+use hyper::Response;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{watch, Notify};
+
+const INVALID_PARAMS_CODE: i32 = -32602;
+const METHOD_NOT_FOUND_CODE: i32 = -32601;
+
+#[derive(Error, Debug)]
+pub enum DrainConfigError {
+    #[error("Invalid {var} '{value}', expected a number of seconds")]
+    InvalidSeconds { var: &'static str, value: String },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DrainConfig {
+    /// How long the gateway keeps accepting connections after a shutdown
+    /// signal, answering `/health` with 503 so load balancers stop sending
+    /// it traffic
+    pub delay: Duration,
+    /// How long open connections get to finish before the gateway exits
+    pub timeout: Duration,
+}
+
+impl DrainConfig {
+    /// Reads `GATEWAY_DRAIN_DELAY_SECS` (default 0) and
+    /// `GATEWAY_DRAIN_TIMEOUT_SECS` (default 30)
+    pub fn from_env() -> Result<Self, DrainConfigError> {
+        Ok(Self {
+            delay: seconds_from_env("GATEWAY_DRAIN_DELAY_SECS", 0)?,
+            timeout: seconds_from_env("GATEWAY_DRAIN_TIMEOUT_SECS", 30)?,
+        })
+    }
+}
+
+fn seconds_from_env(var: &'static str, default: u64) -> Result<Duration, DrainConfigError> {
+    match std::env::var(var) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Duration::from_secs)
+            .map_err(|_| DrainConfigError::InvalidSeconds { var, value }),
+        Err(_) => Ok(Duration::from_secs(default)),
+    }
+}
+
+/// The gateway's own shutdown, in two steps so clients move to other
+/// replicas without having requests cut off.
+///
+/// Once [`GatewayDrain::begin`] is called, every response carries
+/// `Connection: close`, so clients reconnect elsewhere after the request
+/// they are making. Then [`GatewayDrain::close_connections`] closes idle
+/// keep-alive connections and lets busy ones finish their request, and
+/// [`GatewayDrain::wait_idle`] waits for the last of them.
+#[derive(Debug)]
+pub struct GatewayDrain {
+    config: DrainConfig,
+    draining: AtomicBool,
+    closing: watch::Sender<bool>,
+    open: AtomicUsize,
+    idle: Notify,
+}
+
+impl GatewayDrain {
+    pub fn new(config: DrainConfig) -> Self {
+        Self {
+            config,
+            draining: AtomicBool::new(false),
+            closing: watch::Sender::new(false),
+            open: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+
+    pub fn config(&self) -> DrainConfig {
+        self.config
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    pub fn begin(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    /// Tells every connection to close once its current request is done
+    pub fn close_connections(&self) {
+        self.closing.send_replace(true);
+    }
+
+    /// Resolves for a connection once it should close
+    pub fn closing(&self) -> watch::Receiver<bool> {
+        self.closing.subscribe()
+    }
+
+    /// Counts a connection as open until the guard is dropped
+    pub fn track_connection(self: &Arc<Self>) -> OpenConnection {
+        self.open.fetch_add(1, Ordering::SeqCst);
+        OpenConnection(Arc::clone(self))
+    }
+
+    pub fn open_connections(&self) -> usize {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    /// Waits until every connection has closed, for at most
+    /// `config.timeout`. Returns whether they all did.
+    pub async fn wait_idle(&self) -> bool {
+        let all_closed = async {
+            loop {
+                let closed = self.idle.notified();
+                if self.open_connections() == 0 {
+                    return;
+                }
+                closed.await;
+            }
+        };
+        tokio::time::timeout(self.config.timeout, all_closed)
+            .await
+            .is_ok()
+    }
+
+    /// Adds `Connection: close` to a response while draining
+    pub fn mark_response<B>(&self, response: &mut Response<B>) {
+        if self.is_draining() {
+            response
+                .headers_mut()
+                .insert(CONNECTION, HeaderValue::from_static("close"));
+        }
+    }
+}
+
+/// An open client connection, see [`GatewayDrain::track_connection`]
+#[derive(Debug)]
+pub struct OpenConnection(Arc<GatewayDrain>);
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        if self.0.open.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DrainInstanceRequest {
+    /// `user_service` or `product_service`
+    service: String,
+    /// Base URL or `host:port`
+    instance: String,
+    #[serde(default = "default_draining")]
+    draining: bool,
+}
+
+fn default_draining() -> bool {
+    true
+}
+
+/// Answers a JSON-RPC call to `/admin/drain` over the services' instance
+/// pools, keyed by service:
+///
+/// - `drain_instance` `{service, instance, draining?}` takes an instance
+///   out of rotation before it is removed, or puts it back with
+///   `draining: false`
+/// - `list_instances` lists every instance with whether it is draining
+pub fn handle_admin_call(body: &[u8], pools: &[(&str, &UpstreamPool)]) -> Value {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(err) => return rpc_error(Value::Null, -32700, format!("Parse error: {}", err)),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let params = request.get("params").cloned().unwrap_or(json!({}));
+
+    let result = match request.get("method").and_then(Value::as_str) {
+        Some("drain_instance") => drain_instance(params, pools),
+        Some("list_instances") => Ok(list_instances(pools)),
+        Some(method) => Err((
+            METHOD_NOT_FOUND_CODE,
+            format!("Method not found: {}", method),
+        )),
+        None => Err((INVALID_PARAMS_CODE, "Missing method".to_string())),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => rpc_error(id, code, message),
+    }
+}
+
+fn drain_instance(params: Value, pools: &[(&str, &UpstreamPool)]) -> Result<Value, (i32, String)> {
+    let params: DrainInstanceRequest =
+        serde_json::from_value(params).map_err(|err| (INVALID_PARAMS_CODE, err.to_string()))?;
+    let (_, pool) = pools
+        .iter()
+        .find(|(service, _)| *service == params.service)
+        .ok_or_else(|| {
+            (
+                INVALID_PARAMS_CODE,
+                format!("Unknown service '{}'", params.service),
+            )
+        })?;
+    let instance = pool.find(&params.instance).ok_or_else(|| {
+        (
+            INVALID_PARAMS_CODE,
+            format!(
+                "Unknown instance '{}' of {}",
+                params.instance, params.service
+            ),
+        )
+    })?;
+    instance.set_draining(params.draining);
+    Ok(list_instances(pools))
+}
+
+fn list_instances(pools: &[(&str, &UpstreamPool)]) -> Value {
+    let services: serde_json::Map<String, Value> = pools
+        .iter()
+        .map(|(service, pool)| {
+            let instances: Vec<Value> = pool
+                .instances()
+                .iter()
+                .map(|instance| {
+                    json!({
+                        "instance": instance.base_url(),
+                        "draining": instance.is_draining(),
+                        "down": instance.is_down(),
+                    })
+                })
+                .collect();
+            (service.to_string(), json!(instances))
+        })
+        .collect();
+    Value::Object(services)
+}
+
+fn rpc_error(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
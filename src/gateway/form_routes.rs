@@ -0,0 +1,319 @@
+use crate::gateway::routing::{route_for_method, MethodRoute};
+use bytes::Bytes;
+use hyper::header::{HeaderMap, CONTENT_TYPE};
+use hyper::Method;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+const INVALID_PARAMS_CODE: i32 = -32602;
+
+#[derive(Error, Debug)]
+pub enum FormRoutesError {
+    #[error("Failed to read form routes {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid form routes {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid form route '{path}': {message}")]
+    InvalidRoute { path: String, message: String },
+}
+
+/// Why a query string or form could not become params
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FormParamError {
+    #[error("Malformed percent-encoding in '{0}'")]
+    Encoding(String),
+
+    #[error("Parameter '{name}' must be {expected}, got '{value}'")]
+    InvalidValue {
+        name: String,
+        expected: &'static str,
+        value: String,
+    },
+}
+
+impl FormParamError {
+    /// JSON-RPC error body for the client, answered with `400`
+    pub fn to_response(&self) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": Value::Null,
+            "error": { "code": INVALID_PARAMS_CODE, "message": self.to_string() },
+        })
+    }
+}
+
+/// How a query or form value is turned into a JSON param
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    #[default]
+    String,
+    Integer,
+    Number,
+    Boolean,
+    /// Every value of a repeated key, as strings (`tag=a&tag=b`)
+    List,
+}
+
+impl ParamType {
+    fn convert(self, name: &str, mut values: Vec<String>) -> Result<Value, FormParamError> {
+        if self == ParamType::List {
+            return Ok(json!(values));
+        }
+        // A repeated scalar takes its last value, as in most form decoders
+        let value = values.pop().unwrap_or_default();
+        let invalid = |expected| FormParamError::InvalidValue {
+            name: name.to_string(),
+            expected,
+            value: value.clone(),
+        };
+        match self {
+            ParamType::String | ParamType::List => Ok(Value::String(value)),
+            ParamType::Integer => value
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| invalid("an integer")),
+            ParamType::Number => value
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| invalid("a number")),
+            ParamType::Boolean => match value.as_str() {
+                "true" | "1" | "on" | "yes" => Ok(Value::Bool(true)),
+                "false" | "0" | "off" | "no" => Ok(Value::Bool(false)),
+                _ => Err(invalid("a boolean")),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FormRouteConfig {
+    path: String,
+    method: String,
+    #[serde(default = "default_http_methods")]
+    http_methods: Vec<String>,
+    #[serde(default)]
+    params: BTreeMap<String, ParamType>,
+}
+
+fn default_http_methods() -> Vec<String> {
+    vec!["GET".to_string()]
+}
+
+/// A path answered by one JSON-RPC method, with its params taken from the
+/// query string or a form post
+#[derive(Debug, Clone)]
+pub struct FormRoute {
+    path: String,
+    method: String,
+    /// `GET` reads the query string, `POST` a form body plus the query
+    http_methods: Vec<Method>,
+    params: BTreeMap<String, ParamType>,
+}
+
+impl FormRoute {
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Whether the request is one this route converts: an allowed `GET`,
+    /// or an allowed `POST` with an `application/x-www-form-urlencoded`
+    /// body. Anything else, a JSON-RPC post included, passes through.
+    pub fn converts(&self, method: &Method, headers: &HeaderMap) -> bool {
+        self.http_methods.contains(method)
+            && (*method == Method::GET || (*method == Method::POST && is_form(headers)))
+    }
+
+    /// Builds the JSON-RPC call from the query string and form body. Only
+    /// declared params are sent, so webhook payloads can carry more fields
+    /// than the method takes; missing ones are left to the method to
+    /// reject. Params go as one object, the request struct every method
+    /// takes.
+    pub fn to_call(
+        &self,
+        query: Option<&str>,
+        form: Option<&[u8]>,
+        id: impl Into<Value>,
+    ) -> Result<Bytes, FormParamError> {
+        let mut values: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let form = form.map(String::from_utf8_lossy);
+        for pair in query
+            .into_iter()
+            .chain(form.as_deref())
+            .flat_map(|encoded| encoded.split('&'))
+            .filter(|pair| !pair.is_empty())
+        {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let name = decode_component(name)?;
+            if self.params.contains_key(&name) {
+                values
+                    .entry(name)
+                    .or_default()
+                    .push(decode_component(value)?);
+            }
+        }
+
+        let mut params = Map::new();
+        for (name, values) in values {
+            let value = self.params[&name].convert(&name, values)?;
+            params.insert(name, value);
+        }
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": id.into(),
+            "method": self.method,
+            "params": [params],
+        });
+        Ok(Bytes::from(call.to_string()))
+    }
+
+    fn describe(&self) -> Value {
+        json!({
+            "path": self.path,
+            "method": self.method,
+            "http_methods": self.http_methods.iter().map(Method::as_str).collect::<Vec<_>>(),
+            "params": self.params.keys().collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn is_form(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| {
+            mime.trim()
+                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+        })
+}
+
+/// Decodes one `application/x-www-form-urlencoded` name or value: `+` is a
+/// space and `%XX` a byte
+fn decode_component(encoded: &str) -> Result<String, FormParamError> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let byte = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| FormParamError::Encoding(encoded.to_string()))?;
+                decoded.push(byte);
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| FormParamError::Encoding(encoded.to_string()))
+}
+
+/// Paths that take plain `GET` query strings or form posts and call a
+/// JSON-RPC method with them, for browser links and webhooks that cannot
+/// speak JSON-RPC. Paths match exactly, after normalization.
+#[derive(Debug, Default)]
+pub struct FormRoutes {
+    routes: Vec<FormRoute>,
+}
+
+impl FormRoutes {
+    /// Loads the routes file named by `GATEWAY_FORM_ROUTES`; unset converts
+    /// nothing
+    pub fn from_env() -> Result<Self, FormRoutesError> {
+        match std::env::var("GATEWAY_FORM_ROUTES") {
+            Ok(path) => Self::from_file(path),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, FormRoutesError> {
+        let path_str = path.as_ref().display().to_string();
+        let contents = std::fs::read_to_string(&path).map_err(|source| FormRoutesError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+        let routes: Vec<FormRouteConfig> =
+            serde_json::from_str(&contents).map_err(|source| FormRoutesError::Parse {
+                path: path_str,
+                source,
+            })?;
+
+        Ok(Self {
+            routes: routes
+                .into_iter()
+                .map(compile_route)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    pub fn route_for(&self, path: &str) -> Option<&FormRoute> {
+        self.routes.iter().find(|route| route.path == path)
+    }
+
+    /// The routes for `/routes`
+    pub fn describe(&self) -> Value {
+        Value::Array(self.routes.iter().map(FormRoute::describe).collect())
+    }
+}
+
+fn compile_route(config: FormRouteConfig) -> Result<FormRoute, FormRoutesError> {
+    let invalid = |message: String| FormRoutesError::InvalidRoute {
+        path: config.path.clone(),
+        message,
+    };
+    if !config.path.starts_with('/') {
+        return Err(invalid("path must start with /".to_string()));
+    }
+    if route_for_method(&config.method) == MethodRoute::Unknown {
+        return Err(invalid(format!("unknown method {}", config.method)));
+    }
+    let http_methods = config
+        .http_methods
+        .iter()
+        .map(|method| match method.to_ascii_uppercase().as_str() {
+            "GET" => Ok(Method::GET),
+            "POST" => Ok(Method::POST),
+            _ => Err(invalid(format!(
+                "unsupported HTTP method {}, expected GET or POST",
+                method
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if http_methods.is_empty() {
+        return Err(invalid("no HTTP methods".to_string()));
+    }
+
+    Ok(FormRoute {
+        path: config.path,
+        method: config.method,
+        http_methods,
+        params: config.params,
+    })
+}
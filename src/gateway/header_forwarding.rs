@@ -0,0 +1,201 @@
+use crate::crypto::request_signing::{
+    SIGNATURE_HEADER, SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+};
+use crate::gateway::rate_limit_overrides::TENANT_HEADER;
+use crate::middleware::api_version::ACCEPT_VERSION_HEADER;
+use crate::middleware::authorization::CLIENT_AUTHORIZATION_HEADER;
+use crate::middleware::client_ip::FORWARDED_FOR_HEADER;
+use crate::middleware::deadline::DEADLINE_HEADER;
+use crate::middleware::org_context::ORG_HEADER;
+use crate::middleware::request_id::REQUEST_ID_HEADER;
+use crate::middleware::timestamp_format::TIMESTAMP_FORMAT_HEADER;
+use crate::telemetry::log_policy::TRACE_SAMPLED_HEADER;
+use hyper::header::{HeaderMap, HeaderName};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+/// Client headers services receive unless `GATEWAY_FORWARD_HEADERS` says
+/// otherwise
+const DEFAULT_FORWARDED: &[&str] = &[
+    "accept",
+    "accept-language",
+    "authorization",
+    "content-type",
+    "user-agent",
+    ACCEPT_VERSION_HEADER,
+    TIMESTAMP_FORMAT_HEADER,
+    TENANT_HEADER,
+];
+
+/// Headers only the gateway sets. Client values are always dropped, and
+/// the gateway's own are always forwarded.
+const GATEWAY_HEADERS: &[&str] = &[
+    ORG_HEADER,
+    CLIENT_AUTHORIZATION_HEADER,
+    FORWARDED_FOR_HEADER,
+    REQUEST_ID_HEADER,
+    TRACE_SAMPLED_HEADER,
+    DEADLINE_HEADER,
+    SIGNATURE_HEADER,
+    SIGNATURE_TIMESTAMP_HEADER,
+    SIGNATURE_NONCE_HEADER,
+];
+
+/// Internal-only headers stripped from client requests unless
+/// `GATEWAY_STRIP_HEADERS` says otherwise
+const DEFAULT_STRIPPED: &[&str] = &["x-user-id", "x-api-key", "x-internal-*"];
+
+#[derive(Error, Debug)]
+pub enum HeaderForwardingError {
+    #[error("Invalid header name '{0}'")]
+    InvalidHeader(String),
+
+    #[error("Invalid route header entry '{0}', expected /path=header|header")]
+    InvalidRoute(String),
+}
+
+/// Which client headers reach the services.
+///
+/// Internal-only headers (the gateway's own, such as `X-Org-Id`, plus
+/// `GATEWAY_STRIP_HEADERS`) are removed from client requests before the
+/// gateway looks at them. Of the rest, only headers on the allowlist are
+/// sent upstream: `GATEWAY_FORWARD_HEADERS`, plus the extra headers of the
+/// longest `GATEWAY_ROUTE_FORWARD_HEADERS` prefix matching the path.
+#[derive(Debug, Clone)]
+pub struct HeaderForwarding {
+    forwarded: Vec<String>,
+    /// Lowercase names, or prefixes ending in `*`
+    stripped: Vec<String>,
+    /// Path prefix -> extra forwarded headers, longest prefix first
+    routes: Vec<(String, Vec<String>)>,
+}
+
+impl Default for HeaderForwarding {
+    fn default() -> Self {
+        Self {
+            forwarded: DEFAULT_FORWARDED.iter().map(|h| h.to_string()).collect(),
+            stripped: DEFAULT_STRIPPED.iter().map(|h| h.to_string()).collect(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl HeaderForwarding {
+    /// Reads `GATEWAY_FORWARD_HEADERS` and `GATEWAY_STRIP_HEADERS` (comma
+    /// separated, each replacing its default) and
+    /// `GATEWAY_ROUTE_FORWARD_HEADERS` (e.g.
+    /// `/api/users/avatar=content-disposition|x-upload-name`)
+    pub fn from_env() -> Result<Self, HeaderForwardingError> {
+        let mut forwarding = Self::default();
+        if let Ok(value) = std::env::var("GATEWAY_FORWARD_HEADERS") {
+            forwarding.forwarded = parse_names(value.split(','), false)?;
+        }
+        if let Ok(value) = std::env::var("GATEWAY_STRIP_HEADERS") {
+            forwarding.stripped = parse_names(value.split(','), true)?;
+        }
+
+        forwarding.routes = std::env::var("GATEWAY_ROUTE_FORWARD_HEADERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (path, headers) = entry
+                    .split_once('=')
+                    .filter(|(path, _)| path.trim().starts_with('/'))
+                    .ok_or_else(|| HeaderForwardingError::InvalidRoute(entry.to_string()))?;
+                Ok((
+                    path.trim().to_string(),
+                    parse_names(headers.split('|'), false)?,
+                ))
+            })
+            .collect::<Result<_, HeaderForwardingError>>()?;
+        forwarding
+            .routes
+            .sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+
+        Ok(forwarding)
+    }
+
+    pub fn forwarded_count(&self) -> usize {
+        self.forwarded.len()
+    }
+
+    pub fn route_count(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Removes internal-only headers from a client request, returning how
+    /// many were removed
+    pub fn strip_internal(&self, headers: &mut HeaderMap) -> usize {
+        let internal: Vec<HeaderName> = headers
+            .keys()
+            .filter(|name| {
+                let name = name.as_str();
+                GATEWAY_HEADERS.contains(&name)
+                    || self.stripped.iter().any(|pattern| matches(pattern, name))
+            })
+            .cloned()
+            .collect();
+        for name in &internal {
+            headers.remove(name);
+        }
+        internal.len()
+    }
+
+    /// Whether a request to `path` may carry `name` upstream. Set after
+    /// [`Self::strip_internal`], the gateway's own headers always may.
+    pub fn forwards(&self, path: &str, name: &HeaderName) -> bool {
+        let name = name.as_str();
+        GATEWAY_HEADERS.contains(&name)
+            || self.forwarded.iter().any(|allowed| allowed == name)
+            || self
+                .routes
+                .iter()
+                .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+                .is_some_and(|(_, extra)| extra.iter().any(|allowed| allowed == name))
+    }
+
+    /// The allowlists and stripped headers, for `/routes`
+    pub fn describe(&self) -> Value {
+        json!({
+            "forwarded": self.forwarded,
+            "stripped": self.stripped,
+            "gateway": GATEWAY_HEADERS,
+            "routes": self
+                .routes
+                .iter()
+                .map(|(path, headers)| json!({ "path": path, "forwarded": headers }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// `pattern` is a lowercase name, or a prefix when it ends in `*`
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+fn parse_names<'a>(
+    names: impl Iterator<Item = &'a str>,
+    allow_prefix: bool,
+) -> Result<Vec<String>, HeaderForwardingError> {
+    names
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let lower = name.to_ascii_lowercase();
+            let bare = match lower.strip_suffix('*') {
+                Some(prefix) if allow_prefix => prefix,
+                _ => &lower,
+            };
+            if bare.is_empty() || HeaderName::from_bytes(bare.as_bytes()).is_err() {
+                return Err(HeaderForwardingError::InvalidHeader(name.to_string()));
+            }
+            Ok(lower)
+        })
+        .collect()
+}
@@ -0,0 +1,152 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use hyper::body::{Body, Frame};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// Events buffered per subscriber before a slow client starts missing some
+const EVENT_BUFFER: usize = 64;
+/// Comment line sent on idle streams so proxies don't close them
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+impl HealthStatus {
+    pub fn from_healthy(is_healthy: bool) -> Self {
+        if is_healthy {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        }
+    }
+}
+
+/// A service's health as pushed on `/health/stream`: the current state of
+/// every service when a client connects (`snapshot`), then one event per
+/// transition (`transition`)
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthEvent {
+    pub service: &'static str,
+    pub status: HealthStatus,
+    pub consecutive_failures: u32,
+    /// What caused a transition: `health_check`, `upstream_5xx` or
+    /// `shared_state` (reported by another gateway replica)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl HealthEvent {
+    pub fn new(
+        service: &'static str,
+        is_healthy: bool,
+        consecutive_failures: u32,
+        reason: Option<&'static str>,
+    ) -> Self {
+        Self {
+            service,
+            status: HealthStatus::from_healthy(is_healthy),
+            consecutive_failures,
+            reason,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Fans health transitions out to every connected stream
+#[derive(Debug)]
+pub struct HealthEventBus {
+    sender: broadcast::Sender<HealthEvent>,
+}
+
+impl Default for HealthEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthEventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+
+    /// Publishes a transition; a no-op when nobody is listening
+    pub fn publish(&self, event: HealthEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Opens a Server-Sent Events stream that starts with `snapshot` and
+    /// then relays transitions until the client disconnects
+    pub fn stream(&self, snapshot: Vec<HealthEvent>) -> HealthEventBody {
+        let mut events = self.sender.subscribe();
+        let (frames, receiver) = mpsc::channel(EVENT_BUFFER);
+
+        tokio::spawn(async move {
+            for event in &snapshot {
+                if frames.send(sse_frame("snapshot", event)).await.is_err() {
+                    return;
+                }
+            }
+
+            let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+            keepalive.tick().await;
+            loop {
+                let frame = tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => sse_frame("transition", &event),
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            Bytes::from(format!(": missed {} events\n\n", missed))
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    },
+                    _ = keepalive.tick() => Bytes::from_static(b": keepalive\n\n"),
+                };
+                // The client went away
+                if frames.send(frame).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        HealthEventBody { receiver }
+    }
+}
+
+fn sse_frame(kind: &str, event: &HealthEvent) -> Bytes {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    Bytes::from(format!("event: {}\ndata: {}\n\n", kind, data))
+}
+
+/// Response body for `/health/stream`; ends when the bus is dropped
+#[derive(Debug)]
+pub struct HealthEventBody {
+    receiver: mpsc::Receiver<Bytes>,
+}
+
+impl Body for HealthEventBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        self.receiver
+            .poll_recv(cx)
+            .map(|frame| frame.map(|bytes| Ok(Frame::data(bytes))))
+    }
+}
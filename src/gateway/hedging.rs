@@ -0,0 +1,148 @@
+use crate::telemetry::latency_histogram::LatencySnapshot;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Upstreams with fewer recorded responses than this are never hedged; their
+/// p95 means little yet
+const MIN_LATENCY_SAMPLES: u64 = 20;
+/// Hedges that may be saved up while traffic is quiet, so a burst after a
+/// lull cannot double the load
+const MAX_SAVED_HEDGES: u64 = 10;
+/// Budget units per hedge; each eligible request earns `max_percent * 10`
+const HEDGE_COST: u64 = 1000;
+
+#[derive(Error, Debug)]
+pub enum HedgingConfigError {
+    #[error("Invalid GATEWAY_HEDGE_MAX_PERCENT '{0}', expected 0-100")]
+    InvalidPercent(String),
+
+    #[error("Invalid GATEWAY_HEDGE_MIN_DELAY_MS '{0}', expected a number of milliseconds")]
+    InvalidMinDelay(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct HedgingConfig {
+    /// Hedges allowed per 100 eligible requests
+    pub max_percent: u64,
+    /// Never hedge sooner than this, however fast the upstream's p95 is
+    pub min_delay: Duration,
+}
+
+impl HedgingConfig {
+    /// Reads `GATEWAY_HEDGE_MAX_PERCENT` (unset or 0 disables hedging) and
+    /// `GATEWAY_HEDGE_MIN_DELAY_MS` (default 10)
+    pub fn from_env() -> Result<Option<Self>, HedgingConfigError> {
+        let max_percent = match std::env::var("GATEWAY_HEDGE_MAX_PERCENT") {
+            Ok(value) => value
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|percent| *percent <= 100)
+                .ok_or(HedgingConfigError::InvalidPercent(value))?,
+            Err(_) => 0,
+        };
+        if max_percent == 0 {
+            return Ok(None);
+        }
+        let min_delay = match std::env::var("GATEWAY_HEDGE_MIN_DELAY_MS") {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .map(Duration::from_millis)
+                .map_err(|_| HedgingConfigError::InvalidMinDelay(value))?,
+            Err(_) => Duration::from_millis(10),
+        };
+        Ok(Some(Self {
+            max_percent,
+            min_delay,
+        }))
+    }
+}
+
+/// Decides when an idempotent request gets a second, hedged attempt.
+///
+/// The hedge is sent once the first attempt has waited longer than the
+/// upstream's p95 time to first byte, so only the slowest ~5% of requests
+/// qualify. On top of that every eligible request earns a fraction of a
+/// hedge and each hedge spends a whole one, which holds the extra load to
+/// `max_percent` of eligible traffic.
+#[derive(Debug)]
+pub struct HedgePolicy {
+    config: HedgingConfig,
+    budget: AtomicU64,
+    eligible: AtomicU64,
+    hedged: AtomicU64,
+    hedge_wins: AtomicU64,
+    over_budget: AtomicU64,
+}
+
+impl HedgePolicy {
+    pub fn new(config: HedgingConfig) -> Self {
+        Self {
+            config,
+            budget: AtomicU64::new(0),
+            eligible: AtomicU64::new(0),
+            hedged: AtomicU64::new(0),
+            hedge_wins: AtomicU64::new(0),
+            over_budget: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &HedgingConfig {
+        &self.config
+    }
+
+    /// How long to wait on the first attempt before hedging, given the
+    /// upstream's latency so far; `None` while there are too few samples.
+    /// Counts the request towards the hedge budget.
+    pub fn delay_for(&self, ttfb: &LatencySnapshot) -> Option<Duration> {
+        self.eligible.fetch_add(1, Ordering::Relaxed);
+        let earned = self.config.max_percent * HEDGE_COST / 100;
+        let _ = self
+            .budget
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |budget| {
+                Some((budget + earned).min(MAX_SAVED_HEDGES * HEDGE_COST))
+            });
+
+        if ttfb.count < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+        let p95 = Duration::from_micros((ttfb.p95_ms * 1000.0) as u64);
+        Some(p95.max(self.config.min_delay))
+    }
+
+    /// Takes one hedge from the budget; `false` when the cap is reached
+    pub fn try_hedge(&self) -> bool {
+        let taken = self
+            .budget
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |budget| {
+                budget.checked_sub(HEDGE_COST)
+            })
+            .is_ok();
+        if taken {
+            self.hedged.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.over_budget.fetch_add(1, Ordering::Relaxed);
+        }
+        taken
+    }
+
+    /// Notes that the hedged attempt answered before the first one
+    pub fn record_hedge_win(&self) {
+        self.hedge_wins.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> Value {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        json!({
+            "max_percent": self.config.max_percent,
+            "min_delay_ms": self.config.min_delay.as_millis() as u64,
+            "eligible": load(&self.eligible),
+            "hedged": load(&self.hedged),
+            "hedge_wins": load(&self.hedge_wins),
+            "skipped_over_budget": load(&self.over_budget),
+        })
+    }
+}
@@ -0,0 +1,183 @@
+use crate::services::method_namespaces::flat_method_name;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// Rewrites SurrealDB record ids in results into plain id strings, for the
+/// methods listed in `GATEWAY_NORMALIZE_ID_METHODS`.
+///
+/// Two shapes are rewritten: serialized `Thing`s such as
+/// `{"tb": "product", "id": {"String": "xyz"}}`, wherever they appear, and
+/// `table:id` strings such as `product:xyz` or `user:⟨…⟩` in fields named
+/// `id` or ending in `_id` / `_ids`. Both become `"xyz"`, the form the
+/// services accept back as an id.
+#[derive(Debug, Default)]
+pub struct IdNormalizer {
+    methods: HashSet<String>,
+    all_methods: bool,
+}
+
+impl IdNormalizer {
+    /// Reads `GATEWAY_NORMALIZE_ID_METHODS` (comma separated, flat or
+    /// namespaced, `*` for every method; default none)
+    pub fn from_env() -> Self {
+        let mut normalizer = Self::default();
+        for method in std::env::var("GATEWAY_NORMALIZE_ID_METHODS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+        {
+            if method == "*" {
+                normalizer.all_methods = true;
+            } else {
+                normalizer
+                    .methods
+                    .insert(flat_method_name(method).to_string());
+            }
+        }
+        normalizer
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.all_methods || !self.methods.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        if self.all_methods {
+            return "all methods".to_string();
+        }
+        let mut methods: Vec<&str> = self.methods.iter().map(String::as_str).collect();
+        methods.sort_unstable();
+        methods.join(", ")
+    }
+
+    fn applies_to(&self, method: &str) -> bool {
+        self.all_methods || self.methods.contains(flat_method_name(method))
+    }
+
+    /// Picks the calls in a request body whose results get normalized, or
+    /// `None` when there are none
+    pub fn plan(&self, body: &[u8]) -> Option<IdNormalizationPlan> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let request: Value = serde_json::from_slice(body).ok()?;
+        let calls = match &request {
+            Value::Array(calls) => calls.iter().collect::<Vec<_>>(),
+            call => vec![call],
+        };
+
+        let ids: Vec<Value> = calls
+            .into_iter()
+            .filter(|call| call["method"].as_str().is_some_and(|m| self.applies_to(m)))
+            .filter_map(|call| call.get("id").cloned())
+            .collect();
+        (!ids.is_empty()).then_some(IdNormalizationPlan { ids })
+    }
+}
+
+/// The JSON-RPC ids of the calls to normalize in one response
+#[derive(Debug, Clone)]
+pub struct IdNormalizationPlan {
+    ids: Vec<Value>,
+}
+
+impl IdNormalizationPlan {
+    /// Normalizes the results of the planned calls in a single or batch
+    /// response. Returns the number of rewritten ids.
+    pub fn apply(&self, response: &mut Value) -> usize {
+        match response {
+            Value::Array(entries) => entries
+                .iter_mut()
+                .map(|entry| self.apply_entry(entry))
+                .sum(),
+            entry => self.apply_entry(entry),
+        }
+    }
+
+    fn apply_entry(&self, entry: &mut Value) -> usize {
+        if !entry.get("id").is_some_and(|id| self.ids.contains(id)) {
+            return 0;
+        }
+        entry
+            .get_mut("result")
+            .map_or(0, |result| normalize(result, false))
+    }
+}
+
+/// Rewrites ids in `value`; `id_field` is set when `value` sits in a field
+/// that holds ids
+fn normalize(value: &mut Value, id_field: bool) -> usize {
+    if let Some(id) = thing_id(value) {
+        *value = Value::String(id);
+        return 1;
+    }
+    match value {
+        Value::String(s) if id_field => match strip_table(s) {
+            Some(id) => {
+                *s = id;
+                1
+            }
+            None => 0,
+        },
+        Value::Array(items) => items.iter_mut().map(|item| normalize(item, id_field)).sum(),
+        Value::Object(fields) => fields
+            .iter_mut()
+            .map(|(name, field)| normalize(field, is_id_field(name)))
+            .sum(),
+        _ => 0,
+    }
+}
+
+fn is_id_field(name: &str) -> bool {
+    name == "id" || name.ends_with("_id") || name.ends_with("_ids")
+}
+
+/// The plain id of a serialized `Thing`, whose `id` is either bare or
+/// tagged with its kind (`{"String": ...}` / `{"Number": ...}`)
+fn thing_id(value: &Value) -> Option<String> {
+    let fields = value.as_object()?;
+    if fields.len() != 2 || !fields.get("tb")?.is_string() {
+        return None;
+    }
+    match fields.get("id")? {
+        Value::Object(tagged) => tagged_id(tagged),
+        id => plain_id(id),
+    }
+}
+
+fn tagged_id(tagged: &Map<String, Value>) -> Option<String> {
+    let (kind, id) = tagged.iter().next().filter(|_| tagged.len() == 1)?;
+    match kind.as_str() {
+        "String" | "Number" => plain_id(id),
+        _ => None,
+    }
+}
+
+fn plain_id(id: &Value) -> Option<String> {
+    match id {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// `product:xyz` -> `xyz`, `user:⟨a-b⟩` -> `a-b`; `None` for anything that
+/// is not a record id
+fn strip_table(s: &str) -> Option<String> {
+    let (table, id) = s.split_once(':')?;
+    let is_table = table
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_table || id.is_empty() || id.starts_with('/') {
+        return None;
+    }
+    let id = id
+        .strip_prefix('⟨')
+        .and_then(|id| id.strip_suffix('⟩'))
+        .or_else(|| id.strip_prefix('`').and_then(|id| id.strip_suffix('`')))
+        .unwrap_or(id);
+    Some(id.to_string())
+}
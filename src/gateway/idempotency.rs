@@ -0,0 +1,141 @@
+use crate::services::method_namespaces::{flat_method_name, METHOD_LIST_METHOD};
+use hyper::Method;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Methods that are safe to send twice: reads, and writes that set an
+/// absolute value (`update_product_stock`, `update_user_name`,
+/// `set_read_only`, `set_log_sampling`, `set_feature_flag`,
+/// `record_activity`). Anything that creates, counts or moves something is
+/// left out.
+pub const DEFAULT_IDEMPOTENT_METHODS: &[&str] = &[
+    "health",
+    "query_stats",
+    "panic_stats",
+    "retention_stats",
+    "storage_stats",
+    "database_isolation",
+    METHOD_LIST_METHOD,
+    "set_read_only",
+    "set_log_sampling",
+    "set_feature_flag",
+    "list_feature_flags",
+    "evaluate_feature_flag",
+    "get_user",
+    "get_user_history",
+    "get_name_history",
+    "list_users",
+    "export_users",
+    "get_user_stats",
+    "list_inactive_users",
+    "record_activity",
+    "unlock_user",
+    "update_user_name",
+    "validate_address",
+    "list_fraud_hits",
+    "list_org_members",
+    "get_product",
+    "find_similar_products",
+    "list_products",
+    "list_attributes",
+    "search_products_by_attributes",
+    "get_products_by_category",
+    "get_product_stats",
+    "get_category_rollups",
+    "export_products",
+    "get_job_status",
+    "update_product_stock",
+    "get_price_history",
+    "list_locations",
+    "reconcile_stock",
+    "forecast_stock",
+    "generate_feed",
+    "validate_coupon",
+    "get_recommended_products",
+    "get_return",
+    "list_orders_by_user",
+    "count_orders_by_user",
+];
+
+/// Decides whether the gateway may resend a request after a timeout or a
+/// retryable status.
+///
+/// A request is retry-safe when every call in it is a method marked
+/// idempotent. Unknown methods are not. Requests that were never sent
+/// (connection failures) are always safe to retry; the proxy handles that
+/// case itself.
+#[derive(Debug, Clone)]
+pub struct IdempotencyRegistry {
+    idempotent: HashSet<String>,
+}
+
+impl Default for IdempotencyRegistry {
+    fn default() -> Self {
+        Self {
+            idempotent: DEFAULT_IDEMPOTENT_METHODS
+                .iter()
+                .map(|method| method.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl IdempotencyRegistry {
+    /// Starts from [`DEFAULT_IDEMPOTENT_METHODS`], adds
+    /// `GATEWAY_IDEMPOTENT_METHODS` and removes
+    /// `GATEWAY_NON_IDEMPOTENT_METHODS` (comma separated, flat or namespaced)
+    pub fn from_env() -> Self {
+        let mut registry = Self::default();
+        for method in env_methods("GATEWAY_IDEMPOTENT_METHODS") {
+            registry.idempotent.insert(method);
+        }
+        for method in env_methods("GATEWAY_NON_IDEMPOTENT_METHODS") {
+            registry.idempotent.remove(&method);
+        }
+        registry
+    }
+
+    pub fn is_idempotent(&self, method: &str) -> bool {
+        self.idempotent.contains(flat_method_name(method))
+    }
+
+    pub fn len(&self) -> usize {
+        self.idempotent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.idempotent.is_empty()
+    }
+
+    /// True when resending the request cannot repeat a side effect. Bodies
+    /// that are not JSON-RPC fall back to the HTTP method's safety.
+    pub fn is_retry_safe(&self, http_method: &Method, body: &[u8]) -> bool {
+        if body.is_empty() {
+            return matches!(*http_method, Method::GET | Method::HEAD | Method::OPTIONS);
+        }
+
+        let Ok(request) = serde_json::from_slice::<Value>(body) else {
+            return false;
+        };
+        let calls = match &request {
+            Value::Array(calls) if !calls.is_empty() => calls.iter().collect::<Vec<_>>(),
+            Value::Array(_) => return false,
+            call => vec![call],
+        };
+        calls.iter().all(|call| {
+            call.get("method")
+                .and_then(Value::as_str)
+                .is_some_and(|method| self.is_idempotent(method))
+        })
+    }
+}
+
+fn env_methods(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| flat_method_name(m).to_string())
+        .collect()
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Where the gateway listens when `GATEWAY_LISTENERS` is unset
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8082";
+
+/// Gateway endpoints for operators rather than API clients
+pub const ADMIN_PATHS: &[&str] = &[
+    "/metrics",
+    "/routes",
+    "/admin/rate-limits",
+    "/admin/log-sampling",
+    "/admin/filter-rules",
+    "/admin/capture",
+    "/admin/debug",
+    "/admin/drain",
+    "/health",
+    "/health/stream",
+];
+
+#[derive(Error, Debug)]
+pub enum ListenerConfigError {
+    #[error("Invalid GATEWAY_LISTENERS entry '{0}', expected role=host:port")]
+    InvalidEntry(String),
+
+    #[error("Unknown listener role '{0}', expected public, admin or all")]
+    UnknownRole(String),
+
+    #[error("Address {0} is listed more than once in GATEWAY_LISTENERS")]
+    DuplicateAddress(SocketAddr),
+
+    #[error("GATEWAY_LISTENERS needs a public or all listener for API traffic")]
+    NoPublicListener,
+
+    #[error("An all listener serves the admin endpoints, so it cannot be combined with an admin listener")]
+    AllWithAdmin,
+}
+
+/// What a listener serves, and so which middleware its requests run through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerRole {
+    /// API traffic and the admin endpoints on one port, as with a single
+    /// listener
+    All,
+    /// API traffic only: rate limiting, load shedding and priority lanes
+    /// apply, the admin endpoints answer `404`
+    Public,
+    /// The admin endpoints only, without rate limiting; everything else
+    /// answers `404`
+    Admin,
+}
+
+impl ListenerRole {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ListenerRole::All => "all",
+            ListenerRole::Public => "public",
+            ListenerRole::Admin => "admin",
+        }
+    }
+
+    /// Whether requests for `path` are answered on this listener
+    pub fn serves(&self, path: &str) -> bool {
+        match self {
+            ListenerRole::All => true,
+            ListenerRole::Public => !is_admin_path(path),
+            ListenerRole::Admin => is_admin_path(path),
+        }
+    }
+
+    /// Admin listeners are reachable by operators only, so their requests
+    /// skip the per-client rate limit
+    pub fn is_rate_limited(&self) -> bool {
+        *self != ListenerRole::Admin
+    }
+}
+
+impl FromStr for ListenerRole {
+    type Err = ListenerConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "all" => Ok(ListenerRole::All),
+            "public" => Ok(ListenerRole::Public),
+            "admin" => Ok(ListenerRole::Admin),
+            other => Err(ListenerConfigError::UnknownRole(other.to_string())),
+        }
+    }
+}
+
+pub fn is_admin_path(path: &str) -> bool {
+    ADMIN_PATHS.contains(&path)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListenerConfig {
+    pub role: ListenerRole,
+    pub addr: SocketAddr,
+}
+
+impl ListenerConfig {
+    /// Reads `GATEWAY_LISTENERS` (`role=host:port,...`, e.g.
+    /// `public=0.0.0.0:8082,admin=127.0.0.1:9090`). Unset is a single `all`
+    /// listener on [`DEFAULT_LISTEN_ADDR`].
+    pub fn from_env() -> Result<Vec<Self>, ListenerConfigError> {
+        let Ok(list) = std::env::var("GATEWAY_LISTENERS") else {
+            return Ok(vec![Self {
+                role: ListenerRole::All,
+                addr: DEFAULT_LISTEN_ADDR.parse().expect("valid default address"),
+            }]);
+        };
+
+        let mut listeners = Vec::new();
+        let mut addrs = HashSet::new();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (role, addr) = entry
+                .split_once('=')
+                .ok_or_else(|| ListenerConfigError::InvalidEntry(entry.to_string()))?;
+            let addr: SocketAddr = addr
+                .trim()
+                .parse()
+                .map_err(|_| ListenerConfigError::InvalidEntry(entry.to_string()))?;
+            if !addrs.insert(addr) {
+                return Err(ListenerConfigError::DuplicateAddress(addr));
+            }
+            listeners.push(Self {
+                role: role.parse()?,
+                addr,
+            });
+        }
+
+        let has = |role: ListenerRole| listeners.iter().any(|listener| listener.role == role);
+        if !has(ListenerRole::Public) && !has(ListenerRole::All) {
+            return Err(ListenerConfigError::NoPublicListener);
+        }
+        if has(ListenerRole::All) && has(ListenerRole::Admin) {
+            return Err(ListenerConfigError::AllWithAdmin);
+        }
+        Ok(listeners)
+    }
+}
+
+/// Listeners for the startup log, e.g. `public=0.0.0.0:8082, admin=127.0.0.1:9090`
+pub fn describe(listeners: &[ListenerConfig]) -> String {
+    listeners
+        .iter()
+        .map(|listener| format!("{}={}", listener.role.name(), listener.addr))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,51 @@
+use crate::models::admin_model::SetLogSamplingRequest;
+use crate::telemetry::log_policy::log_policy;
+use serde_json::{json, Value};
+
+const INVALID_PARAMS_CODE: i32 = -32602;
+const METHOD_NOT_FOUND_CODE: i32 = -32601;
+
+/// Answers a JSON-RPC call to `/admin/log-sampling`: `set_log_sampling`
+/// (`success_percent`) or `get_log_sampling`. Changes apply to the gateway
+/// only; each service has its own `set_log_sampling` method, and requests
+/// through the gateway carry its decision downstream either way.
+pub fn handle_admin_call(body: &[u8]) -> Value {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(err) => return rpc_error(Value::Null, -32700, format!("Parse error: {}", err)),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let params = request.get("params").cloned().unwrap_or(json!({}));
+
+    let result = match request.get("method").and_then(Value::as_str) {
+        Some("set_log_sampling") => admin_set(params),
+        Some("get_log_sampling") => Ok(json!(log_policy().sampling_status())),
+        Some(method) => Err((
+            METHOD_NOT_FOUND_CODE,
+            format!("Method not found: {}", method),
+        )),
+        None => Err((INVALID_PARAMS_CODE, "Missing method".to_string())),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => rpc_error(id, code, message),
+    }
+}
+
+fn admin_set(params: Value) -> Result<Value, (i32, String)> {
+    let params: SetLogSamplingRequest =
+        serde_json::from_value(params).map_err(|err| (INVALID_PARAMS_CODE, err.to_string()))?;
+    let policy = log_policy();
+    policy
+        .set_success_percent(params.success_percent)
+        .map_err(|err| (INVALID_PARAMS_CODE, err.to_string()))?;
+    Ok(json!(policy.sampling_status()))
+}
+
+fn rpc_error(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
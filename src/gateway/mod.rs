@@ -0,0 +1,35 @@
+pub mod schema_validation;
+pub mod response_cache;
+pub mod upstream;
+pub mod snapshot;
+pub mod overload;
+pub mod status_policy;
+pub mod routing;
+pub mod redaction;
+pub mod health_events;
+pub mod idempotency;
+pub mod upstream_metrics;
+pub mod shared_health;
+pub mod deadline;
+pub mod route_debug;
+pub mod priority_lanes;
+pub mod sanitizer;
+pub mod routing_rules;
+pub mod slo;
+pub mod id_normalization;
+pub mod self_test;
+pub mod rate_limit_overrides;
+pub mod tcp_listener;
+pub mod hedging;
+pub mod org_keys;
+pub mod listeners;
+pub mod streaming;
+pub mod log_sampling;
+pub mod request_filter;
+pub mod capture;
+pub mod debug_dump;
+pub mod probe_coalescing;
+pub mod header_forwarding;
+pub mod balancing;
+pub mod draining;
+pub mod form_routes;
@@ -0,0 +1,56 @@
+use hyper::header::{HeaderMap, AUTHORIZATION};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum OrgKeysError {
+    #[error("Invalid GATEWAY_ORG_KEYS entry '{0}', expected token=org_id")]
+    InvalidEntry(String),
+}
+
+/// Which organization each bearer token acts for. The gateway forwards the
+/// mapped organization to upstreams in `X-Org-Id`, so product listings only
+/// include the private products that organization may see.
+#[derive(Debug, Default)]
+pub struct OrgKeys {
+    keys: HashMap<String, String>,
+}
+
+impl OrgKeys {
+    /// Reads `GATEWAY_ORG_KEYS` (`token=org_id,...`); unset maps no tokens,
+    /// so every caller only sees public products
+    pub fn from_env() -> Result<Self, OrgKeysError> {
+        let keys = std::env::var("GATEWAY_ORG_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .split_once('=')
+                    .map(|(token, org)| (token.trim().to_string(), org.trim().to_string()))
+                    .filter(|(token, org)| !token.is_empty() && !org.is_empty())
+                    .ok_or_else(|| OrgKeysError::InvalidEntry(entry.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+
+    /// The organization the request's bearer token is mapped to
+    pub fn org_for(&self, headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| self.keys.get(token.trim()))
+            .map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
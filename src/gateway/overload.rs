@@ -0,0 +1,288 @@
+use crate::telemetry::latency_histogram::LatencyHistogram;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Windows with fewer samples than this never raise the shedding level
+const MIN_WINDOW_SAMPLES: u64 = 20;
+/// Shedding steps down once p99 falls below this fraction of the threshold
+const RECOVERY_RATIO: f64 = 0.8;
+
+#[derive(Error, Debug)]
+pub enum OverloadConfigError {
+    #[error("Invalid route priority '{0}', expected path=priority")]
+    InvalidRoute(String),
+
+    #[error("Unknown priority '{0}', expected low, normal, high or critical")]
+    UnknownPriority(String),
+}
+
+/// Route priority; lower priorities are shed first and `Critical` never is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low = 0,
+    Normal = 1,
+    High = 2,
+    Critical = 3,
+}
+
+impl Priority {
+    const SHEDDABLE: [Priority; 3] = [Priority::Low, Priority::Normal, Priority::High];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Critical => "critical",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = OverloadConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "normal" => Ok(Priority::Normal),
+            "high" => Ok(Priority::High),
+            "critical" => Ok(Priority::Critical),
+            other => Err(OverloadConfigError::UnknownPriority(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OverloadConfig {
+    /// Windowed p99 above which shedding starts; `None` disables shedding
+    pub p99_threshold: Option<Duration>,
+    pub window: Duration,
+    /// Path prefix -> priority; the longest matching prefix wins
+    pub route_priorities: Vec<(String, Priority)>,
+    pub retry_after: Duration,
+}
+
+impl OverloadConfig {
+    /// Reads `GATEWAY_SHED_P99_MS` (0 or unset disables shedding),
+    /// `GATEWAY_SHED_WINDOW_SECS` (default 10), `GATEWAY_SHED_RETRY_AFTER_SECS`
+    /// (defaults to the window) and `GATEWAY_ROUTE_PRIORITIES`
+    /// (`/path=priority,...`, unlisted routes are `normal`).
+    pub fn from_env() -> Result<Self, OverloadConfigError> {
+        let env_u64 = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+
+        let window = Duration::from_secs(env_u64("GATEWAY_SHED_WINDOW_SECS").unwrap_or(10).max(1));
+        let mut route_priorities = std::env::var("GATEWAY_ROUTE_PRIORITIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (path, priority) = entry
+                    .split_once('=')
+                    .ok_or_else(|| OverloadConfigError::InvalidRoute(entry.to_string()))?;
+                Ok((path.trim().to_string(), priority.parse()?))
+            })
+            .collect::<Result<Vec<_>, OverloadConfigError>>()?;
+        route_priorities.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+        Ok(Self {
+            p99_threshold: env_u64("GATEWAY_SHED_P99_MS")
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            window,
+            route_priorities,
+            retry_after: env_u64("GATEWAY_SHED_RETRY_AFTER_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(window),
+        })
+    }
+}
+
+/// Tracks in-flight requests (globally and per client IP) and recent
+/// latency, and sheds the lowest-priority routes while p99 stays above the
+/// configured threshold. The shedding level is re-evaluated once per window:
+/// it rises one priority at a time while p99 is too high and steps back
+/// down once latency recovers.
+#[derive(Debug)]
+pub struct OverloadController {
+    config: OverloadConfig,
+    epoch: Instant,
+    window_latency: LatencyHistogram,
+    window_started_ms: AtomicU64,
+    last_window_p99_micros: AtomicU64,
+    /// Number of priorities, from `Low` upwards, currently being shed
+    shed_level: AtomicU8,
+    shed_total: AtomicU64,
+    in_flight: AtomicU64,
+    in_flight_by_ip: Mutex<HashMap<IpAddr, u64>>,
+}
+
+/// Held for the lifetime of an admitted request; releases the concurrency
+/// gauges and records the request latency when dropped.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    controller: Arc<OverloadController>,
+    client_ip: IpAddr,
+    started: Instant,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.controller
+            .window_latency
+            .record(self.started.elapsed());
+        self.controller.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        let mut by_ip = self.controller.in_flight_by_ip.lock().unwrap();
+        if let Some(count) = by_ip.get_mut(&self.client_ip) {
+            *count -= 1;
+            if *count == 0 {
+                by_ip.remove(&self.client_ip);
+            }
+        }
+    }
+}
+
+impl OverloadController {
+    pub fn new(config: OverloadConfig) -> Self {
+        Self {
+            config,
+            epoch: Instant::now(),
+            window_latency: LatencyHistogram::new(),
+            window_started_ms: AtomicU64::new(0),
+            last_window_p99_micros: AtomicU64::new(0),
+            shed_level: AtomicU8::new(0),
+            shed_total: AtomicU64::new(0),
+            in_flight: AtomicU64::new(0),
+            in_flight_by_ip: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn priority_for(&self, path: &str) -> Priority {
+        self.config
+            .route_priorities
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, priority)| *priority)
+            .unwrap_or(Priority::Normal)
+    }
+
+    /// Admits a request or, while its route's priority is being shed,
+    /// returns how long the client should wait before retrying.
+    pub fn admit(
+        self: &Arc<Self>,
+        client_ip: IpAddr,
+        path: &str,
+    ) -> Result<InFlightGuard, Duration> {
+        self.maybe_end_window();
+
+        let priority = self.priority_for(path);
+        if (priority as u8) < self.shed_level.load(Ordering::Relaxed) {
+            self.shed_total.fetch_add(1, Ordering::Relaxed);
+            return Err(self.config.retry_after);
+        }
+
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        *self
+            .in_flight_by_ip
+            .lock()
+            .unwrap()
+            .entry(client_ip)
+            .or_insert(0) += 1;
+
+        Ok(InFlightGuard {
+            controller: self.clone(),
+            client_ip,
+            started: Instant::now(),
+        })
+    }
+
+    /// Closes the current window if it has elapsed and adjusts the shedding
+    /// level. Only the caller that wins the window swap evaluates it.
+    fn maybe_end_window(&self) {
+        let now_ms = self.epoch.elapsed().as_millis() as u64;
+        let started_ms = self.window_started_ms.load(Ordering::Relaxed);
+        if now_ms.saturating_sub(started_ms) < self.config.window.as_millis() as u64 {
+            return;
+        }
+        if self
+            .window_started_ms
+            .compare_exchange(started_ms, now_ms, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            return;
+        }
+
+        let snapshot = self.window_latency.snapshot();
+        self.window_latency.reset();
+        self.last_window_p99_micros
+            .store((snapshot.p99_ms * 1000.0) as u64, Ordering::Relaxed);
+
+        let Some(threshold) = self.config.p99_threshold else {
+            return;
+        };
+        let threshold_ms = threshold.as_secs_f64() * 1000.0;
+        let level = self.shed_level.load(Ordering::Relaxed);
+
+        let new_level = if snapshot.count >= MIN_WINDOW_SAMPLES && snapshot.p99_ms > threshold_ms {
+            (level + 1).min(Priority::SHEDDABLE.len() as u8)
+        } else if snapshot.p99_ms < threshold_ms * RECOVERY_RATIO {
+            level.saturating_sub(1)
+        } else {
+            level
+        };
+
+        if new_level != level {
+            self.shed_level.store(new_level, Ordering::Relaxed);
+            if new_level > level {
+                warn!(
+                    "🛑 Overload: p99 {:.1}ms over {:.1}ms, shedding {:?} traffic",
+                    snapshot.p99_ms,
+                    threshold_ms,
+                    self.shedding()
+                );
+            } else {
+                info!(
+                    "🟢 Latency recovering (p99 {:.1}ms), shedding {:?} traffic",
+                    snapshot.p99_ms,
+                    self.shedding()
+                );
+            }
+        }
+    }
+
+    /// Names of the priorities currently being shed
+    pub fn shedding(&self) -> Vec<&'static str> {
+        let level = self.shed_level.load(Ordering::Relaxed) as usize;
+        Priority::SHEDDABLE[..level]
+            .iter()
+            .map(Priority::name)
+            .collect()
+    }
+
+    pub fn stats(&self) -> Value {
+        let by_ip: serde_json::Map<String, Value> = self
+            .in_flight_by_ip
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(ip, count)| (ip.to_string(), json!(count)))
+            .collect();
+
+        json!({
+            "in_flight": self.in_flight.load(Ordering::Relaxed),
+            "in_flight_by_ip": by_ip,
+            "shedding": self.shedding(),
+            "shed_total": self.shed_total.load(Ordering::Relaxed),
+            "last_window_p99_ms": self.last_window_p99_micros.load(Ordering::Relaxed) as f64 / 1000.0,
+            "p99_threshold_ms": self.config.p99_threshold.map(|t| t.as_millis() as u64),
+        })
+    }
+}
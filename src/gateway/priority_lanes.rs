@@ -0,0 +1,266 @@
+use hyper::header::{HeaderMap, AUTHORIZATION};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Request header a client can use to pick its lane (`high` or `low`)
+pub const PRIORITY_HEADER: &str = "x-priority";
+
+const DEFAULT_QUEUE_DEPTH: usize = 100;
+const DEFAULT_QUEUE_TIMEOUT_MS: u64 = 1000;
+
+#[derive(Error, Debug)]
+pub enum LaneConfigError {
+    #[error("Invalid lane entry '{0}', expected key=lane or lane=limit")]
+    InvalidEntry(String),
+
+    #[error("Unknown lane '{0}', expected high or low")]
+    UnknownLane(String),
+
+    #[error("Invalid limit '{0}', expected a positive number")]
+    InvalidLimit(String),
+}
+
+/// Traffic class with its own concurrency budget: interactive calls go to
+/// `High`, batch jobs such as exporters to `Low`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lane {
+    High,
+    Low,
+}
+
+impl Lane {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lane::High => "high",
+            Lane::Low => "low",
+        }
+    }
+}
+
+impl FromStr for Lane {
+    type Err = LaneConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "high" => Ok(Lane::High),
+            "low" => Ok(Lane::Low),
+            other => Err(LaneConfigError::UnknownLane(other.to_string())),
+        }
+    }
+}
+
+/// Why a request was turned away by its lane
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaneRejection {
+    #[error("queue full")]
+    QueueFull,
+
+    #[error("timed out waiting for a slot")]
+    TimedOut,
+}
+
+#[derive(Debug)]
+struct LaneState {
+    /// Most requests in flight at once; `None` is unbounded
+    limit: Option<usize>,
+    semaphore: Arc<Semaphore>,
+    waiting: AtomicUsize,
+    admitted: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl LaneState {
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit.unwrap_or(Semaphore::MAX_PERMITS))),
+            waiting: AtomicUsize::new(0),
+            admitted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    fn in_flight(&self) -> usize {
+        self.limit.unwrap_or(Semaphore::MAX_PERMITS) - self.semaphore.available_permits()
+    }
+
+    fn stats(&self) -> Value {
+        json!({
+            "limit": self.limit,
+            "in_flight": self.in_flight(),
+            "waiting": self.waiting.load(Ordering::Relaxed),
+            "admitted": self.admitted.load(Ordering::Relaxed),
+            "rejected": self.rejected.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Separate concurrency budgets for high and low priority traffic, so batch
+/// clients cannot use up the upstream capacity interactive calls need.
+///
+/// Each lane admits up to its limit of concurrent requests; beyond that
+/// requests wait in a bounded queue and are rejected when it is full or
+/// when no slot frees up in time. A request's lane comes from its bearer
+/// token when the token is mapped to one, otherwise from `X-Priority`,
+/// otherwise the default lane.
+#[derive(Debug)]
+pub struct PriorityLanes {
+    high: LaneState,
+    low: LaneState,
+    default_lane: Lane,
+    /// Bearer token -> lane; wins over the header
+    keys: HashMap<String, Lane>,
+    queue_depth: usize,
+    queue_timeout: Duration,
+}
+
+impl PriorityLanes {
+    /// Reads `GATEWAY_PRIORITY_LANES` (`high=64,low=8`, the most requests
+    /// each lane runs at once; unlisted lanes are unbounded),
+    /// `GATEWAY_LANE_KEYS` (`token=lane,...`), `GATEWAY_LANE_DEFAULT`
+    /// (default `high`), `GATEWAY_LANE_QUEUE_DEPTH` (default 100) and
+    /// `GATEWAY_LANE_QUEUE_TIMEOUT_MS` (default 1000). `None` when no lane
+    /// limits are set.
+    pub fn from_env() -> Result<Option<Self>, LaneConfigError> {
+        let Ok(limits) = std::env::var("GATEWAY_PRIORITY_LANES") else {
+            return Ok(None);
+        };
+        let mut limits: HashMap<Lane, usize> = parse_pairs(&limits)?
+            .into_iter()
+            .map(|(lane, limit)| {
+                let limit = limit
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|limit| *limit > 0)
+                    .ok_or(LaneConfigError::InvalidLimit(limit))?;
+                Ok((lane.parse()?, limit))
+            })
+            .collect::<Result<_, LaneConfigError>>()?;
+        if limits.is_empty() {
+            return Ok(None);
+        }
+
+        let keys = parse_pairs(&std::env::var("GATEWAY_LANE_KEYS").unwrap_or_default())?
+            .into_iter()
+            .map(|(key, lane)| Ok((key, lane.parse()?)))
+            .collect::<Result<_, LaneConfigError>>()?;
+        let default_lane = match std::env::var("GATEWAY_LANE_DEFAULT") {
+            Ok(lane) => lane.parse()?,
+            Err(_) => Lane::High,
+        };
+        let env_u64 = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+
+        Ok(Some(Self {
+            high: LaneState::new(limits.remove(&Lane::High)),
+            low: LaneState::new(limits.remove(&Lane::Low)),
+            default_lane,
+            keys,
+            queue_depth: env_u64("GATEWAY_LANE_QUEUE_DEPTH")
+                .map_or(DEFAULT_QUEUE_DEPTH, |depth| depth as usize),
+            queue_timeout: Duration::from_millis(
+                env_u64("GATEWAY_LANE_QUEUE_TIMEOUT_MS").unwrap_or(DEFAULT_QUEUE_TIMEOUT_MS),
+            ),
+        }))
+    }
+
+    fn state(&self, lane: Lane) -> &LaneState {
+        match lane {
+            Lane::High => &self.high,
+            Lane::Low => &self.low,
+        }
+    }
+
+    /// The lane a request runs in
+    pub fn classify(&self, headers: &HeaderMap) -> Lane {
+        let keyed = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| self.keys.get(token.trim()));
+        if let Some(lane) = keyed {
+            return *lane;
+        }
+        headers
+            .get(PRIORITY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(self.default_lane)
+    }
+
+    /// Waits for a slot in `lane`. The permit holds the slot until dropped.
+    pub async fn acquire(&self, lane: Lane) -> Result<OwnedSemaphorePermit, LaneRejection> {
+        let state = self.state(lane);
+        if let Ok(permit) = Arc::clone(&state.semaphore).try_acquire_owned() {
+            state.admitted.fetch_add(1, Ordering::Relaxed);
+            return Ok(permit);
+        }
+
+        if state.waiting.fetch_add(1, Ordering::Relaxed) >= self.queue_depth {
+            state.waiting.fetch_sub(1, Ordering::Relaxed);
+            state.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(LaneRejection::QueueFull);
+        }
+        let waited = tokio::time::timeout(
+            self.queue_timeout,
+            Arc::clone(&state.semaphore).acquire_owned(),
+        )
+        .await;
+        state.waiting.fetch_sub(1, Ordering::Relaxed);
+
+        match waited {
+            Ok(Ok(permit)) => {
+                state.admitted.fetch_add(1, Ordering::Relaxed);
+                Ok(permit)
+            }
+            // The semaphore is never closed, so only the timeout fails
+            _ => {
+                state.rejected.fetch_add(1, Ordering::Relaxed);
+                Err(LaneRejection::TimedOut)
+            }
+        }
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Human-readable limits for the startup log, e.g. `high=64, low=8`
+    pub fn describe(&self) -> String {
+        [Lane::High, Lane::Low]
+            .iter()
+            .map(|lane| match self.state(*lane).limit {
+                Some(limit) => format!("{}={}", lane.name(), limit),
+                None => format!("{}=unbounded", lane.name()),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn stats(&self) -> Value {
+        json!({
+            "default_lane": self.default_lane.name(),
+            "high": self.high.stats(),
+            "low": self.low.stats(),
+        })
+    }
+}
+
+/// Splits `a=b,c=d` into trimmed pairs, skipping empty entries
+fn parse_pairs(list: &str) -> Result<Vec<(String, String)>, LaneConfigError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (left, right) = entry
+                .split_once('=')
+                .ok_or_else(|| LaneConfigError::InvalidEntry(entry.to_string()))?;
+            Ok((left.trim().to_string(), right.trim().to_string()))
+        })
+        .collect()
+}
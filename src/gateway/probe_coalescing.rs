@@ -0,0 +1,125 @@
+use crate::gateway::upstream::ProbeError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Outcome of one upstream health probe
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    pub healthy: bool,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    #[serde(skip)]
+    pub probed_at: Instant,
+}
+
+/// How a caller got its probe result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeSource {
+    /// This caller ran the probe
+    Probe,
+    /// Waited for a probe another caller already had in flight
+    Coalesced,
+    /// A result still inside the freshness window
+    Cached,
+}
+
+/// Shares upstream health probes between local callers, so the periodic
+/// health checks, `/health` and any number of dashboards polling it cost
+/// the upstream one probe per freshness window.
+///
+/// Callers arriving while a probe is in flight wait for it instead of
+/// starting their own, and results are reused for `fresh_for` after they
+/// come back.
+#[derive(Debug)]
+pub struct ProbeCoalescer {
+    fresh_for: Duration,
+    slots: StdMutex<HashMap<&'static str, Arc<Mutex<Option<ProbeResult>>>>>,
+    probes: AtomicU64,
+    coalesced: AtomicU64,
+    cached: AtomicU64,
+}
+
+impl ProbeCoalescer {
+    pub fn new(fresh_for: Duration) -> Self {
+        Self {
+            fresh_for,
+            slots: StdMutex::new(HashMap::new()),
+            probes: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+            cached: AtomicU64::new(0),
+        }
+    }
+
+    /// Reads `GATEWAY_PROBE_FRESH_MS` (default 2000, `0` still coalesces
+    /// concurrent probes but never reuses a finished one)
+    pub fn from_env() -> Self {
+        let fresh_ms = std::env::var("GATEWAY_PROBE_FRESH_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2000);
+        Self::new(Duration::from_millis(fresh_ms))
+    }
+
+    pub fn fresh_for(&self) -> Duration {
+        self.fresh_for
+    }
+
+    /// The latest result for `service` if it is still fresh, otherwise the
+    /// result of `probe`, run by at most one caller at a time
+    pub async fn probe<F, Fut>(&self, service: &'static str, probe: F) -> (ProbeResult, ProbeSource)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(), ProbeError>>,
+    {
+        let slot = self.slot(service);
+        let in_flight = slot.try_lock().is_err();
+        let mut latest = slot.lock().await;
+
+        if let Some(result) = latest
+            .as_ref()
+            .filter(|result| result.probed_at.elapsed() <= self.fresh_for)
+        {
+            let source = if in_flight {
+                self.coalesced.fetch_add(1, Ordering::Relaxed);
+                ProbeSource::Coalesced
+            } else {
+                self.cached.fetch_add(1, Ordering::Relaxed);
+                ProbeSource::Cached
+            };
+            return (result.clone(), source);
+        }
+
+        self.probes.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+        let outcome = probe().await;
+        let result = ProbeResult {
+            healthy: outcome.is_ok(),
+            error: outcome.err().map(|err| err.to_string()),
+            latency_ms: started.elapsed().as_millis() as u64,
+            probed_at: Instant::now(),
+        };
+        *latest = Some(result.clone());
+        (result, ProbeSource::Probe)
+    }
+
+    /// Probes sent upstream and callers served without one
+    pub fn stats(&self) -> serde_json::Value {
+        serde_json::json!({
+            "fresh_for_ms": self.fresh_for.as_millis() as u64,
+            "probes": self.probes.load(Ordering::Relaxed),
+            "coalesced": self.coalesced.load(Ordering::Relaxed),
+            "cached": self.cached.load(Ordering::Relaxed),
+        })
+    }
+
+    fn slot(&self, service: &'static str) -> Arc<Mutex<Option<ProbeResult>>> {
+        let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(slots.entry(service).or_default())
+    }
+}
@@ -0,0 +1,375 @@
+use chrono::{DateTime, Utc};
+use hyper::header::{HeaderMap, AUTHORIZATION};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{PoisonError, RwLock};
+use thiserror::Error;
+
+/// Request header naming the tenant a call is made for
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+const INVALID_PARAMS_CODE: i32 = -32602;
+const INTERNAL_ERROR_CODE: i32 = -32603;
+const METHOD_NOT_FOUND_CODE: i32 = -32601;
+
+#[derive(Error, Debug)]
+pub enum RateLimitOverrideError {
+    #[error("Failed to read rate limit overrides {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to parse rate limit overrides {path}: {source}")]
+    Parse {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Failed to save rate limit overrides {path}: {source}")]
+    Save {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Who an override applies to. API keys are kept as fingerprints, so the
+/// overrides file never holds a usable credential.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrideSubject {
+    /// Fingerprint of the `Authorization: Bearer` token, see [`key_fingerprint`]
+    ApiKey(String),
+    /// The `X-Tenant-Id` header
+    Tenant(String),
+}
+
+impl OverrideSubject {
+    /// The rate limiter bucket shared by every request of this subject
+    pub fn bucket(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for OverrideSubject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverrideSubject::ApiKey(fingerprint) => write!(f, "api_key:{}", fingerprint),
+            OverrideSubject::Tenant(tenant) => write!(f, "tenant:{}", tenant),
+        }
+    }
+}
+
+/// First 16 bytes of the key's SHA-256, hex encoded
+pub fn key_fingerprint(api_key: &str) -> String {
+    digest(&SHA256, api_key.as_bytes()).as_ref()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A per-minute limit replacing `RATE_LIMIT_PER_MINUTE` for one subject
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitOverride {
+    pub subject: OverrideSubject,
+    pub limit_per_minute: u64,
+    /// The override is ignored, and dropped on the next save, after this
+    pub expires_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+    pub set_at: DateTime<Utc>,
+}
+
+impl RateLimitOverride {
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SubjectParams {
+    api_key: Option<String>,
+    tenant: Option<String>,
+}
+
+impl SubjectParams {
+    fn subject(self) -> Result<OverrideSubject, String> {
+        match (self.api_key, self.tenant) {
+            (Some(api_key), None) if !api_key.trim().is_empty() => {
+                Ok(OverrideSubject::ApiKey(key_fingerprint(api_key.trim())))
+            }
+            (None, Some(tenant)) if !tenant.trim().is_empty() => {
+                Ok(OverrideSubject::Tenant(tenant.trim().to_string()))
+            }
+            _ => Err("Exactly one of api_key or tenant is required".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SetOverrideParams {
+    #[serde(flatten)]
+    subject: SubjectParams,
+    limit_per_minute: u64,
+    expires_at: Option<DateTime<Utc>>,
+    reason: Option<String>,
+}
+
+/// Rate limits set at runtime for API keys and tenants, e.g. to let a big
+/// customer through on launch day without a redeploy.
+///
+/// With `GATEWAY_RATE_LIMIT_OVERRIDES` set, every change is written to that
+/// file and loaded again on startup; otherwise overrides last until the
+/// gateway restarts.
+#[derive(Debug, Default)]
+pub struct RateLimitOverrides {
+    path: Option<PathBuf>,
+    overrides: RwLock<HashMap<OverrideSubject, RateLimitOverride>>,
+}
+
+impl RateLimitOverrides {
+    /// Loads the file named by `GATEWAY_RATE_LIMIT_OVERRIDES`, if set. A
+    /// missing file starts empty and is created on the first change.
+    pub fn from_env() -> Result<Self, RateLimitOverrideError> {
+        let Ok(path) = std::env::var("GATEWAY_RATE_LIMIT_OVERRIDES") else {
+            return Ok(Self::default());
+        };
+        let path = PathBuf::from(path);
+        let path_str = path.display().to_string();
+
+        let overrides: Vec<RateLimitOverride> = match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                serde_json::from_str(&contents).map_err(|source| RateLimitOverrideError::Parse {
+                    path: path_str,
+                    source,
+                })?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(source) => {
+                return Err(RateLimitOverrideError::Io {
+                    path: path_str,
+                    source,
+                })
+            }
+        };
+
+        let now = Utc::now();
+        Ok(Self {
+            path: Some(path),
+            overrides: RwLock::new(
+                overrides
+                    .into_iter()
+                    .filter(|entry| entry.is_active(now))
+                    .map(|entry| (entry.subject.clone(), entry))
+                    .collect(),
+            ),
+        })
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Active overrides, sorted by subject
+    pub fn list(&self) -> Vec<RateLimitOverride> {
+        let now = Utc::now();
+        let mut active: Vec<RateLimitOverride> = self
+            .overrides
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|entry| entry.is_active(now))
+            .cloned()
+            .collect();
+        active.sort_by_key(|entry| entry.subject.to_string());
+        active
+    }
+
+    /// The override for a request, if any. An API key override wins over
+    /// one for the tenant.
+    pub fn limit_for(&self, headers: &HeaderMap) -> Option<(OverrideSubject, u64)> {
+        let overrides = self
+            .overrides
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        if overrides.is_empty() {
+            return None;
+        }
+
+        let api_key = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| OverrideSubject::ApiKey(key_fingerprint(token.trim())));
+        let tenant = headers
+            .get(TENANT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|tenant| OverrideSubject::Tenant(tenant.trim().to_string()));
+
+        let now = Utc::now();
+        [api_key, tenant]
+            .into_iter()
+            .flatten()
+            .filter_map(|subject| overrides.get(&subject))
+            .find(|entry| entry.is_active(now))
+            .map(|entry| (entry.subject.clone(), entry.limit_per_minute))
+    }
+
+    pub fn set(
+        &self,
+        subject: OverrideSubject,
+        limit_per_minute: u64,
+        expires_at: Option<DateTime<Utc>>,
+        reason: Option<String>,
+    ) -> Result<RateLimitOverride, RateLimitOverrideError> {
+        let entry = RateLimitOverride {
+            subject: subject.clone(),
+            limit_per_minute,
+            expires_at,
+            reason,
+            set_at: Utc::now(),
+        };
+        let mut overrides = self
+            .overrides
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let previous = overrides.insert(subject.clone(), entry.clone());
+        if let Err(err) = self.save(&overrides) {
+            // Keep memory and the file in agreement
+            match previous {
+                Some(previous) => overrides.insert(subject, previous),
+                None => overrides.remove(&subject),
+            };
+            return Err(err);
+        }
+        Ok(entry)
+    }
+
+    /// Removes a subject's override; `false` if it had none
+    pub fn remove(&self, subject: &OverrideSubject) -> Result<bool, RateLimitOverrideError> {
+        let mut overrides = self
+            .overrides
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some(previous) = overrides.remove(subject) else {
+            return Ok(false);
+        };
+        if let Err(err) = self.save(&overrides) {
+            overrides.insert(subject.clone(), previous);
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    /// Writes the active overrides next to the file and renames it over,
+    /// so a crash never leaves half a file behind
+    fn save(
+        &self,
+        overrides: &HashMap<OverrideSubject, RateLimitOverride>,
+    ) -> Result<(), RateLimitOverrideError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        let active: Vec<&RateLimitOverride> = overrides
+            .values()
+            .filter(|entry| entry.is_active(now))
+            .collect();
+        let contents = serde_json::to_string_pretty(&active).unwrap_or_else(|_| "[]".to_string());
+
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, contents)
+            .and_then(|_| std::fs::rename(&temp, path))
+            .map_err(|source| RateLimitOverrideError::Save {
+                path: path.display().to_string(),
+                source,
+            })
+    }
+
+    /// Answers one JSON-RPC call to the admin endpoint:
+    ///
+    /// - `set_rate_limit_override` `{api_key | tenant, limit_per_minute, expires_at?, reason?}`
+    /// - `remove_rate_limit_override` `{api_key | tenant}`
+    /// - `list_rate_limit_overrides`
+    pub fn handle_admin_call(&self, body: &[u8]) -> Value {
+        let request: Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(err) => return rpc_error(Value::Null, -32700, format!("Parse error: {}", err)),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+
+        let result = match request.get("method").and_then(Value::as_str) {
+            Some("set_rate_limit_override") => self.admin_set(params),
+            Some("remove_rate_limit_override") => self.admin_remove(params),
+            Some("list_rate_limit_overrides") => Ok(json!(self.list())),
+            Some(method) => Err((
+                METHOD_NOT_FOUND_CODE,
+                format!("Method not found: {}", method),
+            )),
+            None => Err((INVALID_PARAMS_CODE, "Missing method".to_string())),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => rpc_error(id, code, message),
+        }
+    }
+
+    fn admin_set(&self, params: Value) -> Result<Value, (i32, String)> {
+        let params: SetOverrideParams =
+            serde_json::from_value(params).map_err(|err| (INVALID_PARAMS_CODE, err.to_string()))?;
+        if params.limit_per_minute == 0 {
+            return Err((
+                INVALID_PARAMS_CODE,
+                "limit_per_minute must be positive".to_string(),
+            ));
+        }
+        if params
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err((
+                INVALID_PARAMS_CODE,
+                "expires_at must be in the future".to_string(),
+            ));
+        }
+        let subject = params
+            .subject
+            .subject()
+            .map_err(|message| (INVALID_PARAMS_CODE, message))?;
+
+        self.set(
+            subject,
+            params.limit_per_minute,
+            params.expires_at,
+            params.reason,
+        )
+        .map(|entry| json!(entry))
+        .map_err(|err| (INTERNAL_ERROR_CODE, err.to_string()))
+    }
+
+    fn admin_remove(&self, params: Value) -> Result<Value, (i32, String)> {
+        let subject = serde_json::from_value::<SubjectParams>(params)
+            .map_err(|err| err.to_string())
+            .and_then(SubjectParams::subject)
+            .map_err(|message| (INVALID_PARAMS_CODE, message))?;
+        let removed = self
+            .remove(&subject)
+            .map_err(|err| (INTERNAL_ERROR_CODE, err.to_string()))?;
+        Ok(json!({ "subject": subject, "removed": removed }))
+    }
+}
+
+fn rpc_error(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
@@ -0,0 +1,367 @@
+use crate::services::method_namespaces::flat_method_name;
+use hyper::header::{HeaderMap, AUTHORIZATION};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RedactionError {
+    #[error("Failed to read redaction rules {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid redaction rules {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid redaction path '{path}': {message}")]
+    InvalidPath { path: String, message: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+    /// `*` or `[*]`: every member of an object or element of an array
+    Wildcard,
+}
+
+/// A JSONPath-like location inside a method's `result`.
+///
+/// Supports an optional leading `$` (the result), `.field`, `['field']`,
+/// `[n]` and the `*` / `[*]` wildcards, e.g. `$.users[*].email`. Recursive
+/// descent and filters are not supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultPath {
+    segments: Vec<Segment>,
+}
+
+impl ResultPath {
+    pub fn parse(path: &str) -> Result<Self, RedactionError> {
+        let invalid = |message: &str| RedactionError::InvalidPath {
+            path: path.to_string(),
+            message: message.to_string(),
+        };
+
+        let trimmed = path.trim();
+        // The leading `$.` may be left out, e.g. `users[*].email`
+        let normalized = match trimmed.strip_prefix('$') {
+            Some(rest) => rest.to_string(),
+            None if trimmed.starts_with('[') => trimmed.to_string(),
+            None => format!(".{}", trimmed),
+        };
+        let mut rest = normalized.as_str();
+        let mut segments = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                let name = &after[..end];
+                if name.is_empty() {
+                    return Err(invalid("empty field name"));
+                }
+                segments.push(if name == "*" {
+                    Segment::Wildcard
+                } else {
+                    Segment::Field(name.to_string())
+                });
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("unclosed '['"))?;
+                let inner = after[..end].trim();
+                segments.push(if inner == "*" {
+                    Segment::Wildcard
+                } else if let Some(name) = inner
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+                {
+                    Segment::Field(name.to_string())
+                } else {
+                    Segment::Index(
+                        inner
+                            .parse()
+                            .map_err(|_| invalid("expected an index, '*' or a quoted name"))?,
+                    )
+                });
+                rest = &after[end + 1..];
+            } else {
+                return Err(invalid("expected '.' or '['"));
+            }
+        }
+
+        if segments.is_empty() {
+            return Err(invalid("the whole result cannot be redacted"));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Redacts every match in `value`. Object members are removed, or
+    /// replaced when a `replacement` is given; array elements are replaced
+    /// (with `null` by default) so the remaining indexes stay stable.
+    /// Returns the number of redacted values.
+    fn redact(&self, value: &mut Value, replacement: Option<&Value>) -> usize {
+        redact_at(value, &self.segments, replacement)
+    }
+}
+
+fn redact_at(value: &mut Value, segments: &[Segment], replacement: Option<&Value>) -> usize {
+    let Some((segment, rest)) = segments.split_first() else {
+        return 0;
+    };
+
+    if rest.is_empty() {
+        return match (segment, value) {
+            (Segment::Field(name), Value::Object(map)) => match replacement {
+                Some(replacement) => map
+                    .get_mut(name)
+                    .map(|field| *field = replacement.clone())
+                    .is_some() as usize,
+                None => map.remove(name).is_some() as usize,
+            },
+            (Segment::Wildcard, Value::Object(map)) => {
+                let count = map.len();
+                match replacement {
+                    Some(replacement) => map
+                        .values_mut()
+                        .for_each(|field| *field = replacement.clone()),
+                    None => map.clear(),
+                }
+                count
+            }
+            (Segment::Index(index), Value::Array(items)) => items
+                .get_mut(*index)
+                .map(|item| *item = replacement.cloned().unwrap_or(Value::Null))
+                .is_some() as usize,
+            (Segment::Wildcard, Value::Array(items)) => {
+                items
+                    .iter_mut()
+                    .for_each(|item| *item = replacement.cloned().unwrap_or(Value::Null));
+                items.len()
+            }
+            _ => 0,
+        };
+    }
+
+    match (segment, value) {
+        (Segment::Field(name), Value::Object(map)) => map
+            .get_mut(name)
+            .map_or(0, |field| redact_at(field, rest, replacement)),
+        (Segment::Wildcard, Value::Object(map)) => map
+            .values_mut()
+            .map(|field| redact_at(field, rest, replacement))
+            .sum(),
+        (Segment::Index(index), Value::Array(items)) => items
+            .get_mut(*index)
+            .map_or(0, |item| redact_at(item, rest, replacement)),
+        (Segment::Wildcard, Value::Array(items)) => items
+            .iter_mut()
+            .map(|item| redact_at(item, rest, replacement))
+            .sum(),
+        _ => 0,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleFile {
+    method: String,
+    paths: Vec<String>,
+    #[serde(default)]
+    except: Vec<String>,
+    #[serde(default)]
+    replacement: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    trust_levels: HashMap<String, String>,
+    #[serde(default)]
+    rules: Vec<RuleFile>,
+}
+
+/// Fields to strip from one method's results
+#[derive(Debug, Clone)]
+pub struct RedactionRule {
+    pub paths: Vec<ResultPath>,
+    /// Trust levels that see the fields unredacted
+    pub except: Vec<String>,
+    /// Value written over redacted fields instead of removing them
+    pub replacement: Option<Value>,
+}
+
+/// Redaction rules applied to JSON-RPC results before they reach clients,
+/// so one upstream API can serve callers of different trust levels.
+///
+/// Callers are identified by their bearer token; tokens listed in
+/// `trust_levels` get that level, anyone else (including unauthenticated
+/// callers) has none. A rule applies to every caller whose level is not in
+/// its `except` list. Only `result` members are redacted, never errors.
+#[derive(Debug, Default)]
+pub struct RedactionPolicy {
+    /// Bearer token -> trust level
+    trust_levels: HashMap<String, String>,
+    rules: HashMap<String, Vec<RedactionRule>>,
+}
+
+impl RedactionPolicy {
+    /// Loads the rules from the file named by `GATEWAY_REDACTION_RULES`, or
+    /// returns a policy that redacts nothing when the variable is unset.
+    pub fn from_env() -> Result<Self, RedactionError> {
+        match std::env::var("GATEWAY_REDACTION_RULES") {
+            Ok(path) => Self::from_file(path),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RedactionError> {
+        let path_str = path.as_ref().display().to_string();
+        let contents = std::fs::read_to_string(&path).map_err(|source| RedactionError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+        let file: RulesFile =
+            serde_json::from_str(&contents).map_err(|source| RedactionError::Parse {
+                path: path_str,
+                source,
+            })?;
+
+        let mut policy = Self {
+            trust_levels: file.trust_levels,
+            rules: HashMap::new(),
+        };
+        for rule in file.rules {
+            let paths = rule
+                .paths
+                .iter()
+                .map(|path| ResultPath::parse(path))
+                .collect::<Result<_, _>>()?;
+            policy.add_rule(
+                &rule.method,
+                RedactionRule {
+                    paths,
+                    except: rule.except,
+                    replacement: rule.replacement,
+                },
+            );
+        }
+        Ok(policy)
+    }
+
+    /// Adds a rule for `method` (flat or namespaced)
+    pub fn add_rule(&mut self, method: &str, rule: RedactionRule) {
+        self.rules
+            .entry(flat_method_name(method).to_string())
+            .or_default()
+            .push(rule);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.values().map(Vec::len).sum()
+    }
+
+    /// The caller's trust level, from its `Authorization: Bearer` token
+    pub fn trust_level(&self, headers: &HeaderMap) -> Option<&str> {
+        let token = headers
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?
+            .trim();
+        self.trust_levels.get(token).map(String::as_str)
+    }
+
+    /// Works out which rules apply to each call in a request body. Returns
+    /// `None` when nothing in the response needs redacting.
+    pub fn plan(&self, headers: &HeaderMap, body: &[u8]) -> Option<RedactionPlan> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let request: Value = serde_json::from_slice(body).ok()?;
+        let level = self.trust_level(headers);
+        let calls = match &request {
+            Value::Array(calls) => calls.iter().collect::<Vec<_>>(),
+            call => vec![call],
+        };
+
+        let mut calls_to_redact = Vec::new();
+        for call in calls {
+            let (Some(id), Some(method)) = (call.get("id"), call["method"].as_str()) else {
+                continue;
+            };
+            let rules: Vec<RedactionRule> = self
+                .rules
+                .get(flat_method_name(method))
+                .into_iter()
+                .flatten()
+                .filter(|rule| !level.is_some_and(|level| rule.except.iter().any(|e| e == level)))
+                .cloned()
+                .collect();
+            if !rules.is_empty() {
+                calls_to_redact.push((id.clone(), rules));
+            }
+        }
+
+        if calls_to_redact.is_empty() {
+            None
+        } else {
+            Some(RedactionPlan {
+                calls: calls_to_redact,
+            })
+        }
+    }
+}
+
+/// The rules to apply to one response, keyed by JSON-RPC id
+#[derive(Debug, Clone)]
+pub struct RedactionPlan {
+    calls: Vec<(Value, Vec<RedactionRule>)>,
+}
+
+impl RedactionPlan {
+    /// Redacts the results of the planned calls in a single or batch
+    /// response. Returns the number of redacted values.
+    pub fn apply(&self, response: &mut Value) -> usize {
+        match response {
+            Value::Array(entries) => entries
+                .iter_mut()
+                .map(|entry| self.apply_entry(entry))
+                .sum(),
+            entry => self.apply_entry(entry),
+        }
+    }
+
+    fn apply_entry(&self, entry: &mut Value) -> usize {
+        let Some(rules) = self
+            .calls
+            .iter()
+            .find(|(id, _)| entry.get("id") == Some(id))
+            .map(|(_, rules)| rules)
+        else {
+            return 0;
+        };
+        let Some(result) = entry.get_mut("result") else {
+            return 0;
+        };
+
+        rules
+            .iter()
+            .flat_map(|rule| {
+                rule.paths
+                    .iter()
+                    .map(|path| (path, rule.replacement.as_ref()))
+            })
+            .map(|(path, replacement)| path.redact(result, replacement))
+            .sum()
+    }
+}
@@ -0,0 +1,459 @@
+use crate::gateway::sanitizer::glob_matches;
+use chrono::{DateTime, Utc};
+use hyper::header::{HeaderMap, HeaderName, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{PoisonError, RwLock};
+use thiserror::Error;
+
+const INVALID_PARAMS_CODE: i32 = -32602;
+const INTERNAL_ERROR_CODE: i32 = -32603;
+const METHOD_NOT_FOUND_CODE: i32 = -32601;
+
+#[derive(Error, Debug)]
+pub enum RequestFilterError {
+    #[error("Failed to read filter rules {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to parse filter rules {path}: {source}")]
+    Parse {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid filter rule '{name}': {message}")]
+    InvalidRule { name: String, message: String },
+
+    #[error("Failed to save filter rules {path}: {source}")]
+    Save {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterAction {
+    /// Let the request through without checking later rules
+    Allow,
+    /// Refuse the request with `403`
+    Deny,
+}
+
+/// A rule as configured: one matcher, `cidr`, `user_agent` or `header`
+/// with `value`. Patterns are case-insensitive and `*` matches any run of
+/// characters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterRule {
+    pub name: String,
+    pub action: FilterAction,
+    /// Client address range such as `203.0.113.0/24`, or a single address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cidr: Option<String>,
+    /// Pattern for the `User-Agent` header, e.g. `*python-requests*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Header whose value must match `value`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    pub reason: Option<String>,
+    pub set_at: DateTime<Utc>,
+}
+
+/// An address range in CIDR notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IpNet {
+    addr: IpAddr,
+    prefix: u32,
+}
+
+impl IpNet {
+    fn parse(text: &str) -> Option<Self> {
+        let (addr, prefix) = match text.split_once('/') {
+            Some((addr, prefix)) => (addr.parse().ok()?, Some(prefix.parse().ok()?)),
+            None => (text.parse().ok()?, None),
+        };
+        let bits = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = prefix.unwrap_or(bits);
+        (prefix <= bits).then_some(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 clients reaching an IPv6 socket appear as mapped addresses
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            ip => ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Cidr(IpNet),
+    UserAgent(String),
+    Header { name: HeaderName, pattern: String },
+}
+
+impl Matcher {
+    fn for_rule(rule: &FilterRule) -> Result<Self, String> {
+        match (&rule.cidr, &rule.user_agent, &rule.header) {
+            (Some(cidr), None, None) => IpNet::parse(cidr.trim())
+                .map(Matcher::Cidr)
+                .ok_or_else(|| format!("invalid cidr '{}'", cidr)),
+            (None, Some(pattern), None) if !pattern.trim().is_empty() => {
+                Ok(Matcher::UserAgent(pattern.trim().to_lowercase()))
+            }
+            (None, None, Some(name)) => {
+                let name = HeaderName::from_bytes(name.trim().to_lowercase().as_bytes())
+                    .map_err(|_| format!("invalid header name '{}'", name))?;
+                let pattern = rule
+                    .value
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|pattern| !pattern.is_empty())
+                    .ok_or("header rules need a value pattern")?;
+                Ok(Matcher::Header {
+                    name,
+                    pattern: pattern.to_lowercase(),
+                })
+            }
+            _ => Err("exactly one of cidr, user_agent or header is required".to_string()),
+        }
+    }
+
+    fn matches(&self, ip: IpAddr, headers: &HeaderMap) -> bool {
+        let header_matches = |name: &HeaderName, pattern: &str| {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .any(|value| glob_matches(pattern, &value.to_lowercase()))
+        };
+        match self {
+            Matcher::Cidr(net) => net.contains(ip),
+            Matcher::UserAgent(pattern) => header_matches(&USER_AGENT, pattern),
+            Matcher::Header { name, pattern } => header_matches(name, pattern),
+        }
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Matcher::Cidr(net) => write!(f, "client in {}/{}", net.addr, net.prefix),
+            Matcher::UserAgent(pattern) => write!(f, "user-agent matches {}", pattern),
+            Matcher::Header { name, pattern } => write!(f, "{} matches {}", name, pattern),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    rule: FilterRule,
+    matcher: Matcher,
+    matches: AtomicU64,
+}
+
+impl CompiledRule {
+    fn new(rule: FilterRule) -> Result<Self, RequestFilterError> {
+        let matcher =
+            Matcher::for_rule(&rule).map_err(|message| RequestFilterError::InvalidRule {
+                name: rule.name.clone(),
+                message,
+            })?;
+        Ok(Self {
+            rule,
+            matcher,
+            matches: AtomicU64::new(0),
+        })
+    }
+
+    fn describe(&self) -> Value {
+        json!({
+            "rule": self.rule,
+            "matches": self.matches.load(Ordering::Relaxed),
+            "description": self.matcher.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SetRuleParams {
+    name: String,
+    action: FilterAction,
+    cidr: Option<String>,
+    user_agent: Option<String>,
+    header: Option<String>,
+    value: Option<String>,
+    reason: Option<String>,
+    /// Where to put a new rule; existing rules keep their place
+    position: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NameParams {
+    name: String,
+}
+
+/// Allow and deny rules checked before routing, for quick abuse
+/// mitigation: blocking an address range, a scraper's user agent or a
+/// header value without a redeploy.
+///
+/// Rules are tried in order and the first match decides; requests no rule
+/// matches are allowed, so an `allow` rule placed first exempts e.g. the
+/// office network from later `deny` rules. Each rule counts its matches.
+///
+/// With `GATEWAY_FILTER_RULES` set, every change is written to that file
+/// and loaded again on startup; otherwise rules last until the gateway
+/// restarts.
+#[derive(Debug, Default)]
+pub struct RequestFilter {
+    path: Option<PathBuf>,
+    rules: RwLock<Vec<CompiledRule>>,
+    denied: AtomicU64,
+}
+
+impl RequestFilter {
+    /// Loads the file named by `GATEWAY_FILTER_RULES`, if set. A missing
+    /// file starts empty and is created on the first change.
+    pub fn from_env() -> Result<Self, RequestFilterError> {
+        let Ok(path) = std::env::var("GATEWAY_FILTER_RULES") else {
+            return Ok(Self::default());
+        };
+        let path = PathBuf::from(path);
+        let path_str = path.display().to_string();
+
+        let rules: Vec<FilterRule> = match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                serde_json::from_str(&contents).map_err(|source| RequestFilterError::Parse {
+                    path: path_str,
+                    source,
+                })?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(source) => {
+                return Err(RequestFilterError::Io {
+                    path: path_str,
+                    source,
+                })
+            }
+        };
+
+        Ok(Self {
+            path: Some(path),
+            rules: RwLock::new(
+                rules
+                    .into_iter()
+                    .map(CompiledRule::new)
+                    .collect::<Result<_, _>>()?,
+            ),
+            denied: AtomicU64::new(0),
+        })
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Requests refused by a `deny` rule since startup
+    pub fn denied(&self) -> u64 {
+        self.denied.load(Ordering::Relaxed)
+    }
+
+    /// The name of the `deny` rule refusing this request, if any
+    pub fn check(&self, ip: IpAddr, headers: &HeaderMap) -> Option<String> {
+        let rules = self.rules.read().unwrap_or_else(PoisonError::into_inner);
+        let rule = rules
+            .iter()
+            .find(|rule| rule.matcher.matches(ip, headers))?;
+        rule.matches.fetch_add(1, Ordering::Relaxed);
+        match rule.rule.action {
+            FilterAction::Allow => None,
+            FilterAction::Deny => {
+                self.denied.fetch_add(1, Ordering::Relaxed);
+                Some(rule.rule.name.clone())
+            }
+        }
+    }
+
+    /// Rules in evaluation order with their match counts
+    pub fn list(&self) -> Vec<Value> {
+        self.rules
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(CompiledRule::describe)
+            .collect()
+    }
+
+    /// Adds a rule, or replaces the one with the same name in place. New
+    /// rules go at `position`, or last.
+    pub fn set(
+        &self,
+        rule: FilterRule,
+        position: Option<usize>,
+    ) -> Result<Value, RequestFilterError> {
+        let compiled = CompiledRule::new(rule)?;
+        let description = compiled.describe();
+        let mut rules = self.rules.write().unwrap_or_else(PoisonError::into_inner);
+        let existing = rules
+            .iter()
+            .position(|rule| rule.rule.name == compiled.rule.name);
+        let (index, previous) = match existing {
+            Some(index) => (index, Some(std::mem::replace(&mut rules[index], compiled))),
+            None => {
+                let index = position.unwrap_or(rules.len()).min(rules.len());
+                rules.insert(index, compiled);
+                (index, None)
+            }
+        };
+        if let Err(err) = self.save(&rules) {
+            // Keep memory and the file in agreement
+            match previous {
+                Some(previous) => rules[index] = previous,
+                None => {
+                    rules.remove(index);
+                }
+            }
+            return Err(err);
+        }
+        Ok(description)
+    }
+
+    /// Removes a rule by name; `false` if there was none
+    pub fn remove(&self, name: &str) -> Result<bool, RequestFilterError> {
+        let mut rules = self.rules.write().unwrap_or_else(PoisonError::into_inner);
+        let Some(index) = rules.iter().position(|rule| rule.rule.name == name) else {
+            return Ok(false);
+        };
+        let previous = rules.remove(index);
+        if let Err(err) = self.save(&rules) {
+            rules.insert(index, previous);
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    /// Writes the rules next to the file and renames it over, so a crash
+    /// never leaves half a file behind
+    fn save(&self, rules: &[CompiledRule]) -> Result<(), RequestFilterError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let rules: Vec<&FilterRule> = rules.iter().map(|rule| &rule.rule).collect();
+        let contents = serde_json::to_string_pretty(&rules).unwrap_or_else(|_| "[]".to_string());
+
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, contents)
+            .and_then(|_| std::fs::rename(&temp, path))
+            .map_err(|source| RequestFilterError::Save {
+                path: path.display().to_string(),
+                source,
+            })
+    }
+
+    /// Answers one JSON-RPC call to the admin endpoint:
+    ///
+    /// - `set_filter_rule` `{name, action, cidr | user_agent | header + value, reason?, position?}`
+    /// - `remove_filter_rule` `{name}`
+    /// - `list_filter_rules`
+    pub fn handle_admin_call(&self, body: &[u8]) -> Value {
+        let request: Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(err) => return rpc_error(Value::Null, -32700, format!("Parse error: {}", err)),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+
+        let result = match request.get("method").and_then(Value::as_str) {
+            Some("set_filter_rule") => self.admin_set(params),
+            Some("remove_filter_rule") => self.admin_remove(params),
+            Some("list_filter_rules") => Ok(json!({
+                "rules": self.list(),
+                "denied": self.denied(),
+            })),
+            Some(method) => Err((
+                METHOD_NOT_FOUND_CODE,
+                format!("Method not found: {}", method),
+            )),
+            None => Err((INVALID_PARAMS_CODE, "Missing method".to_string())),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => rpc_error(id, code, message),
+        }
+    }
+
+    fn admin_set(&self, params: Value) -> Result<Value, (i32, String)> {
+        let params: SetRuleParams =
+            serde_json::from_value(params).map_err(|err| (INVALID_PARAMS_CODE, err.to_string()))?;
+        if params.name.trim().is_empty() {
+            return Err((INVALID_PARAMS_CODE, "name cannot be empty".to_string()));
+        }
+        let rule = FilterRule {
+            name: params.name.trim().to_string(),
+            action: params.action,
+            cidr: params.cidr,
+            user_agent: params.user_agent,
+            header: params.header,
+            value: params.value,
+            reason: params.reason,
+            set_at: Utc::now(),
+        };
+
+        self.set(rule, params.position).map_err(|err| match err {
+            RequestFilterError::InvalidRule { .. } => (INVALID_PARAMS_CODE, err.to_string()),
+            err => (INTERNAL_ERROR_CODE, err.to_string()),
+        })
+    }
+
+    fn admin_remove(&self, params: Value) -> Result<Value, (i32, String)> {
+        let params: NameParams =
+            serde_json::from_value(params).map_err(|err| (INVALID_PARAMS_CODE, err.to_string()))?;
+        let removed = self
+            .remove(params.name.trim())
+            .map_err(|err| (INTERNAL_ERROR_CODE, err.to_string()))?;
+        Ok(json!({ "name": params.name.trim(), "removed": removed }))
+    }
+}
+
+fn rpc_error(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
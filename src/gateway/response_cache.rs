@@ -0,0 +1,392 @@
+use crate::gateway::rate_limit_overrides::key_fingerprint;
+use crate::services::method_namespaces::flat_method_name;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// JSON-RPC methods whose successful responses may be cached
+    pub methods: HashSet<String>,
+    /// How long an entry is served as fresh
+    pub ttl: Duration,
+    /// How long past `ttl` an entry may still be served while it is refreshed
+    pub max_stale: Duration,
+    pub max_entries: usize,
+}
+
+impl CacheConfig {
+    /// Reads `GATEWAY_CACHE_METHODS` (comma separated, empty disables the
+    /// cache), `GATEWAY_CACHE_TTL_SECS`, `GATEWAY_CACHE_MAX_STALE_SECS` and
+    /// `GATEWAY_CACHE_MAX_ENTRIES`.
+    pub fn from_env() -> Self {
+        let methods = std::env::var("GATEWAY_CACHE_METHODS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(|m| flat_method_name(m).to_string())
+            .collect();
+
+        Self {
+            methods,
+            ttl: Duration::from_secs(env_or("GATEWAY_CACHE_TTL_SECS", 5)),
+            max_stale: Duration::from_secs(env_or("GATEWAY_CACHE_MAX_STALE_SECS", 30)),
+            max_entries: env_or("GATEWAY_CACHE_MAX_ENTRIES", 1000) as usize,
+        }
+    }
+}
+
+fn env_or(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Identifies a cacheable call. `key` ignores the JSON-RPC id so that
+/// different clients share entries; `id` is put back into served responses.
+#[derive(Debug, Clone)]
+pub struct CacheKey {
+    pub key: String,
+    pub id: Value,
+    /// Flat method name, kept for the debug summary
+    pub method: String,
+}
+
+#[derive(Debug)]
+pub enum CacheLookup {
+    Fresh(Value),
+    /// Past its TTL but within the max-staleness window; serve it and refresh
+    Stale(Value),
+    Miss,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: Value,
+    stored_at: Instant,
+    method: String,
+}
+
+/// Response cache for read-only JSON-RPC methods with stale-while-revalidate
+/// semantics: expired entries keep being served for up to `max_stale` while a
+/// single background refresh per key fetches the new value.
+#[derive(Debug)]
+pub struct ResponseCache {
+    config: CacheConfig,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    refreshing: Mutex<HashSet<String>>,
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+            refreshing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.methods.is_empty()
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Returns the cache key for a single (non-batch) call to a cacheable
+    /// method, or `None` when the request must bypass the cache.
+    /// `authorization` is the client's `Authorization` header.
+    pub fn key_for(
+        &self,
+        service: &str,
+        api_version: Option<&str>,
+        timestamp_format: Option<&str>,
+        org: Option<&str>,
+        authorization: Option<&str>,
+        body: &[u8],
+    ) -> Option<CacheKey> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let request: Value = serde_json::from_slice(body).ok()?;
+        // Namespaced aliases share entries with their flat method
+        let method = flat_method_name(request.get("method")?.as_str()?);
+        if !self.config.methods.contains(method) {
+            return None;
+        }
+        // Notifications expect no response, so there is nothing to serve
+        let id = request.get("id")?.clone();
+
+        // serde_json maps are sorted, so equal params always print the same
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        // Each API version and timestamp format has its own response shape,
+        // and each organization sees its own private products. Services
+        // authorize every caller on its own, so a response is only served
+        // again to the credentials it was fetched with.
+        Some(CacheKey {
+            key: format!(
+                "{}|v{}|t{}|o{}|a{}|{}|{}",
+                service,
+                api_version.unwrap_or_default(),
+                timestamp_format.unwrap_or_default(),
+                org.unwrap_or_default(),
+                authorization.map(key_fingerprint).unwrap_or_default(),
+                method,
+                params
+            ),
+            id,
+            method: method.to_string(),
+        })
+    }
+
+    pub async fn lookup(&self, key: &CacheKey) -> CacheLookup {
+        let mut entries = self.entries.lock().await;
+        let Some(entry) = entries.get(&key.key) else {
+            return CacheLookup::Miss;
+        };
+
+        let age = entry.stored_at.elapsed();
+        if age <= self.config.ttl {
+            CacheLookup::Fresh(with_request_id(&entry.response, &key.id))
+        } else if age <= self.config.ttl + self.config.max_stale {
+            CacheLookup::Stale(with_request_id(&entry.response, &key.id))
+        } else {
+            // Past the hard staleness cap: never serve it again
+            entries.remove(&key.key);
+            CacheLookup::Miss
+        }
+    }
+
+    /// Stores a successful upstream response body. JSON-RPC error responses
+    /// and unparseable bodies are not cached.
+    pub async fn store(&self, key: &CacheKey, response_body: &[u8]) {
+        let Ok(response) = serde_json::from_slice::<Value>(response_body) else {
+            return;
+        };
+        if response.get("error").is_some() || response.get("result").is_none() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().await;
+        if entries.len() >= self.config.max_entries && !entries.contains_key(&key.key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(
+            key.key.clone(),
+            CacheEntry {
+                response,
+                stored_at: Instant::now(),
+                method: key.method.clone(),
+            },
+        );
+    }
+
+    /// Claims the background refresh for `key`. Returns `false` if another
+    /// refresh for the same key is already in flight.
+    pub async fn begin_refresh(&self, key: &CacheKey) -> bool {
+        self.refreshing.lock().await.insert(key.key.clone())
+    }
+
+    pub async fn end_refresh(&self, key: &CacheKey) {
+        self.refreshing.lock().await.remove(&key.key);
+    }
+
+    /// Entry counts by freshness and method, for debug dumps. Cached
+    /// responses themselves are left out.
+    pub async fn summary(&self) -> Value {
+        let entries = self.entries.lock().await;
+        let (mut fresh, mut stale, mut expired) = (0usize, 0usize, 0usize);
+        let mut by_method: HashMap<&str, usize> = HashMap::new();
+        for entry in entries.values() {
+            let age = entry.stored_at.elapsed();
+            if age <= self.config.ttl {
+                fresh += 1;
+            } else if age <= self.config.ttl + self.config.max_stale {
+                stale += 1;
+            } else {
+                expired += 1;
+            }
+            *by_method.entry(entry.method.as_str()).or_default() += 1;
+        }
+
+        serde_json::json!({
+            "enabled": self.is_enabled(),
+            "entries": entries.len(),
+            "max_entries": self.config.max_entries,
+            "fresh": fresh,
+            "stale": stale,
+            "expired": expired,
+            "refreshing": self.refreshing.lock().await.len(),
+            "by_method": by_method,
+            "ttl_secs": self.config.ttl.as_secs(),
+            "max_stale_secs": self.config.max_stale.as_secs(),
+        })
+    }
+}
+
+fn with_request_id(response: &Value, id: &Value) -> Value {
+    let mut response = response.clone();
+    if let Some(obj) = response.as_object_mut() {
+        obj.insert("id".to_string(), id.clone());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> ResponseCache {
+        ResponseCache::new(CacheConfig {
+            methods: ["get_product", "list_products"]
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            ttl: Duration::from_secs(60),
+            max_stale: Duration::from_secs(60),
+            max_entries: 10,
+        })
+    }
+
+    fn key(
+        cache: &ResponseCache,
+        org: Option<&str>,
+        auth: Option<&str>,
+        body: &str,
+    ) -> Option<CacheKey> {
+        cache.key_for("product", Some("1"), None, org, auth, body.as_bytes())
+    }
+
+    #[test]
+    fn key_for_separates_what_changes_the_response() {
+        let cache = cache();
+        let call = r#"{"jsonrpc":"2.0","id":1,"method":"get_product","params":{"id":"a"}}"#;
+        let base = key(&cache, Some("acme"), Some("Bearer one"), call).unwrap();
+
+        let cases = [
+            (
+                "another id",
+                r#"{"jsonrpc":"2.0","id":2,"method":"get_product","params":{"id":"a"}}"#,
+                Some("acme"),
+                Some("Bearer one"),
+                true,
+            ),
+            (
+                "namespaced alias",
+                r#"{"jsonrpc":"2.0","id":1,"method":"product.get","params":{"id":"a"}}"#,
+                Some("acme"),
+                Some("Bearer one"),
+                true,
+            ),
+            (
+                "other params",
+                r#"{"jsonrpc":"2.0","id":1,"method":"get_product","params":{"id":"b"}}"#,
+                Some("acme"),
+                Some("Bearer one"),
+                false,
+            ),
+            ("other org", call, Some("globex"), Some("Bearer one"), false),
+            ("no org", call, None, Some("Bearer one"), false),
+            (
+                "other credentials",
+                call,
+                Some("acme"),
+                Some("Bearer two"),
+                false,
+            ),
+            ("anonymous", call, Some("acme"), None, false),
+        ];
+        for (name, body, org, auth, shared) in cases {
+            let other = key(&cache, org, auth, body).unwrap();
+            assert_eq!(other.key == base.key, shared, "{}", name);
+            assert_eq!(other.method, "get_product", "{}", name);
+        }
+        assert!(
+            !base.key.contains("Bearer"),
+            "credentials must not appear in keys"
+        );
+    }
+
+    #[test]
+    fn key_for_bypasses_uncacheable_calls() {
+        let cache = cache();
+        let cases = [
+            (
+                "method not configured",
+                r#"{"jsonrpc":"2.0","id":1,"method":"create_product","params":{}}"#,
+            ),
+            (
+                "notification",
+                r#"{"jsonrpc":"2.0","method":"get_product","params":{"id":"a"}}"#,
+            ),
+            (
+                "batch",
+                r#"[{"jsonrpc":"2.0","id":1,"method":"get_product","params":{"id":"a"}}]"#,
+            ),
+            ("not json", "get_product"),
+        ];
+        for (name, body) in cases {
+            assert!(key(&cache, None, None, body).is_none(), "{}", name);
+        }
+
+        let disabled = ResponseCache::new(CacheConfig {
+            methods: HashSet::new(),
+            ..cache.config().clone()
+        });
+        let call = r#"{"jsonrpc":"2.0","id":1,"method":"get_product","params":{"id":"a"}}"#;
+        assert!(key(&disabled, None, None, call).is_none());
+    }
+
+    #[tokio::test]
+    async fn summary_counts_entries_by_method() {
+        let cache = cache();
+        let calls = [
+            (
+                Some("Bearer one"),
+                r#"{"jsonrpc":"2.0","id":1,"method":"get_product","params":{"id":"a"}}"#,
+            ),
+            (
+                Some("Bearer two"),
+                r#"{"jsonrpc":"2.0","id":1,"method":"get_product","params":{"id":"a"}}"#,
+            ),
+            (
+                None,
+                r#"{"jsonrpc":"2.0","id":1,"method":"product.list","params":{}}"#,
+            ),
+        ];
+        for (auth, body) in calls {
+            let key = key(&cache, None, auth, body).unwrap();
+            cache
+                .store(&key, br#"{"jsonrpc":"2.0","id":1,"result":{}}"#)
+                .await;
+        }
+        // Error responses are not cached
+        let failed = key(&cache, Some("acme"), None, calls[0].1).unwrap();
+        cache
+            .store(
+                &failed,
+                br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32602}}"#,
+            )
+            .await;
+
+        let summary = cache.summary().await;
+        assert_eq!(summary["entries"], 3);
+        assert_eq!(summary["fresh"], 3);
+        assert_eq!(
+            summary["by_method"],
+            serde_json::json!({"get_product": 2, "list_products": 1})
+        );
+    }
+}
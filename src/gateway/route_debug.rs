@@ -0,0 +1,163 @@
+use crate::gateway::routing::{route_for_method, MethodRoute, Upstream};
+use crate::services::method_namespaces::{COMMON_METHODS, PRODUCT_METHODS, USER_METHODS};
+use hyper::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Request header asking for routing metadata, and the response header
+/// carrying it
+pub const ROUTE_DEBUG_HEADER: &str = "x-route-debug";
+
+/// Bearer tokens allowed to use the gateway's admin features: route
+/// debugging and `/routes`
+#[derive(Debug, Clone, Default)]
+pub struct AdminTokens {
+    tokens: HashSet<String>,
+}
+
+impl AdminTokens {
+    /// Reads `GATEWAY_ADMIN_TOKENS`, comma separated. Unset means nobody is
+    /// an admin.
+    pub fn from_env() -> Self {
+        let tokens = std::env::var("GATEWAY_ADMIN_TOKENS")
+            .map(|list| {
+                list.split(',')
+                    .map(str::trim)
+                    .filter(|token| !token.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { tokens }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Whether the request's `Authorization: Bearer` token is an admin token
+    pub fn is_admin(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| self.tokens.contains(token.trim()))
+    }
+
+    /// Whether an admin asked for routing metadata with `X-Route-Debug`.
+    /// Anyone else's header is ignored.
+    pub fn wants_route_debug(&self, headers: &HeaderMap) -> bool {
+        let requested = headers
+            .get(ROUTE_DEBUG_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| matches!(value.trim(), "1" | "true"));
+        requested && self.is_admin(headers)
+    }
+}
+
+/// One try at the upstream
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteAttempt {
+    pub attempt: u32,
+    /// Upstream status, when it responded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Why the attempt failed, when it got no response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Whether the answer came from a hedged second request
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub hedged: bool,
+    /// Base URL of the instance tried, when the service has several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+}
+
+impl RouteAttempt {
+    pub fn responded(attempt: u32, status: u16, duration: Duration) -> Self {
+        Self {
+            attempt,
+            status: Some(status),
+            error: None,
+            duration_ms: duration.as_millis() as u64,
+            hedged: false,
+            instance: None,
+        }
+    }
+
+    pub fn failed(attempt: u32, error: impl Into<String>, duration: Duration) -> Self {
+        Self {
+            attempt,
+            status: None,
+            error: Some(error.into()),
+            duration_ms: duration.as_millis() as u64,
+            hedged: false,
+            instance: None,
+        }
+    }
+}
+
+/// How the gateway handled one request, returned to admins in
+/// `X-Route-Debug`
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteDecision {
+    /// The routing rule that picked the service, e.g. `method create_user`
+    /// or `path prefix /api/users`
+    pub rule: String,
+    pub service: String,
+    /// Base URL of the instance the request was last sent to
+    pub instance: String,
+    /// `HIT` or `STALE` when answered from the response cache
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<&'static str>,
+    pub attempts: Vec<RouteAttempt>,
+    pub total_ms: u64,
+}
+
+impl RouteDecision {
+    /// The decision as compact JSON for the response header
+    pub fn header_value(&self) -> Option<HeaderValue> {
+        let json = serde_json::to_string(self).ok()?;
+        HeaderValue::from_str(&json).ok()
+    }
+}
+
+/// Rule description for a request routed by its JSON-RPC method names
+pub fn method_rule(body: &[u8]) -> String {
+    let request: Value = serde_json::from_slice(body).unwrap_or(Value::Null);
+    let calls = match &request {
+        Value::Array(calls) => calls.iter().collect::<Vec<_>>(),
+        call => vec![call],
+    };
+    let methods: Vec<&str> = calls
+        .iter()
+        .filter_map(|call| call.get("method").and_then(Value::as_str))
+        .filter(|method| matches!(route_for_method(method), MethodRoute::Service(_)))
+        .collect();
+    format!("method {}", methods.join(","))
+}
+
+/// The method map as `/routes` lists it: each method, namespaced and flat,
+/// with the service it goes to (`shared` for methods every service has)
+pub fn method_routes() -> Vec<Value> {
+    COMMON_METHODS
+        .iter()
+        .chain(USER_METHODS)
+        .chain(PRODUCT_METHODS)
+        .map(|(namespaced, flat)| {
+            let service = match route_for_method(flat) {
+                MethodRoute::Service(Upstream::User) => "user_service",
+                MethodRoute::Service(Upstream::Product) => "product_service",
+                MethodRoute::Shared | MethodRoute::Unknown => "shared",
+            };
+            json!({ "method": namespaced, "flat": flat, "service": service })
+        })
+        .collect()
+}
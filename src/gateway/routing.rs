@@ -0,0 +1,84 @@
+use crate::services::method_namespaces::{
+    COMMON_METHODS, METHOD_LIST_METHOD, PRODUCT_METHODS, USER_METHODS,
+};
+use serde_json::Value;
+
+/// Backend service a JSON-RPC method belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Upstream {
+    User,
+    Product,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodRoute {
+    /// Implemented by exactly one service
+    Service(Upstream),
+    /// Implemented by every service (`health`, `set_read_only`, ...); the
+    /// request path decides which one is called
+    Shared,
+    /// Not in the method map; falls back to path-based routing
+    Unknown,
+}
+
+/// The gateway's method map, covering flat and namespaced names. It is
+/// derived from the same tables the services register their aliases from,
+/// and `tests/routing_contract.rs` checks it against the running services.
+pub fn route_for_method(method: &str) -> MethodRoute {
+    let in_table = |table: &[(&str, &str)]| {
+        table
+            .iter()
+            .any(|(namespaced, flat)| *namespaced == method || *flat == method)
+    };
+
+    if method == METHOD_LIST_METHOD || in_table(COMMON_METHODS) {
+        MethodRoute::Shared
+    } else if in_table(USER_METHODS) {
+        MethodRoute::Service(Upstream::User)
+    } else if in_table(PRODUCT_METHODS) {
+        MethodRoute::Service(Upstream::Product)
+    } else {
+        MethodRoute::Unknown
+    }
+}
+
+/// Picks the upstream for a request body from its method names. Returns
+/// `None` when the body is not JSON-RPC, only calls shared or unknown
+/// methods, or is a batch spanning several services; the caller then routes
+/// by path.
+pub fn route_for_body(body: &[u8]) -> Option<Upstream> {
+    let request: Value = serde_json::from_slice(body).ok()?;
+    let calls = match &request {
+        Value::Array(calls) => calls.iter().collect::<Vec<_>>(),
+        call => vec![call],
+    };
+
+    let mut upstream = None;
+    for call in calls {
+        let method = call.get("method").and_then(Value::as_str)?;
+        match route_for_method(method) {
+            MethodRoute::Service(service) if upstream.is_none() => upstream = Some(service),
+            MethodRoute::Service(service) if upstream != Some(service) => return None,
+            _ => {}
+        }
+    }
+    upstream
+}
+
+/// Whether every call in a JSON-RPC body is to a method all services
+/// implement, so any of them can answer it
+pub fn calls_only_shared_methods(body: &[u8]) -> bool {
+    let Ok(request) = serde_json::from_slice::<Value>(body) else {
+        return false;
+    };
+    let calls = match &request {
+        Value::Array(calls) => calls.iter().collect::<Vec<_>>(),
+        call => vec![call],
+    };
+    !calls.is_empty()
+        && calls.iter().all(|call| {
+            call.get("method")
+                .and_then(Value::as_str)
+                .is_some_and(|method| route_for_method(method) == MethodRoute::Shared)
+        })
+}
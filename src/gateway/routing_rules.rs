@@ -0,0 +1,558 @@
+use crate::gateway::routing::{calls_only_shared_methods, route_for_body, Upstream};
+use crate::services::method_namespaces::{
+    flat_method_name, COMMON_METHODS, METHOD_LIST_METHOD, PRODUCT_METHODS, USER_METHODS,
+};
+use hyper::header::{HeaderMap, HeaderName};
+use hyper::StatusCode;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use thiserror::Error;
+
+const METHOD_NOT_FOUND_CODE: i32 = -32601;
+
+#[derive(Error, Debug)]
+pub enum RoutingRuleError {
+    #[error("Failed to read routing rules {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid routing rules {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid routing rule '{rule}': {message}")]
+    InvalidRule { rule: String, message: String },
+
+    #[error("Invalid GATEWAY_UNMATCHED_ROUTES '{0}', expected user_service, product_service, not_found or explain")]
+    InvalidUnmatched(String),
+}
+
+/// What happens to requests that no rule, method or path rule routes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnmatchedRoutes {
+    /// Send them to one service anyway, as the gateway always did
+    Route(Upstream),
+    /// `404`, with a `-32601` error for JSON-RPC bodies
+    NotFound,
+    /// `404` with an error listing the known path prefixes and method
+    /// namespaces
+    Explain,
+}
+
+impl UnmatchedRoutes {
+    fn parse(value: &str) -> Result<Self, RoutingRuleError> {
+        match value.trim() {
+            "not_found" => Ok(UnmatchedRoutes::NotFound),
+            "explain" => Ok(UnmatchedRoutes::Explain),
+            other => upstream_from_key(other)
+                .map(UnmatchedRoutes::Route)
+                .ok_or_else(|| RoutingRuleError::InvalidUnmatched(value.to_string())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnmatchedRoutes::Route(upstream) => upstream_key(*upstream),
+            UnmatchedRoutes::NotFound => "not_found",
+            UnmatchedRoutes::Explain => "explain",
+        }
+    }
+}
+
+/// One condition on a request. Header names are case-insensitive, values
+/// and paths are not.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    PathPrefix(String),
+    PathContains(String),
+    PathRegex(Regex),
+    HeaderEquals {
+        name: HeaderName,
+        value: String,
+    },
+    /// Any call in the body uses this JSON-RPC method, by its flat or
+    /// namespaced name
+    Method(String),
+}
+
+impl Matcher {
+    fn matches(&self, path: &str, headers: &HeaderMap, methods: &[String]) -> bool {
+        match self {
+            Matcher::PathPrefix(prefix) => path.starts_with(prefix.as_str()),
+            Matcher::PathContains(fragment) => path.contains(fragment.as_str()),
+            Matcher::PathRegex(regex) => regex.is_match(path),
+            Matcher::HeaderEquals { name, value } => headers
+                .get_all(name)
+                .iter()
+                .any(|actual| actual.as_bytes() == value.as_bytes()),
+            Matcher::Method(method) => methods
+                .iter()
+                .any(|called| flat_method_name(called) == method.as_str()),
+        }
+    }
+}
+
+impl std::fmt::Display for Matcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Matcher::PathPrefix(prefix) => write!(f, "path prefix {}", prefix),
+            Matcher::PathContains(fragment) => write!(f, "path contains {}", fragment),
+            Matcher::PathRegex(regex) => write!(f, "path matches {}", regex),
+            Matcher::HeaderEquals { name, value } => write!(f, "header {} = {}", name, value),
+            Matcher::Method(method) => write!(f, "method {}", method),
+        }
+    }
+}
+
+/// What a rule does to the requests it matches
+#[derive(Debug, Clone)]
+pub enum RuleAction {
+    Route(Upstream),
+    /// Replaces the path and goes on with the next rule
+    Rewrite(String),
+    Reject {
+        status: StatusCode,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for RuleAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleAction::Route(upstream) => write!(f, "route to {}", upstream_key(*upstream)),
+            RuleAction::Rewrite(path) => write!(f, "rewrite to {}", path),
+            RuleAction::Reject { status, .. } => write!(f, "reject with {}", status.as_u16()),
+        }
+    }
+}
+
+/// Matchers that must all hold, and the action taken when they do. A rule
+/// without matchers matches every request.
+#[derive(Debug, Clone)]
+pub struct RoutingRule {
+    pub name: String,
+    pub matchers: Vec<Matcher>,
+    pub action: RuleAction,
+}
+
+impl RoutingRule {
+    fn path(matcher: Matcher, upstream: Upstream) -> Self {
+        Self {
+            name: matcher.to_string(),
+            matchers: vec![matcher],
+            action: RuleAction::Route(upstream),
+        }
+    }
+
+    fn matches(&self, path: &str, headers: &HeaderMap, methods: &[String]) -> bool {
+        self.matchers
+            .iter()
+            .all(|matcher| matcher.matches(path, headers, methods))
+    }
+
+    fn describe(&self) -> Value {
+        json!({
+            "name": self.name,
+            "match": self.matchers.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            "action": self.action.to_string(),
+        })
+    }
+}
+
+/// Where a request goes, or why it does not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteOutcome {
+    Route {
+        upstream: Upstream,
+        /// The rule that picked the upstream; `None` when the method map did
+        rule: Option<String>,
+        /// The path after rewrites, when a rule changed it
+        rewritten_path: Option<String>,
+    },
+    Reject {
+        status: StatusCode,
+        message: String,
+        rule: String,
+    },
+    /// Nothing matched and `GATEWAY_UNMATCHED_ROUTES` names a service
+    Fallback {
+        upstream: Upstream,
+        rewritten_path: Option<String>,
+    },
+    /// Nothing matched and the request is refused; `body` is JSON
+    Unmatched { status: StatusCode, body: Value },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HeaderConfig {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MatchConfig {
+    path_prefix: Option<String>,
+    path_regex: Option<String>,
+    header: Option<HeaderConfig>,
+    method: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ActionConfig {
+    Route(String),
+    Rewrite(String),
+    Reject {
+        status: u16,
+        message: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleConfig {
+    name: String,
+    #[serde(default, rename = "match")]
+    matchers: MatchConfig,
+    action: ActionConfig,
+}
+
+/// The gateway's routing table, evaluated in order for every request:
+///
+/// 1. configured rules (`GATEWAY_ROUTING_RULES`); the first that routes or
+///    rejects decides, rewrites change the path for the rules after them
+/// 2. the method map, for bodies whose methods belong to one service
+/// 3. the built-in path rules
+/// 4. the user service for calls to shared methods only
+/// 5. whatever `GATEWAY_UNMATCHED_ROUTES` says, the user service by default
+#[derive(Debug, Clone)]
+pub struct RoutingRules {
+    configured: Vec<RoutingRule>,
+    fallback: Vec<RoutingRule>,
+    unmatched: UnmatchedRoutes,
+}
+
+impl Default for RoutingRules {
+    fn default() -> Self {
+        Self {
+            configured: Vec::new(),
+            fallback: vec![
+                RoutingRule::path(Matcher::PathPrefix("/api/users".into()), Upstream::User),
+                RoutingRule::path(Matcher::PathContains("user".into()), Upstream::User),
+                RoutingRule::path(
+                    Matcher::PathPrefix("/api/products".into()),
+                    Upstream::Product,
+                ),
+                RoutingRule::path(Matcher::PathContains("product".into()), Upstream::Product),
+            ],
+            unmatched: UnmatchedRoutes::Route(Upstream::User),
+        }
+    }
+}
+
+impl RoutingRules {
+    /// Loads the rules file named by `GATEWAY_ROUTING_RULES`, if set, and
+    /// reads `GATEWAY_UNMATCHED_ROUTES`
+    pub fn from_env() -> Result<Self, RoutingRuleError> {
+        let rules = match std::env::var("GATEWAY_ROUTING_RULES") {
+            Ok(path) => Self::from_file(path)?,
+            Err(_) => Self::default(),
+        };
+        match std::env::var("GATEWAY_UNMATCHED_ROUTES") {
+            Ok(value) => Ok(rules.with_unmatched(UnmatchedRoutes::parse(&value)?)),
+            Err(_) => Ok(rules),
+        }
+    }
+
+    pub fn with_unmatched(mut self, unmatched: UnmatchedRoutes) -> Self {
+        self.unmatched = unmatched;
+        self
+    }
+
+    pub fn unmatched(&self) -> UnmatchedRoutes {
+        self.unmatched
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RoutingRuleError> {
+        let path_str = path.as_ref().display().to_string();
+        let contents = std::fs::read_to_string(&path).map_err(|source| RoutingRuleError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+        let rules: Vec<RuleConfig> =
+            serde_json::from_str(&contents).map_err(|source| RoutingRuleError::Parse {
+                path: path_str,
+                source,
+            })?;
+
+        Ok(Self {
+            configured: rules
+                .into_iter()
+                .map(compile_rule)
+                .collect::<Result<_, _>>()?,
+            ..Self::default()
+        })
+    }
+
+    pub fn configured_count(&self) -> usize {
+        self.configured.len()
+    }
+
+    /// Decides where a request goes from its path, headers and body
+    pub fn evaluate(&self, path: &str, headers: &HeaderMap, body: &[u8]) -> RouteOutcome {
+        // Only configured rules match on methods
+        let methods = if self.configured.is_empty() {
+            Vec::new()
+        } else {
+            called_methods(body)
+        };
+        let mut rewritten_path: Option<String> = None;
+
+        for rule in &self.configured {
+            let current = rewritten_path.as_deref().unwrap_or(path);
+            if !rule.matches(current, headers, &methods) {
+                continue;
+            }
+            match &rule.action {
+                RuleAction::Route(upstream) => {
+                    return RouteOutcome::Route {
+                        upstream: *upstream,
+                        rule: Some(format!("rule {}", rule.name)),
+                        rewritten_path,
+                    }
+                }
+                RuleAction::Rewrite(to) => rewritten_path = Some(to.clone()),
+                RuleAction::Reject { status, message } => {
+                    return RouteOutcome::Reject {
+                        status: *status,
+                        message: message.clone(),
+                        rule: format!("rule {}", rule.name),
+                    }
+                }
+            }
+        }
+
+        if let Some(upstream) = route_for_body(body) {
+            return RouteOutcome::Route {
+                upstream,
+                rule: None,
+                rewritten_path,
+            };
+        }
+
+        let current = rewritten_path.as_deref().unwrap_or(path);
+        let path_rule = self
+            .fallback
+            .iter()
+            .find(|rule| rule.matches(current, headers, &methods))
+            .and_then(|rule| match rule.action {
+                RuleAction::Route(upstream) => Some((upstream, rule.name.clone())),
+                _ => None,
+            });
+        if let Some((upstream, rule)) = path_rule {
+            return RouteOutcome::Route {
+                upstream,
+                rule: Some(rule),
+                rewritten_path,
+            };
+        }
+
+        // Any service answers `health` or `rpc.methods`, so they are never
+        // unmatched
+        if calls_only_shared_methods(body) {
+            return RouteOutcome::Route {
+                upstream: Upstream::User,
+                rule: Some("shared method".to_string()),
+                rewritten_path,
+            };
+        }
+
+        match self.unmatched {
+            UnmatchedRoutes::Route(upstream) => RouteOutcome::Fallback {
+                upstream,
+                rewritten_path,
+            },
+            UnmatchedRoutes::NotFound => RouteOutcome::Unmatched {
+                status: StatusCode::NOT_FOUND,
+                body: not_found_body(current, body, None),
+            },
+            UnmatchedRoutes::Explain => RouteOutcome::Unmatched {
+                status: StatusCode::NOT_FOUND,
+                body: not_found_body(current, body, Some(self.known_routes())),
+            },
+        }
+    }
+
+    /// Path prefixes and method namespaces requests can be routed by, for
+    /// `explain` errors
+    fn known_routes(&self) -> Value {
+        let mut prefixes: Vec<&str> = self
+            .configured
+            .iter()
+            .chain(&self.fallback)
+            .filter(|rule| matches!(rule.action, RuleAction::Route(_)))
+            .flat_map(|rule| &rule.matchers)
+            .filter_map(|matcher| match matcher {
+                Matcher::PathPrefix(prefix) => Some(prefix.as_str()),
+                _ => None,
+            })
+            .collect();
+        prefixes.dedup();
+        let mut namespaces: Vec<&str> = COMMON_METHODS
+            .iter()
+            .chain(USER_METHODS)
+            .chain(PRODUCT_METHODS)
+            .filter_map(|(namespaced, _)| namespaced.split_once('.'))
+            .map(|(namespace, _)| namespace)
+            .collect();
+        namespaces.sort_unstable();
+        namespaces.dedup();
+        json!({
+            "path_prefixes": prefixes,
+            "method_namespaces": namespaces,
+            "method_list": METHOD_LIST_METHOD,
+        })
+    }
+
+    /// The configured rules, for `/routes`
+    pub fn describe_configured(&self) -> Vec<Value> {
+        self.configured.iter().map(RoutingRule::describe).collect()
+    }
+
+    /// The built-in path rules, for `/routes`
+    pub fn describe_fallback(&self) -> Vec<Value> {
+        self.fallback
+            .iter()
+            .filter_map(|rule| match rule.action {
+                RuleAction::Route(upstream) => {
+                    Some(json!({ "match": rule.name, "service": upstream_key(upstream) }))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn compile_rule(config: RuleConfig) -> Result<RoutingRule, RoutingRuleError> {
+    let invalid = |message: String| RoutingRuleError::InvalidRule {
+        rule: config.name.clone(),
+        message,
+    };
+
+    let mut matchers = Vec::new();
+    if let Some(prefix) = &config.matchers.path_prefix {
+        matchers.push(Matcher::PathPrefix(prefix.clone()));
+    }
+    if let Some(pattern) = &config.matchers.path_regex {
+        let regex = Regex::new(pattern).map_err(|err| invalid(err.to_string()))?;
+        matchers.push(Matcher::PathRegex(regex));
+    }
+    if let Some(header) = &config.matchers.header {
+        let name = HeaderName::from_bytes(header.name.trim().as_bytes())
+            .map_err(|_| invalid(format!("invalid header name '{}'", header.name)))?;
+        matchers.push(Matcher::HeaderEquals {
+            name,
+            value: header.value.clone(),
+        });
+    }
+    if let Some(method) = &config.matchers.method {
+        matchers.push(Matcher::Method(flat_method_name(method).to_string()));
+    }
+
+    let action = match &config.action {
+        ActionConfig::Route(service) => RuleAction::Route(
+            upstream_from_key(service)
+                .ok_or_else(|| invalid(format!("unknown service '{}'", service)))?,
+        ),
+        ActionConfig::Rewrite(path) if path.starts_with('/') => RuleAction::Rewrite(path.clone()),
+        ActionConfig::Rewrite(path) => {
+            return Err(invalid(format!(
+                "rewrite target '{}' must start with /",
+                path
+            )))
+        }
+        ActionConfig::Reject { status, message } => {
+            let status = StatusCode::from_u16(*status)
+                .ok()
+                .filter(|status| status.is_client_error() || status.is_server_error())
+                .ok_or_else(|| invalid(format!("reject status {} is not an error", status)))?;
+            RuleAction::Reject {
+                status,
+                message: message
+                    .clone()
+                    .unwrap_or_else(|| "Request rejected".to_string()),
+            }
+        }
+    };
+
+    Ok(RoutingRule {
+        name: config.name.clone(),
+        matchers,
+        action,
+    })
+}
+
+/// A JSON-RPC `-32601` error for JSON-RPC bodies, a plain error object
+/// otherwise. `known` is added as the error's data.
+fn not_found_body(path: &str, body: &[u8], known: Option<Value>) -> Value {
+    let message = format!("No route for {}", path);
+    let request: Value = serde_json::from_slice(body).unwrap_or(Value::Null);
+    let is_call = |call: &Value| call.get("method").is_some();
+    let rpc_error = |call: &Value| {
+        let mut error = json!({ "code": METHOD_NOT_FOUND_CODE, "message": message });
+        if let Some(known) = &known {
+            error["data"] = known.clone();
+        }
+        json!({ "jsonrpc": "2.0", "id": call.get("id").cloned().unwrap_or(Value::Null), "error": error })
+    };
+
+    match &request {
+        Value::Array(calls) if !calls.is_empty() && calls.iter().all(is_call) => {
+            Value::Array(calls.iter().map(rpc_error).collect())
+        }
+        call if is_call(call) => rpc_error(call),
+        _ => {
+            let mut error = json!({ "error": message });
+            if let Some(known) = known {
+                error["known_routes"] = known;
+            }
+            error
+        }
+    }
+}
+
+/// Method names of every call in a JSON-RPC body
+fn called_methods(body: &[u8]) -> Vec<String> {
+    let request: Value = serde_json::from_slice(body).unwrap_or(Value::Null);
+    let calls = match &request {
+        Value::Array(calls) => calls.iter().collect::<Vec<_>>(),
+        call => vec![call],
+    };
+    calls
+        .iter()
+        .filter_map(|call| call.get("method").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+fn upstream_key(upstream: Upstream) -> &'static str {
+    match upstream {
+        Upstream::User => "user_service",
+        Upstream::Product => "product_service",
+    }
+}
+
+fn upstream_from_key(key: &str) -> Option<Upstream> {
+    match key.trim() {
+        "user_service" | "user" => Some(Upstream::User),
+        "product_service" | "product" => Some(Upstream::Product),
+        _ => None,
+    }
+}
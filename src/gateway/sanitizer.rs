@@ -0,0 +1,222 @@
+use hyper::header::{HeaderMap, CONTENT_LENGTH, TRANSFER_ENCODING};
+use hyper::http::uri::PathAndQuery;
+use hyper::{StatusCode, Uri};
+use thiserror::Error;
+
+const DEFAULT_MAX_HEADER_BYTES: usize = 32 * 1024;
+
+#[derive(Error, Debug)]
+pub enum SanitizerConfigError {
+    #[error("Invalid GATEWAY_MAX_HEADER_BYTES '{0}', expected a number")]
+    InvalidHeaderLimit(String),
+
+    #[error("Invalid blocked path '{0}', expected a path starting with /")]
+    InvalidPattern(String),
+}
+
+/// Why a request was refused before routing
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SanitizeRejection {
+    #[error("invalid path: {0}")]
+    InvalidPath(&'static str),
+
+    #[error("headers are {size} bytes, over the {limit} byte limit")]
+    HeadersTooLarge { size: usize, limit: usize },
+
+    #[error("both Content-Length and Transfer-Encoding are set")]
+    AmbiguousLength,
+
+    #[error("path matches blocked pattern {0}")]
+    Blocked(String),
+}
+
+impl SanitizeRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            SanitizeRejection::InvalidPath(_) | SanitizeRejection::AmbiguousLength => {
+                StatusCode::BAD_REQUEST
+            }
+            SanitizeRejection::HeadersTooLarge { .. } => {
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+            }
+            SanitizeRejection::Blocked(_) => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+/// Cleans up request paths and refuses malformed requests before the
+/// gateway routes them.
+///
+/// Routing matches on path substrings, so `//api/users`, `/x/../api/users`
+/// and `/api/%2e%2e/users` must not reach it in a form the upstream would
+/// read differently. Paths are normalized (duplicate slashes and dot
+/// segments removed), encoded separators, dots, percent signs and control
+/// characters are rejected, and so are oversized headers and requests
+/// carrying both `Content-Length` and `Transfer-Encoding`. Normalized paths
+/// matching a blocked pattern get `403`.
+#[derive(Debug, Clone)]
+pub struct RequestSanitizer {
+    /// `None` disables the header size check
+    max_header_bytes: Option<usize>,
+    /// Lowercase patterns where `*` matches any run of characters
+    blocked: Vec<String>,
+}
+
+impl RequestSanitizer {
+    /// Reads `GATEWAY_MAX_HEADER_BYTES` (default 32768, `0` disables the
+    /// check) and `GATEWAY_BLOCKED_PATHS` (comma separated patterns such as
+    /// `/admin*` or `*/.git*`)
+    pub fn from_env() -> Result<Self, SanitizerConfigError> {
+        let max_header_bytes = match std::env::var("GATEWAY_MAX_HEADER_BYTES") {
+            Ok(value) => value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| SanitizerConfigError::InvalidHeaderLimit(value.clone()))?,
+            Err(_) => DEFAULT_MAX_HEADER_BYTES,
+        };
+        let blocked = std::env::var("GATEWAY_BLOCKED_PATHS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| {
+                if pattern.starts_with('/') || pattern.starts_with('*') {
+                    Ok(pattern.to_lowercase())
+                } else {
+                    Err(SanitizerConfigError::InvalidPattern(pattern.to_string()))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            max_header_bytes: (max_header_bytes > 0).then_some(max_header_bytes),
+            blocked,
+        })
+    }
+
+    pub fn max_header_bytes(&self) -> Option<usize> {
+        self.max_header_bytes
+    }
+
+    pub fn blocked_count(&self) -> usize {
+        self.blocked.len()
+    }
+
+    /// Checks a request and returns its URI with the path normalized, or
+    /// `None` when the path was already clean
+    pub fn check(&self, uri: &Uri, headers: &HeaderMap) -> Result<Option<Uri>, SanitizeRejection> {
+        if let Some(limit) = self.max_header_bytes {
+            let size: usize = headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum();
+            if size > limit {
+                return Err(SanitizeRejection::HeadersTooLarge { size, limit });
+            }
+        }
+        if headers.contains_key(CONTENT_LENGTH) && headers.contains_key(TRANSFER_ENCODING) {
+            return Err(SanitizeRejection::AmbiguousLength);
+        }
+
+        let path = normalize_path(uri.path())?;
+        let lowercase = path.to_lowercase();
+        if let Some(pattern) = self
+            .blocked
+            .iter()
+            .find(|pattern| glob_matches(pattern, &lowercase))
+        {
+            return Err(SanitizeRejection::Blocked(pattern.clone()));
+        }
+
+        if path == uri.path() {
+            return Ok(None);
+        }
+        let path_and_query = match uri.query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        };
+        let mut parts = uri.clone().into_parts();
+        parts.path_and_query = Some(
+            PathAndQuery::try_from(path_and_query)
+                .map_err(|_| SanitizeRejection::InvalidPath("unparseable"))?,
+        );
+        Uri::from_parts(parts)
+            .map(Some)
+            .map_err(|_| SanitizeRejection::InvalidPath("unparseable"))
+    }
+}
+
+/// Collapses duplicate slashes and resolves `.` and `..` segments, keeping
+/// a trailing slash. Fails on paths climbing above the root and on encoded
+/// characters that could change how the path splits into segments.
+pub fn normalize_path(path: &str) -> Result<String, SanitizeRejection> {
+    check_encoding(path)?;
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if segments.pop().is_none() {
+                    return Err(SanitizeRejection::InvalidPath("climbs above the root"));
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    if path.ends_with('/') && !segments.is_empty() {
+        normalized.push('/');
+    }
+    Ok(normalized)
+}
+
+fn check_encoding(path: &str) -> Result<(), SanitizeRejection> {
+    let bytes = path.as_bytes();
+    for (i, byte) in bytes.iter().enumerate() {
+        match byte {
+            b'\\' => return Err(SanitizeRejection::InvalidPath("backslash")),
+            byte if byte.is_ascii_control() => {
+                return Err(SanitizeRejection::InvalidPath("control character"))
+            }
+            b'%' => {
+                let decoded = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or(SanitizeRejection::InvalidPath("malformed percent-encoding"))?;
+                if matches!(decoded, b'/' | b'\\' | b'.' | b'%') || decoded.is_ascii_control() {
+                    return Err(SanitizeRejection::InvalidPath(
+                        "suspicious percent-encoding",
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of
+/// characters
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`: the pattern must match the whole path
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,354 @@
+use crate::services::method_namespaces::flat_method_name;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SchemaRegistryError {
+    #[error("Failed to read schema file {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid schema file {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+
+    #[error("Schema file must be a JSON object mapping method names to schemas")]
+    NotAnObject,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub message: String,
+}
+
+impl SchemaViolation {
+    fn new(path: &str, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// JSON Schemas for JSON-RPC `params`, keyed by method name.
+///
+/// Supports the commonly used subset of JSON Schema: `type`, `enum`,
+/// `properties`, `required`, `additionalProperties`, `items`,
+/// `minLength`/`maxLength`, `minimum`/`maximum`,
+/// `exclusiveMinimum`/`exclusiveMaximum` and `minItems`/`maxItems`.
+/// Unknown keywords are ignored.
+#[derive(Debug, Default)]
+pub struct MethodSchemaRegistry {
+    schemas: HashMap<String, Value>,
+}
+
+impl MethodSchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the registry from the file named by `GATEWAY_METHOD_SCHEMAS`,
+    /// or returns an empty registry when the variable is unset.
+    pub fn from_env() -> Result<Self, SchemaRegistryError> {
+        match std::env::var("GATEWAY_METHOD_SCHEMAS") {
+            Ok(path) => Self::from_file(path),
+            Err(_) => Ok(Self::new()),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SchemaRegistryError> {
+        let path_str = path.as_ref().display().to_string();
+        let contents =
+            std::fs::read_to_string(&path).map_err(|source| SchemaRegistryError::Io {
+                path: path_str.clone(),
+                source,
+            })?;
+        let value: Value =
+            serde_json::from_str(&contents).map_err(|source| SchemaRegistryError::Parse {
+                path: path_str,
+                source,
+            })?;
+
+        match value {
+            Value::Object(map) => Ok(Self {
+                schemas: map
+                    .into_iter()
+                    .map(|(method, schema)| (flat_method_name(&method).to_string(), schema))
+                    .collect(),
+            }),
+            _ => Err(SchemaRegistryError::NotAnObject),
+        }
+    }
+
+    pub fn register(&mut self, method: impl Into<String>, schema: Value) {
+        let method = method.into();
+        self.schemas
+            .insert(flat_method_name(&method).to_string(), schema);
+    }
+
+    pub fn len(&self) -> usize {
+        self.schemas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty()
+    }
+
+    /// Validates `params` against the schema registered for `method` (flat
+    /// or namespaced). Methods without a schema always pass. A missing `params` member is
+    /// validated as `null`.
+    pub fn validate(
+        &self,
+        method: &str,
+        params: Option<&Value>,
+    ) -> Result<(), Vec<SchemaViolation>> {
+        let Some(schema) = self.schemas.get(flat_method_name(method)) else {
+            return Ok(());
+        };
+
+        let mut violations = Vec::new();
+        validate_value(
+            schema,
+            params.unwrap_or(&Value::Null),
+            "params",
+            &mut violations,
+        );
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn validate_value(
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    let Some(schema) = schema.as_object() else {
+        // `true`/`false` boolean schemas
+        if schema == &Value::Bool(false) {
+            violations.push(SchemaViolation::new(path, "no value is allowed here"));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| type_matches(t, value)) {
+            violations.push(SchemaViolation::new(
+                path,
+                format!(
+                    "expected {}, found {}",
+                    allowed.join(" or "),
+                    type_name(value)
+                ),
+            ));
+            // Further keywords would only produce noise for a mistyped value
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            violations.push(SchemaViolation::new(
+                path,
+                format!("value must be one of {}", Value::Array(options.clone())),
+            ));
+        }
+    }
+
+    match value {
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    violations.push(SchemaViolation::new(
+                        path,
+                        format!("must be at least {} characters long", min),
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    violations.push(SchemaViolation::new(
+                        path,
+                        format!("must be at most {} characters long", max),
+                    ));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    violations.push(SchemaViolation::new(path, format!("must be >= {}", min)));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    violations.push(SchemaViolation::new(path, format!("must be <= {}", max)));
+                }
+            }
+            if let Some(min) = schema.get("exclusiveMinimum").and_then(Value::as_f64) {
+                if n <= min {
+                    violations.push(SchemaViolation::new(path, format!("must be > {}", min)));
+                }
+            }
+            if let Some(max) = schema.get("exclusiveMaximum").and_then(Value::as_f64) {
+                if n >= max {
+                    violations.push(SchemaViolation::new(path, format!("must be < {}", max)));
+                }
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if len < min {
+                    violations.push(SchemaViolation::new(
+                        path,
+                        format!("must contain at least {} items", min),
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if len > max {
+                    violations.push(SchemaViolation::new(
+                        path,
+                        format!("must contain at most {} items", max),
+                    ));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_value(
+                        item_schema,
+                        item,
+                        &format!("{}[{}]", path, index),
+                        violations,
+                    );
+                }
+            }
+        }
+        Value::Object(fields) => {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !fields.contains_key(name) {
+                        violations.push(SchemaViolation::new(
+                            &format!("{}.{}", path, name),
+                            "is required",
+                        ));
+                    }
+                }
+            }
+
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field_value) in fields {
+                let field_path = format!("{}.{}", path, name);
+                match properties.and_then(|p| p.get(name)) {
+                    Some(field_schema) => {
+                        validate_value(field_schema, field_value, &field_path, violations)
+                    }
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            violations.push(SchemaViolation::new(&field_path, "unknown field"))
+                        }
+                        Some(extra_schema @ Value::Object(_)) => {
+                            validate_value(extra_schema, field_value, &field_path, violations)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+impl MethodSchemaRegistry {
+    /// Validates a raw JSON-RPC request or batch body. Returns the JSON-RPC
+    /// error response to send back to the client when any call fails
+    /// validation, or `None` when the body should be proxied as-is.
+    ///
+    /// Bodies that are not valid JSON are left for the upstream service to
+    /// reject. A batch is rejected as a whole if any of its calls is invalid,
+    /// with one error entry per failing call.
+    pub fn validate_request_body(&self, body: &[u8]) -> Option<Value> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let request: Value = serde_json::from_slice(body).ok()?;
+        match &request {
+            Value::Array(calls) => {
+                let errors: Vec<Value> = calls
+                    .iter()
+                    .filter_map(|call| self.validate_call(call))
+                    .collect();
+                if errors.is_empty() {
+                    None
+                } else {
+                    Some(Value::Array(errors))
+                }
+            }
+            call => self.validate_call(call),
+        }
+    }
+
+    fn validate_call(&self, call: &Value) -> Option<Value> {
+        let method = call.get("method")?.as_str()?;
+        let violations = self.validate(method, call.get("params")).err()?;
+
+        Some(serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "code": -32602,
+                "message": "Invalid params",
+                "data": {
+                    "method": method,
+                    "violations": violations,
+                },
+            },
+            "id": call.get("id").cloned().unwrap_or(Value::Null),
+        }))
+    }
+}
@@ -0,0 +1,50 @@
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Result of one `--self-test` or `--check-config` check
+#[derive(Debug, Serialize)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// What `--self-test` and `--check-config` print: every check that ran,
+/// and `ok` when all of them passed
+#[derive(Debug, Serialize)]
+pub struct SelfTestReport {
+    pub ok: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl Default for SelfTestReport {
+    fn default() -> Self {
+        Self {
+            ok: true,
+            checks: Vec::new(),
+        }
+    }
+}
+
+impl SelfTestReport {
+    /// Records `result` under `name`, described by `detail` when it passed
+    /// and by the error otherwise. Hands back the value for later checks.
+    pub fn check<T, E: Display>(
+        &mut self,
+        name: impl Into<String>,
+        result: Result<T, E>,
+        detail: impl FnOnce(&T) -> String,
+    ) -> Option<T> {
+        let (ok, detail, value) = match result {
+            Ok(value) => (true, detail(&value), Some(value)),
+            Err(err) => (false, err.to_string(), None),
+        };
+        self.ok &= ok;
+        self.checks.push(SelfTestCheck {
+            name: name.into(),
+            ok,
+            detail,
+        });
+        value
+    }
+}
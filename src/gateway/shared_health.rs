@@ -0,0 +1,205 @@
+use crate::config::database::{ConfigError, DatabaseConfig};
+use crate::repositories::connection::{ConnectionError, DbConnection};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::info;
+use uuid::Uuid;
+
+/// Error text used to tell "someone else holds the lease" from real failures
+const LEASE_HELD: &str = "probe lease held by another gateway";
+
+#[derive(Error, Debug)]
+pub enum SharedHealthError {
+    #[error("Shared health configuration error: {0}")]
+    Config(#[from] ConfigError),
+
+    #[error("Shared health state needs a ws:// or wss:// endpoint in [gateway_health_db], got '{endpoint}'")]
+    LocalEndpoint { endpoint: String },
+
+    #[error("Shared health database error: {0}")]
+    Database(#[from] Box<surrealdb::Error>),
+
+    #[error("{0}")]
+    Unavailable(#[from] ConnectionError),
+}
+
+/// Boxed, as the database error would otherwise make every `Result`
+/// carrying this error large
+impl From<surrealdb::Error> for SharedHealthError {
+    fn from(err: surrealdb::Error) -> Self {
+        SharedHealthError::Database(Box::new(err))
+    }
+}
+
+/// One upstream's health as last reported by any gateway replica
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedServiceHealth {
+    /// `user_service` or `product_service`
+    pub service: String,
+    pub is_healthy: bool,
+    pub consecutive_failures: u32,
+    /// `health_check` or `upstream_5xx`
+    pub reason: String,
+    /// Instance id of the replica that wrote this state
+    pub reported_by: String,
+    /// Unix milliseconds
+    pub updated_at: i64,
+}
+
+impl SharedServiceHealth {
+    pub fn new(
+        service: &str,
+        is_healthy: bool,
+        consecutive_failures: u32,
+        reason: &str,
+        reported_by: &str,
+    ) -> Self {
+        Self {
+            service: service.to_string(),
+            is_healthy,
+            consecutive_failures,
+            reason: reason.to_string(),
+            reported_by: reported_by.to_string(),
+            updated_at: Utc::now().timestamp_millis(),
+        }
+    }
+
+    pub fn age(&self) -> Duration {
+        let millis = Utc::now()
+            .timestamp_millis()
+            .saturating_sub(self.updated_at);
+        Duration::from_millis(millis.max(0) as u64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SharedHealthConfig {
+    pub db: DatabaseConfig,
+    /// Identifies this replica in the lease and in reported states
+    pub instance_id: String,
+    /// How long a leader keeps the probe lease without renewing it
+    pub lease: Duration,
+    /// How often the lease is renewed and followers pull the shared state
+    pub sync_interval: Duration,
+}
+
+impl SharedHealthConfig {
+    /// Returns `None` unless `GATEWAY_SHARED_HEALTH` is `true`. Then reads
+    /// the `[gateway_health_db]` settings, `GATEWAY_INSTANCE_ID` (default: a
+    /// random id), `GATEWAY_SHARED_HEALTH_LEASE_SECS` (default 15) and
+    /// `GATEWAY_SHARED_HEALTH_SYNC_SECS` (default 5).
+    pub fn from_env() -> Result<Option<Self>, SharedHealthError> {
+        let enabled = std::env::var("GATEWAY_SHARED_HEALTH")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+
+        let db = DatabaseConfig::gateway_health()?;
+        // An in-process database cannot be shared between replicas
+        if !db.is_remote() {
+            return Err(SharedHealthError::LocalEndpoint {
+                endpoint: db.endpoint,
+            });
+        }
+
+        let secs = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(default)
+        };
+        Ok(Some(Self {
+            db,
+            instance_id: std::env::var("GATEWAY_INSTANCE_ID")
+                .unwrap_or_else(|_| Uuid::new_v4().simple().to_string()),
+            lease: Duration::from_secs(secs("GATEWAY_SHARED_HEALTH_LEASE_SECS", 15)),
+            sync_interval: Duration::from_secs(secs("GATEWAY_SHARED_HEALTH_SYNC_SECS", 5)),
+        }))
+    }
+}
+
+/// Upstream health shared by gateway replicas through SurrealDB.
+///
+/// One replica at a time holds the probe lease (`gateway_lease:probes`) and
+/// runs the health checks; it writes each result to `gateway_health`. The
+/// others skip probing and pull the shared state instead, so every replica
+/// converges on the leader's view without multiplying probe traffic. A lease
+/// that is not renewed expires and the next replica to ask takes it over.
+pub struct SharedHealthStore {
+    db: Arc<DbConnection>,
+    config: SharedHealthConfig,
+}
+
+impl std::fmt::Debug for SharedHealthStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedHealthStore")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SharedHealthStore {
+    pub async fn connect(config: SharedHealthConfig) -> Result<Self, SharedHealthError> {
+        let db = DbConnection::open(&config.db).await?;
+        info!(
+            "Connected to shared health state at {} as gateway '{}'",
+            config.db.endpoint, config.instance_id
+        );
+        Ok(Self { db, config })
+    }
+
+    pub fn config(&self) -> &SharedHealthConfig {
+        &self.config
+    }
+
+    /// Takes the probe lease, or renews it if this replica already holds
+    /// it. Returns whether this replica is now the leader.
+    pub async fn try_acquire_leadership(&self) -> Result<bool, SharedHealthError> {
+        let db = self.db.handle()?;
+        let now = Utc::now().timestamp_millis();
+        let result = db
+            .query(
+                "BEGIN TRANSACTION; \
+                 LET $lease = (SELECT * FROM gateway_lease:probes)[0]; \
+                 IF $lease.holder != NONE AND $lease.holder != $holder AND $lease.expires_at > $now \
+                 { THROW $held }; \
+                 UPDATE gateway_lease:probes CONTENT { holder: $holder, expires_at: $expires_at }; \
+                 COMMIT TRANSACTION;",
+            )
+            .bind(("holder", self.config.instance_id.as_str()))
+            .bind(("now", now))
+            .bind(("expires_at", now + self.config.lease.as_millis() as i64))
+            .bind(("held", LEASE_HELD))
+            .await
+            .and_then(surrealdb::Response::check);
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(err) if err.to_string().contains(LEASE_HELD) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn publish(&self, state: SharedServiceHealth) -> Result<(), SharedHealthError> {
+        let db = self.db.handle()?;
+        let key = state.service.clone();
+        let _: Option<SharedServiceHealth> = db
+            .update(("gateway_health", key.as_str()))
+            .content(state)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn fetch(&self) -> Result<Vec<SharedServiceHealth>, SharedHealthError> {
+        let db = self.db.handle()?;
+        let states: Vec<SharedServiceHealth> =
+            db.query("SELECT * FROM gateway_health").await?.take(0)?;
+        Ok(states)
+    }
+}
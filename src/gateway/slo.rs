@@ -0,0 +1,356 @@
+use bytes::Bytes;
+use chrono::Utc;
+use http_body_util::Full;
+use hyper::{Request, Uri};
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use hyper_util::rt::TokioExecutor;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Upstreams an SLO can be defined for
+const SERVICES: &[&str] = &["user_service", "product_service"];
+/// Requests are counted in buckets of this length
+const BUCKET: Duration = Duration::from_secs(60);
+/// Span of the fast burn rate that alerts are raised on
+const FAST_WINDOW_BUCKETS: usize = 5;
+/// Fast windows with fewer requests than this never raise an alert
+const MIN_ALERT_REQUESTS: u64 = 20;
+const ALERT_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Error, Debug)]
+pub enum SloConfigError {
+    #[error("Invalid GATEWAY_SLOS entry '{0}', expected service=availability/latency_ms")]
+    InvalidEntry(String),
+
+    #[error("Unknown SLO service '{0}', expected user_service or product_service")]
+    UnknownService(String),
+
+    #[error("Invalid availability target '{0}', expected a percentage below 100")]
+    InvalidAvailability(String),
+
+    #[error("Invalid GATEWAY_SLO_WEBHOOK_URL '{0}', expected an http:// URL")]
+    InvalidWebhook(String),
+}
+
+/// What an upstream promises: the share of requests that must succeed
+/// within the latency threshold
+#[derive(Debug, Clone)]
+pub struct SloTarget {
+    /// Fraction of good requests, e.g. `0.999`
+    pub availability: f64,
+    pub latency_threshold: Duration,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Bucket {
+    index: u64,
+    total: u64,
+    errors: u64,
+    slow: u64,
+}
+
+#[derive(Debug)]
+struct ServiceSlo {
+    service: &'static str,
+    target: SloTarget,
+    buckets: Mutex<VecDeque<Bucket>>,
+    alerting: Mutex<bool>,
+}
+
+/// Totals over part of the rolling window
+#[derive(Debug, Default)]
+struct WindowCounts {
+    total: u64,
+    errors: u64,
+    slow: u64,
+}
+
+impl WindowCounts {
+    /// How many times faster than sustainable the error budget is spent;
+    /// `1.0` uses it up exactly by the end of the window
+    fn burn_rate(&self, availability: f64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let bad_ratio = (self.errors + self.slow) as f64 / self.total as f64;
+        bad_ratio / (1.0 - availability)
+    }
+}
+
+impl ServiceSlo {
+    fn record(&self, bucket_index: u64, ok: bool, elapsed: Duration, window_buckets: usize) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.back().map(|b| b.index) != Some(bucket_index) {
+            buckets.push_back(Bucket {
+                index: bucket_index,
+                ..Bucket::default()
+            });
+        }
+        while buckets
+            .front()
+            .is_some_and(|b| b.index + window_buckets as u64 <= bucket_index)
+        {
+            buckets.pop_front();
+        }
+
+        let bucket = buckets.back_mut().expect("bucket pushed above");
+        bucket.total += 1;
+        if !ok {
+            bucket.errors += 1;
+        } else if elapsed > self.target.latency_threshold {
+            bucket.slow += 1;
+        }
+    }
+
+    /// Totals of the last `span` buckets up to `current`
+    fn counts(&self, current: u64, span: usize) -> WindowCounts {
+        let buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        buckets
+            .iter()
+            .filter(|b| b.index + span as u64 > current)
+            .fold(WindowCounts::default(), |mut counts, b| {
+                counts.total += b.total;
+                counts.errors += b.errors;
+                counts.slow += b.slow;
+                counts
+            })
+    }
+}
+
+/// Rolling error budgets for the upstreams with an SLO.
+///
+/// Every proxied request counts once against its upstream: a 5xx, a busy
+/// response or a proxy error is an error, and a success slower than the
+/// latency threshold is slow. Both spend the budget. Cached responses never
+/// reach an upstream and are not counted. Burn rates compare the share of
+/// bad requests with what the availability target allows, over the whole
+/// window and over its last five minutes; a fast burn rate at or above the
+/// alert threshold logs a warning and, when configured, posts to a webhook.
+#[derive(Debug)]
+pub struct SloTracker {
+    services: Vec<ServiceSlo>,
+    window_buckets: usize,
+    alert_burn_rate: f64,
+    webhook: Option<Uri>,
+    epoch: Instant,
+}
+
+impl SloTracker {
+    /// Reads `GATEWAY_SLOS` (`service=availability/latency_ms,...`, e.g.
+    /// `user_service=99.9/500`), `GATEWAY_SLO_WINDOW_SECS` (default 3600),
+    /// `GATEWAY_SLO_ALERT_BURN_RATE` (default 10) and
+    /// `GATEWAY_SLO_WEBHOOK_URL`
+    pub fn from_env() -> Result<Self, SloConfigError> {
+        let env_u64 = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+
+        let mut services = Vec::new();
+        let list = std::env::var("GATEWAY_SLOS").unwrap_or_default();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (service, target) = entry
+                .split_once('=')
+                .ok_or_else(|| SloConfigError::InvalidEntry(entry.to_string()))?;
+            let service = SERVICES
+                .iter()
+                .find(|known| **known == service.trim())
+                .ok_or_else(|| SloConfigError::UnknownService(service.trim().to_string()))?;
+            let (availability, latency_ms) = target
+                .split_once('/')
+                .and_then(|(availability, latency)| {
+                    Some((availability.trim(), latency.trim().parse::<u64>().ok()?))
+                })
+                .ok_or_else(|| SloConfigError::InvalidEntry(entry.to_string()))?;
+            let percent = availability
+                .parse::<f64>()
+                .ok()
+                .filter(|percent| *percent > 0.0 && *percent < 100.0)
+                .ok_or_else(|| SloConfigError::InvalidAvailability(availability.to_string()))?;
+
+            services.retain(|existing: &ServiceSlo| existing.service != *service);
+            services.push(ServiceSlo {
+                service,
+                target: SloTarget {
+                    availability: percent / 100.0,
+                    latency_threshold: Duration::from_millis(latency_ms),
+                },
+                buckets: Mutex::new(VecDeque::new()),
+                alerting: Mutex::new(false),
+            });
+        }
+
+        let webhook = match std::env::var("GATEWAY_SLO_WEBHOOK_URL") {
+            Ok(url) if !url.trim().is_empty() => Some(
+                url.trim()
+                    .parse::<Uri>()
+                    .ok()
+                    .filter(|uri| uri.scheme_str() == Some("http") && uri.host().is_some())
+                    .ok_or(SloConfigError::InvalidWebhook(url))?,
+            ),
+            _ => None,
+        };
+        let window = env_u64("GATEWAY_SLO_WINDOW_SECS").unwrap_or(3600);
+
+        Ok(Self {
+            services,
+            window_buckets: (window / BUCKET.as_secs()).max(FAST_WINDOW_BUCKETS as u64) as usize,
+            alert_burn_rate: std::env::var("GATEWAY_SLO_ALERT_BURN_RATE")
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|rate| *rate > 0.0)
+                .unwrap_or(10.0),
+            webhook,
+            epoch: Instant::now(),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.services.is_empty()
+    }
+
+    /// Configured targets for the startup log, e.g. `user_service=99.9%/500ms`
+    pub fn describe(&self) -> String {
+        self.services
+            .iter()
+            .map(|slo| {
+                format!(
+                    "{}={}%/{}ms",
+                    slo.service,
+                    slo.target.availability * 100.0,
+                    slo.target.latency_threshold.as_millis()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Counts a proxied request against `service`'s budget; a no-op for
+    /// upstreams without an SLO
+    pub fn record(&self, service: &str, ok: bool, elapsed: Duration) {
+        if let Some(slo) = self.services.iter().find(|slo| slo.service == service) {
+            slo.record(self.current_bucket(), ok, elapsed, self.window_buckets);
+        }
+    }
+
+    /// Error budget state per upstream, for `/metrics`
+    pub fn stats(&self) -> Value {
+        let current = self.current_bucket();
+        let services: serde_json::Map<String, Value> = self
+            .services
+            .iter()
+            .map(|slo| {
+                let availability = slo.target.availability;
+                let window = slo.counts(current, self.window_buckets);
+                let fast = slo.counts(current, FAST_WINDOW_BUCKETS);
+                let consumed = window.burn_rate(availability);
+                (
+                    slo.service.to_string(),
+                    json!({
+                        "availability_target": availability * 100.0,
+                        "latency_threshold_ms": slo.target.latency_threshold.as_millis() as u64,
+                        "requests": window.total,
+                        "errors": window.errors,
+                        "slow": window.slow,
+                        "budget_consumed": consumed,
+                        "budget_remaining": (1.0 - consumed).max(0.0),
+                        "burn_rate": consumed,
+                        "burn_rate_5m": fast.burn_rate(availability),
+                        "alerting": *slo.alerting.lock().unwrap_or_else(PoisonError::into_inner),
+                    }),
+                )
+            })
+            .collect();
+        json!({
+            "window_secs": self.window_buckets as u64 * BUCKET.as_secs(),
+            "alert_burn_rate": self.alert_burn_rate,
+            "services": services,
+        })
+    }
+
+    /// Checks the fast burn rates every 30 seconds and alerts when an
+    /// upstream starts or stops burning its budget too fast
+    pub fn spawn_alerts(self: &Arc<Self>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let tracker = Arc::clone(self);
+        tokio::spawn(async move {
+            let client: Client<HttpConnector, Full<Bytes>> =
+                Client::builder(TokioExecutor::new()).build_http();
+            loop {
+                tokio::time::sleep(ALERT_CHECK_INTERVAL).await;
+                for alert in tracker.check_alerts() {
+                    if let Some(webhook) = &tracker.webhook {
+                        tokio::spawn(post_alert(client.clone(), webhook.clone(), alert));
+                    }
+                }
+            }
+        });
+    }
+
+    /// Updates each upstream's alert state and returns the transitions
+    fn check_alerts(&self) -> Vec<Value> {
+        let current = self.current_bucket();
+        let mut transitions = Vec::new();
+        for slo in &self.services {
+            let fast = slo.counts(current, FAST_WINDOW_BUCKETS);
+            let burn_rate = fast.burn_rate(slo.target.availability);
+            let burning = fast.total >= MIN_ALERT_REQUESTS && burn_rate >= self.alert_burn_rate;
+
+            let mut alerting = slo.alerting.lock().unwrap_or_else(PoisonError::into_inner);
+            if burning == *alerting {
+                continue;
+            }
+            *alerting = burning;
+            if burning {
+                warn!(
+                    "🔥 {} is burning its error budget {:.1}x too fast ({} of {} requests bad in 5m)",
+                    slo.service,
+                    burn_rate,
+                    fast.errors + fast.slow,
+                    fast.total
+                );
+            } else {
+                info!(
+                    "🧯 {} error budget burn back under {:.1}x",
+                    slo.service, burn_rate
+                );
+            }
+            transitions.push(json!({
+                "service": slo.service,
+                "status": if burning { "firing" } else { "resolved" },
+                "burn_rate_5m": burn_rate,
+                "threshold": self.alert_burn_rate,
+                "requests": fast.total,
+                "errors": fast.errors,
+                "slow": fast.slow,
+                "timestamp": Utc::now(),
+            }));
+        }
+        transitions
+    }
+
+    fn current_bucket(&self) -> u64 {
+        self.epoch.elapsed().as_secs() / BUCKET.as_secs()
+    }
+}
+
+async fn post_alert(client: Client<HttpConnector, Full<Bytes>>, webhook: Uri, alert: Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri(webhook)
+        .header("Content-Type", "application/json")
+        .body(Full::new(Bytes::from(alert.to_string())));
+    let Ok(request) = request else {
+        return;
+    };
+    match tokio::time::timeout(WEBHOOK_TIMEOUT, client.request(request)).await {
+        Ok(Ok(response)) if response.status().is_success() => {}
+        Ok(Ok(response)) => warn!("SLO webhook answered {}", response.status()),
+        Ok(Err(err)) => warn!("Failed to post SLO alert: {}", err),
+        Err(_) => warn!("SLO webhook timed out after {:?}", WEBHOOK_TIMEOUT),
+    }
+}
@@ -0,0 +1,166 @@
+use crate::gateway::redaction::RedactionPolicy;
+use crate::gateway::upstream::UpstreamConnection;
+use crate::middleware::org_context::ORG_HEADER;
+use bytes::Bytes;
+use chrono::Utc;
+use http_body_util::{BodyExt, Full};
+use hyper::header::{HeaderMap, AUTHORIZATION};
+use hyper::Request;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One upstream contributing a section of the catalog snapshot
+#[derive(Debug)]
+pub struct SnapshotSource<'a> {
+    /// Key of the section in the snapshot, e.g. "users"
+    pub section: &'static str,
+    pub service_name: &'static str,
+    pub method: &'static str,
+    pub upstream: &'a UpstreamConnection,
+    /// Unhealthy sources are skipped instead of waiting for a timeout
+    pub healthy: bool,
+}
+
+/// The client a snapshot is fetched for. Each section is authorized,
+/// scoped and redacted as the client's own call to its method would be.
+#[derive(Debug)]
+pub struct SnapshotCaller<'a> {
+    /// The client's request headers
+    pub headers: &'a HeaderMap,
+    /// Whether header forwarding lets the client's `Authorization` through
+    pub forwards_authorization: bool,
+    /// Organization of the client's token, sent as `X-Org-Id`
+    pub org: Option<&'a str>,
+    pub redaction: &'a RedactionPolicy,
+}
+
+/// Fetches users and products concurrently and assembles one snapshot
+/// document. A failing source leaves its section `null`, records a warning
+/// and marks the snapshot as partial rather than failing the whole call.
+pub async fn fetch_catalog_snapshot(
+    caller: &SnapshotCaller<'_>,
+    users: SnapshotSource<'_>,
+    products: SnapshotSource<'_>,
+) -> Value {
+    let (user_result, product_result) = tokio::join!(
+        fetch_section(caller, &users),
+        fetch_section(caller, &products)
+    );
+
+    let mut snapshot = serde_json::Map::new();
+    let mut source_info = serde_json::Map::new();
+    let mut warnings = Vec::new();
+
+    for (source, (result, fetched_at, duration_ms)) in
+        [(&users, user_result), (&products, product_result)]
+    {
+        let ok = result.is_ok();
+        match result {
+            Ok(section) => {
+                snapshot.insert(source.section.to_string(), section);
+            }
+            Err(err) => {
+                warnings.push(format!("{}: {}", source.service_name, err));
+                snapshot.insert(source.section.to_string(), Value::Null);
+            }
+        }
+        source_info.insert(
+            source.section.to_string(),
+            json!({
+                "service": source.service_name,
+                "method": source.method,
+                "ok": ok,
+                "fetched_at": fetched_at,
+                "duration_ms": duration_ms,
+            }),
+        );
+    }
+
+    snapshot.insert("partial".to_string(), Value::Bool(!warnings.is_empty()));
+    snapshot.insert("generated_at".to_string(), json!(Utc::now().to_rfc3339()));
+    snapshot.insert("sources".to_string(), Value::Object(source_info));
+    snapshot.insert("warnings".to_string(), json!(warnings));
+    Value::Object(snapshot)
+}
+
+async fn fetch_section(
+    caller: &SnapshotCaller<'_>,
+    source: &SnapshotSource<'_>,
+) -> (Result<Value, String>, String, u64) {
+    let start = Instant::now();
+    let result = if source.healthy {
+        match timeout(
+            SNAPSHOT_TIMEOUT,
+            call_method(caller, source.upstream, source.method),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(format!("timed out after {}s", SNAPSHOT_TIMEOUT.as_secs())),
+        }
+    } else {
+        Err("service unavailable".to_string())
+    };
+
+    (
+        result,
+        Utc::now().to_rfc3339(),
+        start.elapsed().as_millis() as u64,
+    )
+}
+
+async fn call_method(
+    caller: &SnapshotCaller<'_>,
+    upstream: &UpstreamConnection,
+    method: &str,
+) -> Result<Value, String> {
+    let body = json!({ "jsonrpc": "2.0", "method": method, "id": 1 }).to_string();
+    // Fields the caller may not see are stripped as from its own call
+    let redaction = caller.redaction.plan(caller.headers, body.as_bytes());
+    let request = Request::builder()
+        .method("POST")
+        .uri(upstream.base_url())
+        .header("Content-Type", "application/json");
+    let client_authorization = caller
+        .headers
+        .get(AUTHORIZATION)
+        .filter(|_| caller.forwards_authorization);
+    let mut request = upstream.authorize_client(request, client_authorization);
+    if let Some(org) = caller.org {
+        request = request.header(ORG_HEADER, org);
+    }
+    let request = upstream
+        .sign(request, "POST", "/", body.as_bytes())
+        .body(Full::new(Bytes::from(body)))
+        .map_err(|e| e.to_string())?;
+
+    let response = upstream
+        .client
+        .request(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("upstream returned {}", response.status()));
+    }
+
+    let bytes = response
+        .collect()
+        .await
+        .map_err(|e| e.to_string())?
+        .to_bytes();
+    let mut payload: Value = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+    if let Some(plan) = redaction {
+        plan.apply(&mut payload);
+    }
+
+    if let Some(error) = payload.get("error") {
+        return Err(format!("{} failed: {}", method, error));
+    }
+    payload
+        .get_mut("result")
+        .map(Value::take)
+        .ok_or_else(|| format!("{} returned no result", method))
+}
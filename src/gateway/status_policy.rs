@@ -0,0 +1,115 @@
+use crate::middleware::request_id::RequestId;
+use hyper::StatusCode;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// JSON-RPC error code used when the gateway rewrites an upstream error
+pub const UPSTREAM_ERROR_CODE: i32 = -32050;
+
+#[derive(Error, Debug)]
+pub enum StatusPolicyError {
+    #[error("Invalid HTTP status '{0}' in GATEWAY_RETRY_UPSTREAM_STATUSES")]
+    InvalidStatus(String),
+}
+
+/// How an upstream response counts for metrics and circuit breaking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamOutcome {
+    Success,
+    /// 4xx: the caller's fault, the upstream itself is fine
+    ClientError,
+    /// 5xx: counted as a failure and towards opening the circuit
+    ServerError,
+}
+
+impl UpstreamOutcome {
+    pub fn classify(status: StatusCode) -> Self {
+        if status.is_server_error() {
+            UpstreamOutcome::ServerError
+        } else if status.is_client_error() {
+            UpstreamOutcome::ClientError
+        } else {
+            UpstreamOutcome::Success
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        *self == UpstreamOutcome::ServerError
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StatusPolicy {
+    /// Upstream statuses retried like connection failures
+    pub retry_statuses: HashSet<u16>,
+    /// Replace upstream error bodies with the gateway's JSON-RPC error envelope
+    pub rewrite_errors: bool,
+}
+
+impl StatusPolicy {
+    /// Reads `GATEWAY_RETRY_UPSTREAM_STATUSES` (comma separated, e.g.
+    /// `502,503,504`; empty retries nothing) and
+    /// `GATEWAY_REWRITE_UPSTREAM_ERRORS` (`true` to enable).
+    pub fn from_env() -> Result<Self, StatusPolicyError> {
+        let retry_statuses = std::env::var("GATEWAY_RETRY_UPSTREAM_STATUSES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                StatusCode::from_bytes(s.as_bytes())
+                    .map(|status| status.as_u16())
+                    .map_err(|_| StatusPolicyError::InvalidStatus(s.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            retry_statuses,
+            rewrite_errors: matches!(
+                std::env::var("GATEWAY_REWRITE_UPSTREAM_ERRORS").as_deref(),
+                Ok("true") | Ok("1")
+            ),
+        })
+    }
+
+    pub fn should_retry(&self, status: StatusCode) -> bool {
+        self.retry_statuses.contains(&status.as_u16())
+    }
+
+    /// Builds the envelope that replaces an upstream error response, or
+    /// `None` when rewriting is disabled or the status is not an error. The
+    /// upstream body is dropped so internal details never reach clients.
+    pub fn rewrite(
+        &self,
+        status: StatusCode,
+        service: &str,
+        request_body: &[u8],
+        request_id: &RequestId,
+    ) -> Option<Value> {
+        if !self.rewrite_errors || UpstreamOutcome::classify(status) == UpstreamOutcome::Success {
+            return None;
+        }
+
+        // Batches get a null id: the upstream failed them as a whole
+        let id = serde_json::from_slice::<Value>(request_body)
+            .ok()
+            .and_then(|request| request.get("id").cloned())
+            .unwrap_or(Value::Null);
+
+        Some(json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "code": UPSTREAM_ERROR_CODE,
+                "message": "Upstream error",
+                "data": {
+                    "service": service,
+                    "status": status.as_u16(),
+                    "reason": status.canonical_reason().unwrap_or("Unknown"),
+                    "request_id": request_id,
+                },
+            },
+            "id": id,
+        }))
+    }
+}
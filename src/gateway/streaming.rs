@@ -0,0 +1,142 @@
+use bytes::Bytes;
+use hyper::body::{Body, Frame, SizeHint};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StreamingRoutesError {
+    #[error("Invalid GATEWAY_STREAMING_ROUTES entry '{0}', expected a path starting with /")]
+    InvalidRoute(String),
+}
+
+/// Routes whose upstream responses are piped to the client as they arrive
+/// instead of being buffered, for Server-Sent Events and long-polls.
+///
+/// Streamed responses skip everything that needs the whole body: the
+/// response cache, redaction and id normalization.
+#[derive(Debug, Default)]
+pub struct StreamingRoutes {
+    prefixes: Vec<String>,
+}
+
+impl StreamingRoutes {
+    /// Reads `GATEWAY_STREAMING_ROUTES` (`/path,...`, matched as prefixes);
+    /// unset buffers every response
+    pub fn from_env() -> Result<Self, StreamingRoutesError> {
+        let prefixes = std::env::var("GATEWAY_STREAMING_ROUTES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                if entry.starts_with('/') {
+                    Ok(entry.to_string())
+                } else {
+                    Err(StreamingRoutesError::InvalidRoute(entry.to_string()))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { prefixes })
+    }
+
+    pub fn is_streaming(&self, path: &str) -> bool {
+        self.prefixes
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    /// Routes for the startup log, e.g. `/api/events, /api/poll`
+    pub fn describe(&self) -> String {
+        self.prefixes.join(", ")
+    }
+}
+
+/// How a streamed response ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamEnd {
+    /// The upstream finished the body
+    Completed,
+    /// Reading the upstream body failed
+    UpstreamError(String),
+    /// The client went away before the body ended
+    ClientGone,
+}
+
+/// Response body that forwards each upstream frame as soon as it arrives
+/// and reports, once, how the stream ended and how many bytes it carried.
+/// Metrics for a streamed request are recorded from that report rather
+/// than when the response headers go out.
+pub struct MeteredBody<B> {
+    inner: B,
+    bytes: u64,
+    on_end: Option<Box<dyn FnOnce(StreamEnd, u64) + Send + Sync>>,
+}
+
+impl<B> MeteredBody<B> {
+    pub fn new(inner: B, on_end: impl FnOnce(StreamEnd, u64) + Send + Sync + 'static) -> Self {
+        Self {
+            inner,
+            bytes: 0,
+            on_end: Some(Box::new(on_end)),
+        }
+    }
+
+    fn end(&mut self, end: StreamEnd) {
+        if let Some(on_end) = self.on_end.take() {
+            on_end(end, self.bytes);
+        }
+    }
+}
+
+impl<B> Body for MeteredBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: std::fmt::Display,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    self.bytes += data.len() as u64;
+                }
+                // Hyper stops polling once a body says it has ended
+                if self.inner.is_end_stream() {
+                    self.end(StreamEnd::Completed);
+                }
+            }
+            Poll::Ready(Some(Err(err))) => {
+                let end = StreamEnd::UpstreamError(err.to_string());
+                self.end(end);
+            }
+            Poll::Ready(None) => self.end(StreamEnd::Completed),
+            Poll::Pending => {}
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B> Drop for MeteredBody<B> {
+    fn drop(&mut self) {
+        self.end(StreamEnd::ClientGone);
+    }
+}
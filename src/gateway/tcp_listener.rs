@@ -0,0 +1,169 @@
+use bytes::Bytes;
+use hyper::StatusCode;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::net::SocketAddr;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::TcpListener;
+use tracing::{debug, warn};
+
+/// JSON-RPC error code for frames the gateway answered with a non-JSON
+/// HTTP error, e.g. a rate limit or an unroutable request
+pub const GATEWAY_REJECTED_CODE: i32 = -32018;
+
+const DEFAULT_MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum TcpListenerError {
+    #[error("Invalid GATEWAY_TCP_LISTEN '{0}', expected host:port")]
+    InvalidAddress(String),
+
+    #[error("Invalid GATEWAY_TCP_MAX_FRAME_BYTES '{0}', expected a positive number")]
+    InvalidMaxFrame(String),
+}
+
+/// The optional raw TCP listener for internal clients that want JSON-RPC
+/// without HTTP.
+///
+/// Every frame is a 4-byte big-endian length followed by that many bytes
+/// of JSON-RPC (a single call or a batch); every request frame is answered
+/// by one response frame, in order. Frames carry no headers, so they are
+/// routed by method and rate limited by the peer address like a plain
+/// `POST /`.
+#[derive(Debug, Clone)]
+pub struct TcpListenerConfig {
+    pub addr: SocketAddr,
+    pub max_frame_bytes: usize,
+}
+
+impl TcpListenerConfig {
+    /// Reads `GATEWAY_TCP_LISTEN` (e.g. `127.0.0.1:8083`; unset disables the
+    /// listener) and `GATEWAY_TCP_MAX_FRAME_BYTES` (default 1 MiB)
+    pub fn from_env() -> Result<Option<Self>, TcpListenerError> {
+        let Ok(addr) = std::env::var("GATEWAY_TCP_LISTEN") else {
+            return Ok(None);
+        };
+        let addr = addr
+            .trim()
+            .parse()
+            .map_err(|_| TcpListenerError::InvalidAddress(addr))?;
+        let max_frame_bytes = match std::env::var("GATEWAY_TCP_MAX_FRAME_BYTES") {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or(TcpListenerError::InvalidMaxFrame(value))?,
+            Err(_) => DEFAULT_MAX_FRAME_BYTES,
+        };
+        Ok(Some(Self {
+            addr,
+            max_frame_bytes,
+        }))
+    }
+}
+
+/// Reads one frame; `None` when the peer closed the connection between
+/// frames
+pub async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_frame_bytes: usize,
+) -> std::io::Result<Option<Bytes>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length).await {
+        Ok(_) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > max_frame_bytes {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("frame of {} bytes exceeds {}", length, max_frame_bytes),
+        ));
+    }
+    let mut payload = vec![0u8; length];
+    reader.read_exact(&mut payload).await?;
+    Ok(Some(Bytes::from(payload)))
+}
+
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    let length = u32::try_from(payload.len()).map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "response frame too large")
+    })?;
+    writer.write_all(&length.to_be_bytes()).await?;
+    writer.write_all(payload).await?;
+    writer.flush().await
+}
+
+/// The response frame for the gateway's HTTP response to `request`. JSON
+/// bodies pass through; anything else (plain-text rejections such as `429
+/// Rate limit exceeded`) becomes a JSON-RPC error for the request's id so
+/// clients only parse one format.
+pub fn response_frame(request: &[u8], status: StatusCode, body: Bytes) -> Bytes {
+    if serde_json::from_slice::<Value>(&body).is_ok() {
+        return body;
+    }
+    if status.is_success() && body.is_empty() {
+        // Notifications get no answer over HTTP; frames still need one
+        return body;
+    }
+    let id = serde_json::from_slice::<Value>(request)
+        .ok()
+        .and_then(|request| request.get("id").cloned())
+        .unwrap_or(Value::Null);
+    let error = json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": GATEWAY_REJECTED_CODE,
+            "message": String::from_utf8_lossy(&body),
+            "data": { "status": status.as_u16() },
+        },
+    });
+    Bytes::from(error.to_string())
+}
+
+/// Accepts framed connections forever, answering each frame with
+/// `handle(frame, peer)`
+pub async fn serve_frames<H, F>(
+    listener: TcpListener,
+    max_frame_bytes: usize,
+    handle: H,
+) -> std::io::Result<()>
+where
+    H: Fn(Bytes, SocketAddr) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Bytes> + Send,
+{
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        // Frames are small and answered one at a time
+        let _ = stream.set_nodelay(true);
+        let handle = handle.clone();
+
+        tokio::spawn(async move {
+            let (reader, writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            let mut writer = BufWriter::new(writer);
+            loop {
+                let frame = match read_frame(&mut reader, max_frame_bytes).await {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => break,
+                    Err(err) => {
+                        warn!("🔌 Closing framed connection from {}: {}", peer, err);
+                        break;
+                    }
+                };
+                let response = handle(frame, peer).await;
+                if let Err(err) = write_frame(&mut writer, &response).await {
+                    debug!("🔌 Framed connection from {} went away: {}", peer, err);
+                    break;
+                }
+            }
+        });
+    }
+}
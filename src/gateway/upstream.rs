@@ -0,0 +1,448 @@
+use crate::crypto::request_signing::{
+    RequestSigner, SIGNATURE_HEADER, SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+};
+use crate::gateway::upstream_metrics::{TimedConnector, UpstreamMetrics};
+use crate::middleware::authorization::CLIENT_AUTHORIZATION_HEADER;
+use crate::middleware::load_shedding::SERVICE_BUSY_HEADER;
+use base64::Engine;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::header::{HeaderMap, HeaderValue, AUTHORIZATION, RETRY_AFTER};
+use hyper::http::request::Builder;
+use hyper::{Request, Response, StatusCode};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use hyper_util::rt::TokioExecutor;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::time::timeout;
+
+pub type UpstreamClient = Client<TimedConnector<HttpsConnector<HttpConnector>>, Full<Bytes>>;
+
+#[derive(Error, Debug)]
+pub enum UpstreamConfigError {
+    #[error("{var} must be in the form username:password")]
+    InvalidBasicAuth { var: String },
+
+    #[error("{var} is set but {missing} is not")]
+    IncompleteTlsIdentity { var: String, missing: String },
+
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("No private key found in {path}")]
+    MissingPrivateKey { path: String },
+
+    #[error("TLS configuration error: {0}")]
+    Tls(#[from] rustls::Error),
+}
+
+/// Why a health probe failed
+#[derive(Error, Debug)]
+pub enum ProbeError {
+    #[error("Request failed: {0}")]
+    Request(#[from] hyper_util::client::legacy::Error),
+
+    #[error("Responded {0}")]
+    Status(StatusCode),
+
+    #[error("Failed to read the response: {0}")]
+    Body(#[from] hyper::Error),
+
+    #[error("Response is not JSON")]
+    InvalidBody,
+
+    #[error("health returned an error: {0}")]
+    Rpc(serde_json::Value),
+
+    #[error("No answer within {}ms", .0.as_millis())]
+    Timeout(Duration),
+}
+
+/// Authorization the gateway attaches to every request it proxies upstream
+#[derive(Clone)]
+pub enum UpstreamAuth {
+    Bearer(String),
+    Basic { username: String, password: String },
+}
+
+impl std::fmt::Debug for UpstreamAuth {
+    // Never print the secrets themselves
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpstreamAuth::Bearer(_) => f.write_str("Bearer(***)"),
+            UpstreamAuth::Basic { username, .. } => {
+                write!(f, "Basic {{ username: {:?}, password: *** }}", username)
+            }
+        }
+    }
+}
+
+impl UpstreamAuth {
+    pub fn header_value(&self) -> String {
+        match self {
+            UpstreamAuth::Bearer(token) => format!("Bearer {}", token),
+            UpstreamAuth::Basic { username, password } => format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", username, password))
+            ),
+        }
+    }
+}
+
+/// Client certificate (and optional private CA) for mTLS to an upstream
+#[derive(Debug, Clone)]
+pub struct TlsIdentity {
+    pub cert_path: String,
+    pub key_path: String,
+    pub ca_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UpstreamCredentials {
+    pub auth: Option<UpstreamAuth>,
+    pub tls: Option<TlsIdentity>,
+}
+
+impl UpstreamCredentials {
+    /// Reads the credentials for one upstream from `{PREFIX}_BEARER_TOKEN`,
+    /// `{PREFIX}_BASIC_AUTH` (`username:password`) and, for mTLS,
+    /// `{PREFIX}_TLS_CERT`, `{PREFIX}_TLS_KEY` and optionally `{PREFIX}_TLS_CA`.
+    pub fn from_env(prefix: &str) -> Result<Self, UpstreamConfigError> {
+        let var = |name: &str| std::env::var(format!("{}_{}", prefix, name)).ok();
+
+        let auth = if let Some(token) = var("BEARER_TOKEN") {
+            Some(UpstreamAuth::Bearer(token))
+        } else if let Some(basic) = var("BASIC_AUTH") {
+            let (username, password) =
+                basic
+                    .split_once(':')
+                    .ok_or_else(|| UpstreamConfigError::InvalidBasicAuth {
+                        var: format!("{}_BASIC_AUTH", prefix),
+                    })?;
+            Some(UpstreamAuth::Basic {
+                username: username.to_string(),
+                password: password.to_string(),
+            })
+        } else {
+            None
+        };
+
+        let tls = match (var("TLS_CERT"), var("TLS_KEY")) {
+            (Some(cert_path), Some(key_path)) => Some(TlsIdentity {
+                cert_path,
+                key_path,
+                ca_path: var("TLS_CA"),
+            }),
+            (Some(_), None) => {
+                return Err(UpstreamConfigError::IncompleteTlsIdentity {
+                    var: format!("{}_TLS_CERT", prefix),
+                    missing: format!("{}_TLS_KEY", prefix),
+                })
+            }
+            (None, Some(_)) => {
+                return Err(UpstreamConfigError::IncompleteTlsIdentity {
+                    var: format!("{}_TLS_KEY", prefix),
+                    missing: format!("{}_TLS_CERT", prefix),
+                })
+            }
+            (None, None) => None,
+        };
+
+        Ok(Self { auth, tls })
+    }
+
+    /// Upstreams with a TLS identity are reached over HTTPS
+    pub fn scheme(&self) -> &'static str {
+        if self.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        }
+    }
+}
+
+/// Everything needed to talk to one upstream service: its credentials,
+/// a pooled client configured with its TLS identity, its call metrics, the
+/// request signer shared with the services, and whether it asked the
+/// gateway to back off.
+#[derive(Debug, Clone)]
+pub struct UpstreamConnection {
+    pub host: String,
+    pub port: u16,
+    pub credentials: UpstreamCredentials,
+    pub client: UpstreamClient,
+    pub metrics: Arc<UpstreamMetrics>,
+    pub signer: Option<Arc<RequestSigner>>,
+    /// Set when the service sheds load; no requests are sent until then
+    busy_until: Arc<Mutex<Option<Instant>>>,
+    /// Set while an admin takes this instance out of rotation
+    draining: Arc<AtomicBool>,
+    /// Set while this instance fails its health probes
+    down: Arc<AtomicBool>,
+}
+
+impl UpstreamConnection {
+    pub fn new(
+        host: impl Into<String>,
+        port: u16,
+        credentials: UpstreamCredentials,
+    ) -> Result<Self, UpstreamConfigError> {
+        let metrics = Arc::new(UpstreamMetrics::new());
+        let client = build_client(credentials.tls.as_ref(), Arc::clone(&metrics))?;
+        Ok(Self {
+            host: host.into(),
+            port,
+            credentials,
+            client,
+            metrics,
+            signer: None,
+            busy_until: Arc::new(Mutex::new(None)),
+            draining: Arc::new(AtomicBool::new(false)),
+            down: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Another instance of the same service: same credentials, pooled
+    /// client, metrics and signer, but its own back-off, draining and
+    /// health
+    pub fn instance(&self, host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            busy_until: Arc::new(Mutex::new(None)),
+            draining: Arc::new(AtomicBool::new(false)),
+            down: Arc::new(AtomicBool::new(false)),
+            ..self.clone()
+        }
+    }
+
+    /// A draining instance gets no new requests while another one can take
+    /// them, and requests it still gets ask it to close the connection
+    /// afterwards, so none stays pooled
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::Relaxed);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Records the outcome of this instance's last health probe
+    pub fn set_down(&self, down: bool) {
+        self.down.store(down, Ordering::Relaxed);
+    }
+
+    /// Whether this instance failed its last health probe
+    pub fn is_down(&self) -> bool {
+        self.down.load(Ordering::Relaxed)
+    }
+
+    /// Signs every request sent through [`UpstreamConnection::sign`]
+    pub fn with_signer(mut self, signer: Option<Arc<RequestSigner>>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    pub fn base_url(&self) -> String {
+        format!(
+            "{}://{}:{}",
+            self.credentials.scheme(),
+            self.host,
+            self.port
+        )
+    }
+
+    /// The gateway's own credentials for this service. Only its health
+    /// probes are authorized by them alone; calls made for a client go
+    /// through [`UpstreamConnection::authorize_client`].
+    fn authorization(&self) -> Option<String> {
+        self.credentials
+            .auth
+            .as_ref()
+            .map(UpstreamAuth::header_value)
+    }
+
+    /// Sets the credentials of a call made for a client: the gateway's own
+    /// `Authorization` when it authenticates to this service, the client's
+    /// otherwise, and the client's as `X-Client-Authorization` either way,
+    /// empty when it has none. Services authorize the call by the latter.
+    pub fn authorize_client(&self, builder: Builder, client: Option<&HeaderValue>) -> Builder {
+        let builder = match (self.authorization(), client) {
+            (Some(own), _) => builder.header(AUTHORIZATION, own),
+            (None, Some(client)) => builder.header(AUTHORIZATION, client),
+            (None, None) => builder,
+        };
+        builder.header(
+            CLIENT_AUTHORIZATION_HEADER,
+            client
+                .cloned()
+                .unwrap_or_else(|| HeaderValue::from_static("")),
+        )
+    }
+
+    /// Adds the gateway signature headers for a request to `path_and_query`
+    /// with `body`, covering the headers `builder` already has; unchanged
+    /// when signing is not configured
+    pub fn sign(
+        &self,
+        builder: Builder,
+        method: &str,
+        path_and_query: &str,
+        body: &[u8],
+    ) -> Builder {
+        let Some(signer) = &self.signer else {
+            return builder;
+        };
+        let empty = HeaderMap::new();
+        let headers = builder.headers_ref().unwrap_or(&empty);
+        let signed = signer.sign(method, path_and_query, headers, body);
+        builder
+            .header(SIGNATURE_TIMESTAMP_HEADER, signed.timestamp)
+            .header(SIGNATURE_NONCE_HEADER, signed.nonce)
+            .header(SIGNATURE_HEADER, signed.signature)
+    }
+
+    /// Backs off when `response` is the service shedding load, for its
+    /// `Retry-After` (default 1s). Returns whether it was.
+    pub fn note_busy<B>(&self, response: &Response<B>) -> bool {
+        if !response.headers().contains_key(SERVICE_BUSY_HEADER) {
+            return false;
+        }
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map_or(Duration::from_secs(1), Duration::from_secs);
+        *self
+            .busy_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now() + retry_after);
+        true
+    }
+
+    /// Calls the service's `health` method once. A service shedding load
+    /// counts as alive; one still starting answers with a JSON-RPC error.
+    pub async fn probe_health(&self, limit: Duration) -> Result<(), ProbeError> {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri(self.base_url())
+            .header("Content-Type", "application/json");
+        if let Some(authorization) = self.authorization() {
+            builder = builder.header("Authorization", authorization);
+        }
+        let body = r#"{"jsonrpc":"2.0","method":"health","id":0}"#;
+        let request = self
+            .sign(builder, "POST", "/", body.as_bytes())
+            .body(Full::new(Bytes::from(body)))
+            .unwrap();
+
+        let probe = async {
+            let response = self.client.request(request).await?;
+            if self.note_busy(&response) {
+                return Ok(());
+            }
+            if !response.status().is_success() {
+                return Err(ProbeError::Status(response.status()));
+            }
+            let body = response.collect().await?.to_bytes();
+            let payload: serde_json::Value =
+                serde_json::from_slice(&body).map_err(|_| ProbeError::InvalidBody)?;
+            match payload.get("error") {
+                Some(error) => Err(ProbeError::Rpc(error.clone())),
+                None => Ok(()),
+            }
+        };
+        timeout(limit, probe)
+            .await
+            .unwrap_or(Err(ProbeError::Timeout(limit)))
+    }
+
+    /// How much longer the service asked the gateway to back off, if at all
+    pub fn busy_remaining(&self) -> Option<Duration> {
+        let busy_until = *self
+            .busy_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        busy_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+fn build_client(
+    tls: Option<&TlsIdentity>,
+    metrics: Arc<UpstreamMetrics>,
+) -> Result<UpstreamClient, UpstreamConfigError> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?;
+
+    let tls_config = match tls {
+        Some(identity) => {
+            let mut roots = rustls::RootCertStore::empty();
+            if let Some(ca_path) = &identity.ca_path {
+                for cert in read_certs(ca_path)? {
+                    roots.add(cert)?;
+                }
+            }
+            builder
+                .with_root_certificates(roots)
+                .with_client_auth_cert(
+                    read_certs(&identity.cert_path)?,
+                    read_private_key(&identity.key_path)?,
+                )?
+        }
+        // Plain HTTP upstream; the TLS side of the connector is never used
+        None => builder
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth(),
+    };
+
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(tls_config)
+        .https_or_http()
+        .enable_http1()
+        .build();
+
+    Ok(Client::builder(TokioExecutor::new()).build(TimedConnector::new(connector, metrics)))
+}
+
+fn open(path: &str) -> Result<BufReader<File>, UpstreamConfigError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|source| UpstreamConfigError::Io {
+            path: path.to_string(),
+            source,
+        })
+}
+
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, UpstreamConfigError> {
+    rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|source| UpstreamConfigError::Io {
+            path: path.to_string(),
+            source,
+        })
+}
+
+fn read_private_key(path: &str) -> Result<PrivateKeyDer<'static>, UpstreamConfigError> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|source| UpstreamConfigError::Io {
+            path: path.to_string(),
+            source,
+        })?
+        .ok_or_else(|| UpstreamConfigError::MissingPrivateKey {
+            path: path.to_string(),
+        })
+}
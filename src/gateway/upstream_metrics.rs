@@ -0,0 +1,189 @@
+use crate::telemetry::latency_histogram::{LatencyHistogram, LatencySnapshot};
+use hyper::{StatusCode, Uri};
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::Service;
+
+/// Why an attempt to call an upstream did not produce a usable response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamFailure {
+    /// No connection could be established; the request was never sent
+    Connect,
+    /// No response headers within the gateway's deadline
+    Timeout,
+    /// The connection failed after the request was sent, e.g. a reset
+    Transport,
+    /// The upstream answered 4xx
+    ClientError,
+    /// The upstream answered 5xx
+    ServerError,
+    /// The response headers arrived but reading the body failed
+    BodyRead,
+}
+
+impl UpstreamFailure {
+    /// `ClientError` or `ServerError` for error statuses, `None` otherwise
+    pub fn from_status(status: StatusCode) -> Option<Self> {
+        if status.is_server_error() {
+            Some(UpstreamFailure::ServerError)
+        } else if status.is_client_error() {
+            Some(UpstreamFailure::ClientError)
+        } else {
+            None
+        }
+    }
+}
+
+/// Outbound call counters and latencies for one upstream.
+///
+/// Every proxied attempt, including retries, is counted once: as `ok` or
+/// under the cause of its failure. A response whose body cannot be read is
+/// counted under its status and again under `body_read`. Connect latency
+/// covers every new pooled connection, health checks included; TTFB is the
+/// time from sending a proxied request to receiving its response headers.
+#[derive(Debug, Default)]
+pub struct UpstreamMetrics {
+    attempts: AtomicU64,
+    ok: AtomicU64,
+    connect_errors: AtomicU64,
+    timeouts: AtomicU64,
+    transport_errors: AtomicU64,
+    client_errors: AtomicU64,
+    server_errors: AtomicU64,
+    body_read_errors: AtomicU64,
+    connections_opened: AtomicU64,
+    connect_latency: LatencyHistogram,
+    ttfb: LatencyHistogram,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamFailureCounts {
+    pub connect: u64,
+    pub timeout: u64,
+    pub transport: u64,
+    #[serde(rename = "4xx")]
+    pub client_error: u64,
+    #[serde(rename = "5xx")]
+    pub server_error: u64,
+    pub body_read: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamMetricsSnapshot {
+    pub attempts: u64,
+    pub ok: u64,
+    pub failures: UpstreamFailureCounts,
+    pub connections_opened: u64,
+    pub connect_ms: LatencySnapshot,
+    pub ttfb_ms: LatencySnapshot,
+}
+
+impl UpstreamMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the response headers of an attempt, classifying 4xx and 5xx
+    pub fn record_response(&self, status: StatusCode, ttfb: Duration) {
+        self.ttfb.record(ttfb);
+        match UpstreamFailure::from_status(status) {
+            Some(failure) => self.record_failure(failure),
+            None => {
+                self.attempts.fetch_add(1, Ordering::Relaxed);
+                self.ok.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn record_failure(&self, failure: UpstreamFailure) {
+        let counter = match failure {
+            UpstreamFailure::Connect => &self.connect_errors,
+            UpstreamFailure::Timeout => &self.timeouts,
+            UpstreamFailure::Transport => &self.transport_errors,
+            UpstreamFailure::ClientError => &self.client_errors,
+            UpstreamFailure::ServerError => &self.server_errors,
+            // Follows a response that was already counted as an attempt
+            UpstreamFailure::BodyRead => {
+                self.body_read_errors.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        };
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_connect(&self, elapsed: Duration) {
+        self.connections_opened.fetch_add(1, Ordering::Relaxed);
+        self.connect_latency.record(elapsed);
+    }
+
+    /// Time to first byte of the responses so far
+    pub fn ttfb_snapshot(&self) -> LatencySnapshot {
+        self.ttfb.snapshot()
+    }
+
+    pub fn snapshot(&self) -> UpstreamMetricsSnapshot {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        UpstreamMetricsSnapshot {
+            attempts: load(&self.attempts),
+            ok: load(&self.ok),
+            failures: UpstreamFailureCounts {
+                connect: load(&self.connect_errors),
+                timeout: load(&self.timeouts),
+                transport: load(&self.transport_errors),
+                client_error: load(&self.client_errors),
+                server_error: load(&self.server_errors),
+                body_read: load(&self.body_read_errors),
+            },
+            connections_opened: load(&self.connections_opened),
+            connect_ms: self.connect_latency.snapshot(),
+            ttfb_ms: self.ttfb.snapshot(),
+        }
+    }
+}
+
+/// Connector wrapper that times each new upstream connection, including
+/// the TLS handshake for HTTPS upstreams
+#[derive(Debug, Clone)]
+pub struct TimedConnector<C> {
+    inner: C,
+    metrics: Arc<UpstreamMetrics>,
+}
+
+impl<C> TimedConnector<C> {
+    pub fn new(inner: C, metrics: Arc<UpstreamMetrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+impl<C> Service<Uri> for TimedConnector<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let started = Instant::now();
+        let metrics = Arc::clone(&self.metrics);
+        let connecting = self.inner.call(uri);
+        Box::pin(async move {
+            let connection = connecting.await;
+            if connection.is_ok() {
+                metrics.record_connect(started.elapsed());
+            }
+            connection
+        })
+    }
+}
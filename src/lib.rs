@@ -1,4 +1,20 @@
+//! User and product JSON-RPC services, their gateway, and the pieces they
+//! are built from.
+//!
+//! Each layer is one module tree: `models` for the records and request
+//! types, `errors`, `repositories` for SurrealDB access, `services` for the
+//! business logic, `clients` for typed calls between services, and
+//! `middleware`, `gateway`, `telemetry`, `crypto` and `config` for the
+//! plumbing around them. [`prelude`] re-exports the types most users need.
+
 pub mod models;
 pub mod errors;
 pub mod repositories;
 pub mod services;
+pub mod gateway;
+pub mod telemetry;
+pub mod middleware;
+pub mod crypto;
+pub mod config;
+pub mod clients;
+pub mod prelude;
@@ -0,0 +1,253 @@
+use hyper::header::{HeaderMap, HeaderValue};
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::server::MethodResponse;
+use jsonrpsee::types::{ErrorObject, Request};
+use jsonrpsee::ResponsePayload;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use surrealdb::sql::Thing;
+use thiserror::Error;
+use tower::{Layer, Service};
+
+/// Request header selecting the response shape, e.g. `Accept-Version: 2`
+pub const ACCEPT_VERSION_HEADER: &str = "accept-version";
+/// Query parameter alternative to [`ACCEPT_VERSION_HEADER`], e.g.
+/// `?api_version=2`; the header wins when both are sent
+pub const API_VERSION_PARAM: &str = "api_version";
+/// Response header naming the shape the results were serialized in
+pub const API_VERSION_HEADER: &str = "api-version";
+
+/// JSON-RPC error code for calls asking for a version the service does not
+/// serve
+pub const UNSUPPORTED_API_VERSION_CODE: i32 = -32013;
+
+#[derive(Error, Debug, Clone)]
+#[error("Unsupported API version '{0}', expected 1 or 2")]
+pub struct UnsupportedApiVersion(pub String);
+
+/// Major version of the response shapes.
+///
+/// Handlers always build their results in the current model types; each
+/// version's serializer reshapes them on the way out, so a breaking model
+/// change ships as a new version while clients that have not migrated keep
+/// the shape they were written against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    /// Record ids as SurrealDB objects: `{"tb": "user", "id": {"String": "..."}}`
+    V1 = 1,
+    /// Record ids as `table:key` strings, as accepted by the `id` params
+    V2 = 2,
+}
+
+impl ApiVersion {
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+
+    pub fn number(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Reads `API_DEFAULT_VERSION`, the shape served to clients that do
+    /// not ask for one (default 1, so existing clients keep working)
+    pub fn default_from_env() -> Result<Self, UnsupportedApiVersion> {
+        match std::env::var("API_DEFAULT_VERSION") {
+            Ok(value) => value.parse(),
+            Err(_) => Ok(ApiVersion::V1),
+        }
+    }
+
+    /// Reshapes a result built from the current models for this version
+    pub fn serialize_result(&self, result: Value) -> Value {
+        match self {
+            ApiVersion::V1 => result,
+            ApiVersion::V2 => record_ids_as_strings(result),
+        }
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = UnsupportedApiVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        match trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed) {
+            "1" => Ok(ApiVersion::V1),
+            "2" => Ok(ApiVersion::V2),
+            _ => Err(UnsupportedApiVersion(s.to_string())),
+        }
+    }
+}
+
+/// The version a client asked for through [`ACCEPT_VERSION_HEADER`] or the
+/// [`API_VERSION_PARAM`] query parameter, unparsed
+pub fn requested_version(headers: &HeaderMap, query: Option<&str>) -> Option<String> {
+    if let Some(value) = headers.get(ACCEPT_VERSION_HEADER) {
+        return value.to_str().ok().map(str::to_string);
+    }
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == API_VERSION_PARAM)
+        .map(|(_, value)| value.to_string())
+}
+
+fn record_ids_as_strings(value: Value) -> Value {
+    match value {
+        Value::Object(map)
+            if map.len() == 2 && map.contains_key("tb") && map.contains_key("id") =>
+        {
+            let object = Value::Object(map);
+            match serde_json::from_value::<Thing>(object.clone()) {
+                Ok(thing) => Value::String(thing.to_string()),
+                Err(_) => object,
+            }
+        }
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, record_ids_as_strings(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(record_ids_as_strings).collect()),
+        other => other,
+    }
+}
+
+/// The outcome of version negotiation, carried to the RPC middleware
+/// through the request extensions
+#[derive(Debug, Clone)]
+pub struct NegotiatedVersion(pub Result<ApiVersion, UnsupportedApiVersion>);
+
+/// HTTP layer that negotiates the response version and reports it in the
+/// `Api-Version` response header
+#[derive(Debug, Clone)]
+pub struct ApiVersionHeaderLayer {
+    default: ApiVersion,
+}
+
+impl ApiVersionHeaderLayer {
+    pub fn new(default: ApiVersion) -> Self {
+        Self { default }
+    }
+}
+
+impl<S> Layer<S> for ApiVersionHeaderLayer {
+    type Service = ApiVersionHeaderService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiVersionHeaderService {
+            inner,
+            default: self.default,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiVersionHeaderService<S> {
+    inner: S,
+    default: ApiVersion,
+}
+
+impl<S, B, RB> Service<hyper::Request<B>> for ApiVersionHeaderService<S>
+where
+    S: Service<hyper::Request<B>, Response = hyper::Response<RB>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: hyper::Request<B>) -> Self::Future {
+        let negotiated = match requested_version(request.headers(), request.uri().query()) {
+            Some(requested) => requested.parse(),
+            None => Ok(self.default),
+        };
+        let served = negotiated.as_ref().ok().copied();
+        request
+            .extensions_mut()
+            .insert(NegotiatedVersion(negotiated));
+
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = response.await?;
+            if let Some(version) = served {
+                response.headers_mut().insert(
+                    API_VERSION_HEADER,
+                    HeaderValue::from(u16::from(version.number())),
+                );
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Layer installing [`VersionedResponses`] in the JSON-RPC middleware stack
+#[derive(Debug, Clone, Default)]
+pub struct ApiVersionLayer;
+
+impl<S> Layer<S> for ApiVersionLayer {
+    type Service = VersionedResponses<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        VersionedResponses { service }
+    }
+}
+
+/// JSON-RPC middleware that serializes each successful result in the
+/// negotiated version and rejects calls asking for an unknown one with
+/// [`UNSUPPORTED_API_VERSION_CODE`]. Errors are never reshaped.
+#[derive(Debug, Clone)]
+pub struct VersionedResponses<S> {
+    service: S,
+}
+
+impl<'a, S> RpcServiceT<'a> for VersionedResponses<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+    S::Future: 'a,
+{
+    type Future = Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let version = match request.extensions().get::<NegotiatedVersion>() {
+            Some(NegotiatedVersion(Ok(version))) => *version,
+            Some(NegotiatedVersion(Err(unsupported))) => {
+                let error = ErrorObject::owned(
+                    UNSUPPORTED_API_VERSION_CODE,
+                    "Unsupported API version",
+                    Some(json!({ "requested": unsupported.0, "supported": [1, 2] })),
+                );
+                return Box::pin(std::future::ready(MethodResponse::error(
+                    request.id(),
+                    error,
+                )));
+            }
+            None => ApiVersion::V1,
+        };
+        if version == ApiVersion::V1 {
+            return Box::pin(self.service.call(request));
+        }
+
+        let id = request.id().into_owned();
+        let call = self.service.call(request);
+        Box::pin(async move {
+            let response = call.await;
+            if !response.is_success() || !response.is_method_call() {
+                return response;
+            }
+            let Ok(mut envelope) = serde_json::from_str::<Value>(response.as_result()) else {
+                return response;
+            };
+
+            let result = version.serialize_result(envelope["result"].take());
+            let extensions = response.extensions().clone();
+            MethodResponse::response(id, ResponsePayload::success(result), usize::MAX)
+                .with_extensions(extensions)
+        })
+    }
+}
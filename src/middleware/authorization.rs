@@ -0,0 +1,436 @@
+use crate::services::method_namespaces::flat_method_name;
+use hyper::header::AUTHORIZATION;
+use jsonrpsee::server::middleware::rpc::{ResponseFuture, RpcServiceT};
+use jsonrpsee::server::MethodResponse;
+use jsonrpsee::types::{ErrorObject, ErrorObjectOwned, Request};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tower::{Layer, Service};
+use tracing::warn;
+
+/// The client's own `Authorization` as the gateway received it, set on
+/// every proxied request. The gateway may replace `Authorization` with its
+/// upstream credentials, so services authorize the client by this header
+/// when it is present, and an empty value means the client sent none.
+pub const CLIENT_AUTHORIZATION_HEADER: &str = "x-client-authorization";
+
+/// JSON-RPC error code for calls without valid credentials
+pub const UNAUTHENTICATED_CODE: i32 = -32001;
+/// JSON-RPC error code for authenticated callers lacking a role or scope
+pub const FORBIDDEN_CODE: i32 = -32003;
+
+/// Role that may act on any user's behalf
+pub const ADMIN_ROLE: &str = "admin";
+/// Role of the internal callers, such as checkout, that report on users'
+/// behalf
+pub const SERVICE_ROLE: &str = "service";
+
+#[derive(Error, Debug)]
+pub enum PolicyError {
+    #[error("Failed to read policy file {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid policy file {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+}
+
+/// Requirements for calling one method. The caller needs at least one of
+/// `roles` (if any are listed) and every one of `scopes`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MethodPolicy {
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// A caller known to the policy, identified by its bearer token
+#[derive(Debug, Clone, Deserialize)]
+pub struct Principal {
+    pub name: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// The user this principal acts as in self-service calls
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+/// Declarative mapping of JSON-RPC methods to required roles and scopes.
+///
+/// Methods missing from `methods` are open to everyone unless
+/// `deny_unlisted` is set. The default policy allows every call.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthorizationPolicy {
+    /// Bearer token -> principal
+    #[serde(default)]
+    pub principals: HashMap<String, Principal>,
+    #[serde(default)]
+    pub methods: HashMap<String, MethodPolicy>,
+    #[serde(default)]
+    pub deny_unlisted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denial {
+    Unauthenticated,
+    MissingRole { principal: String },
+    MissingScope { principal: String, scope: String },
+    Unlisted { principal: Option<String> },
+    NotOwner { principal: String },
+}
+
+impl Denial {
+    pub fn into_error_object(self, method: &str) -> ErrorObjectOwned {
+        match self {
+            Denial::Unauthenticated => ErrorObject::owned(
+                UNAUTHENTICATED_CODE,
+                "Unauthenticated",
+                Some(json!({ "method": method })),
+            ),
+            Denial::MissingRole { .. } => ErrorObject::owned(
+                FORBIDDEN_CODE,
+                "Forbidden",
+                Some(json!({ "method": method, "reason": "missing required role" })),
+            ),
+            Denial::MissingScope { scope, .. } => ErrorObject::owned(
+                FORBIDDEN_CODE,
+                "Forbidden",
+                Some(
+                    json!({ "method": method, "reason": "missing required scope", "scope": scope }),
+                ),
+            ),
+            Denial::Unlisted { .. } => ErrorObject::owned(
+                FORBIDDEN_CODE,
+                "Forbidden",
+                Some(json!({ "method": method, "reason": "method not permitted by policy" })),
+            ),
+            Denial::NotOwner { .. } => ErrorObject::owned(
+                FORBIDDEN_CODE,
+                "Forbidden",
+                Some(json!({ "method": method, "reason": "not the caller's own record" })),
+            ),
+        }
+    }
+}
+
+impl AuthorizationPolicy {
+    /// Loads the policy from the file named by `AUTH_POLICY_FILE`, or returns
+    /// the allow-all default when the variable is unset.
+    pub fn from_env() -> Result<Self, PolicyError> {
+        match std::env::var("AUTH_POLICY_FILE") {
+            Ok(path) => Self::from_file(path),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, PolicyError> {
+        let path_str = path.as_ref().display().to_string();
+        let contents = std::fs::read_to_string(&path).map_err(|source| PolicyError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+        let mut policy: Self =
+            serde_json::from_str(&contents).map_err(|source| PolicyError::Parse {
+                path: path_str,
+                source,
+            })?;
+
+        // Key methods by their flat name so namespaced aliases share a policy
+        policy.methods = policy
+            .methods
+            .into_iter()
+            .map(|(method, required)| (flat_method_name(&method).to_string(), required))
+            .collect();
+        Ok(policy)
+    }
+
+    /// Requires `required` for `method` unless the policy file lists it,
+    /// for methods that must not be open under the allow-all default
+    pub fn with_default(mut self, method: &str, required: MethodPolicy) -> Self {
+        self.methods
+            .entry(flat_method_name(method).to_string())
+            .or_insert(required);
+        self
+    }
+
+    /// [`AuthorizationPolicy::with_default`] requiring the `admin` role for
+    /// each of `methods`
+    pub fn with_admin_defaults(self, methods: &[&str]) -> Self {
+        let admins = MethodPolicy {
+            roles: vec![ADMIN_ROLE.to_string()],
+            scopes: Vec::new(),
+        };
+        methods.iter().fold(self, |policy, method| {
+            policy.with_default(method, admins.clone())
+        })
+    }
+
+    pub fn is_enforcing(&self) -> bool {
+        self.deny_unlisted || !self.methods.is_empty()
+    }
+
+    /// Decides whether the caller presenting `token` may call `method`,
+    /// under either its flat or namespaced name
+    pub fn authorize(&self, method: &str, token: Option<&str>) -> Result<(), Denial> {
+        let principal = token.and_then(|t| self.principals.get(t));
+
+        let Some(required) = self.methods.get(flat_method_name(method)) else {
+            return if self.deny_unlisted {
+                Err(Denial::Unlisted {
+                    principal: principal.map(|p| p.name.clone()),
+                })
+            } else {
+                Ok(())
+            };
+        };
+
+        let principal = principal.ok_or(Denial::Unauthenticated)?;
+
+        if !required.roles.is_empty() && !required.roles.iter().any(|r| principal.roles.contains(r))
+        {
+            return Err(Denial::MissingRole {
+                principal: principal.name.clone(),
+            });
+        }
+
+        if let Some(scope) = required
+            .scopes
+            .iter()
+            .find(|s| !principal.scopes.contains(s))
+        {
+            return Err(Denial::MissingScope {
+                principal: principal.name.clone(),
+                scope: scope.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Decides whether the caller presenting `token` may act on `user_id`'s
+    /// own record: admins may act on anyone's, other principals only on the
+    /// user they are bound to through `user_id`
+    pub fn authorize_owner(&self, token: Option<&str>, user_id: &str) -> Result<(), Denial> {
+        let principal = token
+            .and_then(|t| self.principals.get(t))
+            .ok_or(Denial::Unauthenticated)?;
+
+        if principal.roles.iter().any(|r| r == ADMIN_ROLE)
+            || principal.user_id.as_deref() == Some(user_id)
+        {
+            Ok(())
+        } else {
+            Err(Denial::NotOwner {
+                principal: principal.name.clone(),
+            })
+        }
+    }
+}
+
+/// Bearer token from the HTTP `Authorization` header, carried to the RPC
+/// middleware through the request extensions
+#[derive(Debug, Clone)]
+pub struct BearerToken(pub String);
+
+/// HTTP layer that extracts the bearer token before the JSON-RPC layer
+/// runs: the client's, from [`CLIENT_AUTHORIZATION_HEADER`], on requests
+/// through the gateway, and `Authorization` on direct calls
+#[derive(Debug, Clone, Default)]
+pub struct BearerTokenLayer;
+
+impl<S> Layer<S> for BearerTokenLayer {
+    type Service = BearerTokenService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BearerTokenService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BearerTokenService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<hyper::Request<B>> for BearerTokenService<S>
+where
+    S: Service<hyper::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: hyper::Request<B>) -> Self::Future {
+        let headers = request.headers();
+        let token = headers
+            .get(CLIENT_AUTHORIZATION_HEADER)
+            .or_else(|| headers.get(AUTHORIZATION))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string());
+        if let Some(token) = token {
+            request.extensions_mut().insert(BearerToken(token));
+        }
+
+        self.inner.call(request)
+    }
+}
+
+/// Layer installing [`Authorization`] in the JSON-RPC middleware stack
+#[derive(Debug, Clone)]
+pub struct AuthorizationLayer {
+    policy: Arc<AuthorizationPolicy>,
+}
+
+impl AuthorizationLayer {
+    pub fn new(policy: Arc<AuthorizationPolicy>) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> Layer<S> for AuthorizationLayer {
+    type Service = Authorization<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Authorization::new(service, self.policy.clone())
+    }
+}
+
+/// JSON-RPC middleware enforcing an [`AuthorizationPolicy`] before any
+/// handler runs, so protected methods need no checks of their own.
+#[derive(Debug, Clone)]
+pub struct Authorization<S> {
+    service: S,
+    policy: Arc<AuthorizationPolicy>,
+}
+
+impl<S> Authorization<S> {
+    pub fn new(service: S, policy: Arc<AuthorizationPolicy>) -> Self {
+        Self { service, policy }
+    }
+}
+
+impl<'a, S> RpcServiceT<'a> for Authorization<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+{
+    type Future = ResponseFuture<S::Future>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let token = request
+            .extensions()
+            .get::<BearerToken>()
+            .map(|t| t.0.as_str());
+
+        match self.policy.authorize(request.method_name(), token) {
+            Ok(()) => ResponseFuture::future(self.service.call(request)),
+            Err(denial) => {
+                warn!("🔒 Denied {}: {:?}", request.method_name(), denial);
+                let error = denial.into_error_object(request.method_name());
+                ResponseFuture::ready(MethodResponse::error(request.id(), error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> AuthorizationPolicy {
+        serde_json::from_value(json!({
+            "principals": {
+                "admin-token": { "name": "ops", "roles": ["admin"] },
+                "alice-token": { "name": "alice", "user_id": "alice" },
+                "service-token": { "name": "gateway", "roles": ["service"] }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn owners_and_admins_act_on_a_user() {
+        let policy = policy();
+        let cases = [
+            (Some("alice-token"), "alice", Ok(())),
+            (Some("admin-token"), "alice", Ok(())),
+            (
+                Some("alice-token"),
+                "bob",
+                Err(Denial::NotOwner {
+                    principal: "alice".to_string(),
+                }),
+            ),
+            (
+                Some("service-token"),
+                "alice",
+                Err(Denial::NotOwner {
+                    principal: "gateway".to_string(),
+                }),
+            ),
+            (Some("unknown-token"), "alice", Err(Denial::Unauthenticated)),
+            (None, "alice", Err(Denial::Unauthenticated)),
+        ];
+
+        for (token, user_id, expected) in cases {
+            assert_eq!(
+                policy.authorize_owner(token, user_id),
+                expected,
+                "{token:?} acting on {user_id}"
+            );
+        }
+    }
+
+    #[test]
+    fn defaults_apply_unless_the_file_lists_the_method() {
+        let admins_only = MethodPolicy {
+            roles: vec![ADMIN_ROLE.to_string()],
+            scopes: Vec::new(),
+        };
+        let defaulted = policy().with_default("user.name.history", admins_only.clone());
+        assert_eq!(
+            defaulted.authorize("get_name_history", Some("admin-token")),
+            Ok(())
+        );
+        assert_eq!(
+            defaulted.authorize("user.name.history", Some("alice-token")),
+            Err(Denial::MissingRole {
+                principal: "alice".to_string()
+            })
+        );
+        assert_eq!(
+            defaulted.authorize("get_name_history", None),
+            Err(Denial::Unauthenticated)
+        );
+
+        let mut listed = policy();
+        listed.methods.insert(
+            "get_name_history".to_string(),
+            MethodPolicy {
+                roles: vec!["service".to_string()],
+                scopes: Vec::new(),
+            },
+        );
+        let listed = listed.with_default("get_name_history", admins_only);
+        assert_eq!(
+            listed.authorize("get_name_history", Some("service-token")),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use crate::services::avatar_storage::{LocalAvatarStorage, AVATAR_KEY_PREFIX};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Limited};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Method, StatusCode};
+use jsonrpsee::core::BoxError;
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::{info, warn};
+
+/// HTTP layer serving the local avatar store.
+///
+/// With `AVATAR_STORAGE=local` the presigned upload URLs point back at the
+/// user service: `PUT /avatars/...` stores the body when the URL's signature
+/// is valid and unexpired, and `GET /avatars/...` serves stored images.
+/// Everything else, and every request when another backend is configured,
+/// goes to the JSON-RPC server.
+#[derive(Clone, Default)]
+pub struct AvatarUploadLayer {
+    storage: Option<Arc<LocalAvatarStorage>>,
+}
+
+impl AvatarUploadLayer {
+    pub fn new(storage: Option<Arc<LocalAvatarStorage>>) -> Self {
+        Self { storage }
+    }
+}
+
+impl<S> Layer<S> for AvatarUploadLayer {
+    type Service = AvatarUploadService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AvatarUploadService {
+            inner,
+            storage: self.storage.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AvatarUploadService<S> {
+    inner: S,
+    storage: Option<Arc<LocalAvatarStorage>>,
+}
+
+impl<S, B> Service<HttpRequest> for AvatarUploadService<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse<B>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    B: hyper::body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        let storage = self.storage.clone().filter(|_| {
+            request
+                .uri()
+                .path()
+                .trim_start_matches('/')
+                .starts_with(AVATAR_KEY_PREFIX)
+        });
+        let Some(storage) = storage else {
+            let response = self.inner.call(request);
+            return Box::pin(async move { Ok(response.await?.map(HttpBody::new)) });
+        };
+
+        Box::pin(async move {
+            let key = request.uri().path().trim_start_matches('/').to_string();
+            let Ok(path) = storage.path_for(&key) else {
+                return plain(StatusCode::NOT_FOUND, "Not found");
+            };
+
+            match *request.method() {
+                Method::PUT => {
+                    let query = request.uri().query().unwrap_or_default();
+                    let param = |name: &str| {
+                        query
+                            .split('&')
+                            .filter_map(|pair| pair.split_once('='))
+                            .find(|(param, _)| *param == name)
+                            .map(|(_, value)| value.to_string())
+                    };
+                    let expires = param("expires").and_then(|v| v.parse().ok());
+                    let signature = param("signature");
+                    let (Some(expires), Some(signature)) = (expires, signature) else {
+                        return plain(StatusCode::FORBIDDEN, "Missing upload signature");
+                    };
+                    if !storage.verify_upload(&key, expires, &signature) {
+                        warn!(
+                            "🖼️ Rejected avatar upload to {}: bad or expired signature",
+                            key
+                        );
+                        return plain(StatusCode::FORBIDDEN, "Invalid or expired upload URL");
+                    }
+
+                    let body = Limited::new(request.into_body(), storage.max_bytes());
+                    let body = match body.collect().await {
+                        Ok(body) => body.to_bytes(),
+                        Err(_) => {
+                            return plain(
+                                StatusCode::PAYLOAD_TOO_LARGE,
+                                &format!("Avatars are limited to {} bytes", storage.max_bytes()),
+                            )
+                        }
+                    };
+                    if body.is_empty() {
+                        return plain(StatusCode::BAD_REQUEST, "Empty upload");
+                    }
+
+                    if let Some(dir) = path.parent() {
+                        tokio::fs::create_dir_all(dir).await?;
+                    }
+                    tokio::fs::write(&path, &body).await?;
+                    info!("🖼️ Stored avatar {} ({} bytes)", key, body.len());
+                    Ok(HttpResponse::builder()
+                        .status(StatusCode::OK)
+                        .body(HttpBody::empty())?)
+                }
+                Method::GET => match tokio::fs::read(&path).await {
+                    Ok(image) => Ok(HttpResponse::builder()
+                        .status(StatusCode::OK)
+                        .header(CONTENT_TYPE, content_type_for(&path))
+                        .body(HttpBody::from(image))?),
+                    Err(_) => plain(StatusCode::NOT_FOUND, "Not found"),
+                },
+                _ => plain(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"),
+            }
+        })
+    }
+}
+
+fn plain(status: StatusCode, message: &str) -> Result<HttpResponse, BoxError> {
+    Ok(HttpResponse::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain")
+        .body(HttpBody::from(message.to_string()))?)
+}
+
+fn content_type_for(path: &Path) -> HeaderValue {
+    let content_type = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => "image/png",
+        Some("jpg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        _ => "application/octet-stream",
+    };
+    HeaderValue::from_static(content_type)
+}
@@ -0,0 +1,60 @@
+use std::net::IpAddr;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Request header carrying the original caller's address, set by the
+/// gateway on every proxied request
+pub const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The address of the client a request was made for, available to handlers
+/// through the request extensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// HTTP layer that reads the caller's address from [`FORWARDED_FOR_HEADER`].
+///
+/// The services sit behind the gateway, which overwrites the header with
+/// the address it accepted the connection from, so the first entry is the
+/// real client. Requests without it (direct calls) get no [`ClientIp`].
+#[derive(Debug, Clone, Default)]
+pub struct ClientIpLayer;
+
+impl<S> Layer<S> for ClientIpLayer {
+    type Service = ClientIpService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClientIpService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientIpService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<hyper::Request<B>> for ClientIpService<S>
+where
+    S: Service<hyper::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: hyper::Request<B>) -> Self::Future {
+        let client_ip = request
+            .headers()
+            .get(FORWARDED_FOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .and_then(|first| first.trim().parse().ok());
+        if let Some(ip) = client_ip {
+            request.extensions_mut().insert(ClientIp(ip));
+        }
+
+        self.inner.call(request)
+    }
+}
@@ -0,0 +1,209 @@
+use hyper::header::HeaderValue;
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::server::MethodResponse;
+use jsonrpsee::types::{ErrorObject, Id, Request};
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::time::timeout;
+use tower::{Layer, Service};
+use tracing::warn;
+
+/// Request header carrying the caller's remaining budget in milliseconds,
+/// set by the gateway on every proxied request
+pub const DEADLINE_HEADER: &str = "x-request-deadline-ms";
+
+/// JSON-RPC error code for calls that ran out of their deadline budget
+pub const DEADLINE_EXCEEDED_CODE: i32 = -32012;
+
+tokio::task_local! {
+    static CURRENT_DEADLINE: Deadline;
+}
+
+/// Returned instead of starting work the caller has already given up on
+#[derive(Error, Debug, Clone)]
+#[error("Deadline exceeded: the {budget_ms}ms request budget ran out")]
+pub struct DeadlineExceeded {
+    pub budget_ms: u64,
+}
+
+/// When the caller stops waiting for a request
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    expires_at: Instant,
+    budget: Duration,
+}
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            expires_at: Instant::now() + budget,
+            budget,
+        }
+    }
+
+    /// Parses a [`DEADLINE_HEADER`] value; the budget starts now
+    pub fn from_header(value: &HeaderValue) -> Option<Self> {
+        let millis: u64 = value.to_str().ok()?.trim().parse().ok()?;
+        Some(Self::after(Duration::from_millis(millis)))
+    }
+
+    /// The deadline of the request being handled by the current task, if
+    /// the caller sent one
+    pub fn current() -> Option<Self> {
+        CURRENT_DEADLINE.try_with(|deadline| *deadline).ok()
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.expires_at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn check(&self) -> Result<(), DeadlineExceeded> {
+        if self.is_expired() {
+            return Err(DeadlineExceeded {
+                budget_ms: self.budget.as_millis() as u64,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Fails with [`DeadlineExceeded`] when the current request's budget is
+/// spent; a no-op outside a request or without a deadline
+pub fn check_current_deadline() -> Result<(), DeadlineExceeded> {
+    Deadline::current().map_or(Ok(()), |deadline| deadline.check())
+}
+
+fn deadline_exceeded(id: Id<'_>, method: &str, deadline: &Deadline) -> MethodResponse {
+    MethodResponse::error(
+        id,
+        ErrorObject::owned(
+            DEADLINE_EXCEEDED_CODE,
+            "Deadline exceeded",
+            Some(json!({
+                "method": method,
+                "budget_ms": deadline.budget().as_millis() as u64,
+            })),
+        ),
+    )
+}
+
+/// HTTP layer that reads [`DEADLINE_HEADER`] before the JSON-RPC layer runs
+#[derive(Debug, Clone, Default)]
+pub struct DeadlineHeaderLayer;
+
+impl<S> Layer<S> for DeadlineHeaderLayer {
+    type Service = DeadlineHeaderService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeadlineHeaderService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadlineHeaderService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<hyper::Request<B>> for DeadlineHeaderService<S>
+where
+    S: Service<hyper::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: hyper::Request<B>) -> Self::Future {
+        let deadline = request
+            .headers()
+            .get(DEADLINE_HEADER)
+            .and_then(Deadline::from_header);
+        if let Some(deadline) = deadline {
+            request.extensions_mut().insert(deadline);
+        }
+
+        self.inner.call(request)
+    }
+}
+
+/// Layer installing [`DeadlineEnforcement`] in the JSON-RPC middleware stack
+#[derive(Debug, Clone, Default)]
+pub struct DeadlineLayer;
+
+impl<S> Layer<S> for DeadlineLayer {
+    type Service = DeadlineEnforcement<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        DeadlineEnforcement { service }
+    }
+}
+
+/// JSON-RPC middleware that runs each call within its caller's deadline.
+///
+/// Calls whose budget is already spent are rejected without running, and
+/// calls still running when it runs out are cancelled, so their pending
+/// database operations are dropped too. Both answer with
+/// [`DEADLINE_EXCEEDED_CODE`]. While a call runs, its deadline is available
+/// through [`Deadline::current`], which the repositories check before each
+/// operation; a call that fails after its deadline passed is reported as
+/// exceeded rather than with the handler's generic error.
+#[derive(Debug, Clone)]
+pub struct DeadlineEnforcement<S> {
+    service: S,
+}
+
+impl<'a, S> RpcServiceT<'a> for DeadlineEnforcement<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+    S::Future: 'a,
+{
+    type Future = Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let Some(deadline) = request.extensions().get::<Deadline>().copied() else {
+            return Box::pin(self.service.call(request));
+        };
+
+        let id = request.id().into_owned();
+        let method = request.method_name().to_string();
+        if deadline.is_expired() {
+            warn!("⏰ Rejected {}: deadline already passed", method);
+            return Box::pin(std::future::ready(deadline_exceeded(
+                id, &method, &deadline,
+            )));
+        }
+
+        let call = self.service.call(request);
+        Box::pin(CURRENT_DEADLINE.scope(deadline, async move {
+            match timeout(deadline.remaining(), call).await {
+                Ok(response) if response.is_error() && deadline.is_expired() => {
+                    deadline_exceeded(id, &method, &deadline)
+                }
+                Ok(response) => response,
+                Err(_) => {
+                    warn!(
+                        "⏰ Cancelled {} after its {}ms deadline",
+                        method,
+                        deadline.budget().as_millis()
+                    );
+                    deadline_exceeded(id, &method, &deadline)
+                }
+            }
+        }))
+    }
+}
@@ -0,0 +1,213 @@
+use bytes::Bytes;
+use hyper::header::{CONTENT_TYPE, RETRY_AFTER};
+use hyper::StatusCode;
+use jsonrpsee::core::BoxError;
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+use tracing::warn;
+
+/// JSON-RPC error code for requests a saturated service turned away
+pub const SERVICE_BUSY_CODE: i32 = -32016;
+
+/// Response header marking a `503` as deliberate shedding rather than a
+/// failure, so the gateway backs off instead of opening its circuit
+pub const SERVICE_BUSY_HEADER: &str = "x-service-busy";
+
+/// Weight of the newest request in the latency average
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// The JSON-RPC error sent with a busy response. The id is null: the
+/// request is refused before its body is read.
+pub fn busy_error(retry_after: Duration) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": SERVICE_BUSY_CODE,
+            "message": "Service busy",
+            "data": { "retry_after_ms": retry_after.as_millis() as u64 },
+        },
+        "id": null,
+    })
+}
+
+/// In-flight requests and recent latency of one service
+#[derive(Debug)]
+struct LoadMonitor {
+    max_in_flight: usize,
+    latency_threshold: Option<Duration>,
+    retry_after: Duration,
+    in_flight: AtomicUsize,
+    /// Exponentially weighted average latency in microseconds
+    average_latency_micros: AtomicU64,
+    shed_total: AtomicU64,
+}
+
+impl LoadMonitor {
+    /// Admission limit right now: halved while the average latency is over
+    /// the threshold, never below one so latency keeps being measured
+    fn limit(&self) -> usize {
+        let slow = self.latency_threshold.is_some_and(|threshold| {
+            self.average_latency_micros.load(Ordering::Relaxed) > threshold.as_micros() as u64
+        });
+        if slow {
+            (self.max_in_flight / 2).max(1)
+        } else {
+            self.max_in_flight
+        }
+    }
+
+    fn record_latency(&self, elapsed: Duration) {
+        let sample = elapsed.as_micros() as f64;
+        let _ = self.average_latency_micros.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |average| {
+                let average = average as f64;
+                Some((average + LATENCY_SMOOTHING * (sample - average)) as u64)
+            },
+        );
+    }
+}
+
+/// Releases the request's slot and records its latency when dropped
+struct InFlight {
+    monitor: Arc<LoadMonitor>,
+    started: Instant,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.monitor.record_latency(self.started.elapsed());
+        self.monitor.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// HTTP layer shedding load once the service is saturated, instead of
+/// letting requests queue up without bound.
+///
+/// With `SERVICE_MAX_IN_FLIGHT` set, requests beyond that many in flight
+/// are refused with `503`, `Retry-After` and a [`SERVICE_BUSY_CODE`]
+/// error; with `SERVICE_SHED_LATENCY_MS` also set, the limit is halved
+/// while the average request latency is above it. Disabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct LoadSheddingLayer {
+    monitor: Option<Arc<LoadMonitor>>,
+}
+
+impl LoadSheddingLayer {
+    /// Reads `SERVICE_MAX_IN_FLIGHT`, `SERVICE_SHED_LATENCY_MS` and
+    /// `SERVICE_BUSY_RETRY_AFTER_SECS` (default 1)
+    pub fn from_env() -> Self {
+        let env_u64 = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        let Some(max_in_flight) = env_u64("SERVICE_MAX_IN_FLIGHT").filter(|max| *max > 0) else {
+            return Self::default();
+        };
+        let monitor = LoadMonitor {
+            max_in_flight: max_in_flight as usize,
+            latency_threshold: env_u64("SERVICE_SHED_LATENCY_MS")
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            retry_after: Duration::from_secs(
+                env_u64("SERVICE_BUSY_RETRY_AFTER_SECS").unwrap_or(1).max(1),
+            ),
+            in_flight: AtomicUsize::new(0),
+            average_latency_micros: AtomicU64::new(0),
+            shed_total: AtomicU64::new(0),
+        };
+        Self {
+            monitor: Some(Arc::new(monitor)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.monitor.is_some()
+    }
+
+    /// `max_in_flight` and the latency threshold, for the startup log
+    pub fn limits(&self) -> Option<(usize, Option<Duration>)> {
+        self.monitor
+            .as_ref()
+            .map(|monitor| (monitor.max_in_flight, monitor.latency_threshold))
+    }
+}
+
+impl<S> Layer<S> for LoadSheddingLayer {
+    type Service = LoadSheddingService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoadSheddingService {
+            inner,
+            monitor: self.monitor.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadSheddingService<S> {
+    inner: S,
+    monitor: Option<Arc<LoadMonitor>>,
+}
+
+impl<S, B> Service<HttpRequest> for LoadSheddingService<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse<B>, Error = BoxError>,
+    S::Future: Send + 'static,
+    B: hyper::body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        let Some(monitor) = self.monitor.clone() else {
+            let response = self.inner.call(request);
+            return Box::pin(async move { Ok(response.await?.map(HttpBody::new)) });
+        };
+
+        let limit = monitor.limit();
+        if monitor.in_flight.fetch_add(1, Ordering::Relaxed) >= limit {
+            monitor.in_flight.fetch_sub(1, Ordering::Relaxed);
+            let shed = monitor.shed_total.fetch_add(1, Ordering::Relaxed) + 1;
+            // One line per hundred sheds is enough to notice
+            if shed % 100 == 1 {
+                warn!(
+                    "🛑 Shedding load at {} requests in flight, {} shed so far",
+                    limit, shed
+                );
+            }
+            return Box::pin(std::future::ready(busy(monitor.retry_after)));
+        }
+
+        let guard = InFlight {
+            monitor,
+            started: Instant::now(),
+        };
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            drop(guard);
+            Ok(response?.map(HttpBody::new))
+        })
+    }
+}
+
+fn busy(retry_after: Duration) -> Result<HttpResponse, BoxError> {
+    Ok(HttpResponse::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header(CONTENT_TYPE, "application/json")
+        .header(RETRY_AFTER, retry_after.as_secs())
+        .header(SERVICE_BUSY_HEADER, "1")
+        .body(HttpBody::from(busy_error(retry_after).to_string()))?)
+}
@@ -0,0 +1,15 @@
+pub mod authorization;
+pub mod notifications;
+pub mod deadline;
+pub mod api_version;
+pub mod avatar_uploads;
+pub mod client_ip;
+pub mod server_timing;
+pub mod request_signing;
+pub mod load_shedding;
+pub mod timestamp_format;
+pub mod org_context;
+pub mod trace_sampling;
+pub mod payload_limits;
+pub mod request_id;
+pub mod panic_recovery;
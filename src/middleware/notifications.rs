@@ -0,0 +1,174 @@
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use hyper::header::{HeaderValue, CONTENT_LENGTH};
+use hyper::{Method, StatusCode};
+use jsonrpsee::core::BoxError;
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// A JSON-RPC 2.0 notification: a call with a method and no `id` member.
+/// `"id": null` is a regular request and still gets a response.
+pub fn is_notification(call: &Value) -> bool {
+    call.get("method").is_some_and(Value::is_string) && call.get("id").is_none()
+}
+
+/// True when a request body is a notification or a batch made up only of
+/// notifications, i.e. the client expects no response body at all.
+pub fn is_notification_body(body: &[u8]) -> bool {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(calls)) => !calls.is_empty() && calls.iter().all(is_notification),
+        Ok(call) => is_notification(&call),
+        Err(_) => false,
+    }
+}
+
+/// HTTP layer that makes notifications actually run.
+///
+/// jsonrpsee silently drops calls without an `id`, so fire-and-forget
+/// methods would never execute. This layer gives each notification a
+/// private id, lets the server handle it like any call, then removes its
+/// response: a body containing only notifications is answered with
+/// `204 No Content`, and batches keep the responses of their regular calls.
+#[derive(Debug, Clone, Default)]
+pub struct NotificationLayer;
+
+impl<S> Layer<S> for NotificationLayer {
+    type Service = NotificationService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        NotificationService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NotificationService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<HttpRequest> for NotificationService<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse<B>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    B: hyper::body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        // Use the instance that was polled ready and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if request.method() != Method::POST {
+                return Ok(inner.call(request).await?.map(HttpBody::new));
+            }
+
+            let (mut parts, body) = request.into_parts();
+            let body = body.collect().await?.to_bytes();
+            let Some((rewritten, notifications)) = assign_notification_ids(&body) else {
+                let request =
+                    HttpRequest::from_parts(parts, HttpBody::new(http_body_util::Full::new(body)));
+                return Ok(inner.call(request).await?.map(HttpBody::new));
+            };
+
+            parts
+                .headers
+                .insert(CONTENT_LENGTH, HeaderValue::from(rewritten.len()));
+            let response = inner
+                .call(HttpRequest::from_parts(parts, HttpBody::from(rewritten)))
+                .await?;
+
+            let (mut parts, body) = response.into_parts();
+            let body = body.collect().await.map_err(Into::into)?.to_bytes();
+            match strip_notification_responses(&body, &notifications) {
+                Some(remaining) => {
+                    parts
+                        .headers
+                        .insert(CONTENT_LENGTH, HeaderValue::from(remaining.len()));
+                    Ok(HttpResponse::from_parts(parts, HttpBody::from(remaining)))
+                }
+                None => Ok(HttpResponse::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(HttpBody::empty())?),
+            }
+        })
+    }
+}
+
+/// Gives every notification in `body` a generated id. Returns the rewritten
+/// body and the generated ids mapped to their method names, or `None` when
+/// the body holds no notifications.
+fn assign_notification_ids(body: &[u8]) -> Option<(Vec<u8>, HashMap<String, String>)> {
+    let mut request: Value = serde_json::from_slice(body).ok()?;
+    let mut notifications = HashMap::new();
+
+    let mut assign = |call: &mut Value| {
+        if is_notification(call) {
+            let id = format!("notification-{}", Uuid::new_v4());
+            let method = call["method"].as_str().unwrap_or_default().to_string();
+            call["id"] = Value::String(id.clone());
+            notifications.insert(id, method);
+        }
+    };
+    match &mut request {
+        Value::Array(calls) => calls.iter_mut().for_each(&mut assign),
+        call => assign(call),
+    }
+
+    if notifications.is_empty() {
+        return None;
+    }
+    Some((serde_json::to_vec(&request).ok()?, notifications))
+}
+
+/// Removes the responses to generated notification ids, logging any that
+/// failed. Returns the body to send back, or `None` when nothing is left.
+fn strip_notification_responses(
+    body: &[u8],
+    notifications: &HashMap<String, String>,
+) -> Option<Vec<u8>> {
+    let response: Value = match serde_json::from_slice(body) {
+        Ok(response) => response,
+        // Not a JSON-RPC response (e.g. a transport error), relay it as is
+        Err(_) if !body.is_empty() => return Some(body.to_vec()),
+        Err(_) => return None,
+    };
+
+    let keep = |entry: &Value| {
+        let Some(method) = entry["id"].as_str().and_then(|id| notifications.get(id)) else {
+            return true;
+        };
+        match entry.get("error") {
+            Some(error) => warn!("📭 Notification {} failed: {}", method, error),
+            None => debug!("📭 Notification {} handled", method),
+        }
+        false
+    };
+
+    match response {
+        Value::Array(entries) => {
+            let remaining: Vec<Value> = entries.into_iter().filter(|entry| keep(entry)).collect();
+            if remaining.is_empty() {
+                None
+            } else {
+                serde_json::to_vec(&remaining).ok()
+            }
+        }
+        entry if keep(&entry) => Some(body.to_vec()),
+        _ => None,
+    }
+}
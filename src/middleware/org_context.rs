@@ -0,0 +1,60 @@
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Request header carrying the organization a request is made for, set by
+/// the gateway from the caller's API key
+pub const ORG_HEADER: &str = "x-org-id";
+
+/// The caller's organization, available to handlers through the request
+/// extensions. Requests without one only see public data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallerOrg(pub String);
+
+/// HTTP layer that reads the caller's organization from [`ORG_HEADER`].
+///
+/// The gateway always replaces the header with the organization mapped to
+/// the caller's bearer token and drops one sent by the client, so only
+/// direct calls to a service can choose their own.
+#[derive(Debug, Clone, Default)]
+pub struct OrgContextLayer;
+
+impl<S> Layer<S> for OrgContextLayer {
+    type Service = OrgContextService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OrgContextService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrgContextService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<hyper::Request<B>> for OrgContextService<S>
+where
+    S: Service<hyper::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: hyper::Request<B>) -> Self::Future {
+        let org = request
+            .headers()
+            .get(ORG_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|org| !org.is_empty())
+            .map(str::to_string);
+        if let Some(org) = org {
+            request.extensions_mut().insert(CallerOrg(org));
+        }
+
+        self.inner.call(request)
+    }
+}
@@ -0,0 +1,215 @@
+use crate::middleware::request_id::RequestId;
+use chrono::{DateTime, Utc};
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::server::MethodResponse;
+use jsonrpsee::types::{ErrorCode, ErrorObject, Request};
+use serde::Serialize;
+use serde_json::json;
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::task::Poll;
+use tower::Layer;
+use tracing::error;
+
+thread_local! {
+    /// Set while this thread polls an RPC handler under [`PanicRecovery`]
+    static IN_HANDLER: Cell<bool> = const { Cell::new(false) };
+    /// Where the last handler panic on this thread happened, recorded by
+    /// the panic hook while the stack is still there
+    static LAST_PANIC: RefCell<Option<PanicSite>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug)]
+struct PanicSite {
+    location: Option<String>,
+    backtrace: Backtrace,
+}
+
+/// Marks the thread as inside a handler until dropped, unwinding included
+struct HandlerScope {
+    was_in_handler: bool,
+}
+
+impl HandlerScope {
+    fn enter() -> Self {
+        Self {
+            was_in_handler: IN_HANDLER.with(|flag| flag.replace(true)),
+        }
+    }
+}
+
+impl Drop for HandlerScope {
+    fn drop(&mut self) {
+        IN_HANDLER.with(|flag| flag.set(self.was_in_handler));
+    }
+}
+
+/// Installs a panic hook that captures the backtrace of panics in RPC
+/// handlers for [`PanicRecovery`] to log with the request. Panics anywhere
+/// else still go to the previous hook.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if IN_HANDLER.with(Cell::get) {
+            let site = PanicSite {
+                location: info.location().map(ToString::to_string),
+                backtrace: Backtrace::force_capture(),
+            };
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some(site));
+        } else {
+            previous(info);
+        }
+    }));
+}
+
+/// Handler panics caught since startup
+#[derive(Debug, Default)]
+pub struct PanicStats {
+    panics: AtomicU64,
+    by_method: Mutex<BTreeMap<String, u64>>,
+    last_panic_at: Mutex<Option<DateTime<Utc>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PanicStatsSnapshot {
+    pub panics: u64,
+    pub by_method: BTreeMap<String, u64>,
+    pub last_panic_at: Option<DateTime<Utc>>,
+}
+
+impl PanicStats {
+    pub fn global() -> &'static PanicStats {
+        static STATS: OnceLock<PanicStats> = OnceLock::new();
+        STATS.get_or_init(PanicStats::default)
+    }
+
+    fn record(&self, method: &str) {
+        self.panics.fetch_add(1, Ordering::Relaxed);
+        *self
+            .by_method
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(method.to_string())
+            .or_default() += 1;
+        *self
+            .last_panic_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Utc::now());
+    }
+
+    pub fn snapshot(&self) -> PanicStatsSnapshot {
+        PanicStatsSnapshot {
+            panics: self.panics.load(Ordering::Relaxed),
+            by_method: self
+                .by_method
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+            last_panic_at: *self
+                .last_panic_at
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+}
+
+/// The message a panic was raised with, when it has one
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<non-string panic payload>")
+}
+
+/// Layer installing [`PanicRecovery`] in the JSON-RPC middleware stack
+#[derive(Debug, Clone, Default)]
+pub struct PanicRecoveryLayer;
+
+impl<S> Layer<S> for PanicRecoveryLayer {
+    type Service = PanicRecovery<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        PanicRecovery { service }
+    }
+}
+
+/// JSON-RPC middleware turning a panic in a handler into an
+/// `InternalError` response carrying the request id, instead of a dropped
+/// connection. The panic is counted in [`PanicStats`] and logged with its
+/// backtrace when [`install_panic_hook`] was called.
+#[derive(Debug, Clone)]
+pub struct PanicRecovery<S> {
+    service: S,
+}
+
+impl<'a, S> RpcServiceT<'a> for PanicRecovery<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+    S::Future: 'a,
+{
+    type Future = Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let id = request.id().into_owned();
+        let method = request.method_name().to_string();
+        let request_id = request
+            .extensions()
+            .get::<RequestId>()
+            .copied()
+            .unwrap_or_else(RequestId::generate);
+        let mut call = Box::pin(self.service.call(request));
+
+        Box::pin(async move {
+            // Once it panicked the handler is dropped, never polled again
+            let outcome = std::future::poll_fn(|cx| {
+                let polled = catch_unwind(AssertUnwindSafe(|| {
+                    let _scope = HandlerScope::enter();
+                    call.as_mut().poll(cx)
+                }));
+                match polled {
+                    Ok(Poll::Ready(response)) => Poll::Ready(Ok(response)),
+                    Ok(Poll::Pending) => Poll::Pending,
+                    Err(payload) => Poll::Ready(Err(payload)),
+                }
+            })
+            .await;
+            let payload = match outcome {
+                Ok(response) => return response,
+                Err(payload) => payload,
+            };
+
+            PanicStats::global().record(&method);
+            let site = LAST_PANIC.with(|last| last.borrow_mut().take());
+            let (location, backtrace) = match &site {
+                Some(site) => (
+                    site.location.as_deref().unwrap_or("unknown location"),
+                    site.backtrace.to_string(),
+                ),
+                None => ("unknown location", "no backtrace captured".to_string()),
+            };
+            error!(
+                "💥 [{}] {} panicked at {}: {}\n{}",
+                request_id,
+                method,
+                location,
+                panic_message(payload.as_ref()),
+                backtrace
+            );
+
+            let error = ErrorObject::owned(
+                ErrorCode::InternalError.code(),
+                ErrorCode::InternalError.message(),
+                Some(json!({ "request_id": request_id })),
+            );
+            MethodResponse::error(id, error)
+        })
+    }
+}
@@ -0,0 +1,365 @@
+use crate::services::method_namespaces::flat_method_name;
+use bytes::Bytes;
+use futures::future::{ready, Either, Ready};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Method, StatusCode};
+use jsonrpsee::core::BoxError;
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse, MethodResponse};
+use jsonrpsee::types::{ErrorObject, Request};
+use serde_json::value::RawValue;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tower::{Layer, Service};
+use tracing::warn;
+
+/// JSON-RPC error code for requests over one of the [`PayloadLimits`]
+pub const PAYLOAD_TOO_LARGE_CODE: i32 = -32019;
+
+/// jsonrpsee's own default body limit
+const DEFAULT_MAX_REQUEST_BYTES: u32 = 10 * 1024 * 1024;
+const DEFAULT_MAX_BATCH_LEN: u32 = 100;
+const DEFAULT_MAX_ARRAY_LEN: usize = 1000;
+
+#[derive(Error, Debug)]
+#[error("Invalid {name} '{value}', expected a positive number")]
+pub struct PayloadLimitsError {
+    name: &'static str,
+    value: String,
+}
+
+/// Size limits on incoming requests, so one giant call cannot stall the
+/// service while it is read, parsed and written to the database
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadLimits {
+    /// Largest request body, single call or batch
+    pub max_request_bytes: u32,
+    /// Most calls in one batch
+    pub max_batch_len: u32,
+    /// Most items in any array within a call's params
+    pub max_array_len: usize,
+    /// Bulk methods allowed longer arrays than `max_array_len`, by flat
+    /// name; each caps its own input
+    pub method_array_lens: &'static [(&'static str, usize)],
+}
+
+impl Default for PayloadLimits {
+    fn default() -> Self {
+        Self {
+            max_request_bytes: DEFAULT_MAX_REQUEST_BYTES,
+            max_batch_len: DEFAULT_MAX_BATCH_LEN,
+            max_array_len: DEFAULT_MAX_ARRAY_LEN,
+            method_array_lens: &[],
+        }
+    }
+}
+
+impl PayloadLimits {
+    /// Reads `SERVICE_MAX_REQUEST_BYTES`, `SERVICE_MAX_BATCH_LEN` and
+    /// `SERVICE_MAX_ARRAY_LEN`, keeping the defaults for unset ones
+    pub fn from_env() -> Result<Self, PayloadLimitsError> {
+        fn limit<T: std::str::FromStr + Default + PartialEq>(
+            name: &'static str,
+            default: T,
+        ) -> Result<T, PayloadLimitsError> {
+            match std::env::var(name) {
+                Ok(value) => value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|limit| *limit != T::default())
+                    .ok_or(PayloadLimitsError { name, value }),
+                Err(_) => Ok(default),
+            }
+        }
+
+        Ok(Self {
+            max_request_bytes: limit("SERVICE_MAX_REQUEST_BYTES", DEFAULT_MAX_REQUEST_BYTES)?,
+            max_batch_len: limit("SERVICE_MAX_BATCH_LEN", DEFAULT_MAX_BATCH_LEN)?,
+            max_array_len: limit("SERVICE_MAX_ARRAY_LEN", DEFAULT_MAX_ARRAY_LEN)?,
+            method_array_lens: &[],
+        })
+    }
+
+    /// Lets the bulk methods in `lens` take arrays up to their own length
+    /// instead of `max_array_len`
+    pub fn with_method_array_lens(mut self, lens: &'static [(&'static str, usize)]) -> Self {
+        self.method_array_lens = lens;
+        self
+    }
+
+    /// Most items in any array within `method`'s params, flat or namespaced
+    pub fn max_array_len_for(&self, method: &str) -> usize {
+        let method = flat_method_name(method);
+        self.method_array_lens
+            .iter()
+            .find(|(bulk, _)| *bulk == method)
+            .map_or(self.max_array_len, |(_, len)| *len)
+    }
+
+    /// The limits, for the startup log and `--check-config`
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "{} bytes, {} calls per batch, {} items per array",
+            self.max_request_bytes, self.max_batch_len, self.max_array_len
+        );
+        for (method, len) in self.method_array_lens {
+            description.push_str(&format!(" ({} for {})", len, method));
+        }
+        description
+    }
+}
+
+/// The JSON-RPC error sent for a body over a limit. The id is null: the
+/// request is refused before its calls are parsed.
+pub fn payload_too_large(data: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": PAYLOAD_TOO_LARGE_CODE,
+            "message": "Payload too large",
+            "data": data,
+        },
+        "id": null,
+    })
+}
+
+fn too_large_response(data: Value) -> Result<HttpResponse, BoxError> {
+    Ok(HttpResponse::builder()
+        .status(StatusCode::PAYLOAD_TOO_LARGE)
+        .header(CONTENT_TYPE, "application/json")
+        .body(HttpBody::from(payload_too_large(data).to_string()))?)
+}
+
+/// HTTP layer that refuses bodies over `max_request_bytes` and batches over
+/// `max_batch_len` with `413` and [`PAYLOAD_TOO_LARGE_CODE`].
+///
+/// Bodies announcing a larger `Content-Length` are refused before they are
+/// read, and others stop being read at the limit. The server is also
+/// configured with the same limits, but answers with jsonrpsee's own codes.
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadLimitHeaderLayer {
+    limits: PayloadLimits,
+}
+
+impl PayloadLimitHeaderLayer {
+    pub fn new(limits: PayloadLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl<S> Layer<S> for PayloadLimitHeaderLayer {
+    type Service = PayloadLimitHeaderService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PayloadLimitHeaderService {
+            inner,
+            limits: self.limits,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PayloadLimitHeaderService<S> {
+    inner: S,
+    limits: PayloadLimits,
+}
+
+impl<S, B> Service<HttpRequest> for PayloadLimitHeaderService<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse<B>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    B: hyper::body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        // Use the instance that was polled ready and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limits = self.limits;
+
+        Box::pin(async move {
+            if request.method() != Method::POST {
+                return Ok(inner.call(request).await?.map(HttpBody::new));
+            }
+
+            let max_bytes = limits.max_request_bytes as usize;
+            let declared = request
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<usize>().ok());
+            if let Some(declared) = declared.filter(|declared| *declared > max_bytes) {
+                warn!(
+                    "📦 Refused a {} byte request, over the {} byte limit",
+                    declared, max_bytes
+                );
+                return too_large_response(json!({
+                    "bytes": declared,
+                    "max_request_bytes": max_bytes,
+                }));
+            }
+
+            let (parts, body) = request.into_parts();
+            let body = match Limited::new(body, max_bytes).collect().await {
+                Ok(collected) => collected.to_bytes(),
+                Err(err) if err.is::<LengthLimitError>() => {
+                    warn!("📦 Refused a request over the {} byte limit", max_bytes);
+                    return too_large_response(json!({ "max_request_bytes": max_bytes }));
+                }
+                Err(err) => return Err(err),
+            };
+
+            // Unparseable bodies are left for the server to report
+            if let Ok(calls) = serde_json::from_slice::<Vec<&RawValue>>(&body) {
+                if calls.len() > limits.max_batch_len as usize {
+                    warn!(
+                        "📦 Refused a batch of {} calls, over the limit of {}",
+                        calls.len(),
+                        limits.max_batch_len
+                    );
+                    return too_large_response(json!({
+                        "batch_len": calls.len(),
+                        "max_batch_len": limits.max_batch_len,
+                    }));
+                }
+            }
+
+            let request = HttpRequest::from_parts(parts, HttpBody::new(Full::new(body)));
+            Ok(inner.call(request).await?.map(HttpBody::new))
+        })
+    }
+}
+
+/// Length of the longest array anywhere in `value`
+fn longest_array(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(longest_array)
+            .max()
+            .unwrap_or_default()
+            .max(items.len()),
+        Value::Object(fields) => fields.values().map(longest_array).max().unwrap_or_default(),
+        _ => 0,
+    }
+}
+
+/// Layer installing [`ParamsLimit`] in the JSON-RPC middleware stack
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadLimitLayer {
+    limits: PayloadLimits,
+}
+
+impl PayloadLimitLayer {
+    pub fn new(limits: PayloadLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl<S> Layer<S> for PayloadLimitLayer {
+    type Service = ParamsLimit<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        ParamsLimit {
+            service,
+            limits: self.limits,
+        }
+    }
+}
+
+/// JSON-RPC middleware that refuses calls whose params hold an array longer
+/// than the method's [`PayloadLimits::max_array_len_for`], at any depth,
+/// with [`PAYLOAD_TOO_LARGE_CODE`], so bulk methods never start on an
+/// oversized list
+#[derive(Debug, Clone)]
+pub struct ParamsLimit<S> {
+    service: S,
+    limits: PayloadLimits,
+}
+
+impl<'a, S> RpcServiceT<'a> for ParamsLimit<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+{
+    type Future = Either<Ready<MethodResponse>, S::Future>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let array_len = request
+            .params()
+            .as_str()
+            .and_then(|params| serde_json::from_str::<Value>(params).ok())
+            .map_or(0, |params| longest_array(&params));
+        let max_array_len = self.limits.max_array_len_for(request.method_name());
+        if array_len <= max_array_len {
+            return Either::Right(self.service.call(request));
+        }
+
+        let method = request.method_name().to_string();
+        warn!(
+            "📦 Rejected {}: an array of {} items, over the limit of {}",
+            method, array_len, max_array_len
+        );
+        Either::Left(ready(MethodResponse::error(
+            request.id().into_owned(),
+            ErrorObject::owned(
+                PAYLOAD_TOO_LARGE_CODE,
+                "Payload too large",
+                Some(json!({
+                    "method": method,
+                    "array_len": array_len,
+                    "max_array_len": max_array_len,
+                })),
+            ),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bulk_methods_have_their_own_array_limit() {
+        let limits =
+            PayloadLimits::default().with_method_array_lens(&[("update_stock_bulk", 10_000)]);
+        let cases = [
+            ("update_stock_bulk", 10_000),
+            ("product.stock.update_bulk", 10_000),
+            ("record_order", DEFAULT_MAX_ARRAY_LEN),
+            ("unknown_method", DEFAULT_MAX_ARRAY_LEN),
+        ];
+        for (method, expected) in cases {
+            assert_eq!(limits.max_array_len_for(method), expected, "{}", method);
+        }
+        assert_eq!(
+            PayloadLimits::default().max_array_len_for("update_stock_bulk"),
+            DEFAULT_MAX_ARRAY_LEN
+        );
+    }
+
+    #[test]
+    fn longest_array_looks_at_every_depth() {
+        let cases = [
+            (json!({}), 0),
+            (json!({ "ids": [1, 2, 3] }), 3),
+            (json!({ "items": [{ "tags": [1, 2, 3, 4] }] }), 4),
+            (json!([[1], [1, 2]]), 2),
+        ];
+        for (params, expected) in cases {
+            assert_eq!(longest_array(&params), expected, "{}", params);
+        }
+    }
+}
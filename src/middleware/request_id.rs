@@ -0,0 +1,139 @@
+use hyper::header::HeaderValue;
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::types::Request;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::instrument::Instrumented;
+use tracing::{info_span, Instrument};
+use uuid::Uuid;
+
+/// Request header carrying the id the gateway gave a request, on the
+/// request it proxies and on the response it sends back
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Identifies one request across the gateway and the services. The gateway
+/// generates it and replaces any id sent by the client; services read it
+/// from [`REQUEST_ID_HEADER`] and generate their own for direct calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestId(Uuid);
+
+impl RequestId {
+    /// A new random id
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    /// Parses a [`REQUEST_ID_HEADER`] value
+    pub fn from_header(value: &HeaderValue) -> Option<Self> {
+        value.to_str().ok()?.trim().parse().ok()
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.hyphenated().fmt(f)
+    }
+}
+
+impl FromStr for RequestId {
+    type Err = uuid::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(text).map(Self)
+    }
+}
+
+impl Serialize for RequestId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl From<RequestId> for HeaderValue {
+    fn from(id: RequestId) -> Self {
+        // A hyphenated UUID is always a valid header value
+        HeaderValue::from_str(&id.to_string()).expect("UUIDs are valid header values")
+    }
+}
+
+/// HTTP layer that reads [`REQUEST_ID_HEADER`] before the JSON-RPC layer
+/// runs, or gives requests without a valid one a new id
+#[derive(Debug, Clone, Default)]
+pub struct RequestIdHeaderLayer;
+
+impl<S> Layer<S> for RequestIdHeaderLayer {
+    type Service = RequestIdHeaderService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdHeaderService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestIdHeaderService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<hyper::Request<B>> for RequestIdHeaderService<S>
+where
+    S: Service<hyper::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: hyper::Request<B>) -> Self::Future {
+        let id = request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(RequestId::from_header)
+            .unwrap_or_else(RequestId::generate);
+        request.extensions_mut().insert(id);
+
+        self.inner.call(request)
+    }
+}
+
+/// Layer installing [`RequestIdSpan`] in the JSON-RPC middleware stack
+#[derive(Debug, Clone, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdSpan<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RequestIdSpan { service }
+    }
+}
+
+/// JSON-RPC middleware that runs each call in an `rpc` span with its
+/// `request_id` and `method`, so handler logs and the `db.query` spans of
+/// the repositories it calls carry the gateway's id
+#[derive(Debug, Clone)]
+pub struct RequestIdSpan<S> {
+    service: S,
+}
+
+impl<'a, S> RpcServiceT<'a> for RequestIdSpan<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+{
+    type Future = Instrumented<S::Future>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let id = request
+            .extensions()
+            .get::<RequestId>()
+            .copied()
+            .unwrap_or_else(RequestId::generate);
+        let span = info_span!("rpc", request_id = %id, method = %request.method_name());
+        self.service.call(request).instrument(span)
+    }
+}
@@ -0,0 +1,176 @@
+use crate::crypto::request_signing::RequestSigner;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::header::CONTENT_TYPE;
+use hyper::StatusCode;
+use jsonrpsee::core::BoxError;
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::warn;
+
+/// Largest body buffered for verification; matches the JSON-RPC server's
+/// default request limit
+const MAX_SIGNED_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// HTTP layer rejecting requests that were not signed by the gateway.
+///
+/// With `GATEWAY_SIGNING_SECRET` set, each request's signature headers are
+/// checked against its method, path and body before the JSON-RPC server
+/// sees it. Unsigned, tampered, stale and replayed requests get `401`.
+/// Without a secret every request passes.
+#[derive(Debug, Clone, Default)]
+pub struct RequestSignatureLayer {
+    signer: Option<Arc<RequestSigner>>,
+}
+
+impl RequestSignatureLayer {
+    pub fn new(signer: Option<Arc<RequestSigner>>) -> Self {
+        Self { signer }
+    }
+}
+
+impl<S> Layer<S> for RequestSignatureLayer {
+    type Service = RequestSignatureService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestSignatureService {
+            inner,
+            signer: self.signer.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestSignatureService<S> {
+    inner: S,
+    signer: Option<Arc<RequestSigner>>,
+}
+
+impl<S, B> Service<HttpRequest> for RequestSignatureService<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse<B>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    B: hyper::body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        let Some(signer) = self.signer.clone() else {
+            let response = self.inner.call(request);
+            return Box::pin(async move { Ok(response.await?.map(HttpBody::new)) });
+        };
+
+        // Use the instance that was polled ready and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = match Limited::new(body, MAX_SIGNED_BODY_BYTES).collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(_) => {
+                    return plain(
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        "Request body too large to verify",
+                    )
+                }
+            };
+
+            let path = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+            if let Err(rejection) =
+                signer.verify(parts.method.as_str(), path, &parts.headers, &body)
+            {
+                warn!("🔏 Rejected {} {}: {}", parts.method, path, rejection);
+                return plain(
+                    StatusCode::UNAUTHORIZED,
+                    &format!("Request rejected: {}", rejection),
+                );
+            }
+
+            let request = HttpRequest::from_parts(parts, HttpBody::new(Full::new(body)));
+            Ok(inner.call(request).await?.map(HttpBody::new))
+        })
+    }
+}
+
+fn plain(status: StatusCode, message: &str) -> Result<HttpResponse, BoxError> {
+    Ok(HttpResponse::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain")
+        .body(HttpBody::from(message.to_string()))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::request_signing::{
+        SIGNATURE_HEADER, SIGNATURE_NONCE_HEADER, SIGNATURE_TIMESTAMP_HEADER,
+    };
+    use hyper::header::HeaderMap;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn request(signer: Option<&RequestSigner>, body: &str) -> HttpRequest {
+        let mut builder = HttpRequest::builder().method("POST").uri("/");
+        if let Some(signer) = signer {
+            let signed = signer.sign("POST", "/", &HeaderMap::new(), b"{}");
+            builder = builder
+                .header(SIGNATURE_TIMESTAMP_HEADER, signed.timestamp)
+                .header(SIGNATURE_NONCE_HEADER, signed.nonce)
+                .header(SIGNATURE_HEADER, signed.signature);
+        }
+        builder.body(HttpBody::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_signed_requests_reach_the_server() {
+        let signer = Arc::new(RequestSigner::new(SECRET, 300).unwrap());
+        let inner = tower::service_fn(|request: HttpRequest| async move {
+            let body = request.into_body().collect().await?.to_bytes();
+            Ok::<_, BoxError>(HttpResponse::new(Full::new(body)))
+        });
+        let mut service = RequestSignatureLayer::new(Some(signer.clone())).layer(inner);
+        let mut unchecked = RequestSignatureLayer::new(None).layer(inner);
+
+        let replayed = request(Some(&signer), "{}");
+        let copy = HttpRequest::builder()
+            .method("POST")
+            .uri("/")
+            .body(HttpBody::from("{}"))
+            .map(|mut copy| {
+                *copy.headers_mut() = replayed.headers().clone();
+                copy
+            })
+            .unwrap();
+        let cases = [
+            ("signed", request(Some(&signer), "{}"), StatusCode::OK),
+            ("unsigned", request(None, "{}"), StatusCode::UNAUTHORIZED),
+            (
+                "tampered body",
+                request(Some(&signer), "{\"admin\":true}"),
+                StatusCode::UNAUTHORIZED,
+            ),
+            ("first use", replayed, StatusCode::OK),
+            ("replayed", copy, StatusCode::UNAUTHORIZED),
+        ];
+        for (name, request, expected) in cases {
+            let response = service.call(request).await.unwrap();
+            assert_eq!(response.status(), expected, "{}", name);
+        }
+
+        let response = unchecked.call(request(None, "{}")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"{}");
+    }
+}
@@ -0,0 +1,126 @@
+use hyper::header::HeaderValue;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// Response header carrying the timing breakdown
+pub const SERVER_TIMING_HEADER: &str = "server-timing";
+
+tokio::task_local! {
+    static REQUEST_TIMING: Arc<RequestTiming>;
+}
+
+/// Database time spent on behalf of one HTTP request, summed over all of
+/// its calls
+#[derive(Debug, Default)]
+pub struct RequestTiming {
+    db_nanos: AtomicU64,
+    db_queries: AtomicU64,
+}
+
+impl RequestTiming {
+    fn db_time(&self) -> Duration {
+        Duration::from_nanos(self.db_nanos.load(Ordering::Relaxed))
+    }
+
+    fn db_queries(&self) -> u64 {
+        self.db_queries.load(Ordering::Relaxed)
+    }
+}
+
+/// Adds a database operation to the current request's timing; a no-op
+/// outside a timed request
+pub fn record_db_time(elapsed: Duration) {
+    let _ = REQUEST_TIMING.try_with(|timing| {
+        timing
+            .db_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        timing.db_queries.fetch_add(1, Ordering::Relaxed);
+    });
+}
+
+/// `db;dur=..;desc="N queries", app;dur=..` in milliseconds
+fn header_value(timing: &RequestTiming, total: Duration) -> Option<HeaderValue> {
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    HeaderValue::from_str(&format!(
+        "db;dur={:.1};desc=\"{} queries\", app;dur={:.1}",
+        millis(timing.db_time()),
+        timing.db_queries(),
+        millis(total)
+    ))
+    .ok()
+}
+
+/// HTTP layer adding a `Server-Timing` header to every response with the
+/// time the service spent on the request (`app`) and how much of it went
+/// to the database (`db`), so clients can tell where latency comes from
+/// without access to server traces. Disabled unless `SERVER_TIMING=true`.
+#[derive(Debug, Clone, Default)]
+pub struct ServerTimingLayer {
+    enabled: bool,
+}
+
+impl ServerTimingLayer {
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("SERVER_TIMING")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        Self { enabled }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl<S> Layer<S> for ServerTimingLayer {
+    type Service = ServerTimingService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ServerTimingService {
+            inner,
+            enabled: self.enabled,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerTimingService<S> {
+    inner: S,
+    enabled: bool,
+}
+
+impl<S, ReqBody, ResBody> Service<hyper::Request<ReqBody>> for ServerTimingService<S>
+where
+    S: Service<hyper::Request<ReqBody>, Response = hyper::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: hyper::Request<ReqBody>) -> Self::Future {
+        let response = self.inner.call(request);
+        if !self.enabled {
+            return Box::pin(response);
+        }
+
+        let started = Instant::now();
+        let timing = Arc::new(RequestTiming::default());
+        Box::pin(REQUEST_TIMING.scope(Arc::clone(&timing), async move {
+            let mut response = response.await?;
+            if let Some(value) = header_value(&timing, started.elapsed()) {
+                response.headers_mut().insert(SERVER_TIMING_HEADER, value);
+            }
+            Ok(response)
+        }))
+    }
+}
@@ -0,0 +1,261 @@
+use chrono::DateTime;
+use hyper::header::{HeaderMap, HeaderValue};
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::server::MethodResponse;
+use jsonrpsee::types::{ErrorObject, Request};
+use jsonrpsee::ResponsePayload;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tower::{Layer, Service};
+
+/// Request header selecting how timestamps are serialized, e.g.
+/// `Timestamp-Format: epoch_millis`
+pub const TIMESTAMP_FORMAT_HEADER: &str = "timestamp-format";
+/// Query parameter alternative to [`TIMESTAMP_FORMAT_HEADER`], e.g.
+/// `?timestamp_format=epoch_millis`; the header wins when both are sent
+pub const TIMESTAMP_FORMAT_PARAM: &str = "timestamp_format";
+
+/// JSON-RPC error code for calls asking for a timestamp format the service
+/// does not know
+pub const UNSUPPORTED_TIMESTAMP_FORMAT_CODE: i32 = -32017;
+
+const SUPPORTED_FORMATS: [&str; 3] = ["rfc3339", "epoch_seconds", "epoch_millis"];
+
+#[derive(Error, Debug, Clone)]
+#[error("Unsupported timestamp format '{0}', expected rfc3339, epoch_seconds or epoch_millis")]
+pub struct UnsupportedTimestampFormat(pub String);
+
+/// How timestamps such as `created_at` and `updated_at` appear in results.
+///
+/// Models always serialize as RFC 3339; other formats are produced by
+/// rewriting every RFC 3339 date-time string in a successful result, so
+/// each model gets the same treatment without per-field serializers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// `"2024-05-01T12:00:00.123456789Z"`, as stored
+    Rfc3339,
+    /// Whole seconds since the Unix epoch: `1714564800`
+    EpochSeconds,
+    /// Milliseconds since the Unix epoch: `1714564800123`
+    EpochMillis,
+}
+
+impl TimestampFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimestampFormat::Rfc3339 => "rfc3339",
+            TimestampFormat::EpochSeconds => "epoch_seconds",
+            TimestampFormat::EpochMillis => "epoch_millis",
+        }
+    }
+
+    /// Reads `API_TIMESTAMP_FORMAT`, the format served to clients that do
+    /// not ask for one (default rfc3339)
+    pub fn default_from_env() -> Result<Self, UnsupportedTimestampFormat> {
+        match std::env::var("API_TIMESTAMP_FORMAT") {
+            Ok(value) => value.parse(),
+            Err(_) => Ok(TimestampFormat::Rfc3339),
+        }
+    }
+
+    /// Rewrites the timestamps in a result serialized from the models
+    pub fn serialize_result(&self, result: Value) -> Value {
+        match self {
+            TimestampFormat::Rfc3339 => result,
+            format => timestamps_as_epoch(result, *format),
+        }
+    }
+}
+
+impl FromStr for TimestampFormat {
+    type Err = UnsupportedTimestampFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "rfc3339" | "iso8601" => Ok(TimestampFormat::Rfc3339),
+            "epoch_seconds" | "seconds" | "unix" => Ok(TimestampFormat::EpochSeconds),
+            "epoch_millis" | "millis" | "unix_millis" => Ok(TimestampFormat::EpochMillis),
+            _ => Err(UnsupportedTimestampFormat(s.to_string())),
+        }
+    }
+}
+
+/// The format a client asked for through [`TIMESTAMP_FORMAT_HEADER`] or the
+/// [`TIMESTAMP_FORMAT_PARAM`] query parameter, unparsed
+pub fn requested_format(headers: &HeaderMap, query: Option<&str>) -> Option<String> {
+    if let Some(value) = headers.get(TIMESTAMP_FORMAT_HEADER) {
+        return value.to_str().ok().map(str::to_string);
+    }
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == TIMESTAMP_FORMAT_PARAM)
+        .map(|(_, value)| value.to_string())
+}
+
+fn timestamps_as_epoch(value: Value, format: TimestampFormat) -> Value {
+    match value {
+        Value::String(s) => match DateTime::parse_from_rfc3339(&s) {
+            Ok(timestamp) if format == TimestampFormat::EpochMillis => {
+                Value::from(timestamp.timestamp_millis())
+            }
+            Ok(timestamp) => Value::from(timestamp.timestamp()),
+            Err(_) => Value::String(s),
+        },
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, timestamps_as_epoch(value, format)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| timestamps_as_epoch(item, format))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// The outcome of timestamp format negotiation, carried to the RPC
+/// middleware through the request extensions
+#[derive(Debug, Clone)]
+pub struct NegotiatedTimestampFormat(pub Result<TimestampFormat, UnsupportedTimestampFormat>);
+
+/// HTTP layer that negotiates the timestamp format and reports it in the
+/// `Timestamp-Format` response header
+#[derive(Debug, Clone)]
+pub struct TimestampFormatHeaderLayer {
+    default: TimestampFormat,
+}
+
+impl TimestampFormatHeaderLayer {
+    pub fn new(default: TimestampFormat) -> Self {
+        Self { default }
+    }
+}
+
+impl<S> Layer<S> for TimestampFormatHeaderLayer {
+    type Service = TimestampFormatHeaderService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimestampFormatHeaderService {
+            inner,
+            default: self.default,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimestampFormatHeaderService<S> {
+    inner: S,
+    default: TimestampFormat,
+}
+
+impl<S, B, RB> Service<hyper::Request<B>> for TimestampFormatHeaderService<S>
+where
+    S: Service<hyper::Request<B>, Response = hyper::Response<RB>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: hyper::Request<B>) -> Self::Future {
+        let negotiated = match requested_format(request.headers(), request.uri().query()) {
+            Some(requested) => requested.parse(),
+            None => Ok(self.default),
+        };
+        let served = negotiated.as_ref().ok().copied();
+        request
+            .extensions_mut()
+            .insert(NegotiatedTimestampFormat(negotiated));
+
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = response.await?;
+            if let Some(format) = served {
+                response.headers_mut().insert(
+                    TIMESTAMP_FORMAT_HEADER,
+                    HeaderValue::from_static(format.name()),
+                );
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Layer installing [`FormattedTimestamps`] in the JSON-RPC middleware stack
+#[derive(Debug, Clone, Default)]
+pub struct TimestampFormatLayer;
+
+impl<S> Layer<S> for TimestampFormatLayer {
+    type Service = FormattedTimestamps<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        FormattedTimestamps { service }
+    }
+}
+
+/// JSON-RPC middleware that rewrites the timestamps in each successful
+/// result into the negotiated format and rejects calls asking for an
+/// unknown one with [`UNSUPPORTED_TIMESTAMP_FORMAT_CODE`]. Errors are never
+/// rewritten.
+#[derive(Debug, Clone)]
+pub struct FormattedTimestamps<S> {
+    service: S,
+}
+
+impl<'a, S> RpcServiceT<'a> for FormattedTimestamps<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+    S::Future: 'a,
+{
+    type Future = Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let format = match request.extensions().get::<NegotiatedTimestampFormat>() {
+            Some(NegotiatedTimestampFormat(Ok(format))) => *format,
+            Some(NegotiatedTimestampFormat(Err(unsupported))) => {
+                let error = ErrorObject::owned(
+                    UNSUPPORTED_TIMESTAMP_FORMAT_CODE,
+                    "Unsupported timestamp format",
+                    Some(json!({ "requested": unsupported.0, "supported": SUPPORTED_FORMATS })),
+                );
+                return Box::pin(std::future::ready(MethodResponse::error(
+                    request.id(),
+                    error,
+                )));
+            }
+            None => TimestampFormat::Rfc3339,
+        };
+        if format == TimestampFormat::Rfc3339 {
+            return Box::pin(self.service.call(request));
+        }
+
+        let id = request.id().into_owned();
+        let call = self.service.call(request);
+        Box::pin(async move {
+            let response = call.await;
+            if !response.is_success() || !response.is_method_call() {
+                return response;
+            }
+            let Ok(mut envelope) = serde_json::from_str::<Value>(response.as_result()) else {
+                return response;
+            };
+
+            let result = format.serialize_result(envelope["result"].take());
+            let extensions = response.extensions().clone();
+            MethodResponse::response(id, ResponsePayload::success(result), usize::MAX)
+                .with_extensions(extensions)
+        })
+    }
+}
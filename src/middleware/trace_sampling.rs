@@ -0,0 +1,99 @@
+use crate::telemetry::log_policy::{with_sampling, TRACE_SAMPLED_HEADER};
+use futures::future::Either;
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::types::Request;
+use std::task::{Context, Poll};
+use tokio::task::futures::TaskLocalFuture;
+use tower::{Layer, Service};
+
+/// The sampling decision the gateway made for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceSampled(pub bool);
+
+/// HTTP layer that reads [`TRACE_SAMPLED_HEADER`] before the JSON-RPC
+/// layer runs
+#[derive(Debug, Clone, Default)]
+pub struct TraceSamplingHeaderLayer;
+
+impl<S> Layer<S> for TraceSamplingHeaderLayer {
+    type Service = TraceSamplingHeaderService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceSamplingHeaderService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceSamplingHeaderService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<hyper::Request<B>> for TraceSamplingHeaderService<S>
+where
+    S: Service<hyper::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: hyper::Request<B>) -> Self::Future {
+        let sampled = match request
+            .headers()
+            .get(TRACE_SAMPLED_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+        {
+            Some("1") => Some(true),
+            Some("0") => Some(false),
+            _ => None,
+        };
+        if let Some(sampled) = sampled {
+            request.extensions_mut().insert(TraceSampled(sampled));
+        }
+
+        self.inner.call(request)
+    }
+}
+
+/// Layer installing [`TraceSampling`] in the JSON-RPC middleware stack
+#[derive(Debug, Clone, Default)]
+pub struct TraceSamplingLayer;
+
+impl<S> Layer<S> for TraceSamplingLayer {
+    type Service = TraceSampling<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        TraceSampling { service }
+    }
+}
+
+/// JSON-RPC middleware that applies the gateway's sampling decision to a
+/// call, so its success logs are kept here exactly when they were kept at
+/// the gateway. Calls made directly to the service are sampled by its own
+/// log policy.
+#[derive(Debug, Clone)]
+pub struct TraceSampling<S> {
+    service: S,
+}
+
+impl<'a, S> RpcServiceT<'a> for TraceSampling<S>
+where
+    S: RpcServiceT<'a> + Send + Sync,
+    S::Future: 'a,
+{
+    type Future = Either<TaskLocalFuture<bool, S::Future>, S::Future>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        match request.extensions().get::<TraceSampled>() {
+            Some(TraceSampled(sampled)) => {
+                let sampled = *sampled;
+                Either::Left(with_sampling(sampled, self.service.call(request)))
+            }
+            None => Either::Right(self.service.call(request)),
+        }
+    }
+}
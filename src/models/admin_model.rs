@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetReadOnlyRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadOnlyStatus {
+    pub read_only: bool,
+}
+
+/// What the retention job purged for one rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionRuleReport {
+    pub rule: String,
+    /// Records older than this many days are purged; `None` keeps them
+    pub max_age_days: Option<u64>,
+    /// Records purged by the latest run
+    pub last_purged: u64,
+    pub total_purged: u64,
+    /// Why the latest run failed for this rule, if it did
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionReport {
+    /// `None` when the retention job is disabled
+    pub interval_secs: Option<u64>,
+    pub runs: u64,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub rules: Vec<RetentionRuleReport>,
+}
+
+/// How close a table or the whole database is to its storage caps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageLevel {
+    #[default]
+    Ok,
+    /// Past the soft cap: still accepting writes, with a warning logged
+    Soft,
+    /// At the hard cap: new records are rejected
+    Full,
+}
+
+/// One table's size at the latest measurement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableStorageReport {
+    pub table: String,
+    pub records: u64,
+    /// Length of the records serialized as text, a rough proxy for memory
+    pub approx_bytes: u64,
+    pub soft_cap_records: Option<u64>,
+    pub hard_cap_records: Option<u64>,
+    pub level: StorageLevel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageReport {
+    /// `None` when storage is not measured
+    pub interval_secs: Option<u64>,
+    pub measured_at: Option<DateTime<Utc>>,
+    pub approx_bytes: u64,
+    pub soft_cap_bytes: Option<u64>,
+    pub hard_cap_bytes: Option<u64>,
+    /// The worst level of the database and any table
+    pub level: StorageLevel,
+    /// Largest first
+    pub tables: Vec<TableStorageReport>,
+    /// Why the latest measurement failed, if it did
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLogSamplingRequest {
+    /// Percentage of successful requests logged, 0 to 100
+    pub success_percent: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSamplingStatus {
+    pub success_percent: u8,
+    /// Requests sampled because an admin asked for it with the debug header
+    pub forced_requests: u64,
+}
+
+/// The database a service is connected to, checked against its config and
+/// the other services'
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseIsolationReport {
+    pub service: String,
+    pub endpoint: String,
+    pub configured_namespace: String,
+    pub configured_database: String,
+    /// As the connection's session reports them
+    pub namespace: Option<String>,
+    pub database: Option<String>,
+    pub tables: Vec<String>,
+    /// Tables another service owns, found in this database
+    pub foreign_tables: Vec<String>,
+    /// Other services configured with the same database
+    pub shared_with: Vec<String>,
+    /// True when none of the problems below were found
+    pub isolated: bool,
+    pub problems: Vec<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// What deleting a record does to the records that refer to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletePolicy {
+    /// Refuse the delete while any exist
+    Block,
+    /// Delete them with the record
+    Cascade,
+    /// Keep them, flagged as referring to a deleted record
+    Orphan,
+}
+
+impl DeletePolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "block" => Some(DeletePolicy::Block),
+            "cascade" => Some(DeletePolicy::Cascade),
+            "orphan" => Some(DeletePolicy::Orphan),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeletePolicy::Block => "block",
+            DeletePolicy::Cascade => "cascade",
+            DeletePolicy::Orphan => "orphan",
+        }
+    }
+}
+
+impl std::fmt::Display for DeletePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Records referring to a record being deleted, and what the delete does
+/// with them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependentRecords {
+    pub dependent: String,
+    pub count: u64,
+    pub policy: DeletePolicy,
+}
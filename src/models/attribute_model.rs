@@ -0,0 +1,124 @@
+use crate::models::product_model::Product;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use surrealdb::sql::Thing;
+
+/// Type of a registered attribute's values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeType {
+    Text,
+    Number,
+    Boolean,
+}
+
+impl fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AttributeType::Text => "text",
+            AttributeType::Number => "number",
+            AttributeType::Boolean => "boolean",
+        })
+    }
+}
+
+/// A product attribute's value, sent and stored as a plain JSON string,
+/// number or boolean
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl AttributeValue {
+    pub fn value_type(&self) -> AttributeType {
+        match self {
+            AttributeValue::Boolean(_) => AttributeType::Boolean,
+            AttributeValue::Number(_) => AttributeType::Number,
+            AttributeValue::Text(_) => AttributeType::Text,
+        }
+    }
+}
+
+/// A registered attribute key, stored in the `attribute_definition` table.
+/// Products only take values for registered keys, of the registered type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeDefinition {
+    pub id: Thing,
+    pub key: String,
+    pub value_type: AttributeType,
+    /// Whether products can be searched by this attribute
+    pub indexed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeDefinitionForCreation {
+    pub key: String,
+    pub value_type: AttributeType,
+    pub indexed: bool,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One indexed attribute value, as mirrored into the `product_attribute`
+/// lookup table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductAttribute {
+    pub product_id: String,
+    pub key: String,
+    pub value: AttributeValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefineAttributeRequest {
+    /// Lowercase letters, digits and underscores, starting with a letter
+    pub key: String,
+    pub value_type: AttributeType,
+    /// Make the attribute searchable with `search_products_by_attributes`
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListAttributesResponse {
+    /// Sorted by key
+    pub attributes: Vec<AttributeDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetAttributeRequest {
+    pub product_id: String,
+    pub key: String,
+    pub value: AttributeValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveAttributeRequest {
+    pub product_id: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchProductsByAttributesRequest {
+    /// Indexed attributes and the values products must have, all of them
+    pub attributes: BTreeMap<String, AttributeValue>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchProductsByAttributesResponse {
+    /// Sorted by name
+    pub products: Vec<Product>,
+    /// Matching products, including those past `limit`
+    pub total: usize,
+}
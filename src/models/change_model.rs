@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What happened to a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A change to a record seen by a live query. Carries the record id only,
+/// never its contents, so it is safe to hand to any subscriber.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    pub table: String,
+    /// Record id, e.g. `product:abc123`
+    pub id: String,
+    pub action: ChangeAction,
+    pub observed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscribeChangesRequest {
+    /// Only send changes to these tables; all of the service's when empty
+    #[serde(default)]
+    pub tables: Vec<String>,
+}
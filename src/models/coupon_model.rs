@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscountType {
+    /// `value` percent off the eligible amount
+    Percent,
+    /// `value` off the eligible amount, never more than the amount itself
+    Fixed,
+}
+
+/// Restrictions on where a coupon applies. Empty lists mean no restriction.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CouponConstraints {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_order_total: Option<f64>,
+    /// Only items in these categories count towards the discount
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Only these products count towards the discount
+    #[serde(default)]
+    pub product_ids: Vec<String>,
+}
+
+impl CouponConstraints {
+    pub fn restricts_items(&self) -> bool {
+        !self.categories.is_empty() || !self.product_ids.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Coupon {
+    pub id: Thing,
+    /// Stored upper-cased; lookups are case-insensitive
+    pub code: String,
+    pub discount_type: DiscountType,
+    pub value: f64,
+    #[serde(default)]
+    pub constraints: CouponConstraints,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// `None` for unlimited redemptions
+    #[serde(default)]
+    pub max_redemptions: Option<u32>,
+    pub redemptions: u32,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CouponForCreation {
+    pub code: String,
+    pub discount_type: DiscountType,
+    pub value: f64,
+    pub constraints: CouponConstraints,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_redemptions: Option<u32>,
+    pub redemptions: u32,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCouponRequest {
+    pub code: String,
+    pub discount_type: DiscountType,
+    pub value: f64,
+    #[serde(default)]
+    pub constraints: CouponConstraints,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub max_redemptions: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCouponResponse {
+    pub id: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// One cart line, as priced by the caller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckoutItem {
+    pub product_id: String,
+    pub category: String,
+    /// Line total (unit price times quantity)
+    pub amount: f64,
+}
+
+/// The checkout a coupon is applied to. `items` is only needed for coupons
+/// restricted to categories or products.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CouponCheckout {
+    pub code: String,
+    pub order_total: f64,
+    #[serde(default)]
+    pub items: Vec<CheckoutItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidateCouponResponse {
+    pub code: String,
+    pub valid: bool,
+    pub discount: f64,
+    pub total_after_discount: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedeemCouponRequest {
+    #[serde(flatten)]
+    pub checkout: CouponCheckout,
+    /// Caller's order reference, for logs
+    #[serde(default)]
+    pub order_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedeemCouponResponse {
+    pub code: String,
+    pub discount: f64,
+    pub total_after_discount: f64,
+    pub redemptions: u32,
+    /// `None` for unlimited coupons
+    pub redemptions_remaining: Option<u32>,
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A client-side event to record in the service logs, usually sent as a
+/// JSON-RPC notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEventRequest {
+    pub event: String,
+    /// `debug`, `info` (default), `warn` or `error`
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub fields: Value,
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+/// A runtime switch for a new behavior, stored in the `feature_flag` table
+/// under its key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureFlag {
+    pub id: Thing,
+    pub key: String,
+    #[serde(default)]
+    pub description: String,
+    /// Master switch: a disabled flag is off for everyone
+    pub enabled: bool,
+    /// Share of callers (0-100) the flag is on for, picked by a stable hash
+    /// of the flag key and user id; `100` is a plain boolean flag
+    pub rollout_percent: u8,
+    /// Tenants the flag is always on for while enabled
+    #[serde(default)]
+    pub tenants: Vec<String>,
+    /// Users the flag is always on for while enabled
+    #[serde(default)]
+    pub users: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureFlagForCreation {
+    pub key: String,
+    pub description: String,
+    pub enabled: bool,
+    pub rollout_percent: u8,
+    pub tenants: Vec<String>,
+    pub users: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Who a flag is evaluated for
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlagContext {
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetFeatureFlagRequest {
+    pub key: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Defaults to 100, on for everyone
+    #[serde(default)]
+    pub rollout_percent: Option<u8>,
+    #[serde(default)]
+    pub tenants: Vec<String>,
+    #[serde(default)]
+    pub users: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluateFeatureFlagRequest {
+    pub key: String,
+    #[serde(default)]
+    pub context: FlagContext,
+}
+
+/// Why a flag evaluated the way it did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagReason {
+    /// No flag with this key; unknown flags are off
+    Unknown,
+    Disabled,
+    TargetedUser,
+    TargetedTenant,
+    /// Inside the rollout percentage
+    Rollout,
+    /// Outside the rollout percentage, or no user id to place
+    NotInRollout,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlagEvaluation {
+    pub key: String,
+    pub enabled: bool,
+    pub reason: FlagReason,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListFeatureFlagsResponse {
+    pub flags: Vec<FeatureFlag>,
+    pub total: usize,
+}
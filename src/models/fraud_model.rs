@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+/// One accepted signup in the `signup_attempt` table, counted by the
+/// velocity rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignupAttemptForCreation {
+    pub email_domain: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<String>,
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// A signup stopped by an anti-fraud rule, kept in `fraud_hit` for review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FraudHit {
+    pub id: Thing,
+    /// `disposable_domain`, `domain_velocity` or `ip_velocity`
+    pub rule: String,
+    /// `rejected` or `rate_limited`
+    pub decision: String,
+    pub email_domain: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FraudHitForCreation {
+    pub rule: String,
+    pub decision: String,
+    pub email_domain: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListFraudHitsRequest {
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListFraudHitsResponse {
+    /// Most recent first
+    pub hits: Vec<FraudHit>,
+    pub total: usize,
+}
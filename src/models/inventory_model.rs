@@ -0,0 +1,312 @@
+use crate::models::product_model::Product;
+use crate::models::quantity_model::Quantity;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use surrealdb::sql::Thing;
+
+/// Location that holds stock for products created before per-location
+/// tracking, and the default target of `update_product_stock`. It always
+/// exists and is not stored in the `location` table.
+pub const DEFAULT_LOCATION: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    pub id: Thing,
+    /// Short unique identifier used in stock requests, e.g. `ams-1`
+    pub code: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationForCreation {
+    pub code: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One product's stock at one location, stored as `stock:<product>__<location>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockLevel {
+    pub id: Thing,
+    pub product_id: String,
+    pub location: String,
+    pub quantity: Quantity,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationStock {
+    pub location: String,
+    pub quantity: Quantity,
+}
+
+/// `get_product` result: the product, whose `stock_quantity` is the total
+/// across locations, plus the per-location breakdown and the locale its
+/// content is in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductDetails {
+    #[serde(flatten)]
+    pub product: Product,
+    pub availability: Vec<LocationStock>,
+    /// Locale the name is served in
+    pub locale: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateLocationRequest {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateLocationResponse {
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListLocationsResponse {
+    pub locations: Vec<Location>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferStockRequest {
+    pub product_id: String,
+    pub from: String,
+    pub to: String,
+    pub quantity: Quantity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferStockResponse {
+    pub product_id: String,
+    /// Stock at both locations after the transfer
+    pub from: LocationStock,
+    pub to: LocationStock,
+}
+
+/// A product's recorded total, for cross-checking against its stock rows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductStockTotal {
+    pub id: Thing,
+    pub stock_quantity: Quantity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// Stock below zero at a location or in total
+    Oversold,
+    /// The product's `stock_quantity` differs from the sum of its rows
+    TotalMismatch,
+    /// A stock row for a product that no longer exists
+    OrphanedStock,
+    /// A stock row at a location that does not exist
+    UnknownLocation,
+    /// Orders waiting to ship need more than the product has in stock
+    Overcommitted,
+    /// An order line for a product that no longer exists
+    OrphanedOrderLine,
+    /// Shipped orders hold more than stock adjustments ever took out
+    UnrecordedShipment,
+    /// Completed returns took back a different quantity than the ledger
+    /// restocked
+    RestockMismatch,
+}
+
+/// Something the stock reconciler found that the stock pipeline should
+/// never produce
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockDiscrepancy {
+    pub kind: DiscrepancyKind,
+    pub product_id: String,
+    /// Set for discrepancies in a single stock row
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// What the stock rows, orders or returns say, when they disagree
+    /// with `actual`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Quantity>,
+    pub actual: Quantity,
+}
+
+/// What orders, returns and the `stock_movement` ledger say about each
+/// product, keyed by product id, for the order-side reconciliation checks
+#[derive(Debug, Clone, Default)]
+pub struct OrderActivity {
+    /// Ordered by completed orders that have not shipped yet
+    pub awaiting_shipment: HashMap<String, Quantity>,
+    /// Ordered by orders that have shipped, returned or not
+    pub shipped: HashMap<String, Quantity>,
+    /// Taken out of stock by adjustments
+    pub removed: HashMap<String, Quantity>,
+    /// Taken back by completed returns
+    pub returned: HashMap<String, Quantity>,
+    /// Put back into stock by return movements
+    pub restocked: HashMap<String, Quantity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconcileStockRequest {
+    /// Check the stock now instead of returning the last scheduled report
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockReconciliationReport {
+    pub checked_at: DateTime<Utc>,
+    pub products_checked: usize,
+    pub stock_rows_checked: usize,
+    pub discrepancies: Vec<StockDiscrepancy>,
+}
+
+/// Why a stock row changed, as recorded in `stock_movement`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MovementKind {
+    /// `update_product_stock` or `update_stock_bulk` set a new quantity:
+    /// sales and write-offs lower it, deliveries raise it
+    Adjustment,
+    /// Stock moved to another location by `transfer_stock`
+    TransferOut,
+    /// Stock moved here from another location by `transfer_stock`
+    TransferIn,
+    /// Returned goods restocked by `complete_return`
+    Return,
+}
+
+/// One entry in the `stock_movement` ledger, written in the same
+/// transaction as the stock change it records
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockMovement {
+    pub product_id: String,
+    pub location: String,
+    pub kind: MovementKind,
+    /// Units added (positive) or removed (negative)
+    pub change: Quantity,
+    /// Stock at the location after the movement
+    pub quantity_after: Quantity,
+    /// Why the stock was set, as given to `update_stock_bulk`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub moved_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkStockItem {
+    /// The product's SKU, as warehouse feeds know it
+    #[serde(default)]
+    pub sku: Option<String>,
+    /// The product's id, for products without a SKU; send one or the other
+    #[serde(default)]
+    pub product_id: Option<String>,
+    /// New stock at the location, in the product's unit
+    pub quantity: Quantity,
+    /// Recorded with the change in the `stock_movement` ledger
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Location whose stock is set; defaults to the `default` location
+    #[serde(default)]
+    pub location: Option<String>,
+    /// Stock the sender last saw at the location; the item is a conflict
+    /// when the location holds something else
+    #[serde(default)]
+    pub expected: Option<Quantity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStockBulkRequest {
+    pub items: Vec<BulkStockItem>,
+    /// Apply nothing unless every item can be applied
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkStockStatus {
+    Applied,
+    /// The location's stock differs from the item's `expected`
+    Conflict,
+    /// The item is invalid, or its product or location does not exist
+    Failed,
+    /// Valid, but not applied because another item of an `atomic`
+    /// request was not
+    Skipped,
+}
+
+/// The outcome of one `update_stock_bulk` item, in request order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkStockResult {
+    /// As sent in the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    /// The product the item was resolved to; missing for an unknown SKU
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_id: Option<String>,
+    pub location: String,
+    pub status: BulkStockStatus,
+    /// Stock at the location before the update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Quantity>,
+    /// Stock at the location after the update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStockBulkResponse {
+    pub total: usize,
+    pub applied: usize,
+    pub conflicts: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub results: Vec<BulkStockResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastStockRequest {
+    pub product_id: String,
+    /// How far ahead to project, in days
+    pub horizon_days: u32,
+}
+
+/// Projected stock at one location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationForecast {
+    pub location: String,
+    pub current_stock: Quantity,
+    /// Units consumed per day over the observed period
+    pub average_daily_consumption: f64,
+    /// Stock left at the end of the horizon at that rate, never below zero
+    pub projected_stock: Quantity,
+    /// When the stock runs out at that rate; `None` without consumption
+    pub predicted_stock_out: Option<DateTime<Utc>>,
+}
+
+/// `forecast_stock` result. Consumption is the average of the decreases
+/// made by stock adjustments over the observed period; deliveries and
+/// transfers between locations are not consumption.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockForecast {
+    pub product_id: String,
+    pub horizon_days: u32,
+    /// Days of ledger history the averages are taken over
+    pub observed_days: f64,
+    pub current_stock: Quantity,
+    pub average_daily_consumption: f64,
+    pub projected_stock: Quantity,
+    pub predicted_stock_out: Option<DateTime<Utc>>,
+    /// Whether the product runs out before the end of the horizon, i.e.
+    /// needs replenishing now
+    pub stock_out_within_horizon: bool,
+    /// Per location, sorted by location code
+    pub locations: Vec<LocationForecast>,
+    pub generated_at: DateTime<Utc>,
+}
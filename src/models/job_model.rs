@@ -0,0 +1,162 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use surrealdb::sql::Thing;
+
+/// Heavy operations that run as background jobs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// `import_products_csv`; params are its request
+    ImportProductsCsv,
+    /// The whole catalog, as `export_products` pages would return it;
+    /// params are [`ExportJobParams`]
+    ExportProducts,
+    /// Rebuilds the attribute lookup table and the recommendations; no
+    /// params
+    Reindex,
+}
+
+impl fmt::Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JobKind::ImportProductsCsv => "import_products_csv",
+            JobKind::ExportProducts => "export_products",
+            JobKind::Reindex => "reindex",
+        })
+    }
+}
+
+/// `queued` -> `running` -> `succeeded` or `failed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Succeeded | JobStatus::Failed)
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+        })
+    }
+}
+
+/// Units of work done out of the total, in whatever the job counts: CSV
+/// rows, exported products or reindex steps. `total` is 0 until known.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// A job as stored in the `job` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: Thing,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default)]
+    pub progress: JobProgress,
+    /// What the operation returns when run directly, once `succeeded`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Why the job `failed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobForCreation {
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub params: Value,
+    pub progress: JobProgress,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartJobRequest {
+    pub kind: JobKind,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportJobParams {
+    /// Products read per page (default and at most 1000)
+    #[serde(default)]
+    pub page_size: Option<usize>,
+}
+
+/// `reindex` job result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexReport {
+    /// Rows written to the `product_attribute` lookup table
+    pub attribute_rows: usize,
+    /// Rows written to the `product_co_occurrence` table
+    pub co_occurrence_rows: usize,
+    /// Categories rebuilt in the `category_rollup` table
+    #[serde(default)]
+    pub rollup_categories: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobIdRequest {
+    pub job_id: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscribeJobEventsRequest {
+    /// Only this job's events; every job's without it
+    #[serde(default)]
+    pub job_id: Option<String>,
+}
+
+/// Sent to `subscribe_job_events` subscribers whenever a job is queued,
+/// starts, progresses or finishes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobEvent {
+    pub job_id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub progress: JobProgress,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+impl JobEvent {
+    pub fn for_job(job: &Job) -> Self {
+        Self {
+            job_id: job.id.id.to_raw(),
+            kind: job.kind,
+            status: job.status,
+            progress: job.progress,
+            error: job.error.clone(),
+            at: Utc::now(),
+        }
+    }
+}
@@ -1,2 +1,16 @@
 pub mod user_model;
 pub mod product_model;
+pub mod admin_model;
+pub mod address_model;
+pub mod event_model;
+pub mod coupon_model;
+pub mod inventory_model;
+pub mod fraud_model;
+pub mod feature_flag_model;
+pub mod change_model;
+pub mod organization_model;
+pub mod recommendation_model;
+pub mod quantity_model;
+pub mod return_model;
+pub mod attribute_model;
+pub mod job_model;
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+/// What a member may do in an organization. Every organization keeps at
+/// least one owner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Owner,
+    Admin,
+    #[default]
+    Member,
+}
+
+impl OrgRole {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OrgRole::Owner => "owner",
+            OrgRole::Admin => "admin",
+            OrgRole::Member => "member",
+        }
+    }
+}
+
+/// A B2B customer account whose users are linked to it by `member_of`
+/// graph edges (`user->member_of->organization`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Organization {
+    pub id: Thing,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizationForCreation {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One user's membership, read from its `member_of` edge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrgMember {
+    pub user_id: String,
+    pub name: String,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateOrgRequest {
+    pub name: String,
+    /// Existing user who becomes the organization's first owner
+    pub owner_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateOrgResponse {
+    pub id: String,
+    pub organization: Organization,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddMemberRequest {
+    pub org_id: String,
+    pub user_id: String,
+    /// Defaults to `member`; adding an existing member changes their role
+    #[serde(default)]
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveMemberRequest {
+    pub org_id: String,
+    pub user_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipResponse {
+    pub org_id: String,
+    pub user_id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOrgMembersRequest {
+    pub org_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOrgMembersResponse {
+    pub organization: Organization,
+    /// Earliest joined first
+    pub members: Vec<OrgMember>,
+    pub total: usize,
+}
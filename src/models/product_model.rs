@@ -1,5 +1,8 @@
+use crate::models::attribute_model::AttributeValue;
+use crate::models::quantity_model::{Quantity, StockUnit};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use surrealdb::sql::Thing;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,24 +12,55 @@ pub struct Product {
     pub description: String,
     pub price: f64,
     pub category: String,
-    pub stock_quantity: i32,
+    /// Stock keeping unit warehouse feeds know the product by, unique
+    /// across the catalog
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    pub stock_quantity: Quantity,
+    /// What `stock_quantity` counts
+    #[serde(default)]
+    pub unit: StockUnit,
+    /// Name and description per locale (BCP 47 tag); `name` and
+    /// `description` above are in the default locale
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub translations: BTreeMap<String, ProductTranslation>,
+    /// Organizations that can see the product; empty for a public product
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub visible_to: Vec<String>,
+    /// Values of registered attributes, by key
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, AttributeValue>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// A product's content in one locale. Missing fields fall back to a less
+/// specific locale, then to the default one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProductTranslation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProductForCreation {
     pub name: String,
     pub description: String,
     pub price: f64,
     pub category: String,
-    pub stock_quantity: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    pub stock_quantity: Quantity,
+    pub unit: StockUnit,
+    pub visible_to: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
 impl Product {
-    pub fn new(name: String, description: String, price: f64, category: String, stock_quantity: i32) -> Self {
+    pub fn new(name: String, description: String, price: f64, category: String, stock_quantity: Quantity, unit: StockUnit) -> Self {
         let now = Utc::now();
         Self {
             id: Thing::from(("product", "temp")), // Will be replaced by SurrealDB
@@ -34,7 +68,12 @@ impl Product {
             description,
             price,
             category,
+            sku: None,
             stock_quantity,
+            unit,
+            translations: BTreeMap::new(),
+            visible_to: Vec::new(),
+            attributes: BTreeMap::new(),
             created_at: now,
             updated_at: now,
         }
@@ -46,7 +85,10 @@ impl Product {
             description: self.description.clone(),
             price: self.price,
             category: self.category.clone(),
+            sku: self.sku.clone(),
             stock_quantity: self.stock_quantity,
+            unit: self.unit,
+            visible_to: self.visible_to.clone(),
             created_at: self.created_at,
             updated_at: self.updated_at,
         }
@@ -55,6 +97,12 @@ impl Product {
     pub fn id_string(&self) -> String {
         self.id.to_string()
     }
+
+    /// Public products are visible to everyone, restricted ones only to
+    /// callers from a listed organization
+    pub fn is_visible_to(&self, org: Option<&str>) -> bool {
+        self.visible_to.is_empty() || org.is_some_and(|org| self.visible_to.iter().any(|o| o == org))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,33 +111,397 @@ pub struct CreateProductRequest {
     pub description: String,
     pub price: f64,
     pub category: String,
-    pub stock_quantity: i32,
+    /// Unique stock keeping unit, for warehouse feeds
+    #[serde(default)]
+    pub sku: Option<String>,
+    /// Whole pieces, or kilograms or liters to three decimals
+    pub stock_quantity: Quantity,
+    /// Piece (default), kg or liter
+    #[serde(default)]
+    pub unit: StockUnit,
+    /// Fail when an existing product's name is at least this similar
+    /// (0-1, as reported by `find_similar_products`)
+    #[serde(default)]
+    pub max_similarity: Option<f64>,
+    /// Run every check, name lookups included, without creating the product
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Organizations the product is restricted to; empty or missing for a
+    /// public product
+    #[serde(default)]
+    pub visible_to: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateProductResponse {
+    /// Empty for a dry run
     pub id: String,
     pub message: String,
+    /// Set when nothing was written
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindSimilarProductsRequest {
+    pub name: String,
+    /// Lowest similarity reported, 0-1 (default 0.5)
+    #[serde(default)]
+    pub threshold: Option<f64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarProduct {
+    pub id: String,
+    pub name: String,
+    /// Share of name trigrams in common, 1 for the same normalized name
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindSimilarProductsResponse {
+    pub products: Vec<SimilarProduct>,
+}
+
+/// A product's id and name, for comparing names across the catalog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductName {
+    pub id: Thing,
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetProductRequest {
     pub id: String,
+    /// Serve name and description in this locale, e.g. `fr-CA`
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetTranslationRequest {
+    pub product_id: String,
+    pub locale: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetProductVisibilityRequest {
+    pub product_id: String,
+    /// Organizations that can see the product; empty makes it public
+    #[serde(default)]
+    pub visible_to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetProductSkuRequest {
+    pub product_id: String,
+    /// Missing or null removes the product's SKU
+    #[serde(default)]
+    pub sku: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateProductStockRequest {
     pub id: String,
-    pub quantity: i32,
+    /// In the product's unit
+    pub quantity: Quantity,
+    /// Location whose stock is set; defaults to the `default` location
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+/// Fields `list_products` can sort by. Each has an index, and ties are
+/// broken by id so pages never shift between calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductSortField {
+    #[default]
+    CreatedAt,
+    Name,
+    Price,
+    StockQuantity,
+    Category,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListProductsRequest {
+    #[serde(default)]
+    pub sort_by: ProductSortField,
+    /// Defaults to newest first for `created_at` and ascending otherwise
+    #[serde(default)]
+    pub sort_dir: Option<SortDirection>,
+    /// Page size; without it or a cursor every product is returned
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page, with the same sort
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+impl ListProductsRequest {
+    pub fn direction(&self) -> SortDirection {
+        match (self.sort_dir, self.sort_by) {
+            (Some(direction), _) => direction,
+            (None, ProductSortField::CreatedAt) => SortDirection::Desc,
+            (None, _) => SortDirection::Asc,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListProductsResponse {
     pub products: Vec<Product>,
     pub total: usize,
+    /// Signed cursor for the next page, when paging and more remain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Position in a `list_products` listing, handed to clients signed as an
+/// opaque cursor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductPageCursor {
+    pub sort_by: ProductSortField,
+    pub sort_dir: SortDirection,
+    pub offset: usize,
+    /// Digest of the caller's organization, which decides what is listed
+    pub filter: String,
+}
+
+/// Stock of the products counted in one unit. Kilograms, liters and
+/// pieces do not add up, so stock is only ever totalled per unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitStock {
+    pub unit: StockUnit,
+    pub products: usize,
+    pub total_stock: Quantity,
+    /// Sum of price times stock
+    pub inventory_value: f64,
+}
+
+/// Catalog aggregates for one category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryStats {
+    pub category: String,
+    pub products: usize,
+    /// Sum of price times stock, over every unit
+    pub inventory_value: f64,
+    pub average_price: f64,
+    pub out_of_stock: usize,
+    /// Sorted by unit; units without products are left out
+    #[serde(default)]
+    pub stock_by_unit: Vec<UnitStock>,
+}
+
+/// `get_product_stats` result: catalog-wide aggregates plus one entry per category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductStats {
+    pub total_products: usize,
+    /// Sum of price times stock, over every unit
+    pub inventory_value: f64,
+    pub average_price: f64,
+    pub out_of_stock: usize,
+    /// Sorted by unit; units without products are left out
+    pub stock_by_unit: Vec<UnitStock>,
+    pub categories: Vec<CategoryStats>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Running totals for one category, updated by the database with every
+/// product write instead of aggregated on request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryRollup {
+    pub category: String,
+    pub products: usize,
+    /// Sum of price times stock, over every unit
+    pub inventory_value: f64,
+    /// Sorted by unit; units without products are left out
+    pub stock_by_unit: Vec<UnitStock>,
+    /// Last product write that changed the category
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `get_category_rollups` result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryRollupsResponse {
+    /// Sorted by category; categories without products are left out
+    pub categories: Vec<CategoryRollup>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetProductsByCategoryRequest {
     pub category: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportProductsRequest {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportProductsResponse {
+    pub products: Vec<Product>,
+    pub offset: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProductsCsvRequest {
+    /// CSV with a header row naming name, description, price, category and stock_quantity
+    pub csv: String,
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowReport {
+    /// Line the row starts on in the CSV, counting the header as line 1
+    pub line: usize,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProductsCsvResponse {
+    pub total_rows: usize,
+    pub imported: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRowReport>,
+}
+
+/// One entry in the `price_history` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceChange {
+    pub id: Thing,
+    pub product_id: String,
+    /// `None` for the price a product was created with
+    pub old_price: Option<f64>,
+    pub new_price: f64,
+    /// What changed the price: `created`, `imported` or `scheduled`
+    pub source: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceChangeForCreation {
+    pub product_id: String,
+    pub old_price: Option<f64>,
+    pub new_price: f64,
+    pub source: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// A price change waiting in the `scheduled_price_change` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledPriceChange {
+    pub id: Thing,
+    pub product_id: String,
+    pub new_price: f64,
+    pub effective_at: DateTime<Utc>,
+    /// `pending` until the scheduler applies it, then `applied`
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledPriceChangeForCreation {
+    pub product_id: String,
+    pub new_price: f64,
+    pub effective_at: DateTime<Utc>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulePriceChangeRequest {
+    pub product_id: String,
+    pub new_price: f64,
+    /// RFC 3339 timestamp; changes already due are applied on the next scheduler run
+    pub effective_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulePriceChangeResponse {
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPriceHistoryRequest {
+    pub product_id: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceHistoryResponse {
+    pub product_id: String,
+    pub current_price: f64,
+    /// Most recent first
+    pub changes: Vec<PriceChange>,
+    /// Scheduled changes not applied yet, soonest first
+    pub pending: Vec<ScheduledPriceChange>,
+}
+
+/// Product feed formats for marketing integrations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    /// Google Merchant RSS 2.0
+    #[default]
+    Xml,
+    Csv,
+}
+
+impl FeedFormat {
+    pub const ALL: [FeedFormat; 2] = [FeedFormat::Xml, FeedFormat::Csv];
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Xml => "application/xml",
+            FeedFormat::Csv => "text/csv",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FeedFormat::Xml => "xml",
+            FeedFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateFeedRequest {
+    #[serde(default)]
+    pub format: FeedFormat,
+    /// Rebuild from the catalog now instead of returning the last scheduled feed
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductFeed {
+    pub format: FeedFormat,
+    pub content_type: String,
+    pub generated_at: DateTime<Utc>,
+    pub product_count: usize,
+    pub content: String,
+}
@@ -0,0 +1,326 @@
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Thousandths per unit: the finest stock step is a gram or a milliliter
+const SCALE: i64 = 1000;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum QuantityError {
+    #[error("Invalid quantity '{0}'")]
+    Invalid(String),
+
+    #[error("Quantity '{0}' is finer than a thousandth of a unit")]
+    TooPrecise(String),
+
+    #[error("Quantity {quantity} is not a whole number of pieces")]
+    NotWhole { quantity: Quantity },
+
+    #[error("Unknown unit '{0}', expected piece, kg or liter")]
+    UnknownUnit(String),
+
+    #[error("Cannot convert '{text}' to {unit}")]
+    WrongUnit { text: String, unit: StockUnit },
+}
+
+/// What a product's stock is counted in. Products created before units
+/// were tracked count pieces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StockUnit {
+    #[default]
+    Piece,
+    /// Weighed goods, stocked to the gram
+    Kg,
+    /// Liquids, stocked to the milliliter
+    Liter,
+}
+
+impl fmt::Display for StockUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StockUnit::Piece => "piece",
+            StockUnit::Kg => "kg",
+            StockUnit::Liter => "liter",
+        })
+    }
+}
+
+impl FromStr for StockUnit {
+    type Err = QuantityError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "piece" => Ok(StockUnit::Piece),
+            "kg" => Ok(StockUnit::Kg),
+            "liter" => Ok(StockUnit::Liter),
+            _ => Err(QuantityError::UnknownUnit(text.to_string())),
+        }
+    }
+}
+
+impl StockUnit {
+    /// Whether stock may be a fraction of the unit
+    pub fn is_fractional(&self) -> bool {
+        !matches!(self, StockUnit::Piece)
+    }
+
+    /// Smallest unit quantities are converted to and from: grams,
+    /// milliliters or pieces
+    pub fn base_unit(&self) -> &'static str {
+        match self {
+            StockUnit::Piece => "piece",
+            StockUnit::Kg => "g",
+            StockUnit::Liter => "ml",
+        }
+    }
+
+    /// Fails for a fraction of a piece
+    pub fn validate(&self, quantity: Quantity) -> Result<(), QuantityError> {
+        if self.is_fractional() || quantity.is_whole() {
+            Ok(())
+        } else {
+            Err(QuantityError::NotWhole { quantity })
+        }
+    }
+
+    /// `quantity` in [`Self::base_unit`]s, e.g. 1.25 kg is 1250 g
+    pub fn to_base_units(&self, quantity: Quantity) -> i64 {
+        match self {
+            StockUnit::Piece => quantity.0 / SCALE,
+            StockUnit::Kg | StockUnit::Liter => quantity.0,
+        }
+    }
+
+    /// A quantity given in [`Self::base_unit`]s, e.g. 750 ml is 0.75 liter
+    pub fn from_base_units(&self, base_units: i64) -> Quantity {
+        match self {
+            StockUnit::Piece => Quantity::from_units(base_units),
+            StockUnit::Kg | StockUnit::Liter => Quantity(base_units),
+        }
+    }
+
+    /// Parses a quantity in this unit, bare (`1.5`) or with a unit of the
+    /// same kind (`250 g`, `2kg`, `750ml`, `3 pcs`), and validates it
+    pub fn parse_quantity(&self, text: &str) -> Result<Quantity, QuantityError> {
+        let trimmed = text.trim();
+        let split = trimmed
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(trimmed.len());
+        let (number, suffix) = trimmed.split_at(split);
+
+        let (unit, per_unit) = match suffix.trim().to_ascii_lowercase().as_str() {
+            "" => (*self, SCALE),
+            "kg" => (StockUnit::Kg, SCALE),
+            "g" => (StockUnit::Kg, 1),
+            "l" => (StockUnit::Liter, SCALE),
+            "ml" => (StockUnit::Liter, 1),
+            "pc" | "pcs" | "piece" | "pieces" => (StockUnit::Piece, SCALE),
+            _ => return Err(QuantityError::Invalid(text.to_string())),
+        };
+        if unit != *self {
+            return Err(QuantityError::WrongUnit {
+                text: text.to_string(),
+                unit: *self,
+            });
+        }
+
+        let quantity = parse_scaled(number.trim(), per_unit, text)?;
+        self.validate(quantity)?;
+        Ok(quantity)
+    }
+}
+
+/// `number` times `per_unit` thousandths, exactly
+fn parse_scaled(number: &str, per_unit: i64, text: &str) -> Result<Quantity, QuantityError> {
+    let invalid = || QuantityError::Invalid(text.to_string());
+    let (negative, digits) = match number.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, number),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        || whole.len() + fraction.len() > 18
+    {
+        return Err(invalid());
+    }
+
+    let mantissa: i128 = format!("{}{}", whole, fraction)
+        .parse()
+        .map_err(|_| invalid())?;
+    let divisor = 10i128.pow(fraction.len() as u32);
+    let scaled = mantissa * i128::from(per_unit);
+    if scaled % divisor != 0 {
+        return Err(QuantityError::TooPrecise(text.to_string()));
+    }
+    let thousandths = i64::try_from(scaled / divisor).map_err(|_| invalid())?;
+    Ok(Quantity(if negative { -thousandths } else { thousandths }))
+}
+
+/// An amount of stock as a fixed-point decimal with three places, so
+/// kilograms and liters add up exactly to the gram and milliliter.
+///
+/// Whole quantities are sent and stored as integers, as pieces always
+/// were, and fractional ones as numbers. Numbers read back are rounded to
+/// the nearest thousandth, which also drops float error the database may
+/// add when summing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Quantity(i64);
+
+impl Quantity {
+    pub const ZERO: Quantity = Quantity(0);
+
+    pub fn from_units(units: i64) -> Self {
+        Self(units.saturating_mul(SCALE))
+    }
+
+    /// Rounds to the nearest thousandth; `None` for NaN, infinities and
+    /// values out of range
+    pub fn from_f64(value: f64) -> Option<Self> {
+        let thousandths = (value * SCALE as f64).round();
+        (thousandths.is_finite() && thousandths.abs() < i64::MAX as f64)
+            .then_some(Self(thousandths as i64))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+
+    pub fn is_whole(self) -> bool {
+        self.0 % SCALE == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn abs(self) -> Self {
+        Self(self.0.saturating_abs())
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let (whole, fraction) = (
+            self.0.unsigned_abs() / SCALE as u64,
+            self.0.unsigned_abs() % SCALE as u64,
+        );
+        if fraction == 0 {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            let fraction = format!("{:03}", fraction);
+            write!(f, "{}{}.{}", sign, whole, fraction.trim_end_matches('0'))
+        }
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = QuantityError;
+
+    /// Parses a plain decimal such as `12` or `0.375`, exactly
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_scaled(text.trim(), SCALE, text)
+    }
+}
+
+impl Add for Quantity {
+    type Output = Quantity;
+
+    fn add(self, other: Quantity) -> Quantity {
+        Quantity(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Quantity {
+    type Output = Quantity;
+
+    fn sub(self, other: Quantity) -> Quantity {
+        Quantity(self.0.saturating_sub(other.0))
+    }
+}
+
+impl AddAssign for Quantity {
+    fn add_assign(&mut self, other: Quantity) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Quantity {
+    fn sub_assign(&mut self, other: Quantity) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Quantity {
+    type Output = Quantity;
+
+    fn neg(self) -> Quantity {
+        Quantity(self.0.saturating_neg())
+    }
+}
+
+impl Sum for Quantity {
+    fn sum<I: Iterator<Item = Quantity>>(iter: I) -> Quantity {
+        iter.fold(Quantity::ZERO, Add::add)
+    }
+}
+
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_whole() {
+            serializer.serialize_i64(self.0 / SCALE)
+        } else {
+            serializer.serialize_f64(self.to_f64())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct QuantityVisitor;
+
+        impl Visitor<'_> for QuantityVisitor {
+            type Value = Quantity;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a number or decimal string")
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Quantity, E> {
+                value
+                    .checked_mul(SCALE)
+                    .map(Quantity)
+                    .ok_or_else(|| E::custom("quantity out of range"))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Quantity, E> {
+                i64::try_from(value)
+                    .map_err(|_| E::custom("quantity out of range"))
+                    .and_then(|value| self.visit_i64(value))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Quantity, E> {
+                Quantity::from_f64(value).ok_or_else(|| E::custom("quantity out of range"))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Quantity, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(QuantityVisitor)
+    }
+}
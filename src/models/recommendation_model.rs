@@ -0,0 +1,208 @@
+use crate::models::address_model::Address;
+use crate::models::quantity_model::Quantity;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use surrealdb::sql::Thing;
+
+/// One product of an order with what was paid for it; returns are capped
+/// and refunded by these
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderLine {
+    pub product_id: String,
+    /// In the product's unit
+    pub quantity: Quantity,
+    /// Price paid per unit
+    pub unit_price: f64,
+}
+
+/// Where a recorded order stands with its shipment and returns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    /// Placed and waiting to ship; nothing has come back yet
+    #[default]
+    Completed,
+    /// Handed to the carrier for a validated shipping address
+    Shipped,
+    /// Completed returns took back part of the order
+    PartiallyReturned,
+    /// Completed returns took back every line in full
+    Returned,
+}
+
+impl OrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Completed => "completed",
+            OrderStatus::Shipped => "shipped",
+            OrderStatus::PartiallyReturned => "partially_returned",
+            OrderStatus::Returned => "returned",
+        }
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A completed order as stored in `order_history`: which products were
+/// bought together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderForRecording {
+    /// Caller's order reference, for logs
+    pub order_id: Option<String>,
+    /// User who placed the order, for `list_orders_by_user`
+    pub user_id: Option<String>,
+    pub status: OrderStatus,
+    /// Distinct products in the order
+    pub product_ids: Vec<String>,
+    /// Quantities and prices of the products reported with them
+    pub lines: Vec<OrderLine>,
+    pub ordered_at: DateTime<Utc>,
+}
+
+/// A stored `order_history` row, as returns read it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedOrder {
+    pub id: Thing,
+    pub order_id: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub status: OrderStatus,
+    pub product_ids: Vec<String>,
+    /// Empty for orders recorded without quantities and prices
+    #[serde(default)]
+    pub lines: Vec<OrderLine>,
+    pub ordered_at: DateTime<Utc>,
+    /// Sum of the refunds of completed returns
+    #[serde(default)]
+    pub refunded_amount: f64,
+    /// Normalized address the order was shipped to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shipping_address: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shipped_at: Option<DateTime<Utc>>,
+}
+
+impl RecordedOrder {
+    pub fn line(&self, product_id: &str) -> Option<&OrderLine> {
+        self.lines.iter().find(|line| line.product_id == product_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordOrderRequest {
+    #[serde(default)]
+    pub order_id: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub product_ids: Vec<String>,
+    /// Products with their quantities and prices; needed for returns
+    #[serde(default)]
+    pub lines: Vec<OrderLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordOrderResponse {
+    /// Distinct products recorded for the order
+    pub product_count: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShipOrderRequest {
+    pub order_id: String,
+    /// Checked like `validate_address` on the user service
+    pub shipping_address: Address,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOrdersByUserRequest {
+    pub user_id: String,
+    #[serde(default)]
+    pub status: Option<OrderStatus>,
+    /// Orders placed at or after this time
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    /// Orders placed before this time
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+    /// Page size, 100 by default
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page, with the same filters
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOrdersResponse {
+    /// Newest first
+    pub orders: Vec<RecordedOrder>,
+    /// Orders matching the filters across all pages
+    pub total: usize,
+    /// Signed cursor for the next page, when more remain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CountOrdersByUserRequest {
+    pub user_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserOrderCount {
+    pub user_id: String,
+    /// Orders recorded for the user, whatever their status
+    pub count: u64,
+}
+
+/// Page state of a `list_orders_by_user` cursor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderPageCursor {
+    pub offset: usize,
+    /// Digest of the user and filters the listing was made with
+    pub filter: String,
+}
+
+/// How often two products were bought in the same order, one row per
+/// direction in the `product_co_occurrence` table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoOccurrence {
+    pub product_id: String,
+    pub other_id: String,
+    pub orders: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetRecommendedProductsRequest {
+    pub product_id: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecommendedProduct {
+    pub id: String,
+    pub name: String,
+    pub price: f64,
+    pub category: String,
+    /// Orders that contained both products
+    pub bought_together: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecommendedProductsResponse {
+    pub product_id: String,
+    /// Most often bought together first
+    pub products: Vec<RecommendedProduct>,
+    /// When the co-occurrence table was last rebuilt; `None` until the
+    /// first build since the service started
+    pub built_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,105 @@
+use crate::models::quantity_model::Quantity;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use surrealdb::sql::Thing;
+
+/// Where a return is in its workflow. Each step only follows the one
+/// before it: `requested` -> `approved` -> `completed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnStatus {
+    /// Created by `create_return`; nothing is restocked or refunded yet
+    Requested,
+    /// Accepted by `approve_return`, waiting for the goods to arrive
+    Approved,
+    /// Goods restocked and refund recorded by `complete_return`
+    Completed,
+}
+
+impl ReturnStatus {
+    /// The status a return must have to move to this one
+    pub fn previous(&self) -> Option<ReturnStatus> {
+        match self {
+            ReturnStatus::Requested => None,
+            ReturnStatus::Approved => Some(ReturnStatus::Requested),
+            ReturnStatus::Completed => Some(ReturnStatus::Approved),
+        }
+    }
+}
+
+impl fmt::Display for ReturnStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReturnStatus::Requested => "requested",
+            ReturnStatus::Approved => "approved",
+            ReturnStatus::Completed => "completed",
+        })
+    }
+}
+
+/// One returned product
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnItem {
+    pub product_id: String,
+    /// In the product's unit
+    pub quantity: Quantity,
+    /// Amount refunded for this line, at the price the order was recorded with
+    pub refund_amount: f64,
+    /// Location the goods are restocked at; defaults to `default`
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+/// A return as stored in the `product_return` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductReturn {
+    pub id: Thing,
+    pub order_id: String,
+    pub items: Vec<ReturnItem>,
+    pub reason: String,
+    pub status: ReturnStatus,
+    /// Sum of the items' refunds
+    pub refund_total: f64,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub approved_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnForCreation {
+    pub order_id: String,
+    pub items: Vec<ReturnItem>,
+    pub reason: String,
+    pub status: ReturnStatus,
+    pub refund_total: f64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One product the caller wants to return; the refund is worked out from
+/// the order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnItemRequest {
+    pub product_id: String,
+    pub quantity: Quantity,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateReturnRequest {
+    /// `order_id` the order was recorded with by `record_order`
+    pub order_id: String,
+    pub items: Vec<ReturnItemRequest>,
+    pub reason: String,
+}
+
+/// `approve_return`, `complete_return` and `get_return` params
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnIdRequest {
+    pub return_id: String,
+}
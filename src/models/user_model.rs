@@ -1,3 +1,4 @@
+use crate::models::admin_model::DependentRecords;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Thing;
@@ -7,25 +8,76 @@ pub struct User {
     pub id: Thing,
     pub name: String,
     pub email: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    /// Storage key of the confirmed avatar image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_key: Option<String>,
+    /// Where the avatar is served from; filled in by the service from
+    /// `avatar_key`, never stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    /// When the current email address was confirmed; cleared when it changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// Last sign-in reported through `record_activity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_login_at: Option<DateTime<Utc>>,
+    /// Last authenticated activity reported through `record_activity`.
+    /// Written in batches, so it can lag by `USER_ACTIVITY_FLUSH_SECS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_at: Option<DateTime<Utc>>,
+    /// Consecutive failed sign-ins reported through `record_login_attempt`
+    #[serde(default)]
+    pub failed_logins: u32,
+    /// Sign-ins are refused until then after too many failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<DateTime<Utc>>,
+    /// Names the user had before, oldest first. Stored on the row but left
+    /// out of every response except `get_name_history`.
+    #[serde(default, skip_serializing)]
+    pub name_history: Vec<NameChange>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// A name a user gave up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameChange {
+    pub name: String,
+    /// When the user was renamed from it
+    pub changed_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserForCreation {
     pub name: String,
     pub email: String,
+    /// Blind index of the email, set when PII encryption is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
 impl User {
-    pub fn new(name: String, email: String) -> Self {
+    pub fn new(name: String, email: String, phone: Option<String>) -> Self {
         let now = Utc::now();
         Self {
             id: Thing::from(("user", "temp")), // Will be replaced by SurrealDB
             name,
             email,
+            phone,
+            avatar_key: None,
+            avatar_url: None,
+            email_verified_at: None,
+            last_login_at: None,
+            last_seen_at: None,
+            failed_logins: 0,
+            locked_until: None,
+            name_history: Vec::new(),
             created_at: now,
             updated_at: now,
         }
@@ -35,6 +87,8 @@ impl User {
         UserForCreation {
             name: self.name.clone(),
             email: self.email.clone(),
+            email_hash: None,
+            phone: self.phone.clone(),
             created_at: self.created_at,
             updated_at: self.updated_at,
         }
@@ -49,12 +103,22 @@ impl User {
 pub struct CreateUserRequest {
     pub name: String,
     pub email: String,
+    #[serde(default)]
+    pub phone: Option<String>,
+    /// Run every check, signup rules and email lookup included, without
+    /// creating the user
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateUserResponse {
+    /// Empty for a dry run
     pub id: String,
     pub message: String,
+    /// Set when nothing was written
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,3 +131,360 @@ pub struct ListUsersResponse {
     pub users: Vec<User>,
     pub total: usize,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportUsersRequest {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportUsersResponse {
+    pub users: Vec<User>,
+    pub offset: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotateEncryptionKeysRequest {
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotateEncryptionKeysResponse {
+    pub active_key_id: String,
+    pub reencrypted: usize,
+    pub batches: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateUserRequest {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// An empty string removes the phone number
+    #[serde(default)]
+    pub phone: Option<String>,
+    /// Marks the email address as confirmed (`true`) or not (`false`).
+    /// Changing the email without setting this leaves it unverified.
+    #[serde(default)]
+    pub email_verified: Option<bool>,
+    /// Return the user as the update would leave it, without saving it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateUserNameRequest {
+    pub id: String,
+    /// 2 to 64 characters: letters, digits, spaces and `'` `-` `.` `_`.
+    /// Surrounding whitespace is trimmed.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetNameHistoryRequest {
+    pub user_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameHistoryResponse {
+    pub user_id: String,
+    /// The current name
+    pub name: String,
+    /// Previous names, oldest first
+    pub history: Vec<NameChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteUserRequest {
+    pub id: String,
+    /// Check the user can be deleted without deleting it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteUserResponse {
+    pub id: String,
+    pub message: String,
+    /// Set when nothing was written
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// Records referring to the user that were (or, in a dry run, would
+    /// be) cascaded or orphaned
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependents: Vec<DependentRecords>,
+}
+
+/// Event types in the `user_event` log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UserEventKind {
+    UserCreated,
+    UserUpdated,
+    UserDeleted,
+}
+
+/// Fields set by a user event. `None` keeps the previous value and an
+/// empty `phone` or `avatar_key` removes it. A new `email` resets
+/// verification unless `email_verified` is set with it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserChanges {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Blind index of the email, set when PII encryption is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+impl UserChanges {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.email.is_none()
+            && self.phone.is_none()
+            && self.avatar_key.is_none()
+            && self.email_verified.is_none()
+    }
+
+    /// The user's `email_verified_at` after these changes, applied at `at`
+    pub fn email_verified_at(
+        &self,
+        previous: Option<DateTime<Utc>>,
+        at: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        match (self.email_verified, self.email.is_some()) {
+            (Some(true), false) => previous.or(Some(at)),
+            (Some(true), true) => Some(at),
+            (Some(false), _) | (None, true) => None,
+            (None, false) => previous,
+        }
+    }
+
+    /// `user`'s name history after these changes, applied at `at`: the
+    /// current name is kept when a different one replaces it
+    pub fn name_history(&self, user: &User, at: DateTime<Utc>) -> Option<Vec<NameChange>> {
+        self.name.as_ref().filter(|name| **name != user.name)?;
+        let mut history = user.name_history.clone();
+        history.push(NameChange {
+            name: user.name.clone(),
+            changed_at: at,
+        });
+        Some(history)
+    }
+
+    /// `user` with these changes applied at `at`
+    pub fn apply_to(&self, user: User, at: DateTime<Utc>) -> User {
+        let name_history = self
+            .name_history(&user, at)
+            .unwrap_or_else(|| user.name_history.clone());
+        User {
+            name: self.name.clone().unwrap_or(user.name),
+            email: self.email.clone().unwrap_or(user.email),
+            phone: match &self.phone {
+                Some(phone) if phone.is_empty() => None,
+                Some(phone) => Some(phone.clone()),
+                None => user.phone,
+            },
+            avatar_key: match &self.avatar_key {
+                Some(key) if key.is_empty() => None,
+                Some(key) => Some(key.clone()),
+                None => user.avatar_key,
+            },
+            email_verified_at: self.email_verified_at(user.email_verified_at, at),
+            name_history,
+            updated_at: at,
+            ..user
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserEvent {
+    pub id: Thing,
+    pub user_id: String,
+    /// 1 for `UserCreated`, then one more per event
+    pub version: u64,
+    pub kind: UserEventKind,
+    pub changes: UserChanges,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserEventForCreation {
+    pub user_id: String,
+    pub version: u64,
+    pub kind: UserEventKind,
+    pub changes: UserChanges,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl UserEvent {
+    /// The user after this event, given the user before it
+    pub fn apply(&self, state: Option<User>) -> Option<User> {
+        let changes = &self.changes;
+        match self.kind {
+            UserEventKind::UserCreated => Some(User {
+                id: Thing::from(("user", self.user_id.as_str())),
+                name: changes.name.clone().unwrap_or_default(),
+                email: changes.email.clone().unwrap_or_default(),
+                phone: changes.phone.clone().filter(|phone| !phone.is_empty()),
+                avatar_key: changes.avatar_key.clone().filter(|key| !key.is_empty()),
+                avatar_url: None,
+                email_verified_at: changes.email_verified_at(None, self.recorded_at),
+                last_login_at: None,
+                last_seen_at: None,
+                failed_logins: 0,
+                locked_until: None,
+                name_history: Vec::new(),
+                created_at: self.recorded_at,
+                updated_at: self.recorded_at,
+            }),
+            UserEventKind::UserUpdated => {
+                state.map(|user| changes.apply_to(user, self.recorded_at))
+            }
+            UserEventKind::UserDeleted => None,
+        }
+    }
+}
+
+/// Rebuilds a user by folding its events, oldest first. `None` means the
+/// user never existed or was deleted by the last event.
+pub fn replay_user_events(events: &[UserEvent]) -> Option<User> {
+    events.iter().fold(None, |state, event| event.apply(state))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestAvatarUploadRequest {
+    pub user_id: String,
+    /// `image/png`, `image/jpeg`, `image/webp` or `image/gif`
+    pub content_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvatarUploadResponse {
+    /// Presigned URL to upload the image to, with `method` and the
+    /// `Content-Type` below
+    pub upload_url: String,
+    pub method: String,
+    /// Pass to `confirm_avatar` once the upload succeeded
+    pub key: String,
+    pub content_type: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmAvatarRequest {
+    pub user_id: String,
+    /// The `key` returned by `request_avatar_upload`
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetUserHistoryRequest {
+    pub user_id: String,
+    /// Only replay events recorded at or before this time
+    #[serde(default)]
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserHistoryResponse {
+    pub user_id: String,
+    /// Events up to `as_of`, oldest first
+    pub events: Vec<UserEvent>,
+    /// The account as of `as_of` (or now); `None` if it did not exist yet
+    /// or had been deleted
+    pub state: Option<User>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetUserStatsRequest {
+    /// How many days of signups to return, today included (default 30)
+    #[serde(default)]
+    pub days: Option<u32>,
+}
+
+/// Signups on one UTC day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailySignups {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub signups: usize,
+}
+
+/// User counts for the admin dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserStats {
+    pub total_users: usize,
+    pub verified_users: usize,
+    pub unverified_users: usize,
+    /// One entry per day, oldest first; days without signups count zero
+    pub signups_per_day: Vec<DailySignups>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordActivityRequest {
+    pub user_id: String,
+    /// The activity is a sign-in, which also sets `last_login_at`
+    #[serde(default)]
+    pub login: bool,
+}
+
+/// One user's activity since the last flush
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserActivity {
+    pub user_id: String,
+    pub seen_at: DateTime<Utc>,
+    pub login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListInactiveUsersRequest {
+    /// Users not seen for at least this many days
+    pub days: u32,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListInactiveUsersResponse {
+    /// Least recently seen first; users never seen count from signup
+    pub users: Vec<User>,
+    pub total: usize,
+    pub cutoff: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordLoginAttemptRequest {
+    pub email: String,
+    /// Whether the credentials checked out
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginAttemptResponse {
+    /// Whether the sign-in may go ahead; `false` while the account is
+    /// locked, even for correct credentials
+    pub allowed: bool,
+    pub failed_attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockUserRequest {
+    pub id: String,
+}
@@ -0,0 +1,27 @@
+//! The building blocks most library users need, importable in one line:
+//!
+//! ```ignore
+//! use jpc_rust::prelude::*;
+//! ```
+//!
+//! Request and response types for individual methods stay in their
+//! `models::*_model` modules.
+
+pub use crate::clients::internal::{InternalClient, InternalClientError};
+pub use crate::clients::product_client::ProductClient;
+pub use crate::clients::user_client::UserClient;
+pub use crate::config::database::{ConfigError, DatabaseConfig};
+pub use crate::errors::error_detail::ErrorDetail;
+pub use crate::errors::product_error::ProductServiceError;
+pub use crate::errors::user_error::UserServiceError;
+pub use crate::models::organization_model::{OrgRole, Organization};
+pub use crate::models::product_model::Product;
+pub use crate::models::user_model::User;
+pub use crate::repositories::connection::{DatabaseUnavailable, DbConnection};
+pub use crate::repositories::organization_repository::OrganizationRepository;
+pub use crate::repositories::product_repository::ProductRepository;
+pub use crate::repositories::user_repository::UserRepository;
+pub use crate::services::product_service::ProductService;
+pub use crate::services::read_only::ReadOnlyMode;
+pub use crate::services::storage_caps::{StorageFull, StorageMonitor};
+pub use crate::services::user_service::UserService;
@@ -0,0 +1,181 @@
+use crate::{
+    errors::product_error::ProductServiceError,
+    models::attribute_model::{
+        AttributeDefinition, AttributeDefinitionForCreation, AttributeValue, ProductAttribute,
+    },
+    models::product_model::Product,
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use surrealdb::sql::Thing;
+use tracing::{debug, error};
+
+/// Attribute keys are unique, and indexed values are looked up by key and
+/// value or replaced by product
+const ATTRIBUTE_INDEXES: &str = "\
+    DEFINE INDEX attribute_definition_key ON TABLE attribute_definition COLUMNS key UNIQUE; \
+    DEFINE INDEX product_attribute_lookup ON TABLE product_attribute COLUMNS key, value; \
+    DEFINE INDEX product_attribute_product ON TABLE product_attribute COLUMNS product_id;";
+
+/// A product's attributes, for rebuilding the lookup table
+#[derive(Debug, Deserialize)]
+pub struct ProductAttributes {
+    pub id: Thing,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, AttributeValue>,
+}
+
+/// Registered attribute keys and the `product_attribute` lookup table that
+/// makes indexed attributes searchable. The values themselves live on the
+/// product.
+pub struct AttributeRepository {
+    db: Arc<DbConnection>,
+}
+
+impl AttributeRepository {
+    pub async fn new(db: Arc<DbConnection>) -> Result<Self, ProductServiceError> {
+        let handle = db.handle()?;
+        traced_query(ATTRIBUTE_INDEXES, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        Ok(Self { db })
+    }
+
+    pub async fn define(
+        &self,
+        definition: AttributeDefinitionForCreation,
+    ) -> Result<AttributeDefinition, ProductServiceError> {
+        let db = self.db.handle()?;
+        let created: Vec<AttributeDefinition> =
+            traced_query("CREATE attribute_definition CONTENT $content", |_| {
+                db.create("attribute_definition").content(definition)
+            })
+            .await?;
+
+        match created.into_iter().next() {
+            Some(definition) => {
+                debug!("Defined attribute {}", definition.key);
+                Ok(definition)
+            }
+            None => {
+                error!("Failed to define attribute");
+                Err(ProductServiceError::Internal(anyhow::anyhow!(
+                    "Failed to define attribute"
+                )))
+            }
+        }
+    }
+
+    pub async fn definition(
+        &self,
+        key: &str,
+    ) -> Result<Option<AttributeDefinition>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let definitions: Vec<AttributeDefinition> = traced_query(
+            "SELECT * FROM attribute_definition WHERE key = $key",
+            |sql| db.query(sql).bind(("key", key)),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(definitions.into_iter().next())
+    }
+
+    /// Every registered attribute, sorted by key
+    pub async fn definitions(&self) -> Result<Vec<AttributeDefinition>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let definitions: Vec<AttributeDefinition> =
+            traced_query("SELECT * FROM attribute_definition ORDER BY key", |sql| {
+                db.query(sql)
+            })
+            .await?
+            .take(0)?;
+
+        Ok(definitions)
+    }
+
+    /// Replaces a product's attributes and its rows in the lookup table,
+    /// which hold the `indexed` ones, in one transaction
+    pub async fn set_attributes(
+        &self,
+        product_id: &str,
+        attributes: &BTreeMap<String, AttributeValue>,
+        indexed: Vec<ProductAttribute>,
+    ) -> Result<Product, ProductServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "BEGIN TRANSACTION; \
+             UPDATE $product SET attributes = $attributes, updated_at = time::now(); \
+             DELETE product_attribute WHERE product_id = $product_id; \
+             FOR $row IN $rows { CREATE product_attribute CONTENT $row; }; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("product", Thing::from(("product", product_id))))
+                    .bind(("attributes", attributes))
+                    .bind(("product_id", product_id))
+                    .bind(("rows", indexed))
+            },
+        )
+        .await?
+        .check()?;
+
+        let product: Option<Product> =
+            traced_query("SELECT * FROM $id", |_| db.select(("product", product_id))).await?;
+        product.ok_or_else(|| ProductServiceError::ProductNotFound {
+            id: product_id.to_string(),
+        })
+    }
+
+    /// Every product's attributes
+    pub async fn product_attributes(&self) -> Result<Vec<ProductAttributes>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let products: Vec<ProductAttributes> =
+            traced_query("SELECT id, attributes FROM product", |sql| db.query(sql))
+                .await?
+                .take(0)?;
+
+        Ok(products)
+    }
+
+    /// Replaces the whole lookup table in one transaction
+    pub async fn replace_lookup(
+        &self,
+        rows: Vec<ProductAttribute>,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        let count = rows.len();
+        traced_query(
+            "BEGIN TRANSACTION; \
+             DELETE product_attribute; \
+             FOR $row IN $rows { CREATE product_attribute CONTENT $row; }; \
+             COMMIT TRANSACTION;",
+            |sql| db.query(sql).bind(("rows", rows)),
+        )
+        .await?
+        .check()?;
+
+        debug!("Rebuilt the attribute lookup table with {} rows", count);
+        Ok(())
+    }
+
+    /// Ids of the products whose indexed attribute `key` is `value`
+    pub async fn product_ids_with(
+        &self,
+        key: &str,
+        value: &AttributeValue,
+    ) -> Result<Vec<String>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let ids: Vec<String> = traced_query(
+            "SELECT VALUE product_id FROM product_attribute WHERE key = $key AND value = $value",
+            |sql| db.query(sql).bind(("key", key)).bind(("value", value)),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(ids)
+    }
+}
@@ -0,0 +1,261 @@
+use crate::middleware::deadline::{check_current_deadline, DeadlineExceeded};
+use crate::{config::database::DatabaseConfig, services::startup::init_with_backoff};
+use chrono::{DateTime, Utc};
+use futures::future::try_join_all;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, Weak};
+use std::time::Duration;
+use surrealdb::{engine::any::Any, Surreal};
+use thiserror::Error;
+use tokio::time::{timeout, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// JSON-RPC error code returned by `health` while the database connection
+/// is down
+pub const DATABASE_UNAVAILABLE_CODE: i32 = -32011;
+
+/// How long a health ping may take before the connection counts as dropped
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Returned instead of running an operation while the supervisor is
+/// reconnecting, so callers fail fast rather than hang on a dead socket
+#[derive(Error, Debug, Clone)]
+#[error("Database unavailable at {endpoint}: reconnecting")]
+pub struct DatabaseUnavailable {
+    pub endpoint: String,
+}
+
+/// Why [`DbConnection::handle`] refused to start an operation
+#[derive(Error, Debug, Clone)]
+pub enum ConnectionError {
+    #[error(transparent)]
+    Unavailable(#[from] DatabaseUnavailable),
+
+    #[error(transparent)]
+    DeadlineExceeded(#[from] DeadlineExceeded),
+}
+
+/// Database status as reported by the `health` RPC
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseHealth {
+    pub endpoint: String,
+    /// True while at least one pooled connection is up
+    pub connected: bool,
+    pub pool_size: usize,
+    /// Pooled connections currently up
+    pub healthy_connections: usize,
+    /// When the current outage of the whole pool started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub down_since: Option<DateTime<Utc>>,
+    /// Why a connection was last considered dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Successful reconnections since startup
+    pub reconnects: u64,
+}
+
+#[derive(Debug, Default)]
+struct Outage {
+    down_since: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+/// One connection of the pool
+struct PooledConnection {
+    db: RwLock<Surreal<Any>>,
+    connected: AtomicBool,
+}
+
+impl PooledConnection {
+    fn new(db: Surreal<Any>) -> Self {
+        Self {
+            db: RwLock::new(db),
+            connected: AtomicBool::new(true),
+        }
+    }
+
+    fn current(&self) -> Surreal<Any> {
+        self.db
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+}
+
+/// A pool of SurrealDB connections shared by a service's repositories.
+///
+/// `ws://` / `wss://` endpoints get `pool_size` connections, and each
+/// operation takes the next healthy one in turn, so one slow or dropped
+/// socket neither serializes every query nor takes the service down.
+/// A supervisor task per connection pings it every
+/// `DB_HEALTH_CHECK_INTERVAL_SECS` (default 5, `0` disables them). When a
+/// ping fails that connection is taken out of rotation and reconnected with
+/// the same backoff as startup; only when every connection is down are
+/// operations rejected with [`DatabaseUnavailable`]. `mem://` databases
+/// live in-process, use a single connection and are never supervised.
+pub struct DbConnection {
+    config: DatabaseConfig,
+    pool: Vec<PooledConnection>,
+    next: AtomicUsize,
+    outage: Mutex<Outage>,
+    reconnects: AtomicU64,
+}
+
+impl DbConnection {
+    pub async fn open(config: &DatabaseConfig) -> Result<Arc<Self>, surrealdb::Error> {
+        let pool = try_join_all((0..config.pool_size()).map(|_| config.connect())).await?;
+        let connection = Arc::new(Self {
+            config: config.clone(),
+            pool: pool.into_iter().map(PooledConnection::new).collect(),
+            next: AtomicUsize::new(0),
+            outage: Mutex::new(Outage::default()),
+            reconnects: AtomicU64::new(0),
+        });
+
+        if config.is_remote() {
+            match health_check_interval_from_env() {
+                Some(interval) => {
+                    info!(
+                        "🩺 Supervising {} SurrealDB connections to {} every {:?}",
+                        connection.pool.len(),
+                        config.endpoint,
+                        interval
+                    );
+                    for index in 0..connection.pool.len() {
+                        spawn_supervisor(Arc::downgrade(&connection), index, interval);
+                    }
+                }
+                None => warn!("SurrealDB connection supervisor disabled"),
+            }
+        }
+
+        Ok(connection)
+    }
+
+    /// The next healthy connection of the pool, or an error while all of
+    /// them are being re-established or once the current request's
+    /// deadline has passed
+    pub fn handle(&self) -> Result<Surreal<Any>, ConnectionError> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let Some(member) = (0..self.pool.len())
+            .map(|offset| &self.pool[start.wrapping_add(offset) % self.pool.len()])
+            .find(|member| member.is_connected())
+        else {
+            return Err(DatabaseUnavailable {
+                endpoint: self.config.endpoint.clone(),
+            }
+            .into());
+        };
+        check_current_deadline()?;
+        Ok(member.current())
+    }
+
+    /// The settings the connection was opened with
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.pool.iter().any(PooledConnection::is_connected)
+    }
+
+    pub fn health(&self) -> DatabaseHealth {
+        let outage = self.outage.lock().unwrap_or_else(PoisonError::into_inner);
+        DatabaseHealth {
+            endpoint: self.config.endpoint.clone(),
+            connected: self.is_connected(),
+            pool_size: self.pool.len(),
+            healthy_connections: self
+                .pool
+                .iter()
+                .filter(|member| member.is_connected())
+                .count(),
+            down_since: outage.down_since,
+            last_error: outage.last_error.clone(),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+        }
+    }
+
+    fn mark_down(&self, index: usize, reason: String) {
+        warn!(
+            "🔌 SurrealDB connection {} to {} lost: {}; reconnecting",
+            index, self.config.endpoint, reason
+        );
+        let mut outage = self.outage.lock().unwrap_or_else(PoisonError::into_inner);
+        self.pool[index].connected.store(false, Ordering::Release);
+        if !self.is_connected() && outage.down_since.is_none() {
+            warn!(
+                "🔌 All SurrealDB connections to {} are down",
+                self.config.endpoint
+            );
+            outage.down_since = Some(Utc::now());
+        }
+        outage.last_error = Some(reason);
+    }
+
+    fn mark_reconnected(&self, index: usize, db: Surreal<Any>) {
+        let member = &self.pool[index];
+        *member.db.write().unwrap_or_else(PoisonError::into_inner) = db;
+        let mut outage = self.outage.lock().unwrap_or_else(PoisonError::into_inner);
+        member.connected.store(true, Ordering::Release);
+        if let Some(down_since) = outage.down_since.take() {
+            info!(
+                "🔌 SurrealDB connection to {} restored after {}s",
+                self.config.endpoint,
+                (Utc::now() - down_since).num_seconds()
+            );
+        }
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Whether `err` is a write rejected by the UNIQUE index `index`. Matched
+/// on the message, which embedded and remote engines both report.
+pub(crate) fn is_unique_violation(err: &surrealdb::Error, index: &str) -> bool {
+    err.to_string()
+        .contains(&format!("Database index `{}` already contains", index))
+}
+
+/// Reads `DB_HEALTH_CHECK_INTERVAL_SECS` (default 5); `0` disables the
+/// supervisor
+fn health_check_interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("DB_HEALTH_CHECK_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Pings one pooled connection until the pool is dropped, reconnecting it
+/// whenever a ping fails or times out
+fn spawn_supervisor(connection: Weak<DbConnection>, index: usize, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            let Some(connection) = connection.upgrade() else {
+                return;
+            };
+
+            let db = connection.pool[index].current();
+            let reason = match timeout(PING_TIMEOUT, db.health()).await {
+                Ok(Ok(())) => continue,
+                Ok(Err(err)) => err.to_string(),
+                Err(_) => format!("no answer within {:?}", PING_TIMEOUT),
+            };
+            connection.mark_down(index, reason);
+
+            let config = connection.config.clone();
+            let db = init_with_backoff("SurrealDB connection", || config.connect()).await;
+            connection.mark_reconnected(index, db);
+        }
+    });
+}
@@ -0,0 +1,103 @@
+use crate::{
+    errors::product_error::ProductServiceError,
+    models::coupon_model::{Coupon, CouponForCreation},
+    repositories::connection::{is_unique_violation, DbConnection},
+    telemetry::query_metrics::traced_query,
+};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// Codes are unique, so concurrent creates of one code cannot both succeed
+const COUPON_CODE_INDEX: &str = "DEFINE INDEX coupon_code ON TABLE coupon COLUMNS code UNIQUE;";
+
+/// Coupons live in the product database and share its connection
+pub struct CouponRepository {
+    db: Arc<DbConnection>,
+}
+
+impl CouponRepository {
+    pub async fn new(db: Arc<DbConnection>) -> Result<Self, ProductServiceError> {
+        let handle = db.handle()?;
+        traced_query(COUPON_CODE_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        Ok(Self { db })
+    }
+
+    pub async fn create_coupon(
+        &self,
+        coupon: CouponForCreation,
+    ) -> Result<Coupon, ProductServiceError> {
+        let db = self.db.handle()?;
+        if self.get_coupon_by_code(&coupon.code).await?.is_some() {
+            return Err(ProductServiceError::CouponAlreadyExists { code: coupon.code });
+        }
+
+        let code = coupon.code.clone();
+        let created: Vec<Coupon> = match traced_query("CREATE coupon CONTENT $content", |_| {
+            db.create("coupon").content(coupon)
+        })
+        .await
+        {
+            Ok(created) => created,
+            // A concurrent create took the code after the check above
+            Err(err) if is_unique_violation(&err, "coupon_code") => {
+                return Err(ProductServiceError::CouponAlreadyExists { code });
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        match created.into_iter().next() {
+            Some(coupon) => {
+                debug!("Created coupon {} with id: {}", coupon.code, coupon.id);
+                Ok(coupon)
+            }
+            None => {
+                error!("Failed to create coupon");
+                Err(ProductServiceError::Internal(anyhow::anyhow!(
+                    "Failed to create coupon"
+                )))
+            }
+        }
+    }
+
+    pub async fn get_coupon_by_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<Coupon>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let coupons: Vec<Coupon> = traced_query("SELECT * FROM coupon WHERE code = $code", |sql| {
+            db.query(sql).bind(("code", code))
+        })
+        .await?
+        .take(0)?;
+
+        Ok(coupons.into_iter().next())
+    }
+
+    /// Counts one redemption in a single conditional update, so concurrent
+    /// checkouts can never push a coupon past `max_redemptions`. Returns
+    /// `None` when the coupon is inactive, expired or used up.
+    pub async fn increment_redemptions(
+        &self,
+        code: &str,
+    ) -> Result<Option<Coupon>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let updated: Vec<Coupon> = traced_query(
+            "UPDATE coupon SET redemptions += 1, updated_at = time::now() \
+             WHERE code = $code AND active = true \
+             AND (!expires_at OR expires_at > $now) \
+             AND (!max_redemptions OR redemptions < max_redemptions) \
+             RETURN AFTER",
+            |sql| {
+                db.query(sql)
+                    .bind(("code", code))
+                    .bind(("now", chrono::Utc::now()))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        Ok(updated.into_iter().next())
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    errors::feature_flag_error::FeatureFlagError,
+    models::feature_flag_model::{FeatureFlag, FeatureFlagForCreation},
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Feature flags live in each service's own database and share its
+/// connection
+pub struct FeatureFlagRepository {
+    db: Arc<DbConnection>,
+}
+
+impl FeatureFlagRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self { db }
+    }
+
+    pub async fn list_flags(&self) -> Result<Vec<FeatureFlag>, FeatureFlagError> {
+        let db = self.db.handle()?;
+        let flags: Vec<FeatureFlag> =
+            traced_query("SELECT * FROM feature_flag ORDER BY key", |sql| {
+                db.query(sql)
+            })
+            .await?
+            .take(0)?;
+
+        debug!("Loaded {} feature flags", flags.len());
+        Ok(flags)
+    }
+
+    /// Creates or replaces the flag stored under its key
+    pub async fn save_flag(
+        &self,
+        flag: FeatureFlagForCreation,
+    ) -> Result<FeatureFlag, FeatureFlagError> {
+        let db = self.db.handle()?;
+        let key = flag.key.clone();
+        let saved: Option<FeatureFlag> = traced_query(
+            "UPDATE type::thing('feature_flag', $key) CONTENT $flag",
+            |sql| {
+                db.query(sql)
+                    .bind(("key", key.as_str()))
+                    .bind(("flag", flag))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        saved.ok_or(FeatureFlagError::NotFound { key })
+    }
+}
@@ -0,0 +1,432 @@
+use crate::{
+    errors::product_error::ProductServiceError,
+    models::inventory_model::{
+        Location, LocationForCreation, ProductStockTotal, StockLevel, StockMovement,
+    },
+    models::quantity_model::Quantity,
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use surrealdb::sql::Thing;
+use tracing::{debug, error};
+
+/// Forecasts read one product's recent movements at a time
+const STOCK_MOVEMENT_INDEX: &str = "DEFINE INDEX stock_movement_product \
+     ON TABLE stock_movement COLUMNS product_id, moved_at;";
+
+#[derive(Debug, Deserialize)]
+struct MovementTotal {
+    product_id: String,
+    total: Quantity,
+}
+
+/// One item of a bulk stock update as bound into its transaction
+#[derive(Debug, Serialize)]
+pub struct StockSet {
+    pub row: Thing,
+    pub product_id: String,
+    pub location: String,
+    pub quantity: Quantity,
+    pub expected: Option<Quantity>,
+    pub reason: Option<String>,
+}
+
+impl StockSet {
+    pub fn new(
+        product_id: &str,
+        location: &str,
+        quantity: Quantity,
+        expected: Option<Quantity>,
+        reason: Option<String>,
+    ) -> Self {
+        Self {
+            row: stock_thing(product_id, location),
+            product_id: product_id.to_string(),
+            location: location.to_string(),
+            quantity,
+            expected,
+            reason,
+        }
+    }
+}
+
+/// A product whose total is recomputed after a bulk stock update
+#[derive(Debug, Serialize)]
+struct StockTotal {
+    product: Thing,
+    product_id: String,
+}
+
+/// Locations, per-location stock rows and the `stock_movement` ledger of
+/// changes to them, stored in the product database
+pub struct InventoryRepository {
+    db: Arc<DbConnection>,
+}
+
+pub(crate) fn product_thing(product_id: &str) -> Thing {
+    Thing::from(("product", product_id))
+}
+
+pub(crate) fn stock_thing(product_id: &str, location: &str) -> Thing {
+    Thing::from(("stock", format!("{}__{}", product_id, location).as_str()))
+}
+
+impl InventoryRepository {
+    pub async fn new(db: Arc<DbConnection>) -> Result<Self, ProductServiceError> {
+        let handle = db.handle()?;
+        traced_query(STOCK_MOVEMENT_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        Ok(Self { db })
+    }
+
+    pub async fn create_location(
+        &self,
+        location: LocationForCreation,
+    ) -> Result<Location, ProductServiceError> {
+        let db = self.db.handle()?;
+        if self.get_location(&location.code).await?.is_some() {
+            return Err(ProductServiceError::LocationAlreadyExists {
+                code: location.code,
+            });
+        }
+
+        let created: Vec<Location> = traced_query("CREATE location CONTENT $content", |_| {
+            db.create("location").content(location)
+        })
+        .await?;
+
+        match created.into_iter().next() {
+            Some(location) => {
+                debug!(
+                    "Created location {} with id: {}",
+                    location.code, location.id
+                );
+                Ok(location)
+            }
+            None => {
+                error!("Failed to create location");
+                Err(ProductServiceError::Internal(anyhow::anyhow!(
+                    "Failed to create location"
+                )))
+            }
+        }
+    }
+
+    pub async fn get_location(&self, code: &str) -> Result<Option<Location>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let locations: Vec<Location> =
+            traced_query("SELECT * FROM location WHERE code = $code", |sql| {
+                db.query(sql).bind(("code", code))
+            })
+            .await?
+            .take(0)?;
+
+        Ok(locations.into_iter().next())
+    }
+
+    pub async fn list_locations(&self) -> Result<Vec<Location>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let locations: Vec<Location> =
+            traced_query("SELECT * FROM location ORDER BY code", |sql| db.query(sql))
+                .await?
+                .take(0)?;
+
+        Ok(locations)
+    }
+
+    /// The product's stock rows, by location code
+    pub async fn stock_levels(
+        &self,
+        product_id: &str,
+    ) -> Result<Vec<StockLevel>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let levels: Vec<StockLevel> = traced_query(
+            "SELECT * FROM stock WHERE product_id = $product_id ORDER BY location",
+            |sql| db.query(sql).bind(("product_id", product_id)),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(levels)
+    }
+
+    /// The stock rows of every product in `product_ids`
+    pub async fn stock_levels_for(
+        &self,
+        product_ids: &[String],
+    ) -> Result<Vec<StockLevel>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let levels: Vec<StockLevel> = traced_query(
+            "SELECT * FROM stock WHERE product_id INSIDE $product_ids",
+            |sql| db.query(sql).bind(("product_ids", product_ids)),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(levels)
+    }
+
+    /// Every stock row, for reconciliation
+    pub async fn all_stock_levels(&self) -> Result<Vec<StockLevel>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let levels: Vec<StockLevel> =
+            traced_query("SELECT * FROM stock ORDER BY product_id, location", |sql| {
+                db.query(sql)
+            })
+            .await?
+            .take(0)?;
+
+        Ok(levels)
+    }
+
+    /// Every product's recorded total, for reconciliation
+    pub async fn product_totals(&self) -> Result<Vec<ProductStockTotal>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let totals: Vec<ProductStockTotal> =
+            traced_query("SELECT id, stock_quantity FROM product", |sql| {
+                db.query(sql)
+            })
+            .await?
+            .take(0)?;
+
+        Ok(totals)
+    }
+
+    /// Units each product's stock adjustments took out, summed over the
+    /// whole ledger, for reconciliation
+    pub async fn removed_by_adjustments(
+        &self,
+    ) -> Result<HashMap<String, Quantity>, ProductServiceError> {
+        let removed = self
+            .movement_totals(
+                "SELECT product_id, math::sum(change) AS total FROM stock_movement \
+                 WHERE kind = 'adjustment' AND change < 0 GROUP BY product_id",
+            )
+            .await?;
+        Ok(removed
+            .into_iter()
+            .map(|(product_id, total)| (product_id, -total))
+            .collect())
+    }
+
+    /// Units each product's return movements put back, for reconciliation
+    pub async fn restocked_by_returns(
+        &self,
+    ) -> Result<HashMap<String, Quantity>, ProductServiceError> {
+        self.movement_totals(
+            "SELECT product_id, math::sum(change) AS total FROM stock_movement \
+             WHERE kind = 'return' GROUP BY product_id",
+        )
+        .await
+    }
+
+    async fn movement_totals(
+        &self,
+        sql: &'static str,
+    ) -> Result<HashMap<String, Quantity>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let totals: Vec<MovementTotal> = traced_query(sql, |sql| db.query(sql)).await?.take(0)?;
+
+        Ok(totals
+            .into_iter()
+            .map(|row| (row.product_id, row.total))
+            .collect())
+    }
+
+    /// Moves a product's pre-location `stock_quantity` into a row at
+    /// `location`. A no-op if the row already exists, so concurrent callers
+    /// cannot double it.
+    pub async fn seed_stock(
+        &self,
+        product_id: &str,
+        location: &str,
+        quantity: Quantity,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        let row = StockLevel {
+            id: stock_thing(product_id, location),
+            product_id: product_id.to_string(),
+            location: location.to_string(),
+            quantity,
+            updated_at: Utc::now(),
+        };
+        traced_query("INSERT IGNORE INTO stock $row", |sql| {
+            db.query(sql).bind(("row", row))
+        })
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// Sets the stock at one location, records the change in the ledger
+    /// and recomputes the product's total `stock_quantity` in the same
+    /// transaction
+    pub async fn set_stock(
+        &self,
+        product_id: &str,
+        location: &str,
+        quantity: Quantity,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "BEGIN TRANSACTION; \
+             LET $before = (SELECT VALUE quantity FROM ONLY $row) OR 0; \
+             UPDATE $row SET product_id = $product_id, location = $location, \
+             quantity = $quantity, updated_at = time::now(); \
+             IF $quantity != $before { \
+             CREATE stock_movement CONTENT { product_id: $product_id, location: $location, \
+             kind: 'adjustment', change: $quantity - $before, quantity_after: $quantity, \
+             moved_at: time::now() } }; \
+             UPDATE $product SET stock_quantity = math::sum(\
+             (SELECT VALUE quantity FROM stock WHERE product_id = $product_id)), \
+             updated_at = time::now(); \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("row", stock_thing(product_id, location)))
+                    .bind(("product", product_thing(product_id)))
+                    .bind(("product_id", product_id))
+                    .bind(("location", location))
+                    .bind(("quantity", quantity))
+            },
+        )
+        .await?
+        .check()?;
+
+        debug!(
+            "Set stock for product {} at {}: {}",
+            product_id, location, quantity
+        );
+        Ok(())
+    }
+
+    /// Seeds the rows of products in `seeds` that have none, sets the stock
+    /// of every item with a ledger entry for each change and recomputes the
+    /// products' totals, all in one transaction. Fails without changing
+    /// anything if a location no longer holds an item's `expected` stock.
+    pub async fn set_stock_bulk(
+        &self,
+        seeds: Vec<StockLevel>,
+        items: Vec<StockSet>,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        let mut seen = HashSet::new();
+        let totals: Vec<StockTotal> = items
+            .iter()
+            .filter(|item| seen.insert(item.product_id.as_str()))
+            .map(|item| StockTotal {
+                product: product_thing(&item.product_id),
+                product_id: item.product_id.clone(),
+            })
+            .collect();
+        let count = items.len();
+
+        traced_query(
+            "BEGIN TRANSACTION; \
+             FOR $seed IN $seeds { INSERT IGNORE INTO stock $seed; }; \
+             FOR $item IN $items { \
+             LET $before = (SELECT VALUE quantity FROM ONLY $item.row) OR 0; \
+             IF $item.expected != NONE AND $item.expected != $before { \
+             THROW 'Stock changed while the bulk update was checked' }; \
+             UPDATE $item.row SET product_id = $item.product_id, location = $item.location, \
+             quantity = $item.quantity, updated_at = time::now(); \
+             IF $item.quantity != $before { \
+             CREATE stock_movement CONTENT { product_id: $item.product_id, \
+             location: $item.location, kind: 'adjustment', reason: $item.reason, \
+             change: $item.quantity - $before, quantity_after: $item.quantity, \
+             moved_at: time::now() } }; \
+             }; \
+             FOR $total IN $totals { \
+             UPDATE $total.product SET stock_quantity = math::sum(\
+             (SELECT VALUE quantity FROM stock WHERE product_id = $total.product_id)), \
+             updated_at = time::now(); \
+             }; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("seeds", seeds))
+                    .bind(("items", items))
+                    .bind(("totals", totals))
+            },
+        )
+        .await?
+        .check()?;
+
+        debug!("Set stock for {} items in one transaction", count);
+        Ok(())
+    }
+
+    /// Moves `quantity` units between locations in one transaction, with a
+    /// ledger entry at each end. The source is re-checked inside the
+    /// transaction, so concurrent transfers can never take it below zero.
+    /// The product's total is unchanged.
+    pub async fn transfer_stock(
+        &self,
+        product_id: &str,
+        from: &str,
+        to: &str,
+        quantity: Quantity,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "BEGIN TRANSACTION; \
+             LET $available = (SELECT VALUE quantity FROM ONLY $from_row) OR 0; \
+             IF $available < $quantity { THROW 'Insufficient stock at source location' }; \
+             UPDATE $from_row SET quantity -= $quantity, updated_at = time::now(); \
+             UPDATE $to_row SET product_id = $product_id, location = $to, \
+             quantity = (quantity OR 0) + $quantity, updated_at = time::now(); \
+             CREATE stock_movement CONTENT { product_id: $product_id, location: $from, \
+             kind: 'transfer_out', change: -$quantity, quantity_after: $available - $quantity, \
+             moved_at: time::now() }; \
+             CREATE stock_movement CONTENT { product_id: $product_id, location: $to, \
+             kind: 'transfer_in', change: $quantity, \
+             quantity_after: (SELECT VALUE quantity FROM ONLY $to_row), moved_at: time::now() }; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("from_row", stock_thing(product_id, from)))
+                    .bind(("to_row", stock_thing(product_id, to)))
+                    .bind(("product_id", product_id))
+                    .bind(("from", from))
+                    .bind(("to", to))
+                    .bind(("quantity", quantity))
+            },
+        )
+        .await?
+        .check()?;
+
+        debug!(
+            "Transferred {} units of product {} from {} to {}",
+            quantity, product_id, from, to
+        );
+        Ok(())
+    }
+
+    /// The product's ledger entries since `since`, oldest first
+    pub async fn movements_since(
+        &self,
+        product_id: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<StockMovement>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let movements: Vec<StockMovement> = traced_query(
+            "SELECT * FROM stock_movement WHERE product_id = $product_id \
+             AND moved_at >= $since ORDER BY moved_at",
+            |sql| {
+                db.query(sql)
+                    .bind(("product_id", product_id))
+                    .bind(("since", since))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        Ok(movements)
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    errors::product_error::ProductServiceError,
+    models::job_model::{Job, JobForCreation, JobProgress, JobStatus},
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use serde_json::Value;
+use std::sync::Arc;
+use surrealdb::sql::Thing;
+use tracing::{debug, error};
+
+/// Workers look for the oldest queued job
+const JOB_STATUS_INDEX: &str =
+    "DEFINE INDEX job_status ON TABLE job COLUMNS status, created_at;";
+
+/// Background jobs, stored in the product database as `job`. The table is
+/// also the queue: workers claim queued jobs with a conditional update, so
+/// each job runs once however many workers there are.
+pub struct JobRepository {
+    db: Arc<DbConnection>,
+}
+
+impl JobRepository {
+    pub async fn new(db: Arc<DbConnection>) -> Result<Self, ProductServiceError> {
+        let handle = db.handle()?;
+        traced_query(JOB_STATUS_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        Ok(Self { db })
+    }
+
+    pub async fn create_job(&self, job: JobForCreation) -> Result<Job, ProductServiceError> {
+        let db = self.db.handle()?;
+        let created: Vec<Job> = traced_query("CREATE job CONTENT $content", |_| {
+            db.create("job").content(job)
+        })
+        .await?;
+
+        match created.into_iter().next() {
+            Some(job) => {
+                debug!("Queued {} job {}", job.kind, job.id);
+                Ok(job)
+            }
+            None => {
+                error!("Failed to create job");
+                Err(ProductServiceError::Internal(anyhow::anyhow!(
+                    "Failed to create job"
+                )))
+            }
+        }
+    }
+
+    pub async fn get_job(&self, job_id: &str) -> Result<Job, ProductServiceError> {
+        let db = self.db.handle()?;
+        let job: Option<Job> =
+            traced_query("SELECT * FROM $job", |_| db.select(("job", job_id))).await?;
+
+        job.ok_or_else(|| ProductServiceError::JobNotFound {
+            id: job_id.to_string(),
+        })
+    }
+
+    /// Moves the oldest queued job to `running` and returns it. `None` when
+    /// nothing is queued, or another worker claimed the job first.
+    pub async fn claim_next(&self) -> Result<Option<Job>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let claimed: Vec<Job> = traced_query(
+            "LET $next = (SELECT VALUE id FROM job WHERE status = 'queued' \
+             ORDER BY created_at LIMIT 1); \
+             UPDATE $next SET status = 'running', started_at = time::now(), \
+             updated_at = time::now() WHERE status = 'queued' RETURN AFTER;",
+            |sql| db.query(sql),
+        )
+        .await?
+        .take(1)?;
+
+        Ok(claimed.into_iter().next())
+    }
+
+    pub async fn set_progress(
+        &self,
+        job: &Thing,
+        progress: JobProgress,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "UPDATE $job SET progress = $progress, updated_at = time::now()",
+            |sql| {
+                db.query(sql)
+                    .bind(("job", job.clone()))
+                    .bind(("progress", progress))
+            },
+        )
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// Records a running job's outcome
+    pub async fn finish(
+        &self,
+        job: &Thing,
+        status: JobStatus,
+        result: Option<Value>,
+        error: Option<String>,
+    ) -> Result<Job, ProductServiceError> {
+        let db = self.db.handle()?;
+        let finished: Option<Job> = traced_query(
+            "UPDATE $job SET status = $status, result = $result, error = $error, \
+             finished_at = time::now(), updated_at = time::now() RETURN AFTER",
+            |sql| {
+                db.query(sql)
+                    .bind(("job", job.clone()))
+                    .bind(("status", status))
+                    .bind(("result", result))
+                    .bind(("error", error))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        finished.ok_or_else(|| ProductServiceError::JobNotFound {
+            id: job.id.to_raw(),
+        })
+    }
+
+    /// Fails the jobs left `running` by a previous process, which stopped
+    /// before finishing them
+    pub async fn fail_interrupted(&self) -> Result<Vec<Job>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let interrupted: Vec<Job> = traced_query(
+            "UPDATE job SET status = 'failed', error = 'Interrupted by a service restart', \
+             finished_at = time::now(), updated_at = time::now() \
+             WHERE status = 'running' RETURN AFTER",
+            |sql| db.query(sql),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(interrupted)
+    }
+}
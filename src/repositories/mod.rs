@@ -1,2 +1,13 @@
 pub mod product_repository;
 pub mod user_repository;
+pub mod coupon_repository;
+pub mod inventory_repository;
+pub mod connection;
+pub mod signup_repository;
+pub mod feature_flag_repository;
+pub mod organization_repository;
+pub mod order_history_repository;
+pub mod return_repository;
+pub mod attribute_repository;
+pub mod job_repository;
+pub mod rollup_repository;
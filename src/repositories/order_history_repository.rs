@@ -0,0 +1,311 @@
+use crate::{
+    errors::product_error::ProductServiceError,
+    models::address_model::Address,
+    models::product_model::Product,
+    models::quantity_model::Quantity,
+    models::recommendation_model::{
+        CoOccurrence, ListOrdersByUserRequest, OrderForRecording, OrderLine, OrderStatus,
+        RecordedOrder,
+    },
+    repositories::connection::{is_unique_violation, DbConnection},
+    telemetry::query_metrics::traced_query,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use surrealdb::sql::Thing;
+use tracing::debug;
+
+#[derive(Debug, Deserialize)]
+struct CountResult {
+    total: usize,
+}
+
+/// Recommendations look up one product's row at a time
+const CO_OCCURRENCE_INDEX: &str = "DEFINE INDEX product_co_occurrence_product \
+     ON TABLE product_co_occurrence COLUMNS product_id;";
+
+/// Returns look orders up by the caller's reference, which identifies one
+/// order; orders recorded without one are not indexed
+const ORDER_ID_INDEX: &str = "DEFINE INDEX order_history_order_id \
+     ON TABLE order_history COLUMNS order_id UNIQUE;";
+
+/// Account pages list one user's orders
+const USER_ID_INDEX: &str = "DEFINE INDEX order_history_user_id \
+     ON TABLE order_history COLUMNS user_id;";
+
+/// Completed orders and the co-occurrence table built from them. Both live
+/// in the product database and share its connection.
+pub struct OrderHistoryRepository {
+    db: Arc<DbConnection>,
+}
+
+impl OrderHistoryRepository {
+    pub async fn new(db: Arc<DbConnection>) -> Result<Self, ProductServiceError> {
+        let handle = db.handle()?;
+        traced_query(CO_OCCURRENCE_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        traced_query(ORDER_ID_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        traced_query(USER_ID_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        Ok(Self { db })
+    }
+
+    /// Fails when an order with the same `order_id` was recorded before
+    pub async fn record_order(&self, order: OrderForRecording) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        let order_id = order.order_id.clone();
+        let response = traced_query("CREATE order_history CONTENT $content", |sql| {
+            db.query(sql).bind(("content", order))
+        })
+        .await?;
+        match response.check() {
+            Ok(_) => Ok(()),
+            Err(err) if is_unique_violation(&err, "order_history_order_id") => {
+                Err(ProductServiceError::OrderAlreadyRecorded {
+                    id: order_id.unwrap_or_default(),
+                })
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// The order recorded with `order_id`
+    pub async fn find_order(
+        &self,
+        order_id: &str,
+    ) -> Result<Option<RecordedOrder>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let orders: Vec<RecordedOrder> = traced_query(
+            "SELECT * FROM order_history WHERE order_id = $order_id LIMIT 1",
+            |sql| db.query(sql).bind(("order_id", order_id)),
+        )
+        .await?
+        .take(0)?;
+        Ok(orders.into_iter().next())
+    }
+
+    /// A page of the orders `request` asks for, newest first, and how many
+    /// match across all pages
+    pub async fn orders_by_user(
+        &self,
+        request: &ListOrdersByUserRequest,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<RecordedOrder>, usize), ProductServiceError> {
+        let db = self.db.handle()?;
+        // Unset filters are NONE; rows recorded before statuses existed are
+        // completed orders
+        let mut response = traced_query(
+            "SELECT * FROM order_history WHERE user_id = $user_id \
+             AND ($status = NONE OR (status OR 'completed') = $status) \
+             AND ($from = NONE OR ordered_at >= $from) AND ($to = NONE OR ordered_at < $to) \
+             ORDER BY ordered_at DESC, id DESC LIMIT $limit START $offset; \
+             SELECT count() AS total FROM order_history WHERE user_id = $user_id \
+             AND ($status = NONE OR (status OR 'completed') = $status) \
+             AND ($from = NONE OR ordered_at >= $from) AND ($to = NONE OR ordered_at < $to) \
+             GROUP ALL;",
+            |sql| {
+                db.query(sql)
+                    .bind(("user_id", request.user_id.as_str()))
+                    .bind(("status", request.status))
+                    .bind(("from", request.from))
+                    .bind(("to", request.to))
+                    .bind(("limit", limit))
+                    .bind(("offset", offset))
+            },
+        )
+        .await?;
+        let orders: Vec<RecordedOrder> = response.take(0)?;
+        let total: Option<CountResult> = response.take(1)?;
+        Ok((orders, total.map(|c| c.total).unwrap_or(0)))
+    }
+
+    /// How many orders were recorded for `user_id`, whatever their status
+    pub async fn count_by_user(&self, user_id: &str) -> Result<u64, ProductServiceError> {
+        let db = self.db.handle()?;
+        let count: Option<CountResult> = traced_query(
+            "SELECT count() AS total FROM order_history WHERE user_id = $user_id GROUP ALL",
+            |sql| db.query(sql).bind(("user_id", user_id)),
+        )
+        .await?
+        .take(0)?;
+        Ok(count.map_or(0, |c| c.total as u64))
+    }
+
+    /// How many orders with `product_id` are waiting to ship or have
+    /// shipped and may still come back
+    pub async fn count_open_by_product(
+        &self,
+        product_id: &str,
+    ) -> Result<usize, ProductServiceError> {
+        let db = self.db.handle()?;
+        let count: Option<CountResult> = traced_query(
+            "SELECT count() AS total FROM order_history WHERE product_ids CONTAINS $product_id \
+             AND (status OR 'completed') IN ['completed', 'shipped'] GROUP ALL",
+            |sql| db.query(sql).bind(("product_id", product_id)),
+        )
+        .await?
+        .take(0)?;
+        Ok(count.map_or(0, |c| c.total))
+    }
+
+    /// How much of each product orders waiting to ship hold, for
+    /// reconciliation
+    pub async fn awaiting_shipment_quantities(
+        &self,
+    ) -> Result<HashMap<String, Quantity>, ProductServiceError> {
+        self.line_quantities(
+            "SELECT VALUE lines OR [] FROM order_history WHERE (status OR 'completed') = 'completed'",
+        )
+        .await
+    }
+
+    /// How much of each product orders past `completed` hold: shipped, or
+    /// already returned in part or in full. Their goods have left stock.
+    pub async fn shipped_quantities(
+        &self,
+    ) -> Result<HashMap<String, Quantity>, ProductServiceError> {
+        self.line_quantities(
+            "SELECT VALUE lines OR [] FROM order_history WHERE (status OR 'completed') != 'completed'",
+        )
+        .await
+    }
+
+    async fn line_quantities(
+        &self,
+        sql: &'static str,
+    ) -> Result<HashMap<String, Quantity>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let lines: Vec<Vec<OrderLine>> = traced_query(sql, |sql| db.query(sql)).await?.take(0)?;
+
+        let mut ordered: HashMap<String, Quantity> = HashMap::new();
+        for line in lines.into_iter().flatten() {
+            *ordered.entry(line.product_id).or_default() += line.quantity;
+        }
+        Ok(ordered)
+    }
+
+    /// Ships the order with `order_id` to `address` if it is waiting to
+    /// ship, in one statement so concurrent calls ship it once. `None` when
+    /// it is not waiting or does not exist.
+    pub async fn mark_shipped(
+        &self,
+        order_id: &str,
+        address: Address,
+    ) -> Result<Option<RecordedOrder>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let orders: Vec<RecordedOrder> = traced_query(
+            "UPDATE order_history SET status = 'shipped', shipping_address = $address, \
+             shipped_at = time::now() \
+             WHERE order_id = $order_id AND (status OR 'completed') = 'completed' RETURN AFTER",
+            |sql| {
+                db.query(sql)
+                    .bind(("order_id", order_id))
+                    .bind(("address", address))
+            },
+        )
+        .await?
+        .take(0)?;
+        Ok(orders.into_iter().next())
+    }
+
+    /// Moves an order to `status` after one of its returns completed
+    pub async fn set_status(
+        &self,
+        order: &Thing,
+        status: OrderStatus,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        traced_query("UPDATE $order SET status = $status", |sql| {
+            db.query(sql)
+                .bind(("order", order.clone()))
+                .bind(("status", status))
+        })
+        .await?
+        .check()?;
+        Ok(())
+    }
+
+    /// The products of every recorded order
+    pub async fn order_baskets(&self) -> Result<Vec<Vec<String>>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let baskets: Vec<Vec<String>> =
+            traced_query("SELECT VALUE product_ids FROM order_history", |sql| {
+                db.query(sql)
+            })
+            .await?
+            .take(0)?;
+        Ok(baskets)
+    }
+
+    /// Swaps the whole co-occurrence table for `rows` in one transaction, so
+    /// readers see either the previous build or the new one
+    pub async fn replace_co_occurrences(
+        &self,
+        rows: Vec<CoOccurrence>,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        let count = rows.len();
+        traced_query(
+            "BEGIN TRANSACTION; \
+             DELETE product_co_occurrence; \
+             INSERT INTO product_co_occurrence $rows; \
+             COMMIT TRANSACTION;",
+            |sql| db.query(sql).bind(("rows", rows)),
+        )
+        .await?
+        .check()?;
+
+        debug!("Replaced product co-occurrences with {} rows", count);
+        Ok(())
+    }
+
+    /// Products bought together with `product_id` that `org` can see, most
+    /// often first, with the number of shared orders
+    pub async fn bought_together(
+        &self,
+        product_id: &str,
+        org: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<(Product, u64)>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let rows: Vec<CoOccurrence> = traced_query(
+            "SELECT * FROM product_co_occurrence WHERE product_id = $product_id \
+             ORDER BY orders DESC",
+            |sql| db.query(sql).bind(("product_id", product_id)),
+        )
+        .await?
+        .take(0)?;
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<Thing> = rows
+            .iter()
+            .map(|row| Thing::from(("product", row.other_id.as_str())))
+            .collect();
+        let products: Vec<Product> = traced_query(
+            "SELECT * FROM product WHERE id INSIDE $ids \
+             AND (array::len(visible_to OR []) = 0 OR $org INSIDE visible_to)",
+            |sql| db.query(sql).bind(("ids", ids)).bind(("org", org)),
+        )
+        .await?
+        .take(0)?;
+
+        // Keep the co-occurrence order; deleted and hidden products drop out
+        let mut products: HashMap<String, Product> = products
+            .into_iter()
+            .map(|product| (product.id.id.to_raw(), product))
+            .collect();
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some((products.remove(&row.other_id)?, row.orders)))
+            .take(limit)
+            .collect())
+    }
+}
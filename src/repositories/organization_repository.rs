@@ -0,0 +1,213 @@
+use crate::{
+    errors::user_error::UserServiceError,
+    models::organization_model::{OrgMember, OrgRole, Organization, OrganizationForCreation},
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use surrealdb::sql::Thing;
+use tracing::debug;
+use uuid::Uuid;
+
+/// A user is linked to an organization by at most one `member_of` edge
+const MEMBERSHIP_INDEX: &str =
+    "DEFINE INDEX member_of_pair ON TABLE member_of COLUMNS in, out UNIQUE;";
+
+#[derive(Debug, Deserialize)]
+struct MembershipRole {
+    role: OrgRole,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountResult {
+    total: u64,
+}
+
+fn org_thing(org_id: &str) -> Thing {
+    Thing::from(("organization", org_id))
+}
+
+fn user_thing(user_id: &str) -> Thing {
+    Thing::from(("user", user_id))
+}
+
+/// Organizations and their `user->member_of->organization` edges, stored
+/// in the user database. Deleting a user deletes its edges with it.
+pub struct OrganizationRepository {
+    db: Arc<DbConnection>,
+}
+
+impl OrganizationRepository {
+    pub async fn new(db: Arc<DbConnection>) -> Result<Self, UserServiceError> {
+        let handle = db.handle()?;
+        traced_query(MEMBERSHIP_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        Ok(Self { db })
+    }
+
+    /// Creates the organization with `owner_id` as its first owner, in one
+    /// transaction
+    pub async fn create_org(
+        &self,
+        organization: OrganizationForCreation,
+        owner_id: &str,
+    ) -> Result<Organization, UserServiceError> {
+        let db = self.db.handle()?;
+        let org = org_thing(&Uuid::new_v4().simple().to_string());
+        let created: Vec<Organization> = traced_query(
+            "BEGIN TRANSACTION; \
+             CREATE $org CONTENT $content; \
+             RELATE $owner->member_of->$org SET role = 'owner', joined_at = time::now(); \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("org", org))
+                    .bind(("content", organization))
+                    .bind(("owner", user_thing(owner_id)))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        match created.into_iter().next() {
+            Some(organization) => {
+                debug!(
+                    "Created organization {} owned by {}",
+                    organization.id, owner_id
+                );
+                Ok(organization)
+            }
+            None => Err(UserServiceError::Internal(anyhow::anyhow!(
+                "Failed to create organization"
+            ))),
+        }
+    }
+
+    pub async fn get_org(&self, org_id: &str) -> Result<Organization, UserServiceError> {
+        let db = self.db.handle()?;
+        let organization: Option<Organization> =
+            traced_query("SELECT * FROM $id", |_| db.select(("organization", org_id))).await?;
+        organization.ok_or_else(|| UserServiceError::OrganizationNotFound {
+            id: org_id.to_string(),
+        })
+    }
+
+    /// The user's role in the organization, `None` when not a member
+    pub async fn role_of(
+        &self,
+        org_id: &str,
+        user_id: &str,
+    ) -> Result<Option<OrgRole>, UserServiceError> {
+        let db = self.db.handle()?;
+        let memberships: Vec<MembershipRole> = traced_query(
+            "SELECT role FROM member_of WHERE in = $user AND out = $org",
+            |sql| {
+                db.query(sql)
+                    .bind(("user", user_thing(user_id)))
+                    .bind(("org", org_thing(org_id)))
+            },
+        )
+        .await?
+        .take(0)?;
+        Ok(memberships.into_iter().next().map(|m| m.role))
+    }
+
+    /// Adds the user to the organization or changes their role. The last
+    /// owner check is repeated inside the transaction, so concurrent calls
+    /// cannot leave the organization without an owner.
+    pub async fn set_member(
+        &self,
+        org_id: &str,
+        user_id: &str,
+        role: OrgRole,
+    ) -> Result<(), UserServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "BEGIN TRANSACTION; \
+             LET $current = (SELECT VALUE role FROM member_of WHERE in = $user AND out = $org)[0]; \
+             LET $owners = count(SELECT VALUE id FROM member_of WHERE out = $org AND role = 'owner'); \
+             IF $current = 'owner' AND $role != 'owner' AND $owners <= 1 \
+             { THROW 'An organization must keep at least one owner' }; \
+             IF $current { UPDATE member_of SET role = $role WHERE in = $user AND out = $org } \
+             ELSE { RELATE $user->member_of->$org SET role = $role, joined_at = time::now() }; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("user", user_thing(user_id)))
+                    .bind(("org", org_thing(org_id)))
+                    .bind(("role", role))
+            },
+        )
+        .await?
+        .check()?;
+
+        debug!(
+            "Set {} as {} of organization {}",
+            user_id,
+            role.name(),
+            org_id
+        );
+        Ok(())
+    }
+
+    /// Removes the user's membership, unless they are the last owner
+    pub async fn remove_member(&self, org_id: &str, user_id: &str) -> Result<(), UserServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "BEGIN TRANSACTION; \
+             LET $current = (SELECT VALUE role FROM member_of WHERE in = $user AND out = $org)[0]; \
+             LET $owners = count(SELECT VALUE id FROM member_of WHERE out = $org AND role = 'owner'); \
+             IF $current = 'owner' AND $owners <= 1 \
+             { THROW 'An organization must keep at least one owner' }; \
+             DELETE member_of WHERE in = $user AND out = $org; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("user", user_thing(user_id)))
+                    .bind(("org", org_thing(org_id)))
+            },
+        )
+        .await?
+        .check()?;
+
+        debug!("Removed {} from organization {}", user_id, org_id);
+        Ok(())
+    }
+
+    /// Number of organizations the user belongs to
+    pub async fn membership_count(&self, user_id: &str) -> Result<u64, UserServiceError> {
+        let db = self.db.handle()?;
+        let count: Option<CountResult> = traced_query(
+            "SELECT count() AS total FROM member_of WHERE in = $user GROUP ALL",
+            |sql| db.query(sql).bind(("user", user_thing(user_id))),
+        )
+        .await?
+        .take(0)?;
+        Ok(count.map(|c| c.total).unwrap_or(0))
+    }
+
+    /// Number of owners, for checks made before a write
+    pub async fn owner_count(&self, org_id: &str) -> Result<usize, UserServiceError> {
+        Ok(self
+            .members(org_id)
+            .await?
+            .iter()
+            .filter(|member| member.role == OrgRole::Owner)
+            .count())
+    }
+
+    /// Earliest joined first
+    pub async fn members(&self, org_id: &str) -> Result<Vec<OrgMember>, UserServiceError> {
+        let db = self.db.handle()?;
+        let members: Vec<OrgMember> = traced_query(
+            "SELECT meta::id(in) AS user_id, in.name AS name, role, joined_at \
+             FROM member_of WHERE out = $org ORDER BY joined_at",
+            |sql| db.query(sql).bind(("org", org_thing(org_id))),
+        )
+        .await?
+        .take(0)?;
+        Ok(members)
+    }
+}
@@ -1,49 +1,134 @@
-use crate::{errors::product_error::ProductServiceError, models::product_model::Product};
-use surrealdb::{engine::local::Mem, Surreal};
-use tracing::{error, info};
+use crate::{
+    config::database::DatabaseConfig,
+    errors::product_error::ProductServiceError,
+    models::product_model::{
+        CategoryStats, PriceChange, PriceChangeForCreation, Product, ProductForCreation,
+        ProductName, ProductSortField, ProductStats, ProductTranslation, ScheduledPriceChange,
+        ScheduledPriceChangeForCreation, SortDirection, UnitStock,
+    },
+    repositories::connection::{is_unique_violation, DbConnection},
+    telemetry::query_metrics::traced_query,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use surrealdb::sql::Thing;
+use tracing::{debug, error, info};
+
+/// Indexes backing the `list_products` sort fields
+const SORT_INDEXES: &str = "\
+    DEFINE INDEX product_created_at ON TABLE product COLUMNS created_at; \
+    DEFINE INDEX product_name ON TABLE product COLUMNS name; \
+    DEFINE INDEX product_price ON TABLE product COLUMNS price; \
+    DEFINE INDEX product_stock_quantity ON TABLE product COLUMNS stock_quantity; \
+    DEFINE INDEX product_category ON TABLE product COLUMNS category;";
+
+/// SKUs are unique, so two products can never claim one warehouse item
+const SKU_INDEX: &str = "DEFINE INDEX product_sku ON TABLE product COLUMNS sku UNIQUE;";
+
+/// Product query limited to what the caller's organization, bound as
+/// `$org`, can see: public products (no `visible_to`) and those restricted
+/// to `$org`. Callers without an organization bind `NONE` and see public
+/// products only.
+macro_rules! visible_products {
+    ($rest:literal) => {
+        concat!(
+            "SELECT * FROM product WHERE (array::len(visible_to OR []) = 0 OR $org INSIDE visible_to) ",
+            $rest
+        )
+    };
+}
+
+#[derive(Debug, Deserialize)]
+struct CountResult {
+    total: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogTotals {
+    products: usize,
+    inventory_value: f64,
+    average_price: f64,
+    out_of_stock: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct CategoryUnitStock {
+    category: String,
+    #[serde(flatten)]
+    stock: UnitStock,
+}
 
 pub struct ProductRepository {
-    db: Surreal<surrealdb::engine::local::Db>,
+    db: Arc<DbConnection>,
 }
 
 impl ProductRepository {
-    pub async fn new() -> Result<Self, ProductServiceError> {
-        let db = Surreal::new::<Mem>(()).await?;
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, ProductServiceError> {
+        let db = DbConnection::open(config).await?;
 
-        // Use a namespace and database
-        db.use_ns("product_service").use_db("products").await?;
-
-        info!("Connected to SurrealDB for Product Service");
+        info!(
+            "Connected to SurrealDB for Product Service at {} ({}/{})",
+            config.endpoint, config.namespace, config.database
+        );
 
+        let handle = db.handle()?;
+        traced_query(SORT_INDEXES, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        traced_query(SKU_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
         Ok(Self { db })
     }
 
-    pub async fn create_product(&self, product: Product) -> Result<Product, ProductServiceError> {
-        // Check if product with name already exists
-        let existing: Vec<Product> = self
-            .db
-            .query("SELECT * FROM product WHERE name = $name")
-            .bind(("name", &product.name))
+    /// Connection shared with the repositories that use the product database
+    pub fn connection(&self) -> Arc<DbConnection> {
+        Arc::clone(&self.db)
+    }
+
+    /// Fails when a product named `name` already exists
+    pub async fn ensure_name_available(&self, name: &str) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        let existing: Vec<Product> =
+            traced_query("SELECT * FROM product WHERE name = $name", |sql| {
+                db.query(sql).bind(("name", name))
+            })
             .await?
             .take(0)?;
 
         if !existing.is_empty() {
             return Err(ProductServiceError::ProductAlreadyExists {
-                name: product.name.clone(),
+                name: name.to_string(),
             });
         }
+        Ok(())
+    }
+
+    pub async fn create_product(&self, product: Product) -> Result<Product, ProductServiceError> {
+        self.ensure_name_available(&product.name).await?;
+        let db = self.db.handle()?;
 
         // Create the product - let SurrealDB generate the ID
         let product_for_creation = product.for_creation();
-        let created: Vec<Product> = self
-            .db
-            .create("product")
-            .content(product_for_creation)
-            .await?;
+        let created: Vec<Product> = match traced_query("CREATE product CONTENT $content", |_| {
+            db.create("product").content(product_for_creation)
+        })
+        .await
+        {
+            Ok(created) => created,
+            Err(err) if is_unique_violation(&err, "product_sku") => {
+                return Err(ProductServiceError::SkuAlreadyExists {
+                    sku: product.sku.unwrap_or_default(),
+                });
+            }
+            Err(err) => return Err(err.into()),
+        };
 
         match created.into_iter().next() {
             Some(product) => {
-                info!("Created product with id: {}", product.id);
+                debug!("Created product with id: {}", product.id);
                 Ok(product)
             }
             None => {
@@ -56,40 +141,56 @@ impl ProductRepository {
     }
 
     pub async fn get_product(&self, id: &str) -> Result<Product, ProductServiceError> {
-        let product: Option<Product> = self.db.select(("product", id)).await?;
+        let db = self.db.handle()?;
+        let product: Option<Product> =
+            traced_query("SELECT * FROM $id", |_| db.select(("product", id))).await?;
 
         match product {
             Some(product) => {
-                info!("Retrieved product with id: {}", id);
+                debug!("Retrieved product with id: {}", id);
                 Ok(product)
             }
             None => Err(ProductServiceError::ProductNotFound { id: id.to_string() }),
         }
     }
 
-    pub async fn list_products(&self) -> Result<Vec<Product>, ProductServiceError> {
-        let products: Vec<Product> = self
-            .db
-            .query("SELECT * FROM product ORDER BY created_at DESC")
+    /// Every product `org` can see in the given order, ties broken by id
+    pub async fn list_products(
+        &self,
+        sort_by: ProductSortField,
+        direction: SortDirection,
+        org: Option<&str>,
+    ) -> Result<Vec<Product>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let products: Vec<Product> =
+            traced_query(list_statement(sort_by, direction), |sql| {
+                db.query(sql).bind(("org", org))
+            })
             .await?
             .take(0)?;
 
-        info!("Retrieved {} products", products.len());
+        debug!("Retrieved {} products", products.len());
         Ok(products)
     }
 
     pub async fn get_products_by_category(
         &self,
         category: &str,
+        org: Option<&str>,
     ) -> Result<Vec<Product>, ProductServiceError> {
-        let products: Vec<Product> = self
-            .db
-            .query("SELECT * FROM product WHERE category = $category ORDER BY name")
-            .bind(("category", category))
-            .await?
-            .take(0)?;
+        let db = self.db.handle()?;
+        let products: Vec<Product> = traced_query(
+            visible_products!("AND category = $category ORDER BY name"),
+            |sql| {
+                db.query(sql)
+                    .bind(("category", category))
+                    .bind(("org", org))
+            },
+        )
+        .await?
+        .take(0)?;
 
-        info!(
+        debug!(
             "Retrieved {} products in category '{}'",
             products.len(),
             category
@@ -97,51 +198,437 @@ impl ProductRepository {
         Ok(products)
     }
 
-    pub async fn update_product_stock(
+    /// The products among `ids`, whoever can see them
+    pub async fn get_products(&self, ids: &[String]) -> Result<Vec<Product>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let things: Vec<Thing> = ids
+            .iter()
+            .map(|id| Thing::from(("product", id.as_str())))
+            .collect();
+        let products: Vec<Product> =
+            traced_query("SELECT * FROM product WHERE id INSIDE $ids", |sql| {
+                db.query(sql).bind(("ids", things))
+            })
+            .await?
+            .take(0)?;
+
+        Ok(products)
+    }
+
+    /// The products with any of `skus`
+    pub async fn get_products_by_sku(
         &self,
-        id: &str,
-        new_quantity: i32,
-    ) -> Result<Product, ProductServiceError> {
-        // First get the current product
-        let _product = self.get_product(id).await?;
-
-        // Update the stock quantity
-        let updated: Vec<Product> = self
-            .db
-            .query("UPDATE $id SET stock_quantity = $quantity, updated_at = time::now()")
-            .bind(("id", format!("product:{}", id)))
-            .bind(("quantity", new_quantity))
+        skus: &[String],
+    ) -> Result<Vec<Product>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let products: Vec<Product> =
+            traced_query("SELECT * FROM product WHERE sku INSIDE $skus", |sql| {
+                db.query(sql).bind(("skus", skus))
+            })
             .await?
             .take(0)?;
 
-        match updated.into_iter().next() {
-            Some(product) => {
-                info!(
-                    "Updated stock for product {}: new quantity = {}",
-                    id, new_quantity
-                );
-                Ok(product)
-            }
-            None => {
-                error!("Failed to update product stock");
-                Err(ProductServiceError::Internal(anyhow::anyhow!(
-                    "Failed to update product stock"
-                )))
+        Ok(products)
+    }
+
+    /// The products among `ids` that `org` can see, by name
+    pub async fn get_visible_products(
+        &self,
+        ids: &[String],
+        org: Option<&str>,
+    ) -> Result<Vec<Product>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let things: Vec<Thing> = ids
+            .iter()
+            .map(|id| Thing::from(("product", id.as_str())))
+            .collect();
+        let products: Vec<Product> = traced_query(
+            visible_products!("AND id INSIDE $ids ORDER BY name ASC, id ASC"),
+            |sql| db.query(sql).bind(("ids", things)).bind(("org", org)),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(products)
+    }
+
+    /// Returns one page of products in a stable order, for bulk export, and
+    /// the total number of products, both read in one transaction
+    pub async fn export_products(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<Product>, usize), ProductServiceError> {
+        let db = self.db.handle()?;
+        let mut response = traced_query(
+            "BEGIN TRANSACTION; \
+             SELECT * FROM product ORDER BY created_at, id LIMIT $limit START $offset; \
+             SELECT count() AS total FROM product GROUP ALL; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("limit", limit))
+                    .bind(("offset", offset))
+            },
+        )
+        .await?;
+        let products: Vec<Product> = response.take(0)?;
+        let count: Option<CountResult> = response.take(1)?;
+
+        debug!(
+            "Exported {} products from offset {}",
+            products.len(),
+            offset
+        );
+        Ok((products, count.map(|c| c.total).unwrap_or(0)))
+    }
+
+    /// Catalog-wide and per-category aggregates, computed by the database.
+    /// Stock is summed per unit; products from before units were tracked
+    /// count pieces.
+    pub async fn product_stats(&self) -> Result<ProductStats, ProductServiceError> {
+        let db = self.db.handle()?;
+        let mut response = traced_query(
+            "SELECT count() AS products, \
+             math::sum(price * stock_quantity) AS inventory_value, \
+             math::mean(price) AS average_price, \
+             count(stock_quantity <= 0) AS out_of_stock FROM product GROUP ALL; \
+             SELECT unit OR 'piece' AS unit, count() AS products, \
+             math::sum(stock_quantity) AS total_stock, \
+             math::sum(price * stock_quantity) AS inventory_value \
+             FROM product GROUP BY unit ORDER BY unit; \
+             SELECT category, count() AS products, \
+             math::sum(price * stock_quantity) AS inventory_value, \
+             math::mean(price) AS average_price, \
+             count(stock_quantity <= 0) AS out_of_stock \
+             FROM product GROUP BY category ORDER BY category; \
+             SELECT category, unit OR 'piece' AS unit, count() AS products, \
+             math::sum(stock_quantity) AS total_stock, \
+             math::sum(price * stock_quantity) AS inventory_value \
+             FROM product GROUP BY category, unit ORDER BY category, unit",
+            |sql| db.query(sql),
+        )
+        .await?;
+        let totals: Option<CatalogTotals> = response.take(0)?;
+        let stock_by_unit: Vec<UnitStock> = response.take(1)?;
+        let mut categories: Vec<CategoryStats> = response.take(2)?;
+        let category_units: Vec<CategoryUnitStock> = response.take(3)?;
+
+        for row in category_units {
+            if let Some(category) = categories.iter_mut().find(|c| c.category == row.category) {
+                category.stock_by_unit.push(row.stock);
             }
         }
+
+        // An empty catalog has no rows to aggregate
+        let totals = totals.unwrap_or(CatalogTotals {
+            products: 0,
+            inventory_value: 0.0,
+            average_price: 0.0,
+            out_of_stock: 0,
+        });
+        Ok(ProductStats {
+            total_products: totals.products,
+            inventory_value: totals.inventory_value,
+            average_price: totals.average_price,
+            out_of_stock: totals.out_of_stock,
+            stock_by_unit,
+            categories,
+            generated_at: Utc::now(),
+        })
+    }
+
+    /// Returns which of `names` are already taken
+    pub async fn existing_product_names(
+        &self,
+        names: &[String],
+    ) -> Result<HashSet<String>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let existing: Vec<String> = traced_query(
+            "SELECT VALUE name FROM product WHERE name INSIDE $names",
+            |sql| db.query(sql).bind(("names", names)),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(existing.into_iter().collect())
+    }
+
+    /// Inserts a batch of products in a single statement. Callers are
+    /// responsible for checking name uniqueness first.
+    pub async fn create_products(
+        &self,
+        products: &[Product],
+    ) -> Result<Vec<Product>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let rows: Vec<ProductForCreation> = products.iter().map(Product::for_creation).collect();
+        let created: Vec<Product> = traced_query("INSERT INTO product $rows", |sql| {
+            db.query(sql).bind(("rows", rows))
+        })
+        .await?
+        .take(0)?;
+
+        debug!("Inserted batch of {} products", created.len());
+        Ok(created)
+    }
+
+    /// Replaces a product's translations
+    pub async fn set_translations(
+        &self,
+        id: &str,
+        translations: &BTreeMap<String, ProductTranslation>,
+    ) -> Result<Product, ProductServiceError> {
+        let db = self.db.handle()?;
+        let updated: Option<Product> = traced_query(
+            "UPDATE $product SET translations = $translations, updated_at = time::now()",
+            |sql| {
+                db.query(sql)
+                    .bind(("product", Thing::from(("product", id))))
+                    .bind(("translations", translations))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        updated.ok_or_else(|| ProductServiceError::ProductNotFound { id: id.to_string() })
+    }
+
+    /// Every product's id and name
+    pub async fn product_names(&self) -> Result<Vec<ProductName>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let names: Vec<ProductName> =
+            traced_query("SELECT id, name FROM product", |sql| db.query(sql))
+                .await?
+                .take(0)?;
+
+        Ok(names)
+    }
+
+    /// The id and name of every product `org` can see
+    pub async fn visible_product_names(
+        &self,
+        org: Option<&str>,
+    ) -> Result<Vec<ProductName>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let names: Vec<ProductName> = traced_query(
+            "SELECT id, name FROM product \
+             WHERE array::len(visible_to OR []) = 0 OR $org INSIDE visible_to",
+            |sql| db.query(sql).bind(("org", org)),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(names)
+    }
+
+    /// Sets the product's SKU, or removes it with `None`
+    pub async fn set_sku(
+        &self,
+        id: &str,
+        sku: Option<&str>,
+    ) -> Result<Product, ProductServiceError> {
+        let db = self.db.handle()?;
+        let mut response = match traced_query(
+            "UPDATE $product SET sku = $sku, updated_at = time::now()",
+            |sql| {
+                db.query(sql)
+                    .bind(("product", Thing::from(("product", id))))
+                    .bind(("sku", sku))
+            },
+        )
+        .await?
+        .check()
+        {
+            Ok(response) => response,
+            Err(err) if is_unique_violation(&err, "product_sku") => {
+                return Err(ProductServiceError::SkuAlreadyExists {
+                    sku: sku.unwrap_or_default().to_string(),
+                });
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let updated: Option<Product> = response.take(0)?;
+
+        updated.ok_or_else(|| ProductServiceError::ProductNotFound { id: id.to_string() })
+    }
+
+    /// Restricts the product to `visible_to`, or makes it public when empty
+    pub async fn set_visibility(
+        &self,
+        id: &str,
+        visible_to: &[String],
+    ) -> Result<Product, ProductServiceError> {
+        let db = self.db.handle()?;
+        let updated: Option<Product> = traced_query(
+            "UPDATE $product SET visible_to = $visible_to, updated_at = time::now()",
+            |sql| {
+                db.query(sql)
+                    .bind(("product", Thing::from(("product", id))))
+                    .bind(("visible_to", visible_to))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        updated.ok_or_else(|| ProductServiceError::ProductNotFound { id: id.to_string() })
     }
 
     pub async fn get_product_by_name(
         &self,
         name: &str,
     ) -> Result<Option<Product>, ProductServiceError> {
-        let products: Vec<Product> = self
-            .db
-            .query("SELECT * FROM product WHERE name = $name")
-            .bind(("name", name))
+        let db = self.db.handle()?;
+        let products: Vec<Product> =
+            traced_query("SELECT * FROM product WHERE name = $name", |sql| {
+                db.query(sql).bind(("name", name))
+            })
             .await?
             .take(0)?;
 
         Ok(products.into_iter().next())
     }
+
+    /// Appends entries to `price_history`
+    pub async fn record_price_changes(
+        &self,
+        changes: &[PriceChangeForCreation],
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        traced_query("INSERT INTO price_history $rows", |sql| {
+            db.query(sql).bind(("rows", changes))
+        })
+        .await?
+        .check()?;
+
+        debug!("Recorded {} price changes", changes.len());
+        Ok(())
+    }
+
+    pub async fn get_price_history(
+        &self,
+        product_id: &str,
+        limit: usize,
+    ) -> Result<Vec<PriceChange>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let changes: Vec<PriceChange> = traced_query(
+            "SELECT * FROM price_history WHERE product_id = $product_id ORDER BY changed_at DESC LIMIT $limit",
+            |sql| {
+                db
+                    .query(sql)
+                    .bind(("product_id", product_id))
+                    .bind(("limit", limit))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        Ok(changes)
+    }
+
+    pub async fn schedule_price_change(
+        &self,
+        change: ScheduledPriceChangeForCreation,
+    ) -> Result<ScheduledPriceChange, ProductServiceError> {
+        let db = self.db.handle()?;
+        let created: Vec<ScheduledPriceChange> =
+            traced_query("CREATE scheduled_price_change CONTENT $content", |_| {
+                db.create("scheduled_price_change").content(change)
+            })
+            .await?;
+
+        created.into_iter().next().ok_or_else(|| {
+            error!("Failed to schedule price change");
+            ProductServiceError::Internal(anyhow::anyhow!("Failed to schedule price change"))
+        })
+    }
+
+    /// Pending scheduled changes, soonest first. Pass `product_id` to only
+    /// return changes for one product.
+    pub async fn pending_price_changes(
+        &self,
+        product_id: Option<&str>,
+    ) -> Result<Vec<ScheduledPriceChange>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let mut response = match product_id {
+            Some(product_id) => {
+                traced_query(
+                    "SELECT * FROM scheduled_price_change WHERE status = 'pending' AND product_id = $product_id ORDER BY effective_at",
+                    |sql| db.query(sql).bind(("product_id", product_id)),
+                )
+                .await?
+            }
+            None => {
+                traced_query(
+                    "SELECT * FROM scheduled_price_change WHERE status = 'pending' ORDER BY effective_at",
+                    |sql| db.query(sql),
+                )
+                .await?
+            }
+        };
+
+        Ok(response.take(0)?)
+    }
+
+    /// Sets the product's price, records it in `price_history` and marks the
+    /// scheduled change applied, all in one transaction
+    pub async fn apply_scheduled_price_change(
+        &self,
+        change: &ScheduledPriceChange,
+        old_price: f64,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "BEGIN TRANSACTION; \
+             UPDATE $product SET price = $price, updated_at = time::now(); \
+             CREATE price_history CONTENT $history; \
+             UPDATE $change SET status = 'applied'; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind((
+                        "product",
+                        Thing::from(("product", change.product_id.as_str())),
+                    ))
+                    .bind(("price", change.new_price))
+                    .bind((
+                        "history",
+                        PriceChangeForCreation {
+                            product_id: change.product_id.clone(),
+                            old_price: Some(old_price),
+                            new_price: change.new_price,
+                            source: "scheduled".to_string(),
+                            changed_at: chrono::Utc::now(),
+                        },
+                    ))
+                    .bind(("change", change.id.clone()))
+            },
+        )
+        .await?
+        .check()?;
+
+        debug!(
+            "Applied scheduled price change {} to product {}: {} -> {}",
+            change.id, change.product_id, old_price, change.new_price
+        );
+        Ok(())
+    }
+}
+
+/// Statements are picked from a fixed set rather than built from the
+/// request, so sort parameters can never reach the query text
+fn list_statement(sort_by: ProductSortField, direction: SortDirection) -> &'static str {
+    use ProductSortField::*;
+    use SortDirection::*;
+    match (sort_by, direction) {
+        (CreatedAt, Asc) => visible_products!("ORDER BY created_at ASC, id ASC"),
+        (CreatedAt, Desc) => visible_products!("ORDER BY created_at DESC, id ASC"),
+        (Name, Asc) => visible_products!("ORDER BY name ASC, id ASC"),
+        (Name, Desc) => visible_products!("ORDER BY name DESC, id ASC"),
+        (Price, Asc) => visible_products!("ORDER BY price ASC, id ASC"),
+        (Price, Desc) => visible_products!("ORDER BY price DESC, id ASC"),
+        (StockQuantity, Asc) => visible_products!("ORDER BY stock_quantity ASC, id ASC"),
+        (StockQuantity, Desc) => visible_products!("ORDER BY stock_quantity DESC, id ASC"),
+        (Category, Asc) => visible_products!("ORDER BY category ASC, id ASC"),
+        (Category, Desc) => visible_products!("ORDER BY category DESC, id ASC"),
+    }
 }
@@ -0,0 +1,256 @@
+use crate::{
+    errors::product_error::ProductServiceError,
+    models::quantity_model::Quantity,
+    models::recommendation_model::OrderLine,
+    models::return_model::{ProductReturn, ReturnForCreation, ReturnItem, ReturnStatus},
+    repositories::connection::DbConnection,
+    repositories::inventory_repository::{product_thing, stock_thing},
+    telemetry::query_metrics::traced_query,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use surrealdb::sql::Thing;
+use tracing::{debug, error};
+
+/// One returned line as bound into the completion transaction
+#[derive(Debug, Serialize)]
+struct Restock {
+    row: Thing,
+    product: Thing,
+    product_id: String,
+    location: String,
+    quantity: Quantity,
+}
+
+/// One returned line with the quantity ordered, for the check that runs
+/// in the creating transaction
+#[derive(Debug, Serialize)]
+struct ReturnLimit {
+    product_id: String,
+    quantity: Quantity,
+    ordered: Quantity,
+}
+
+/// Returns are summed per order before a new one is opened
+const ORDER_ID_INDEX: &str = "DEFINE INDEX product_return_order_id \
+     ON TABLE product_return COLUMNS order_id;";
+
+/// Returns against recorded orders, stored in the product database as
+/// `product_return`
+pub struct ReturnRepository {
+    db: Arc<DbConnection>,
+}
+
+fn return_thing(return_id: &str) -> Thing {
+    Thing::from(("product_return", return_id))
+}
+
+impl ReturnRepository {
+    pub async fn new(db: Arc<DbConnection>) -> Result<Self, ProductServiceError> {
+        let handle = db.handle()?;
+        traced_query(ORDER_ID_INDEX, |sql| handle.query(sql))
+            .await?
+            .check()?;
+        Ok(Self { db })
+    }
+
+    /// Creates the return unless, together with the returns already opened
+    /// against the order, it would return more of a product than `lines`
+    /// says was ordered. The check and the insert run in one transaction,
+    /// so concurrent returns cannot both take the last units.
+    pub async fn create_return(
+        &self,
+        product_return: ReturnForCreation,
+        lines: &[OrderLine],
+    ) -> Result<ProductReturn, ProductServiceError> {
+        let db = self.db.handle()?;
+        let limits: Vec<ReturnLimit> = product_return
+            .items
+            .iter()
+            .map(|item| ReturnLimit {
+                product_id: item.product_id.clone(),
+                quantity: item.quantity,
+                ordered: lines
+                    .iter()
+                    .find(|line| line.product_id == item.product_id)
+                    .map_or(Quantity::ZERO, |line| line.quantity),
+            })
+            .collect();
+
+        let created: Vec<ProductReturn> = traced_query(
+            "BEGIN TRANSACTION; \
+             LET $returned = array::flatten(\
+             (SELECT VALUE items FROM product_return WHERE order_id = $content.order_id)); \
+             FOR $limit IN $limits { \
+             IF math::sum($returned[WHERE product_id = $limit.product_id].quantity) \
+             + $limit.quantity > $limit.ordered { \
+             THROW 'Return exceeds the quantity ordered' }; \
+             }; \
+             CREATE product_return CONTENT $content; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("content", product_return))
+                    .bind(("limits", limits))
+            },
+        )
+        .await?
+        .take(2)?;
+
+        match created.into_iter().next() {
+            Some(product_return) => {
+                debug!(
+                    "Created return {} for order {}",
+                    product_return.id, product_return.order_id
+                );
+                Ok(product_return)
+            }
+            None => {
+                error!("Failed to create return");
+                Err(ProductServiceError::Internal(anyhow::anyhow!(
+                    "Failed to create return"
+                )))
+            }
+        }
+    }
+
+    /// How much of each product the returns opened against `order_id`
+    /// take back, counting only returns in `status` if one is given
+    pub async fn returned_quantities(
+        &self,
+        order_id: &str,
+        status: Option<ReturnStatus>,
+    ) -> Result<HashMap<String, Quantity>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let items: Vec<Vec<ReturnItem>> = traced_query(
+            "SELECT VALUE items FROM product_return WHERE order_id = $order_id \
+             AND ($status = NONE OR status = $status)",
+            |sql| {
+                db.query(sql)
+                    .bind(("order_id", order_id))
+                    .bind(("status", status))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        let mut returned: HashMap<String, Quantity> = HashMap::new();
+        for item in items.into_iter().flatten() {
+            *returned.entry(item.product_id).or_default() += item.quantity;
+        }
+        Ok(returned)
+    }
+
+    /// How much of each product completed returns took back, across every
+    /// order, for reconciliation
+    pub async fn completed_quantities(
+        &self,
+    ) -> Result<HashMap<String, Quantity>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let items: Vec<Vec<ReturnItem>> = traced_query(
+            "SELECT VALUE items FROM product_return WHERE status = 'completed'",
+            |sql| db.query(sql),
+        )
+        .await?
+        .take(0)?;
+
+        let mut returned: HashMap<String, Quantity> = HashMap::new();
+        for item in items.into_iter().flatten() {
+            *returned.entry(item.product_id).or_default() += item.quantity;
+        }
+        Ok(returned)
+    }
+
+    pub async fn get_return(&self, return_id: &str) -> Result<ProductReturn, ProductServiceError> {
+        let db = self.db.handle()?;
+        let product_return: Option<ProductReturn> = traced_query("SELECT * FROM $return", |_| {
+            db.select(("product_return", return_id))
+        })
+        .await?;
+
+        product_return.ok_or_else(|| ProductServiceError::ReturnNotFound {
+            id: return_id.to_string(),
+        })
+    }
+
+    /// Moves a `requested` return to `approved`. `None` if it was not
+    /// `requested` any more when the update ran.
+    pub async fn approve(
+        &self,
+        return_id: &str,
+    ) -> Result<Option<ProductReturn>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let approved: Vec<ProductReturn> = traced_query(
+            "UPDATE $return SET status = 'approved', approved_at = time::now(), \
+             updated_at = time::now() WHERE status = 'requested' RETURN AFTER",
+            |sql| db.query(sql).bind(("return", return_thing(return_id))),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(approved.into_iter().next())
+    }
+
+    /// Moves an `approved` return to `completed` in one transaction that
+    /// restocks every item at its location through the `stock_movement`
+    /// ledger, recomputes the products' totals and adds the refund to the
+    /// order's `refunded_amount`. Fails without changing anything if the
+    /// return was not `approved` any more.
+    pub async fn complete(
+        &self,
+        product_return: &ProductReturn,
+        order: &Thing,
+        default_location: &str,
+    ) -> Result<(), ProductServiceError> {
+        let db = self.db.handle()?;
+        let restock: Vec<Restock> = product_return
+            .items
+            .iter()
+            .map(|item| {
+                let location = item.location.as_deref().unwrap_or(default_location);
+                Restock {
+                    row: stock_thing(&item.product_id, location),
+                    product: product_thing(&item.product_id),
+                    product_id: item.product_id.clone(),
+                    location: location.to_string(),
+                    quantity: item.quantity,
+                }
+            })
+            .collect();
+
+        traced_query(
+            "BEGIN TRANSACTION; \
+             LET $done = (UPDATE $return SET status = 'completed', completed_at = time::now(), \
+             updated_at = time::now() WHERE status = 'approved' RETURN AFTER); \
+             IF array::len($done) = 0 { THROW 'Return is no longer approved' }; \
+             FOR $item IN $restock { \
+             UPDATE $item.row SET product_id = $item.product_id, location = $item.location, \
+             quantity = (quantity OR 0) + $item.quantity, updated_at = time::now(); \
+             CREATE stock_movement CONTENT { product_id: $item.product_id, \
+             location: $item.location, kind: 'return', change: $item.quantity, \
+             quantity_after: (SELECT VALUE quantity FROM ONLY $item.row), moved_at: time::now() }; \
+             UPDATE $item.product SET stock_quantity = math::sum(\
+             (SELECT VALUE quantity FROM stock WHERE product_id = $item.product_id)), \
+             updated_at = time::now(); \
+             }; \
+             UPDATE $order SET refunded_amount = (refunded_amount OR 0) + $refund_total; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("return", product_return.id.clone()))
+                    .bind(("restock", restock))
+                    .bind(("order", order.clone()))
+                    .bind(("refund_total", product_return.refund_total))
+            },
+        )
+        .await?
+        .check()?;
+
+        debug!(
+            "Completed return {} for order {}, refunded {}",
+            product_return.id, product_return.order_id, product_return.refund_total
+        );
+        Ok(())
+    }
+}
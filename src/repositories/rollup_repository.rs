@@ -0,0 +1,161 @@
+use crate::{
+    errors::product_error::ProductServiceError,
+    models::product_model::{CategoryRollup, UnitStock},
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Keeps `category_rollup` in step with `product`. The event runs inside
+/// the transaction of every product write, whichever query made it, and
+/// moves the product's old values out of its old category and unit and
+/// its new values into its new ones. There is one row per category and
+/// unit, since stock in different units does not add up. Rows left
+/// without products are dropped.
+const CATEGORY_ROLLUP_EVENT: &str = "\
+    DEFINE EVENT category_rollup ON TABLE product \
+    WHEN $before.category != $after.category OR $before.price != $after.price \
+        OR $before.stock_quantity != $after.stock_quantity OR $before.unit != $after.unit \
+    THEN { \
+        IF $before.category != NONE { \
+            UPDATE type::thing('category_rollup', [$before.category, $before.unit OR 'piece']) SET \
+                category = $before.category, \
+                unit = $before.unit OR 'piece', \
+                products = (products OR 0) - 1, \
+                total_stock = (total_stock OR 0) - $before.stock_quantity, \
+                inventory_value = (inventory_value OR 0) - $before.price * $before.stock_quantity, \
+                updated_at = time::now(); \
+        }; \
+        IF $after.category != NONE { \
+            UPDATE type::thing('category_rollup', [$after.category, $after.unit OR 'piece']) SET \
+                category = $after.category, \
+                unit = $after.unit OR 'piece', \
+                products = (products OR 0) + 1, \
+                total_stock = (total_stock OR 0) + $after.stock_quantity, \
+                inventory_value = (inventory_value OR 0) + $after.price * $after.stock_quantity, \
+                updated_at = time::now(); \
+        }; \
+        DELETE category_rollup WHERE products <= 0; \
+    };";
+
+#[derive(Debug, Deserialize)]
+struct CountResult {
+    total: usize,
+}
+
+/// One `category_rollup` row: a category's products in one unit
+#[derive(Debug, Deserialize)]
+struct RollupRow {
+    category: String,
+    #[serde(flatten)]
+    stock: UnitStock,
+    updated_at: DateTime<Utc>,
+}
+
+/// Per-category product counts, stock and inventory value, maintained by
+/// the database as products change so dashboards never scan the catalog
+pub struct CategoryRollupRepository {
+    db: Arc<DbConnection>,
+}
+
+impl CategoryRollupRepository {
+    /// Defines the rollup event, and builds the rollups from the catalog
+    /// when there are none yet, e.g. on the first start after an upgrade,
+    /// or when they predate the split by unit
+    pub async fn new(db: Arc<DbConnection>) -> Result<Self, ProductServiceError> {
+        let handle = db.handle()?;
+        traced_query(CATEGORY_ROLLUP_EVENT, |sql| handle.query(sql))
+            .await?
+            .check()?;
+
+        let repository = Self { db };
+        if repository.needs_rebuild().await? {
+            let categories = repository.rebuild().await?;
+            if categories > 0 {
+                info!("Built rollups for {} categories", categories);
+            }
+        }
+        Ok(repository)
+    }
+
+    /// Whether there are no rollups, or some without a unit
+    async fn needs_rebuild(&self) -> Result<bool, ProductServiceError> {
+        let db = self.db.handle()?;
+        let mut response = traced_query(
+            "SELECT count() AS total FROM category_rollup GROUP ALL; \
+             SELECT count() AS total FROM category_rollup WHERE unit = NONE GROUP ALL",
+            |sql| db.query(sql),
+        )
+        .await?;
+        let rows: Option<CountResult> = response.take(0)?;
+        let without_unit: Option<CountResult> = response.take(1)?;
+        Ok(rows.map_or(0, |count| count.total) == 0
+            || without_unit.map_or(0, |count| count.total) > 0)
+    }
+
+    /// Every category's rollup, sorted by category, with its stock per unit
+    pub async fn rollups(&self) -> Result<Vec<CategoryRollup>, ProductServiceError> {
+        let db = self.db.handle()?;
+        let rows: Vec<RollupRow> = traced_query(
+            "SELECT category, unit, products, total_stock, inventory_value, updated_at \
+             FROM category_rollup ORDER BY category, unit",
+            |sql| db.query(sql),
+        )
+        .await?
+        .take(0)?;
+
+        let mut rollups: Vec<CategoryRollup> = Vec::new();
+        for row in rows {
+            match rollups.last_mut() {
+                Some(rollup) if rollup.category == row.category => {
+                    rollup.products += row.stock.products;
+                    rollup.inventory_value += row.stock.inventory_value;
+                    rollup.updated_at = rollup.updated_at.max(row.updated_at);
+                    rollup.stock_by_unit.push(row.stock);
+                }
+                _ => rollups.push(CategoryRollup {
+                    category: row.category,
+                    products: row.stock.products,
+                    inventory_value: row.stock.inventory_value,
+                    updated_at: row.updated_at,
+                    stock_by_unit: vec![row.stock],
+                }),
+            }
+        }
+        Ok(rollups)
+    }
+
+    /// Replaces the rollups with totals from a full scan of the catalog, in
+    /// one transaction. Products from before units were tracked are added
+    /// to their category's pieces. Returns the number of categories.
+    pub async fn rebuild(&self) -> Result<usize, ProductServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "BEGIN TRANSACTION; \
+             DELETE category_rollup; \
+             FOR $row IN (SELECT category, unit, count() AS products, \
+                 math::sum(stock_quantity) AS total_stock, \
+                 math::sum(price * stock_quantity) AS inventory_value \
+                 FROM product GROUP BY category, unit) { \
+                 UPDATE type::thing('category_rollup', [$row.category, $row.unit OR 'piece']) SET \
+                     category = $row.category, \
+                     unit = $row.unit OR 'piece', \
+                     products = (products OR 0) + $row.products, \
+                     total_stock = (total_stock OR 0) + $row.total_stock, \
+                     inventory_value = (inventory_value OR 0) + $row.inventory_value, \
+                     updated_at = time::now(); \
+             }; \
+             COMMIT TRANSACTION;",
+            |sql| db.query(sql),
+        )
+        .await?
+        .check()?;
+        let categories = self.rollups().await?.len();
+
+        debug!("Rebuilt rollups for {} categories", categories);
+        Ok(categories)
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    errors::user_error::UserServiceError,
+    models::fraud_model::{FraudHit, FraudHitForCreation, SignupAttemptForCreation},
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+struct CountResult {
+    total: usize,
+}
+
+/// Signup attempts and fraud hits live in the user database and share its
+/// connection
+pub struct SignupRepository {
+    db: Arc<DbConnection>,
+}
+
+impl SignupRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self { db }
+    }
+
+    pub async fn record_signup(
+        &self,
+        attempt: SignupAttemptForCreation,
+    ) -> Result<(), UserServiceError> {
+        let db = self.db.handle()?;
+        let _: Vec<serde_json::Value> =
+            traced_query("CREATE signup_attempt CONTENT $content", |_| {
+                db.create("signup_attempt").content(attempt)
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn signups_from_domain_since(
+        &self,
+        email_domain: &str,
+        since: DateTime<Utc>,
+    ) -> Result<usize, UserServiceError> {
+        let db = self.db.handle()?;
+        let count: Option<CountResult> = traced_query(
+            "SELECT count() AS total FROM signup_attempt \
+             WHERE email_domain = $domain AND attempted_at > $since GROUP ALL",
+            |sql| {
+                db.query(sql)
+                    .bind(("domain", email_domain))
+                    .bind(("since", since))
+            },
+        )
+        .await?
+        .take(0)?;
+        Ok(count.map(|c| c.total).unwrap_or(0))
+    }
+
+    pub async fn signups_from_ip_since(
+        &self,
+        client_ip: &str,
+        since: DateTime<Utc>,
+    ) -> Result<usize, UserServiceError> {
+        let db = self.db.handle()?;
+        let count: Option<CountResult> = traced_query(
+            "SELECT count() AS total FROM signup_attempt \
+             WHERE client_ip = $ip AND attempted_at > $since GROUP ALL",
+            |sql| db.query(sql).bind(("ip", client_ip)).bind(("since", since)),
+        )
+        .await?
+        .take(0)?;
+        Ok(count.map(|c| c.total).unwrap_or(0))
+    }
+
+    pub async fn record_hit(&self, hit: FraudHitForCreation) -> Result<(), UserServiceError> {
+        let db = self.db.handle()?;
+        let _: Vec<FraudHit> = traced_query("CREATE fraud_hit CONTENT $content", |_| {
+            db.create("fraud_hit").content(hit)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Most recent first
+    /// The latest `limit` hits and the total number of hits, read in one
+    /// transaction
+    pub async fn list_hits(
+        &self,
+        limit: usize,
+    ) -> Result<(Vec<FraudHit>, usize), UserServiceError> {
+        let db = self.db.handle()?;
+        let mut response = traced_query(
+            "BEGIN TRANSACTION; \
+             SELECT * FROM fraud_hit ORDER BY recorded_at DESC LIMIT $limit; \
+             SELECT count() AS total FROM fraud_hit GROUP ALL; \
+             COMMIT TRANSACTION;",
+            |sql| db.query(sql).bind(("limit", limit)),
+        )
+        .await?;
+        let hits: Vec<FraudHit> = response.take(0)?;
+        let count: Option<CountResult> = response.take(1)?;
+        Ok((hits, count.map(|c| c.total).unwrap_or(0)))
+    }
+}
@@ -1,50 +1,221 @@
-use crate::{errors::user_error::UserServiceError, models::user_model::User};
+use crate::{
+    config::database::DatabaseConfig,
+    crypto::pii::PiiCipher,
+    errors::user_error::UserServiceError,
+    models::user_model::{
+        replay_user_events, DailySignups, User, UserActivity, UserChanges, UserEvent,
+        UserEventForCreation, UserEventKind, UserForCreation, UserStats,
+    },
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
 use std::time::Duration;
-use surrealdb::{engine::local::Mem, Surreal};
+use surrealdb::{engine::any::Any, sql::Thing, Surreal};
 use tokio::time::timeout;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct CountResult {
+    total: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginFailures {
+    failed_logins: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserTotals {
+    total: usize,
+    verified: usize,
+}
+
+/// How user writes are persisted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserStorageMode {
+    /// The `user` table is the source of truth (default)
+    State,
+    /// Every write appends a versioned event to the `user_event` log, which
+    /// `get_user` folds into the current state. The `user` table is kept as
+    /// a projection for lists, exports and email lookups.
+    EventSourced,
+}
+
+impl UserStorageMode {
+    /// Reads `USER_STORAGE_MODE` (`state` or `event_sourced`)
+    pub fn from_env() -> Self {
+        match std::env::var("USER_STORAGE_MODE").as_deref() {
+            Ok("event_sourced") => UserStorageMode::EventSourced,
+            _ => UserStorageMode::State,
+        }
+    }
+}
+
+fn event_thing(user_id: &str, version: u64) -> Thing {
+    Thing::from(("user_event", format!("{}__{}", user_id, version).as_str()))
+}
+
+/// `MERGE` content for an update of `current`; an empty phone or avatar
+/// key removes it, and a new name keeps the old one in `name_history`
+fn merge_fields(changes: &UserChanges, current: &User, updated_at: DateTime<Utc>) -> Value {
+    // Same format serde gives chrono timestamps everywhere else
+    let timestamp =
+        |at: DateTime<Utc>| Value::String(at.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+    let mut fields = Map::new();
+    if let Some(name) = &changes.name {
+        fields.insert("name".to_string(), Value::String(name.clone()));
+    }
+    if let Some(history) = changes.name_history(current, updated_at) {
+        fields.insert("name_history".to_string(), json!(history));
+    }
+    if let Some(email) = &changes.email {
+        fields.insert("email".to_string(), Value::String(email.clone()));
+        fields.insert("email_hash".to_string(), changes.email_hash.clone().into());
+    }
+    match changes.phone.as_deref() {
+        Some("") => {
+            fields.insert("phone".to_string(), Value::Null);
+        }
+        Some(phone) => {
+            fields.insert("phone".to_string(), Value::String(phone.to_string()));
+        }
+        None => {}
+    }
+    match changes.avatar_key.as_deref() {
+        Some("") => {
+            fields.insert("avatar_key".to_string(), Value::Null);
+        }
+        Some(key) => {
+            fields.insert("avatar_key".to_string(), Value::String(key.to_string()));
+        }
+        None => {}
+    }
+    if changes.email.is_some() || changes.email_verified.is_some() {
+        let verified_at = changes.email_verified_at(current.email_verified_at, updated_at);
+        fields.insert(
+            "email_verified_at".to_string(),
+            verified_at.map_or(Value::Null, timestamp),
+        );
+    }
+    fields.insert("updated_at".to_string(), timestamp(updated_at));
+    Value::Object(fields)
+}
 
 pub struct UserRepository {
-    db: Surreal<surrealdb::engine::local::Db>,
+    db: Arc<DbConnection>,
+    mode: UserStorageMode,
+    /// Encrypts email and phone at rest when configured
+    cipher: Option<PiiCipher>,
 }
 
 impl UserRepository {
-    pub async fn new() -> Result<Self, UserServiceError> {
-        let db = Surreal::new::<Mem>(()).await?;
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, UserServiceError> {
+        let db = DbConnection::open(config).await?;
+
+        info!(
+            "Connected to SurrealDB at {} ({}/{})",
+            config.endpoint, config.namespace, config.database
+        );
+
+        let cipher = PiiCipher::from_env()?;
+        match &cipher {
+            Some(cipher) => info!(
+                "PII encryption enabled with active key '{}'",
+                cipher.active_key_id()
+            ),
+            None => warn!("PII encryption disabled; email and phone are stored in plaintext"),
+        }
+
+        let mode = UserStorageMode::from_env();
+        if mode == UserStorageMode::EventSourced {
+            info!("User storage is event-sourced: writes are appended to user_event");
+        }
+
+        Ok(Self { db, mode, cipher })
+    }
+
+    pub fn connection(&self) -> Arc<DbConnection> {
+        Arc::clone(&self.db)
+    }
 
-        // Use a namespace and database
-        db.use_ns("user_service").use_db("users").await?;
+    pub fn storage_mode(&self) -> UserStorageMode {
+        self.mode
+    }
 
-        info!("Connected to SurrealDB");
+    /// Id of the key new PII is encrypted with, if encryption is enabled
+    pub fn encryption_key_id(&self) -> Option<&str> {
+        self.cipher.as_ref().map(PiiCipher::active_key_id)
+    }
+
+    fn seal(&self, user: UserForCreation) -> Result<UserForCreation, UserServiceError> {
+        match &self.cipher {
+            Some(cipher) => Ok(cipher.seal_user(user)?),
+            None => Ok(user),
+        }
+    }
+
+    fn open(&self, user: User) -> Result<User, UserServiceError> {
+        match &self.cipher {
+            Some(cipher) => Ok(cipher.open_user(user)?),
+            None => Ok(user),
+        }
+    }
 
-        Ok(Self { db })
+    fn open_all(&self, users: Vec<User>) -> Result<Vec<User>, UserServiceError> {
+        users.into_iter().map(|user| self.open(user)).collect()
+    }
+
+    fn seal_changes(&self, changes: UserChanges) -> Result<UserChanges, UserServiceError> {
+        match &self.cipher {
+            Some(cipher) => Ok(cipher.seal_changes(changes)?),
+            None => Ok(changes),
+        }
+    }
+
+    /// Decrypts an event for display; the blind index is not returned
+    fn open_event(&self, event: UserEvent) -> Result<UserEvent, UserServiceError> {
+        let changes = match &self.cipher {
+            Some(cipher) => cipher.open_changes(event.changes)?,
+            None => event.changes,
+        };
+        Ok(UserEvent {
+            changes: UserChanges {
+                email_hash: None,
+                ..changes
+            },
+            ..event
+        })
     }
 
     pub async fn create_user(&self, user: User) -> Result<User, UserServiceError> {
+        let db = self.db.handle()?;
         // Add timeout to prevent hanging operations under stress
         let result = timeout(Duration::from_secs(10), async {
-            // Check if user with email already exists
-            let existing: Vec<User> = self
-                .db
-                .query("SELECT * FROM user WHERE email = $email")
-                .bind(("email", &user.email))
-                .await?
-                .take(0)?;
+            self.ensure_email_available(&user.email, None).await?;
 
-            if !existing.is_empty() {
-                return Err(UserServiceError::UserAlreadyExists {
-                    email: user.email.clone(),
-                });
-            }
-
-            // Create the user - let SurrealDB generate the ID
-            let user_for_creation = user.for_creation();
-            let created: Vec<User> = self.db.create("user").content(user_for_creation).await?;
+            let user_for_creation = self.seal(user.for_creation())?;
+            let created: Vec<User> = match self.mode {
+                // Create the user - let SurrealDB generate the ID
+                UserStorageMode::State => {
+                    traced_query("CREATE user CONTENT $content", |_| {
+                        db.create("user").content(user_for_creation)
+                    })
+                    .await?
+                }
+                UserStorageMode::EventSourced => {
+                    vec![self.append_created(&db, user_for_creation).await?]
+                }
+            };
 
             match created.into_iter().next() {
                 Some(user) => {
-                    info!("Created user with id: {}", user.id);
-                    Ok(user)
+                    debug!("Created user with id: {}", user.id);
+                    self.open(user)
                 }
                 None => {
                     error!("Failed to create user");
@@ -68,13 +239,19 @@ impl UserRepository {
     }
 
     pub async fn get_user(&self, id: &str) -> Result<User, UserServiceError> {
+        let db = self.db.handle()?;
         let result = timeout(Duration::from_secs(5), async {
-            let user: Option<User> = self.db.select(("user", id)).await?;
+            let user: Option<User> = match self.mode {
+                UserStorageMode::State => {
+                    traced_query("SELECT * FROM $id", |_| db.select(("user", id))).await?
+                }
+                UserStorageMode::EventSourced => self.replay(&db, id).await?,
+            };
 
             match user {
                 Some(user) => {
-                    info!("Retrieved user with id: {}", id);
-                    Ok(user)
+                    debug!("Retrieved user with id: {}", id);
+                    self.open(user)
                 }
                 None => Err(UserServiceError::UserNotFound { id: id.to_string() }),
             }
@@ -93,15 +270,17 @@ impl UserRepository {
     }
 
     pub async fn list_users(&self) -> Result<Vec<User>, UserServiceError> {
+        let db = self.db.handle()?;
         let result = timeout(Duration::from_secs(10), async {
-            let users: Vec<User> = self
-                .db
-                .query("SELECT * FROM user ORDER BY created_at DESC")
+            let users: Vec<User> =
+                traced_query("SELECT * FROM user ORDER BY created_at DESC", |sql| {
+                    db.query(sql)
+                })
                 .await?
                 .take(0)?;
 
-            info!("Retrieved {} users", users.len());
-            Ok(users)
+            debug!("Retrieved {} users", users.len());
+            self.open_all(users)
         })
         .await;
 
@@ -116,14 +295,545 @@ impl UserRepository {
         }
     }
 
+    /// Returns one page of users in a stable order, for bulk export, and
+    /// the total number of users. Both come from one transaction, so the
+    /// total matches the data the page was read from.
+    pub async fn export_users(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<User>, usize), UserServiceError> {
+        let db = self.db.handle()?;
+        let mut response = traced_query(
+            "BEGIN TRANSACTION; \
+             SELECT * FROM user ORDER BY created_at, id LIMIT $limit START $offset; \
+             SELECT count() AS total FROM user GROUP ALL; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("limit", limit))
+                    .bind(("offset", offset))
+            },
+        )
+        .await?;
+        let users: Vec<User> = response.take(0)?;
+        let count: Option<CountResult> = response.take(1)?;
+
+        debug!("Exported {} users from offset {}", users.len(), offset);
+        Ok((self.open_all(users)?, count.map(|c| c.total).unwrap_or(0)))
+    }
+
+    /// User totals and signups per UTC day since `since`, computed by the
+    /// database. Only days with signups are returned.
+    pub async fn user_stats(&self, since: DateTime<Utc>) -> Result<UserStats, UserServiceError> {
+        let db = self.db.handle()?;
+        let mut response = traced_query(
+            "SELECT count() AS total, \
+             count(email_verified_at != NONE AND email_verified_at != NULL) AS verified \
+             FROM user GROUP ALL; \
+             SELECT date, count() AS signups FROM ( \
+             SELECT time::format(<datetime> created_at, '%Y-%m-%d') AS date \
+             FROM user WHERE created_at >= $since \
+             ) GROUP BY date ORDER BY date",
+            |sql| db.query(sql).bind(("since", since)),
+        )
+        .await?;
+        let totals: Option<UserTotals> = response.take(0)?;
+        let signups_per_day: Vec<DailySignups> = response.take(1)?;
+
+        let (total, verified) = totals.map_or((0, 0), |t| (t.total, t.verified));
+        Ok(UserStats {
+            total_users: total,
+            verified_users: verified,
+            unverified_users: total - verified,
+            signups_per_day,
+            generated_at: Utc::now(),
+        })
+    }
+
+    /// Users not seen since `cutoff`, counting users never seen from their
+    /// signup, plus how many there are in total. Never-seen users come
+    /// first, then the least recently seen.
+    pub async fn inactive_users(
+        &self,
+        cutoff: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<(Vec<User>, usize), UserServiceError> {
+        let db = self.db.handle()?;
+        let mut response = traced_query(
+            "BEGIN TRANSACTION; \
+             SELECT * FROM user WHERE (last_seen_at OR created_at) < $cutoff \
+             ORDER BY last_seen_at, created_at LIMIT $limit; \
+             SELECT count() AS total FROM user \
+             WHERE (last_seen_at OR created_at) < $cutoff GROUP ALL; \
+             COMMIT TRANSACTION;",
+            |sql| {
+                db.query(sql)
+                    .bind(("cutoff", cutoff))
+                    .bind(("limit", limit))
+            },
+        )
+        .await?;
+        let users: Vec<User> = response.take(0)?;
+        let count: Option<CountResult> = response.take(1)?;
+
+        Ok((self.open_all(users)?, count.map(|c| c.total).unwrap_or(0)))
+    }
+
+    /// Writes a batch of activity in one query. Timestamps only move
+    /// forward, so replicas flushing out of order cannot rewind them, and
+    /// users deleted since are skipped rather than re-created.
+    pub async fn record_activity(&self, batch: Vec<UserActivity>) -> Result<(), UserServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "FOR $activity IN $batch { \
+             UPDATE type::thing('user', $activity.user_id) \
+             SET last_seen_at = $activity.seen_at \
+             WHERE created_at != NONE \
+             AND (last_seen_at = NONE OR last_seen_at < $activity.seen_at); \
+             IF $activity.login_at { \
+             UPDATE type::thing('user', $activity.user_id) \
+             SET last_login_at = $activity.login_at \
+             WHERE created_at != NONE \
+             AND (last_login_at = NONE OR last_login_at < $activity.login_at); \
+             }; \
+             };",
+            |sql| db.query(sql).bind(("batch", batch)),
+        )
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// Counts a failed sign-in and returns the consecutive failures so far,
+    /// or `None` for a user that no longer exists
+    pub async fn record_login_failure(&self, id: &str) -> Result<Option<u32>, UserServiceError> {
+        let db = self.db.handle()?;
+        let counted: Vec<LoginFailures> = traced_query(
+            "UPDATE $user SET failed_logins = (failed_logins OR 0) + 1 \
+             WHERE created_at != NONE RETURN failed_logins",
+            |sql| db.query(sql).bind(("user", Thing::from(("user", id)))),
+        )
+        .await?
+        .take(0)?;
+
+        Ok(counted.first().map(|c| c.failed_logins))
+    }
+
+    pub async fn lock_user(&self, id: &str, until: DateTime<Utc>) -> Result<(), UserServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "UPDATE $user SET locked_until = $until WHERE created_at != NONE",
+            |sql| {
+                db.query(sql)
+                    .bind(("user", Thing::from(("user", id))))
+                    .bind(("until", until))
+            },
+        )
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// Clears the failed sign-in count and any lock
+    pub async fn reset_login_failures(&self, id: &str) -> Result<(), UserServiceError> {
+        let db = self.db.handle()?;
+        traced_query(
+            "UPDATE $user SET failed_logins = 0, locked_until = NONE WHERE created_at != NONE",
+            |sql| db.query(sql).bind(("user", Thing::from(("user", id)))),
+        )
+        .await?
+        .check()?;
+
+        Ok(())
+    }
+
+    /// Fails when a user other than `owner` already has `email`
+    pub async fn ensure_email_available(
+        &self,
+        email: &str,
+        owner: Option<&Thing>,
+    ) -> Result<(), UserServiceError> {
+        match self.get_user_by_email(email).await? {
+            Some(existing) if Some(&existing.id) != owner => {
+                Err(UserServiceError::UserAlreadyExists {
+                    email: email.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, UserServiceError> {
-        let users: Vec<User> = self
-            .db
-            .query("SELECT * FROM user WHERE email = $email")
-            .bind(("email", email))
+        let db = self.db.handle()?;
+        let users: Vec<User> = match &self.cipher {
+            // Rows written before encryption was enabled still hold plaintext
+            Some(cipher) => traced_query(
+                "SELECT * FROM user WHERE email_hash = $email_hash OR email = $email",
+                |sql| {
+                    db.query(sql)
+                        .bind(("email_hash", cipher.blind_index(email)))
+                        .bind(("email", email))
+                },
+            )
+            .await?
+            .take(0)?,
+            None => traced_query("SELECT * FROM user WHERE email = $email", |sql| {
+                db.query(sql).bind(("email", email))
+            })
             .await?
-            .take(0)?;
+            .take(0)?,
+        };
+
+        users
+            .into_iter()
+            .next()
+            .map(|user| self.open(user))
+            .transpose()
+    }
+
+    /// Rewrites up to `batch_size` users whose PII is not encrypted under the
+    /// active key, including rows stored before encryption was enabled.
+    /// Returns the number of users rewritten; 0 means rotation is complete.
+    pub async fn reencrypt_batch(&self, batch_size: usize) -> Result<usize, UserServiceError> {
+        let db = self.db.handle()?;
+        let Some(cipher) = &self.cipher else {
+            return Err(UserServiceError::Validation {
+                message: "PII encryption is not configured".to_string(),
+            });
+        };
+
+        let stale: Vec<User> = traced_query(
+            "SELECT * FROM user WHERE !string::starts_with(email, $prefix) \
+                 OR (phone != NONE AND !string::starts_with(phone, $prefix)) LIMIT $limit",
+            |sql| {
+                db.query(sql)
+                    .bind(("prefix", cipher.active_prefix()))
+                    .bind(("limit", batch_size))
+            },
+        )
+        .await?
+        .take(0)?;
+
+        for user in &stale {
+            let sealed = cipher.seal_user(cipher.open_user(user.clone())?.for_creation())?;
+
+            let mut fields = Map::new();
+            fields.insert("email".to_string(), Value::String(sealed.email));
+            fields.insert("email_hash".to_string(), sealed.email_hash.into());
+            if let Some(phone) = sealed.phone {
+                fields.insert("phone".to_string(), Value::String(phone));
+            }
+
+            let updated: Option<User> = traced_query("UPDATE $id MERGE $fields", |_| {
+                db.update(("user", user.id.id.to_raw()))
+                    .merge(Value::Object(fields))
+            })
+            .await?;
+            if updated.is_none() {
+                warn!("User {} disappeared during key rotation", user.id);
+            }
+        }
+
+        debug!("Re-encrypted {} users", stale.len());
+        Ok(stale.len())
+    }
+
+    /// Applies `changes` to an existing user and returns the result
+    pub async fn update_user(
+        &self,
+        id: &str,
+        changes: UserChanges,
+    ) -> Result<User, UserServiceError> {
+        let current = self.get_user(id).await?;
+        if let Some(email) = &changes.email {
+            self.ensure_email_available(email, Some(&current.id))
+                .await?;
+        }
+
+        let db = self.db.handle()?;
+        let changes = self.seal_changes(changes)?;
+        let now = Utc::now();
+        match self.mode {
+            UserStorageMode::State => {
+                let updated: Option<User> = traced_query("UPDATE $id MERGE $fields", |_| {
+                    db.update(("user", id))
+                        .merge(merge_fields(&changes, &current, now))
+                })
+                .await?;
+                let user =
+                    updated.ok_or_else(|| UserServiceError::UserNotFound { id: id.to_string() })?;
+                debug!("Updated user with id: {}", id);
+                self.open(user)
+            }
+            UserStorageMode::EventSourced => {
+                let version = self.next_version(&db, &current).await?;
+                let fields = merge_fields(&changes, &current, now);
+                self.append_event(
+                    &db,
+                    UserEventForCreation {
+                        user_id: id.to_string(),
+                        version,
+                        kind: UserEventKind::UserUpdated,
+                        changes,
+                        recorded_at: now,
+                    },
+                    "BEGIN TRANSACTION; \
+                     CREATE $event CONTENT $content; \
+                     UPDATE $user MERGE $projection; \
+                     COMMIT TRANSACTION;",
+                    fields,
+                )
+                .await?;
+                self.get_user(id).await
+            }
+        }
+    }
 
-        Ok(users.into_iter().next())
+    /// The user as `update_user` would leave it, after the same checks,
+    /// without writing anything
+    pub async fn preview_update(
+        &self,
+        id: &str,
+        changes: &UserChanges,
+    ) -> Result<User, UserServiceError> {
+        let current = self.get_user(id).await?;
+        if let Some(email) = &changes.email {
+            self.ensure_email_available(email, Some(&current.id))
+                .await?;
+        }
+        Ok(changes.apply_to(current, Utc::now()))
+    }
+
+    pub async fn delete_user(&self, id: &str) -> Result<(), UserServiceError> {
+        let current = self.get_user(id).await?;
+        let db = self.db.handle()?;
+        match self.mode {
+            UserStorageMode::State => {
+                let deleted: Option<User> =
+                    traced_query("DELETE $id", |_| db.delete(("user", id))).await?;
+                if deleted.is_none() {
+                    return Err(UserServiceError::UserNotFound { id: id.to_string() });
+                }
+            }
+            UserStorageMode::EventSourced => {
+                let version = self.next_version(&db, &current).await?;
+                self.append_event(
+                    &db,
+                    UserEventForCreation {
+                        user_id: id.to_string(),
+                        version,
+                        kind: UserEventKind::UserDeleted,
+                        changes: UserChanges::default(),
+                        recorded_at: Utc::now(),
+                    },
+                    "BEGIN TRANSACTION; \
+                     CREATE $event CONTENT $content; \
+                     DELETE $user; \
+                     COMMIT TRANSACTION;",
+                    (),
+                )
+                .await?;
+            }
+        }
+
+        debug!("Deleted user with id: {}", id);
+        Ok(())
+    }
+
+    /// The user's events recorded at or before `as_of`, oldest first and
+    /// decrypted, plus the state they fold into
+    pub async fn user_history(
+        &self,
+        id: &str,
+        as_of: Option<DateTime<Utc>>,
+    ) -> Result<(Vec<UserEvent>, Option<User>), UserServiceError> {
+        let db = self.db.handle()?;
+        let mut events = self.load_events(&db, id).await?;
+        if let Some(as_of) = as_of {
+            events.retain(|event| event.recorded_at <= as_of);
+        }
+
+        let state = replay_user_events(&events)
+            .map(|user| self.open(user))
+            .transpose()?;
+        let events = events
+            .into_iter()
+            .map(|event| self.open_event(event))
+            .collect::<Result<_, _>>()?;
+        Ok((events, state))
+    }
+
+    async fn load_events(
+        &self,
+        db: &Surreal<Any>,
+        id: &str,
+    ) -> Result<Vec<UserEvent>, UserServiceError> {
+        let events: Vec<UserEvent> = traced_query(
+            "SELECT * FROM user_event WHERE user_id = $user_id ORDER BY version",
+            |sql| db.query(sql).bind(("user_id", id)),
+        )
+        .await?
+        .take(0)?;
+        Ok(events)
+    }
+
+    /// Current state folded from the event log. Users written before event
+    /// sourcing was enabled have no events and are read from their row.
+    async fn replay(&self, db: &Surreal<Any>, id: &str) -> Result<Option<User>, UserServiceError> {
+        let events = self.load_events(db, id).await?;
+        let row: Option<User> =
+            traced_query("SELECT * FROM $id", |_| db.select(("user", id))).await?;
+        if events.is_empty() {
+            return Ok(row);
+        }
+        // Activity, lockouts and name history are only written to the row,
+        // never to the event log
+        Ok(replay_user_events(&events).map(|user| match row {
+            Some(row) => User {
+                last_login_at: row.last_login_at,
+                last_seen_at: row.last_seen_at,
+                failed_logins: row.failed_logins,
+                locked_until: row.locked_until,
+                name_history: row.name_history,
+                ..user
+            },
+            None => user,
+        }))
+    }
+
+    /// Version for the next event of `current`. A user without events gets
+    /// a `UserCreated` snapshot of its current row first, so its history has
+    /// a starting point.
+    async fn next_version(
+        &self,
+        db: &Surreal<Any>,
+        current: &User,
+    ) -> Result<u64, UserServiceError> {
+        let id = current.id.id.to_raw();
+        let latest: Vec<u64> = traced_query(
+            "SELECT VALUE version FROM user_event WHERE user_id = $user_id ORDER BY version DESC LIMIT 1",
+            |sql| db.query(sql).bind(("user_id", id.as_str())),
+        )
+        .await?
+        .take(0)?;
+        if let Some(version) = latest.first() {
+            return Ok(version + 1);
+        }
+
+        let snapshot = self.seal_changes(UserChanges {
+            name: Some(current.name.clone()),
+            email: Some(current.email.clone()),
+            email_hash: None,
+            phone: current.phone.clone(),
+            avatar_key: current.avatar_key.clone(),
+            email_verified: Some(current.email_verified_at.is_some()),
+        })?;
+        let event = UserEventForCreation {
+            user_id: id.clone(),
+            version: 1,
+            kind: UserEventKind::UserCreated,
+            changes: snapshot,
+            recorded_at: current.updated_at,
+        };
+        let created: Option<UserEvent> = traced_query("CREATE $event CONTENT $content", |_| {
+            db.create(event_thing(&id, 1)).content(event)
+        })
+        .await?;
+        if created.is_some() {
+            info!(
+                "Snapshotted user {} into user_event before its first event",
+                id
+            );
+        }
+        Ok(2)
+    }
+
+    /// Appends `UserCreated` for a new user and creates its row in one
+    /// transaction. The id is generated here because the event names it.
+    async fn append_created(
+        &self,
+        db: &Surreal<Any>,
+        user: UserForCreation,
+    ) -> Result<User, UserServiceError> {
+        let id = Uuid::new_v4().simple().to_string();
+        let event = UserEventForCreation {
+            user_id: id.clone(),
+            version: 1,
+            kind: UserEventKind::UserCreated,
+            changes: UserChanges {
+                name: Some(user.name.clone()),
+                email: Some(user.email.clone()),
+                email_hash: user.email_hash.clone(),
+                phone: user.phone.clone(),
+                avatar_key: None,
+                email_verified: None,
+            },
+            recorded_at: user.created_at,
+        };
+        let created = User {
+            id: Thing::from(("user", id.as_str())),
+            name: user.name.clone(),
+            email: user.email.clone(),
+            phone: user.phone.clone(),
+            avatar_key: None,
+            avatar_url: None,
+            email_verified_at: None,
+            last_login_at: None,
+            last_seen_at: None,
+            failed_logins: 0,
+            locked_until: None,
+            name_history: Vec::new(),
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        };
+
+        self.append_event(
+            db,
+            event,
+            "BEGIN TRANSACTION; \
+             CREATE $event CONTENT $content; \
+             CREATE $user CONTENT $projection; \
+             COMMIT TRANSACTION;",
+            user,
+        )
+        .await?;
+        Ok(created)
+    }
+
+    /// Appends `event` and runs `statement` to update the user's row in the
+    /// same transaction. Event ids are `user_event:<user>__<version>`, so of
+    /// two writers racing for the same version only one can commit.
+    async fn append_event(
+        &self,
+        db: &Surreal<Any>,
+        event: UserEventForCreation,
+        statement: &'static str,
+        projection: impl Serialize,
+    ) -> Result<(), UserServiceError> {
+        let (id, version, kind) = (event.user_id.clone(), event.version, event.kind);
+        let result = traced_query(statement, |sql| {
+            db.query(sql)
+                .bind(("event", event_thing(&id, version)))
+                .bind(("content", event))
+                .bind(("user", Thing::from(("user", id.as_str()))))
+                .bind(("projection", projection))
+        })
+        .await
+        .and_then(surrealdb::Response::check);
+
+        match result {
+            Ok(_) => {
+                debug!("Appended {:?} v{} for user {}", kind, version, id);
+                Ok(())
+            }
+            // Another writer appended this version first
+            Err(err) if err.to_string().contains("already exists") => {
+                Err(UserServiceError::VersionConflict { id, version })
+            }
+            Err(err) => Err(err.into()),
+        }
     }
 }
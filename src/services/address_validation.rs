@@ -0,0 +1,208 @@
+use crate::models::address_model::{Address, AddressFieldError, ValidateAddressResponse};
+
+const MAX_FIELD_LENGTH: usize = 200;
+
+/// Per-country address requirements. Postal code patterns use `N` for a
+/// digit, `A` for a letter and match any other character literally; a
+/// country with no patterns does not use postal codes.
+struct CountryRules {
+    code: &'static str,
+    requires_region: bool,
+    postal_patterns: &'static [&'static str],
+}
+
+const COUNTRY_RULES: &[CountryRules] = &[
+    CountryRules {
+        code: "AU",
+        requires_region: true,
+        postal_patterns: &["NNNN"],
+    },
+    CountryRules {
+        code: "CA",
+        requires_region: true,
+        postal_patterns: &["ANA NAN"],
+    },
+    CountryRules {
+        code: "DE",
+        requires_region: false,
+        postal_patterns: &["NNNNN"],
+    },
+    CountryRules {
+        code: "FR",
+        requires_region: false,
+        postal_patterns: &["NNNNN"],
+    },
+    CountryRules {
+        code: "GB",
+        requires_region: false,
+        postal_patterns: &[
+            "AN NAA", "ANN NAA", "AAN NAA", "AANN NAA", "ANA NAA", "AANA NAA",
+        ],
+    },
+    CountryRules {
+        code: "HK",
+        requires_region: false,
+        postal_patterns: &[],
+    },
+    CountryRules {
+        code: "IN",
+        requires_region: true,
+        postal_patterns: &["NNNNNN"],
+    },
+    CountryRules {
+        code: "JP",
+        requires_region: true,
+        postal_patterns: &["NNN-NNNN"],
+    },
+    CountryRules {
+        code: "KH",
+        requires_region: false,
+        postal_patterns: &["NNNNN", "NNNNNN"],
+    },
+    CountryRules {
+        code: "NL",
+        requires_region: false,
+        postal_patterns: &["NNNN AA"],
+    },
+    CountryRules {
+        code: "SG",
+        requires_region: false,
+        postal_patterns: &["NNNNNN"],
+    },
+    CountryRules {
+        code: "TH",
+        requires_region: false,
+        postal_patterns: &["NNNNN"],
+    },
+    CountryRules {
+        code: "US",
+        requires_region: true,
+        postal_patterns: &["NNNNN", "NNNNN-NNNN"],
+    },
+    CountryRules {
+        code: "VN",
+        requires_region: false,
+        postal_patterns: &["NNNNNN"],
+    },
+];
+
+/// Validates an address against the rules for its country and returns every
+/// problem found, not just the first. Countries without specific rules only
+/// need a street line and a city.
+pub fn validate_address(address: &Address) -> ValidateAddressResponse {
+    let normalized = normalize(address);
+    let mut errors = Vec::new();
+
+    require(&mut errors, "line1", Some(&normalized.line1));
+    require(&mut errors, "city", Some(&normalized.city));
+    for (field, value) in [
+        ("line1", Some(&normalized.line1)),
+        ("line2", normalized.line2.as_ref()),
+        ("city", Some(&normalized.city)),
+        ("region", normalized.region.as_ref()),
+    ] {
+        if value.is_some_and(|v| v.chars().count() > MAX_FIELD_LENGTH) {
+            errors.push(field_error(
+                field,
+                format!("Must be at most {} characters", MAX_FIELD_LENGTH),
+            ));
+        }
+    }
+
+    let country = &normalized.country;
+    if country.len() != 2 || !country.chars().all(|c| c.is_ascii_uppercase()) {
+        errors.push(field_error(
+            "country",
+            "Must be a two-letter ISO 3166-1 country code".to_string(),
+        ));
+    } else if let Some(rules) = COUNTRY_RULES.iter().find(|rules| rules.code == country) {
+        if rules.requires_region {
+            require(&mut errors, "region", normalized.region.as_ref());
+        }
+        check_postal_code(&mut errors, rules, normalized.postal_code.as_deref());
+    }
+
+    let valid = errors.is_empty();
+    ValidateAddressResponse {
+        valid,
+        errors,
+        normalized: valid.then_some(normalized),
+    }
+}
+
+fn check_postal_code(
+    errors: &mut Vec<AddressFieldError>,
+    rules: &CountryRules,
+    postal_code: Option<&str>,
+) {
+    match postal_code {
+        None if rules.postal_patterns.is_empty() => {}
+        None => errors.push(field_error(
+            "postal_code",
+            format!("Required for {}", rules.code),
+        )),
+        Some(_) if rules.postal_patterns.is_empty() => errors.push(field_error(
+            "postal_code",
+            format!("{} does not use postal codes", rules.code),
+        )),
+        Some(code) => {
+            if !rules
+                .postal_patterns
+                .iter()
+                .any(|pattern| matches_pattern(code, pattern))
+            {
+                errors.push(field_error(
+                    "postal_code",
+                    format!(
+                        "Invalid postal code for {}, expected {}",
+                        rules.code,
+                        rules.postal_patterns.join(" or ")
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn matches_pattern(value: &str, pattern: &str) -> bool {
+    value.len() == pattern.len()
+        && value.chars().zip(pattern.chars()).all(|(c, p)| match p {
+            'N' => c.is_ascii_digit(),
+            'A' => c.is_ascii_uppercase(),
+            _ => c == p,
+        })
+}
+
+/// Trims every field, drops empty optional fields and upper-cases the
+/// country and postal code
+fn normalize(address: &Address) -> Address {
+    let optional = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+
+    Address {
+        line1: address.line1.trim().to_string(),
+        line2: optional(&address.line2),
+        city: address.city.trim().to_string(),
+        region: optional(&address.region),
+        postal_code: optional(&address.postal_code).map(|code| code.to_uppercase()),
+        country: address.country.trim().to_uppercase(),
+    }
+}
+
+fn require(errors: &mut Vec<AddressFieldError>, field: &str, value: Option<&String>) {
+    if value.is_none_or(|v| v.is_empty()) {
+        errors.push(field_error(field, "Required".to_string()));
+    }
+}
+
+fn field_error(field: &str, message: String) -> AddressFieldError {
+    AddressFieldError {
+        field: field.to_string(),
+        message,
+    }
+}
@@ -0,0 +1,363 @@
+use chrono::{DateTime, Utc};
+use hyper::Uri;
+use ring::digest::{digest, SHA256};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Object keys are `avatars/<user id>/<file>`
+pub const AVATAR_KEY_PREFIX: &str = "avatars/";
+
+#[derive(Error, Debug)]
+pub enum AvatarStorageError {
+    #[error("Unknown AVATAR_STORAGE '{0}', expected local or s3")]
+    UnknownBackend(String),
+
+    #[error("{0} must be set for S3 avatar storage")]
+    MissingSetting(&'static str),
+
+    #[error("Invalid {name}: {message}")]
+    InvalidSetting { name: &'static str, message: String },
+
+    #[error("Invalid avatar key '{0}'")]
+    InvalidKey(String),
+}
+
+/// Image types accepted for avatars, with the file extension they are
+/// stored under
+pub fn avatar_extension(content_type: &str) -> Option<&'static str> {
+    match content_type.trim().to_ascii_lowercase().as_str() {
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/webp" => Some("webp"),
+        "image/gif" => Some("gif"),
+        _ => None,
+    }
+}
+
+/// Whether `key` is a well-formed avatar key. Keys become URL paths and,
+/// with local storage, file paths, so only `avatars/<segment>/<segment>`
+/// made of letters, digits, `-`, `_` and `.` is accepted.
+pub fn is_valid_avatar_key(key: &str) -> bool {
+    let Some(rest) = key.strip_prefix(AVATAR_KEY_PREFIX) else {
+        return false;
+    };
+    let segments: Vec<&str> = rest.split('/').collect();
+    segments.len() == 2
+        && segments.iter().all(|segment| {
+            !segment.is_empty()
+                && !segment.starts_with('.')
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        })
+}
+
+/// Where avatar images live. Clients upload straight to the store through a
+/// presigned URL, so image bytes never pass through the JSON-RPC API.
+pub trait AvatarStorage: Send + Sync {
+    fn backend(&self) -> &'static str;
+
+    /// A URL the client can `PUT` the image to until `expires_at`
+    fn presign_upload(&self, key: &str, expires_at: DateTime<Utc>) -> String;
+
+    /// The URL the image is served from once uploaded
+    fn public_url(&self, key: &str) -> String;
+}
+
+/// Reads the storage backend from `AVATAR_STORAGE` (`local` by default)
+pub enum AvatarBackend {
+    Local(Arc<LocalAvatarStorage>),
+    S3(Arc<S3AvatarStorage>),
+}
+
+impl AvatarBackend {
+    pub fn from_env() -> Result<Self, AvatarStorageError> {
+        let backend = std::env::var("AVATAR_STORAGE").unwrap_or_else(|_| "local".to_string());
+        match backend.as_str() {
+            "local" => Ok(AvatarBackend::Local(Arc::new(
+                LocalAvatarStorage::from_env()?,
+            ))),
+            "s3" => Ok(AvatarBackend::S3(Arc::new(S3AvatarStorage::from_env()?))),
+            other => Err(AvatarStorageError::UnknownBackend(other.to_string())),
+        }
+    }
+
+    pub fn storage(&self) -> Arc<dyn AvatarStorage> {
+        match self {
+            AvatarBackend::Local(storage) => storage.clone(),
+            AvatarBackend::S3(storage) => storage.clone(),
+        }
+    }
+
+    /// The local store, whose uploads the service itself must accept
+    pub fn local(&self) -> Option<Arc<LocalAvatarStorage>> {
+        match self {
+            AvatarBackend::Local(storage) => Some(Arc::clone(storage)),
+            AvatarBackend::S3(_) => None,
+        }
+    }
+}
+
+/// Development storage: images are written to a local directory by the user
+/// service itself, which accepts uploads on `PUT /avatars/...` when the URL
+/// carries a valid signature and serves them back on `GET`.
+pub struct LocalAvatarStorage {
+    dir: PathBuf,
+    base_url: String,
+    signing_key: hmac::Key,
+    max_bytes: usize,
+}
+
+impl LocalAvatarStorage {
+    /// Reads `AVATAR_LOCAL_DIR` (default `./avatar-uploads`),
+    /// `AVATAR_PUBLIC_BASE_URL` (default `http://127.0.0.1:8080`),
+    /// `AVATAR_MAX_BYTES` (default 2 MiB) and `AVATAR_SIGNING_KEY`. Without
+    /// a signing key a random one is generated, so upload URLs stop working
+    /// after a restart.
+    pub fn from_env() -> Result<Self, AvatarStorageError> {
+        let signing_key = match std::env::var("AVATAR_SIGNING_KEY") {
+            Ok(secret) => secret.into_bytes(),
+            Err(_) => {
+                warn!("AVATAR_SIGNING_KEY not set; avatar upload URLs are valid until restart");
+                let mut secret = vec![0u8; 32];
+                SystemRandom::new().fill(&mut secret).map_err(|_| {
+                    AvatarStorageError::InvalidSetting {
+                        name: "AVATAR_SIGNING_KEY",
+                        message: "failed to generate a random key".to_string(),
+                    }
+                })?;
+                secret
+            }
+        };
+
+        let storage = Self {
+            dir: std::env::var("AVATAR_LOCAL_DIR")
+                .unwrap_or_else(|_| "./avatar-uploads".to_string())
+                .into(),
+            base_url: base_url_from_env("http://127.0.0.1:8080"),
+            signing_key: hmac::Key::new(hmac::HMAC_SHA256, &signing_key),
+            max_bytes: std::env::var("AVATAR_MAX_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2 * 1024 * 1024),
+        };
+        info!(
+            "Avatars stored locally in {} and served from {}",
+            storage.dir.display(),
+            storage.base_url
+        );
+        Ok(storage)
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    fn signature(&self, key: &str, expires: i64) -> String {
+        let tag = hmac::sign(
+            &self.signing_key,
+            format!("PUT\n{}\n{}", key, expires).as_bytes(),
+        );
+        hex(tag.as_ref())
+    }
+
+    /// Checks the `expires` and `signature` query parameters of an upload
+    pub fn verify_upload(&self, key: &str, expires: i64, signature: &str) -> bool {
+        if expires < Utc::now().timestamp() {
+            return false;
+        }
+        let message = format!("PUT\n{}\n{}", key, expires);
+        let Some(tag) = unhex(signature) else {
+            return false;
+        };
+        hmac::verify(&self.signing_key, message.as_bytes(), &tag).is_ok()
+    }
+
+    /// Where the image for `key` is stored on disk
+    pub fn path_for(&self, key: &str) -> Result<PathBuf, AvatarStorageError> {
+        if !is_valid_avatar_key(key) {
+            return Err(AvatarStorageError::InvalidKey(key.to_string()));
+        }
+        Ok(self.dir.join(Path::new(key)))
+    }
+}
+
+impl AvatarStorage for LocalAvatarStorage {
+    fn backend(&self) -> &'static str {
+        "local"
+    }
+
+    fn presign_upload(&self, key: &str, expires_at: DateTime<Utc>) -> String {
+        let expires = expires_at.timestamp();
+        format!(
+            "{}/{}?expires={}&signature={}",
+            self.base_url,
+            key,
+            expires,
+            self.signature(key, expires)
+        )
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, key)
+    }
+}
+
+/// Any S3-compatible object store (AWS S3, MinIO, R2, ...), addressed
+/// path-style. Upload URLs are presigned with AWS Signature Version 4.
+pub struct S3AvatarStorage {
+    endpoint: String,
+    host: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    public_base_url: String,
+}
+
+impl S3AvatarStorage {
+    /// Reads `AVATAR_S3_ENDPOINT` (e.g. `https://s3.eu-west-1.amazonaws.com`),
+    /// `AVATAR_S3_BUCKET`, `AVATAR_S3_REGION` (default `us-east-1`),
+    /// `AVATAR_S3_ACCESS_KEY`, `AVATAR_S3_SECRET_KEY` and
+    /// `AVATAR_PUBLIC_BASE_URL` (default: the bucket's URL, e.g. a CDN
+    /// in front of it).
+    pub fn from_env() -> Result<Self, AvatarStorageError> {
+        let required = |name: &'static str| {
+            std::env::var(name).map_err(|_| AvatarStorageError::MissingSetting(name))
+        };
+
+        let endpoint = required("AVATAR_S3_ENDPOINT")?
+            .trim_end_matches('/')
+            .to_string();
+        let host = endpoint
+            .parse::<Uri>()
+            .ok()
+            .and_then(|uri| uri.authority().map(|authority| authority.to_string()))
+            .ok_or_else(|| AvatarStorageError::InvalidSetting {
+                name: "AVATAR_S3_ENDPOINT",
+                message: format!("'{}' is not an absolute URL", endpoint),
+            })?;
+        let bucket = required("AVATAR_S3_BUCKET")?;
+
+        let storage = Self {
+            public_base_url: base_url_from_env(&format!("{}/{}", endpoint, bucket)),
+            host,
+            bucket,
+            region: std::env::var("AVATAR_S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            access_key: required("AVATAR_S3_ACCESS_KEY")?,
+            secret_key: required("AVATAR_S3_SECRET_KEY")?,
+            endpoint,
+        };
+        info!(
+            "Avatars stored in S3 bucket '{}' at {}",
+            storage.bucket, storage.endpoint
+        );
+        Ok(storage)
+    }
+
+    /// Query-string SigV4 signature for a `PUT` of `key`, signing only the
+    /// `host` header so any client can use the URL as is
+    fn presign(&self, key: &str, now: DateTime<Utc>, expires_in: i64) -> String {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let path = format!(
+            "/{}/{}",
+            uri_encode(&self.bucket, false),
+            uri_encode(key, false)
+        );
+
+        // Already in the sorted order SigV4 requires
+        let query = [
+            ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
+            ("X-Amz-Credential", format!("{}/{}", self.access_key, scope)),
+            ("X-Amz-Date", amz_date.clone()),
+            ("X-Amz-Expires", expires_in.to_string()),
+            ("X-Amz-SignedHeaders", "host".to_string()),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, uri_encode(value, true)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+        let canonical_request = format!(
+            "PUT\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+            path, query, self.host
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(digest(&SHA256, canonical_request.as_bytes()).as_ref())
+        );
+
+        let sign = |key: &[u8], data: &str| {
+            hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+        };
+        let signing_key = [self.region.as_str(), "s3", "aws4_request"].iter().fold(
+            sign(format!("AWS4{}", self.secret_key).as_bytes(), &date),
+            |key, part| sign(key.as_ref(), part),
+        );
+        let signature = hex(sign(signing_key.as_ref(), &string_to_sign).as_ref());
+
+        format!(
+            "{}{}?{}&X-Amz-Signature={}",
+            self.endpoint, path, query, signature
+        )
+    }
+}
+
+impl AvatarStorage for S3AvatarStorage {
+    fn backend(&self) -> &'static str {
+        "s3"
+    }
+
+    fn presign_upload(&self, key: &str, expires_at: DateTime<Utc>) -> String {
+        let now = Utc::now();
+        // SigV4 accepts 1 second to 7 days
+        let expires_in = (expires_at - now).num_seconds().clamp(1, 604_800);
+        self.presign(key, now, expires_in)
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_base_url, key)
+    }
+}
+
+fn base_url_from_env(default: &str) -> String {
+    std::env::var("AVATAR_PUBLIC_BASE_URL")
+        .unwrap_or_else(|_| default.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `None` unless `value` is an even-length hex string
+fn unhex(value: &str) -> Option<Vec<u8>> {
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Percent-encodes everything but unreserved characters, as SigV4 requires;
+/// `/` is kept in paths
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b'/' if !encode_slash => "/".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
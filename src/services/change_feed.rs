@@ -0,0 +1,204 @@
+use crate::{
+    models::change_model::{ChangeAction, ChangeEvent},
+    repositories::connection::DbConnection,
+};
+use chrono::Utc;
+use futures::StreamExt;
+use jsonrpsee::{SubscriptionMessage, SubscriptionSink};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use surrealdb::{sql::Thing, Action};
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
+use tracing::{debug, info, warn};
+
+/// Changes buffered per subscriber before the slowest ones start missing
+/// events
+const CHANNEL_CAPACITY: usize = 1024;
+/// Pause before re-subscribing after a live query ends or fails, e.g.
+/// while the database connection is being re-established
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(2);
+
+/// The part of a changed record a live query needs
+#[derive(Debug, Deserialize)]
+struct ChangedRecord {
+    id: Thing,
+}
+
+/// Pushes changes to the service's tables to in-process caches and
+/// JSON-RPC subscribers.
+///
+/// Each watched table gets a SurrealDB `LIVE SELECT`, so writes made by
+/// other replicas, migrations or the SurrealDB console are seen too, not
+/// just this process's own. The live queries are re-created whenever they
+/// end, which happens when the connection to the database is replaced.
+/// Live queries do not work over the HTTP engine, so the feed is off
+/// unless `LIVE_QUERIES=true`.
+#[derive(Debug)]
+pub struct ChangeFeed {
+    tables: Vec<&'static str>,
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl ChangeFeed {
+    /// Starts watching `tables` when `LIVE_QUERIES=true`
+    pub fn from_env(db: &Arc<DbConnection>, tables: &[&'static str]) -> Option<Arc<Self>> {
+        let enabled = std::env::var("LIVE_QUERIES")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        for table in tables {
+            tokio::spawn(watch_table(Arc::clone(db), table, sender.clone()));
+        }
+        info!("📡 Live queries watching {}", tables.join(", "));
+        Some(Arc::new(Self {
+            tables: tables.to_vec(),
+            sender,
+        }))
+    }
+
+    /// The tables this feed reports changes for
+    pub fn tables(&self) -> &[&'static str] {
+        &self.tables
+    }
+
+    /// The tables a subscriber asked for, all of them when it named none.
+    /// Fails with the first table this feed does not watch.
+    pub fn resolve_tables(&self, requested: &[String]) -> Result<Vec<String>, String> {
+        if requested.is_empty() {
+            return Ok(self.tables.iter().map(|table| table.to_string()).collect());
+        }
+        match requested
+            .iter()
+            .find(|table| !self.tables.contains(&table.as_str()))
+        {
+            Some(unknown) => Err(unknown.clone()),
+            None => Ok(requested.to_vec()),
+        }
+    }
+
+    /// Every change from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Tracks changes to `table` for a cache
+    pub fn watch(&self, table: &'static str) -> ChangeWatcher {
+        ChangeWatcher {
+            table,
+            receiver: Mutex::new(self.subscribe()),
+        }
+    }
+}
+
+/// A cache's view of the change feed: tells it whether its table changed
+/// since it last asked, so it can drop entries before serving them
+#[derive(Debug)]
+pub struct ChangeWatcher {
+    table: &'static str,
+    receiver: Mutex<broadcast::Receiver<ChangeEvent>>,
+}
+
+impl ChangeWatcher {
+    /// Whether the table changed since the last call. Missed events count
+    /// as a change.
+    pub fn changed(&self) -> bool {
+        let mut receiver = self.receiver.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut changed = false;
+        loop {
+            match receiver.try_recv() {
+                Ok(event) => changed |= event.table == self.table,
+                Err(TryRecvError::Lagged(_)) => changed = true,
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return changed,
+            }
+        }
+    }
+}
+
+/// Sends changes to `tables` to a JSON-RPC subscriber until it
+/// unsubscribes or disconnects. A subscriber too slow to keep up is dropped
+/// rather than silently skipped, so it knows to re-fetch what it caches.
+pub async fn forward_changes(
+    mut receiver: broadcast::Receiver<ChangeEvent>,
+    tables: Vec<String>,
+    sink: SubscriptionSink,
+) {
+    loop {
+        let event = tokio::select! {
+            _ = sink.closed() => return,
+            event = receiver.recv() => event,
+        };
+        match event {
+            Ok(event) if tables.contains(&event.table) => {
+                let Ok(message) = SubscriptionMessage::from_json(&event) else {
+                    continue;
+                };
+                if sink.send(message).await.is_err() {
+                    return;
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => {
+                warn!(
+                    "Dropping change subscriber {:?}: missed {} events",
+                    sink.subscription_id(),
+                    missed
+                );
+                return;
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Keeps a live query on `table` running for the life of the process
+async fn watch_table(
+    db: Arc<DbConnection>,
+    table: &'static str,
+    sender: broadcast::Sender<ChangeEvent>,
+) {
+    loop {
+        match stream_changes(&db, table, &sender).await {
+            Ok(()) => debug!("Live query on {} ended, re-subscribing", table),
+            Err(err) => warn!(
+                "Live query on {} failed: {}; retrying in {:?}",
+                table, err, RESUBSCRIBE_DELAY
+            ),
+        }
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+async fn stream_changes(
+    db: &DbConnection,
+    table: &'static str,
+    sender: &broadcast::Sender<ChangeEvent>,
+) -> anyhow::Result<()> {
+    let handle = db.handle()?;
+    let mut stream = handle.select::<Vec<ChangedRecord>>(table).live().await?;
+    while let Some(notification) = stream.next().await {
+        let notification = notification?;
+        let action = if notification.action == Action::Create {
+            ChangeAction::Created
+        } else if notification.action == Action::Delete {
+            ChangeAction::Deleted
+        } else {
+            ChangeAction::Updated
+        };
+        // No subscribers is fine; the event is simply dropped
+        let _ = sender.send(ChangeEvent {
+            table: table.to_string(),
+            id: notification.data.id.to_string(),
+            action,
+            observed_at: Utc::now(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use crate::models::event_model::LogEventRequest;
+use tracing::{debug, error, info, warn};
+
+const MAX_EVENT_NAME_LENGTH: usize = 100;
+
+/// Writes a client event to the log under the `client_event` target so it
+/// can be filtered separately from the service's own output.
+pub fn log_client_event(service: &str, request: &LogEventRequest) {
+    let event: String = request.event.chars().take(MAX_EVENT_NAME_LENGTH).collect();
+    let fields = &request.fields;
+
+    match request.level.as_deref() {
+        Some("debug") => debug!(target: "client_event", service, fields = %fields, "{}", event),
+        Some("warn") => warn!(target: "client_event", service, fields = %fields, "{}", event),
+        Some("error") => error!(target: "client_event", service, fields = %fields, "{}", event),
+        _ => info!(target: "client_event", service, fields = %fields, "{}", event),
+    }
+}
@@ -0,0 +1,78 @@
+use crate::models::coupon_model::{Coupon, CouponCheckout, DiscountType};
+use chrono::{DateTime, Utc};
+
+/// Coupon codes are matched case-insensitively and without surrounding
+/// whitespace
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_ascii_uppercase()
+}
+
+/// Rounds a currency amount to cents
+pub fn round_to_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+/// Computes the discount `coupon` gives on `checkout` at `now`, or the
+/// reason it does not apply. Usage limits are checked here for a quick
+/// answer, but only the atomic increment at redemption enforces them.
+///
+/// This is the integration point for checkout flows running in-process;
+/// remote callers use the `validate_coupon` and `redeem_coupon` RPCs.
+pub fn quote(
+    coupon: &Coupon,
+    checkout: &CouponCheckout,
+    now: DateTime<Utc>,
+) -> Result<f64, String> {
+    if !coupon.active {
+        return Err("Coupon is not active".to_string());
+    }
+    if coupon
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+    {
+        return Err("Coupon has expired".to_string());
+    }
+    if coupon
+        .max_redemptions
+        .is_some_and(|max| coupon.redemptions >= max)
+    {
+        return Err("Coupon usage limit reached".to_string());
+    }
+    if !(checkout.order_total.is_finite() && checkout.order_total >= 0.0) {
+        return Err(format!("Invalid order total: {}", checkout.order_total));
+    }
+
+    let constraints = &coupon.constraints;
+    if let Some(min) = constraints.min_order_total {
+        if checkout.order_total < min {
+            return Err(format!("Order total must be at least {}", min));
+        }
+    }
+
+    let eligible = if constraints.restricts_items() {
+        checkout
+            .items
+            .iter()
+            .filter(|item| {
+                constraints.product_ids.contains(&item.product_id)
+                    || constraints
+                        .categories
+                        .iter()
+                        .any(|category| category.eq_ignore_ascii_case(&item.category))
+            })
+            .map(|item| item.amount.max(0.0))
+            .sum::<f64>()
+            .min(checkout.order_total)
+    } else {
+        checkout.order_total
+    };
+    if eligible <= 0.0 {
+        return Err("No items in the order are eligible for this coupon".to_string());
+    }
+
+    let discount = match coupon.discount_type {
+        DiscountType::Percent => eligible * coupon.value / 100.0,
+        DiscountType::Fixed => coupon.value.min(eligible),
+    };
+    Ok(round_to_cents(discount))
+}
@@ -0,0 +1,195 @@
+use crate::{
+    config::database::DatabaseConfig, models::admin_model::DatabaseIsolationReport,
+    repositories::connection::DbConnection, telemetry::query_metrics::traced_query,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+use thiserror::Error;
+
+pub const USER_SERVICE: &str = "user-service";
+pub const PRODUCT_SERVICE: &str = "product-service";
+const GATEWAY: &str = "gateway";
+
+/// Tables only the user service writes
+const USER_TABLES: &[&str] = &[
+    "user",
+    "user_event",
+    "signup_attempt",
+    "fraud_hit",
+    "organization",
+    "member_of",
+];
+
+/// Tables only the product service writes
+const PRODUCT_TABLES: &[&str] = &[
+    "product",
+    "price_history",
+    "scheduled_price_change",
+    "coupon",
+    "location",
+    "stock",
+    "stock_movement",
+    "order_history",
+    "product_return",
+    "product_co_occurrence",
+    "attribute_definition",
+    "product_attribute",
+    "job",
+    "category_rollup",
+];
+
+const TABLE_OWNERS: &[(&str, &[&str])] = &[
+    (USER_SERVICE, USER_TABLES),
+    (PRODUCT_SERVICE, PRODUCT_TABLES),
+];
+
+/// Returned by [`check_configured`] when two services are configured with
+/// the same database
+#[derive(Error, Debug)]
+#[error("{service} and {other} both use {database}")]
+pub struct SharedDatabase {
+    pub service: String,
+    pub other: String,
+    /// `namespace/database on endpoint`
+    pub database: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionInfo {
+    namespace: Option<String>,
+    database: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DatabaseInfo {
+    #[serde(default)]
+    tables: HashMap<String, serde_json::Value>,
+}
+
+/// The database every part of the system is configured with. Settings
+/// that fail to load are left out; each service reports its own.
+fn configured_databases() -> Vec<(&'static str, DatabaseConfig)> {
+    [
+        (USER_SERVICE, DatabaseConfig::user()),
+        (PRODUCT_SERVICE, DatabaseConfig::product()),
+        (GATEWAY, DatabaseConfig::gateway_health()),
+    ]
+    .into_iter()
+    .filter_map(|(service, config)| Some((service, config.ok()?)))
+    .collect()
+}
+
+/// Whether two configs point at the same namespace and database of one
+/// server. `mem://` databases live in their own process and never collide.
+fn same_database(a: &DatabaseConfig, b: &DatabaseConfig) -> bool {
+    a.is_remote()
+        && a.endpoint.trim_end_matches('/') == b.endpoint.trim_end_matches('/')
+        && a.namespace == b.namespace
+        && a.database == b.database
+}
+
+/// Other services configured with the same database as `service`
+fn sharing_services(service: &str, config: &DatabaseConfig) -> Vec<&'static str> {
+    configured_databases()
+        .into_iter()
+        .filter(|(other, other_config)| *other != service && same_database(config, other_config))
+        .map(|(other, _)| other)
+        .collect()
+}
+
+/// Fails when `service`'s database settings also match another service's,
+/// e.g. after copying one config section into another
+pub fn check_configured(service: &str, config: &DatabaseConfig) -> Result<(), SharedDatabase> {
+    match sharing_services(service, config).first() {
+        Some(other) => Err(SharedDatabase {
+            service: service.to_string(),
+            other: other.to_string(),
+            database: format!(
+                "{}/{} on {}",
+                config.namespace, config.database, config.endpoint
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Reports the namespace, database and tables `service`'s connection
+/// actually uses. It is isolated when the session matches the config, no
+/// other service is configured with the same database and no table owned
+/// by another service is present.
+pub async fn inspect(
+    service: &str,
+    connection: &DbConnection,
+) -> anyhow::Result<DatabaseIsolationReport> {
+    let handle = connection.handle()?;
+    let session: Option<SessionInfo> = traced_query(
+        "RETURN { namespace: session::ns(), database: session::db() }",
+        |sql| handle.query(sql),
+    )
+    .await?
+    .take(0)?;
+    let info: Option<DatabaseInfo> = traced_query("INFO FOR DB", |sql| handle.query(sql))
+        .await?
+        .take(0)?;
+
+    let config = connection.config();
+    let (namespace, database) = session.map_or((None, None), |session| {
+        (session.namespace, session.database)
+    });
+    let mut tables: Vec<String> = info.unwrap_or_default().tables.into_keys().collect();
+    tables.sort();
+
+    let mut problems = Vec::new();
+    if namespace.as_deref() != Some(config.namespace.as_str()) {
+        problems.push(format!(
+            "Connected to namespace {:?}, configured {}",
+            namespace, config.namespace
+        ));
+    }
+    if database.as_deref() != Some(config.database.as_str()) {
+        problems.push(format!(
+            "Connected to database {:?}, configured {}",
+            database, config.database
+        ));
+    }
+
+    let shared_with: Vec<String> = sharing_services(service, config)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    for other in &shared_with {
+        problems.push(format!("{} is configured with the same database", other));
+    }
+
+    let foreign_tables: Vec<String> = tables
+        .iter()
+        .filter(|table| {
+            TABLE_OWNERS
+                .iter()
+                .any(|(owner, owned)| *owner != service && owned.contains(&table.as_str()))
+        })
+        .cloned()
+        .collect();
+    if !foreign_tables.is_empty() {
+        problems.push(format!(
+            "Holds tables of another service: {}",
+            foreign_tables.join(", ")
+        ));
+    }
+
+    Ok(DatabaseIsolationReport {
+        service: service.to_string(),
+        endpoint: config.endpoint.clone(),
+        configured_namespace: config.namespace.clone(),
+        configured_database: config.database.clone(),
+        namespace,
+        database,
+        tables,
+        foreign_tables,
+        shared_with,
+        isolated: problems.is_empty(),
+        problems,
+        checked_at: Utc::now(),
+    })
+}
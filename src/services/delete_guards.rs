@@ -0,0 +1,140 @@
+use crate::models::admin_model::{DeletePolicy, DependentRecords};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DeleteGuardError {
+    #[error("Invalid {var} entry '{entry}', expected <dependent>=block, cascade or orphan")]
+    InvalidEntry { var: &'static str, entry: String },
+
+    #[error("Unknown dependent '{dependent}' in {var}, expected one of: {known}")]
+    UnknownDependent {
+        var: &'static str,
+        dependent: String,
+        known: String,
+    },
+
+    #[error("{var} sets '{dependent}' to {policy}, which it does not support")]
+    UnsupportedPolicy {
+        var: &'static str,
+        dependent: String,
+        policy: DeletePolicy,
+    },
+}
+
+/// A kind of record that refers to the records a service deletes, and the
+/// policies it can be handled with
+#[derive(Debug, Clone, Copy)]
+pub struct Dependent {
+    pub name: &'static str,
+    pub supports: &'static [DeletePolicy],
+}
+
+/// The dependents found for one delete
+#[derive(Debug, Clone, Default)]
+pub struct DeletePlan {
+    pub dependents: Vec<DependentRecords>,
+}
+
+impl DeletePlan {
+    /// Dependents that stop the delete
+    pub fn blocking(&self) -> Vec<DependentRecords> {
+        self.dependents
+            .iter()
+            .filter(|records| records.policy == DeletePolicy::Block)
+            .cloned()
+            .collect()
+    }
+}
+
+/// The policy for each registered dependent of a service's deletes.
+/// Every dependent blocks deletes until configured otherwise, so nothing
+/// is removed or left dangling without someone choosing it.
+#[derive(Debug, Clone)]
+pub struct DeleteGuards {
+    policies: BTreeMap<&'static str, DeletePolicy>,
+}
+
+impl DeleteGuards {
+    pub fn new(dependents: &[Dependent]) -> Self {
+        Self {
+            policies: dependents
+                .iter()
+                .map(|dependent| (dependent.name, DeletePolicy::Block))
+                .collect(),
+        }
+    }
+
+    /// Reads policies from `var` as `<dependent>=<policy>,...`, e.g.
+    /// `org_memberships=cascade`; unlisted dependents block
+    pub fn from_env(var: &'static str, dependents: &[Dependent]) -> Result<Self, DeleteGuardError> {
+        let mut guards = Self::new(dependents);
+        for entry in std::env::var(var)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (name, policy) = entry
+                .split_once('=')
+                .and_then(|(name, policy)| Some((name.trim(), DeletePolicy::parse(policy)?)))
+                .ok_or_else(|| DeleteGuardError::InvalidEntry {
+                    var,
+                    entry: entry.to_string(),
+                })?;
+            let dependent = dependents
+                .iter()
+                .find(|dependent| dependent.name == name)
+                .ok_or_else(|| DeleteGuardError::UnknownDependent {
+                    var,
+                    dependent: name.to_string(),
+                    known: dependents
+                        .iter()
+                        .map(|dependent| dependent.name)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                })?;
+            if !dependent.supports.contains(&policy) {
+                return Err(DeleteGuardError::UnsupportedPolicy {
+                    var,
+                    dependent: name.to_string(),
+                    policy,
+                });
+            }
+            guards.policies.insert(dependent.name, policy);
+        }
+        Ok(guards)
+    }
+
+    pub fn policy(&self, dependent: &str) -> DeletePolicy {
+        self.policies
+            .get(dependent)
+            .copied()
+            .unwrap_or(DeletePolicy::Block)
+    }
+
+    /// Pairs each dependent's count of referring records with its policy,
+    /// leaving out dependents with none
+    pub fn plan<'a>(&self, counts: impl IntoIterator<Item = (&'a str, u64)>) -> DeletePlan {
+        DeletePlan {
+            dependents: counts
+                .into_iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(dependent, count)| DependentRecords {
+                    dependent: dependent.to_string(),
+                    count,
+                    policy: self.policy(dependent),
+                })
+                .collect(),
+        }
+    }
+
+    /// Policies for the startup log, e.g. `org_memberships=block`
+    pub fn describe(&self) -> String {
+        self.policies
+            .iter()
+            .map(|(dependent, policy)| format!("{}={}", dependent, policy))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
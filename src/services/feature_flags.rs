@@ -0,0 +1,215 @@
+use crate::{
+    errors::feature_flag_error::FeatureFlagError,
+    models::feature_flag_model::{
+        FeatureFlag, FeatureFlagForCreation, FlagContext, FlagEvaluation, FlagReason,
+        SetFeatureFlagRequest,
+    },
+    repositories::{connection::DbConnection, feature_flag_repository::FeatureFlagRepository},
+    services::change_feed::ChangeWatcher,
+};
+use chrono::Utc;
+use ring::digest::{digest, SHA256};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const MAX_KEY_LEN: usize = 64;
+
+/// Reads `FEATURE_FLAG_REFRESH_SECS` (default 30): how long evaluations use
+/// the cached flags before reloading them, which is how changes made
+/// through another replica arrive
+fn refresh_interval_from_env() -> Duration {
+    let secs = std::env::var("FEATURE_FLAG_REFRESH_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    Duration::from_secs(secs)
+}
+
+struct FlagCache {
+    flags: HashMap<String, FeatureFlag>,
+    loaded_at: Instant,
+}
+
+/// Evaluation client for the service's feature flags.
+///
+/// Services gate new behaviors with `flags.is_enabled("key", &context)`.
+/// Flags are read from a cache reloaded from the database at most every
+/// refresh interval, so checks on the request path rarely touch the
+/// database; with live queries, also as soon as any flag changes. Unknown
+/// flags, and every flag while the database cannot be read, evaluate to
+/// off.
+pub struct FeatureFlags {
+    repository: FeatureFlagRepository,
+    refresh_interval: Duration,
+    cache: RwLock<Option<FlagCache>>,
+    /// Changes to the `feature_flag` table, when live queries are on
+    changes: Option<ChangeWatcher>,
+}
+
+impl FeatureFlags {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            repository: FeatureFlagRepository::new(db),
+            refresh_interval: refresh_interval_from_env(),
+            cache: RwLock::new(None),
+            changes: None,
+        }
+    }
+
+    /// Reloads the flags whenever `changes` reports a change
+    pub fn with_change_watcher(mut self, changes: Option<ChangeWatcher>) -> Self {
+        self.changes = changes;
+        self
+    }
+
+    /// Whether `key` is on for `context`
+    pub async fn is_enabled(&self, key: &str, context: &FlagContext) -> bool {
+        match self.evaluate(key, context).await {
+            Ok(evaluation) => evaluation.enabled,
+            Err(err) => {
+                warn!("Feature flag {} evaluated as off: {}", key, err);
+                false
+            }
+        }
+    }
+
+    pub async fn evaluate(
+        &self,
+        key: &str,
+        context: &FlagContext,
+    ) -> Result<FlagEvaluation, FeatureFlagError> {
+        self.refresh_if_stale().await?;
+        let cache = self.cache.read().unwrap_or_else(PoisonError::into_inner);
+        let flag = cache.as_ref().and_then(|cache| cache.flags.get(key));
+        let (enabled, reason) = match flag {
+            Some(flag) => evaluate_flag(flag, context),
+            None => (false, FlagReason::Unknown),
+        };
+        Ok(FlagEvaluation {
+            key: key.to_string(),
+            enabled,
+            reason,
+        })
+    }
+
+    /// Every flag, fresh from the database
+    pub async fn list(&self) -> Result<Vec<FeatureFlag>, FeatureFlagError> {
+        let flags = self.repository.list_flags().await?;
+        self.replace_cache(&flags);
+        Ok(flags)
+    }
+
+    /// Creates or replaces a flag. This replica sees the change at once,
+    /// others within their refresh interval, or right away with live
+    /// queries.
+    pub async fn set(
+        &self,
+        request: SetFeatureFlagRequest,
+    ) -> Result<FeatureFlag, FeatureFlagError> {
+        let key = request.key.trim().to_string();
+        if key.is_empty()
+            || key.len() > MAX_KEY_LEN
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        {
+            return Err(FeatureFlagError::Validation {
+                message: format!(
+                    "Invalid flag key '{}': use up to {} letters, digits, '.', '_' or '-'",
+                    request.key, MAX_KEY_LEN
+                ),
+            });
+        }
+        let rollout_percent = request.rollout_percent.unwrap_or(100);
+        if rollout_percent > 100 {
+            return Err(FeatureFlagError::Validation {
+                message: format!("rollout_percent must be 0-100, got {}", rollout_percent),
+            });
+        }
+
+        let flag = self
+            .repository
+            .save_flag(FeatureFlagForCreation {
+                key,
+                description: request.description.unwrap_or_default(),
+                enabled: request.enabled,
+                rollout_percent,
+                tenants: request.tenants,
+                users: request.users,
+                updated_at: Utc::now(),
+            })
+            .await?;
+        info!(
+            "🚩 Feature flag {} set: enabled={}, rollout={}%",
+            flag.key, flag.enabled, flag.rollout_percent
+        );
+
+        let mut cache = self.cache.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(cache) = cache.as_mut() {
+            cache.flags.insert(flag.key.clone(), flag.clone());
+        }
+        Ok(flag)
+    }
+
+    async fn refresh_if_stale(&self) -> Result<(), FeatureFlagError> {
+        let fresh = self
+            .cache
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .is_some_and(|cache| cache.loaded_at.elapsed() < self.refresh_interval);
+        let changed = self.changes.as_ref().is_some_and(ChangeWatcher::changed);
+        if !fresh || changed {
+            let flags = self.repository.list_flags().await?;
+            self.replace_cache(&flags);
+        }
+        Ok(())
+    }
+
+    fn replace_cache(&self, flags: &[FeatureFlag]) {
+        let flags = flags
+            .iter()
+            .map(|flag| (flag.key.clone(), flag.clone()))
+            .collect();
+        *self.cache.write().unwrap_or_else(PoisonError::into_inner) = Some(FlagCache {
+            flags,
+            loaded_at: Instant::now(),
+        });
+    }
+}
+
+fn evaluate_flag(flag: &FeatureFlag, context: &FlagContext) -> (bool, FlagReason) {
+    if !flag.enabled {
+        return (false, FlagReason::Disabled);
+    }
+    if let Some(user_id) = &context.user_id {
+        if flag.users.contains(user_id) {
+            return (true, FlagReason::TargetedUser);
+        }
+    }
+    if let Some(tenant_id) = &context.tenant_id {
+        if flag.tenants.contains(tenant_id) {
+            return (true, FlagReason::TargetedTenant);
+        }
+    }
+    if flag.rollout_percent >= 100 {
+        return (true, FlagReason::Rollout);
+    }
+    match &context.user_id {
+        Some(user_id) if rollout_bucket(&flag.key, user_id) < flag.rollout_percent => {
+            (true, FlagReason::Rollout)
+        }
+        _ => (false, FlagReason::NotInRollout),
+    }
+}
+
+/// Stable 0-99 bucket for a user and flag, so raising a flag's percentage
+/// only ever adds users and different flags pick different users
+fn rollout_bucket(key: &str, user_id: &str) -> u8 {
+    let hash = digest(&SHA256, format!("{}:{}", key, user_id).as_bytes());
+    let bytes = hash.as_ref();
+    let value = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (value % 100) as u8
+}
@@ -0,0 +1,147 @@
+use crate::{models::job_model::JobEvent, services::product_service::ProductService};
+use jsonrpsee::{SubscriptionMessage, SubscriptionSink};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{Notify, RwLock};
+use tracing::{info, warn};
+
+/// Job events buffered per subscriber before the slowest ones start
+/// missing events
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// How many jobs run at once and how often idle workers look for queued
+/// jobs they were not woken for, e.g. ones queued by another replica
+#[derive(Debug, Clone, Copy)]
+pub struct JobWorkerConfig {
+    pub workers: usize,
+    pub poll_interval: Duration,
+}
+
+impl JobWorkerConfig {
+    /// Reads `JOB_WORKERS` (default 1, `0` leaves jobs queued) and
+    /// `JOB_POLL_INTERVAL_SECS` (default 5)
+    pub fn from_env() -> Self {
+        let workers = std::env::var("JOB_WORKERS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1);
+        let poll_secs = std::env::var("JOB_POLL_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(5);
+        Self {
+            workers,
+            poll_interval: Duration::from_secs(poll_secs),
+        }
+    }
+}
+
+/// Wakes the workers when a job is queued and fans job events out to
+/// subscribers
+#[derive(Debug)]
+pub struct JobBoard {
+    events: broadcast::Sender<JobEvent>,
+    queued: Notify,
+}
+
+impl Default for JobBoard {
+    fn default() -> Self {
+        let (events, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            events,
+            queued: Notify::new(),
+        }
+    }
+}
+
+impl JobBoard {
+    pub fn publish(&self, event: JobEvent) {
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    /// Wakes one idle worker
+    pub fn notify_queued(&self) {
+        self.queued.notify_one();
+    }
+}
+
+/// Runs queued jobs on `config.workers` workers. Each worker runs one job
+/// at a time and waits for a new one, or the poll interval, when the queue
+/// is empty.
+pub fn spawn_job_workers(service: Arc<RwLock<Option<ProductService>>>, config: JobWorkerConfig) {
+    for worker in 0..config.workers {
+        let service = Arc::clone(&service);
+        tokio::spawn(async move {
+            loop {
+                let board = {
+                    let guard = service.read().await;
+                    let Some(service) = guard.as_ref() else {
+                        drop(guard);
+                        tokio::time::sleep(config.poll_interval).await;
+                        continue;
+                    };
+
+                    match service.run_next_job().await {
+                        Ok(Some(job)) => {
+                            info!(
+                                "⚙️ Worker {} finished {} job {}: {}",
+                                worker, job.kind, job.id, job.status
+                            );
+                            continue;
+                        }
+                        Ok(None) => {}
+                        Err(err) => warn!("Worker {} failed to run a job: {}", worker, err),
+                    }
+                    Arc::clone(service.job_board())
+                };
+
+                tokio::select! {
+                    _ = board.queued.notified() => {}
+                    _ = tokio::time::sleep(config.poll_interval) => {}
+                }
+            }
+        });
+    }
+}
+
+/// Sends job events, for one job or all of them, to a subscriber until it
+/// unsubscribes
+pub async fn forward_job_events(
+    mut receiver: broadcast::Receiver<JobEvent>,
+    job_id: Option<String>,
+    sink: SubscriptionSink,
+) {
+    loop {
+        let event = tokio::select! {
+            _ = sink.closed() => return,
+            event = receiver.recv() => event,
+        };
+        match event {
+            Ok(event) if job_id.as_ref().is_none_or(|id| *id == event.job_id) => {
+                let Ok(message) = SubscriptionMessage::from_json(&event) else {
+                    continue;
+                };
+                if sink.send(message).await.is_err() {
+                    return;
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => {
+                warn!(
+                    "Dropping job event subscriber {:?}: missed {} events",
+                    sink.subscription_id(),
+                    missed
+                );
+                return;
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
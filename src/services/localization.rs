@@ -0,0 +1,89 @@
+use crate::models::product_model::Product;
+
+/// Reads `PRODUCT_DEFAULT_LOCALE`, the locale of every product's own `name`
+/// and `description` (default `en`)
+pub fn default_locale_from_env() -> String {
+    std::env::var("PRODUCT_DEFAULT_LOCALE")
+        .ok()
+        .and_then(|tag| normalize_locale(&tag))
+        .unwrap_or_else(|| "en".to_string())
+}
+
+/// Canonical form of a BCP 47 language tag such as `fr`, `pt-br` or
+/// `zh-Hant-TW`: lowercase language, title-case script, uppercase region.
+/// `None` if `tag` does not look like one.
+pub fn normalize_locale(tag: &str) -> Option<String> {
+    let mut subtags = tag.trim().split(['-', '_']);
+    let language = subtags.next()?;
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let mut normalized = language.to_ascii_lowercase();
+    for subtag in subtags {
+        if !(2..=8).contains(&subtag.len()) || !subtag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        normalized.push('-');
+        match subtag.len() {
+            2 => normalized.push_str(&subtag.to_ascii_uppercase()),
+            4 => {
+                normalized.push_str(&subtag[..1].to_ascii_uppercase());
+                normalized.push_str(&subtag[1..].to_ascii_lowercase());
+            }
+            _ => normalized.push_str(&subtag.to_ascii_lowercase()),
+        }
+    }
+    Some(normalized)
+}
+
+/// Locales to try for `locale`, most specific first: `fr-CA` falls back to
+/// `fr`. The default locale is the product's own content and is not listed.
+fn fallback_chain(locale: &str) -> Vec<&str> {
+    let mut chain = vec![locale];
+    let mut current = locale;
+    while let Some((parent, _)) = current.rsplit_once('-') {
+        chain.push(parent);
+        current = parent;
+    }
+    chain
+}
+
+/// Replaces the product's name and description with their translation into
+/// `locale`, field by field, falling back to less specific locales and then
+/// to the default locale. Returns the product and the locale its name is
+/// served in.
+pub fn localize_product(
+    mut product: Product,
+    locale: &str,
+    default_locale: &str,
+) -> (Product, String) {
+    let mut served = default_locale.to_string();
+    let mut name = None;
+    let mut description = None;
+    for candidate in fallback_chain(locale) {
+        if candidate == default_locale {
+            break;
+        }
+        let Some(translation) = product.translations.get(candidate) else {
+            continue;
+        };
+        if name.is_none() {
+            if let Some(translated) = &translation.name {
+                name = Some(translated.clone());
+                served = candidate.to_string();
+            }
+        }
+        if description.is_none() {
+            description = translation.description.clone();
+        }
+    }
+
+    if let Some(name) = name {
+        product.name = name;
+    }
+    if let Some(description) = description {
+        product.description = description;
+    }
+    (product, served)
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LoginPolicyError {
+    #[error("Invalid {name} '{value}', expected a positive number")]
+    InvalidValue { name: &'static str, value: String },
+}
+
+/// When repeated failed sign-ins lock an account, and for how long.
+///
+/// The `max_failures`-th consecutive failure locks the account for
+/// `base_lockout`, and every failure after the lock expires doubles it, up
+/// to `max_lockout`. A successful sign-in resets the count.
+#[derive(Debug, Clone)]
+pub struct LoginPolicy {
+    pub max_failures: u32,
+    pub base_lockout: Duration,
+    pub max_lockout: Duration,
+}
+
+impl Default for LoginPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: 5,
+            base_lockout: Duration::from_secs(60),
+            max_lockout: Duration::from_secs(24 * 3600),
+        }
+    }
+}
+
+impl LoginPolicy {
+    /// Reads `LOGIN_MAX_FAILURES` (default 5), `LOGIN_LOCKOUT_SECS`
+    /// (default 60) and `LOGIN_LOCKOUT_MAX_SECS` (default 86400)
+    pub fn from_env() -> Result<Self, LoginPolicyError> {
+        let defaults = Self::default();
+        Ok(Self {
+            max_failures: env_positive("LOGIN_MAX_FAILURES")?
+                .map_or(defaults.max_failures, |n| n.min(u64::from(u32::MAX)) as u32),
+            base_lockout: env_positive("LOGIN_LOCKOUT_SECS")?
+                .map_or(defaults.base_lockout, Duration::from_secs),
+            max_lockout: env_positive("LOGIN_LOCKOUT_MAX_SECS")?
+                .map_or(defaults.max_lockout, Duration::from_secs),
+        })
+    }
+
+    /// How long the account is locked after `failures` consecutive failed
+    /// sign-ins; `None` below the threshold
+    pub fn lockout_for(&self, failures: u32) -> Option<Duration> {
+        let doublings = failures.checked_sub(self.max_failures)?;
+        let factor = 2u32.checked_pow(doublings).unwrap_or(u32::MAX);
+        Some(
+            self.base_lockout
+                .checked_mul(factor)
+                .map_or(self.max_lockout, |lockout| lockout.min(self.max_lockout)),
+        )
+    }
+}
+
+fn env_positive(name: &'static str) -> Result<Option<u64>, LoginPolicyError> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .map(Some)
+            .ok_or(LoginPolicyError::InvalidValue { name, value }),
+        Err(_) => Ok(None),
+    }
+}
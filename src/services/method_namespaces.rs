@@ -0,0 +1,137 @@
+use jsonrpsee::core::RegisterMethodError;
+use jsonrpsee::RpcModule;
+
+/// Method every service registers to report its own method names
+pub const METHOD_LIST_METHOD: &str = "rpc.methods";
+
+/// Namespaced name -> existing flat name, for methods every service exposes
+pub const COMMON_METHODS: &[(&str, &str)] = &[
+    ("system.health", "health"),
+    ("system.query_stats", "query_stats"),
+    ("system.panic_stats", "panic_stats"),
+    ("system.retention_stats", "retention_stats"),
+    ("system.storage_stats", "storage_stats"),
+    ("system.database_isolation", "database_isolation"),
+    ("admin.read_only.set", "set_read_only"),
+    ("admin.log_sampling.set", "set_log_sampling"),
+    ("admin.flags.set", "set_feature_flag"),
+    ("admin.flags.list", "list_feature_flags"),
+    ("flags.evaluate", "evaluate_feature_flag"),
+    ("events.log", "log_event"),
+    ("changes.subscribe", "subscribe_changes"),
+    ("changes.unsubscribe", "unsubscribe_changes"),
+];
+
+pub const USER_METHODS: &[(&str, &str)] = &[
+    ("user.create", "create_user"),
+    ("user.get", "get_user"),
+    ("user.update", "update_user"),
+    ("user.name.update", "update_user_name"),
+    ("user.name.history", "get_name_history"),
+    ("user.delete", "delete_user"),
+    ("user.history", "get_user_history"),
+    ("user.list", "list_users"),
+    ("user.export", "export_users"),
+    ("user.stats", "get_user_stats"),
+    ("user.list_inactive", "list_inactive_users"),
+    ("user.activity.record", "record_activity"),
+    ("user.login.record_attempt", "record_login_attempt"),
+    ("user.unlock", "unlock_user"),
+    ("user.encryption.rotate_keys", "rotate_encryption_keys"),
+    ("user.address.validate", "validate_address"),
+    ("user.avatar.request_upload", "request_avatar_upload"),
+    ("user.avatar.confirm", "confirm_avatar"),
+    ("user.fraud.hits", "list_fraud_hits"),
+    ("org.create", "create_org"),
+    ("org.member.add", "add_member"),
+    ("org.member.remove", "remove_member"),
+    ("org.member.list", "list_org_members"),
+];
+
+pub const PRODUCT_METHODS: &[(&str, &str)] = &[
+    ("product.create", "create_product"),
+    ("product.get", "get_product"),
+    ("product.find_similar", "find_similar_products"),
+    ("product.translation.set", "set_translation"),
+    ("product.visibility.set", "set_product_visibility"),
+    ("product.sku.set", "set_product_sku"),
+    ("product.attributes.define", "define_attribute"),
+    ("product.attributes.list", "list_attributes"),
+    ("product.attributes.set", "set_attribute"),
+    ("product.attributes.remove", "remove_attribute"),
+    ("product.attributes.search", "search_products_by_attributes"),
+    ("product.list", "list_products"),
+    ("product.list_by_category", "get_products_by_category"),
+    ("product.stats", "get_product_stats"),
+    ("product.stats.categories", "get_category_rollups"),
+    ("product.stock.update", "update_product_stock"),
+    ("product.stock.update_bulk", "update_stock_bulk"),
+    ("product.stock.transfer", "transfer_stock"),
+    ("location.create", "create_location"),
+    ("location.list", "list_locations"),
+    ("product.stock.reconcile", "reconcile_stock"),
+    ("product.stock.forecast", "forecast_stock"),
+    ("product.export", "export_products"),
+    ("product.import_csv", "import_products_csv"),
+    ("product.jobs.start", "start_job"),
+    ("product.jobs.status", "get_job_status"),
+    ("product.jobs.subscribe", "subscribe_job_events"),
+    ("product.jobs.unsubscribe", "unsubscribe_job_events"),
+    ("product.price.schedule", "schedule_price_change"),
+    ("product.price.history", "get_price_history"),
+    ("product.feed.generate", "generate_feed"),
+    ("coupon.create", "create_coupon"),
+    ("coupon.validate", "validate_coupon"),
+    ("coupon.redeem", "redeem_coupon"),
+    ("product.orders.record", "record_order"),
+    ("product.orders.ship", "ship_order"),
+    ("product.orders.list", "list_orders_by_user"),
+    ("product.orders.count", "count_orders_by_user"),
+    ("product.recommendations.get", "get_recommended_products"),
+    ("product.returns.create", "create_return"),
+    ("product.returns.approve", "approve_return"),
+    ("product.returns.complete", "complete_return"),
+    ("product.returns.get", "get_return"),
+];
+
+/// Resolves a namespaced method name to the flat name it aliases. Flat and
+/// unknown names are returned unchanged, so callers can use this to key
+/// per-method configuration regardless of which name a client used.
+pub fn flat_method_name(method: &str) -> &str {
+    COMMON_METHODS
+        .iter()
+        .chain(USER_METHODS)
+        .chain(PRODUCT_METHODS)
+        .find(|(namespaced, _)| *namespaced == method)
+        .map(|(_, flat)| *flat)
+        .unwrap_or(method)
+}
+
+/// Registers the common namespaced names plus `service_methods` as aliases
+/// of the flat methods already on `module`. Fails if a flat method is
+/// missing, so these tables cannot drift from the `#[rpc]` traits.
+pub fn register_namespaced_methods<Context: Send + Sync + 'static>(
+    module: &mut RpcModule<Context>,
+    service_methods: &[(&'static str, &'static str)],
+) -> Result<(), RegisterMethodError> {
+    for (namespaced, flat) in COMMON_METHODS.iter().chain(service_methods) {
+        module.register_alias(namespaced, flat)?;
+    }
+    Ok(())
+}
+
+/// Registers `rpc.methods`, which returns the sorted names of every method
+/// on `module` (flat names and aliases). The gateway's routing contract test
+/// compares its method map against this list.
+pub fn register_method_list<Context: Send + Sync + 'static>(
+    module: &mut RpcModule<Context>,
+) -> Result<(), RegisterMethodError> {
+    let mut names: Vec<String> = module
+        .method_names()
+        .chain(std::iter::once(METHOD_LIST_METHOD))
+        .map(str::to_string)
+        .collect();
+    names.sort();
+    module.register_method(METHOD_LIST_METHOD, move |_, _, _| names.clone())?;
+    Ok(())
+}
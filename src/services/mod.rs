@@ -1,2 +1,27 @@
 pub mod product_service;
 pub mod user_service;
+pub mod startup;
+pub mod read_only;
+pub mod product_import;
+pub mod address_validation;
+pub mod client_events;
+pub mod method_namespaces;
+pub mod price_scheduler;
+pub mod coupon_pricing;
+pub mod avatar_storage;
+pub mod product_feed;
+pub mod signup_rules;
+pub mod localization;
+pub mod feature_flags;
+pub mod change_feed;
+pub mod retention;
+pub mod stock_reconciliation;
+pub mod user_activity;
+pub mod login_throttle;
+pub mod product_similarity;
+pub mod storage_caps;
+pub mod recommendations;
+pub mod stock_forecast;
+pub mod database_isolation;
+pub mod jobs;
+pub mod delete_guards;
@@ -0,0 +1,38 @@
+use crate::services::product_service::ProductService;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Reads `PRICE_SCHEDULER_INTERVAL_SECS` (default 30, `0` disables the
+/// scheduler)
+pub fn scheduler_interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("PRICE_SCHEDULER_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Applies due scheduled price changes every `interval`. Ticks are skipped
+/// while the service is still starting.
+pub fn spawn_price_scheduler(service: Arc<RwLock<Option<ProductService>>>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            let guard = service.read().await;
+            let Some(service) = guard.as_ref() else {
+                continue;
+            };
+
+            match service.apply_due_price_changes().await {
+                Ok(0) => {}
+                Ok(applied) => info!("💲 Applied {} scheduled price changes", applied),
+                Err(err) => warn!("Failed to apply scheduled price changes: {}", err),
+            }
+        }
+    });
+}
@@ -0,0 +1,297 @@
+use crate::models::product_model::{FeedFormat, Product, ProductFeed};
+use crate::services::product_service::ProductService;
+use chrono::{SecondsFormat, Utc};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Google Merchant attributes and the product fields they are filled from
+const DEFAULT_FIELDS: &str = "id=id,title=name,description=description,link=link,\
+                              price=price,availability=availability,product_type=category";
+
+#[derive(Error, Debug)]
+pub enum FeedConfigError {
+    #[error("Invalid feed field mapping '{0}', expected attribute=source with a name made of letters, digits and _")]
+    InvalidMapping(String),
+
+    #[error("Unknown feed source '{0}': use a product field or const:<value>")]
+    UnknownSource(String),
+}
+
+/// Where a feed attribute's value comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedSource {
+    Id,
+    Name,
+    Description,
+    /// Price with the feed currency, e.g. `19.99 USD`
+    Price,
+    Category,
+    StockQuantity,
+    /// `piece`, `kg` or `liter`
+    Unit,
+    /// `in_stock` or `out_of_stock`
+    Availability,
+    /// The product page, from the link template
+    Link,
+    CreatedAt,
+    UpdatedAt,
+    /// The same value for every product, e.g. a brand
+    Const(String),
+}
+
+impl FeedSource {
+    fn parse(source: &str) -> Result<Self, FeedConfigError> {
+        if let Some(value) = source.strip_prefix("const:") {
+            return Ok(FeedSource::Const(value.to_string()));
+        }
+        match source {
+            "id" => Ok(FeedSource::Id),
+            "name" => Ok(FeedSource::Name),
+            "description" => Ok(FeedSource::Description),
+            "price" => Ok(FeedSource::Price),
+            "category" => Ok(FeedSource::Category),
+            "stock_quantity" => Ok(FeedSource::StockQuantity),
+            "unit" => Ok(FeedSource::Unit),
+            "availability" => Ok(FeedSource::Availability),
+            "link" => Ok(FeedSource::Link),
+            "created_at" => Ok(FeedSource::CreatedAt),
+            "updated_at" => Ok(FeedSource::UpdatedAt),
+            other => Err(FeedConfigError::UnknownSource(other.to_string())),
+        }
+    }
+}
+
+/// How the catalog is turned into a feed
+#[derive(Debug, Clone)]
+pub struct ProductFeedConfig {
+    pub title: String,
+    /// The shop's home page, used as the channel link
+    pub site_url: String,
+    /// Product page URL with `{id}` standing for the product's key
+    pub link_template: String,
+    pub currency: String,
+    /// Feed attribute -> source, in output order
+    pub fields: Vec<(String, FeedSource)>,
+}
+
+impl ProductFeedConfig {
+    /// Reads `PRODUCT_FEED_TITLE`, `PRODUCT_FEED_SITE_URL` (default
+    /// `https://example.com`), `PRODUCT_FEED_LINK_TEMPLATE` (default
+    /// `<site url>/products/{id}`), `PRODUCT_FEED_CURRENCY` (default `USD`)
+    /// and `PRODUCT_FEED_FIELDS` (`attribute=source,...`, default the
+    /// Google Merchant required attributes).
+    pub fn from_env() -> Result<Self, FeedConfigError> {
+        let site_url = std::env::var("PRODUCT_FEED_SITE_URL")
+            .unwrap_or_else(|_| "https://example.com".to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let fields = std::env::var("PRODUCT_FEED_FIELDS")
+            .unwrap_or_else(|_| DEFAULT_FIELDS.to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (attribute, source) = entry
+                    .split_once('=')
+                    .filter(|(attribute, _)| is_attribute_name(attribute.trim()))
+                    .ok_or_else(|| FeedConfigError::InvalidMapping(entry.to_string()))?;
+                Ok((
+                    attribute.trim().to_string(),
+                    FeedSource::parse(source.trim())?,
+                ))
+            })
+            .collect::<Result<Vec<_>, FeedConfigError>>()?;
+
+        Ok(Self {
+            title: std::env::var("PRODUCT_FEED_TITLE")
+                .unwrap_or_else(|_| "Product catalog".to_string()),
+            link_template: std::env::var("PRODUCT_FEED_LINK_TEMPLATE")
+                .unwrap_or_else(|_| format!("{}/products/{{id}}", site_url)),
+            site_url,
+            currency: std::env::var("PRODUCT_FEED_CURRENCY").unwrap_or_else(|_| "USD".to_string()),
+            fields,
+        })
+    }
+
+    fn value(&self, product: &Product, source: &FeedSource) -> String {
+        match source {
+            FeedSource::Id => product.id.id.to_raw(),
+            FeedSource::Name => product.name.clone(),
+            FeedSource::Description => product.description.clone(),
+            FeedSource::Price => format!("{:.2} {}", product.price, self.currency),
+            FeedSource::Category => product.category.clone(),
+            FeedSource::StockQuantity => product.stock_quantity.to_string(),
+            FeedSource::Unit => product.unit.to_string(),
+            FeedSource::Availability if product.stock_quantity.is_positive() => {
+                "in_stock".to_string()
+            }
+            FeedSource::Availability => "out_of_stock".to_string(),
+            FeedSource::Link => self.link_template.replace("{id}", &product.id.id.to_raw()),
+            FeedSource::CreatedAt => product
+                .created_at
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            FeedSource::UpdatedAt => product
+                .updated_at
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            FeedSource::Const(value) => value.clone(),
+        }
+    }
+}
+
+/// Renders `products` as a feed in `format`
+pub fn render_feed(
+    products: &[Product],
+    config: &ProductFeedConfig,
+    format: FeedFormat,
+) -> ProductFeed {
+    let content = match format {
+        FeedFormat::Xml => render_xml(products, config),
+        FeedFormat::Csv => render_csv(products, config),
+    };
+    ProductFeed {
+        format,
+        content_type: format.content_type().to_string(),
+        generated_at: Utc::now(),
+        product_count: products.len(),
+        content,
+    }
+}
+
+/// RSS 2.0 with every attribute in the `g:` namespace, as Google Merchant
+/// Center expects
+fn render_xml(products: &[Product], config: &ProductFeedConfig) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <rss version=\"2.0\" xmlns:g=\"http://base.google.com/ns/1.0\">\n<channel>\n",
+    );
+    xml.push_str(&format!("<title>{}</title>\n", escape_xml(&config.title)));
+    xml.push_str(&format!("<link>{}</link>\n", escape_xml(&config.site_url)));
+    xml.push_str(&format!(
+        "<description>{}</description>\n",
+        escape_xml(&config.title)
+    ));
+    for product in products {
+        xml.push_str("<item>\n");
+        for (attribute, source) in &config.fields {
+            let name = attribute.strip_prefix("g:").unwrap_or(attribute);
+            xml.push_str(&format!(
+                "  <g:{name}>{}</g:{name}>\n",
+                escape_xml(&config.value(product, source))
+            ));
+        }
+        xml.push_str("</item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// One header row of attribute names, then one row per product
+fn render_csv(products: &[Product], config: &ProductFeedConfig) -> String {
+    let header: Vec<String> = config
+        .fields
+        .iter()
+        .map(|(attribute, _)| escape_csv(attribute.strip_prefix("g:").unwrap_or(attribute)))
+        .collect();
+    let mut csv = header.join(",") + "\n";
+    for product in products {
+        let row: Vec<String> = config
+            .fields
+            .iter()
+            .map(|(_, source)| escape_csv(&config.value(product, source)))
+            .collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Attribute names become XML element names and CSV headers
+fn is_attribute_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Reads `PRODUCT_FEED_INTERVAL_SECS` (default 3600, `0` disables scheduled
+/// regeneration)
+pub fn feed_interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("PRODUCT_FEED_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3600);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Reads `PRODUCT_FEED_OUTPUT_DIR`, where scheduled runs also write
+/// `product_feed.xml` and `product_feed.csv` for integrations that fetch a
+/// static file
+pub fn feed_output_dir_from_env() -> Option<PathBuf> {
+    std::env::var("PRODUCT_FEED_OUTPUT_DIR")
+        .ok()
+        .map(PathBuf::from)
+}
+
+/// Regenerates every feed format every `interval`, starting right away.
+/// Ticks are skipped while the service is still starting.
+pub fn spawn_feed_scheduler(
+    service: Arc<RwLock<Option<ProductService>>>,
+    interval: Duration,
+    output_dir: Option<PathBuf>,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            let guard = service.read().await;
+            let Some(service) = guard.as_ref() else {
+                continue;
+            };
+
+            let feeds = match service.regenerate_feeds().await {
+                Ok(feeds) => feeds,
+                Err(err) => {
+                    warn!("Failed to regenerate product feeds: {}", err);
+                    continue;
+                }
+            };
+            info!(
+                "📰 Regenerated product feeds: {} products",
+                feeds.first().map_or(0, |feed| feed.product_count)
+            );
+
+            let Some(dir) = &output_dir else {
+                continue;
+            };
+            for feed in &feeds {
+                let path = dir.join(format!("product_feed.{}", feed.format.extension()));
+                if let Err(err) = tokio::fs::write(&path, &feed.content).await {
+                    warn!("Failed to write product feed {}: {}", path.display(), err);
+                }
+            }
+        }
+    });
+}
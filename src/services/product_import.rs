@@ -0,0 +1,161 @@
+use crate::models::product_model::CreateProductRequest;
+use crate::models::quantity_model::{QuantityError, StockUnit};
+
+/// One CSV record and the line it starts on (1-based, counting the header)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvRecord {
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// Parses RFC 4180 CSV: comma separated, `"` quoting with `""` escapes,
+/// quoted fields may span lines, LF or CRLF line endings. Blank lines are
+/// skipped.
+pub fn parse_csv(input: &str) -> Result<Vec<CsvRecord>, String> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut was_quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() && !was_quoted => {
+                in_quotes = true;
+                was_quoted = true;
+            }
+            ',' => {
+                fields.push(std::mem::take(&mut field));
+                was_quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                push_record(&mut records, record_line, std::mem::take(&mut fields));
+                was_quoted = false;
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!(
+            "Unterminated quoted field in record starting on line {}",
+            record_line
+        ));
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        push_record(&mut records, record_line, fields);
+    }
+
+    Ok(records)
+}
+
+fn push_record(records: &mut Vec<CsvRecord>, line: usize, fields: Vec<String>) {
+    let blank = fields.len() == 1 && fields[0].trim().is_empty();
+    if !blank {
+        records.push(CsvRecord { line, fields });
+    }
+}
+
+/// Positions of the product columns in the header row
+#[derive(Debug, Clone)]
+pub struct ProductCsvColumns {
+    name: usize,
+    description: usize,
+    price: usize,
+    category: usize,
+    stock_quantity: usize,
+    /// Optional; rows without it count pieces
+    unit: Option<usize>,
+    /// Optional; rows without it have no SKU
+    sku: Option<usize>,
+}
+
+impl ProductCsvColumns {
+    /// Locates the required columns by name (case-insensitive, any order).
+    /// Extra columns are ignored.
+    pub fn from_header(header: &[String]) -> Result<Self, String> {
+        let find = |column: &str| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(column))
+                .ok_or_else(|| format!("CSV header is missing the '{}' column", column))
+        };
+
+        Ok(Self {
+            name: find("name")?,
+            description: find("description")?,
+            price: find("price")?,
+            category: find("category")?,
+            stock_quantity: find("stock_quantity")?,
+            unit: find("unit").ok(),
+            sku: find("sku").ok(),
+        })
+    }
+
+    pub fn to_request(&self, record: &CsvRecord) -> Result<CreateProductRequest, String> {
+        let field = |index: usize, column: &str| {
+            record
+                .fields
+                .get(index)
+                .map(|value| value.trim().to_string())
+                .ok_or_else(|| format!("Missing value for '{}'", column))
+        };
+
+        let price = field(self.price, "price")?;
+        let stock_quantity = field(self.stock_quantity, "stock_quantity")?;
+        let unit = match self.unit.map(|index| field(index, "unit")).transpose()? {
+            Some(unit) if !unit.is_empty() => {
+                unit.parse().map_err(|err: QuantityError| err.to_string())?
+            }
+            _ => StockUnit::default(),
+        };
+
+        Ok(CreateProductRequest {
+            name: field(self.name, "name")?,
+            description: field(self.description, "description")?,
+            price: price
+                .parse::<f64>()
+                .ok()
+                .filter(|p| p.is_finite())
+                .ok_or_else(|| format!("Invalid price: '{}'", price))?,
+            category: field(self.category, "category")?,
+            sku: self
+                .sku
+                .map(|index| field(index, "sku"))
+                .transpose()?
+                .filter(|sku| !sku.is_empty()),
+            max_similarity: None,
+            dry_run: false,
+            visible_to: Vec::new(),
+            stock_quantity: unit
+                .parse_quantity(&stock_quantity)
+                .map_err(|err| format!("Invalid stock_quantity: {}", err))?,
+            unit,
+        })
+    }
+}
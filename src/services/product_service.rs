@@ -1,87 +1,1796 @@
 use crate::{
+    config::database::DatabaseConfig,
+    crypto::page_cursor::{filter_hash, CursorRejection, CursorSigner},
     errors::product_error::ProductServiceError,
-    models::product_model::{CreateProductRequest, CreateProductResponse, GetProductRequest, GetProductsByCategoryRequest, ListProductsResponse, Product, UpdateProductStockRequest},
-    repositories::product_repository::ProductRepository,
+    models::admin_model::DatabaseIsolationReport,
+    models::attribute_model::{AttributeDefinition, AttributeDefinitionForCreation, AttributeValue, DefineAttributeRequest, ListAttributesResponse, ProductAttribute, RemoveAttributeRequest, SearchProductsByAttributesRequest, SearchProductsByAttributesResponse, SetAttributeRequest},
+    models::coupon_model::{CouponCheckout, CouponForCreation, CreateCouponRequest, CreateCouponResponse, DiscountType, RedeemCouponRequest, RedeemCouponResponse, ValidateCouponResponse},
+    models::job_model::{ExportJobParams, Job, JobEvent, JobForCreation, JobIdRequest, JobKind, JobProgress, JobStatus, ReindexReport, StartJobRequest},
+    models::inventory_model::{BulkStockResult, BulkStockStatus, CreateLocationRequest, CreateLocationResponse, ForecastStockRequest, ListLocationsResponse, LocationForCreation, LocationStock, OrderActivity, ProductDetails, ReconcileStockRequest, StockForecast, StockLevel, StockReconciliationReport, TransferStockRequest, TransferStockResponse, UpdateStockBulkRequest, UpdateStockBulkResponse, DEFAULT_LOCATION},
+    models::quantity_model::{Quantity, StockUnit},
+    models::return_model::{CreateReturnRequest, ProductReturn, ReturnForCreation, ReturnIdRequest, ReturnItem, ReturnStatus},
+    models::recommendation_model::{CountOrdersByUserRequest, GetRecommendedProductsRequest, ListOrdersByUserRequest, ListOrdersResponse, OrderForRecording, OrderLine, OrderPageCursor, OrderStatus, RecommendedProduct, RecommendedProductsResponse, RecordOrderRequest, RecordOrderResponse, RecordedOrder, ShipOrderRequest, UserOrderCount},
+    models::product_model::{CategoryRollupsResponse, CreateProductRequest, CreateProductResponse, ExportProductsRequest, ExportProductsResponse, FeedFormat, FindSimilarProductsRequest, FindSimilarProductsResponse, GenerateFeedRequest, GetPriceHistoryRequest, GetProductRequest, GetProductsByCategoryRequest, ImportProductsCsvRequest, ImportProductsCsvResponse, ImportRowReport, ListProductsRequest, ListProductsResponse, PriceChangeForCreation, ProductPageCursor, PriceHistoryResponse, Product, ProductFeed, ProductSortField, ProductStats, ProductTranslation, SchedulePriceChangeRequest, SchedulePriceChangeResponse, ScheduledPriceChangeForCreation, SetProductSkuRequest, SetProductVisibilityRequest, SetTranslationRequest, SortDirection, UnitStock, UpdateProductStockRequest},
+    repositories::{attribute_repository::AttributeRepository, connection::{DatabaseHealth, DbConnection}, coupon_repository::CouponRepository, inventory_repository::{stock_thing, InventoryRepository, StockSet}, job_repository::JobRepository, order_history_repository::OrderHistoryRepository, product_repository::ProductRepository, return_repository::ReturnRepository, rollup_repository::CategoryRollupRepository},
+    services::{
+        address_validation::validate_address,
+        change_feed::{ChangeFeed, ChangeWatcher},
+        coupon_pricing::{normalize_code, quote, round_to_cents},
+        database_isolation::{self, PRODUCT_SERVICE},
+        feature_flags::FeatureFlags,
+        jobs::JobBoard,
+        localization::{default_locale_from_env, localize_product, normalize_locale},
+        product_feed::{render_feed, ProductFeedConfig},
+        product_import::{parse_csv, ProductCsvColumns},
+        product_similarity::{find_similar, DEFAULT_SIMILARITY_THRESHOLD},
+        read_only::ReadOnlyMode,
+        recommendations::{count_co_occurrences, CoOccurrenceConfig, MAX_RELATED_PER_PRODUCT},
+        storage_caps::StorageMonitor,
+        retention::RetentionTarget,
+        stock_forecast::{forecast_stock, FORECAST_LOOKBACK_DAYS, MAX_FORECAST_HORIZON_DAYS},
+        stock_reconciliation::find_discrepancies,
+    },
 };
-use tracing::info;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, PoisonError, RwLock};
+use tracing::{info, warn};
+
+const MAX_EXPORT_PAGE_SIZE: usize = 1000;
+
+const DEFAULT_LIST_PAGE_SIZE: usize = 100;
+const MAX_LIST_PAGE_SIZE: usize = 1000;
+const DEFAULT_IMPORT_BATCH_SIZE: usize = 100;
+const MAX_IMPORT_BATCH_SIZE: usize = 1000;
+const MAX_IMPORT_ROWS: usize = 10_000;
+const DEFAULT_PRICE_HISTORY_LIMIT: usize = 100;
+const MAX_PRICE_HISTORY_LIMIT: usize = 1000;
+const DEFAULT_SIMILAR_PRODUCTS_LIMIT: usize = 10;
+const MAX_SIMILAR_PRODUCTS_LIMIT: usize = 100;
+const DEFAULT_RECOMMENDATIONS_LIMIT: usize = 10;
+/// Every pair in an order is counted, so huge orders are refused
+const MAX_ORDER_PRODUCTS: usize = 100;
+/// Warehouse syncs send their whole stock at once
+const MAX_BULK_STOCK_ITEMS: usize = 10_000;
+/// Array limits of the bulk methods, above `SERVICE_MAX_ARRAY_LEN`
+pub const BULK_ARRAY_LIMITS: &[(&str, usize)] = &[("update_stock_bulk", MAX_BULK_STOCK_ITEMS)];
+const MAX_STOCK_REASON_LEN: usize = 200;
+const MAX_SKU_LEN: usize = 64;
+const MAX_ATTRIBUTE_KEY_LEN: usize = 64;
+const MAX_ATTRIBUTE_TEXT_LEN: usize = 1000;
+const MAX_PRODUCT_ATTRIBUTES: usize = 100;
 
 pub struct ProductService {
     repository: ProductRepository,
+    coupons: CouponRepository,
+    inventory: InventoryRepository,
+    orders: OrderHistoryRepository,
+    returns: ReturnRepository,
+    attributes: AttributeRepository,
+    /// Per-category totals the database keeps up to date
+    rollups: CategoryRollupRepository,
+    jobs: JobRepository,
+    /// Wakes job workers and carries job events to subscribers
+    job_board: Arc<JobBoard>,
+    read_only: Arc<ReadOnlyMode>,
+    /// Hard caps that stop new records once reached
+    storage: Arc<StorageMonitor>,
+    feed_config: Arc<ProductFeedConfig>,
+    /// Signs `list_products` and `list_orders_by_user` cursors
+    cursors: Arc<CursorSigner>,
+    /// Last generated feed per format
+    feeds: RwLock<HashMap<FeedFormat, ProductFeed>>,
+    /// Locale of each product's own name and description
+    default_locale: String,
+    feature_flags: FeatureFlags,
+    /// Live changes to the product and flag tables, when enabled
+    change_feed: Option<Arc<ChangeFeed>>,
+    /// Product changes, which make the cached feeds stale
+    product_changes: Option<ChangeWatcher>,
+    /// Latest stock reconciliation
+    reconciliation: RwLock<Option<StockReconciliationReport>>,
+    co_occurrence: CoOccurrenceConfig,
+    /// When the co-occurrence table was last rebuilt
+    recommendations_built_at: RwLock<Option<DateTime<Utc>>>,
 }
 
 impl ProductService {
-    pub async fn new() -> Result<Self, ProductServiceError> {
-        let repository = ProductRepository::new().await?;
-        info!("ProductService initialized");
-        Ok(Self { repository })
+    pub async fn new(read_only: Arc<ReadOnlyMode>, storage: Arc<StorageMonitor>, feed_config: Arc<ProductFeedConfig>, cursors: Arc<CursorSigner>, db_config: &DatabaseConfig) -> Result<Self, ProductServiceError> {
+        let repository = ProductRepository::new(db_config).await?;
+        let coupons = CouponRepository::new(repository.connection()).await?;
+        let inventory = InventoryRepository::new(repository.connection()).await?;
+        let orders = OrderHistoryRepository::new(repository.connection()).await?;
+        let returns = ReturnRepository::new(repository.connection()).await?;
+        let attributes = AttributeRepository::new(repository.connection()).await?;
+        let rollups = CategoryRollupRepository::new(repository.connection()).await?;
+        let jobs = JobRepository::new(repository.connection()).await?;
+        let interrupted = jobs.fail_interrupted().await?;
+        if !interrupted.is_empty() {
+            warn!("Marked {} jobs interrupted by a restart as failed", interrupted.len());
+        }
+        let change_feed = ChangeFeed::from_env(&repository.connection(), &["product", "feature_flag"]);
+        let feature_flags = FeatureFlags::new(repository.connection()).with_change_watcher(change_feed.as_ref().map(|feed| feed.watch("feature_flag")));
+        let product_changes = change_feed.as_ref().map(|feed| feed.watch("product"));
+        let default_locale = default_locale_from_env();
+        info!("ProductService initialized (default locale {})", default_locale);
+        Ok(Self { repository, coupons, inventory, orders, returns, attributes, rollups, jobs, job_board: Arc::new(JobBoard::default()), read_only, storage, feed_config, cursors, feeds: RwLock::new(HashMap::new()), default_locale, feature_flags, change_feed, product_changes, reconciliation: RwLock::new(None), co_occurrence: CoOccurrenceConfig::from_env(), recommendations_built_at: RwLock::new(None) })
+    }
+
+    /// Status of the product database connection
+    pub fn database_health(&self) -> DatabaseHealth {
+        self.repository.connection().health()
+    }
+
+    /// The namespace, database and tables the product service actually
+    /// uses, checked against its config
+    pub async fn database_isolation(&self) -> anyhow::Result<DatabaseIsolationReport> {
+        database_isolation::inspect(PRODUCT_SERVICE, &self.repository.connection()).await
+    }
+
+    /// The product service's feature flags
+    pub fn feature_flags(&self) -> &FeatureFlags {
+        &self.feature_flags
+    }
+
+    /// Live changes to the service's tables, when `LIVE_QUERIES` is on
+    pub fn change_feed(&self) -> Option<&Arc<ChangeFeed>> {
+        self.change_feed.as_ref()
+    }
+
+    /// Job events and the workers' wake-up signal
+    pub fn job_board(&self) -> &Arc<JobBoard> {
+        &self.job_board
+    }
+
+    fn ensure_writable(&self) -> Result<(), ProductServiceError> {
+        if self.read_only.is_enabled() {
+            return Err(ProductServiceError::ServiceReadOnly);
+        }
+        Ok(())
     }
 
     pub async fn create_product(
         &self,
         request: CreateProductRequest,
     ) -> Result<CreateProductResponse, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("product")?;
+
         // Validate input
         self.validate_create_product_request(&request)?;
+        if let Some(max_similarity) = request.max_similarity {
+            let candidates = self.repository.product_names().await?;
+            if let Some(similar) = find_similar(&request.name, &candidates, max_similarity, 1).into_iter().next() {
+                return Err(ProductServiceError::SimilarProductExists {
+                    name: request.name,
+                    similar_to: similar.name,
+                    id: similar.id,
+                    similarity: similar.similarity,
+                });
+            }
+        }
+
+        if request.dry_run {
+            self.repository.ensure_name_available(&request.name).await?;
+            if let Some(sku) = request.sku.as_deref().map(normalize_sku).transpose()? {
+                if !self.repository.get_products_by_sku(std::slice::from_ref(&sku)).await?.is_empty() {
+                    return Err(ProductServiceError::SkuAlreadyExists { sku });
+                }
+            }
+            return Ok(CreateProductResponse {
+                id: String::new(),
+                message: "Dry run: the product would be created".to_string(),
+                dry_run: true,
+            });
+        }
 
-        let product = Product::new(
+        let mut product = Product::new(
             request.name,
             request.description,
             request.price,
             request.category,
             request.stock_quantity,
+            request.unit,
         );
+        product.visible_to = normalize_orgs(request.visible_to)?;
+        product.sku = request.sku.as_deref().map(normalize_sku).transpose()?;
         let created_product = self.repository.create_product(product).await?;
+        self.record_initial_prices(std::slice::from_ref(&created_product), "created").await;
 
         Ok(CreateProductResponse {
             id: created_product.id.to_string(),
             message: format!("Product created successfully with id: {}", created_product.id),
+            dry_run: false,
+        })
+    }
+
+    /// Existing products whose names look like `name`, for warning about
+    /// near-duplicates before creating one. Compares against every name in
+    /// the catalog that `org` can see.
+    pub async fn find_similar_products(&self, request: FindSimilarProductsRequest, org: Option<&str>) -> Result<FindSimilarProductsResponse, ProductServiceError> {
+        if request.name.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product name cannot be empty".to_string(),
+            });
+        }
+        let threshold = request.threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD);
+        validate_similarity(threshold)?;
+        let limit = request.limit.unwrap_or(DEFAULT_SIMILAR_PRODUCTS_LIMIT).clamp(1, MAX_SIMILAR_PRODUCTS_LIMIT);
+
+        let candidates = self.repository.visible_product_names(org).await?;
+        Ok(FindSimilarProductsResponse {
+            products: find_similar(&request.name, &candidates, threshold, limit),
         })
     }
 
-    pub async fn get_product(&self, request: GetProductRequest) -> Result<Product, ProductServiceError> {
+    /// Products restricted to other organizations than `org` are reported
+    /// as not found, so their ids reveal nothing
+    pub async fn get_product(&self, request: GetProductRequest, org: Option<&str>) -> Result<ProductDetails, ProductServiceError> {
         if request.id.trim().is_empty() {
             return Err(ProductServiceError::Validation {
                 message: "Product ID cannot be empty".to_string(),
             });
         }
 
-        self.repository.get_product(&request.id).await
+        let product = self.repository.get_product(&request.id).await?;
+        if !product.is_visible_to(org) {
+            return Err(ProductServiceError::ProductNotFound { id: request.id });
+        }
+        let (product, locale) = match request.locale.as_deref() {
+            Some(tag) => {
+                let locale = normalize_locale(tag).ok_or_else(|| ProductServiceError::Validation {
+                    message: format!("Invalid locale '{}'", tag),
+                })?;
+                localize_product(product, &locale, &self.default_locale)
+            }
+            None => (product, self.default_locale.clone()),
+        };
+        let levels = self.inventory.stock_levels(&request.id).await?;
+        let availability = if levels.is_empty() {
+            // Not tracked per location yet: all stock is at the default location
+            vec![LocationStock { location: DEFAULT_LOCATION.to_string(), quantity: product.stock_quantity }]
+        } else {
+            levels
+                .into_iter()
+                .map(|level| LocationStock { location: level.location, quantity: level.quantity })
+                .collect()
+        };
+
+        Ok(ProductDetails { product, availability, locale })
+    }
+
+    /// Sets a product's name and description in one locale. A request
+    /// with neither removes the locale's translation.
+    pub async fn set_translation(&self, request: SetTranslationRequest) -> Result<Product, ProductServiceError> {
+        self.ensure_writable()?;
+
+        let locale = normalize_locale(&request.locale).ok_or_else(|| ProductServiceError::Validation {
+            message: format!("Invalid locale '{}'", request.locale),
+        })?;
+        if locale == self.default_locale {
+            return Err(ProductServiceError::Validation {
+                message: format!("{} is the default locale: update the product's own name and description instead", locale),
+            });
+        }
+        let name = request.name.map(|name| name.trim().to_string());
+        if name.as_deref() == Some("") {
+            return Err(ProductServiceError::Validation {
+                message: "Translated name cannot be empty".to_string(),
+            });
+        }
+
+        let mut translations = self.repository.get_product(&request.product_id).await?.translations;
+        if name.is_none() && request.description.is_none() {
+            translations.remove(&locale);
+        } else {
+            translations.insert(locale, ProductTranslation { name, description: request.description });
+        }
+        self.repository.set_translations(&request.product_id, &translations).await
+    }
+
+    /// Registers an attribute key products can then take values for. Keys
+    /// cannot be redefined, so stored values always match their type.
+    pub async fn define_attribute(&self, request: DefineAttributeRequest) -> Result<AttributeDefinition, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("attribute_definition")?;
+
+        let key = request.key.trim().to_string();
+        if key.is_empty()
+            || key.len() > MAX_ATTRIBUTE_KEY_LEN
+            || !key.starts_with(|c: char| c.is_ascii_lowercase())
+            || !key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(ProductServiceError::Validation {
+                message: format!("Attribute key '{}' must be up to {} lowercase letters, digits and underscores, starting with a letter", key, MAX_ATTRIBUTE_KEY_LEN),
+            });
+        }
+        if self.attributes.definition(&key).await?.is_some() {
+            return Err(ProductServiceError::AttributeAlreadyDefined { key });
+        }
+
+        let description = request.description.map(|description| description.trim().to_string()).filter(|description| !description.is_empty());
+        let definition = self
+            .attributes
+            .define(AttributeDefinitionForCreation { key, value_type: request.value_type, indexed: request.indexed, description, created_at: Utc::now() })
+            .await?;
+
+        info!("Attribute {} defined ({}, indexed: {})", definition.key, definition.value_type, definition.indexed);
+        Ok(definition)
     }
 
-    pub async fn list_products(&self) -> Result<ListProductsResponse, ProductServiceError> {
-        let products = self.repository.list_products().await?;
+    pub async fn list_attributes(&self) -> Result<ListAttributesResponse, ProductServiceError> {
+        Ok(ListAttributesResponse { attributes: self.attributes.definitions().await? })
+    }
+
+    /// Sets one registered attribute on a product, replacing its value
+    pub async fn set_attribute(&self, request: SetAttributeRequest) -> Result<Product, ProductServiceError> {
+        self.ensure_writable()?;
+
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+        let key = request.key.trim().to_string();
+        let definition = self.attributes.definition(&key).await?.ok_or_else(|| ProductServiceError::AttributeNotDefined { key: key.clone() })?;
+        validate_attribute_value(&definition, &request.value)?;
+
+        let mut attributes = self.repository.get_product(&request.product_id).await?.attributes;
+        attributes.insert(key, request.value);
+        if attributes.len() > MAX_PRODUCT_ATTRIBUTES {
+            return Err(ProductServiceError::PayloadTooLarge { what: "Product attributes".to_string(), len: attributes.len(), max: MAX_PRODUCT_ATTRIBUTES });
+        }
+        self.save_attributes(&request.product_id, attributes).await
+    }
+
+    /// Removes an attribute from a product; removing one it does not have
+    /// changes nothing
+    pub async fn remove_attribute(&self, request: RemoveAttributeRequest) -> Result<Product, ProductServiceError> {
+        self.ensure_writable()?;
+
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+        let product = self.repository.get_product(&request.product_id).await?;
+        let mut attributes = product.attributes.clone();
+        if attributes.remove(request.key.trim()).is_none() {
+            return Ok(product);
+        }
+        self.save_attributes(&request.product_id, attributes).await
+    }
+
+    /// Writes a product's attributes, mirroring the indexed ones into the
+    /// lookup table searches use
+    async fn save_attributes(&self, product_id: &str, attributes: BTreeMap<String, AttributeValue>) -> Result<Product, ProductServiceError> {
+        let indexed = self.indexed_attribute_keys().await?;
+        let rows = attributes
+            .iter()
+            .filter(|(key, _)| indexed.contains(*key))
+            .map(|(key, value)| ProductAttribute { product_id: product_id.to_string(), key: key.clone(), value: value.clone() })
+            .collect();
+        self.attributes.set_attributes(product_id, &attributes, rows).await
+    }
+
+    async fn indexed_attribute_keys(&self) -> Result<HashSet<String>, ProductServiceError> {
+        Ok(self.attributes.definitions().await?.into_iter().filter(|definition| definition.indexed).map(|definition| definition.key).collect())
+    }
+
+    /// Products `org` can see that have every given value, on indexed
+    /// attributes only
+    pub async fn search_products_by_attributes(&self, request: SearchProductsByAttributesRequest, org: Option<&str>) -> Result<SearchProductsByAttributesResponse, ProductServiceError> {
+        if request.attributes.is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Search needs at least one attribute".to_string(),
+            });
+        }
+        let limit = request.limit.unwrap_or(DEFAULT_LIST_PAGE_SIZE);
+        if limit == 0 || limit > MAX_LIST_PAGE_SIZE {
+            return Err(ProductServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_LIST_PAGE_SIZE),
+            });
+        }
+
+        let mut matching: Option<HashSet<String>> = None;
+        for (key, value) in &request.attributes {
+            let definition = self.attributes.definition(key).await?.ok_or_else(|| ProductServiceError::AttributeNotDefined { key: key.clone() })?;
+            if !definition.indexed {
+                return Err(ProductServiceError::Validation {
+                    message: format!("Attribute '{}' is not indexed and cannot be searched", key),
+                });
+            }
+            validate_attribute_value(&definition, value)?;
+
+            let ids: HashSet<String> = self.attributes.product_ids_with(key, value).await?.into_iter().collect();
+            let ids = match matching {
+                Some(matching) => matching.intersection(&ids).cloned().collect(),
+                None => ids,
+            };
+            if ids.is_empty() {
+                return Ok(SearchProductsByAttributesResponse { products: Vec::new(), total: 0 });
+            }
+            matching = Some(ids);
+        }
+
+        let ids: Vec<String> = matching.unwrap_or_default().into_iter().collect();
+        let mut products = self.repository.get_visible_products(&ids, org).await?;
+        let total = products.len();
+        products.truncate(limit);
+        Ok(SearchProductsByAttributesResponse { products, total })
+    }
+
+    /// Public products plus those restricted to `org`, in pages when a
+    /// limit or cursor is given
+    pub async fn list_products(&self, request: ListProductsRequest, org: Option<&str>) -> Result<ListProductsResponse, ProductServiceError> {
+        let sort_dir = request.direction();
+        let filter = filter_hash(&[org.unwrap_or_default()]);
+        let offset = match &request.cursor {
+            Some(token) => {
+                let cursor: ProductPageCursor = self.cursors.verify(token)?;
+                if cursor.sort_by != request.sort_by || cursor.sort_dir != sort_dir || cursor.filter != filter {
+                    return Err(CursorRejection::Mismatched.into());
+                }
+                Some(cursor.offset)
+            }
+            None => None,
+        };
+
+        let products = self.repository.list_products(request.sort_by, sort_dir, org).await?;
         let total = products.len();
+        let Some(limit) = request.limit.or(offset.map(|_| DEFAULT_LIST_PAGE_SIZE)) else {
+            return Ok(ListProductsResponse { products, total, next_cursor: None });
+        };
+        if limit == 0 || limit > MAX_LIST_PAGE_SIZE {
+            return Err(ProductServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_LIST_PAGE_SIZE),
+            });
+        }
+
+        // Ties are broken by id, so the same offset always lands on the same product
+        let offset = offset.unwrap_or_default();
+        let products: Vec<Product> = products.into_iter().skip(offset).take(limit).collect();
+        let next_offset = offset + products.len();
+        let next_cursor = (next_offset < total).then(|| {
+            self.cursors.sign(&ProductPageCursor { sort_by: request.sort_by, sort_dir, offset: next_offset, filter })
+        });
+        Ok(ListProductsResponse { products, total, next_cursor })
+    }
 
-        Ok(ListProductsResponse { products, total })
+    /// Dashboard aggregates computed by the database instead of the client
+    pub async fn get_product_stats(&self) -> Result<ProductStats, ProductServiceError> {
+        let mut stats = self.repository.product_stats().await?;
+        stats.inventory_value = round_to_cents(stats.inventory_value);
+        stats.average_price = round_to_cents(stats.average_price);
+        round_unit_values(&mut stats.stock_by_unit);
+        for category in &mut stats.categories {
+            category.inventory_value = round_to_cents(category.inventory_value);
+            category.average_price = round_to_cents(category.average_price);
+            round_unit_values(&mut category.stock_by_unit);
+        }
+        Ok(stats)
     }
 
-    pub async fn get_products_by_category(&self, request: GetProductsByCategoryRequest) -> Result<ListProductsResponse, ProductServiceError> {
+    /// Per-category totals read from the rollup table, without scanning
+    /// the catalog
+    pub async fn get_category_rollups(&self) -> Result<CategoryRollupsResponse, ProductServiceError> {
+        let mut categories = self.rollups.rollups().await?;
+        for category in &mut categories {
+            category.inventory_value = round_to_cents(category.inventory_value);
+            round_unit_values(&mut category.stock_by_unit);
+        }
+        Ok(CategoryRollupsResponse { categories })
+    }
+
+    pub async fn get_products_by_category(&self, request: GetProductsByCategoryRequest, org: Option<&str>) -> Result<ListProductsResponse, ProductServiceError> {
         if request.category.trim().is_empty() {
             return Err(ProductServiceError::Validation {
                 message: "Category cannot be empty".to_string(),
             });
         }
 
-        let products = self.repository.get_products_by_category(&request.category).await?;
+        let products = self.repository.get_products_by_category(&request.category, org).await?;
         let total = products.len();
 
-        Ok(ListProductsResponse { products, total })
+        Ok(ListProductsResponse { products, total, next_cursor: None })
+    }
+
+    /// Restricts a product to the listed organizations, or makes it public
+    /// again with an empty list. A product cannot be restricted while
+    /// orders of it are waiting to ship or have shipped, since its buyers
+    /// may lose sight of what they are waiting for or sending back.
+    pub async fn set_product_visibility(&self, request: SetProductVisibilityRequest) -> Result<Product, ProductServiceError> {
+        self.ensure_writable()?;
+
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+        let visible_to = normalize_orgs(request.visible_to)?;
+        if !visible_to.is_empty() {
+            let orders = self.orders.count_open_by_product(&request.product_id).await?;
+            if orders > 0 {
+                return Err(ProductServiceError::ProductHasOpenOrders { id: request.product_id, orders });
+            }
+        }
+        self.repository.set_visibility(&request.product_id, &visible_to).await
+    }
+
+    /// Sets the SKU warehouse feeds know the product by, or removes it
+    pub async fn set_product_sku(&self, request: SetProductSkuRequest) -> Result<Product, ProductServiceError> {
+        self.ensure_writable()?;
+
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+        let sku = request.sku.as_deref().map(normalize_sku).transpose()?;
+        self.repository.get_product(&request.product_id).await?;
+        self.repository.set_sku(&request.product_id, sku.as_deref()).await
     }
 
     pub async fn update_product_stock(&self, request: UpdateProductStockRequest) -> Result<Product, ProductServiceError> {
+        self.ensure_writable()?;
+
         if request.id.trim().is_empty() {
             return Err(ProductServiceError::Validation {
                 message: "Product ID cannot be empty".to_string(),
             });
         }
 
-        if request.quantity < 0 {
+        if request.quantity.is_negative() {
             return Err(ProductServiceError::Validation {
                 message: "Stock quantity cannot be negative".to_string(),
             });
         }
 
-        self.repository.update_product_stock(&request.id, request.quantity).await
+        let location = request.location.as_deref().map(normalize_location).unwrap_or_else(|| DEFAULT_LOCATION.to_string());
+        self.ensure_location(&location).await?;
+
+        let product = self.repository.get_product(&request.id).await?;
+        validate_quantity(product.unit, request.quantity)?;
+        self.ensure_stock_rows(&request.id, product.stock_quantity).await?;
+        self.inventory.set_stock(&request.id, &location, request.quantity).await?;
+
+        self.repository.get_product(&request.id).await
+    }
+
+    /// Sets the stock of many products and locations at once, for
+    /// warehouse syncs. Items are checked first and every applicable one is
+    /// then written in one transaction; the others are reported as
+    /// conflicts or failures, and with `atomic` nothing is written unless
+    /// every item applies.
+    pub async fn update_stock_bulk(&self, request: UpdateStockBulkRequest) -> Result<UpdateStockBulkResponse, ProductServiceError> {
+        self.ensure_writable()?;
+
+        if request.items.is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "A bulk stock update needs at least one item".to_string(),
+            });
+        }
+        if request.items.len() > MAX_BULK_STOCK_ITEMS {
+            return Err(ProductServiceError::PayloadTooLarge { what: "Bulk stock update".to_string(), len: request.items.len(), max: MAX_BULK_STOCK_ITEMS });
+        }
+
+        let skus: Vec<String> = request.items.iter().filter_map(|item| item.sku.as_deref()).map(|sku| sku.trim().to_string()).collect::<HashSet<_>>().into_iter().collect();
+        let by_sku: HashMap<String, String> = self.repository.get_products_by_sku(&skus).await?.into_iter().filter_map(|product| Some((product.sku?, product.id.id.to_raw()))).collect();
+        let product_ids: Vec<String> = request.items.iter().filter_map(|item| item.product_id.as_deref()).map(|id| id.trim().to_string()).chain(by_sku.values().cloned()).collect::<HashSet<_>>().into_iter().collect();
+        let products: HashMap<String, Product> = self.repository.get_products(&product_ids).await?.into_iter().map(|product| (product.id.id.to_raw(), product)).collect();
+        let mut current: HashMap<(String, String), Quantity> = HashMap::new();
+        let mut tracked = HashSet::new();
+        for level in self.inventory.stock_levels_for(&product_ids).await? {
+            tracked.insert(level.product_id.clone());
+            current.insert((level.product_id, level.location), level.quantity);
+        }
+
+        let mut locations: HashMap<String, bool> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut results = Vec::with_capacity(request.items.len());
+        let mut pending = Vec::new();
+        for item in request.items {
+            let sku = item.sku.as_deref().map(|sku| sku.trim().to_string());
+            let (product_id, missing) = match (&sku, item.product_id.as_deref()) {
+                (Some(_), Some(_)) => (String::new(), Some("Send either sku or product_id, not both".to_string())),
+                (None, None) => (String::new(), Some("Item needs a sku or product_id".to_string())),
+                (Some(sku), None) if sku.is_empty() => (String::new(), Some("SKU cannot be empty".to_string())),
+                (Some(sku), None) => match by_sku.get(sku) {
+                    Some(product_id) => (product_id.clone(), None),
+                    None => (String::new(), Some(format!("No product has SKU {}", sku))),
+                },
+                (None, Some(product_id)) => (product_id.trim().to_string(), None),
+            };
+            let location = item.location.as_deref().map(normalize_location).unwrap_or_else(|| DEFAULT_LOCATION.to_string());
+            let reason = item.reason.map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty());
+            let mut result = BulkStockResult { sku: sku.clone(), product_id: Some(product_id.clone()).filter(|id| !id.is_empty()), location: location.clone(), status: BulkStockStatus::Failed, previous: None, quantity: None, error: None };
+
+            let known_location = match locations.get(&location) {
+                Some(known) => *known,
+                None => {
+                    let known = location == DEFAULT_LOCATION || self.inventory.get_location(&location).await?.is_some();
+                    locations.insert(location.clone(), known);
+                    known
+                }
+            };
+            let problem = if missing.is_some() {
+                missing
+            } else if product_id.is_empty() {
+                Some("Product ID cannot be empty".to_string())
+            } else if item.quantity.is_negative() {
+                Some("Stock quantity cannot be negative".to_string())
+            } else if reason.as_ref().is_some_and(|reason| reason.chars().count() > MAX_STOCK_REASON_LEN) {
+                Some(format!("Reason is longer than {} characters", MAX_STOCK_REASON_LEN))
+            } else if !seen.insert((product_id.clone(), location.clone())) {
+                Some(format!("Product {} at {} is listed more than once", product_id, location))
+            } else if !known_location {
+                Some(ProductServiceError::LocationNotFound { code: location.clone() }.to_string())
+            } else {
+                match products.get(&product_id) {
+                    None => Some(ProductServiceError::ProductNotFound { id: product_id.clone() }.to_string()),
+                    Some(product) => product.unit.validate(item.quantity).err().map(|err| err.to_string()),
+                }
+            };
+            if let Some(problem) = problem {
+                result.error = Some(problem);
+                results.push(result);
+                continue;
+            }
+
+            // Products without stock rows hold all their stock at the default location
+            let previous = match current.get(&(product_id.clone(), location.clone())) {
+                Some(quantity) => *quantity,
+                None if !tracked.contains(&product_id) && location == DEFAULT_LOCATION => products[&product_id].stock_quantity,
+                None => Quantity::ZERO,
+            };
+            result.previous = Some(previous);
+            if let Some(expected) = item.expected.filter(|expected| *expected != previous) {
+                result.status = BulkStockStatus::Conflict;
+                result.error = Some(format!("Expected {} at {}, found {}", expected, location, previous));
+                results.push(result);
+                continue;
+            }
+
+            pending.push((results.len(), StockSet::new(&product_id, &location, item.quantity, item.expected, reason)));
+            results.push(result);
+        }
+
+        let all_valid = pending.len() == results.len();
+        if !pending.is_empty() && (all_valid || !request.atomic) {
+            let seeds = pending
+                .iter()
+                .map(|(_, item)| item.product_id.as_str())
+                .filter(|product_id| !tracked.contains(*product_id))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .map(|product_id| StockLevel { id: stock_thing(product_id, DEFAULT_LOCATION), product_id: product_id.to_string(), location: DEFAULT_LOCATION.to_string(), quantity: products[product_id].stock_quantity, updated_at: Utc::now() })
+                .collect();
+            let (indexes, items): (Vec<usize>, Vec<StockSet>) = pending.into_iter().unzip();
+            let quantities: Vec<Quantity> = items.iter().map(|item| item.quantity).collect();
+            self.inventory.set_stock_bulk(seeds, items).await?;
+            for (index, quantity) in indexes.into_iter().zip(quantities) {
+                results[index].status = BulkStockStatus::Applied;
+                results[index].quantity = Some(quantity);
+            }
+        } else {
+            for (index, _) in pending {
+                results[index].status = BulkStockStatus::Skipped;
+            }
+        }
+
+        let count = |status: BulkStockStatus| results.iter().filter(|result| result.status == status).count();
+        let response = UpdateStockBulkResponse {
+            total: results.len(),
+            applied: count(BulkStockStatus::Applied),
+            conflicts: count(BulkStockStatus::Conflict),
+            failed: count(BulkStockStatus::Failed),
+            skipped: count(BulkStockStatus::Skipped),
+            results,
+        };
+        info!("Bulk stock update: {} applied, {} conflicts, {} failed, {} skipped", response.applied, response.conflicts, response.failed, response.skipped);
+        Ok(response)
+    }
+
+    pub async fn create_location(&self, request: CreateLocationRequest) -> Result<CreateLocationResponse, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("location")?;
+
+        let code = normalize_location(&request.code);
+        if code.is_empty() || code.len() > 32 || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(ProductServiceError::Validation {
+                message: "Location code must be 1-32 letters, digits or '-'".to_string(),
+            });
+        }
+
+        if code == DEFAULT_LOCATION {
+            return Err(ProductServiceError::LocationAlreadyExists { code });
+        }
+
+        if request.name.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Location name cannot be empty".to_string(),
+            });
+        }
+
+        let location = self
+            .inventory
+            .create_location(LocationForCreation {
+                code,
+                name: request.name.trim().to_string(),
+                created_at: Utc::now(),
+            })
+            .await?;
+
+        Ok(CreateLocationResponse {
+            id: location.id.to_string(),
+            message: format!("Location {} created successfully", location.code),
+        })
+    }
+
+    pub async fn list_locations(&self) -> Result<ListLocationsResponse, ProductServiceError> {
+        let locations = self.inventory.list_locations().await?;
+        let total = locations.len();
+
+        Ok(ListLocationsResponse { locations, total })
+    }
+
+    pub async fn transfer_stock(&self, request: TransferStockRequest) -> Result<TransferStockResponse, ProductServiceError> {
+        self.ensure_writable()?;
+
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+
+        if !request.quantity.is_positive() {
+            return Err(ProductServiceError::Validation {
+                message: "Transfer quantity must be positive".to_string(),
+            });
+        }
+
+        let from = normalize_location(&request.from);
+        let to = normalize_location(&request.to);
+        if from == to {
+            return Err(ProductServiceError::Validation {
+                message: "Source and destination locations must differ".to_string(),
+            });
+        }
+        self.ensure_location(&from).await?;
+        self.ensure_location(&to).await?;
+
+        let product = self.repository.get_product(&request.product_id).await?;
+        validate_quantity(product.unit, request.quantity)?;
+        self.ensure_stock_rows(&request.product_id, product.stock_quantity).await?;
+
+        let stock_at = |levels: &[StockLevel], location: &str| {
+            levels.iter().find(|level| level.location == location).map_or(Quantity::ZERO, |level| level.quantity)
+        };
+
+        // Fail early with the available quantity; the transaction re-checks it
+        let levels = self.inventory.stock_levels(&request.product_id).await?;
+        let available = stock_at(&levels, &from);
+        if available < request.quantity {
+            return Err(ProductServiceError::InsufficientStock {
+                id: request.product_id,
+                available,
+                requested: request.quantity,
+            });
+        }
+
+        self.inventory.transfer_stock(&request.product_id, &from, &to, request.quantity).await?;
+
+        let levels = self.inventory.stock_levels(&request.product_id).await?;
+        Ok(TransferStockResponse {
+            from: LocationStock { quantity: stock_at(&levels, &from), location: from },
+            to: LocationStock { quantity: stock_at(&levels, &to), location: to },
+            product_id: request.product_id,
+        })
+    }
+
+    async fn ensure_location(&self, code: &str) -> Result<(), ProductServiceError> {
+        if code == DEFAULT_LOCATION || self.inventory.get_location(code).await?.is_some() {
+            Ok(())
+        } else {
+            Err(ProductServiceError::LocationNotFound { code: code.to_string() })
+        }
+    }
+
+    /// Products created before per-location tracking (or never stocked
+    /// since) have no stock rows; their whole `stock_quantity` becomes the
+    /// default location's row before the first per-location change.
+    async fn ensure_stock_rows(&self, product_id: &str, stock_quantity: Quantity) -> Result<(), ProductServiceError> {
+        if self.inventory.stock_levels(product_id).await?.is_empty() {
+            self.inventory.seed_stock(product_id, DEFAULT_LOCATION, stock_quantity).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn export_products(&self, request: ExportProductsRequest) -> Result<ExportProductsResponse, ProductServiceError> {
+        if request.limit == 0 || request.limit > MAX_EXPORT_PAGE_SIZE {
+            return Err(ProductServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_EXPORT_PAGE_SIZE),
+            });
+        }
+
+        let (products, total) = self.repository.export_products(request.offset, request.limit).await?;
+
+        Ok(ExportProductsResponse {
+            products,
+            offset: request.offset,
+            total,
+        })
+    }
+
+    /// Validates every CSV row with the same rules as `create_product` and
+    /// inserts the valid ones in batches. Row failures are reported rather
+    /// than aborting the import; only an unreadable file fails the call.
+    pub async fn import_products_csv(
+        &self,
+        request: ImportProductsCsvRequest,
+    ) -> Result<ImportProductsCsvResponse, ProductServiceError> {
+        self.import_rows(request, None).await
+    }
+
+    /// `import_products_csv`, reporting the rows done after each batch to
+    /// `job` when run as one
+    async fn import_rows(&self, request: ImportProductsCsvRequest, job: Option<&Job>) -> Result<ImportProductsCsvResponse, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("product")?;
+
+        let batch_size = request.batch_size.unwrap_or(DEFAULT_IMPORT_BATCH_SIZE);
+        if batch_size == 0 || batch_size > MAX_IMPORT_BATCH_SIZE {
+            return Err(ProductServiceError::Validation {
+                message: format!("Batch size must be between 1 and {}", MAX_IMPORT_BATCH_SIZE),
+            });
+        }
+
+        let mut records = parse_csv(&request.csv)
+            .map_err(|message| ProductServiceError::Validation { message })?
+            .into_iter();
+        let header = records.next().ok_or_else(|| ProductServiceError::Validation {
+            message: "CSV is empty".to_string(),
+        })?;
+        let columns = ProductCsvColumns::from_header(&header.fields)
+            .map_err(|message| ProductServiceError::Validation { message })?;
+
+        let records: Vec<_> = records.collect();
+        if records.len() > MAX_IMPORT_ROWS {
+            return Err(ProductServiceError::PayloadTooLarge { what: "CSV".to_string(), len: records.len(), max: MAX_IMPORT_ROWS });
+        }
+
+        // Validate every row up front, keeping the first occurrence of each name
+        let mut rows = Vec::with_capacity(records.len());
+        let mut pending = Vec::new();
+        let mut seen_names = HashSet::new();
+        let mut seen_skus = HashSet::new();
+        for record in &records {
+            let result = columns
+                .to_request(record)
+                .and_then(|request| {
+                    self.validate_create_product_request(&request)
+                        .map(|_| request)
+                        .map_err(|err| err.to_string())
+                })
+                .and_then(|request| {
+                    if seen_names.insert(request.name.clone()) {
+                        Ok(request)
+                    } else {
+                        Err(format!("Duplicate product name '{}' in CSV", request.name))
+                    }
+                })
+                .and_then(|mut request| {
+                    request.sku = request.sku.as_deref().map(normalize_sku).transpose().map_err(|err| err.to_string())?;
+                    match &request.sku {
+                        Some(sku) if !seen_skus.insert(sku.clone()) => Err(format!("Duplicate SKU '{}' in CSV", sku)),
+                        _ => Ok(request),
+                    }
+                });
+
+            match result {
+                Ok(request) => {
+                    pending.push((rows.len(), request));
+                    rows.push(ImportRowReport { line: record.line, success: false, id: None, error: None });
+                }
+                Err(error) => rows.push(ImportRowReport { line: record.line, success: false, id: None, error: Some(error) }),
+            }
+        }
+
+        // Invalid rows are done already
+        let mut done = rows.len() - pending.len();
+        self.report_job_progress(job, done, rows.len()).await;
+        for batch in pending.chunks(batch_size) {
+            self.import_batch(&mut rows, batch).await;
+            done += batch.len();
+            self.report_job_progress(job, done, rows.len()).await;
+        }
+
+        let imported = rows.iter().filter(|row| row.success).count();
+        info!("Imported {} of {} products from CSV", imported, rows.len());
+
+        Ok(ImportProductsCsvResponse {
+            total_rows: rows.len(),
+            imported,
+            failed: rows.len() - imported,
+            rows,
+        })
+    }
+
+    /// Inserts one batch of validated rows, recording each row's outcome
+    async fn import_batch(&self, rows: &mut [ImportRowReport], batch: &[(usize, CreateProductRequest)]) {
+        let names: Vec<String> = batch.iter().map(|(_, request)| request.name.clone()).collect();
+        let existing = match self.repository.existing_product_names(&names).await {
+            Ok(existing) => existing,
+            Err(err) => {
+                fail_batch(rows, batch, &err.to_string());
+                return;
+            }
+        };
+        let skus: Vec<String> = batch.iter().filter_map(|(_, request)| request.sku.clone()).collect();
+        let taken_skus: HashSet<String> = match self.repository.get_products_by_sku(&skus).await {
+            Ok(products) => products.into_iter().filter_map(|product| product.sku).collect(),
+            Err(err) => {
+                fail_batch(rows, batch, &err.to_string());
+                return;
+            }
+        };
+
+        let mut to_insert = Vec::new();
+        for (index, request) in batch {
+            if existing.contains(&request.name) {
+                rows[*index].error = Some(ProductServiceError::ProductAlreadyExists { name: request.name.clone() }.to_string());
+            } else if let Some(sku) = request.sku.as_ref().filter(|sku| taken_skus.contains(*sku)) {
+                rows[*index].error = Some(ProductServiceError::SkuAlreadyExists { sku: sku.clone() }.to_string());
+            } else {
+                to_insert.push((*index, request));
+            }
+        }
+        if to_insert.is_empty() {
+            return;
+        }
+
+        let products: Vec<Product> = to_insert
+            .iter()
+            .map(|(_, request)| {
+                let mut product = Product::new(
+                    request.name.clone(),
+                    request.description.clone(),
+                    request.price,
+                    request.category.clone(),
+                    request.stock_quantity,
+                    request.unit,
+                );
+                product.sku = request.sku.clone();
+                product
+            })
+            .collect();
+
+        match self.repository.create_products(&products).await {
+            Ok(created) => {
+                self.record_initial_prices(&created, "imported").await;
+                let ids: HashMap<&str, String> = created.iter().map(|product| (product.name.as_str(), product.id_string())).collect();
+                for (index, request) in to_insert {
+                    let row = &mut rows[index];
+                    match ids.get(request.name.as_str()) {
+                        Some(id) => {
+                            row.success = true;
+                            row.id = Some(id.clone());
+                        }
+                        None => row.error = Some("Product was not created".to_string()),
+                    }
+                }
+            }
+            Err(err) => {
+                let message = err.to_string();
+                for (index, _) in to_insert {
+                    rows[index].error = Some(message.clone());
+                }
+            }
+        }
+    }
+
+    /// Queues a heavy operation to run on a job worker and returns the job
+    /// right away. Params are checked now, so a job only fails for reasons
+    /// found while it runs.
+    pub async fn start_job(&self, request: StartJobRequest) -> Result<Job, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("job")?;
+
+        let params = if request.params.is_null() { Value::Object(Default::default()) } else { request.params };
+        match request.kind {
+            JobKind::ImportProductsCsv => {
+                parse_job_params::<ImportProductsCsvRequest>(request.kind, &params)?;
+            }
+            JobKind::ExportProducts => {
+                export_page_size(&parse_job_params(request.kind, &params)?)?;
+            }
+            JobKind::Reindex => {}
+        }
+
+        let now = Utc::now();
+        let job = self
+            .jobs
+            .create_job(JobForCreation { kind: request.kind, status: JobStatus::Queued, params, progress: JobProgress::default(), created_at: now, updated_at: now })
+            .await?;
+        self.job_board.publish(JobEvent::for_job(&job));
+        self.job_board.notify_queued();
+
+        info!("{} job {} queued", job.kind, job.id);
+        Ok(job)
+    }
+
+    pub async fn get_job_status(&self, request: JobIdRequest) -> Result<Job, ProductServiceError> {
+        self.jobs.get_job(&request.job_id).await
+    }
+
+    /// Claims the oldest queued job, runs it and records the outcome.
+    /// `None` when no job was waiting.
+    pub async fn run_next_job(&self) -> Result<Option<Job>, ProductServiceError> {
+        let Some(job) = self.jobs.claim_next().await? else {
+            return Ok(None);
+        };
+        self.job_board.publish(JobEvent::for_job(&job));
+
+        let outcome = match job.kind {
+            JobKind::ImportProductsCsv => match parse_job_params(job.kind, &job.params) {
+                Ok(request) => job_result(self.import_rows(request, Some(&job)).await),
+                Err(err) => Err(err.to_string()),
+            },
+            JobKind::ExportProducts => match parse_job_params(job.kind, &job.params) {
+                Ok(params) => job_result(self.export_all(&params, &job).await),
+                Err(err) => Err(err.to_string()),
+            },
+            JobKind::Reindex => job_result(self.reindex(&job).await),
+        };
+        let finished = match outcome {
+            Ok(result) => self.jobs.finish(&job.id, JobStatus::Succeeded, Some(result), None).await?,
+            Err(error) => self.jobs.finish(&job.id, JobStatus::Failed, None, Some(error)).await?,
+        };
+        self.job_board.publish(JobEvent::for_job(&finished));
+        Ok(Some(finished))
+    }
+
+    /// Every product, read page by page
+    async fn export_all(&self, params: &ExportJobParams, job: &Job) -> Result<ExportProductsResponse, ProductServiceError> {
+        let page_size = export_page_size(params)?;
+        let mut products = Vec::new();
+        loop {
+            let (page, total) = self.repository.export_products(products.len(), page_size).await?;
+            let last = page.len() < page_size;
+            products.extend(page);
+            self.report_job_progress(Some(job), products.len(), total.max(products.len())).await;
+            if last {
+                let total = products.len();
+                return Ok(ExportProductsResponse { products, offset: 0, total });
+            }
+        }
+    }
+
+    /// Rebuilds the tables derived from other data: the attribute lookup
+    /// table from the products' attributes, the recommendations, then the
+    /// category rollups
+    async fn reindex(&self, job: &Job) -> Result<ReindexReport, ProductServiceError> {
+        self.ensure_writable()?;
+        self.report_job_progress(Some(job), 0, 3).await;
+
+        let indexed = self.indexed_attribute_keys().await?;
+        let rows: Vec<ProductAttribute> = self
+            .attributes
+            .product_attributes()
+            .await?
+            .into_iter()
+            .flat_map(|product| {
+                let product_id = product.id.id.to_raw();
+                product
+                    .attributes
+                    .into_iter()
+                    .filter(|(key, _)| indexed.contains(key))
+                    .map(move |(key, value)| ProductAttribute { product_id: product_id.clone(), key, value })
+            })
+            .collect();
+        let attribute_rows = rows.len();
+        self.attributes.replace_lookup(rows).await?;
+        self.report_job_progress(Some(job), 1, 3).await;
+
+        let co_occurrence_rows = self.rebuild_recommendations().await?;
+        self.report_job_progress(Some(job), 2, 3).await;
+
+        let rollup_categories = self.rollups.rebuild().await?;
+        self.report_job_progress(Some(job), 3, 3).await;
+        Ok(ReindexReport { attribute_rows, co_occurrence_rows, rollup_categories })
+    }
+
+    /// Records a running job's progress and tells subscribers. Progress is
+    /// informational, so failing to store it does not fail the job.
+    async fn report_job_progress(&self, job: Option<&Job>, done: usize, total: usize) {
+        let Some(job) = job else {
+            return;
+        };
+        let progress = JobProgress { done, total };
+        if let Err(err) = self.jobs.set_progress(&job.id, progress).await {
+            warn!("Failed to record progress of job {}: {}", job.id, err);
+        }
+        self.job_board.publish(JobEvent { progress, status: JobStatus::Running, ..JobEvent::for_job(job) });
+    }
+
+    pub async fn schedule_price_change(&self, request: SchedulePriceChangeRequest) -> Result<SchedulePriceChangeResponse, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("scheduled_price_change")?;
+
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+
+        if !(request.new_price > 0.0 && request.new_price.is_finite()) {
+            return Err(ProductServiceError::InvalidPrice {
+                price: request.new_price,
+            });
+        }
+
+        // Fail now rather than when the change comes due
+        self.repository.get_product(&request.product_id).await?;
+
+        let scheduled = self
+            .repository
+            .schedule_price_change(ScheduledPriceChangeForCreation {
+                product_id: request.product_id,
+                new_price: request.new_price,
+                effective_at: request.effective_at,
+                status: "pending".to_string(),
+                created_at: Utc::now(),
+            })
+            .await?;
+
+        Ok(SchedulePriceChangeResponse {
+            id: scheduled.id.to_string(),
+            message: format!("Price change to {} scheduled for {}", scheduled.new_price, scheduled.effective_at),
+        })
+    }
+
+    pub async fn get_price_history(&self, request: GetPriceHistoryRequest) -> Result<PriceHistoryResponse, ProductServiceError> {
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+
+        let limit = request.limit.unwrap_or(DEFAULT_PRICE_HISTORY_LIMIT);
+        if limit == 0 || limit > MAX_PRICE_HISTORY_LIMIT {
+            return Err(ProductServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_PRICE_HISTORY_LIMIT),
+            });
+        }
+
+        let product = self.repository.get_product(&request.product_id).await?;
+        let changes = self.repository.get_price_history(&request.product_id, limit).await?;
+        let pending = self.repository.pending_price_changes(Some(&request.product_id)).await?;
+
+        Ok(PriceHistoryResponse {
+            product_id: request.product_id,
+            current_price: product.price,
+            changes,
+            pending,
+        })
+    }
+
+    /// Applies every scheduled price change whose `effective_at` has passed,
+    /// oldest first. Does nothing in read-only mode; the changes stay pending.
+    /// A change that fails is logged and retried on the next run.
+    pub async fn apply_due_price_changes(&self) -> Result<usize, ProductServiceError> {
+        if self.read_only.is_enabled() {
+            return Ok(0);
+        }
+
+        let now = Utc::now();
+        let mut applied = 0;
+        for change in self.repository.pending_price_changes(None).await? {
+            if change.effective_at > now {
+                continue;
+            }
+
+            let result = match self.repository.get_product(&change.product_id).await {
+                Ok(product) => self.repository.apply_scheduled_price_change(&change, product.price).await,
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(()) => applied += 1,
+                Err(err) => warn!("Failed to apply scheduled price change {}: {}", change.id, err),
+            }
+        }
+
+        Ok(applied)
+    }
+
+    /// Returns the last generated feed in the requested format, building it
+    /// from the catalog first when there is none yet or `refresh` is set
+    pub async fn generate_feed(&self, request: GenerateFeedRequest) -> Result<ProductFeed, ProductServiceError> {
+        // Feeds rendered before a product changed no longer match the catalog
+        if self.product_changes.as_ref().is_some_and(ChangeWatcher::changed) {
+            self.feeds.write().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+        if !request.refresh {
+            let feeds = self.feeds.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(feed) = feeds.get(&request.format) {
+                return Ok(feed.clone());
+            }
+        }
+
+        // Feeds are published, so they only ever list public products
+        let products = self.repository.list_products(ProductSortField::CreatedAt, SortDirection::Desc, None).await?;
+        let feed = render_feed(&products, &self.feed_config, request.format);
+        self.feeds.write().unwrap_or_else(PoisonError::into_inner).insert(request.format, feed.clone());
+        Ok(feed)
+    }
+
+    /// Rebuilds the feed in every format from one read of the catalog
+    pub async fn regenerate_feeds(&self) -> Result<Vec<ProductFeed>, ProductServiceError> {
+        let products = self.repository.list_products(ProductSortField::CreatedAt, SortDirection::Desc, None).await?;
+        let feeds: Vec<ProductFeed> = FeedFormat::ALL
+            .iter()
+            .map(|format| render_feed(&products, &self.feed_config, *format))
+            .collect();
+
+        let mut cached = self.feeds.write().unwrap_or_else(PoisonError::into_inner);
+        for feed in &feeds {
+            cached.insert(feed.format, feed.clone());
+        }
+        Ok(feeds)
+    }
+
+    /// The last stock reconciliation, or a new one when asked to refresh or
+    /// none has run yet
+    pub async fn reconcile_stock(&self, request: ReconcileStockRequest) -> Result<StockReconciliationReport, ProductServiceError> {
+        if !request.refresh {
+            let last = self.reconciliation.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(report) = last.as_ref() {
+                return Ok(report.clone());
+            }
+        }
+        self.run_stock_reconciliation().await
+    }
+
+    /// Cross-checks every product's total against its stock rows, and
+    /// orders and returns against the stock ledger, and keeps the result
+    /// for `reconcile_stock`
+    pub async fn run_stock_reconciliation(&self) -> Result<StockReconciliationReport, ProductServiceError> {
+        let totals = self.inventory.product_totals().await?;
+        let levels = self.inventory.all_stock_levels().await?;
+        let locations = self.inventory.list_locations().await?;
+        let activity = OrderActivity {
+            awaiting_shipment: self.orders.awaiting_shipment_quantities().await?,
+            shipped: self.orders.shipped_quantities().await?,
+            removed: self.inventory.removed_by_adjustments().await?,
+            returned: self.returns.completed_quantities().await?,
+            restocked: self.inventory.restocked_by_returns().await?,
+        };
+        let report = StockReconciliationReport {
+            checked_at: Utc::now(),
+            products_checked: totals.len(),
+            stock_rows_checked: levels.len(),
+            discrepancies: find_discrepancies(&totals, &levels, &locations, &activity),
+        };
+        *self.reconciliation.write().unwrap_or_else(PoisonError::into_inner) = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Projects the product's stock `horizon_days` ahead from the last
+    /// `FORECAST_LOOKBACK_DAYS` days of its stock movements
+    pub async fn forecast_stock(&self, request: ForecastStockRequest) -> Result<StockForecast, ProductServiceError> {
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+
+        if request.horizon_days == 0 || request.horizon_days > MAX_FORECAST_HORIZON_DAYS {
+            return Err(ProductServiceError::Validation {
+                message: format!("Horizon must be between 1 and {} days", MAX_FORECAST_HORIZON_DAYS),
+            });
+        }
+
+        let product = self.repository.get_product(&request.product_id).await?;
+        let levels = self.inventory.stock_levels(&request.product_id).await?;
+        // Products without stock rows hold their whole total at the default location
+        let stock: Vec<LocationStock> = if levels.is_empty() {
+            vec![LocationStock { location: DEFAULT_LOCATION.to_string(), quantity: product.stock_quantity }]
+        } else {
+            levels.into_iter().map(|level| LocationStock { location: level.location, quantity: level.quantity }).collect()
+        };
+
+        let now = Utc::now();
+        let since = now - chrono::Duration::days(FORECAST_LOOKBACK_DAYS);
+        let movements = self.inventory.movements_since(&request.product_id, since).await?;
+
+        Ok(forecast_stock(&request.product_id, &stock, &movements, request.horizon_days, now))
+    }
+
+    pub async fn create_coupon(&self, request: CreateCouponRequest) -> Result<CreateCouponResponse, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("coupon")?;
+
+        let code = normalize_code(&request.code);
+        if code.len() < 3 || code.len() > 32 || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(ProductServiceError::Validation {
+                message: "Coupon code must be 3-32 letters, digits, '-' or '_'".to_string(),
+            });
+        }
+
+        let valid_value = match request.discount_type {
+            DiscountType::Percent => request.value > 0.0 && request.value <= 100.0,
+            DiscountType::Fixed => request.value > 0.0 && request.value.is_finite(),
+        };
+        if !valid_value {
+            return Err(ProductServiceError::Validation {
+                message: format!("Invalid {:?} discount value: {}", request.discount_type, request.value),
+            });
+        }
+
+        if request.constraints.min_order_total.is_some_and(|min| !(min >= 0.0 && min.is_finite())) {
+            return Err(ProductServiceError::Validation {
+                message: "Minimum order total cannot be negative".to_string(),
+            });
+        }
+
+        if request.max_redemptions == Some(0) {
+            return Err(ProductServiceError::Validation {
+                message: "Max redemptions must be at least 1".to_string(),
+            });
+        }
+
+        let now = Utc::now();
+        if request.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(ProductServiceError::Validation {
+                message: "Coupon expiry must be in the future".to_string(),
+            });
+        }
+
+        let coupon = self
+            .coupons
+            .create_coupon(CouponForCreation {
+                code,
+                discount_type: request.discount_type,
+                value: request.value,
+                constraints: request.constraints,
+                expires_at: request.expires_at,
+                max_redemptions: request.max_redemptions,
+                redemptions: 0,
+                active: true,
+                created_at: now,
+                updated_at: now,
+            })
+            .await?;
+
+        Ok(CreateCouponResponse {
+            id: coupon.id.to_string(),
+            message: format!("Coupon {} created successfully", coupon.code),
+            code: coupon.code,
+        })
+    }
+
+    /// Quotes a coupon for a checkout without using it up. Unknown or
+    /// inapplicable coupons come back as `valid: false` with a reason.
+    pub async fn validate_coupon(&self, checkout: CouponCheckout) -> Result<ValidateCouponResponse, ProductServiceError> {
+        let code = normalize_code(&checkout.code);
+        let result = match self.coupons.get_coupon_by_code(&code).await? {
+            Some(coupon) => quote(&coupon, &checkout, Utc::now()),
+            None => Err("Coupon not found".to_string()),
+        };
+
+        Ok(match result {
+            Ok(discount) => ValidateCouponResponse {
+                code,
+                valid: true,
+                discount,
+                total_after_discount: round_to_cents(checkout.order_total - discount),
+                reason: None,
+            },
+            Err(reason) => ValidateCouponResponse {
+                code,
+                valid: false,
+                discount: 0.0,
+                total_after_discount: checkout.order_total,
+                reason: Some(reason),
+            },
+        })
+    }
+
+    /// Applies a coupon at checkout and counts the redemption atomically;
+    /// the caller charges `total_after_discount`.
+    pub async fn redeem_coupon(&self, request: RedeemCouponRequest) -> Result<RedeemCouponResponse, ProductServiceError> {
+        self.ensure_writable()?;
+
+        let checkout = request.checkout;
+        let code = normalize_code(&checkout.code);
+        let coupon = self
+            .coupons
+            .get_coupon_by_code(&code)
+            .await?
+            .ok_or_else(|| ProductServiceError::CouponNotFound { code: code.clone() })?;
+        let discount = quote(&coupon, &checkout, Utc::now())
+            .map_err(|reason| ProductServiceError::CouponRejected { code: code.clone(), reason })?;
+
+        // Another checkout may have used the last redemption since the quote
+        let redeemed = self
+            .coupons
+            .increment_redemptions(&code)
+            .await?
+            .ok_or_else(|| ProductServiceError::CouponRejected {
+                code: code.clone(),
+                reason: "Coupon is no longer valid or its usage limit was reached".to_string(),
+            })?;
+
+        info!(
+            "Coupon {} redeemed for order {}: {} off",
+            code,
+            request.order_id.as_deref().unwrap_or("-"),
+            discount
+        );
+        Ok(RedeemCouponResponse {
+            code,
+            discount,
+            total_after_discount: round_to_cents(checkout.order_total - discount),
+            redemptions: redeemed.redemptions,
+            redemptions_remaining: redeemed.max_redemptions.map(|max| max.saturating_sub(redeemed.redemptions)),
+        })
+    }
+
+    /// Records which products were bought together in a completed order,
+    /// for `get_recommended_products`, and the quantities and prices of its
+    /// `lines`, which returns are checked against, under the `user_id`
+    /// `list_orders_by_user` lists it for
+    pub async fn record_order(&self, request: RecordOrderRequest) -> Result<RecordOrderResponse, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("order_history")?;
+
+        let user_id = request.user_id.map(|id| id.trim().to_string());
+        if user_id.as_deref() == Some("") {
+            return Err(ProductServiceError::Validation {
+                message: "User ID cannot be empty".to_string(),
+            });
+        }
+
+        let mut lines: Vec<OrderLine> = Vec::with_capacity(request.lines.len());
+        for line in request.lines {
+            let product_id = line.product_id.trim().to_string();
+            if lines.iter().any(|seen| seen.product_id == product_id) {
+                return Err(ProductServiceError::Validation {
+                    message: format!("Product {} is listed more than once", product_id),
+                });
+            }
+            if !line.quantity.is_positive() {
+                return Err(ProductServiceError::Validation {
+                    message: "Order line quantity must be positive".to_string(),
+                });
+            }
+            if !line.unit_price.is_finite() || line.unit_price < 0.0 {
+                return Err(ProductServiceError::Validation {
+                    message: "Unit price cannot be negative".to_string(),
+                });
+            }
+            lines.push(OrderLine { product_id, quantity: line.quantity, unit_price: line.unit_price });
+        }
+
+        let mut product_ids: Vec<String> = request.product_ids.into_iter().map(|id| id.trim().to_string()).chain(lines.iter().map(|line| line.product_id.clone())).collect();
+        if product_ids.is_empty() || product_ids.iter().any(String::is_empty) {
+            return Err(ProductServiceError::Validation {
+                message: "An order needs at least one product, and product ids cannot be empty".to_string(),
+            });
+        }
+        product_ids.sort();
+        product_ids.dedup();
+        if product_ids.len() > MAX_ORDER_PRODUCTS {
+            return Err(ProductServiceError::PayloadTooLarge { what: "Order".to_string(), len: product_ids.len(), max: MAX_ORDER_PRODUCTS });
+        }
+
+        let product_count = product_ids.len();
+        self.orders
+            .record_order(OrderForRecording { order_id: request.order_id, user_id, status: OrderStatus::Completed, product_ids, lines, ordered_at: Utc::now() })
+            .await?;
+        Ok(RecordOrderResponse {
+            product_count,
+            message: format!("Order recorded with {} products", product_count),
+        })
+    }
+
+    /// One user's recorded orders, newest first, optionally only those in
+    /// `status` or placed between `from` and `to`, in pages
+    pub async fn list_orders_by_user(&self, mut request: ListOrdersByUserRequest) -> Result<ListOrdersResponse, ProductServiceError> {
+        request.user_id = request.user_id.trim().to_string();
+        if request.user_id.is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "User ID cannot be empty".to_string(),
+            });
+        }
+        if let (Some(from), Some(to)) = (request.from, request.to) {
+            if from >= to {
+                return Err(ProductServiceError::Validation {
+                    message: "`from` must be before `to`".to_string(),
+                });
+            }
+        }
+        let limit = request.limit.unwrap_or(DEFAULT_LIST_PAGE_SIZE);
+        if limit == 0 || limit > MAX_LIST_PAGE_SIZE {
+            return Err(ProductServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_LIST_PAGE_SIZE),
+            });
+        }
+
+        let from = request.from.map(|at| at.to_rfc3339()).unwrap_or_default();
+        let to = request.to.map(|at| at.to_rfc3339()).unwrap_or_default();
+        let filter = filter_hash(&[&request.user_id, request.status.map_or("", |status| status.as_str()), &from, &to]);
+        let offset = match &request.cursor {
+            Some(token) => {
+                let cursor: OrderPageCursor = self.cursors.verify(token)?;
+                if cursor.filter != filter {
+                    return Err(CursorRejection::Mismatched.into());
+                }
+                cursor.offset
+            }
+            None => 0,
+        };
+
+        let (orders, total) = self.orders.orders_by_user(&request, offset, limit).await?;
+        let next_offset = offset + orders.len();
+        let next_cursor = (next_offset < total).then(|| self.cursors.sign(&OrderPageCursor { offset: next_offset, filter }));
+        Ok(ListOrdersResponse { orders, total, next_cursor })
+    }
+
+    /// Ships an order waiting to ship. The shipping address must pass the
+    /// same country rules as `validate_address`, and is stored normalized.
+    pub async fn ship_order(&self, request: ShipOrderRequest) -> Result<RecordedOrder, ProductServiceError> {
+        self.ensure_writable()?;
+
+        let order_id = request.order_id.trim().to_string();
+        if order_id.is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Order ID cannot be empty".to_string(),
+            });
+        }
+        let validation = validate_address(&request.shipping_address);
+        let Some(address) = validation.normalized else {
+            let errors: Vec<String> = validation.errors.iter().map(|error| format!("{}: {}", error.field, error.message)).collect();
+            return Err(ProductServiceError::Validation {
+                message: format!("Invalid shipping address ({})", errors.join("; ")),
+            });
+        };
+
+        let Some(shipped) = self.orders.mark_shipped(&order_id, address).await? else {
+            let order = self.orders.find_order(&order_id).await?.ok_or_else(|| ProductServiceError::OrderNotFound { id: order_id.clone() })?;
+            return Err(ProductServiceError::InvalidOrderTransition { id: order_id, status: order.status, to: OrderStatus::Shipped });
+        };
+
+        info!("Order {} shipped to {}", order_id, shipped.shipping_address.as_ref().map_or("", |address| address.country.as_str()));
+        Ok(shipped)
+    }
+
+    /// How many orders were recorded for a user, which the user service
+    /// checks before deleting them
+    pub async fn count_orders_by_user(&self, request: CountOrdersByUserRequest) -> Result<UserOrderCount, ProductServiceError> {
+        let user_id = request.user_id.trim().to_string();
+        if user_id.is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "User ID cannot be empty".to_string(),
+            });
+        }
+        let count = self.orders.count_by_user(&user_id).await?;
+        Ok(UserOrderCount { user_id, count })
+    }
+
+    /// Products most often bought together with `product_id`, from the
+    /// latest co-occurrence build. Only products `org` can see are listed,
+    /// and a product it cannot see is reported as not found.
+    pub async fn get_recommended_products(&self, request: GetRecommendedProductsRequest, org: Option<&str>) -> Result<RecommendedProductsResponse, ProductServiceError> {
+        if request.product_id.trim().is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Product ID cannot be empty".to_string(),
+            });
+        }
+        let limit = request.limit.unwrap_or(DEFAULT_RECOMMENDATIONS_LIMIT);
+        if limit == 0 || limit > MAX_RELATED_PER_PRODUCT {
+            return Err(ProductServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_RELATED_PER_PRODUCT),
+            });
+        }
+
+        let product = self.repository.get_product(&request.product_id).await?;
+        if !product.is_visible_to(org) {
+            return Err(ProductServiceError::ProductNotFound { id: request.product_id });
+        }
+        let products = self
+            .orders
+            .bought_together(&request.product_id, org, limit)
+            .await?
+            .into_iter()
+            .map(|(product, bought_together)| RecommendedProduct {
+                id: product.id.id.to_raw(),
+                name: product.name,
+                price: product.price,
+                category: product.category,
+                bought_together,
+            })
+            .collect();
+
+        Ok(RecommendedProductsResponse {
+            product_id: request.product_id,
+            products,
+            built_at: *self.recommendations_built_at.read().unwrap_or_else(PoisonError::into_inner),
+        })
+    }
+
+    /// Opens a return against an order recorded by `record_order` with
+    /// lines. At most the quantity ordered, less what earlier returns of the
+    /// order take back, can be returned, and the refund is worked out from
+    /// the price paid; nothing is restocked or refunded until the return is
+    /// completed.
+    pub async fn create_return(&self, request: CreateReturnRequest) -> Result<ProductReturn, ProductServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("product_return")?;
+
+        let order_id = request.order_id.trim().to_string();
+        if order_id.is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Order ID cannot be empty".to_string(),
+            });
+        }
+        let reason = request.reason.trim().to_string();
+        if reason.is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "Return reason cannot be empty".to_string(),
+            });
+        }
+        if request.items.is_empty() {
+            return Err(ProductServiceError::Validation {
+                message: "A return needs at least one item".to_string(),
+            });
+        }
+        if request.items.len() > MAX_ORDER_PRODUCTS {
+            return Err(ProductServiceError::PayloadTooLarge { what: "Return".to_string(), len: request.items.len(), max: MAX_ORDER_PRODUCTS });
+        }
+
+        let order = self.orders.find_order(&order_id).await?.ok_or_else(|| ProductServiceError::OrderNotFound { id: order_id.clone() })?;
+        let returned = self.returns.returned_quantities(&order_id, None).await?;
+
+        let mut items = Vec::with_capacity(request.items.len());
+        let mut seen = HashSet::new();
+        for item in request.items {
+            let product_id = item.product_id.trim().to_string();
+            let Some(line) = order.line(&product_id) else {
+                let message = if order.product_ids.contains(&product_id) {
+                    format!("Order {} was recorded without a quantity and price for product {}", order_id, product_id)
+                } else {
+                    format!("Product {} is not part of order {}", product_id, order_id)
+                };
+                return Err(ProductServiceError::Validation { message });
+            };
+            if !seen.insert(product_id.clone()) {
+                return Err(ProductServiceError::Validation {
+                    message: format!("Product {} is listed more than once", product_id),
+                });
+            }
+            if !item.quantity.is_positive() {
+                return Err(ProductServiceError::Validation {
+                    message: "Return quantity must be positive".to_string(),
+                });
+            }
+            let returnable = line.quantity - returned.get(&product_id).copied().unwrap_or_default();
+            if item.quantity > returnable {
+                return Err(ProductServiceError::Validation {
+                    message: format!("Only {} of product {} can still be returned from order {}", returnable.max(Quantity::ZERO), product_id, order_id),
+                });
+            }
+
+            let product = self.repository.get_product(&product_id).await?;
+            validate_quantity(product.unit, item.quantity)?;
+            let location = item.location.as_deref().map(normalize_location).unwrap_or_else(|| DEFAULT_LOCATION.to_string());
+            self.ensure_location(&location).await?;
+
+            let refund_amount = round_to_cents(line.unit_price * item.quantity.to_f64());
+            items.push(ReturnItem { product_id, quantity: item.quantity, refund_amount, location: Some(location) });
+        }
+
+        let now = Utc::now();
+        let refund_total = round_to_cents(items.iter().map(|item| item.refund_amount).sum());
+        let product_return = self
+            .returns
+            .create_return(ReturnForCreation { order_id, items, reason, status: ReturnStatus::Requested, refund_total, created_at: now, updated_at: now }, &order.lines)
+            .await?;
+
+        info!("Return {} requested for order {}", product_return.id, product_return.order_id);
+        Ok(product_return)
+    }
+
+    pub async fn approve_return(&self, request: ReturnIdRequest) -> Result<ProductReturn, ProductServiceError> {
+        self.ensure_writable()?;
+
+        let product_return = self.return_in_status(&request.return_id, ReturnStatus::Approved).await?;
+        let Some(approved) = self.returns.approve(&request.return_id).await? else {
+            // A concurrent call moved it on first
+            let current = self.returns.get_return(&request.return_id).await?;
+            return Err(ProductServiceError::InvalidReturnTransition { id: request.return_id, status: current.status, to: ReturnStatus::Approved });
+        };
+
+        info!("Return {} approved for order {}", approved.id, product_return.order_id);
+        Ok(approved)
+    }
+
+    /// Restocks the returned items through the stock ledger and records the
+    /// refund on the order, in one transaction
+    pub async fn complete_return(&self, request: ReturnIdRequest) -> Result<ProductReturn, ProductServiceError> {
+        self.ensure_writable()?;
+
+        let product_return = self.return_in_status(&request.return_id, ReturnStatus::Completed).await?;
+        let order = self
+            .orders
+            .find_order(&product_return.order_id)
+            .await?
+            .ok_or_else(|| ProductServiceError::OrderNotFound { id: product_return.order_id.clone() })?;
+        for item in &product_return.items {
+            let product = self.repository.get_product(&item.product_id).await?;
+            self.ensure_stock_rows(&item.product_id, product.stock_quantity).await?;
+        }
+
+        self.returns.complete(&product_return, &order.id, DEFAULT_LOCATION).await?;
+
+        // The order is returned once completed returns cover every line
+        let completed = self.returns.returned_quantities(&product_return.order_id, Some(ReturnStatus::Completed)).await?;
+        let status = if order.lines.iter().all(|line| completed.get(&line.product_id).is_some_and(|returned| *returned >= line.quantity)) { OrderStatus::Returned } else { OrderStatus::PartiallyReturned };
+        self.orders.set_status(&order.id, status).await?;
+
+        info!("Return {} completed for order {}, refunded {:.2}", product_return.id, product_return.order_id, product_return.refund_total);
+        self.returns.get_return(&request.return_id).await
+    }
+
+    pub async fn get_return(&self, request: ReturnIdRequest) -> Result<ProductReturn, ProductServiceError> {
+        self.returns.get_return(&request.return_id).await
+    }
+
+    /// The return, if it may move to `to` from its current status
+    async fn return_in_status(&self, return_id: &str, to: ReturnStatus) -> Result<ProductReturn, ProductServiceError> {
+        let product_return = self.returns.get_return(return_id).await?;
+        if Some(product_return.status) != to.previous() {
+            return Err(ProductServiceError::InvalidReturnTransition { id: return_id.to_string(), status: product_return.status, to });
+        }
+        Ok(product_return)
+    }
+
+    /// Recounts which products are bought together across the whole order
+    /// history and replaces the co-occurrence table. Returns the number of
+    /// rows written; does nothing in read-only mode.
+    pub async fn rebuild_recommendations(&self) -> Result<usize, ProductServiceError> {
+        if self.read_only.is_enabled() {
+            return Ok(0);
+        }
+
+        let baskets = self.orders.order_baskets().await?;
+        let rows = count_co_occurrences(&baskets, self.co_occurrence.min_orders);
+        let count = rows.len();
+        self.orders.replace_co_occurrences(rows).await?;
+        *self.recommendations_built_at.write().unwrap_or_else(PoisonError::into_inner) = Some(Utc::now());
+        Ok(count)
+    }
+
+    /// Records the price products were created with. History is best effort:
+    /// a failure here is logged but does not fail the creation.
+    async fn record_initial_prices(&self, products: &[Product], source: &str) {
+        let changes: Vec<PriceChangeForCreation> = products
+            .iter()
+            .map(|product| PriceChangeForCreation {
+                product_id: product.id.id.to_raw(),
+                old_price: None,
+                new_price: product.price,
+                source: source.to_string(),
+                changed_at: product.created_at,
+            })
+            .collect();
+
+        if let Err(err) = self.repository.record_price_changes(&changes).await {
+            warn!("Failed to record price history for {} products: {}", changes.len(), err);
+        }
     }
 
     fn validate_create_product_request(
@@ -112,12 +1821,128 @@ impl ProductService {
             });
         }
 
-        if request.stock_quantity < 0 {
+        if let Some(max_similarity) = request.max_similarity {
+            validate_similarity(max_similarity)?;
+        }
+
+        if request.stock_quantity.is_negative() {
             return Err(ProductServiceError::Validation {
                 message: "Stock quantity cannot be negative".to_string(),
             });
         }
 
+        validate_quantity(request.unit, request.stock_quantity)?;
+
+        if let Some(sku) = &request.sku {
+            normalize_sku(sku)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl RetentionTarget for ProductService {
+    fn connection(&self) -> Arc<DbConnection> {
+        self.repository.connection()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only.is_enabled()
+    }
+}
+
+fn normalize_location(code: &str) -> String {
+    code.trim().to_ascii_lowercase()
+}
+
+/// The trimmed SKU, which must be 1-64 characters without whitespace
+fn normalize_sku(sku: &str) -> Result<String, ProductServiceError> {
+    let sku = sku.trim();
+    if sku.is_empty() || sku.chars().count() > MAX_SKU_LEN || sku.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(ProductServiceError::Validation {
+            message: format!("SKU must be 1-{} characters without whitespace", MAX_SKU_LEN),
+        });
+    }
+    Ok(sku.to_string())
+}
+
+/// Trimmed, sorted and deduplicated organization ids for `visible_to`
+fn normalize_orgs(orgs: Vec<String>) -> Result<Vec<String>, ProductServiceError> {
+    let mut orgs: Vec<String> = orgs.into_iter().map(|org| org.trim().to_string()).collect();
+    if orgs.iter().any(String::is_empty) {
+        return Err(ProductServiceError::Validation {
+            message: "Organization ids in visible_to cannot be empty".to_string(),
+        });
+    }
+    orgs.sort();
+    orgs.dedup();
+    Ok(orgs)
+}
+
+/// Fails unless `value` has the attribute's registered type and is a
+/// finite number or a text of reasonable length
+fn validate_attribute_value(definition: &AttributeDefinition, value: &AttributeValue) -> Result<(), ProductServiceError> {
+    if value.value_type() != definition.value_type {
+        return Err(ProductServiceError::Validation {
+            message: format!("Attribute '{}' takes {} values, got {}", definition.key, definition.value_type, value.value_type()),
+        });
+    }
+    match value {
+        AttributeValue::Number(number) if !number.is_finite() => Err(ProductServiceError::Validation {
+            message: format!("Attribute '{}' must be a finite number", definition.key),
+        }),
+        AttributeValue::Text(text) if text.chars().count() > MAX_ATTRIBUTE_TEXT_LEN => Err(ProductServiceError::Validation {
+            message: format!("Attribute '{}' is longer than {} characters", definition.key, MAX_ATTRIBUTE_TEXT_LEN),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Pieces are counted whole; kilograms and liters go down to the gram and
+/// milliliter
+fn validate_quantity(unit: StockUnit, quantity: Quantity) -> Result<(), ProductServiceError> {
+    unit.validate(quantity).map_err(|err| ProductServiceError::Validation { message: err.to_string() })
+}
+
+fn round_unit_values(stock_by_unit: &mut [UnitStock]) {
+    for unit in stock_by_unit {
+        unit.inventory_value = round_to_cents(unit.inventory_value);
+    }
+}
+
+fn validate_similarity(similarity: f64) -> Result<(), ProductServiceError> {
+    if similarity > 0.0 && similarity <= 1.0 {
         Ok(())
+    } else {
+        Err(ProductServiceError::Validation {
+            message: format!("Similarity must be above 0 and at most 1, got {}", similarity),
+        })
+    }
+}
+
+/// A job's params as the operation's request
+fn parse_job_params<T: DeserializeOwned>(kind: JobKind, params: &Value) -> Result<T, ProductServiceError> {
+    serde_json::from_value(params.clone()).map_err(|err| ProductServiceError::Validation {
+        message: format!("Invalid params for a {} job: {}", kind, err),
+    })
+}
+
+fn export_page_size(params: &ExportJobParams) -> Result<usize, ProductServiceError> {
+    match params.page_size.unwrap_or(MAX_EXPORT_PAGE_SIZE) {
+        size @ 1..=MAX_EXPORT_PAGE_SIZE => Ok(size),
+        _ => Err(ProductServiceError::Validation {
+            message: format!("Page size must be between 1 and {}", MAX_EXPORT_PAGE_SIZE),
+        }),
+    }
+}
+
+/// A finished job's result, or its error message
+fn job_result<T: Serialize>(result: Result<T, ProductServiceError>) -> Result<Value, String> {
+    result.map_err(|err| err.to_string()).and_then(|value| serde_json::to_value(value).map_err(|err| err.to_string()))
+}
+
+fn fail_batch(rows: &mut [ImportRowReport], batch: &[(usize, CreateProductRequest)], message: &str) {
+    for (index, _) in batch {
+        rows[*index].error = Some(message.to_string());
     }
 }
@@ -0,0 +1,67 @@
+use crate::models::product_model::{ProductName, SimilarProduct};
+use std::collections::HashSet;
+
+/// Similarity at which `find_similar_products` reports a name by default
+pub const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.5;
+
+/// Lowercases `name` and reduces it to words of letters and digits, so
+/// `"Wireless  Mouse (Black)"` and `"wireless mouse - black"` compare equal
+fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Three-character windows over each word of a normalized name, padded
+/// like `pg_trgm` so short words and word starts still count
+fn trigrams(normalized: &str) -> HashSet<[char; 3]> {
+    let mut trigrams = HashSet::new();
+    for word in normalized.split(' ').filter(|w| !w.is_empty()) {
+        let padded: Vec<char> = "  ".chars().chain(word.chars()).chain([' ']).collect();
+        trigrams.extend(padded.windows(3).map(|w| [w[0], w[1], w[2]]));
+    }
+    trigrams
+}
+
+/// Share of trigrams two names have in common, from 0 (nothing) to 1 (same
+/// normalized name)
+fn similarity(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// The `limit` candidates at least `threshold` similar to `name`, most
+/// similar first
+pub fn find_similar(
+    name: &str,
+    candidates: &[ProductName],
+    threshold: f64,
+    limit: usize,
+) -> Vec<SimilarProduct> {
+    let target = trigrams(&normalize_name(name));
+    let mut similar: Vec<SimilarProduct> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let score = similarity(&target, &trigrams(&normalize_name(&candidate.name)));
+            (score >= threshold).then(|| SimilarProduct {
+                id: candidate.id.id.to_raw(),
+                name: candidate.name.clone(),
+                similarity: (score * 1000.0).round() / 1000.0,
+            })
+        })
+        .collect();
+    similar.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    similar.truncate(limit);
+    similar
+}
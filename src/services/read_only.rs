@@ -0,0 +1,57 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Switch that makes a service reject mutating methods while still serving
+/// reads, e.g. during a migration or while persistent storage is degraded.
+#[derive(Debug, Default)]
+pub struct ReadOnlyMode {
+    enabled: AtomicBool,
+}
+
+impl ReadOnlyMode {
+    /// Starts enabled when `SERVICE_READ_ONLY` is `true` or `1`
+    pub fn from_env() -> Self {
+        let enabled = matches!(
+            std::env::var("SERVICE_READ_ONLY").as_deref(),
+            Ok("true") | Ok("1")
+        );
+        Self {
+            enabled: AtomicBool::new(enabled),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Flips the mode and returns the new state
+    pub fn toggle(&self) -> bool {
+        !self.enabled.fetch_xor(true, Ordering::Relaxed)
+    }
+}
+
+/// Toggles read-only mode every time the process receives SIGUSR1
+#[cfg(unix)]
+pub fn toggle_on_sigusr1(
+    mode: std::sync::Arc<ReadOnlyMode>,
+    service_name: &'static str,
+) -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    use tracing::warn;
+
+    let mut signals = signal(SignalKind::user_defined1())?;
+    tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            let enabled = mode.toggle();
+            warn!(
+                "{} read-only mode {} by SIGUSR1",
+                service_name,
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use crate::models::recommendation_model::CoOccurrence;
+use crate::services::product_service::ProductService;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Most related products kept per product; recommendations never list more
+pub const MAX_RELATED_PER_PRODUCT: usize = 50;
+
+/// How the co-occurrence table behind `get_recommended_products` is built
+#[derive(Debug, Clone, Copy)]
+pub struct CoOccurrenceConfig {
+    /// How often the table is rebuilt; `None` disables the job
+    pub interval: Option<Duration>,
+    /// Pairs bought together in fewer orders are left out as noise
+    pub min_orders: u64,
+}
+
+impl CoOccurrenceConfig {
+    /// Reads `RECOMMENDATIONS_INTERVAL_SECS` (default 300, `0` disables the
+    /// job) and `RECOMMENDATIONS_MIN_ORDERS` (default 2)
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let interval_secs = var("RECOMMENDATIONS_INTERVAL_SECS", 300);
+        Self {
+            interval: (interval_secs > 0).then(|| Duration::from_secs(interval_secs)),
+            min_orders: var("RECOMMENDATIONS_MIN_ORDERS", 2).max(1),
+        }
+    }
+}
+
+/// Counts, for every pair of products, the orders containing both. Each
+/// pair is kept in both directions, and each product keeps its
+/// [`MAX_RELATED_PER_PRODUCT`] most frequent partners seen in at least
+/// `min_orders` orders.
+pub fn count_co_occurrences(baskets: &[Vec<String>], min_orders: u64) -> Vec<CoOccurrence> {
+    let mut counts: HashMap<(&str, &str), u64> = HashMap::new();
+    for basket in baskets {
+        let mut products: Vec<&str> = basket.iter().map(String::as_str).collect();
+        products.sort_unstable();
+        products.dedup();
+        for (i, a) in products.iter().enumerate() {
+            for b in &products[i + 1..] {
+                *counts.entry((a, b)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut related: HashMap<&str, Vec<(&str, u64)>> = HashMap::new();
+    for ((a, b), orders) in counts {
+        if orders < min_orders {
+            continue;
+        }
+        related.entry(a).or_default().push((b, orders));
+        related.entry(b).or_default().push((a, orders));
+    }
+
+    let mut rows = Vec::new();
+    for (product_id, mut others) in related {
+        others.sort_by(|x, y| y.1.cmp(&x.1).then_with(|| x.0.cmp(y.0)));
+        others.truncate(MAX_RELATED_PER_PRODUCT);
+        rows.extend(others.into_iter().map(|(other_id, orders)| CoOccurrence {
+            product_id: product_id.to_string(),
+            other_id: other_id.to_string(),
+            orders,
+        }));
+    }
+    rows
+}
+
+/// Rebuilds the co-occurrence table from the order history every
+/// `interval`. Ticks are skipped while the service is still starting.
+pub fn spawn_recommendation_builder(
+    service: Arc<RwLock<Option<ProductService>>>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            let guard = service.read().await;
+            let Some(service) = guard.as_ref() else {
+                continue;
+            };
+
+            match service.rebuild_recommendations().await {
+                Ok(0) => {}
+                Ok(pairs) => info!("🛒 Rebuilt recommendations from {} product pairs", pairs),
+                Err(err) => warn!("Failed to rebuild recommendations: {}", err),
+            }
+        }
+    });
+}
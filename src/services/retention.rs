@@ -0,0 +1,298 @@
+use crate::{
+    models::admin_model::{RetentionReport, RetentionRuleReport},
+    repositories::connection::DbConnection,
+    telemetry::query_metrics::traced_query,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+#[derive(Error, Debug)]
+pub enum RetentionConfigError {
+    #[error("Invalid RETENTION_DAYS entry '{0}', expected rule=days")]
+    InvalidEntry(String),
+
+    #[error("Unknown retention rule '{rule}', expected one of: {known}")]
+    UnknownRule { rule: String, known: String },
+}
+
+/// A kind of record that stops being useful after a while
+#[derive(Debug)]
+pub struct RetentionRule {
+    pub name: &'static str,
+    /// Deletes the records older than `$cutoff` and returns them
+    pub statement: &'static str,
+    /// Age in days purged without configuration; `None` keeps the records
+    /// until a retention is set
+    pub default_days: Option<u64>,
+}
+
+/// Records the user service purges. Signup attempts only matter to the
+/// hourly velocity limits; fraud hits and the event history of deleted
+/// users are audit data and kept unless configured.
+pub const USER_RETENTION_RULES: &[RetentionRule] = &[
+    RetentionRule {
+        name: "signup_attempt",
+        statement: "DELETE signup_attempt WHERE attempted_at < $cutoff RETURN BEFORE",
+        default_days: Some(7),
+    },
+    RetentionRule {
+        name: "fraud_hit",
+        statement: "DELETE fraud_hit WHERE recorded_at < $cutoff RETURN BEFORE",
+        default_days: None,
+    },
+    RetentionRule {
+        name: "deleted_user_events",
+        statement: "DELETE user_event WHERE user_id INSIDE \
+                    (SELECT VALUE user_id FROM user_event \
+                     WHERE kind = 'UserDeleted' AND recorded_at < $cutoff) \
+                    RETURN BEFORE",
+        default_days: None,
+    },
+];
+
+/// Records the product service purges. Applied scheduled price changes are
+/// already reflected in the price history, which is kept unless configured.
+/// Finished jobs only matter until their results are collected.
+pub const PRODUCT_RETENTION_RULES: &[RetentionRule] = &[
+    RetentionRule {
+        name: "applied_price_change",
+        statement: "DELETE scheduled_price_change \
+                    WHERE status = 'applied' AND effective_at < $cutoff RETURN BEFORE",
+        default_days: Some(30),
+    },
+    RetentionRule {
+        name: "price_history",
+        statement: "DELETE price_history WHERE changed_at < $cutoff RETURN BEFORE",
+        default_days: None,
+    },
+    RetentionRule {
+        name: "stock_movement",
+        statement: "DELETE stock_movement WHERE moved_at < $cutoff RETURN BEFORE",
+        default_days: None,
+    },
+    RetentionRule {
+        name: "order_history",
+        statement: "DELETE order_history WHERE ordered_at < $cutoff RETURN BEFORE",
+        default_days: None,
+    },
+    RetentionRule {
+        name: "finished_job",
+        statement: "DELETE job WHERE status INSIDE ['succeeded', 'failed'] \
+                    AND finished_at < $cutoff RETURN BEFORE",
+        default_days: Some(7),
+    },
+];
+
+/// A service whose database the retention job cleans up
+pub trait RetentionTarget: Send + Sync + 'static {
+    fn connection(&self) -> Arc<DbConnection>;
+
+    /// Purging is a write, so it pauses while this is true
+    fn is_read_only(&self) -> bool;
+}
+
+#[derive(Debug)]
+struct RuleState {
+    rule: &'static RetentionRule,
+    max_age_days: Option<u64>,
+    last_purged: AtomicU64,
+    total_purged: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+/// Which records a service purges and after how long, with counters for
+/// each run.
+///
+/// Each rule's age comes from `RETENTION_DAYS` when listed there, otherwise
+/// from its default; `0` keeps the records forever.
+#[derive(Debug)]
+pub struct RetentionPolicy {
+    rules: Vec<RuleState>,
+    interval: Option<Duration>,
+    runs: AtomicU64,
+    last_run_at: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl RetentionPolicy {
+    /// Reads `RETENTION_DAYS` (`rule=days,...`) and
+    /// `RETENTION_INTERVAL_SECS` (default 3600, `0` disables the job)
+    pub fn from_env(rules: &'static [RetentionRule]) -> Result<Self, RetentionConfigError> {
+        let mut overrides = HashMap::new();
+        let list = std::env::var("RETENTION_DAYS").unwrap_or_default();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, days) = entry
+                .split_once('=')
+                .and_then(|(name, days)| Some((name.trim(), days.trim().parse::<u64>().ok()?)))
+                .ok_or_else(|| RetentionConfigError::InvalidEntry(entry.to_string()))?;
+            if !rules.iter().any(|rule| rule.name == name) {
+                return Err(RetentionConfigError::UnknownRule {
+                    rule: name.to_string(),
+                    known: rules
+                        .iter()
+                        .map(|rule| rule.name)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                });
+            }
+            overrides.insert(name.to_string(), days);
+        }
+
+        let interval_secs = std::env::var("RETENTION_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+        Ok(Self {
+            rules: rules
+                .iter()
+                .map(|rule| RuleState {
+                    rule,
+                    max_age_days: overrides
+                        .get(rule.name)
+                        .copied()
+                        .or(rule.default_days)
+                        .filter(|days| *days > 0),
+                    last_purged: AtomicU64::new(0),
+                    total_purged: AtomicU64::new(0),
+                    last_error: Mutex::new(None),
+                })
+                .collect(),
+            interval: (interval_secs > 0).then(|| Duration::from_secs(interval_secs)),
+            runs: AtomicU64::new(0),
+            last_run_at: Mutex::new(None),
+        })
+    }
+
+    /// How often the job runs; `None` when it is disabled or has nothing
+    /// to purge
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+            .filter(|_| self.rules.iter().any(|state| state.max_age_days.is_some()))
+    }
+
+    /// Active rules for the startup log, e.g. `signup_attempt=7d`
+    pub fn describe(&self) -> String {
+        self.rules
+            .iter()
+            .filter_map(|state| {
+                state
+                    .max_age_days
+                    .map(|days| format!("{}={}d", state.rule.name, days))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Purges every rule's expired records and returns how many went. A
+    /// failing rule is logged and does not stop the others.
+    pub async fn run(&self, db: &DbConnection) -> u64 {
+        let now = Utc::now();
+        let mut purged_total = 0;
+        for state in &self.rules {
+            let Some(days) = state.max_age_days else {
+                continue;
+            };
+            let cutoff = now - chrono::Duration::days(days as i64);
+            let error = match purge(db, state.rule, cutoff).await {
+                Ok(purged) => {
+                    state.last_purged.store(purged, Ordering::Relaxed);
+                    state.total_purged.fetch_add(purged, Ordering::Relaxed);
+                    purged_total += purged;
+                    None
+                }
+                Err(err) => {
+                    warn!("Failed to purge expired {}: {}", state.rule.name, err);
+                    state.last_purged.store(0, Ordering::Relaxed);
+                    Some(err.to_string())
+                }
+            };
+            *state
+                .last_error
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = error;
+        }
+
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        *self
+            .last_run_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(now);
+        purged_total
+    }
+
+    pub fn report(&self) -> RetentionReport {
+        RetentionReport {
+            interval_secs: self.interval().map(|interval| interval.as_secs()),
+            runs: self.runs.load(Ordering::Relaxed),
+            last_run_at: *self
+                .last_run_at
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+            rules: self
+                .rules
+                .iter()
+                .map(|state| RetentionRuleReport {
+                    rule: state.rule.name.to_string(),
+                    max_age_days: state.max_age_days,
+                    last_purged: state.last_purged.load(Ordering::Relaxed),
+                    total_purged: state.total_purged.load(Ordering::Relaxed),
+                    last_error: state
+                        .last_error
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+async fn purge(
+    db: &DbConnection,
+    rule: &RetentionRule,
+    cutoff: DateTime<Utc>,
+) -> anyhow::Result<u64> {
+    let handle = db.handle()?;
+    let purged: Vec<serde_json::Value> = traced_query(rule.statement, |sql| {
+        handle.query(sql).bind(("cutoff", cutoff))
+    })
+    .await?
+    .take(0)?;
+    Ok(purged.len() as u64)
+}
+
+/// Runs `policy` every `interval`. Ticks are skipped while the service is
+/// still starting or read-only.
+pub fn spawn_retention_job<S: RetentionTarget>(
+    service: Arc<RwLock<Option<S>>>,
+    policy: Arc<RetentionPolicy>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            let guard = service.read().await;
+            let Some(service) = guard.as_ref() else {
+                continue;
+            };
+            if service.is_read_only() {
+                continue;
+            }
+
+            match policy.run(&service.connection()).await {
+                0 => {}
+                purged => info!("🧹 Purged {} expired records", purged),
+            }
+        }
+    });
+}
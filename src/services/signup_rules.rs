@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// JSON-RPC error code for signups over a velocity limit; retry later
+pub const SIGNUP_RATE_LIMITED_CODE: i32 = -32014;
+/// JSON-RPC error code for signups refused outright, e.g. from a disposable
+/// email domain
+pub const SIGNUP_REJECTED_CODE: i32 = -32015;
+
+/// Velocity limits count signups over this trailing window
+pub const VELOCITY_WINDOW_SECS: u64 = 3600;
+
+/// Throwaway-mailbox providers blocked even without a configured list
+const DISPOSABLE_DOMAINS: &[&str] = &[
+    "10minutemail.com",
+    "discard.email",
+    "dispostable.com",
+    "fakeinbox.com",
+    "getnada.com",
+    "guerrillamail.com",
+    "mailinator.com",
+    "maildrop.cc",
+    "mintemail.com",
+    "sharklasers.com",
+    "temp-mail.org",
+    "tempmail.com",
+    "throwawaymail.com",
+    "trashmail.com",
+    "yopmail.com",
+];
+
+#[derive(Error, Debug)]
+pub enum SignupRulesError {
+    #[error("Invalid {name} '{value}', expected a non-negative number")]
+    InvalidLimit { name: &'static str, value: String },
+
+    #[error("Failed to read SIGNUP_BLOCKED_DOMAINS_FILE {path}: {source}")]
+    BlocklistFile {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Which anti-fraud rule stopped a signup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignupRule {
+    DisposableDomain,
+    DomainVelocity,
+    IpVelocity,
+}
+
+impl SignupRule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SignupRule::DisposableDomain => "disposable_domain",
+            SignupRule::DomainVelocity => "domain_velocity",
+            SignupRule::IpVelocity => "ip_velocity",
+        }
+    }
+}
+
+/// Anti-fraud rules evaluated by `create_user`.
+///
+/// Signups from a blocked domain (or any subdomain of one) are rejected.
+/// Accepted signups are recorded with their email domain and the caller's
+/// IP, and a signup is rate limited once its domain or IP already made the
+/// configured number within the last hour. The counts live in the user
+/// database, so every replica enforces the same limits.
+#[derive(Debug, Clone, Default)]
+pub struct SignupRules {
+    /// `None` disables the per-domain limit
+    pub max_per_domain: Option<usize>,
+    /// `None` disables the per-IP limit
+    pub max_per_ip: Option<usize>,
+    pub blocked_domains: HashSet<String>,
+}
+
+impl SignupRules {
+    /// Reads `SIGNUP_MAX_PER_DOMAIN_PER_HOUR` and
+    /// `SIGNUP_MAX_PER_IP_PER_HOUR` (unset or `0` disables the limit), and
+    /// adds `SIGNUP_BLOCKED_DOMAINS` (comma separated) and the lines of
+    /// `SIGNUP_BLOCKED_DOMAINS_FILE` to the built-in disposable domains.
+    /// `SIGNUP_ALLOW_DISPOSABLE=true` drops the built-in list.
+    pub fn from_env() -> Result<Self, SignupRulesError> {
+        let limit = |name: &'static str| match std::env::var(name) {
+            Ok(value) => value
+                .trim()
+                .parse::<usize>()
+                .map(|limit| (limit > 0).then_some(limit))
+                .map_err(|_| SignupRulesError::InvalidLimit { name, value }),
+            Err(_) => Ok(None),
+        };
+
+        let allow_disposable = std::env::var("SIGNUP_ALLOW_DISPOSABLE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        let mut blocked_domains: HashSet<String> = if allow_disposable {
+            HashSet::new()
+        } else {
+            DISPOSABLE_DOMAINS.iter().map(|d| d.to_string()).collect()
+        };
+        if let Ok(list) = std::env::var("SIGNUP_BLOCKED_DOMAINS") {
+            blocked_domains.extend(list.split(',').filter_map(normalize_domain));
+        }
+        if let Ok(path) = std::env::var("SIGNUP_BLOCKED_DOMAINS_FILE") {
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|source| SignupRulesError::BlocklistFile { path, source })?;
+            blocked_domains.extend(
+                contents
+                    .lines()
+                    .filter(|line| !line.trim_start().starts_with('#'))
+                    .filter_map(normalize_domain),
+            );
+        }
+
+        Ok(Self {
+            max_per_domain: limit("SIGNUP_MAX_PER_DOMAIN_PER_HOUR")?,
+            max_per_ip: limit("SIGNUP_MAX_PER_IP_PER_HOUR")?,
+            blocked_domains,
+        })
+    }
+
+    /// Whether `domain` or one of its parent domains is blocked
+    pub fn is_blocked(&self, domain: &str) -> bool {
+        let mut candidate = domain;
+        loop {
+            if self.blocked_domains.contains(candidate) {
+                return true;
+            }
+            match candidate.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => candidate = parent,
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// The lowercased domain of an email address
+pub fn email_domain(email: &str) -> Option<String> {
+    email
+        .rsplit_once('@')
+        .and_then(|(_, domain)| normalize_domain(domain))
+}
+
+fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+    (!domain.is_empty()).then_some(domain)
+}
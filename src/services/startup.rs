@@ -0,0 +1,222 @@
+use crate::gateway::self_test::SelfTestReport;
+use crate::repositories::connection::{ConnectionError, DatabaseUnavailable};
+use std::error::Error;
+use std::fmt::Display;
+use std::future::Future;
+use std::process::ExitCode;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+/// JSON-RPC error code returned while a lazily started service is still
+/// initializing its repository
+pub const SERVICE_STARTING_CODE: i32 = -32010;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Command-line flag that makes a binary validate its configuration, print
+/// the report and exit without starting
+pub const CHECK_CONFIG_FLAG: &str = "--check-config";
+
+/// Whether the binary was started with [`CHECK_CONFIG_FLAG`]
+pub fn check_config_requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == CHECK_CONFIG_FLAG)
+}
+
+/// A listening socket that could not be opened, kept apart from other I/O
+/// errors so the address can be named in the diagnosis
+#[derive(Error, Debug)]
+#[error("Failed to listen on {addr}: {source}")]
+pub struct BindFailed {
+    pub addr: String,
+    #[source]
+    pub source: std::io::Error,
+}
+
+/// Why a binary could not start, each with its own exit code so process
+/// supervisors and scripts can react without parsing logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupFailure {
+    /// Exit code 1
+    Other,
+    /// Exit code 2: a setting is missing, malformed or points to an
+    /// unreadable file
+    InvalidConfig,
+    /// Exit code 3: a listening port is taken or may not be used
+    PortUnavailable,
+    /// Exit code 4: the database could not be reached or initialized
+    DatabaseUnavailable,
+}
+
+impl StartupFailure {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            StartupFailure::Other => 1,
+            StartupFailure::InvalidConfig => 2,
+            StartupFailure::PortUnavailable => 3,
+            StartupFailure::DatabaseUnavailable => 4,
+        }
+    }
+
+    /// Works out what went wrong from the error chain, with a hint on how
+    /// to fix it. `config` is the binary's configuration check, which tells
+    /// configuration errors apart from everything else.
+    pub fn diagnose(
+        err: &(dyn Error + 'static),
+        config: impl FnOnce() -> SelfTestReport,
+    ) -> (Self, String) {
+        let chain = || std::iter::successors(Some(err), |&err| err.source());
+
+        if let Some(bind) = chain().find_map(|err| err.downcast_ref::<BindFailed>()) {
+            let hint = match bind.source.kind() {
+                std::io::ErrorKind::AddrInUse => format!(
+                    "{} is already in use. Stop the other process listening there \
+                     (`lsof -i :{}` finds it) or run this one on another address.",
+                    bind.addr,
+                    bind.addr.rsplit(':').next().unwrap_or_default()
+                ),
+                std::io::ErrorKind::PermissionDenied => format!(
+                    "Not allowed to listen on {}. Ports below 1024 need extra privileges.",
+                    bind.addr
+                ),
+                std::io::ErrorKind::AddrNotAvailable => format!(
+                    "{} is not an address of this host. Check the listen address.",
+                    bind.addr
+                ),
+                _ => format!("Could not listen on {}: {}", bind.addr, bind.source),
+            };
+            return (StartupFailure::PortUnavailable, hint);
+        }
+
+        let report = config();
+        if !report.ok {
+            let failed: Vec<String> = report
+                .checks
+                .iter()
+                .filter(|check| !check.ok)
+                .map(|check| format!("{}: {}", check.name, check.detail))
+                .collect();
+            return (
+                StartupFailure::InvalidConfig,
+                format!(
+                    "Fix these settings, then run with {} to confirm: {}",
+                    CHECK_CONFIG_FLAG,
+                    failed.join("; ")
+                ),
+            );
+        }
+
+        let database = chain().any(|err| {
+            err.is::<surrealdb::Error>()
+                || err.is::<Box<surrealdb::Error>>()
+                || err.is::<ConnectionError>()
+                || err.is::<DatabaseUnavailable>()
+        });
+        if database {
+            return (
+                StartupFailure::DatabaseUnavailable,
+                "Check that the configured database endpoint is reachable and the credentials \
+                 are right, or set SERVICE_STARTUP_MODE=lazy to start anyway and keep retrying \
+                 in the background."
+                    .to_string(),
+            );
+        }
+
+        (StartupFailure::Other, err.to_string())
+    }
+}
+
+/// Turns the result of a binary's startup and run into its exit code,
+/// logging the diagnosis when it failed
+pub fn exit_code_for<E>(
+    name: &str,
+    result: Result<(), E>,
+    config: impl FnOnce() -> SelfTestReport,
+) -> ExitCode
+where
+    E: AsRef<dyn Error + Send + Sync + 'static>,
+{
+    let Err(err) = result else {
+        return ExitCode::SUCCESS;
+    };
+    let err: &(dyn Error + 'static) = err.as_ref();
+    let (failure, hint) = StartupFailure::diagnose(err, config);
+    error!(
+        "❌ {} failed to start: {} (exit code {})",
+        name,
+        err,
+        failure.exit_code()
+    );
+    if failure != StartupFailure::Other {
+        error!("💡 {}", hint);
+    }
+    ExitCode::from(failure.exit_code())
+}
+
+/// Prints the configuration report as JSON for [`CHECK_CONFIG_FLAG`] and
+/// returns the exit code: `0` when valid, `2` otherwise
+pub fn print_config_report(report: &SelfTestReport) -> ExitCode {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{}", json),
+        Err(err) => eprintln!("Failed to print the configuration report: {}", err),
+    }
+    if report.ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(StartupFailure::InvalidConfig.exit_code())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupMode {
+    /// Initialize the repository before the RPC server starts (default)
+    Eager,
+    /// Start the RPC server immediately and initialize in the background
+    Lazy,
+}
+
+impl StartupMode {
+    /// Reads `SERVICE_STARTUP_MODE` (`eager` or `lazy`)
+    pub fn from_env() -> Self {
+        match std::env::var("SERVICE_STARTUP_MODE").as_deref() {
+            Ok("lazy") => StartupMode::Lazy,
+            _ => StartupMode::Eager,
+        }
+    }
+}
+
+/// Runs `init` until it succeeds, doubling the delay between attempts from
+/// 500ms up to `SERVICE_INIT_MAX_BACKOFF_SECS` (default 30s).
+pub async fn init_with_backoff<T, E, F, Fut>(name: &str, mut init: F) -> T
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let max_backoff = Duration::from_secs(
+        std::env::var("SERVICE_INIT_MAX_BACKOFF_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30),
+    );
+    let mut backoff = INITIAL_BACKOFF.min(max_backoff);
+    let mut attempt = 1;
+
+    loop {
+        match init().await {
+            Ok(value) => {
+                info!("{} initialized after {} attempt(s)", name, attempt);
+                return value;
+            }
+            Err(err) => {
+                warn!(
+                    "{} initialization attempt {} failed: {}; retrying in {:?}",
+                    name, attempt, err, backoff
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(max_backoff);
+                attempt += 1;
+            }
+        }
+    }
+}
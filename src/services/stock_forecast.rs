@@ -0,0 +1,124 @@
+use crate::models::inventory_model::{
+    LocationForecast, LocationStock, MovementKind, StockForecast, StockMovement,
+};
+use crate::models::quantity_model::Quantity;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+/// Days of ledger history a forecast averages over
+pub const FORECAST_LOOKBACK_DAYS: i64 = 28;
+
+/// Longest horizon `forecast_stock` projects over
+pub const MAX_FORECAST_HORIZON_DAYS: u32 = 365;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Consumption and time to stock-out at a constant daily rate
+struct Projection {
+    rate: f64,
+    projected_stock: Quantity,
+    stock_out: Option<DateTime<Utc>>,
+}
+
+fn project(
+    stock: Quantity,
+    consumed: Quantity,
+    observed_days: f64,
+    horizon_days: u32,
+    now: DateTime<Utc>,
+) -> Projection {
+    let rate = if observed_days > 0.0 {
+        consumed.to_f64() / observed_days
+    } else {
+        0.0
+    };
+    let projected_stock =
+        Quantity::from_f64((stock.to_f64() - rate * f64::from(horizon_days)).max(0.0))
+            .unwrap_or_default();
+    let stock_out = (rate > 0.0).then(|| {
+        let days_left = stock.max(Quantity::ZERO).to_f64() / rate;
+        now + Duration::seconds((days_left * SECONDS_PER_DAY) as i64)
+    });
+    Projection {
+        rate,
+        projected_stock,
+        stock_out,
+    }
+}
+
+/// Projects a product's stock `horizon_days` ahead from its recent ledger
+/// entries, at the average daily consumption over the observed period.
+///
+/// The period runs from the first movement in `movements` to `now`, and
+/// at least a day, so a product only tracked for a few days is not diluted
+/// by the days before it. Only decreases made by stock adjustments count as
+/// consumption: deliveries raise stock without offsetting it, and transfers
+/// move stock without consuming it.
+pub fn forecast_stock(
+    product_id: &str,
+    stock: &[LocationStock],
+    movements: &[StockMovement],
+    horizon_days: u32,
+    now: DateTime<Utc>,
+) -> StockForecast {
+    let observed_days = movements
+        .iter()
+        .map(|movement| movement.moved_at)
+        .min()
+        .map_or(0.0, |first| {
+            ((now - first).num_seconds() as f64 / SECONDS_PER_DAY).max(1.0)
+        });
+
+    let mut consumed: HashMap<&str, Quantity> = HashMap::new();
+    for movement in movements {
+        if movement.kind == MovementKind::Adjustment && movement.change.is_negative() {
+            *consumed.entry(movement.location.as_str()).or_default() += movement.change.abs();
+        }
+    }
+
+    let mut stock: Vec<&LocationStock> = stock.iter().collect();
+    stock.sort_by(|a, b| a.location.cmp(&b.location));
+    let locations: Vec<LocationForecast> = stock
+        .into_iter()
+        .map(|level| {
+            let consumed = consumed
+                .get(level.location.as_str())
+                .copied()
+                .unwrap_or_default();
+            let projection = project(level.quantity, consumed, observed_days, horizon_days, now);
+            LocationForecast {
+                location: level.location.clone(),
+                current_stock: level.quantity,
+                average_daily_consumption: projection.rate,
+                projected_stock: projection.projected_stock,
+                predicted_stock_out: projection.stock_out,
+            }
+        })
+        .collect();
+
+    let current_stock = locations
+        .iter()
+        .map(|location| location.current_stock)
+        .sum();
+    let total = project(
+        current_stock,
+        consumed.values().copied().sum(),
+        observed_days,
+        horizon_days,
+        now,
+    );
+    let horizon_end = now + Duration::days(i64::from(horizon_days));
+
+    StockForecast {
+        product_id: product_id.to_string(),
+        horizon_days,
+        observed_days,
+        current_stock,
+        average_daily_consumption: total.rate,
+        projected_stock: total.projected_stock,
+        predicted_stock_out: total.stock_out,
+        stock_out_within_horizon: total.stock_out.is_some_and(|at| at <= horizon_end),
+        locations,
+        generated_at: now,
+    }
+}
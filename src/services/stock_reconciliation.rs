@@ -0,0 +1,227 @@
+use crate::models::inventory_model::{
+    DiscrepancyKind, Location, OrderActivity, ProductStockTotal, StockDiscrepancy, StockLevel,
+    DEFAULT_LOCATION,
+};
+use crate::models::quantity_model::Quantity;
+use crate::services::product_service::ProductService;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// Reads `STOCK_RECONCILE_INTERVAL_SECS` (default 900, `0` disables the
+/// scheduled check)
+pub fn reconcile_interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("STOCK_RECONCILE_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(900);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Cross-checks products' totals against their per-location stock rows,
+/// and orders and returns against the products and the `stock_movement`
+/// ledger.
+///
+/// Products without stock rows predate per-location tracking, so only
+/// their own total is checked. Discrepancies are sorted by product, then
+/// location.
+pub fn find_discrepancies(
+    totals: &[ProductStockTotal],
+    levels: &[StockLevel],
+    locations: &[Location],
+    activity: &OrderActivity,
+) -> Vec<StockDiscrepancy> {
+    let known_locations: HashSet<&str> = locations
+        .iter()
+        .map(|location| location.code.as_str())
+        .chain(std::iter::once(DEFAULT_LOCATION))
+        .collect();
+    let mut rows_by_product: HashMap<&str, Vec<&StockLevel>> = HashMap::new();
+    for level in levels {
+        rows_by_product
+            .entry(level.product_id.as_str())
+            .or_default()
+            .push(level);
+    }
+
+    let mut discrepancies = Vec::new();
+    let mut products = HashMap::new();
+    for total in totals {
+        let product_id = total.id.id.to_raw();
+        let rows = rows_by_product
+            .get(product_id.as_str())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if total.stock_quantity.is_negative() {
+            discrepancies.push(discrepancy(
+                DiscrepancyKind::Oversold,
+                &product_id,
+                None,
+                None,
+                total.stock_quantity,
+            ));
+        }
+        if !rows.is_empty() {
+            let sum: Quantity = rows.iter().map(|row| row.quantity).sum();
+            if sum != total.stock_quantity {
+                discrepancies.push(discrepancy(
+                    DiscrepancyKind::TotalMismatch,
+                    &product_id,
+                    None,
+                    Some(sum),
+                    total.stock_quantity,
+                ));
+            }
+        }
+        products.insert(product_id, total.stock_quantity);
+    }
+
+    for level in levels {
+        let location = Some(level.location.as_str());
+        if !products.contains_key(&level.product_id) {
+            discrepancies.push(discrepancy(
+                DiscrepancyKind::OrphanedStock,
+                &level.product_id,
+                location,
+                None,
+                level.quantity,
+            ));
+        }
+        if !known_locations.contains(level.location.as_str()) {
+            discrepancies.push(discrepancy(
+                DiscrepancyKind::UnknownLocation,
+                &level.product_id,
+                location,
+                None,
+                level.quantity,
+            ));
+        }
+        if level.quantity.is_negative() {
+            discrepancies.push(discrepancy(
+                DiscrepancyKind::Oversold,
+                &level.product_id,
+                location,
+                None,
+                level.quantity,
+            ));
+        }
+    }
+
+    let ordered: BTreeSet<&String> = activity
+        .awaiting_shipment
+        .keys()
+        .chain(activity.shipped.keys())
+        .collect();
+    for product_id in ordered {
+        let awaiting = quantity_of(&activity.awaiting_shipment, product_id);
+        let shipped = quantity_of(&activity.shipped, product_id);
+        let Some(&stock) = products.get(product_id) else {
+            discrepancies.push(discrepancy(
+                DiscrepancyKind::OrphanedOrderLine,
+                product_id,
+                None,
+                None,
+                awaiting + shipped,
+            ));
+            continue;
+        };
+        if awaiting > stock {
+            discrepancies.push(discrepancy(
+                DiscrepancyKind::Overcommitted,
+                product_id,
+                None,
+                Some(awaiting),
+                stock,
+            ));
+        }
+        // Sales lower stock through adjustments; shipping never does, so
+        // every shipped unit must have been adjusted out at some point
+        let removed = quantity_of(&activity.removed, product_id);
+        if shipped > removed {
+            discrepancies.push(discrepancy(
+                DiscrepancyKind::UnrecordedShipment,
+                product_id,
+                None,
+                Some(shipped),
+                removed,
+            ));
+        }
+    }
+
+    let returned: BTreeSet<&String> = activity
+        .returned
+        .keys()
+        .chain(activity.restocked.keys())
+        .collect();
+    for product_id in returned {
+        let expected = quantity_of(&activity.returned, product_id);
+        let restocked = quantity_of(&activity.restocked, product_id);
+        if expected != restocked {
+            discrepancies.push(discrepancy(
+                DiscrepancyKind::RestockMismatch,
+                product_id,
+                None,
+                Some(expected),
+                restocked,
+            ));
+        }
+    }
+
+    discrepancies.sort_by(|a, b| (&a.product_id, &a.location).cmp(&(&b.product_id, &b.location)));
+    discrepancies
+}
+
+fn quantity_of(quantities: &HashMap<String, Quantity>, product_id: &str) -> Quantity {
+    quantities.get(product_id).copied().unwrap_or_default()
+}
+
+fn discrepancy(
+    kind: DiscrepancyKind,
+    product_id: &str,
+    location: Option<&str>,
+    expected: Option<Quantity>,
+    actual: Quantity,
+) -> StockDiscrepancy {
+    StockDiscrepancy {
+        kind,
+        product_id: product_id.to_string(),
+        location: location.map(str::to_string),
+        expected,
+        actual,
+    }
+}
+
+/// Reconciles the stock every `interval` and warns when discrepancies
+/// appear. Ticks are skipped while the service is still starting.
+pub fn spawn_stock_reconciler(service: Arc<RwLock<Option<ProductService>>>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            let guard = service.read().await;
+            let Some(service) = guard.as_ref() else {
+                continue;
+            };
+
+            match service.run_stock_reconciliation().await {
+                Ok(report) if report.discrepancies.is_empty() => {}
+                Ok(report) => {
+                    let mut by_kind: BTreeMap<String, usize> = BTreeMap::new();
+                    for found in &report.discrepancies {
+                        *by_kind.entry(format!("{:?}", found.kind)).or_default() += 1;
+                    }
+                    warn!(
+                        "🧮 Stock reconciliation found {} discrepancies: {:?}",
+                        report.discrepancies.len(),
+                        by_kind
+                    );
+                }
+                Err(err) => warn!("Failed to reconcile stock: {}", err),
+            }
+        }
+    });
+}
@@ -0,0 +1,365 @@
+use crate::{
+    models::admin_model::{StorageLevel, StorageReport, TableStorageReport},
+    repositories::connection::DbConnection,
+    services::retention::RetentionTarget,
+    telemetry::query_metrics::traced_query,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+const DEFAULT_INTERVAL_SECS: u64 = 30;
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum StorageCapsError {
+    #[error("Invalid {name} '{value}', expected a number of megabytes")]
+    InvalidSize { name: &'static str, value: String },
+
+    #[error("Invalid DB_TABLE_CAPS entry '{0}', expected table=soft:hard")]
+    InvalidEntry(String),
+
+    #[error("Soft cap for {0} is above its hard cap")]
+    SoftAboveHard(String),
+}
+
+/// Returned instead of writing a new record once a hard cap is reached
+#[derive(Error, Debug, Clone)]
+pub enum StorageFull {
+    #[error(
+        "Storage full: the database holds about {approx_mb} MB, at its hard cap of {cap_mb} MB"
+    )]
+    Database { approx_mb: u64, cap_mb: u64 },
+
+    #[error("Storage full: table '{table}' holds {records} records, at its hard cap of {cap}")]
+    Table {
+        table: String,
+        records: u64,
+        cap: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Caps {
+    soft: Option<u64>,
+    hard: Option<u64>,
+}
+
+impl Caps {
+    fn level(&self, value: u64) -> StorageLevel {
+        if self.hard.is_some_and(|hard| value >= hard) {
+            StorageLevel::Full
+        } else if self.soft.is_some_and(|soft| value >= soft) {
+            StorageLevel::Soft
+        } else {
+            StorageLevel::Ok
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DatabaseInfo {
+    #[serde(default)]
+    tables: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TableSize {
+    records: u64,
+    /// `math::sum` may come back as a float
+    #[serde(default)]
+    approx_bytes: f64,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    measured_at: Option<DateTime<Utc>>,
+    /// Table -> (records, approximate bytes)
+    tables: HashMap<String, (u64, u64)>,
+    last_error: Option<String>,
+    level: StorageLevel,
+}
+
+impl Usage {
+    fn approx_bytes(&self) -> u64 {
+        self.tables.values().map(|(_, bytes)| bytes).sum()
+    }
+}
+
+/// Soft and hard caps on how much a service keeps in its database.
+///
+/// A `mem://` database lives in the service's own heap, so an unbounded
+/// table can take the whole process down. The monitor measures every
+/// table's record count and approximate size on an interval. Past a soft
+/// cap it logs a warning; at a hard cap the service rejects writes that
+/// add records with [`StorageFull`] until the next measurement finds room.
+/// Updates and deletes still go through, so space can be freed.
+#[derive(Debug)]
+pub struct StorageMonitor {
+    database: Caps,
+    tables: HashMap<String, Caps>,
+    interval: Option<Duration>,
+    usage: Mutex<Usage>,
+}
+
+impl StorageMonitor {
+    /// Reads `DB_SOFT_CAP_MB` / `DB_HARD_CAP_MB` (approximate size of the
+    /// whole database), `DB_TABLE_CAPS` (`table=soft:hard` record counts,
+    /// either side may be empty) and `DB_STORAGE_CHECK_INTERVAL_SECS`
+    /// (default 30, `0` disables the caps)
+    pub fn from_env() -> Result<Self, StorageCapsError> {
+        let megabytes = |name: &'static str| match std::env::var(name) {
+            Ok(value) => value
+                .trim()
+                .parse::<u64>()
+                .map(|mb| Some(mb * BYTES_PER_MB))
+                .map_err(|_| StorageCapsError::InvalidSize { name, value }),
+            Err(_) => Ok(None),
+        };
+        let database = Caps {
+            soft: megabytes("DB_SOFT_CAP_MB")?,
+            hard: megabytes("DB_HARD_CAP_MB")?,
+        };
+        check_order("the database", database)?;
+
+        let mut tables = HashMap::new();
+        let list = std::env::var("DB_TABLE_CAPS").unwrap_or_default();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let invalid = || StorageCapsError::InvalidEntry(entry.to_string());
+            let (table, caps) = entry.split_once('=').ok_or_else(invalid)?;
+            let (soft, hard) = caps.split_once(':').ok_or_else(invalid)?;
+            let count = |value: &str| match value.trim() {
+                "" => Ok(None),
+                value => value.parse::<u64>().map(Some).map_err(|_| invalid()),
+            };
+            let caps = Caps {
+                soft: count(soft)?,
+                hard: count(hard)?,
+            };
+            let table = table.trim().to_string();
+            check_order(&table, caps)?;
+            tables.insert(table, caps);
+        }
+
+        let interval_secs = std::env::var("DB_STORAGE_CHECK_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+        Ok(Self {
+            database,
+            tables,
+            interval: (interval_secs > 0).then(|| Duration::from_secs(interval_secs)),
+            usage: Mutex::new(Usage::default()),
+        })
+    }
+
+    /// How often storage is measured; `None` when disabled or no caps are set
+    pub fn interval(&self) -> Option<Duration> {
+        let any_caps =
+            self.database.soft.is_some() || self.database.hard.is_some() || !self.tables.is_empty();
+        self.interval.filter(|_| any_caps)
+    }
+
+    /// Caps for the startup log, e.g. `database=256MB/512MB, user=-/100000`
+    pub fn describe(&self) -> String {
+        let show = |value: Option<u64>, unit: u64, suffix: &str| {
+            value.map_or("-".to_string(), |value| {
+                format!("{}{}", value / unit, suffix)
+            })
+        };
+        let mut caps = Vec::new();
+        if self.database.soft.is_some() || self.database.hard.is_some() {
+            caps.push(format!(
+                "database={}/{}",
+                show(self.database.soft, BYTES_PER_MB, "MB"),
+                show(self.database.hard, BYTES_PER_MB, "MB")
+            ));
+        }
+        let mut tables: Vec<_> = self.tables.iter().collect();
+        tables.sort_by(|a, b| a.0.cmp(b.0));
+        for (table, table_caps) in tables {
+            caps.push(format!(
+                "{}={}/{}",
+                table,
+                show(table_caps.soft, 1, ""),
+                show(table_caps.hard, 1, "")
+            ));
+        }
+        caps.join(", ")
+    }
+
+    /// Fails with [`StorageFull`] when the database or `table` had reached
+    /// its hard cap at the latest measurement
+    pub fn check_write(&self, table: &str) -> Result<(), StorageFull> {
+        let usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        let approx_bytes = usage.approx_bytes();
+        if let Some(cap) = self.database.hard.filter(|cap| approx_bytes >= *cap) {
+            return Err(StorageFull::Database {
+                approx_mb: approx_bytes / BYTES_PER_MB,
+                cap_mb: cap / BYTES_PER_MB,
+            });
+        }
+        let records = usage.tables.get(table).map_or(0, |(records, _)| *records);
+        match self.tables.get(table).and_then(|caps| caps.hard) {
+            Some(cap) if records >= cap => Err(StorageFull::Table {
+                table: table.to_string(),
+                records,
+                cap,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Measures every table and logs when the overall level changes
+    pub async fn measure(&self, db: &DbConnection) {
+        let measured = measure_tables(db).await;
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        match measured {
+            Ok(tables) => {
+                usage.tables = tables;
+                usage.measured_at = Some(Utc::now());
+                usage.last_error = None;
+            }
+            Err(err) => {
+                warn!("Failed to measure database storage: {}", err);
+                usage.last_error = Some(err.to_string());
+                return;
+            }
+        }
+
+        let report = self.report_for(&usage);
+        if report.level == usage.level {
+            return;
+        }
+        usage.level = report.level;
+        let over: Vec<String> = report
+            .tables
+            .iter()
+            .filter(|table| table.level != StorageLevel::Ok)
+            .map(|table| format!("{} ({} records)", table.table, table.records))
+            .collect();
+        let approx_mb = report.approx_bytes / BYTES_PER_MB;
+        match report.level {
+            StorageLevel::Ok => info!(
+                "💾 Database storage back under its caps (~{} MB)",
+                approx_mb
+            ),
+            StorageLevel::Soft => warn!(
+                "💾 Database storage past its soft cap (~{} MB; tables: {})",
+                approx_mb,
+                over.join(", ")
+            ),
+            StorageLevel::Full => error!(
+                "💾 Database storage at its hard cap, rejecting new records (~{} MB; tables: {})",
+                approx_mb,
+                over.join(", ")
+            ),
+        }
+    }
+
+    pub fn report(&self) -> StorageReport {
+        let usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        self.report_for(&usage)
+    }
+
+    fn report_for(&self, usage: &Usage) -> StorageReport {
+        let none = Caps {
+            soft: None,
+            hard: None,
+        };
+        let mut tables: Vec<TableStorageReport> = usage
+            .tables
+            .iter()
+            .map(|(table, (records, approx_bytes))| {
+                let caps = self.tables.get(table).copied().unwrap_or(none);
+                TableStorageReport {
+                    table: table.clone(),
+                    records: *records,
+                    approx_bytes: *approx_bytes,
+                    soft_cap_records: caps.soft,
+                    hard_cap_records: caps.hard,
+                    level: caps.level(*records),
+                }
+            })
+            .collect();
+        tables.sort_by_key(|table| std::cmp::Reverse(table.approx_bytes));
+
+        let approx_bytes = usage.approx_bytes();
+        let level = tables
+            .iter()
+            .map(|table| table.level)
+            .chain([self.database.level(approx_bytes)])
+            .max()
+            .unwrap_or_default();
+        StorageReport {
+            interval_secs: self.interval().map(|interval| interval.as_secs()),
+            measured_at: usage.measured_at,
+            approx_bytes,
+            soft_cap_bytes: self.database.soft,
+            hard_cap_bytes: self.database.hard,
+            level,
+            tables,
+            last_error: usage.last_error.clone(),
+        }
+    }
+}
+
+fn check_order(name: &str, caps: Caps) -> Result<(), StorageCapsError> {
+    match (caps.soft, caps.hard) {
+        (Some(soft), Some(hard)) if soft > hard => {
+            Err(StorageCapsError::SoftAboveHard(name.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Record count and the summed length of each record serialized as text.
+/// The text length tracks the engine's memory closely enough for caps, and
+/// costs one scan per table.
+async fn measure_tables(db: &DbConnection) -> anyhow::Result<HashMap<String, (u64, u64)>> {
+    let handle = db.handle()?;
+    let info: Option<DatabaseInfo> = traced_query("INFO FOR DB", |sql| handle.query(sql))
+        .await?
+        .take(0)?;
+
+    let mut tables = HashMap::new();
+    for table in info.map(|info| info.tables).unwrap_or_default().into_keys() {
+        let size: Option<TableSize> = traced_query(
+            "SELECT count() AS records, math::sum(string::len(<string> $this)) AS approx_bytes \
+             FROM type::table($table) GROUP ALL",
+            |sql| handle.query(sql).bind(("table", table.clone())),
+        )
+        .await?
+        .take(0)?;
+        let size = size.unwrap_or_default();
+        tables.insert(table, (size.records, size.approx_bytes as u64));
+    }
+    Ok(tables)
+}
+
+/// Measures storage every `interval`, skipping ticks while the service is
+/// still starting
+pub fn spawn_storage_monitor<S: RetentionTarget>(
+    service: Arc<RwLock<Option<S>>>,
+    monitor: Arc<StorageMonitor>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            let guard = service.read().await;
+            let Some(service) = guard.as_ref() else {
+                continue;
+            };
+            monitor.measure(&service.connection()).await;
+        }
+    });
+}
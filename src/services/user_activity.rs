@@ -0,0 +1,115 @@
+use crate::models::user_model::UserActivity;
+use crate::services::user_service::UserService;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+/// Users whose activity is held between flushes; activity for further
+/// users is dropped until the next flush
+const MAX_PENDING_USERS: usize = 100_000;
+
+/// Reads `USER_ACTIVITY_FLUSH_SECS` (default 30, `0` disables activity
+/// tracking)
+pub fn activity_flush_interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("USER_ACTIVITY_FLUSH_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingActivity {
+    seen_at: DateTime<Utc>,
+    login_at: Option<DateTime<Utc>>,
+}
+
+/// Collects user activity in memory so a user making a hundred calls a
+/// minute costs one write per flush instead of a hundred. Only the latest
+/// timestamps per user are kept.
+#[derive(Debug, Default)]
+pub struct ActivityTracker {
+    pending: Mutex<HashMap<String, PendingActivity>>,
+}
+
+impl ActivityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notes that `user_id` was active now, and signed in if `login`
+    pub fn record(&self, user_id: &str, login: bool) {
+        let now = Utc::now();
+        self.merge(
+            user_id,
+            PendingActivity {
+                seen_at: now,
+                login_at: login.then_some(now),
+            },
+        );
+    }
+
+    /// Takes everything recorded since the last call
+    pub fn drain(&self) -> Vec<UserActivity> {
+        let pending =
+            std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+        pending
+            .into_iter()
+            .map(|(user_id, activity)| UserActivity {
+                user_id,
+                seen_at: activity.seen_at,
+                login_at: activity.login_at,
+            })
+            .collect()
+    }
+
+    /// Puts back a batch that failed to flush, keeping anything newer
+    pub fn requeue(&self, batch: Vec<UserActivity>) {
+        for activity in batch {
+            self.merge(
+                &activity.user_id,
+                PendingActivity {
+                    seen_at: activity.seen_at,
+                    login_at: activity.login_at,
+                },
+            );
+        }
+    }
+
+    fn merge(&self, user_id: &str, activity: PendingActivity) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(existing) = pending.get_mut(user_id) {
+            existing.seen_at = existing.seen_at.max(activity.seen_at);
+            existing.login_at = existing.login_at.max(activity.login_at);
+        } else if pending.len() < MAX_PENDING_USERS {
+            pending.insert(user_id.to_string(), activity);
+        }
+    }
+}
+
+/// Writes the collected activity every `interval`. Ticks are skipped while
+/// the service is still starting; in read-only mode activity keeps
+/// collecting until writes are allowed again.
+pub fn spawn_activity_flusher(service: Arc<RwLock<Option<UserService>>>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            let guard = service.read().await;
+            let Some(service) = guard.as_ref() else {
+                continue;
+            };
+
+            match service.flush_activity().await {
+                Ok(0) => {}
+                Ok(flushed) => debug!("Recorded activity for {} users", flushed),
+                Err(err) => warn!("Failed to record user activity: {}", err),
+            }
+        }
+    });
+}
@@ -1,56 +1,999 @@
 use crate::{
+    clients::product_client::ProductClient,
+    config::database::DatabaseConfig,
     errors::user_error::UserServiceError,
+    models::admin_model::{DatabaseIsolationReport, DeletePolicy},
+    models::fraud_model::{
+        FraudHitForCreation, ListFraudHitsRequest, ListFraudHitsResponse, SignupAttemptForCreation,
+    },
+    models::organization_model::{
+        AddMemberRequest, CreateOrgRequest, CreateOrgResponse, ListOrgMembersRequest,
+        ListOrgMembersResponse, MembershipResponse, OrgRole, OrganizationForCreation,
+        RemoveMemberRequest,
+    },
     models::user_model::{
-        CreateUserRequest, CreateUserResponse, GetUserRequest, ListUsersResponse, User,
+        AvatarUploadResponse, ConfirmAvatarRequest, CreateUserRequest, CreateUserResponse,
+        DailySignups, DeleteUserRequest, DeleteUserResponse, ExportUsersRequest,
+        ExportUsersResponse, GetNameHistoryRequest, GetUserHistoryRequest, GetUserRequest,
+        GetUserStatsRequest, ListInactiveUsersRequest, ListInactiveUsersResponse,
+        ListUsersResponse, LoginAttemptResponse, NameHistoryResponse, RecordActivityRequest,
+        RecordLoginAttemptRequest, RequestAvatarUploadRequest, RotateEncryptionKeysRequest,
+        RotateEncryptionKeysResponse, UnlockUserRequest, UpdateUserNameRequest, UpdateUserRequest,
+        User, UserChanges, UserHistoryResponse, UserStats,
+    },
+    repositories::{
+        connection::{DatabaseHealth, DbConnection},
+        organization_repository::OrganizationRepository,
+        signup_repository::SignupRepository,
+        user_repository::{UserRepository, UserStorageMode},
+    },
+    services::{
+        avatar_storage::{avatar_extension, is_valid_avatar_key, AvatarStorage, AVATAR_KEY_PREFIX},
+        change_feed::ChangeFeed,
+        database_isolation::{self, USER_SERVICE},
+        delete_guards::{DeleteGuards, Dependent},
+        feature_flags::FeatureFlags,
+        login_throttle::LoginPolicy,
+        read_only::ReadOnlyMode,
+        retention::RetentionTarget,
+        signup_rules::{email_domain, SignupRule, SignupRules, VELOCITY_WINDOW_SECS},
+        storage_caps::StorageMonitor,
+        user_activity::{activity_flush_interval_from_env, ActivityTracker},
     },
-    repositories::user_repository::UserRepository,
 };
-use tracing::info;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const MAX_EXPORT_PAGE_SIZE: usize = 1000;
+const MAX_ROTATION_BATCH_SIZE: usize = 1000;
+const DEFAULT_FRAUD_HITS_LIMIT: usize = 100;
+const MAX_FRAUD_HITS_LIMIT: usize = 1000;
+const DEFAULT_STATS_DAYS: u32 = 30;
+const MAX_STATS_DAYS: u32 = 365;
+const DEFAULT_INACTIVE_USERS_LIMIT: usize = 100;
+const MAX_INACTIVE_USERS_LIMIT: usize = 1000;
+const MIN_NAME_CHARS: usize = 2;
+const MAX_NAME_CHARS: usize = 64;
+
+/// A user's organization memberships. They are `member_of` edges, which
+/// the database deletes with the user, so they can block the delete or go
+/// with it but cannot be orphaned.
+pub const ORG_MEMBERSHIPS: Dependent = Dependent {
+    name: "org_memberships",
+    supports: &[DeletePolicy::Block, DeletePolicy::Cascade],
+};
+
+/// Orders recorded for the user in the product service's `order_history`.
+/// They are the user's purchase history, so they only ever block.
+pub const USER_ORDERS: Dependent = Dependent {
+    name: "orders",
+    supports: &[DeletePolicy::Block],
+};
+
+/// Records that refer to users, checked by `delete_user` against
+/// `USER_DELETE_POLICIES`
+pub const USER_DEPENDENTS: &[Dependent] = &[ORG_MEMBERSHIPS, USER_ORDERS];
 
 pub struct UserService {
     repository: UserRepository,
+    signups: SignupRepository,
+    organizations: OrganizationRepository,
+    /// Counts the orders that refer to a user being deleted
+    products: ProductClient,
+    read_only: Arc<ReadOnlyMode>,
+    /// Hard caps that stop new records once reached
+    storage: Arc<StorageMonitor>,
+    signup_rules: Arc<SignupRules>,
+    login_policy: Arc<LoginPolicy>,
+    /// What deleting a user does to the records that refer to them
+    delete_guards: Arc<DeleteGuards>,
+    avatars: Arc<dyn AvatarStorage>,
+    /// How long presigned avatar upload URLs stay valid
+    avatar_upload_expiry: Duration,
+    feature_flags: FeatureFlags,
+    /// Live changes to the user and flag tables, when enabled
+    change_feed: Option<Arc<ChangeFeed>>,
+    /// Activity waiting to be written; `None` when tracking is disabled
+    activity: Option<ActivityTracker>,
 }
 
 impl UserService {
-    pub async fn new() -> Result<Self, UserServiceError> {
-        let repository = UserRepository::new().await?;
+    /// Reads `AVATAR_UPLOAD_EXPIRY_SECS` (default 900) for the lifetime of
+    /// avatar upload URLs
+    pub async fn new(
+        read_only: Arc<ReadOnlyMode>,
+        storage: Arc<StorageMonitor>,
+        signup_rules: Arc<SignupRules>,
+        login_policy: Arc<LoginPolicy>,
+        delete_guards: Arc<DeleteGuards>,
+        avatars: Arc<dyn AvatarStorage>,
+        db_config: &DatabaseConfig,
+    ) -> Result<Self, UserServiceError> {
+        let repository = UserRepository::new(db_config).await?;
+        let signups = SignupRepository::new(repository.connection());
+        let organizations = OrganizationRepository::new(repository.connection()).await?;
+        let products = ProductClient::from_env().map_err(anyhow::Error::new)?;
+        let change_feed = ChangeFeed::from_env(&repository.connection(), &["user", "feature_flag"]);
+        let feature_flags = FeatureFlags::new(repository.connection())
+            .with_change_watcher(change_feed.as_ref().map(|feed| feed.watch("feature_flag")));
+        let avatar_upload_expiry = std::env::var("AVATAR_UPLOAD_EXPIRY_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::seconds)
+            .unwrap_or_else(|| Duration::minutes(15));
         info!("UserService initialized");
-        Ok(Self { repository })
+        Ok(Self {
+            repository,
+            signups,
+            organizations,
+            products,
+            read_only,
+            storage,
+            signup_rules,
+            login_policy,
+            delete_guards,
+            avatars,
+            avatar_upload_expiry,
+            feature_flags,
+            change_feed,
+            activity: activity_flush_interval_from_env().map(|_| ActivityTracker::new()),
+        })
+    }
+
+    /// Status of the user database connection
+    pub fn database_health(&self) -> DatabaseHealth {
+        self.repository.connection().health()
+    }
+
+    /// The namespace, database and tables the user service actually uses,
+    /// checked against its config
+    pub async fn database_isolation(&self) -> anyhow::Result<DatabaseIsolationReport> {
+        database_isolation::inspect(USER_SERVICE, &self.repository.connection()).await
     }
 
+    /// The user service's feature flags
+    pub fn feature_flags(&self) -> &FeatureFlags {
+        &self.feature_flags
+    }
+
+    /// Live changes to the service's tables, when `LIVE_QUERIES` is on
+    pub fn change_feed(&self) -> Option<&Arc<ChangeFeed>> {
+        self.change_feed.as_ref()
+    }
+
+    fn ensure_writable(&self) -> Result<(), UserServiceError> {
+        if self.read_only.is_enabled() {
+            return Err(UserServiceError::ServiceReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Creates a user once the anti-fraud rules accept the signup.
+    /// `client_ip` is the caller's address when the gateway forwarded it.
+    /// A dry run makes the same checks but records nothing.
     pub async fn create_user(
         &self,
         request: CreateUserRequest,
+        client_ip: Option<IpAddr>,
     ) -> Result<CreateUserResponse, UserServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("user")?;
+
         // Validate input
         self.validate_create_user_request(&request)?;
 
-        let user = User::new(request.name, request.email);
+        let domain = email_domain(&request.email).unwrap_or_default();
+        let client_ip = client_ip.map(|ip| ip.to_string());
+        self.check_signup(&domain, client_ip.as_deref(), request.dry_run)
+            .await?;
+
+        if request.dry_run {
+            self.repository
+                .ensure_email_available(&request.email, None)
+                .await?;
+            return Ok(CreateUserResponse {
+                id: String::new(),
+                message: "Dry run: the user would be created".to_string(),
+                dry_run: true,
+            });
+        }
+
+        let user = User::new(request.name, request.email, request.phone);
         let created_user = self.repository.create_user(user).await?;
 
+        let attempt = SignupAttemptForCreation {
+            email_domain: domain,
+            client_ip,
+            attempted_at: Utc::now(),
+        };
+        if let Err(err) = self.signups.record_signup(attempt).await {
+            warn!("Failed to record signup for velocity checks: {}", err);
+        }
+
         Ok(CreateUserResponse {
             id: created_user.id.to_string(),
             message: format!("User created successfully with id: {}", created_user.id),
+            dry_run: false,
         })
     }
 
+    /// Evaluates the anti-fraud rules for a signup
+    async fn check_signup(
+        &self,
+        domain: &str,
+        client_ip: Option<&str>,
+        dry_run: bool,
+    ) -> Result<(), UserServiceError> {
+        let rules = &self.signup_rules;
+        if rules.is_blocked(domain) {
+            return self
+                .stop_signup(SignupRule::DisposableDomain, domain, client_ip, dry_run)
+                .await;
+        }
+
+        let since = Utc::now() - Duration::seconds(VELOCITY_WINDOW_SECS as i64);
+        if let Some(max) = rules.max_per_domain {
+            if self
+                .signups
+                .signups_from_domain_since(domain, since)
+                .await?
+                >= max
+            {
+                return self
+                    .stop_signup(SignupRule::DomainVelocity, domain, client_ip, dry_run)
+                    .await;
+            }
+        }
+        if let (Some(max), Some(ip)) = (rules.max_per_ip, client_ip) {
+            if self.signups.signups_from_ip_since(ip, since).await? >= max {
+                return self
+                    .stop_signup(SignupRule::IpVelocity, domain, client_ip, dry_run)
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a hit for review, unless this is a dry run, and fails the
+    /// signup
+    async fn stop_signup(
+        &self,
+        rule: SignupRule,
+        domain: &str,
+        client_ip: Option<&str>,
+        dry_run: bool,
+    ) -> Result<(), UserServiceError> {
+        let error = match rule {
+            SignupRule::DisposableDomain => UserServiceError::SignupRejected {
+                rule: rule.name(),
+                reason: format!("{} is a disposable email domain", domain),
+            },
+            SignupRule::DomainVelocity | SignupRule::IpVelocity => {
+                UserServiceError::SignupRateLimited {
+                    rule: rule.name(),
+                    retry_after_secs: VELOCITY_WINDOW_SECS,
+                }
+            }
+        };
+        let decision = match error {
+            UserServiceError::SignupRejected { .. } => "rejected",
+            _ => "rate_limited",
+        };
+        warn!(
+            "Signup {} by {} (domain {}, ip {})",
+            decision,
+            rule.name(),
+            domain,
+            client_ip.unwrap_or("unknown")
+        );
+        if dry_run {
+            return Err(error);
+        }
+
+        let hit = FraudHitForCreation {
+            rule: rule.name().to_string(),
+            decision: decision.to_string(),
+            email_domain: domain.to_string(),
+            client_ip: client_ip.map(str::to_string),
+            recorded_at: Utc::now(),
+        };
+        if let Err(err) = self.signups.record_hit(hit).await {
+            warn!("Failed to record fraud hit: {}", err);
+        }
+        Err(error)
+    }
+
+    /// Signups stopped by the anti-fraud rules, most recent first
+    pub async fn list_fraud_hits(
+        &self,
+        request: ListFraudHitsRequest,
+    ) -> Result<ListFraudHitsResponse, UserServiceError> {
+        let limit = request.limit.unwrap_or(DEFAULT_FRAUD_HITS_LIMIT);
+        if limit == 0 || limit > MAX_FRAUD_HITS_LIMIT {
+            return Err(UserServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_FRAUD_HITS_LIMIT),
+            });
+        }
+
+        let (hits, total) = self.signups.list_hits(limit).await?;
+        Ok(ListFraudHitsResponse { hits, total })
+    }
+
     pub async fn get_user(&self, request: GetUserRequest) -> Result<User, UserServiceError> {
-        if request.id.trim().is_empty() {
+        validate_id(&request.id)?;
+
+        let user = self.repository.get_user(&request.id).await?;
+        Ok(self.with_avatar_url(user))
+    }
+
+    pub async fn update_user(&self, request: UpdateUserRequest) -> Result<User, UserServiceError> {
+        self.ensure_writable()?;
+        validate_id(&request.id)?;
+
+        let changes = UserChanges {
+            name: request.name,
+            email: request.email,
+            email_hash: None,
+            phone: request.phone,
+            avatar_key: None,
+            email_verified: request.email_verified,
+        };
+        if changes.is_empty() {
             return Err(UserServiceError::Validation {
-                message: "User ID cannot be empty".to_string(),
+                message: "Nothing to update: set name, email, phone or email_verified".to_string(),
+            });
+        }
+        if changes
+            .name
+            .as_deref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Err(UserServiceError::Validation {
+                message: "Name cannot be empty".to_string(),
+            });
+        }
+        if let Some(email) = &changes.email {
+            validate_email(email)?;
+        }
+        // An empty phone removes it
+        if let Some(phone) = changes.phone.as_deref().filter(|phone| !phone.is_empty()) {
+            validate_phone(phone)?;
+        }
+
+        let user = if request.dry_run {
+            self.repository
+                .preview_update(&request.id, &changes)
+                .await?
+        } else {
+            self.repository.update_user(&request.id, changes).await?
+        };
+        Ok(self.with_avatar_url(user))
+    }
+
+    /// Renames a user on their own behalf, keeping the previous name in
+    /// their name history. Setting the current name again changes nothing.
+    pub async fn update_user_name(
+        &self,
+        request: UpdateUserNameRequest,
+    ) -> Result<User, UserServiceError> {
+        self.ensure_writable()?;
+        validate_id(&request.id)?;
+        let name = validate_user_name(&request.name)?;
+
+        let current = self.repository.get_user(&request.id).await?;
+        if current.name == name {
+            return Ok(self.with_avatar_url(current));
+        }
+        let changes = UserChanges {
+            name: Some(name.to_string()),
+            ..UserChanges::default()
+        };
+        let user = self.repository.update_user(&request.id, changes).await?;
+        info!(
+            target: "audit",
+            user_id = %request.id,
+            previous_name = %current.name,
+            name = %user.name,
+            "user_renamed"
+        );
+        Ok(self.with_avatar_url(user))
+    }
+
+    /// The names a user had before their current one, for admins auditing
+    /// handle changes
+    pub async fn get_name_history(
+        &self,
+        request: GetNameHistoryRequest,
+    ) -> Result<NameHistoryResponse, UserServiceError> {
+        validate_id(&request.user_id)?;
+
+        let user = self.repository.get_user(&request.user_id).await?;
+        Ok(NameHistoryResponse {
+            user_id: request.user_id,
+            name: user.name,
+            history: user.name_history,
+        })
+    }
+
+    /// Presigns an upload URL for a new avatar. The avatar only replaces the
+    /// current one once the client calls `confirm_avatar` after uploading.
+    pub async fn request_avatar_upload(
+        &self,
+        request: RequestAvatarUploadRequest,
+    ) -> Result<AvatarUploadResponse, UserServiceError> {
+        self.ensure_writable()?;
+        validate_id(&request.user_id)?;
+
+        let Some(extension) = avatar_extension(&request.content_type) else {
+            return Err(UserServiceError::Validation {
+                message: format!(
+                    "Unsupported avatar type '{}': use image/png, image/jpeg, image/webp or image/gif",
+                    request.content_type
+                ),
+            });
+        };
+        // Fails with "not found" for unknown ids
+        let user = self.repository.get_user(&request.user_id).await?;
+
+        let key = format!(
+            "{}{}/{}.{}",
+            AVATAR_KEY_PREFIX,
+            user.id.id.to_raw(),
+            Uuid::new_v4().simple(),
+            extension
+        );
+        let expires_at = Utc::now() + self.avatar_upload_expiry;
+        Ok(AvatarUploadResponse {
+            upload_url: self.avatars.presign_upload(&key, expires_at),
+            method: "PUT".to_string(),
+            key,
+            content_type: request.content_type.trim().to_ascii_lowercase(),
+            expires_at,
+        })
+    }
+
+    /// Attaches an uploaded avatar to its user
+    pub async fn confirm_avatar(
+        &self,
+        request: ConfirmAvatarRequest,
+    ) -> Result<User, UserServiceError> {
+        self.ensure_writable()?;
+        validate_id(&request.user_id)?;
+
+        let user = self.repository.get_user(&request.user_id).await?;
+        let owner = format!("{}{}/", AVATAR_KEY_PREFIX, user.id.id.to_raw());
+        if !request.key.starts_with(&owner) || !is_valid_avatar_key(&request.key) {
+            return Err(UserServiceError::Validation {
+                message: format!(
+                    "Avatar key '{}' was not issued for user {}",
+                    request.key, request.user_id
+                ),
+            });
+        }
+
+        let changes = UserChanges {
+            avatar_key: Some(request.key),
+            ..UserChanges::default()
+        };
+        let user = self
+            .repository
+            .update_user(&request.user_id, changes)
+            .await?;
+        info!(
+            "Avatar confirmed for user {} ({} storage)",
+            request.user_id,
+            self.avatars.backend()
+        );
+        Ok(self.with_avatar_url(user))
+    }
+
+    pub async fn delete_user(
+        &self,
+        request: DeleteUserRequest,
+    ) -> Result<DeleteUserResponse, UserServiceError> {
+        self.ensure_writable()?;
+        validate_id(&request.id)?;
+
+        let memberships = self.organizations.membership_count(&request.id).await?;
+        let orders = self
+            .products
+            .count_orders_by_user(&request.id)
+            .await
+            .map_err(|source| UserServiceError::DependentCheck {
+                id: request.id.clone(),
+                dependent: USER_ORDERS.name,
+                source,
+            })?;
+        let plan = self.delete_guards.plan([
+            (ORG_MEMBERSHIPS.name, memberships),
+            (USER_ORDERS.name, orders),
+        ]);
+        let blocking = plan.blocking();
+        if !blocking.is_empty() {
+            return Err(UserServiceError::DeleteBlocked {
+                id: request.id,
+                dependents: blocking,
+            });
+        }
+
+        if request.dry_run {
+            // Fails with "not found" for unknown ids
+            self.repository.get_user(&request.id).await?;
+            return Ok(DeleteUserResponse {
+                message: format!("Dry run: user {} would be deleted", request.id),
+                id: request.id,
+                dry_run: true,
+                dependents: plan.dependents,
+            });
+        }
+
+        // Memberships are edges, which the database deletes with the user
+        self.repository.delete_user(&request.id).await?;
+        for records in &plan.dependents {
+            info!(target: "audit", user_id = %request.id, dependent = %records.dependent, count = records.count, policy = %records.policy, "user_dependents_deleted");
+        }
+        Ok(DeleteUserResponse {
+            message: format!("User deleted successfully with id: {}", request.id),
+            id: request.id,
+            dry_run: false,
+            dependents: plan.dependents,
+        })
+    }
+
+    /// Counts a sign-in checked by the caller against the account's lockout.
+    /// The caller must refuse the sign-in when `allowed` is false, even if
+    /// the credentials were right. Unknown emails are answered like
+    /// accounts without failures, so the response reveals nothing. Nothing
+    /// is counted in read-only mode, but existing locks still apply.
+    pub async fn record_login_attempt(
+        &self,
+        request: RecordLoginAttemptRequest,
+    ) -> Result<LoginAttemptResponse, UserServiceError> {
+        let email = request.email.trim();
+        let unlocked = LoginAttemptResponse {
+            allowed: true,
+            failed_attempts: 0,
+            locked_until: None,
+        };
+        let Some(user) = self.repository.get_user_by_email(email).await? else {
+            return Ok(unlocked);
+        };
+        let id = user.id.id.to_raw();
+        let now = Utc::now();
+
+        if let Some(until) = user.locked_until.filter(|until| *until > now) {
+            warn!(target: "audit", user_id = %id, locked_until = %until, "login_refused_while_locked");
+            return Ok(LoginAttemptResponse {
+                allowed: false,
+                failed_attempts: user.failed_logins,
+                locked_until: Some(until),
+            });
+        }
+        if self.read_only.is_enabled() {
+            return Ok(LoginAttemptResponse {
+                failed_attempts: user.failed_logins,
+                ..unlocked
+            });
+        }
+
+        if request.success {
+            if user.failed_logins > 0 || user.locked_until.is_some() {
+                self.repository.reset_login_failures(&id).await?;
+            }
+            if let Some(activity) = &self.activity {
+                activity.record(&id, true);
+            }
+            return Ok(unlocked);
+        }
+
+        let Some(failures) = self.repository.record_login_failure(&id).await? else {
+            return Ok(unlocked);
+        };
+        let locked_until = match self.login_policy.lockout_for(failures) {
+            Some(lockout) => {
+                let until = Duration::from_std(lockout)
+                    .ok()
+                    .and_then(|lockout| now.checked_add_signed(lockout))
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC);
+                self.repository.lock_user(&id, until).await?;
+                warn!(
+                    target: "audit",
+                    user_id = %id,
+                    failed_attempts = failures,
+                    locked_until = %until,
+                    "account_locked"
+                );
+                Some(until)
+            }
+            None => None,
+        };
+        Ok(LoginAttemptResponse {
+            allowed: locked_until.is_none(),
+            failed_attempts: failures,
+            locked_until,
+        })
+    }
+
+    /// Clears a user's failed sign-ins and lock
+    pub async fn unlock_user(&self, request: UnlockUserRequest) -> Result<User, UserServiceError> {
+        self.ensure_writable()?;
+        validate_id(&request.id)?;
+
+        let user = self.repository.get_user(&request.id).await?;
+        self.repository.reset_login_failures(&request.id).await?;
+        info!(
+            target: "audit",
+            user_id = %request.id,
+            failed_attempts = user.failed_logins,
+            "account_unlocked"
+        );
+        let user = self.repository.get_user(&request.id).await?;
+        Ok(self.with_avatar_url(user))
+    }
+
+    /// Replays the user's event log, optionally only up to `as_of`
+    pub async fn get_user_history(
+        &self,
+        request: GetUserHistoryRequest,
+    ) -> Result<UserHistoryResponse, UserServiceError> {
+        if self.repository.storage_mode() != UserStorageMode::EventSourced {
+            return Err(UserServiceError::Validation {
+                message: "User history requires USER_STORAGE_MODE=event_sourced".to_string(),
+            });
+        }
+        validate_id(&request.user_id)?;
+
+        let (events, state) = self
+            .repository
+            .user_history(&request.user_id, request.as_of)
+            .await?;
+        if events.is_empty() && request.as_of.is_none() {
+            // Fails with "not found" for unknown ids
+            self.repository.get_user(&request.user_id).await?;
+            return Err(UserServiceError::Validation {
+                message: format!(
+                    "User {} has no history: it was last written before event sourcing was enabled",
+                    request.user_id
+                ),
             });
         }
 
-        self.repository.get_user(&request.id).await
+        Ok(UserHistoryResponse {
+            user_id: request.user_id,
+            events,
+            state,
+            as_of: request.as_of,
+        })
     }
 
     pub async fn list_users(&self) -> Result<ListUsersResponse, UserServiceError> {
-        let users = self.repository.list_users().await?;
+        let users = self.with_avatar_urls(self.repository.list_users().await?);
         let total = users.len();
 
         Ok(ListUsersResponse { users, total })
     }
 
+    pub async fn export_users(
+        &self,
+        request: ExportUsersRequest,
+    ) -> Result<ExportUsersResponse, UserServiceError> {
+        if request.limit == 0 || request.limit > MAX_EXPORT_PAGE_SIZE {
+            return Err(UserServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_EXPORT_PAGE_SIZE),
+            });
+        }
+
+        let (users, total) = self
+            .repository
+            .export_users(request.offset, request.limit)
+            .await?;
+        let users = self.with_avatar_urls(users);
+
+        Ok(ExportUsersResponse {
+            users,
+            offset: request.offset,
+            total,
+        })
+    }
+
+    /// User totals and daily signups over the last `days` days, today
+    /// included, for the admin dashboard
+    /// Notes a user's authenticated activity; written on the next flush.
+    /// A no-op when activity tracking is disabled.
+    pub fn record_activity(&self, request: RecordActivityRequest) -> Result<(), UserServiceError> {
+        if request.user_id.trim().is_empty() {
+            return Err(UserServiceError::Validation {
+                message: "User ID cannot be empty".to_string(),
+            });
+        }
+        if let Some(activity) = &self.activity {
+            activity.record(request.user_id.trim(), request.login);
+        }
+        Ok(())
+    }
+
+    /// Writes the activity collected since the last flush and returns for
+    /// how many users. Activity is kept for later while read-only or when
+    /// the write fails.
+    pub async fn flush_activity(&self) -> Result<usize, UserServiceError> {
+        let Some(activity) = &self.activity else {
+            return Ok(0);
+        };
+        if self.read_only.is_enabled() {
+            return Ok(0);
+        }
+        let batch = activity.drain();
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        let flushed = batch.len();
+        if let Err(err) = self.repository.record_activity(batch.clone()).await {
+            activity.requeue(batch);
+            return Err(err);
+        }
+        Ok(flushed)
+    }
+
+    /// Users not seen for `days` days, for lifecycle campaigns
+    pub async fn list_inactive_users(
+        &self,
+        request: ListInactiveUsersRequest,
+    ) -> Result<ListInactiveUsersResponse, UserServiceError> {
+        if request.days == 0 || request.days > MAX_STATS_DAYS {
+            return Err(UserServiceError::Validation {
+                message: format!("Days must be between 1 and {}", MAX_STATS_DAYS),
+            });
+        }
+        let limit = request.limit.unwrap_or(DEFAULT_INACTIVE_USERS_LIMIT);
+        if limit == 0 || limit > MAX_INACTIVE_USERS_LIMIT {
+            return Err(UserServiceError::Validation {
+                message: format!("Limit must be between 1 and {}", MAX_INACTIVE_USERS_LIMIT),
+            });
+        }
+
+        let cutoff = Utc::now() - Duration::days(i64::from(request.days));
+        let (users, total) = self.repository.inactive_users(cutoff, limit).await?;
+        Ok(ListInactiveUsersResponse {
+            users: self.with_avatar_urls(users),
+            total,
+            cutoff,
+        })
+    }
+
+    pub async fn get_user_stats(
+        &self,
+        request: GetUserStatsRequest,
+    ) -> Result<UserStats, UserServiceError> {
+        let days = request.days.unwrap_or(DEFAULT_STATS_DAYS);
+        if days == 0 || days > MAX_STATS_DAYS {
+            return Err(UserServiceError::Validation {
+                message: format!("Days must be between 1 and {}", MAX_STATS_DAYS),
+            });
+        }
+
+        let today = Utc::now().date_naive();
+        let first_day = today - Duration::days(i64::from(days) - 1);
+        let since = first_day.and_time(NaiveTime::MIN).and_utc();
+        let mut stats = self.repository.user_stats(since).await?;
+
+        // The database only returns days that had signups
+        let counted: HashMap<String, usize> = stats
+            .signups_per_day
+            .drain(..)
+            .map(|day| (day.date, day.signups))
+            .collect();
+        stats.signups_per_day = first_day
+            .iter_days()
+            .take(days as usize)
+            .map(|day| {
+                let date = day.format("%Y-%m-%d").to_string();
+                let signups = counted.get(&date).copied().unwrap_or(0);
+                DailySignups { date, signups }
+            })
+            .collect();
+        Ok(stats)
+    }
+
+    /// Re-encrypts every user's PII under the active key, one batch at a time
+    pub async fn rotate_encryption_keys(
+        &self,
+        request: RotateEncryptionKeysRequest,
+    ) -> Result<RotateEncryptionKeysResponse, UserServiceError> {
+        // Rotation rewrites every user row
+        self.ensure_writable()?;
+
+        if request.batch_size == 0 || request.batch_size > MAX_ROTATION_BATCH_SIZE {
+            return Err(UserServiceError::Validation {
+                message: format!(
+                    "Batch size must be between 1 and {}",
+                    MAX_ROTATION_BATCH_SIZE
+                ),
+            });
+        }
+
+        let mut reencrypted = 0;
+        let mut batches = 0;
+        loop {
+            let count = self.repository.reencrypt_batch(request.batch_size).await?;
+            if count == 0 {
+                break;
+            }
+            reencrypted += count;
+            batches += 1;
+            info!(
+                "Key rotation batch {}: {} users re-encrypted",
+                batches, count
+            );
+        }
+
+        Ok(RotateEncryptionKeysResponse {
+            active_key_id: self
+                .repository
+                .encryption_key_id()
+                .unwrap_or_default()
+                .to_string(),
+            reencrypted,
+            batches,
+        })
+    }
+
+    /// Creates an organization with an existing user as its first owner
+    pub async fn create_org(
+        &self,
+        request: CreateOrgRequest,
+    ) -> Result<CreateOrgResponse, UserServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("organization")?;
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(UserServiceError::Validation {
+                message: "Organization name cannot be empty".to_string(),
+            });
+        }
+        validate_id(&request.owner_id)?;
+        // Fails with "not found" for unknown owners
+        self.repository.get_user(&request.owner_id).await?;
+
+        let organization = self
+            .organizations
+            .create_org(
+                OrganizationForCreation {
+                    name: name.to_string(),
+                    created_at: Utc::now(),
+                },
+                &request.owner_id,
+            )
+            .await?;
+        Ok(CreateOrgResponse {
+            id: organization.id.id.to_raw(),
+            message: format!("Organization created successfully: {}", organization.name),
+            organization,
+        })
+    }
+
+    /// Adds a user to an organization, or changes the role of an existing
+    /// member. The last owner cannot be given another role.
+    pub async fn add_member(
+        &self,
+        request: AddMemberRequest,
+    ) -> Result<MembershipResponse, UserServiceError> {
+        self.ensure_writable()?;
+        self.storage.check_write("member_of")?;
+        validate_org_id(&request.org_id)?;
+        validate_id(&request.user_id)?;
+        self.organizations.get_org(&request.org_id).await?;
+        self.repository.get_user(&request.user_id).await?;
+
+        let current = self
+            .organizations
+            .role_of(&request.org_id, &request.user_id)
+            .await?;
+        if current == Some(OrgRole::Owner) && request.role != OrgRole::Owner {
+            self.ensure_other_owner(&request.org_id).await?;
+        }
+
+        self.organizations
+            .set_member(&request.org_id, &request.user_id, request.role)
+            .await?;
+        let message = match current {
+            Some(previous) => format!(
+                "Role changed from {} to {}",
+                previous.name(),
+                request.role.name()
+            ),
+            None => format!("Member added as {}", request.role.name()),
+        };
+        Ok(MembershipResponse {
+            org_id: request.org_id,
+            user_id: request.user_id,
+            message,
+        })
+    }
+
+    /// Removes a user from an organization, unless they are its last owner
+    pub async fn remove_member(
+        &self,
+        request: RemoveMemberRequest,
+    ) -> Result<MembershipResponse, UserServiceError> {
+        self.ensure_writable()?;
+        validate_org_id(&request.org_id)?;
+        validate_id(&request.user_id)?;
+        self.organizations.get_org(&request.org_id).await?;
+
+        let current = self
+            .organizations
+            .role_of(&request.org_id, &request.user_id)
+            .await?;
+        match current {
+            None => {
+                return Err(UserServiceError::MembershipNotFound {
+                    org_id: request.org_id,
+                    user_id: request.user_id,
+                })
+            }
+            Some(OrgRole::Owner) => self.ensure_other_owner(&request.org_id).await?,
+            Some(_) => {}
+        }
+
+        self.organizations
+            .remove_member(&request.org_id, &request.user_id)
+            .await?;
+        Ok(MembershipResponse {
+            message: format!(
+                "User {} removed from organization {}",
+                request.user_id, request.org_id
+            ),
+            org_id: request.org_id,
+            user_id: request.user_id,
+        })
+    }
+
+    pub async fn list_org_members(
+        &self,
+        request: ListOrgMembersRequest,
+    ) -> Result<ListOrgMembersResponse, UserServiceError> {
+        validate_org_id(&request.org_id)?;
+        let organization = self.organizations.get_org(&request.org_id).await?;
+        let members = self.organizations.members(&request.org_id).await?;
+        Ok(ListOrgMembersResponse {
+            organization,
+            total: members.len(),
+            members,
+        })
+    }
+
+    async fn ensure_other_owner(&self, org_id: &str) -> Result<(), UserServiceError> {
+        if self.organizations.owner_count(org_id).await? <= 1 {
+            return Err(UserServiceError::Validation {
+                message: "An organization must keep at least one owner".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn with_avatar_url(&self, user: User) -> User {
+        User {
+            avatar_url: user
+                .avatar_key
+                .as_deref()
+                .map(|key| self.avatars.public_url(key)),
+            ..user
+        }
+    }
+
+    fn with_avatar_urls(&self, users: Vec<User>) -> Vec<User> {
+        users
+            .into_iter()
+            .map(|user| self.with_avatar_url(user))
+            .collect()
+    }
+
     fn validate_create_user_request(
         &self,
         request: &CreateUserRequest,
@@ -61,19 +1004,97 @@ impl UserService {
             });
         }
 
-        if request.email.trim().is_empty() {
-            return Err(UserServiceError::Validation {
-                message: "Email cannot be empty".to_string(),
-            });
-        }
+        validate_email(&request.email)?;
 
-        // Simple email validation
-        if !request.email.contains('@') || !request.email.contains('.') {
-            return Err(UserServiceError::InvalidEmail {
-                email: request.email.clone(),
-            });
+        if let Some(phone) = &request.phone {
+            validate_phone(phone)?;
         }
 
         Ok(())
     }
 }
+
+impl RetentionTarget for UserService {
+    fn connection(&self) -> Arc<DbConnection> {
+        self.repository.connection()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only.is_enabled()
+    }
+}
+
+fn validate_id(id: &str) -> Result<(), UserServiceError> {
+    if id.trim().is_empty() {
+        return Err(UserServiceError::Validation {
+            message: "User ID cannot be empty".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The trimmed name, if it has an allowed length and only letters, digits,
+/// spaces and `'` `-` `.` `_`
+fn validate_user_name(name: &str) -> Result<&str, UserServiceError> {
+    let name = name.trim();
+    let chars = name.chars().count();
+    if !(MIN_NAME_CHARS..=MAX_NAME_CHARS).contains(&chars) {
+        return Err(UserServiceError::Validation {
+            message: format!(
+                "Name must be between {} and {} characters",
+                MIN_NAME_CHARS, MAX_NAME_CHARS
+            ),
+        });
+    }
+    if let Some(invalid) = name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || *c == ' ' || "'-._".contains(*c)))
+    {
+        return Err(UserServiceError::Validation {
+            message: format!(
+                "Name cannot contain '{}': use letters, digits, spaces and ' - . _",
+                invalid.escape_default()
+            ),
+        });
+    }
+    Ok(name)
+}
+
+fn validate_org_id(id: &str) -> Result<(), UserServiceError> {
+    if id.trim().is_empty() {
+        return Err(UserServiceError::Validation {
+            message: "Organization ID cannot be empty".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn validate_email(email: &str) -> Result<(), UserServiceError> {
+    if email.trim().is_empty() {
+        return Err(UserServiceError::Validation {
+            message: "Email cannot be empty".to_string(),
+        });
+    }
+
+    // Simple email validation
+    if !email.contains('@') || !email.contains('.') {
+        return Err(UserServiceError::InvalidEmail {
+            email: email.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn validate_phone(phone: &str) -> Result<(), UserServiceError> {
+    let digits = phone.chars().filter(char::is_ascii_digit).count();
+    if digits < 7
+        || !phone
+            .chars()
+            .all(|c| c.is_ascii_digit() || "+-() ".contains(c))
+    {
+        return Err(UserServiceError::Validation {
+            message: "Invalid phone number".to_string(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use crate::models::admin_model::LogSamplingStatus;
+use crate::telemetry::plain_format::PlainFormat;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::task::futures::TaskLocalFuture;
+use tracing::subscriber::Interest;
+use tracing::{Level, Metadata};
+use tracing_subscriber::{
+    filter::{EnvFilter, FilterExt},
+    fmt,
+    layer::{Context, Filter, SubscriberExt},
+    util::SubscriberInitExt,
+    Layer,
+};
+
+/// Request header carrying the gateway's sampling decision to the services:
+/// `1` when the request's success logs are kept, `0` otherwise
+pub const TRACE_SAMPLED_HEADER: &str = "x-trace-sampled";
+
+/// Request header with which a caller holding an admin token asks the
+/// gateway to sample a request regardless of the success percentage
+pub const TRACE_DEBUG_HEADER: &str = "x-trace-debug";
+
+tokio::task_local! {
+    static REQUEST_SAMPLED: bool;
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid success sample percentage {0}, expected 0 to 100")]
+pub struct InvalidSamplePercent(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Default multi-field format with targets and ANSI colors
+    Full,
+    /// Single-line format without targets or colors, for production shipping
+    Compact,
+    /// ASCII-only `key=value` lines without emoji, see [`PlainFormat`]
+    Plain,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogPolicyConfig {
+    /// Percentage of successful requests logged; failures are always logged
+    pub success_sample_percent: u8,
+    /// Maximum INFO/DEBUG/TRACE events per module per second (0 = unlimited)
+    pub max_events_per_target_per_sec: u32,
+    pub format: LogFormat,
+}
+
+impl Default for LogPolicyConfig {
+    fn default() -> Self {
+        Self {
+            success_sample_percent: 100,
+            max_events_per_target_per_sec: 0,
+            format: LogFormat::Full,
+        }
+    }
+}
+
+impl LogPolicyConfig {
+    /// Reads `LOG_SUCCESS_SAMPLE_PERCENT` (or the older
+    /// `LOG_SUCCESS_SAMPLE_RATE`, one in N), `LOG_RATE_LIMIT_PER_SEC` and
+    /// `LOG_FORMAT` (`full`, `compact` or `plain`).
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let format = match std::env::var("LOG_FORMAT").as_deref() {
+            Ok("compact") => LogFormat::Compact,
+            Ok("plain") => LogFormat::Plain,
+            _ => defaults.format,
+        };
+
+        Self {
+            success_sample_percent: std::env::var("LOG_SUCCESS_SAMPLE_PERCENT")
+                .ok()
+                .and_then(|v| v.parse::<u8>().ok())
+                .or_else(|| {
+                    std::env::var("LOG_SUCCESS_SAMPLE_RATE")
+                        .ok()
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(|rate| (100 / rate.max(1)) as u8)
+                })
+                .unwrap_or(defaults.success_sample_percent)
+                .min(100),
+            max_events_per_target_per_sec: std::env::var("LOG_RATE_LIMIT_PER_SEC")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_events_per_target_per_sec),
+            format,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LogPolicy {
+    config: LogPolicyConfig,
+    /// Starts at the configured percentage, changeable at runtime
+    success_percent: AtomicU8,
+    success_counter: AtomicU64,
+    forced_requests: AtomicU64,
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+    suppressed_events: AtomicU64,
+}
+
+impl LogPolicy {
+    pub fn new(config: LogPolicyConfig) -> Self {
+        Self {
+            success_percent: AtomicU8::new(config.success_sample_percent),
+            config,
+            success_counter: AtomicU64::new(0),
+            forced_requests: AtomicU64::new(0),
+            windows: Mutex::new(HashMap::new()),
+            suppressed_events: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &LogPolicyConfig {
+        &self.config
+    }
+
+    /// Returns `true` for the configured percentage of calls, spread
+    /// evenly: at 25, every fourth call
+    pub fn sample_success(&self) -> bool {
+        let percent = u64::from(self.success_percent.load(Ordering::Relaxed));
+        let n = self.success_counter.fetch_add(1, Ordering::Relaxed);
+        (n + 1) * percent / 100 > n * percent / 100
+    }
+
+    /// Decides once whether a request's success logs are kept. Forced
+    /// requests always are.
+    pub fn decide(&self, forced: bool) -> bool {
+        if forced {
+            self.forced_requests.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+        self.sample_success()
+    }
+
+    pub fn set_success_percent(&self, percent: u64) -> Result<(), InvalidSamplePercent> {
+        let percent = u8::try_from(percent)
+            .ok()
+            .filter(|percent| *percent <= 100)
+            .ok_or(InvalidSamplePercent(percent))?;
+        self.success_percent.store(percent, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn sampling_status(&self) -> LogSamplingStatus {
+        LogSamplingStatus {
+            success_percent: self.success_percent.load(Ordering::Relaxed),
+            forced_requests: self.forced_requests.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Per-module rate limit. Warnings and errors are never suppressed.
+    fn allow_event(&self, metadata: &Metadata<'_>) -> bool {
+        let limit = self.config.max_events_per_target_per_sec;
+        if limit == 0 || !metadata.is_event() || *metadata.level() <= Level::WARN {
+            return true;
+        }
+
+        let mut windows = self.windows.lock().unwrap();
+        let now = Instant::now();
+        let (window_start, count) = windows
+            .entry(metadata.target().to_string())
+            .or_insert((now, 0));
+
+        if now.duration_since(*window_start) >= Duration::from_secs(1) {
+            *window_start = now;
+            *count = 0;
+        }
+
+        if *count < limit {
+            *count += 1;
+            true
+        } else {
+            self.suppressed_events.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+
+    pub fn suppressed_events(&self) -> u64 {
+        self.suppressed_events.load(Ordering::Relaxed)
+    }
+}
+
+static LOG_POLICY: OnceLock<LogPolicy> = OnceLock::new();
+
+/// The process-wide policy, configured from the environment on first use
+pub fn log_policy() -> &'static LogPolicy {
+    LOG_POLICY.get_or_init(|| LogPolicy::new(LogPolicyConfig::from_env()))
+}
+
+/// Whether this successful request should be logged. Within
+/// [`with_sampling`] every call gives the request's decision, so a request
+/// is logged everywhere or nowhere.
+pub fn sample_success() -> bool {
+    REQUEST_SAMPLED
+        .try_with(|sampled| *sampled)
+        .unwrap_or_else(|_| log_policy().sample_success())
+}
+
+/// Runs `request` with its sampling decision, made once by the gateway or
+/// read from [`TRACE_SAMPLED_HEADER`]
+pub fn with_sampling<F: Future>(sampled: bool, request: F) -> TaskLocalFuture<bool, F> {
+    REQUEST_SAMPLED.scope(sampled, request)
+}
+
+/// Rate-limits events per target. Decisions depend on time, so callsite
+/// interest is never cached.
+struct RateLimitFilter;
+
+impl<S> Filter<S> for RateLimitFilter {
+    fn enabled(&self, metadata: &Metadata<'_>, _cx: &Context<'_, S>) -> bool {
+        log_policy().allow_event(metadata)
+    }
+
+    fn callsite_enabled(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        Interest::sometimes()
+    }
+}
+
+/// Installs the global subscriber used by every binary: `RUST_LOG` filtering
+/// (default `info`), the per-module rate limit and the configured format.
+pub fn init_tracing() {
+    let policy = log_policy();
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let fmt_layer = match policy.config.format {
+        LogFormat::Full => fmt::layer().boxed(),
+        LogFormat::Compact => fmt::layer()
+            .compact()
+            .with_target(false)
+            .with_ansi(false)
+            .boxed(),
+        LogFormat::Plain => fmt::layer()
+            .event_format(PlainFormat)
+            .with_ansi(false)
+            .boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(env_filter.and(RateLimitFilter)))
+        .init();
+}
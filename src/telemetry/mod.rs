@@ -0,0 +1,4 @@
+pub mod log_policy;
+pub mod latency_histogram;
+pub mod query_metrics;
+pub mod plain_format;
@@ -0,0 +1,124 @@
+use chrono::{SecondsFormat, Utc};
+use std::fmt::{self, Write};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Event format for `LOG_FORMAT=plain`: one ASCII-only line per event,
+///
+/// ```text
+/// 2026-01-01T12:00:00.000Z WARN  jpc_rust::repositories::connection: SurrealDB connection 0 to ws://db:8000 lost reason="timed out"
+/// ```
+///
+/// for log shippers and terminals that cannot take UTF-8. Emoji and other
+/// pictographs are dropped from messages, any other non-ASCII character is
+/// escaped as `\u{..}`, and fields follow the message as `key=value`,
+/// quoted when they contain spaces. Fields of the spans the event is in,
+/// such as a call's `request_id`, follow the event's own.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainFormat;
+
+impl<S, N> FormatEvent<S, N> for PlainFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut fields = PlainFields::default();
+        event.record(&mut fields);
+
+        write!(
+            writer,
+            "{} {:<5} {}: {}",
+            Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            metadata.level(),
+            metadata.target(),
+            fields.message.trim()
+        )?;
+        for (name, value) in &fields.fields {
+            write!(writer, " {}={}", name, quote(value))?;
+        }
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                match extensions.get::<FormattedFields<N>>() {
+                    Some(span_fields) if !span_fields.is_empty() => {
+                        write!(writer, " {}", ascii(span_fields, false))?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        writeln!(writer)
+    }
+}
+
+#[derive(Default)]
+struct PlainFields {
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for PlainFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, format!("{:?}", value));
+    }
+}
+
+impl PlainFields {
+    fn record(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = ascii(&value, true);
+        } else {
+            self.fields.push((field.name(), ascii(&value, false)));
+        }
+    }
+}
+
+/// Emoji, pictographs and the joiners and selectors that combine them
+fn is_pictograph(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2190}'..='\u{2BFF}'
+            | '\u{1F000}'..='\u{1FAFF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{200D}'
+            | '\u{20E3}'
+    )
+}
+
+/// `text` with non-ASCII characters escaped, and pictographs dropped when
+/// `drop_pictographs` is set. Runs of spaces left behind are collapsed.
+fn ascii(text: &str, drop_pictographs: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            if !(c == ' ' && out.ends_with(' ')) {
+                out.push(c);
+            }
+        } else if !(drop_pictographs && is_pictograph(c)) {
+            let _ = write!(out, "\\u{{{:x}}}", c as u32);
+        }
+    }
+    out
+}
+
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains([' ', '=', '"']) {
+        format!("{:?}", value)
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,118 @@
+use crate::middleware::server_timing::record_db_time;
+use crate::telemetry::latency_histogram::{LatencyHistogram, LatencySnapshot};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::future::IntoFuture;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::{field, warn, Instrument};
+
+/// Process-wide database query counters, shared by every repository
+#[derive(Debug)]
+pub struct QueryStats {
+    queries: AtomicU64,
+    slow_queries: AtomicU64,
+    latency: LatencyHistogram,
+    slow_threshold: Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryStatsSnapshot {
+    pub queries: u64,
+    pub slow_queries: u64,
+    pub slow_query_threshold_ms: u64,
+    pub latency: LatencySnapshot,
+}
+
+impl QueryStats {
+    /// Reads `DB_SLOW_QUERY_MS` (default 100) on first use
+    pub fn global() -> &'static QueryStats {
+        static STATS: OnceLock<QueryStats> = OnceLock::new();
+        STATS.get_or_init(|| {
+            let threshold_ms = std::env::var("DB_SLOW_QUERY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100);
+            QueryStats {
+                queries: AtomicU64::new(0),
+                slow_queries: AtomicU64::new(0),
+                latency: LatencyHistogram::new(),
+                slow_threshold: Duration::from_millis(threshold_ms),
+            }
+        })
+    }
+
+    fn record(&self, statement: &str, binds: usize, elapsed: Duration) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        self.latency.record(elapsed);
+
+        if elapsed >= self.slow_threshold {
+            self.slow_queries.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "🐢 Slow query took {:.1}ms ({} binds): {}",
+                elapsed.as_secs_f64() * 1000.0,
+                binds,
+                statement
+            );
+        }
+    }
+
+    pub fn snapshot(&self) -> QueryStatsSnapshot {
+        QueryStatsSnapshot {
+            queries: self.queries.load(Ordering::Relaxed),
+            slow_queries: self.slow_queries.load(Ordering::Relaxed),
+            slow_query_threshold_ms: self.slow_threshold.as_millis() as u64,
+            latency: self.latency.snapshot(),
+        }
+    }
+}
+
+/// Runs a database operation inside a `db.query` span recording the
+/// parameterized statement, its bind count and duration, and feeds the
+/// global [`QueryStats`] and the current request's `Server-Timing`.
+///
+/// `build` receives `statement` so SurrealQL queries only spell it once,
+/// e.g. `traced_query("SELECT * FROM user WHERE email = $email", |sql| {
+/// db.query(sql).bind(("email", email)) })`. For builder calls such as
+/// `create` or `select`, pass an equivalent statement for the logs. Binds
+/// are counted from the distinct `$params` in the statement.
+pub async fn traced_query<F, Q>(statement: &'static str, build: F) -> Q::Output
+where
+    F: FnOnce(&'static str) -> Q,
+    Q: IntoFuture,
+{
+    let binds = count_binds(statement);
+    let span = tracing::debug_span!(
+        "db.query",
+        db.statement = statement,
+        db.binds = binds,
+        duration_ms = field::Empty
+    );
+
+    let started = Instant::now();
+    let output = build(statement)
+        .into_future()
+        .instrument(span.clone())
+        .await;
+    let elapsed = started.elapsed();
+
+    span.record("duration_ms", elapsed.as_secs_f64() * 1000.0);
+    QueryStats::global().record(statement, binds, elapsed);
+    record_db_time(elapsed);
+    output
+}
+
+fn count_binds(statement: &str) -> usize {
+    statement
+        .split('$')
+        .skip(1)
+        .map(|rest| {
+            rest.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or_default()
+        })
+        .filter(|name| !name.is_empty())
+        .collect::<HashSet<_>>()
+        .len()
+}
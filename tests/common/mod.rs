@@ -0,0 +1,131 @@
+//! Fixtures that keep repository tests from seeing each other's data, so
+//! they can run in parallel against one database server.
+//!
+//! Every [`TestDatabase`] gets its own namespace. With the default `mem://`
+//! endpoint each repository also gets its own in-process datastore; set
+//! `TEST_DB_ENDPOINT=ws://localhost:8000` (plus `TEST_DB_USERNAME` /
+//! `TEST_DB_PASSWORD`) to run against a real server instead.
+
+use jpc_rust::config::database::DatabaseConfig;
+use jpc_rust::repositories::connection::DbConnection;
+use jpc_rust::repositories::product_repository::ProductRepository;
+use jpc_rust::repositories::user_repository::UserRepository;
+use serde_json::Value;
+use surrealdb::sql;
+use uuid::Uuid;
+
+/// A namespace of its own for one test. Call [`TestDatabase::teardown`] at
+/// the end of the test; a test that panics first leaves its `test_*`
+/// namespace behind on a server, to be removed by hand.
+pub struct TestDatabase {
+    config: DatabaseConfig,
+}
+
+impl TestDatabase {
+    pub fn new(database: &str) -> Self {
+        let endpoint = std::env::var("TEST_DB_ENDPOINT").unwrap_or_else(|_| "mem://".to_string());
+        let remote = endpoint.starts_with("ws://") || endpoint.starts_with("wss://");
+        Self {
+            config: DatabaseConfig {
+                endpoint,
+                namespace: format!("test_{}", Uuid::new_v4().simple()),
+                database: database.to_string(),
+                username: std::env::var("TEST_DB_USERNAME").ok().filter(|_| remote),
+                password: std::env::var("TEST_DB_PASSWORD").ok().filter(|_| remote),
+                pool_size: None,
+            },
+        }
+    }
+
+    pub async fn user_repository(&self) -> UserRepository {
+        UserRepository::new(&self.config)
+            .await
+            .expect("user repository for the test namespace")
+    }
+
+    pub async fn product_repository(&self) -> ProductRepository {
+        ProductRepository::new(&self.config)
+            .await
+            .expect("product repository for the test namespace")
+    }
+
+    /// Removes the namespace from the server; `mem://` datastores go away
+    /// with their connection
+    pub async fn teardown(self, connection: &DbConnection) {
+        let db = connection.handle().expect("test database connection");
+        if self.config.is_remote() {
+            // Namespaces are generated from a uuid, so they need no escaping
+            db.query(format!("REMOVE NAMESPACE {}", self.config.namespace))
+                .await
+                .and_then(surrealdb::Response::check)
+                .expect("remove test namespace");
+        }
+    }
+}
+
+/// The records of every table at one point in a test, to roll back to.
+///
+/// Only records are restored: tables created after the snapshot are
+/// removed, but indexes or fields defined on existing tables are kept.
+pub struct Snapshot {
+    tables: Vec<(String, sql::Value)>,
+}
+
+impl Snapshot {
+    pub async fn take(connection: &DbConnection) -> Self {
+        let db = connection.handle().expect("test database connection");
+        let mut tables = Vec::new();
+        for table in table_names(connection).await {
+            let records: sql::Value = db
+                .query(format!("SELECT * FROM `{}`", table))
+                .await
+                .expect("snapshot query")
+                .take(0)
+                .expect("snapshot records");
+            tables.push((table, records));
+        }
+        Self { tables }
+    }
+
+    /// Puts every table back to how it was when the snapshot was taken
+    pub async fn rollback(&self, connection: &DbConnection) {
+        let db = connection.handle().expect("test database connection");
+        for table in table_names(connection).await {
+            if !self.tables.iter().any(|(name, _)| *name == table) {
+                db.query(format!("REMOVE TABLE `{}`", table))
+                    .await
+                    .and_then(surrealdb::Response::check)
+                    .expect("remove table created after the snapshot");
+            }
+        }
+        for (table, records) in &self.tables {
+            db.query(format!(
+                "BEGIN TRANSACTION; \
+                 DELETE `{table}`; \
+                 INSERT INTO `{table}` $records; \
+                 COMMIT TRANSACTION;"
+            ))
+            .bind(("records", records.clone()))
+            .await
+            .and_then(surrealdb::Response::check)
+            .expect("restore snapshot records");
+        }
+    }
+}
+
+async fn table_names(connection: &DbConnection) -> Vec<String> {
+    let db = connection.handle().expect("test database connection");
+    let info: Option<Value> = db
+        .query("INFO FOR DB")
+        .await
+        .expect("table info query")
+        .take(0)
+        .expect("table info");
+    let mut names: Vec<String> = info
+        .as_ref()
+        .and_then(|info| info["tables"].as_object())
+        .map(|tables| tables.keys().cloned().collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
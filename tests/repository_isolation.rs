@@ -0,0 +1,637 @@
+//! Repository behaviour against isolated test databases. The tests share
+//! record names on purpose: they only pass when each one sees nothing but
+//! its own data, whatever order or parallelism they run with.
+
+mod common;
+
+use chrono::{Duration, Utc};
+use common::{Snapshot, TestDatabase};
+use jpc_rust::models::address_model::Address;
+use jpc_rust::models::admin_model::DeletePolicy;
+use jpc_rust::models::inventory_model::{DiscrepancyKind, OrderActivity, DEFAULT_LOCATION};
+use jpc_rust::models::organization_model::OrganizationForCreation;
+use jpc_rust::models::product_model::UnitStock;
+use jpc_rust::models::quantity_model::{Quantity, StockUnit};
+use jpc_rust::models::recommendation_model::{
+    ListOrdersByUserRequest, OrderForRecording, OrderLine, OrderStatus,
+};
+use jpc_rust::models::return_model::{ReturnForCreation, ReturnItem, ReturnStatus};
+use jpc_rust::prelude::{Product, ProductServiceError, User, UserServiceError};
+use jpc_rust::repositories::inventory_repository::InventoryRepository;
+use jpc_rust::repositories::order_history_repository::OrderHistoryRepository;
+use jpc_rust::repositories::organization_repository::OrganizationRepository;
+use jpc_rust::repositories::return_repository::ReturnRepository;
+use jpc_rust::repositories::rollup_repository::CategoryRollupRepository;
+use jpc_rust::services::delete_guards::DeleteGuards;
+use jpc_rust::services::stock_reconciliation::find_discrepancies;
+use jpc_rust::services::user_service::{USER_DEPENDENTS, USER_ORDERS};
+
+const EMAIL: &str = "fixture@example.com";
+
+fn user() -> User {
+    User::new("Fixture".to_string(), EMAIL.to_string(), None)
+}
+
+fn product(name: &str) -> Product {
+    Product::new(
+        name.to_string(),
+        "Fixture".to_string(),
+        9.99,
+        "fixtures".to_string(),
+        Quantity::from_units(1),
+        StockUnit::Piece,
+    )
+}
+
+#[tokio::test]
+async fn duplicate_email_is_rejected_within_one_database() {
+    let database = TestDatabase::new("users");
+    let users = database.user_repository().await;
+
+    users.create_user(user()).await.expect("first user");
+    let duplicate = users.create_user(user()).await;
+    assert!(
+        matches!(duplicate, Err(UserServiceError::UserAlreadyExists { .. })),
+        "expected UserAlreadyExists, got {:?}",
+        duplicate
+    );
+
+    database.teardown(&users.connection()).await;
+}
+
+#[tokio::test]
+async fn same_email_in_another_test_database_is_free() {
+    let database = TestDatabase::new("users");
+    let users = database.user_repository().await;
+
+    users
+        .create_user(user())
+        .await
+        .expect("no user from another test is visible");
+
+    database.teardown(&users.connection()).await;
+}
+
+#[tokio::test]
+async fn memberships_are_counted_per_user() {
+    let database = TestDatabase::new("users");
+    let users = database.user_repository().await;
+    let organizations = OrganizationRepository::new(users.connection())
+        .await
+        .expect("organization repository");
+
+    let organization = OrganizationForCreation {
+        name: "Fixture".to_string(),
+        created_at: Utc::now(),
+    };
+    organizations
+        .create_org(organization, "owner")
+        .await
+        .expect("organization");
+
+    for (user_id, expected) in [("owner", 1), ("stranger", 0)] {
+        let count = organizations
+            .membership_count(user_id)
+            .await
+            .expect("membership count");
+        assert_eq!(count, expected, "{user_id}");
+    }
+
+    database.teardown(&users.connection()).await;
+}
+
+#[tokio::test]
+async fn rollback_discards_records_written_after_the_snapshot() {
+    let database = TestDatabase::new("products");
+    let products = database.product_repository().await;
+    let connection = products.connection();
+
+    products
+        .create_product(product("Kept"))
+        .await
+        .expect("kept product");
+    let snapshot = Snapshot::take(&connection).await;
+    products
+        .create_product(product("Discarded"))
+        .await
+        .expect("discarded product");
+
+    snapshot.rollback(&connection).await;
+    let names: Vec<String> = products
+        .product_names()
+        .await
+        .expect("product names")
+        .into_iter()
+        .map(|product| product.name)
+        .collect();
+    assert_eq!(names, vec!["Kept".to_string()]);
+
+    database.teardown(&connection).await;
+}
+
+fn product_return(quantity: i64) -> ReturnForCreation {
+    let now = Utc::now();
+    ReturnForCreation {
+        order_id: "order-1".to_string(),
+        items: vec![ReturnItem {
+            product_id: "widget".to_string(),
+            quantity: Quantity::from_units(quantity),
+            refund_amount: 2.5 * quantity as f64,
+            location: None,
+        }],
+        reason: "Damaged".to_string(),
+        status: ReturnStatus::Requested,
+        refund_total: 2.5 * quantity as f64,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+#[tokio::test]
+async fn returns_of_an_order_cannot_exceed_the_quantity_ordered() {
+    let database = TestDatabase::new("products");
+    let products = database.product_repository().await;
+    let connection = products.connection();
+    let returns = ReturnRepository::new(products.connection())
+        .await
+        .expect("return repository");
+    let lines = [OrderLine {
+        product_id: "widget".to_string(),
+        quantity: Quantity::from_units(3),
+        unit_price: 2.5,
+    }];
+
+    returns
+        .create_return(product_return(2), &lines)
+        .await
+        .expect("first return within the order");
+    let over = returns.create_return(product_return(2), &lines).await;
+    assert!(over.is_err(), "expected the cap to refuse, got {:?}", over);
+    returns
+        .create_return(product_return(1), &lines)
+        .await
+        .expect("the last unit can still be returned");
+
+    let returned = returns
+        .returned_quantities("order-1", None)
+        .await
+        .expect("returned quantities");
+    assert_eq!(returned.get("widget"), Some(&Quantity::from_units(3)));
+
+    database.teardown(&connection).await;
+}
+
+#[tokio::test]
+async fn stock_is_totalled_per_unit() {
+    let database = TestDatabase::new("products");
+    let products = database.product_repository().await;
+    let connection = products.connection();
+    let rollups = CategoryRollupRepository::new(products.connection())
+        .await
+        .expect("rollup repository");
+    let flour = Product::new(
+        "Flour".to_string(),
+        "Fixture".to_string(),
+        2.0,
+        "fixtures".to_string(),
+        StockUnit::Kg.parse_quantity("2.5").expect("quantity"),
+        StockUnit::Kg,
+    );
+    for product in [product("Widget"), product("Gadget"), flour] {
+        products
+            .create_product(product)
+            .await
+            .expect("create product");
+    }
+    let by_unit = |stock: &[UnitStock]| {
+        stock
+            .iter()
+            .map(|unit| (unit.unit, unit.products, unit.total_stock))
+            .collect::<Vec<_>>()
+    };
+    let expected = vec![
+        (
+            StockUnit::Kg,
+            1,
+            StockUnit::Kg.parse_quantity("2.5").unwrap(),
+        ),
+        (StockUnit::Piece, 2, Quantity::from_units(2)),
+    ];
+
+    let stats = products.product_stats().await.expect("product stats");
+    assert_eq!(stats.total_products, 3);
+    assert_eq!(by_unit(&stats.stock_by_unit), expected);
+    assert_eq!(by_unit(&stats.categories[0].stock_by_unit), expected);
+
+    let categories = rollups.rollups().await.expect("rollups");
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0].products, 3);
+    assert_eq!(by_unit(&categories[0].stock_by_unit), expected);
+    assert!((categories[0].inventory_value - 24.98).abs() < 1e-9);
+
+    rollups.rebuild().await.expect("rebuild");
+    let rebuilt = rollups.rollups().await.expect("rollups");
+    assert_eq!(by_unit(&rebuilt[0].stock_by_unit), expected);
+
+    database.teardown(&connection).await;
+}
+
+#[tokio::test]
+async fn an_order_id_is_recorded_once() {
+    let database = TestDatabase::new("products");
+    let products = database.product_repository().await;
+    let connection = products.connection();
+    let orders = OrderHistoryRepository::new(products.connection())
+        .await
+        .expect("order history repository");
+    let order = |order_id: Option<&str>, user_id: &str| OrderForRecording {
+        order_id: order_id.map(str::to_string),
+        user_id: Some(user_id.to_string()),
+        status: OrderStatus::Completed,
+        product_ids: vec!["widget".to_string()],
+        lines: Vec::new(),
+        ordered_at: Utc::now(),
+    };
+
+    orders
+        .record_order(order(Some("o-1"), "alice"))
+        .await
+        .expect("first order");
+    let duplicate = orders.record_order(order(Some("o-1"), "bob")).await;
+    assert!(
+        matches!(duplicate, Err(ProductServiceError::OrderAlreadyRecorded { ref id }) if id == "o-1"),
+        "expected a duplicate order, got {:?}",
+        duplicate
+    );
+    for user_id in ["alice", "bob"] {
+        orders
+            .record_order(order(None, user_id))
+            .await
+            .expect("orders without an id are not unique");
+    }
+
+    let recorded = orders.find_order("o-1").await.expect("find order");
+    assert_eq!(
+        recorded.and_then(|order| order.user_id),
+        Some("alice".to_string())
+    );
+
+    database.teardown(&connection).await;
+}
+
+async fn open_orders(orders: &OrderHistoryRepository, product_id: &str) -> usize {
+    orders
+        .count_open_by_product(product_id)
+        .await
+        .expect("count open orders")
+}
+
+#[tokio::test]
+async fn an_order_ships_once_and_stays_open_until_returned() {
+    let database = TestDatabase::new("products");
+    let products = database.product_repository().await;
+    let connection = products.connection();
+    let orders = OrderHistoryRepository::new(products.connection())
+        .await
+        .expect("order history repository");
+    orders
+        .record_order(OrderForRecording {
+            order_id: Some("o-1".to_string()),
+            user_id: Some("alice".to_string()),
+            status: OrderStatus::Completed,
+            product_ids: vec!["widget".to_string()],
+            lines: Vec::new(),
+            ordered_at: Utc::now(),
+        })
+        .await
+        .expect("record order");
+    let address = Address {
+        line1: "1 MAIN ST".to_string(),
+        line2: None,
+        city: "SPRINGFIELD".to_string(),
+        region: None,
+        postal_code: None,
+        country: "ZZ".to_string(),
+    };
+    assert_eq!(open_orders(&orders, "widget").await, 1);
+
+    let shipped = orders
+        .mark_shipped("o-1", address.clone())
+        .await
+        .expect("ship order")
+        .expect("a waiting order ships");
+    assert_eq!(shipped.status, OrderStatus::Shipped);
+    assert_eq!(shipped.shipping_address, Some(address.clone()));
+    assert!(shipped.shipped_at.is_some());
+    for order_id in ["o-1", "missing"] {
+        let again = orders
+            .mark_shipped(order_id, address.clone())
+            .await
+            .expect("ship order");
+        assert!(again.is_none(), "{} shipped again", order_id);
+    }
+    assert_eq!(open_orders(&orders, "widget").await, 1);
+    assert_eq!(open_orders(&orders, "gadget").await, 0);
+
+    orders
+        .set_status(&shipped.id, OrderStatus::Returned)
+        .await
+        .expect("return order");
+    assert_eq!(open_orders(&orders, "widget").await, 0);
+
+    database.teardown(&connection).await;
+}
+
+async fn reconcile(
+    inventory: &InventoryRepository,
+    orders: &OrderHistoryRepository,
+    returns: &ReturnRepository,
+) -> Vec<(DiscrepancyKind, String, Option<Quantity>, Quantity)> {
+    let activity = OrderActivity {
+        awaiting_shipment: orders
+            .awaiting_shipment_quantities()
+            .await
+            .expect("awaiting"),
+        shipped: orders.shipped_quantities().await.expect("shipped"),
+        removed: inventory.removed_by_adjustments().await.expect("removed"),
+        returned: returns.completed_quantities().await.expect("returned"),
+        restocked: inventory.restocked_by_returns().await.expect("restocked"),
+    };
+    let totals = inventory.product_totals().await.expect("totals");
+    let levels = inventory.all_stock_levels().await.expect("stock levels");
+    let locations = inventory.list_locations().await.expect("locations");
+    find_discrepancies(&totals, &levels, &locations, &activity)
+        .into_iter()
+        .map(|found| (found.kind, found.product_id, found.expected, found.actual))
+        .collect()
+}
+
+#[tokio::test]
+async fn order_lines_and_returns_are_reconciled_against_the_ledger() {
+    let database = TestDatabase::new("products");
+    let products = database.product_repository().await;
+    let connection = products.connection();
+    let inventory = InventoryRepository::new(products.connection())
+        .await
+        .expect("inventory repository");
+    let orders = OrderHistoryRepository::new(products.connection())
+        .await
+        .expect("order history repository");
+    let returns = ReturnRepository::new(products.connection())
+        .await
+        .expect("return repository");
+    let widget = products
+        .create_product(product("Widget"))
+        .await
+        .expect("create product")
+        .id
+        .id
+        .to_raw();
+    let units = Quantity::from_units;
+    // Delivered 5, then sold 2
+    for quantity in [5, 3] {
+        inventory
+            .set_stock(&widget, DEFAULT_LOCATION, units(quantity))
+            .await
+            .expect("set stock");
+    }
+    let order = |order_id: &str, product_id: &str, quantity: i64| OrderForRecording {
+        order_id: Some(order_id.to_string()),
+        user_id: Some("alice".to_string()),
+        status: OrderStatus::Completed,
+        product_ids: vec![product_id.to_string()],
+        lines: vec![OrderLine {
+            product_id: product_id.to_string(),
+            quantity: units(quantity),
+            unit_price: 9.99,
+        }],
+        ordered_at: Utc::now(),
+    };
+    let address = Address {
+        line1: "1 MAIN ST".to_string(),
+        line2: None,
+        city: "SPRINGFIELD".to_string(),
+        region: None,
+        postal_code: None,
+        country: "ZZ".to_string(),
+    };
+
+    orders
+        .record_order(order("o-1", &widget, 2))
+        .await
+        .expect("record order");
+    orders
+        .mark_shipped("o-1", address.clone())
+        .await
+        .expect("ship order")
+        .expect("a waiting order ships");
+    let sold = orders
+        .find_order("o-1")
+        .await
+        .expect("find order")
+        .expect("recorded order");
+    let now = Utc::now();
+    let product_return = returns
+        .create_return(
+            ReturnForCreation {
+                order_id: "o-1".to_string(),
+                items: vec![ReturnItem {
+                    product_id: widget.clone(),
+                    quantity: units(1),
+                    refund_amount: 9.99,
+                    location: None,
+                }],
+                reason: "Damaged".to_string(),
+                status: ReturnStatus::Requested,
+                refund_total: 9.99,
+                created_at: now,
+                updated_at: now,
+            },
+            &sold.lines,
+        )
+        .await
+        .expect("create return");
+    let approved = returns
+        .approve(&product_return.id.id.to_raw())
+        .await
+        .expect("approve return")
+        .expect("a requested return is approved");
+    returns
+        .complete(&approved, &sold.id, DEFAULT_LOCATION)
+        .await
+        .expect("complete return");
+    assert_eq!(reconcile(&inventory, &orders, &returns).await, Vec::new());
+
+    // 4 in stock after the return, 5 waiting to ship
+    orders
+        .record_order(order("o-2", &widget, 5))
+        .await
+        .expect("record order");
+    orders
+        .record_order(order("o-3", "ghost", 1))
+        .await
+        .expect("record order");
+    let mut expected = vec![
+        (
+            DiscrepancyKind::OrphanedOrderLine,
+            "ghost".to_string(),
+            None,
+            units(1),
+        ),
+        (
+            DiscrepancyKind::Overcommitted,
+            widget.clone(),
+            Some(units(5)),
+            units(4),
+        ),
+    ];
+    expected.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(reconcile(&inventory, &orders, &returns).await, expected);
+
+    // Shipping o-2 without selling its stock first leaves 7 shipped
+    // against 2 sold
+    orders
+        .mark_shipped("o-2", address)
+        .await
+        .expect("ship order")
+        .expect("a waiting order ships");
+    let mut expected = vec![
+        (
+            DiscrepancyKind::OrphanedOrderLine,
+            "ghost".to_string(),
+            None,
+            units(1),
+        ),
+        (
+            DiscrepancyKind::UnrecordedShipment,
+            widget,
+            Some(units(7)),
+            units(2),
+        ),
+    ];
+    expected.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(reconcile(&inventory, &orders, &returns).await, expected);
+
+    database.teardown(&connection).await;
+}
+
+#[tokio::test]
+async fn a_users_orders_block_deleting_them() {
+    let database = TestDatabase::new("products");
+    let products = database.product_repository().await;
+    let connection = products.connection();
+    let orders = OrderHistoryRepository::new(products.connection())
+        .await
+        .expect("order history repository");
+
+    for (user_id, status) in [
+        (Some("alice"), OrderStatus::Completed),
+        (Some("alice"), OrderStatus::Returned),
+        (Some("bob"), OrderStatus::Completed),
+        (None, OrderStatus::Completed),
+    ] {
+        orders
+            .record_order(OrderForRecording {
+                order_id: None,
+                user_id: user_id.map(str::to_string),
+                status,
+                product_ids: vec!["widget".to_string()],
+                lines: Vec::new(),
+                ordered_at: Utc::now(),
+            })
+            .await
+            .expect("recorded order");
+    }
+
+    let guards = DeleteGuards::new(USER_DEPENDENTS);
+    for (user_id, expected) in [("alice", 2), ("bob", 1), ("carol", 0)] {
+        let count = orders.count_by_user(user_id).await.expect("order count");
+        assert_eq!(count, expected, "{}", user_id);
+        let blocking: Vec<_> = guards
+            .plan([(USER_ORDERS.name, count)])
+            .blocking()
+            .into_iter()
+            .map(|records| (records.dependent, records.count))
+            .collect();
+        let expected_blocking: Vec<_> = (expected > 0)
+            .then(|| ("orders".to_string(), expected))
+            .into_iter()
+            .collect();
+        assert_eq!(blocking, expected_blocking, "{}", user_id);
+    }
+    assert_eq!(USER_ORDERS.supports, &[DeletePolicy::Block]);
+
+    database.teardown(&connection).await;
+}
+
+#[tokio::test]
+async fn orders_by_user_are_filtered_and_paged_newest_first() {
+    let database = TestDatabase::new("products");
+    let products = database.product_repository().await;
+    let connection = products.connection();
+    let orders = OrderHistoryRepository::new(products.connection())
+        .await
+        .expect("order history repository");
+
+    let now = Utc::now();
+    for (order_id, user_id, status, days_ago) in [
+        ("a", "alice", OrderStatus::Completed, 3),
+        ("b", "alice", OrderStatus::Returned, 2),
+        ("c", "alice", OrderStatus::Completed, 1),
+        ("d", "bob", OrderStatus::Completed, 1),
+    ] {
+        orders
+            .record_order(OrderForRecording {
+                order_id: Some(order_id.to_string()),
+                user_id: Some(user_id.to_string()),
+                status,
+                product_ids: vec!["widget".to_string()],
+                lines: Vec::new(),
+                ordered_at: now - Duration::days(days_ago),
+            })
+            .await
+            .expect("recorded order");
+    }
+
+    let request = |status, from| ListOrdersByUserRequest {
+        user_id: "alice".to_string(),
+        status,
+        from,
+        to: None,
+        limit: None,
+        cursor: None,
+    };
+    let cases = [
+        (request(None, None), 0, 10, vec!["c", "b", "a"], 3),
+        (request(None, None), 1, 1, vec!["b"], 3),
+        (
+            request(Some(OrderStatus::Completed), None),
+            0,
+            10,
+            vec!["c", "a"],
+            2,
+        ),
+        (
+            request(None, Some(now - Duration::hours(36))),
+            0,
+            10,
+            vec!["c"],
+            1,
+        ),
+    ];
+
+    for (request, offset, limit, expected, expected_total) in cases {
+        let (page, total) = orders
+            .orders_by_user(&request, offset, limit)
+            .await
+            .expect("orders by user");
+        let ids: Vec<&str> = page
+            .iter()
+            .filter_map(|order| order.order_id.as_deref())
+            .collect();
+        assert_eq!(ids, expected, "{:?} from {}", request.status, offset);
+        assert_eq!(total, expected_total, "{:?}", request.status);
+    }
+
+    database.teardown(&connection).await;
+}
@@ -0,0 +1,112 @@
+//! Contract between the gateway's method map and the methods the services
+//! actually serve. Boots both services with in-memory databases, asks each
+//! for its method list and fails if the gateway would misroute any method.
+//!
+//! Uses ports 8080 and 8081, so stop locally running services first.
+
+use jpc_rust::gateway::routing::{route_for_method, MethodRoute, Upstream};
+use jpc_rust::services::method_namespaces::{
+    COMMON_METHODS, METHOD_LIST_METHOD, PRODUCT_METHODS, USER_METHODS,
+};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::HttpClientBuilder;
+use jsonrpsee::rpc_params;
+use std::collections::BTreeSet;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+/// Kills the service when the test ends, pass or fail
+struct ServiceProcess(Child);
+
+impl ServiceProcess {
+    fn spawn(binary: &str) -> Self {
+        let child = Command::new(binary)
+            .env("SERVICE_STARTUP_MODE", "eager")
+            .env("APP_USER_DB__ENDPOINT", "mem://")
+            .env("APP_PRODUCT_DB__ENDPOINT", "mem://")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|err| panic!("failed to start {}: {}", binary, err));
+        Self(child)
+    }
+}
+
+impl Drop for ServiceProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Polls `rpc.methods` until the service answers or 30s have passed
+async fn method_list(url: &str) -> BTreeSet<String> {
+    let client = HttpClientBuilder::default()
+        .build(url)
+        .expect("valid service url");
+
+    for _ in 0..60 {
+        match client
+            .request::<Vec<String>, _>(METHOD_LIST_METHOD, rpc_params![])
+            .await
+        {
+            Ok(methods) => return methods.into_iter().collect(),
+            Err(_) => tokio::time::sleep(Duration::from_millis(500)).await,
+        }
+    }
+    panic!("{} did not answer {} in time", url, METHOD_LIST_METHOD);
+}
+
+#[tokio::test]
+async fn gateway_routes_every_service_method_exactly_once() {
+    let _user = ServiceProcess::spawn(env!("CARGO_BIN_EXE_user-service"));
+    let _product = ServiceProcess::spawn(env!("CARGO_BIN_EXE_product-service"));
+
+    let user_methods = method_list("http://127.0.0.1:8080").await;
+    let product_methods = method_list("http://127.0.0.1:8081").await;
+
+    let mut problems = Vec::new();
+    for method in user_methods.union(&product_methods) {
+        let on_user = user_methods.contains(method);
+        let on_product = product_methods.contains(method);
+        let expected = match (on_user, on_product) {
+            (true, true) => MethodRoute::Shared,
+            (true, false) => MethodRoute::Service(Upstream::User),
+            _ => MethodRoute::Service(Upstream::Product),
+        };
+        let actual = route_for_method(method);
+        if actual != expected {
+            problems.push(format!(
+                "{}: served by {}, gateway routes it as {:?}",
+                method,
+                match (on_user, on_product) {
+                    (true, true) => "both services",
+                    (true, false) => "user-service",
+                    _ => "product-service",
+                },
+                actual
+            ));
+        }
+    }
+
+    // Stale entries would route clients to a method that no longer exists
+    let mapped = COMMON_METHODS
+        .iter()
+        .chain(USER_METHODS)
+        .chain(PRODUCT_METHODS)
+        .flat_map(|(namespaced, flat)| [*namespaced, *flat]);
+    for method in mapped {
+        if !user_methods.contains(method) && !product_methods.contains(method) {
+            problems.push(format!(
+                "{}: in the gateway's method map but not served",
+                method
+            ));
+        }
+    }
+
+    assert!(
+        problems.is_empty(),
+        "gateway method map is out of sync with the services:\n  {}",
+        problems.join("\n  ")
+    );
+}
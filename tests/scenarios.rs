@@ -0,0 +1,366 @@
+//! Acceptance flows as executable specs. Each file in `tests/scenarios`
+//! describes a sequence of JSON-RPC calls and what they should return; this
+//! test replays them in order against a running stack and fails on the
+//! first step of a scenario that does not match.
+//!
+//! Needs the gateway and both services running, so it is ignored by a plain
+//! `cargo test`. Scenarios record orders, which only services and admins
+//! may do, so start the services with the policy in
+//! `tests/scenario_policy.json` and present its token:
+//!
+//! ```bash
+//! AUTH_POLICY_FILE=tests/scenario_policy.json cargo run --bin user-service &
+//! AUTH_POLICY_FILE=tests/scenario_policy.json cargo run --bin product-service &
+//! cargo run --bin gateway &
+//! SCENARIO_BEARER_TOKEN=scenario-token cargo test --test scenarios -- --ignored
+//! ```
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::{Method, Request};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    #[serde(default)]
+    name: Option<String>,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+    #[serde(default)]
+    expect: Expect,
+    /// Variables to set from the result, as `name: path`, where the path is
+    /// dot-separated keys and array indexes, e.g. `items.0.id`. A path
+    /// ending in `| key` keeps only the key of a `table:key` record id,
+    /// the form the services take ids in.
+    #[serde(default)]
+    save: BTreeMap<String, String>,
+}
+
+/// What a step's response must contain. Objects match when every expected
+/// key matches, so fields that change between runs can be left out. A step
+/// without `error` must succeed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expect {
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+struct ScenarioConfig {
+    dir: PathBuf,
+    target_url: String,
+    bearer_token: Option<String>,
+    filter: Option<String>,
+}
+
+impl ScenarioConfig {
+    fn from_env() -> Self {
+        Self {
+            dir: std::env::var("SCENARIO_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios")),
+            target_url: std::env::var("SCENARIO_TARGET_URL")
+                .unwrap_or_else(|_| "http://127.0.0.1:8082".to_string()),
+            bearer_token: std::env::var("SCENARIO_BEARER_TOKEN").ok(),
+            filter: std::env::var("SCENARIO_FILTER").ok(),
+        }
+    }
+}
+
+/// Replaces `${name}` in strings with the variable's value. A string that
+/// is only a reference takes the value as is, numbers and objects included.
+fn substitute(value: &Value, vars: &BTreeMap<String, Value>) -> Result<Value, String> {
+    match value {
+        Value::String(text) => {
+            if let Some(name) = text
+                .strip_prefix("${")
+                .and_then(|rest| rest.strip_suffix('}'))
+                .filter(|name| !name.contains('}'))
+            {
+                return vars
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("unknown variable ${{{}}}", name));
+            }
+            let mut out = String::new();
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("${") {
+                let Some(len) = rest[start..].find('}') else {
+                    break;
+                };
+                let name = &rest[start + 2..start + len];
+                let value = vars
+                    .get(name)
+                    .ok_or_else(|| format!("unknown variable ${{{}}}", name))?;
+                out.push_str(&rest[..start]);
+                match value {
+                    Value::String(value) => out.push_str(value),
+                    value => out.push_str(&value.to_string()),
+                }
+                rest = &rest[start + len + 1..];
+            }
+            out.push_str(rest);
+            Ok(Value::String(out))
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| substitute(item, vars))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), substitute(value, vars)?)))
+            .collect::<Result<_, String>>()
+            .map(Value::Object),
+        value => Ok(value.clone()),
+    }
+}
+
+/// Collects where `actual` differs from `expected`. Objects may have more
+/// keys than expected; arrays must have the same length. Numbers compare by
+/// value, so `9` matches `9.0`.
+fn compare(expected: &Value, actual: &Value, path: &str, problems: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected) in expected {
+                let path = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual) => compare(expected, actual, &path, problems),
+                    None => problems.push(format!("{}: missing, expected {}", path, expected)),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+            for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                compare(expected, actual, &format!("{}.{}", path, index), problems);
+            }
+        }
+        (Value::Number(expected), Value::Number(actual))
+            if expected.as_f64() == actual.as_f64() => {}
+        (expected, actual) if expected == actual => {}
+        (expected, actual) => {
+            problems.push(format!("{}: expected {}, got {}", path, expected, actual))
+        }
+    }
+}
+
+/// Looks up a dot-separated path such as `items.0.id`
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |value, segment| match value {
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            value => value.get(segment),
+        })
+}
+
+/// The value a `save` entry names: the value at its path, with `| key`
+/// reducing a `table:key` record id to its key
+fn saved_value(result: &Value, expression: &str) -> Result<Value, String> {
+    let (path, filter) = match expression.split_once('|') {
+        Some((path, filter)) => (path.trim(), Some(filter.trim())),
+        None => (expression.trim(), None),
+    };
+    let value =
+        lookup(result, path).ok_or_else(|| format!("nothing at result.{} to save", path))?;
+    match (filter, value) {
+        (None, value) => Ok(value.clone()),
+        (Some("key"), Value::String(id)) => Ok(Value::String(
+            id.split_once(':')
+                .map_or(id.as_str(), |(_, key)| key)
+                .to_string(),
+        )),
+        (Some("key"), value) => Err(format!("result.{} is {}, not a record id", path, value)),
+        (Some(filter), _) => Err(format!("unknown filter '{}' in save", filter)),
+    }
+}
+
+struct ScenarioRunner {
+    config: ScenarioConfig,
+    client: Client<HttpConnector, Full<Bytes>>,
+}
+
+impl ScenarioRunner {
+    async fn call(&self, id: usize, method: &str, params: Option<Value>) -> Result<Value, String> {
+        let mut call = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        if let Some(params) = params {
+            call["params"] = params;
+        }
+
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(&self.config.target_url)
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(token) = &self.config.bearer_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = request
+            .body(Full::new(Bytes::from(call.to_string())))
+            .map_err(|err| err.to_string())?;
+
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|err| format!("{} unreachable: {}", self.config.target_url, err))?;
+        let status = response.status();
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|err| err.to_string())?
+            .to_bytes();
+        serde_json::from_slice(&body).map_err(|_| {
+            format!(
+                "HTTP {} with a body that is not JSON-RPC: {}",
+                status,
+                String::from_utf8_lossy(&body)
+            )
+        })
+    }
+
+    /// Runs the steps in order, stopping at the first failure
+    async fn run(&self, scenario: &Scenario) -> Result<(), String> {
+        let mut vars = BTreeMap::from([(
+            "run_id".to_string(),
+            Value::String(uuid::Uuid::new_v4().simple().to_string()),
+        )]);
+
+        for (index, step) in scenario.steps.iter().enumerate() {
+            let label = format!(
+                "step {} ({})",
+                index + 1,
+                step.name.as_deref().unwrap_or(&step.method)
+            );
+            let fail = |problem: String| format!("{}: {}", label, problem);
+
+            let params = step
+                .params
+                .as_ref()
+                .map(|params| substitute(params, &vars))
+                .transpose()
+                .map_err(fail)?;
+            let response = self
+                .call(index + 1, &step.method, params)
+                .await
+                .map_err(fail)?;
+
+            let mut problems = Vec::new();
+            match (&step.expect.error, response.get("error")) {
+                (Some(expected), Some(error)) => {
+                    let expected = substitute(expected, &vars).map_err(fail)?;
+                    compare(&expected, error, "error", &mut problems);
+                }
+                (Some(expected), None) => problems.push(format!(
+                    "expected error {}, got result {}",
+                    expected,
+                    response.get("result").unwrap_or(&Value::Null)
+                )),
+                (None, Some(error)) => problems.push(format!("failed with {}", error)),
+                (None, None) => {
+                    let result = response.get("result").unwrap_or(&Value::Null);
+                    if let Some(expected) = &step.expect.result {
+                        let expected = substitute(expected, &vars).map_err(fail)?;
+                        compare(&expected, result, "result", &mut problems);
+                    }
+                    for (name, expression) in &step.save {
+                        match saved_value(result, expression) {
+                            Ok(value) => {
+                                vars.insert(name.clone(), value);
+                            }
+                            Err(problem) => problems.push(problem),
+                        }
+                    }
+                }
+            }
+            if !problems.is_empty() {
+                return Err(fail(problems.join("\n    ")));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn scenario_files(config: &ScenarioConfig) -> Vec<PathBuf> {
+    let entries = std::fs::read_dir(&config.dir)
+        .unwrap_or_else(|err| panic!("failed to read {}: {}", config.dir.display(), err));
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter(|path| {
+            config.filter.as_deref().is_none_or(|filter| {
+                path.file_stem()
+                    .is_some_and(|stem| stem.to_string_lossy().contains(filter))
+            })
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[tokio::test]
+#[ignore = "needs a running stack, run with --ignored"]
+async fn scenarios_pass_against_running_stack() {
+    let runner = ScenarioRunner {
+        config: ScenarioConfig::from_env(),
+        client: Client::builder(TokioExecutor::new()).build_http(),
+    };
+    let files = scenario_files(&runner.config);
+    assert!(
+        !files.is_empty(),
+        "no scenarios in {}",
+        runner.config.dir.display()
+    );
+
+    let mut failures = Vec::new();
+    for file in &files {
+        let contents = std::fs::read_to_string(file)
+            .unwrap_or_else(|err| panic!("failed to read {}: {}", file.display(), err));
+        let scenario: Scenario = serde_json::from_str(&contents)
+            .unwrap_or_else(|err| panic!("invalid scenario {}: {}", file.display(), err));
+
+        match runner.run(&scenario).await {
+            Ok(()) => println!("✅ {}", scenario.name),
+            Err(problem) => {
+                println!("❌ {}", scenario.name);
+                if let Some(description) = &scenario.description {
+                    println!("   {}", description);
+                }
+                failures.push(format!(
+                    "{} ({}): {}",
+                    scenario.name,
+                    file.display(),
+                    problem
+                ));
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} scenarios failed:\n  {}",
+        failures.len(),
+        files.len(),
+        failures.join("\n  ")
+    );
+}